@@ -4,9 +4,13 @@
 //!
 //! This uses the mature and battle-tested libcurl SSPI implementation on Windows.
 
-use super::{AuthConfig, HttpBackend, Method, RequestConfig, Response};
+use super::{
+    note_challenge, note_location, AuthConfig, HttpBackend, Method, RequestConfig, Response,
+    Timings,
+};
 use curl::easy::{Auth, Easy, List};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 pub struct CurlBackend;
 
@@ -62,10 +66,32 @@ impl HttpBackend for CurlBackend {
                 easy.username(username)?;
                 easy.password(password)?;
             }
+            AuthConfig::Ntlm { credentials } => {
+                let mut auth = Auth::new();
+                auth.ntlm(true);
+                easy.http_auth(&auth)?;
+                set_credentials(&mut easy, credentials)?;
+            }
+            AuthConfig::Digest { username, password } => {
+                let mut auth = Auth::new();
+                auth.digest(true);
+                easy.http_auth(&auth)?;
+                easy.username(username)?;
+                easy.password(password)?;
+            }
             AuthConfig::Basic { username, password } => {
+                let mut auth = Auth::new();
+                auth.basic(true);
+                easy.http_auth(&auth)?;
                 easy.username(username)?;
                 easy.password(password)?;
             }
+            AuthConfig::Any { credentials } => {
+                let mut auth = Auth::new();
+                auth.gssnegotiate(true).ntlm(true).digest(true).basic(true);
+                easy.http_auth(&auth)?;
+                set_credentials(&mut easy, credentials)?;
+            }
         }
 
         // Set headers
@@ -89,6 +115,15 @@ impl HttpBackend for CurlBackend {
             easy.verbose(true)?;
         }
 
+        if let Some(ref proxy) = config.proxy {
+            easy.proxy(proxy)?;
+        }
+
+        if config.follow_redirects {
+            easy.follow_location(true)?;
+            easy.max_redirections(config.max_redirects)?;
+        }
+
         if config.insecure {
             easy.ssl_verify_peer(false)?;
             easy.ssl_verify_host(false)?;
@@ -98,6 +133,10 @@ impl HttpBackend for CurlBackend {
         let mut response_body = Vec::new();
         let mut response_headers = HashMap::new();
         let mut status_line = String::new();
+        let mut round_trips = 0;
+        let mut auth_events = Vec::new();
+        let mut response_times = Vec::new();
+        let start = Instant::now();
 
         {
             let mut transfer = easy.transfer();
@@ -107,10 +146,24 @@ impl HttpBackend for CurlBackend {
                 if let Ok(header_str) = std::str::from_utf8(header) {
                     let header_str = header_str.trim();
                     if header_str.starts_with("HTTP/") {
+                        // Each response curl reads, bar interim 1xx ones, is
+                        // one request sent; keep the headers of the last.
+                        let code = header_str.split_whitespace().nth(1).unwrap_or("");
+                        if !code.starts_with('1') {
+                            round_trips += 1;
+                            response_headers.clear();
+                            auth_events.push(format!("<- {}", code));
+                            response_times.push(start.elapsed());
+                        }
                         status_line = header_str.to_string();
                     } else if let Some(colon_pos) = header_str.find(':') {
                         let name = header_str[..colon_pos].trim().to_string();
                         let value = header_str[colon_pos + 1..].trim().to_string();
+                        if name.eq_ignore_ascii_case("www-authenticate") {
+                            note_challenge(&mut auth_events, &value);
+                        } else if name.eq_ignore_ascii_case("location") {
+                            note_location(&mut auth_events, &value);
+                        }
                         response_headers.insert(name, value);
                     }
                 }
@@ -136,11 +189,41 @@ impl HttpBackend for CurlBackend {
             .unwrap_or("")
             .to_string();
 
+        // curl reports 0 for the phases it skipped, such as TLS over http
+        let phase = |time: Duration| Some(time).filter(|time| !time.is_zero());
+        let timings = Timings {
+            dns: phase(easy.namelookup_time()?),
+            connect: phase(easy.connect_time()?),
+            tls: phase(easy.appconnect_time()?),
+            round_trips: response_times,
+            total: phase(easy.total_time()?),
+        };
+
         Ok(Response {
             status,
             status_text,
             headers: response_headers,
             body: response_body,
+            round_trips: Some(round_trips),
+            auth_events,
+            timings,
         })
     }
 }
+
+/// Sets `credentials`, or with none, those of the current Windows user.
+fn set_credentials(
+    easy: &mut Easy,
+    credentials: &Option<(String, String)>,
+) -> Result<(), curl::Error> {
+    match credentials {
+        Some((username, password)) => {
+            easy.username(username)?;
+            easy.password(password)
+        }
+        None => {
+            easy.username(":")?;
+            easy.password("")
+        }
+    }
+}
@@ -0,0 +1,412 @@
+// examples/backends/hyper_backend.rs
+
+//! Raw hyper backend, with none of reqwest's client layer.
+//!
+//! Each request goes on a fresh HTTP/1 connection made with hyper's
+//! low-level `client::conn` API, and the head of every message is printed to
+//! stderr as it goes over the wire, like `curl -v`. Negotiate and NTLM tokens
+//! come straight from SSPI, so that a failure can be pinned either on the
+//! exchange itself or on reqwest's client layer. It doesn't follow redirects
+//! or go through proxies.
+
+use super::sspi;
+use super::{
+    auth_scheme, note_challenge, note_location, AuthConfig, HttpBackend, Method, RequestConfig,
+    Response, Timings,
+};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::header::{AUTHORIZATION, HOST, LOCATION, WWW_AUTHENTICATE};
+use hyper_util::rt::TokioIo;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+/// How many times a request is sent again with a token after a 401.
+const MAX_AUTH_ROUNDS: usize = 3;
+
+pub struct HyperBackend {
+    runtime: tokio::runtime::Runtime,
+}
+
+impl HyperBackend {
+    pub fn new() -> Self {
+        let runtime = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+        Self { runtime }
+    }
+}
+
+impl HttpBackend for HyperBackend {
+    fn name(&self) -> &'static str {
+        "hyper (raw)"
+    }
+
+    fn execute(&self, config: &RequestConfig) -> Result<Response, Box<dyn std::error::Error>> {
+        if config.proxy.is_some() {
+            return Err("the hyper backend doesn't go through proxies".into());
+        }
+        if config.follow_redirects {
+            return Err("the hyper backend doesn't follow redirects".into());
+        }
+
+        // The SSPI package to answer challenges with, Negotiate falling back
+        // to NTLM if that's all the server offers
+        let (package, credentials) = match &config.auth {
+            AuthConfig::None | AuthConfig::Basic { .. } => (None, None),
+            AuthConfig::Negotiate => (Some("Negotiate"), None),
+            AuthConfig::NegotiateWithCredentials { username, password } => (
+                Some("Negotiate"),
+                Some((username.as_str(), password.as_str())),
+            ),
+            AuthConfig::Ntlm { credentials } => (
+                Some("NTLM"),
+                credentials.as_ref().map(|(u, p)| (u.as_str(), p.as_str())),
+            ),
+            AuthConfig::Any { credentials } => (
+                Some("Negotiate"),
+                credentials.as_ref().map(|(u, p)| (u.as_str(), p.as_str())),
+            ),
+            AuthConfig::Digest { .. } => {
+                return Err("the hyper backend doesn't support Digest authentication".into());
+            }
+        };
+
+        let url = reqwest::Url::parse(&config.url)?;
+        let host = url.host_str().ok_or("URL has no host")?.to_string();
+        let port = url.port_or_known_default().ok_or("URL has no port")?;
+        let host_header = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.clone(),
+        };
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let method = match config.method {
+            Method::Get => hyper::Method::GET,
+            Method::Post => hyper::Method::POST,
+            Method::Put => hyper::Method::PUT,
+            Method::Delete => hyper::Method::DELETE,
+            Method::Head => hyper::Method::HEAD,
+        };
+        let body = Bytes::from(config.body.clone().unwrap_or_default());
+
+        self.runtime.block_on(async {
+            let start = Instant::now();
+            let mut timings = Timings::default();
+
+            let addrs: Vec<_> = tokio::net::lookup_host((host.as_str(), port))
+                .await?
+                .collect();
+            timings.dns = Some(start.elapsed());
+            let tcp = TcpStream::connect(&addrs[..]).await?;
+            timings.connect = Some(start.elapsed());
+            let io: Box<dyn Io> = if url.scheme() == "https" {
+                let tls = tls_connector(config.insecure)?;
+                let stream = tls
+                    .connect(ServerName::try_from(host.clone())?, tcp)
+                    .await?;
+                timings.tls = Some(start.elapsed());
+                Box::new(stream)
+            } else {
+                Box::new(tcp)
+            };
+
+            let (mut sender, conn) =
+                hyper::client::conn::http1::handshake(TokioIo::new(Wire::new(io))).await?;
+            tokio::spawn(async move {
+                if let Err(e) = conn.await {
+                    eprintln!("* connection error: {}", e);
+                }
+            });
+
+            let mut authorization = match &config.auth {
+                AuthConfig::Basic { username, password } => Some(format!(
+                    "Basic {}",
+                    BASE64.encode(format!("{}:{}", username, password))
+                )),
+                _ => None,
+            };
+            let mut context: Option<(&str, sspi::Context)> = None;
+            let mut auth_events = Vec::new();
+            let mut round = 0;
+
+            let response = loop {
+                let mut request = hyper::Request::builder()
+                    .method(method.clone())
+                    .uri(path.as_str())
+                    .header(HOST, host_header.as_str());
+                for (name, value) in &config.headers {
+                    request = request.header(name.as_str(), value.as_str());
+                }
+                auth_events.push(match &authorization {
+                    Some(value) => {
+                        request = request.header(AUTHORIZATION, value.as_str());
+                        format!("-> request, Authorization: {}", auth_scheme(value))
+                    }
+                    None => "-> request".to_string(),
+                });
+
+                let response = sender
+                    .send_request(request.body(Full::new(body.clone()))?)
+                    .await?;
+                timings.round_trips.push(start.elapsed());
+                auth_events.push(format!("<- {}", response.status().as_u16()));
+                let challenges: Vec<String> = response
+                    .headers()
+                    .get_all(WWW_AUTHENTICATE)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .map(str::to_string)
+                    .collect();
+                for challenge in &challenges {
+                    note_challenge(&mut auth_events, challenge);
+                }
+                if let Some(location) = response.headers().get(LOCATION) {
+                    note_location(&mut auth_events, location.to_str().unwrap_or(""));
+                }
+
+                let package = match package {
+                    Some(package) if response.status() == 401 && round < MAX_AUTH_ROUNDS => package,
+                    _ => break response,
+                };
+                let scheme = match &context {
+                    Some((scheme, _)) => *scheme,
+                    None => match pick_scheme(&challenges, package) {
+                        Some(scheme) => scheme,
+                        None => break response,
+                    },
+                };
+                let token = challenges
+                    .iter()
+                    .find(|challenge| auth_scheme(challenge).eq_ignore_ascii_case(scheme))
+                    .and_then(|challenge| challenge.split_whitespace().nth(1))
+                    .map(|token| BASE64.decode(token))
+                    .transpose()?;
+
+                // Read the 401 to the end, to send the next request on the
+                // same connection, which connection-based schemes need.
+                response.into_body().collect().await?;
+                round += 1;
+
+                if context.is_none() {
+                    context = Some((scheme, sspi::Context::new(scheme, credentials)?));
+                }
+                if let Some((_, ref mut context)) = context {
+                    let token = context.step(&format!("HTTP/{}", host), token.as_deref())?;
+                    authorization = Some(format!("{} {}", scheme, BASE64.encode(token)));
+                }
+            };
+
+            let status = response.status().as_u16();
+            let status_text = response
+                .status()
+                .canonical_reason()
+                .unwrap_or("")
+                .to_string();
+            let mut headers = HashMap::new();
+            for (name, value) in response.headers() {
+                if let Ok(value) = value.to_str() {
+                    headers.insert(name.to_string(), value.to_string());
+                }
+            }
+            let body = response.into_body().collect().await?.to_bytes().to_vec();
+            timings.total = Some(start.elapsed());
+
+            Ok(Response {
+                status,
+                status_text,
+                headers,
+                body,
+                round_trips: Some(round + 1),
+                auth_events,
+                timings,
+            })
+        })
+    }
+}
+
+/// The scheme to answer `challenges` with, using `package`, or NTLM for
+/// Negotiate if the server only offers that.
+fn pick_scheme(challenges: &[String], package: &'static str) -> Option<&'static str> {
+    let offered = |scheme: &str| {
+        challenges
+            .iter()
+            .any(|c| auth_scheme(c).eq_ignore_ascii_case(scheme))
+    };
+    if offered(package) {
+        Some(package)
+    } else if package == "Negotiate" && offered("NTLM") {
+        Some("NTLM")
+    } else {
+        None
+    }
+}
+
+/// What a connection can be, plain or over TLS.
+trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+/// A connection printing the head of each message going through it.
+struct Wire {
+    inner: Box<dyn Io>,
+    sent: Head,
+    received: Head,
+}
+
+impl Wire {
+    fn new(inner: Box<dyn Io>) -> Self {
+        Self {
+            inner,
+            sent: Head::default(),
+            received: Head::default(),
+        }
+    }
+}
+
+/// The head of the message being sent or received.
+#[derive(Default)]
+struct Head {
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl Head {
+    /// Takes the bytes of `data` until the head is complete, then returns it.
+    fn feed(&mut self, data: &[u8]) -> Option<Vec<u8>> {
+        if self.done {
+            return None;
+        }
+        self.buf.extend_from_slice(data);
+        let end = self.buf.windows(4).position(|w| w == b"\r\n\r\n")?;
+        self.done = true;
+        let mut head = std::mem::take(&mut self.buf);
+        head.truncate(end);
+        Some(head)
+    }
+}
+
+fn print_head(prefix: char, head: &[u8]) {
+    for line in head.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        eprintln!("{} {}", prefix, line.escape_ascii());
+    }
+    eprintln!("{}", prefix);
+}
+
+impl AsyncRead for Wire {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            if let Some(head) = self.received.feed(&buf.filled()[filled..]) {
+                print_head('<', &head);
+                self.sent = Head::default();
+            }
+        }
+        poll
+    }
+}
+
+impl AsyncWrite for Wire {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            if let Some(head) = self.sent.feed(&buf[..n]) {
+                print_head('>', &head);
+                self.received = Head::default();
+            }
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+fn tls_connector(insecure: bool) -> Result<tokio_rustls::TlsConnector, rustls::Error> {
+    let config = if insecure {
+        rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerifier))
+            .with_no_client_auth()
+    } else {
+        use rustls_platform_verifier::ConfigVerifierExt;
+        rustls::ClientConfig::with_platform_verifier()?
+    };
+    Ok(tokio_rustls::TlsConnector::from(Arc::new(config)))
+}
+
+/// Accepts any certificate, for `--insecure`.
+#[derive(Debug)]
+struct NoVerifier;
+
+impl ServerCertVerifier for NoVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer,
+        _intermediates: &[CertificateDer],
+        _server_name: &ServerName,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![
+            SignatureScheme::RSA_PKCS1_SHA256,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA512,
+            SignatureScheme::ECDSA_NISTP521_SHA512,
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::RSA_PSS_SHA384,
+            SignatureScheme::RSA_PSS_SHA512,
+            SignatureScheme::ED25519,
+        ]
+    }
+}
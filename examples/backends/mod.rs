@@ -4,15 +4,27 @@
 //!
 //! This module provides a common interface that can be implemented by:
 //! - curl backend (using libcurl's mature SSPI/GSS-Negotiate support)
+//! - WinHTTP backend (using the Negotiate support built into Windows)
+//! - hyper backend (raw HTTP/1 with SSPI tokens, printing each message head)
 //! - reqwest backend (using our custom negotiate implementation)
 
-#[cfg(feature = "curl")]
+#[cfg(feature = "backend-curl")]
 pub mod curl_backend;
 
-#[cfg(feature = "reqwest")]
+#[cfg(all(windows, feature = "backend-winhttp"))]
+pub mod winhttp_backend;
+
+#[cfg(feature = "backend-hyper")]
+pub mod hyper_backend;
+
+#[cfg(feature = "backend-reqwest")]
 pub mod reqwest_backend;
 
+pub mod spn;
+pub mod sspi;
+
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// HTTP method
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -45,8 +57,20 @@ pub enum AuthConfig {
     Negotiate,
     /// Negotiate with explicit credentials (fallback to Basic)
     NegotiateWithCredentials { username: String, password: String },
+    /// NTLM only, as the current user without credentials
+    Ntlm {
+        credentials: Option<(String, String)>,
+    },
+    /// Digest authentication
+    // Only read by the backends that support it
+    #[allow(dead_code)]
+    Digest { username: String, password: String },
     /// Basic authentication
     Basic { username: String, password: String },
+    /// The safest scheme the server offers, like `curl --anyauth`
+    Any {
+        credentials: Option<(String, String)>,
+    },
 }
 
 /// Request configuration
@@ -59,6 +83,10 @@ pub struct RequestConfig {
     pub body: Option<String>,
     pub verbose: bool,
     pub insecure: bool,
+    pub proxy: Option<String>,
+    pub follow_redirects: bool,
+    /// Redirects followed at most, 50 unless set, like curl.
+    pub max_redirects: u32,
 }
 
 impl RequestConfig {
@@ -71,6 +99,9 @@ impl RequestConfig {
             body: None,
             verbose: false,
             insecure: false,
+            proxy: None,
+            follow_redirects: false,
+            max_redirects: 50,
         }
     }
 
@@ -94,6 +125,21 @@ impl RequestConfig {
         self
     }
 
+    pub fn ntlm(mut self, credentials: Option<(String, String)>) -> Self {
+        self.auth = AuthConfig::Ntlm { credentials };
+        self
+    }
+
+    pub fn digest(mut self, username: String, password: String) -> Self {
+        self.auth = AuthConfig::Digest { username, password };
+        self
+    }
+
+    pub fn any_auth(mut self, credentials: Option<(String, String)>) -> Self {
+        self.auth = AuthConfig::Any { credentials };
+        self
+    }
+
     pub fn header(mut self, name: String, value: String) -> Self {
         self.headers.insert(name, value);
         self
@@ -113,6 +159,21 @@ impl RequestConfig {
         self.insecure = insecure;
         self
     }
+
+    pub fn proxy(mut self, proxy: String) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    pub fn follow_redirects(mut self, follow: bool) -> Self {
+        self.follow_redirects = follow;
+        self
+    }
+
+    pub fn max_redirects(mut self, max: u32) -> Self {
+        self.max_redirects = max;
+        self
+    }
 }
 
 /// HTTP response
@@ -122,6 +183,52 @@ pub struct Response {
     pub status_text: String,
     pub headers: HashMap<String, String>,
     pub body: Vec<u8>,
+    /// Requests sent to get this response, so 1 without an authentication
+    /// round-trip. `None` if the backend can't tell.
+    pub round_trips: Option<usize>,
+    /// The steps of the exchange that the backend could see, such as
+    /// `-> request, Authorization: Negotiate` or
+    /// `<- 401, WWW-Authenticate: Negotiate, NTLM`.
+    pub auth_events: Vec<String>,
+    pub timings: Timings,
+}
+
+/// When each phase of a request ended, measured from its start like the
+/// times of `curl -w`. `None` for a phase the backend can't see.
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    /// Name resolved, as `time_namelookup`.
+    pub dns: Option<Duration>,
+    /// TCP connected, as `time_connect`.
+    pub connect: Option<Duration>,
+    /// TLS handshake done, as `time_appconnect`.
+    pub tls: Option<Duration>,
+    /// The headers of each response received, one per round-trip.
+    pub round_trips: Vec<Duration>,
+    /// Whole body read, as `time_total`.
+    pub total: Option<Duration>,
+}
+
+impl Timings {
+    /// The headers of the final response received, as `time_starttransfer`.
+    pub fn ttfb(&self) -> Option<Duration> {
+        self.round_trips.last().copied()
+    }
+
+    /// Each phase with its name, in order.
+    pub fn phases(&self) -> Vec<(String, Option<Duration>)> {
+        let mut phases = vec![
+            ("dns".to_string(), self.dns),
+            ("connect".to_string(), self.connect),
+            ("tls".to_string(), self.tls),
+        ];
+        for (i, round_trip) in self.round_trips.iter().enumerate() {
+            phases.push((format!("round-trip {}", i + 1), Some(*round_trip)));
+        }
+        phases.push(("ttfb".to_string(), self.ttfb()));
+        phases.push(("total".to_string(), self.total));
+        phases
+    }
 }
 
 impl Response {
@@ -134,6 +241,31 @@ impl Response {
     }
 }
 
+/// The auth scheme of an `Authorization` or `WWW-Authenticate` value.
+pub fn auth_scheme(value: &str) -> &str {
+    value.split_whitespace().next().unwrap_or("")
+}
+
+/// Adds a `WWW-Authenticate` challenge to the last `<-` event.
+pub fn note_challenge(events: &mut [String], value: &str) {
+    if let Some(last) = events.last_mut() {
+        if last.contains("WWW-Authenticate:") {
+            last.push_str(", ");
+        } else {
+            last.push_str(", WWW-Authenticate: ");
+        }
+        last.push_str(auth_scheme(value));
+    }
+}
+
+/// Adds the `Location` of a redirect to the last `<-` event.
+pub fn note_location(events: &mut [String], location: &str) {
+    if let Some(last) = events.last_mut() {
+        last.push_str(", Location: ");
+        last.push_str(location);
+    }
+}
+
 /// HTTP client backend trait
 pub trait HttpBackend {
     /// Name of the backend (for display purposes)
@@ -145,19 +277,63 @@ pub trait HttpBackend {
 
 /// Get the active backend based on compile-time features
 pub fn get_backend() -> Box<dyn HttpBackend> {
-    #[cfg(feature = "curl")]
+    #[cfg(feature = "backend-curl")]
     {
         Box::new(curl_backend::CurlBackend::new())
     }
 
-    #[cfg(all(feature = "reqwest", not(feature = "curl")))]
+    #[cfg(all(windows, feature = "backend-winhttp", not(feature = "backend-curl")))]
+    {
+        Box::new(winhttp_backend::WinHttpBackend::new())
+    }
+
+    #[cfg(all(
+        feature = "backend-hyper",
+        not(feature = "backend-curl"),
+        not(all(windows, feature = "backend-winhttp"))
+    ))]
+    {
+        Box::new(hyper_backend::HyperBackend::new())
+    }
+
+    #[cfg(all(
+        feature = "backend-reqwest",
+        not(feature = "backend-curl"),
+        not(all(windows, feature = "backend-winhttp")),
+        not(feature = "backend-hyper")
+    ))]
     {
         Box::new(reqwest_backend::ReqwestBackend::new())
     }
 
-    #[cfg(all(not(feature = "curl"), not(feature = "reqwest")))]
+    #[cfg(all(
+        not(feature = "backend-curl"),
+        not(feature = "backend-reqwest"),
+        not(all(windows, feature = "backend-winhttp")),
+        not(feature = "backend-hyper")
+    ))]
     {
-        compile_error!("Either curl or reqwest feature must be enabled");
+        compile_error!("Either curl, winhttp, hyper or reqwest feature must be enabled");
         unreachable!()
     }
 }
+
+/// Every backend compiled in, for `--compare`
+#[allow(clippy::vec_init_then_push)]
+pub fn all_backends() -> Vec<Box<dyn HttpBackend>> {
+    let mut backends: Vec<Box<dyn HttpBackend>> = Vec::new();
+
+    #[cfg(feature = "backend-curl")]
+    backends.push(Box::new(curl_backend::CurlBackend::new()));
+
+    #[cfg(all(windows, feature = "backend-winhttp"))]
+    backends.push(Box::new(winhttp_backend::WinHttpBackend::new()));
+
+    #[cfg(feature = "backend-hyper")]
+    backends.push(Box::new(hyper_backend::HyperBackend::new()));
+
+    #[cfg(feature = "backend-reqwest")]
+    backends.push(Box::new(reqwest_backend::ReqwestBackend::new()));
+
+    backends
+}
@@ -4,8 +4,18 @@
 //!
 //! This uses the reqwest library with our newly implemented negotiate feature.
 
-use super::{AuthConfig, HttpBackend, Method, RequestConfig, Response};
+use super::{
+    auth_scheme, note_challenge, note_location, AuthConfig, HttpBackend, Method, RequestConfig,
+    Response, Timings,
+};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
 use std::collections::HashMap;
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 pub struct ReqwestBackend {
     runtime: tokio::runtime::Runtime,
@@ -18,6 +28,77 @@ impl ReqwestBackend {
     }
 }
 
+/// Timings noted by the client's hooks, measured from `start`.
+struct Clock {
+    start: Instant,
+    timings: Timings,
+}
+
+impl Clock {
+    fn lap(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// The system resolver, noting when the first lookup ended.
+struct TimedResolver(Arc<Mutex<Clock>>);
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let clock = self.0.clone();
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0)).await?;
+            let mut clock = clock.lock().unwrap();
+            let lap = clock.lap();
+            clock.timings.dns.get_or_insert(lap);
+            let addrs: Addrs = Box::new(addrs.collect::<Vec<_>>().into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// A connector noting when the first connection, with its TLS handshake
+/// if any, was ready.
+#[derive(Clone)]
+struct TimedConnect<S> {
+    inner: S,
+    clock: Arc<Mutex<Clock>>,
+    tls: bool,
+}
+
+impl<S, R> tower::Service<R> for TimedConnect<S>
+where
+    S: tower::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let connecting = self.inner.call(req);
+        let clock = self.clock.clone();
+        let tls = self.tls;
+        Box::pin(async move {
+            let conn = connecting.await?;
+            let mut clock = clock.lock().unwrap();
+            let lap = clock.lap();
+            // Over TLS, only the end of the handshake can be seen from here
+            let phase = if tls {
+                &mut clock.timings.tls
+            } else {
+                &mut clock.timings.connect
+            };
+            phase.get_or_insert(lap);
+            Ok(conn)
+        })
+    }
+}
+
 impl HttpBackend for ReqwestBackend {
     fn name(&self) -> &'static str {
         "reqwest (custom negotiate)"
@@ -25,8 +106,69 @@ impl HttpBackend for ReqwestBackend {
 
     fn execute(&self, config: &RequestConfig) -> Result<Response, Box<dyn std::error::Error>> {
         self.runtime.block_on(async {
-            // Build client with authentication
-            let mut client_builder = reqwest::Client::builder();
+            // Build client with authentication, recording each request the
+            // negotiate loop sends and each response it gets
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let on_request = events.clone();
+            let on_response = events.clone();
+            let clock = Arc::new(Mutex::new(Clock {
+                start: Instant::now(),
+                timings: Timings::default(),
+            }));
+            let response_clock = clock.clone();
+            let connect_clock = clock.clone();
+            let tls = config.url.starts_with("https:");
+            let mut client_builder = reqwest::Client::builder()
+                .dns_resolver(TimedResolver(clock.clone()))
+                .connector_layer(tower::layer::layer_fn(move |inner| TimedConnect {
+                    inner,
+                    clock: connect_clock.clone(),
+                    tls,
+                }))
+                .on_request(move |req| {
+                    let event = match req.headers().get(reqwest::header::AUTHORIZATION) {
+                        Some(value) => format!(
+                            "-> request, Authorization: {}",
+                            auth_scheme(value.to_str().unwrap_or(""))
+                        ),
+                        None => "-> request".to_string(),
+                    };
+                    on_request.lock().unwrap().push(event);
+                    ControlFlow::Continue(())
+                })
+                .on_response(move |res| {
+                    let mut clock = response_clock.lock().unwrap();
+                    let lap = clock.lap();
+                    clock.timings.round_trips.push(lap);
+                    let mut events = on_response.lock().unwrap();
+                    events.push(format!("<- {}", res.status().as_u16()));
+                    for value in res.headers().get_all(reqwest::header::WWW_AUTHENTICATE) {
+                        note_challenge(&mut events, value.to_str().unwrap_or(""));
+                    }
+                    if let Some(location) = res.headers().get(reqwest::header::LOCATION) {
+                        note_location(&mut events, location.to_str().unwrap_or(""));
+                    }
+                });
+
+            // Hooks only see the final response, so the policy records the
+            // redirects followed on the way
+            client_builder = client_builder.redirect(if config.follow_redirects {
+                let events = events.clone();
+                let max = config.max_redirects as usize;
+                reqwest::redirect::Policy::custom(move |attempt| {
+                    let mut events = events.lock().unwrap();
+                    events.push(format!("<- {}", attempt.status().as_u16()));
+                    note_location(&mut events, attempt.url().as_str());
+                    if attempt.previous().len() > max {
+                        attempt.error("too many redirects")
+                    } else {
+                        events.push("-> request".to_string());
+                        attempt.follow()
+                    }
+                })
+            } else {
+                reqwest::redirect::Policy::none()
+            });
 
             match &config.auth {
                 AuthConfig::None => {}
@@ -34,15 +176,31 @@ impl HttpBackend for ReqwestBackend {
                     client_builder = client_builder.negotiate();
                 }
                 AuthConfig::NegotiateWithCredentials { username, password } => {
-                    client_builder =
-                        client_builder.negotiate_with_credentials(username, password);
+                    client_builder = client_builder.negotiate_with_credentials(username, password);
+                }
+                // reqwest has no NTLM-only mode, but its negotiate support
+                // answers NTLM challenges too, and falls back to Basic
+                AuthConfig::Ntlm { credentials } | AuthConfig::Any { credentials } => {
+                    client_builder = match credentials {
+                        Some((username, password)) => {
+                            client_builder.negotiate_with_credentials(username, password)
+                        }
+                        None => client_builder.negotiate(),
+                    };
                 }
-                AuthConfig::Basic { username, password } => {
+                AuthConfig::Digest { .. } => {
+                    return Err("the reqwest backend doesn't support Digest authentication".into());
+                }
+                AuthConfig::Basic { .. } => {
                     // For basic auth, we'll add it as a header in the request
                     // (reqwest doesn't have a built-in .basic_auth on ClientBuilder)
                 }
             }
 
+            if let Some(ref proxy) = config.proxy {
+                client_builder = client_builder.proxy(reqwest::Proxy::all(proxy)?);
+            }
+
             if config.insecure {
                 client_builder = client_builder.tls_danger_accept_invalid_certs(true);
             }
@@ -73,7 +231,15 @@ impl HttpBackend for ReqwestBackend {
                 request_builder = request_builder.basic_auth(username, Some(password));
             }
 
+            // Show how to send the same request with curl, to compare
+            if config.verbose {
+                if let Some(curl) = request_builder.to_curl_command() {
+                    eprintln!("* equivalent: {curl}");
+                }
+            }
+
             // Execute request
+            clock.lock().unwrap().start = Instant::now();
             let response = request_builder.send().await?;
 
             // Extract response data
@@ -92,12 +258,23 @@ impl HttpBackend for ReqwestBackend {
             }
 
             let body = response.bytes().await?.to_vec();
+            let timings = {
+                let clock = clock.lock().unwrap();
+                let mut timings = clock.timings.clone();
+                timings.total = Some(clock.lap());
+                timings
+            };
+            let auth_events = events.lock().unwrap().clone();
+            let round_trips = auth_events.iter().filter(|e| e.starts_with("->")).count();
 
             Ok(Response {
                 status,
                 status_text,
                 headers,
                 body,
+                round_trips: Some(round_trips),
+                auth_events,
+                timings,
             })
         })
     }
@@ -0,0 +1,156 @@
+// examples/backends/spn.rs
+
+//! Finding the SPN a server's Kerberos tickets can be had for.
+//!
+//! When there is no ticket for the SPN a client asks for, Negotiate falls
+//! back to NTLM, or fails outright. The backends ask for `HTTP/<host>`, but
+//! other clients use the canonical name the host resolves to, or add the
+//! port, and the service account may only be registered under one of them.
+//! [`probe`] asks the KDC for a ticket for each of them.
+
+use super::sspi;
+use std::io;
+use std::net::IpAddr;
+
+/// What [`probe`] found out about a URL.
+pub struct Probe {
+    pub host: String,
+    pub addresses: Vec<IpAddr>,
+    pub canonical_name: Option<String>,
+    pub candidates: Vec<Candidate>,
+}
+
+/// An SPN tried by [`probe`].
+pub struct Candidate {
+    pub spn: String,
+    /// Which name and port the SPN was made of.
+    pub source: &'static str,
+    /// The size of the first Kerberos token, or why there is no ticket.
+    pub result: Result<usize, String>,
+}
+
+/// Resolves the host of `url`, and tries each SPN it could go by for a
+/// Kerberos ticket, as the logged-on user without `credentials`.
+pub fn probe(url: &str, credentials: Option<(&str, &str)>) -> Result<Probe, String> {
+    let url = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    let host = url.host_str().ok_or("URL has no host")?.to_string();
+    let port = url.port_or_known_default().ok_or("URL has no port")?;
+
+    let mut addresses: Vec<IpAddr> = url
+        .socket_addrs(|| None)
+        .map_err(|e| format!("Can't resolve {}: {}", host, e))?
+        .iter()
+        .map(|addr| addr.ip())
+        .collect();
+    addresses.dedup();
+
+    // IP addresses have no canonical name to look up
+    let canonical_name = match url.host() {
+        Some(url::Host::Domain(domain)) => {
+            canonical_name(domain).map_err(|e| format!("Can't resolve {}: {}", host, e))?
+        }
+        _ => None,
+    };
+
+    let mut names = vec![
+        (format!("HTTP/{}", host), "host, as the backends ask"),
+        (format!("HTTP/{}:{}", host, port), "host and port"),
+    ];
+    if let Some(ref canonical) = canonical_name {
+        names.push((format!("HTTP/{}", canonical), "canonical name"));
+        names.push((
+            format!("HTTP/{}:{}", canonical, port),
+            "canonical name and port",
+        ));
+    }
+
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for (spn, source) in names {
+        if candidates
+            .iter()
+            .any(|candidate| candidate.spn.eq_ignore_ascii_case(&spn))
+        {
+            continue;
+        }
+        // Kerberos rather than Negotiate, which would fall back to NTLM
+        let result = sspi::Context::new("Kerberos", credentials)
+            .and_then(|mut context| context.step(&spn, None))
+            .map(|token| token.len())
+            .map_err(|e| e.to_string());
+        candidates.push(Candidate {
+            spn,
+            source,
+            result,
+        });
+    }
+
+    Ok(Probe {
+        host,
+        addresses,
+        canonical_name,
+        candidates,
+    })
+}
+
+/// The name `host` is an alias of, if any.
+#[cfg(unix)]
+fn canonical_name(host: &str) -> io::Result<Option<String>> {
+    use std::ffi::{CStr, CString};
+
+    let node = CString::new(host).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut hints: libc::addrinfo = unsafe { std::mem::zeroed() };
+    hints.ai_flags = libc::AI_CANONNAME;
+    hints.ai_socktype = libc::SOCK_STREAM;
+    let mut list = std::ptr::null_mut();
+    unsafe {
+        let status = libc::getaddrinfo(node.as_ptr(), std::ptr::null(), &hints, &mut list);
+        if status != 0 {
+            let message = CStr::from_ptr(libc::gai_strerror(status));
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                message.to_string_lossy().into_owned(),
+            ));
+        }
+        let name = (*list).ai_canonname;
+        let name = (!name.is_null()).then(|| CStr::from_ptr(name).to_string_lossy().into_owned());
+        libc::freeaddrinfo(list);
+        Ok(name.filter(|name| !name.eq_ignore_ascii_case(host)))
+    }
+}
+
+/// The name `host` is an alias of, if any.
+#[cfg(windows)]
+fn canonical_name(host: &str) -> io::Result<Option<String>> {
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::Networking::WinSock::{
+        FreeAddrInfoW, GetAddrInfoW, ADDRINFOW, AI_CANONNAME, SOCK_STREAM,
+    };
+
+    // Winsock was started by the lookup of the addresses
+    let hints = ADDRINFOW {
+        ai_flags: AI_CANONNAME as i32,
+        ai_socktype: SOCK_STREAM.0,
+        ..Default::default()
+    };
+    let mut list = std::ptr::null_mut();
+    unsafe {
+        let status = GetAddrInfoW(
+            &HSTRING::from(host),
+            PCWSTR::null(),
+            Some(&hints),
+            &mut list,
+        );
+        if status != 0 {
+            return Err(io::Error::from_raw_os_error(status));
+        }
+        let name = (*list).ai_canonname;
+        let name = (!name.is_null()).then(|| String::from_utf16_lossy(name.as_wide()));
+        FreeAddrInfoW(Some(list));
+        Ok(name.filter(|name| !name.eq_ignore_ascii_case(host)))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn canonical_name(_host: &str) -> io::Result<Option<String>> {
+    Ok(None)
+}
@@ -0,0 +1,195 @@
+// examples/backends/sspi.rs
+
+//! Negotiate, NTLM and Kerberos tokens straight from SSPI, for the backends
+//! and tools that don't go through reqwest's own.
+
+pub use imp::Context;
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::c_void;
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::Foundation::{SEC_E_OK, SEC_I_CONTINUE_NEEDED};
+    use windows::Win32::Security::Authentication::Identity::{
+        AcquireCredentialsHandleW, DeleteSecurityContext, FreeCredentialsHandle,
+        InitializeSecurityContextW, SecBuffer, SecBufferDesc, ISC_REQ_FLAGS, SECBUFFER_TOKEN,
+        SECBUFFER_VERSION, SECPKG_CRED_OUTBOUND, SECURITY_NATIVE_DREP,
+    };
+    use windows::Win32::Security::Credentials::SecHandle;
+
+    const EMPTY: SecHandle = SecHandle {
+        dwLower: 0,
+        dwUpper: 0,
+    };
+
+    /// SEC_WINNT_AUTH_IDENTITY_W, with SEC_WINNT_AUTH_IDENTITY_UNICODE flags.
+    #[repr(C)]
+    struct AuthIdentity {
+        user: *const u16,
+        user_length: u32,
+        domain: *const u16,
+        domain_length: u32,
+        password: *const u16,
+        password_length: u32,
+        flags: u32,
+    }
+
+    pub struct Context {
+        credentials: SecHandle,
+        context: Option<SecHandle>,
+    }
+
+    impl Context {
+        /// A context for `package`, as the logged-on user without
+        /// `credentials`.
+        pub fn new(
+            package: &str,
+            credentials: Option<(&str, &str)>,
+        ) -> Result<Self, Box<dyn std::error::Error>> {
+            let mut handle = EMPTY;
+            unsafe {
+                match credentials {
+                    Some((username, password)) => {
+                        let (user, domain) = match username.split_once('\\') {
+                            Some((domain, user)) => (user, domain),
+                            None => match username.split_once('@') {
+                                Some((user, domain)) => (user, domain),
+                                None => (username, ""),
+                            },
+                        };
+                        let (user, domain, password) = (
+                            HSTRING::from(user),
+                            HSTRING::from(domain),
+                            HSTRING::from(password),
+                        );
+                        let identity = AuthIdentity {
+                            user: user.as_ptr(),
+                            user_length: user.len() as u32,
+                            domain: domain.as_ptr(),
+                            domain_length: domain.len() as u32,
+                            password: password.as_ptr(),
+                            password_length: password.len() as u32,
+                            flags: 0x2,
+                        };
+                        AcquireCredentialsHandleW(
+                            PCWSTR::null(),
+                            &HSTRING::from(package),
+                            SECPKG_CRED_OUTBOUND,
+                            None,
+                            Some(&identity as *const AuthIdentity as *const c_void),
+                            None,
+                            None,
+                            &mut handle,
+                            None,
+                        )?;
+                    }
+                    None => AcquireCredentialsHandleW(
+                        PCWSTR::null(),
+                        &HSTRING::from(package),
+                        SECPKG_CRED_OUTBOUND,
+                        None,
+                        None,
+                        None,
+                        None,
+                        &mut handle,
+                        None,
+                    )?,
+                }
+            }
+            Ok(Self {
+                credentials: handle,
+                context: None,
+            })
+        }
+
+        /// The next token for `spn`, given the last one from the server.
+        pub fn step(
+            &mut self,
+            spn: &str,
+            input: Option<&[u8]>,
+        ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            let mut in_buffer = SecBuffer {
+                cbBuffer: input.map_or(0, |input| input.len() as u32),
+                BufferType: SECBUFFER_TOKEN,
+                pvBuffer: input.map_or(std::ptr::null_mut(), |input| input.as_ptr() as *mut c_void),
+            };
+            let in_desc = SecBufferDesc {
+                ulVersion: SECBUFFER_VERSION,
+                cBuffers: 1,
+                pBuffers: &mut in_buffer,
+            };
+            let mut token = vec![0u8; 48000];
+            let mut out_buffer = SecBuffer {
+                cbBuffer: token.len() as u32,
+                BufferType: SECBUFFER_TOKEN,
+                pvBuffer: token.as_mut_ptr() as *mut c_void,
+            };
+            let mut out_desc = SecBufferDesc {
+                ulVersion: SECBUFFER_VERSION,
+                cBuffers: 1,
+                pBuffers: &mut out_buffer,
+            };
+            let spn = HSTRING::from(spn);
+            let mut context = self.context.unwrap_or(EMPTY);
+            let mut attributes = 0;
+            let status = unsafe {
+                InitializeSecurityContextW(
+                    Some(&self.credentials),
+                    self.context
+                        .as_ref()
+                        .map(|context| context as *const SecHandle),
+                    Some(spn.as_ptr()),
+                    // ISC_REQ_MUTUAL_AUTH | ISC_REQ_DELEGATE, as reqwest asks
+                    ISC_REQ_FLAGS(0x3),
+                    0,
+                    SECURITY_NATIVE_DREP,
+                    input.map(|_| &in_desc as *const SecBufferDesc),
+                    0,
+                    Some(&mut context),
+                    Some(&mut out_desc),
+                    &mut attributes,
+                    None,
+                )
+            };
+            if status != SEC_E_OK && status != SEC_I_CONTINUE_NEEDED {
+                return Err(windows::core::Error::from(status).into());
+            }
+            self.context = Some(context);
+            token.truncate(out_buffer.cbBuffer as usize);
+            Ok(token)
+        }
+    }
+
+    impl Drop for Context {
+        fn drop(&mut self) {
+            unsafe {
+                if let Some(context) = self.context {
+                    let _ = DeleteSecurityContext(&context);
+                }
+                let _ = FreeCredentialsHandle(&self.credentials);
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    pub struct Context;
+
+    impl Context {
+        pub fn new(
+            _package: &str,
+            _credentials: Option<(&str, &str)>,
+        ) -> Result<Self, Box<dyn std::error::Error>> {
+            Err("SSPI is only available on Windows".into())
+        }
+
+        pub fn step(
+            &mut self,
+            _spn: &str,
+            _input: Option<&[u8]>,
+        ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            unreachable!()
+        }
+    }
+}
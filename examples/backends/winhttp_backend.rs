@@ -0,0 +1,405 @@
+// examples/backends/winhttp_backend.rs
+
+//! WinHTTP backend implementation using Windows' built-in negotiate support.
+//!
+//! WinHTTP is what most Windows components use to talk to IIS and AD-joined
+//! services, so it is a second reference to compare against, next to curl.
+
+use super::{note_location, AuthConfig, HttpBackend, Method, RequestConfig, Response, Timings};
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::time::Instant;
+use windows::core::{w, HSTRING, PCWSTR};
+use windows::Win32::Networking::WinHttp::*;
+
+/// How many times a request is sent again with credentials after a 401.
+const MAX_AUTH_ROUNDS: usize = 3;
+
+pub struct WinHttpBackend;
+
+impl WinHttpBackend {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A WinHTTP handle, closed when dropped.
+struct Handle(*mut c_void);
+
+impl Handle {
+    fn new(raw: *mut c_void, what: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        if raw.is_null() {
+            return Err(format!("{}: {}", what, windows::core::Error::from_win32()).into());
+        }
+        Ok(Self(raw))
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = WinHttpCloseHandle(self.0);
+        }
+    }
+}
+
+impl HttpBackend for WinHttpBackend {
+    fn name(&self) -> &'static str {
+        "WinHTTP (Windows)"
+    }
+
+    fn execute(&self, config: &RequestConfig) -> Result<Response, Box<dyn std::error::Error>> {
+        let url = reqwest::Url::parse(&config.url)?;
+        let host = url.host_str().ok_or("URL has no host")?;
+        let port = url.port_or_known_default().ok_or("URL has no port")?;
+        let secure = url.scheme() == "https";
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+
+        let verb = match config.method {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Head => "HEAD",
+        };
+
+        let proxy = config.proxy.as_deref().map(HSTRING::from);
+        let access = if proxy.is_some() {
+            WINHTTP_ACCESS_TYPE_NAMED_PROXY
+        } else {
+            WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY
+        };
+
+        unsafe {
+            let session = Handle::new(
+                WinHttpOpen(
+                    w!("test_negotiate"),
+                    access,
+                    pcwstr(proxy.as_ref()),
+                    PCWSTR::null(),
+                    0,
+                ),
+                "WinHttpOpen",
+            )?;
+            let connect = Handle::new(
+                WinHttpConnect(session.0, &HSTRING::from(host), port, 0),
+                "WinHttpConnect",
+            )?;
+            let flags = if secure {
+                WINHTTP_FLAG_SECURE
+            } else {
+                WINHTTP_OPEN_REQUEST_FLAGS(0)
+            };
+            let request = Handle::new(
+                WinHttpOpenRequest(
+                    connect.0,
+                    &HSTRING::from(verb),
+                    &HSTRING::from(path.as_str()),
+                    PCWSTR::null(),
+                    PCWSTR::null(),
+                    std::ptr::null(),
+                    flags,
+                ),
+                "WinHttpOpenRequest",
+            )?;
+
+            if config.insecure {
+                let ignore = SECURITY_FLAG_IGNORE_UNKNOWN_CA
+                    | SECURITY_FLAG_IGNORE_CERT_DATE_INVALID
+                    | SECURITY_FLAG_IGNORE_CERT_CN_INVALID
+                    | SECURITY_FLAG_IGNORE_CERT_WRONG_USAGE;
+                WinHttpSetOption(
+                    Some(request.0 as *const c_void),
+                    WINHTTP_OPTION_SECURITY_FLAGS,
+                    Some(&ignore.to_ne_bytes()),
+                )?;
+            }
+
+            // WinHTTP follows redirects by default, and hides the hops
+            let policy = if config.follow_redirects {
+                WINHTTP_OPTION_REDIRECT_POLICY_ALWAYS
+            } else {
+                WINHTTP_OPTION_REDIRECT_POLICY_NEVER
+            };
+            WinHttpSetOption(
+                Some(request.0 as *const c_void),
+                WINHTTP_OPTION_REDIRECT_POLICY,
+                Some(&policy.to_ne_bytes()),
+            )?;
+            if config.follow_redirects {
+                WinHttpSetOption(
+                    Some(request.0 as *const c_void),
+                    WINHTTP_OPTION_MAX_HTTP_AUTOMATIC_REDIRECTS,
+                    Some(&config.max_redirects.to_ne_bytes()),
+                )?;
+            }
+
+            // Like `curl -u :`, send the logged-on user's credentials to
+            // any server that asks, not only to intranet ones.
+            if let AuthConfig::Negotiate = config.auth {
+                WinHttpSetOption(
+                    Some(request.0 as *const c_void),
+                    WINHTTP_OPTION_AUTOLOGON_POLICY,
+                    Some(&WINHTTP_AUTOLOGON_SECURITY_LEVEL_LOW.to_ne_bytes()),
+                )?;
+            }
+
+            for (name, value) in &config.headers {
+                let line: Vec<u16> = format!("{}: {}\r\n", name, value).encode_utf16().collect();
+                WinHttpAddRequestHeaders(
+                    request.0,
+                    &line,
+                    WINHTTP_ADDREQ_FLAG_ADD | WINHTTP_ADDREQ_FLAG_REPLACE,
+                )?;
+            }
+
+            let body = config.body.as_deref().unwrap_or("").as_bytes();
+            let mut round = 0;
+            let mut auth_events = Vec::new();
+            let mut sent_scheme = None;
+            // WinHTTP resolves and connects inside WinHttpSendRequest, so
+            // only the responses and the end can be timed
+            let start = Instant::now();
+            let mut timings = Timings::default();
+            let status = loop {
+                auth_events.push(match sent_scheme {
+                    Some(scheme) => format!("-> request, Authorization: {}", scheme_name(scheme)),
+                    None => "-> request".to_string(),
+                });
+                WinHttpSendRequest(
+                    request.0,
+                    None,
+                    Some(body.as_ptr() as *const c_void),
+                    body.len() as u32,
+                    body.len() as u32,
+                    0,
+                )?;
+                WinHttpReceiveResponse(request.0, std::ptr::null_mut())?;
+                timings.round_trips.push(start.elapsed());
+
+                let status = query_status(&request)?;
+                auth_events.push(format!("<- {}", status));
+                if status != 401 || round == MAX_AUTH_ROUNDS {
+                    break status;
+                }
+                round += 1;
+
+                let mut supported = 0;
+                let mut first = 0;
+                let mut target = 0;
+                WinHttpQueryAuthSchemes(request.0, &mut supported, &mut first, &mut target)?;
+                if config.verbose {
+                    eprintln!("* WinHTTP: 401, schemes offered {supported:#x}");
+                }
+                let offered: Vec<&str> = SCHEMES
+                    .iter()
+                    .filter(|(scheme, _)| supported & scheme != 0)
+                    .map(|(_, name)| *name)
+                    .collect();
+                if let Some(last) = auth_events.last_mut() {
+                    last.push_str(&format!(", WWW-Authenticate: {}", offered.join(", ")));
+                }
+
+                let (scheme, user, pass) = match &config.auth {
+                    AuthConfig::None => break status,
+                    AuthConfig::Negotiate => (pick_negotiate(supported), None, None),
+                    AuthConfig::NegotiateWithCredentials { username, password } => {
+                        let scheme = if supported & WINHTTP_AUTH_SCHEME_NEGOTIATE.0 != 0
+                            || supported & WINHTTP_AUTH_SCHEME_NTLM.0 != 0
+                        {
+                            pick_negotiate(supported)
+                        } else {
+                            WINHTTP_AUTH_SCHEME_BASIC.0
+                        };
+                        (scheme, Some(username), Some(password))
+                    }
+                    AuthConfig::Ntlm { credentials } => {
+                        let (user, pass) = split(credentials);
+                        (WINHTTP_AUTH_SCHEME_NTLM.0, user, pass)
+                    }
+                    AuthConfig::Digest { username, password } => {
+                        (WINHTTP_AUTH_SCHEME_DIGEST, Some(username), Some(password))
+                    }
+                    AuthConfig::Basic { username, password } => {
+                        (WINHTTP_AUTH_SCHEME_BASIC.0, Some(username), Some(password))
+                    }
+                    AuthConfig::Any { credentials } => {
+                        // SCHEMES is in order of preference
+                        let scheme = SCHEMES
+                            .iter()
+                            .map(|(scheme, _)| *scheme)
+                            .find(|scheme| supported & scheme != 0)
+                            .unwrap_or(0);
+                        let (user, pass) = split(credentials);
+                        (scheme, user, pass)
+                    }
+                };
+                if scheme & supported == 0 {
+                    break status;
+                }
+                if config.verbose {
+                    eprintln!("* WinHTTP: retrying with scheme {scheme:#x}");
+                }
+                sent_scheme = Some(scheme);
+
+                // No user name means the credentials of the logged-on user.
+                let user = user.map(|u| HSTRING::from(u.as_str()));
+                let pass = pass.map(|p| HSTRING::from(p.as_str()));
+                WinHttpSetCredentials(
+                    request.0,
+                    target,
+                    scheme,
+                    pcwstr(user.as_ref()),
+                    pcwstr(pass.as_ref()),
+                    std::ptr::null_mut(),
+                )?;
+            };
+
+            if (300..400).contains(&status) {
+                if let Ok(location) = query_string(&request, WINHTTP_QUERY_LOCATION) {
+                    note_location(&mut auth_events, &location);
+                }
+            }
+
+            let status_text = query_string(&request, WINHTTP_QUERY_STATUS_TEXT)?;
+            let raw_headers = query_string(&request, WINHTTP_QUERY_RAW_HEADERS_CRLF)?;
+            let mut headers = HashMap::new();
+            for line in raw_headers.lines().skip(1) {
+                if let Some(colon_pos) = line.find(':') {
+                    let name = line[..colon_pos].trim().to_string();
+                    let value = line[colon_pos + 1..].trim().to_string();
+                    headers.insert(name, value);
+                }
+            }
+            if config.verbose {
+                eprint!("{}", raw_headers);
+            }
+
+            let mut body = Vec::new();
+            loop {
+                let mut available = 0;
+                WinHttpQueryDataAvailable(request.0, &mut available)?;
+                if available == 0 {
+                    break;
+                }
+                let start = body.len();
+                body.resize(start + available as usize, 0);
+                let mut read = 0;
+                WinHttpReadData(
+                    request.0,
+                    body[start..].as_mut_ptr() as *mut c_void,
+                    available,
+                    &mut read,
+                )?;
+                body.truncate(start + read as usize);
+            }
+
+            timings.total = Some(start.elapsed());
+
+            // With the autologon policy, WinHTTP answers Negotiate
+            // challenges itself, and it runs the legs of Negotiate and NTLM
+            // handshakes within one send, so those round-trips aren't seen.
+            let connection_based = |scheme| {
+                scheme == WINHTTP_AUTH_SCHEME_NEGOTIATE.0 || scheme == WINHTTP_AUTH_SCHEME_NTLM.0
+            };
+            let round_trips = match (&config.auth, sent_scheme) {
+                (AuthConfig::Negotiate, _) => None,
+                (_, Some(scheme)) if connection_based(scheme) => None,
+                _ => Some(round + 1),
+            };
+
+            Ok(Response {
+                status,
+                status_text,
+                headers,
+                body,
+                round_trips,
+                auth_events,
+                timings,
+            })
+        }
+    }
+}
+
+/// The auth schemes WinHTTP reports, with their names, safest first.
+const SCHEMES: &[(u32, &str)] = &[
+    (WINHTTP_AUTH_SCHEME_NEGOTIATE.0, "Negotiate"),
+    (WINHTTP_AUTH_SCHEME_NTLM.0, "NTLM"),
+    (WINHTTP_AUTH_SCHEME_DIGEST, "Digest"),
+    (WINHTTP_AUTH_SCHEME_BASIC.0, "Basic"),
+    (WINHTTP_AUTH_SCHEME_PASSPORT, "Passport"),
+];
+
+fn scheme_name(scheme: u32) -> &'static str {
+    SCHEMES
+        .iter()
+        .find(|(s, _)| *s == scheme)
+        .map_or("?", |(_, name)| name)
+}
+
+/// The user name and password of `credentials`, if any.
+fn split(credentials: &Option<(String, String)>) -> (Option<&String>, Option<&String>) {
+    match credentials {
+        Some((user, pass)) => (Some(user), Some(pass)),
+        None => (None, None),
+    }
+}
+
+/// A pointer to `s`, or null for `None`.
+fn pcwstr(s: Option<&HSTRING>) -> PCWSTR {
+    match s {
+        None => PCWSTR::null(),
+        Some(s) if s.is_empty() => w!(""),
+        Some(s) => PCWSTR(s.as_ptr()),
+    }
+}
+
+/// Negotiate if the server offers it, NTLM otherwise.
+fn pick_negotiate(supported: u32) -> u32 {
+    if supported & WINHTTP_AUTH_SCHEME_NEGOTIATE.0 != 0 {
+        WINHTTP_AUTH_SCHEME_NEGOTIATE.0
+    } else {
+        WINHTTP_AUTH_SCHEME_NTLM.0
+    }
+}
+
+unsafe fn query_status(request: &Handle) -> windows::core::Result<u16> {
+    let mut status: u32 = 0;
+    let mut len = std::mem::size_of::<u32>() as u32;
+    WinHttpQueryHeaders(
+        request.0,
+        WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+        PCWSTR::null(),
+        Some(&mut status as *mut u32 as *mut c_void),
+        &mut len,
+        std::ptr::null_mut(),
+    )?;
+    Ok(status as u16)
+}
+
+unsafe fn query_string(request: &Handle, info: u32) -> windows::core::Result<String> {
+    // The first call only reports the size needed, in bytes.
+    let mut len = 0;
+    let _ = WinHttpQueryHeaders(
+        request.0,
+        info,
+        PCWSTR::null(),
+        None,
+        &mut len,
+        std::ptr::null_mut(),
+    );
+    let mut buf = vec![0u16; len as usize / 2 + 1];
+    WinHttpQueryHeaders(
+        request.0,
+        info,
+        PCWSTR::null(),
+        Some(buf.as_mut_ptr() as *mut c_void),
+        &mut len,
+        std::ptr::null_mut(),
+    )?;
+    Ok(String::from_utf16_lossy(&buf[..len as usize / 2]))
+}
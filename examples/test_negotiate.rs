@@ -1,6 +1,6 @@
 // examples/test_negotiate.rs
 
-//! Test CLI for comparing curl vs reqwest negotiate authentication implementations.
+//! Test CLI for comparing curl, WinHTTP, hyper and reqwest negotiate authentication implementations.
 //!
 //! # Usage
 //!
@@ -10,6 +10,18 @@
 //!     https://ad-server.corp.com/api --negotiate
 //! ```
 //!
+//! Test with WinHTTP backend (Windows' built-in implementation):
+//! ```bash
+//! cargo run --example test_negotiate --no-default-features --features backend-winhttp -- \
+//!     https://ad-server.corp.com/api --negotiate
+//! ```
+//!
+//! Test with hyper backend (raw SSPI tokens, printing the message heads):
+//! ```bash
+//! cargo run --example test_negotiate --no-default-features --features backend-hyper -- \
+//!     https://ad-server.corp.com/api --negotiate
+//! ```
+//!
 //! Test with reqwest backend (custom implementation):
 //! ```bash
 //! cargo run --example test_negotiate --no-default-features --features backend-reqwest -- \
@@ -22,6 +34,54 @@
 //!     https://ad-server.corp.com/api --negotiate -u user:pass
 //! ```
 //!
+//! Exercise a single scheme with `--ntlm`, `--digest` or `--basic-only`, or
+//! let the backend pick with `--anyauth`:
+//! ```bash
+//! cargo run --example test_negotiate -F backend-curl -- \
+//!     https://ad-server.corp.com/api --digest -u user:pass
+//! ```
+//!
+//! Compare every backend compiled in on the same request:
+//! ```bash
+//! cargo run --example test_negotiate -F backend-curl,backend-winhttp,backend-reqwest -- \
+//!     https://ad-server.corp.com/api --negotiate --compare
+//! ```
+//!
+//! JSON output, for CI smoke tests:
+//! ```bash
+//! cargo run --example test_negotiate -F backend-reqwest -- \
+//!     https://ad-server.corp.com/api --negotiate --output json
+//! ```
+//!
+//! Follow the redirects to an STS and save what comes back:
+//! ```bash
+//! cargo run --example test_negotiate -F backend-reqwest -- \
+//!     https://app.corp.com/login --negotiate -L --max-redirs 5 -o token.html
+//! ```
+//!
+//! Find out which SPN a Kerberos ticket can be had for: the host, its
+//! canonical name, each with and without the port:
+//! ```bash
+//! cargo run --example test_negotiate -F backend-reqwest -- \
+//!     https://ad-server.corp.com/api --probe-spn
+//! ```
+//!
+//! Settings used again and again can be kept as profiles in a TOML file,
+//! `test_negotiate.toml` unless `--config` says otherwise:
+//! ```toml
+//! [corp-api]
+//! url = "https://ad-server.corp.com/api"
+//! auth = "negotiate"  # or "ntlm", "digest", "basic", "any"
+//! user = "user@CORP.COM:password"
+//! headers = ["Accept: application/json"]
+//! proxy = "http://proxy.corp.com:8080"
+//! insecure = true
+//! ```
+//! and used with `--profile`, other options adding to or overriding them:
+//! ```bash
+//! cargo run --example test_negotiate -F backend-reqwest -- --profile corp-api -v
+//! ```
+//!
 //! POST with JSON:
 //! ```bash
 //! cargo run --example test_negotiate -F backend-reqwest -- \
@@ -30,14 +90,28 @@
 
 mod backends;
 
-use backends::{get_backend, Method, RequestConfig};
+use backends::{all_backends, get_backend, spn, Method, RequestConfig, Response};
+use base64::Engine;
+use std::collections::BTreeSet;
 use std::env;
+use std::time::{Duration, Instant};
+
+/// Headers that differ on every response, left out of `--compare`.
+const VOLATILE_HEADERS: &[&str] = &["date"];
+
+/// The profile file read by `--profile` when `--config` isn't given.
+const DEFAULT_CONFIG: &str = "test_negotiate.toml";
 
 fn print_usage() {
     eprintln!("Usage: test_negotiate <url> [OPTIONS]");
+    eprintln!("       test_negotiate --profile <name> [<url>] [OPTIONS]");
     eprintln!();
     eprintln!("Options:");
     eprintln!("  --negotiate          Enable Negotiate (Kerberos/SPNEGO) authentication");
+    eprintln!("  --ntlm               Use NTLM only");
+    eprintln!("  --digest             Use Digest authentication (needs -u)");
+    eprintln!("  --basic-only         Use Basic authentication only (needs -u)");
+    eprintln!("  --anyauth            Use the safest scheme the server offers");
     eprintln!("  -u, --user <user:pass>  Username and password (enables fallback to Basic)");
     eprintln!("  --post               Use POST method");
     eprintln!("  --put                Use PUT method");
@@ -48,6 +122,22 @@ fn print_usage() {
     eprintln!("  --data <data>        Send request body");
     eprintln!("  -v, --verbose        Verbose output");
     eprintln!("  -k, --insecure       Skip SSL verification");
+    eprintln!("  -x, --proxy <url>    Send the request through this proxy");
+    eprintln!("  -L, --location       Follow redirects");
+    eprintln!("  --max-redirs <n>     Follow at most n redirects (default: 50)");
+    eprintln!("  -o, --output-file <file>  Save the response body to a file");
+    eprintln!(
+        "  --profile <name>     Start from the settings of a profile; options given override them"
+    );
+    eprintln!(
+        "  --config <file>      The profile file (default: {})",
+        DEFAULT_CONFIG
+    );
+    eprintln!("  --compare            Run all compiled backends and show the differences");
+    eprintln!("  --probe-spn          Try each SPN the host could go by for a Kerberos ticket");
+    eprintln!(
+        "  --output <text|json> Output format (json prints one object, or an array with --compare)"
+    );
     eprintln!("  -h, --help           Show this help");
     eprintln!();
     eprintln!("Examples:");
@@ -59,9 +149,16 @@ fn print_usage() {
     eprintln!("  test_negotiate https://ad-server.corp.com/api --negotiate \\");
     eprintln!("    -u user@DOMAIN.COM:password --post --json '{{\"query\":\"test\"}}'");
     eprintln!();
+    eprintln!("  # Find the SPN the server is registered under:");
+    eprintln!("  test_negotiate https://ad-server.corp.com/api --probe-spn");
+    eprintln!();
     eprintln!("  # Compare backends:");
     eprintln!("  cargo run --example test_negotiate -F backend-curl -- <url> --negotiate");
+    eprintln!("  cargo run --example test_negotiate -F backend-winhttp -- <url> --negotiate");
+    eprintln!("  cargo run --example test_negotiate -F backend-hyper -- <url> --negotiate");
     eprintln!("  cargo run --example test_negotiate -F backend-reqwest -- <url> --negotiate");
+    eprintln!("  cargo run --example test_negotiate -F backend-curl,backend-reqwest -- \\");
+    eprintln!("    <url> --negotiate --compare");
 }
 
 fn parse_user_pass(s: &str) -> (String, String) {
@@ -74,33 +171,585 @@ fn parse_user_pass(s: &str) -> (String, String) {
     }
 }
 
+/// Runs `config` through every backend compiled in, and prints a row for
+/// the status, round-trips, body size and each header, marking with `!` the
+/// rows where the backends disagree. Returns whether they all agreed.
+fn compare(config: &RequestConfig) -> bool {
+    let backends = all_backends();
+    if backends.len() < 2 {
+        eprintln!(
+            "Note: {} backend(s) compiled in; enable more with \
+             -F backend-curl,backend-winhttp,backend-hyper,backend-reqwest",
+            backends.len()
+        );
+    }
+
+    let results: Vec<(&'static str, Result<Response, String>)> = backends
+        .iter()
+        .map(|backend| {
+            println!("Sending request with {}...", backend.name());
+            let result = backend.execute(config).map_err(|e| e.to_string());
+            (backend.name(), result)
+        })
+        .collect();
+
+    let header_names: BTreeSet<String> = results
+        .iter()
+        .filter_map(|(_, result)| result.as_ref().ok())
+        .flat_map(|response| response.headers.keys())
+        .map(|name| name.to_ascii_lowercase())
+        .filter(|name| !VOLATILE_HEADERS.contains(&name.as_str()))
+        .collect();
+
+    let mut rows: Vec<(String, Vec<String>)> = Vec::new();
+    let mut row = |label: String, value: &dyn Fn(&Response) -> String| {
+        let values = results
+            .iter()
+            .map(|(_, result)| match result {
+                Ok(response) => value(response),
+                Err(_) => "-".to_string(),
+            })
+            .collect();
+        rows.push((label, values));
+    };
+    row("status".to_string(), &|r| {
+        format!("{} {}", r.status, r.status_text)
+    });
+    row("round-trips".to_string(), &|r| {
+        r.round_trips.map_or("?".to_string(), |n| n.to_string())
+    });
+    row("body bytes".to_string(), &|r| r.body.len().to_string());
+    for name in &header_names {
+        row(format!("{}:", name), &|r| {
+            r.headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map_or("-".to_string(), |(_, v)| v.clone())
+        });
+    }
+    rows.insert(
+        0,
+        (
+            "error".to_string(),
+            results
+                .iter()
+                .map(|(_, result)| result.as_ref().err().cloned().unwrap_or_default())
+                .collect(),
+        ),
+    );
+
+    const MAX_WIDTH: usize = 40;
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = results
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| {
+            rows.iter()
+                .map(|(_, values)| values[i].len())
+                .chain(Some(name.len()))
+                .max()
+                .unwrap_or(0)
+                .min(MAX_WIDTH)
+        })
+        .collect();
+    let cell = |value: &str, width: usize| {
+        let value: String = value.chars().take(width).collect();
+        format!("{:<width$}", value, width = width)
+    };
+
+    println!();
+    println!("=== Comparison ===");
+    let names: Vec<String> = results
+        .iter()
+        .zip(&widths)
+        .map(|((name, _), &width)| cell(name, width))
+        .collect();
+    let header = format!("  {:<label_width$}  {}", "", names.join(" | "));
+    println!("{}", header.trim_end());
+
+    let mut agreed = true;
+    for (label, values) in &rows {
+        if label == "error" && values.iter().all(String::is_empty) {
+            continue;
+        }
+        // An unknown value doesn't count as a difference.
+        let mut known = values.iter().filter(|v| v.as_str() != "?");
+        let differs = match known.next() {
+            Some(first) => known.any(|v| v != first),
+            None => false,
+        };
+        agreed &= !differs;
+        let values: Vec<String> = values
+            .iter()
+            .zip(&widths)
+            .map(|(value, &width)| cell(value, width))
+            .collect();
+        let line = format!(
+            "{} {:<label_width$}  {}",
+            if differs { '!' } else { ' ' },
+            label,
+            values.join(" | ")
+        );
+        println!("{}", line.trim_end());
+    }
+
+    println!();
+    if agreed {
+        println!("✓ All backends agree");
+    } else {
+        println!("✗ Backends differ on the rows marked with !");
+    }
+    agreed
+}
+
+/// `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `time` in milliseconds, or `null`.
+fn json_ms(time: Option<Duration>) -> String {
+    time.map_or("null".to_string(), |time| {
+        format!("{:.3}", time.as_secs_f64() * 1000.0)
+    })
+}
+
+/// What one backend got for `config`, as a JSON object. The body is a string
+/// if it is UTF-8, and base64 otherwise, as told by `body_encoding`.
+fn json_result(
+    backend: &str,
+    config: &RequestConfig,
+    result: &Result<Response, String>,
+    elapsed: Duration,
+    body_file: Option<&str>,
+) -> String {
+    let mut fields = vec![
+        ("backend", json_string(backend)),
+        ("url", json_string(&config.url)),
+        ("method", json_string(&config.method.to_string())),
+        ("elapsed_ms", json_ms(Some(elapsed))),
+    ];
+    match result {
+        Ok(response) => {
+            fields.push(("status", response.status.to_string()));
+            fields.push(("status_text", json_string(&response.status_text)));
+            fields.push((
+                "round_trips",
+                response
+                    .round_trips
+                    .map_or("null".to_string(), |n| n.to_string()),
+            ));
+            let events: Vec<String> = response
+                .auth_events
+                .iter()
+                .map(|event| json_string(event))
+                .collect();
+            fields.push(("auth_events", format!("[{}]", events.join(","))));
+            let timings = &response.timings;
+            let round_trips: Vec<String> = timings
+                .round_trips
+                .iter()
+                .map(|time| json_ms(Some(*time)))
+                .collect();
+            fields.push((
+                "timings_ms",
+                format!(
+                    "{{\"dns\":{},\"connect\":{},\"tls\":{},\"round_trips\":[{}],\"ttfb\":{},\"total\":{}}}",
+                    json_ms(timings.dns),
+                    json_ms(timings.connect),
+                    json_ms(timings.tls),
+                    round_trips.join(","),
+                    json_ms(timings.ttfb()),
+                    json_ms(timings.total),
+                ),
+            ));
+            let mut headers: Vec<(&String, &String)> = response.headers.iter().collect();
+            headers.sort();
+            let headers: Vec<String> = headers
+                .iter()
+                .map(|(name, value)| format!("{}:{}", json_string(name), json_string(value)))
+                .collect();
+            fields.push(("headers", format!("{{{}}}", headers.join(","))));
+            if let Some(path) = body_file {
+                fields.push(("body_file", json_string(path)));
+            } else {
+                let (body, encoding) = match std::str::from_utf8(&response.body) {
+                    Ok(text) => (text.to_string(), "utf-8"),
+                    Err(_) => (
+                        base64::engine::general_purpose::STANDARD.encode(&response.body),
+                        "base64",
+                    ),
+                };
+                fields.push(("body", json_string(&body)));
+                fields.push(("body_encoding", json_string(encoding)));
+            }
+        }
+        Err(e) => fields.push(("error", json_string(e))),
+    }
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(name, value)| format!("{}:{}", json_string(name), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Writes the body of `response` to the file at `path`.
+fn save_body(path: &str, response: &Response) -> Result<(), String> {
+    std::fs::write(path, &response.body).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Runs `config` through `backends`, printing what each got as JSON. Returns
+/// whether every backend got a response.
+fn print_json(
+    backends: &[Box<dyn backends::HttpBackend>],
+    config: &RequestConfig,
+    array: bool,
+    body_file: Option<&str>,
+) -> bool {
+    let mut ok = true;
+    let results: Vec<String> = backends
+        .iter()
+        .map(|backend| {
+            let start = Instant::now();
+            let result = backend.execute(config).map_err(|e| e.to_string());
+            let elapsed = start.elapsed();
+            let result = match (result, body_file) {
+                (Ok(response), Some(path)) => save_body(path, &response).map(|()| response),
+                (result, _) => result,
+            };
+            ok &= result.is_ok();
+            json_result(backend.name(), config, &result, elapsed, body_file)
+        })
+        .collect();
+    if array {
+        println!("[{}]", results.join(","));
+    } else {
+        println!("{}", results.join("\n"));
+    }
+    ok
+}
+
+/// Probes the SPNs the host of `url` could go by, and prints which of them
+/// a Kerberos ticket can be had for. Returns whether there was one for any.
+fn print_probe(url: &str, credentials: Option<(&str, &str)>, json: bool) -> bool {
+    let probe = match spn::probe(url, credentials) {
+        Ok(probe) => probe,
+        Err(e) => {
+            if json {
+                println!(
+                    "{{\"url\":{},\"error\":{}}}",
+                    json_string(url),
+                    json_string(&e)
+                );
+            } else {
+                eprintln!("Error: {}", e);
+            }
+            return false;
+        }
+    };
+    let found = probe
+        .candidates
+        .iter()
+        .filter(|candidate| candidate.result.is_ok())
+        .count();
+
+    if json {
+        let addresses: Vec<String> = probe
+            .addresses
+            .iter()
+            .map(|address| json_string(&address.to_string()))
+            .collect();
+        let candidates: Vec<String> = probe
+            .candidates
+            .iter()
+            .map(|candidate| {
+                let result = match candidate.result {
+                    Ok(_) => "\"ok\":true".to_string(),
+                    Err(ref e) => format!("\"ok\":false,\"error\":{}", json_string(e)),
+                };
+                format!(
+                    "{{\"spn\":{},\"source\":{},{}}}",
+                    json_string(&candidate.spn),
+                    json_string(candidate.source),
+                    result
+                )
+            })
+            .collect();
+        println!(
+            "{{\"url\":{},\"host\":{},\"addresses\":[{}],\"canonical_name\":{},\"spns\":[{}]}}",
+            json_string(url),
+            json_string(&probe.host),
+            addresses.join(","),
+            probe
+                .canonical_name
+                .as_deref()
+                .map_or("null".to_string(), json_string),
+            candidates.join(",")
+        );
+        return found > 0;
+    }
+
+    println!("=== SPN Probe ===");
+    println!("URL: {}", url);
+    let addresses: Vec<String> = probe.addresses.iter().map(|a| a.to_string()).collect();
+    println!("{} resolves to {}", probe.host, addresses.join(", "));
+    if let Some(ref canonical) = probe.canonical_name {
+        println!("Canonical name: {}", canonical);
+    }
+    println!();
+    for candidate in &probe.candidates {
+        match candidate.result {
+            Ok(_) => println!("  ✓ {} ({})", candidate.spn, candidate.source),
+            Err(ref e) => {
+                println!("  ✗ {} ({})", candidate.spn, candidate.source);
+                println!("      {}", e);
+            }
+        }
+    }
+    println!();
+    if found > 0 {
+        println!(
+            "✓ A ticket can be had for {} of {} SPNs",
+            found,
+            probe.candidates.len()
+        );
+    } else {
+        println!("✗ No ticket for any SPN; Negotiate will fall back to NTLM or fail");
+    }
+    found > 0
+}
+
+/// A value in a profile file.
+enum Value {
+    String(String),
+    Bool(bool),
+    Array(Vec<String>),
+}
+
+/// Replaces `--profile <name>` and `--config <file>` in `args` with the
+/// arguments the profile stands for, put first so that the others override
+/// them.
+fn expand_profile(args: Vec<String>) -> Result<Vec<String>, String> {
+    let mut profile = None;
+    let mut path = DEFAULT_CONFIG.to_string();
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" | "--config" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires an argument", arg))?;
+                if arg == "--profile" {
+                    profile = Some(value);
+                } else {
+                    path = value;
+                }
+            }
+            _ => rest.push(arg),
+        }
+    }
+    match profile {
+        Some(name) => {
+            let mut args = load_profile(&path, &name)?;
+            args.extend(rest);
+            Ok(args)
+        }
+        None => Ok(rest),
+    }
+}
+
+/// Reads the `[name]` profile of the file at `path`, as command-line
+/// arguments.
+///
+/// Only the part of TOML that profiles need is understood: tables, and keys
+/// set on one line to a string, a boolean or an array of strings.
+fn load_profile(path: &str, name: &str) -> Result<Vec<String>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut profiles = Vec::new();
+    let mut args = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let at = |e: String| format!("{}:{}: {}", path, n + 1, e);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let (header, rest) = header
+                .split_once(']')
+                .ok_or_else(|| at("unclosed table header".to_string()))?;
+            if !rest.trim().is_empty() && !rest.trim().starts_with('#') {
+                return Err(at(format!("unexpected `{}`", rest.trim())));
+            }
+            profiles.push(header.trim().trim_matches('"').to_string());
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| at("expected `key = value`".to_string()))?;
+        let value = parse_value(value).map_err(at)?;
+        if profiles.last().map(String::as_str) == Some(name) {
+            profile_args(key.trim().trim_matches('"'), value, &mut args).map_err(at)?;
+        }
+    }
+    if !profiles.iter().any(|profile| profile == name) {
+        return Err(format!(
+            "{}: no profile '{}' (found: {})",
+            path,
+            name,
+            profiles.join(", ")
+        ));
+    }
+    Ok(args)
+}
+
+/// Adds the command-line arguments for `key = value` to `args`.
+fn profile_args(key: &str, value: Value, args: &mut Vec<String>) -> Result<(), String> {
+    match (key, value) {
+        ("url", Value::String(url)) => args.push(url),
+        ("auth", Value::String(auth)) => {
+            let flag = match auth.as_str() {
+                "negotiate" => "--negotiate",
+                "ntlm" => "--ntlm",
+                "digest" => "--digest",
+                "basic" => "--basic-only",
+                "any" => "--anyauth",
+                other => return Err(format!("unknown auth '{}'", other)),
+            };
+            args.push(flag.to_string());
+        }
+        ("user", Value::String(user)) => args.extend(["-u".to_string(), user]),
+        ("headers", Value::Array(headers)) => {
+            for header in headers {
+                args.extend(["-H".to_string(), header]);
+            }
+        }
+        ("proxy", Value::String(proxy)) => args.extend(["--proxy".to_string(), proxy]),
+        ("insecure", Value::Bool(insecure)) => {
+            if insecure {
+                args.push("--insecure".to_string());
+            }
+        }
+        ("url" | "auth" | "user" | "headers" | "proxy" | "insecure", _) => {
+            return Err(format!("wrong type for `{}`", key));
+        }
+        _ => return Err(format!("unknown key `{}`", key)),
+    }
+    Ok(())
+}
+
+/// Parses the value of a `key = value` line.
+fn parse_value(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    let (value, rest) = if let Some(mut rest) = text.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                break (Value::Array(items), after);
+            }
+            let (item, after) = parse_string(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("expected `,` or `]` in array".to_string());
+            }
+        }
+    } else if let Some(rest) = text.strip_prefix("true") {
+        (Value::Bool(true), rest)
+    } else if let Some(rest) = text.strip_prefix("false") {
+        (Value::Bool(false), rest)
+    } else {
+        let (string, rest) = parse_string(text)?;
+        (Value::String(string), rest)
+    };
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("unexpected `{}`", rest));
+    }
+    Ok(value)
+}
+
+/// Parses the string `text` starts with, returning it and what follows.
+fn parse_string(text: &str) -> Result<(String, &str), String> {
+    if let Some(rest) = text.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unclosed string")?;
+        return Ok((rest[..end].to_string(), &rest[end + 1..]));
+    }
+    let rest = text
+        .strip_prefix('"')
+        .ok_or_else(|| format!("expected a value, found `{}`", text))?;
+    let mut string = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((string, &rest[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => string.push('\n'),
+                Some((_, 't')) => string.push('\t'),
+                Some((_, c @ ('"' | '\\'))) => string.push(c),
+                _ => return Err("unsupported escape in string".to_string()),
+            },
+            c => string.push(c),
+        }
+    }
+    Err("unclosed string".to_string())
+}
+
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
 
-    if args.is_empty() || args.contains(&"-h".to_string()) || args.contains(&"--help".to_string())
-    {
+    if args.is_empty() || args.contains(&"-h".to_string()) || args.contains(&"--help".to_string()) {
         print_usage();
         std::process::exit(if args.is_empty() { 1 } else { 0 });
     }
 
-    let url = &args[0];
+    let args = match expand_profile(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     // Parse arguments
-    let mut config = RequestConfig::new(url);
-    let mut i = 1;
+    let mut url = None;
+    let mut config = RequestConfig::new("");
+    let mut compare_backends = false;
+    let mut probe_spn = false;
+    let mut json = false;
+    let mut output_file = None;
+    let mut scheme = None;
+    let mut credentials = None;
+    let mut i = 0;
 
     while i < args.len() {
         match args[i].as_str() {
-            "--negotiate" => {
-                config = config.negotiate();
+            "--negotiate" | "--ntlm" | "--digest" | "--basic-only" | "--anyauth" => {
+                scheme = Some(args[i].clone());
             }
             "-u" | "--user" => {
                 if i + 1 >= args.len() {
                     eprintln!("Error: {} requires an argument", args[i]);
                     std::process::exit(1);
                 }
-                let (user, pass) = parse_user_pass(&args[i + 1]);
-                config = config.negotiate_with_credentials(user, pass);
+                credentials = Some(parse_user_pass(&args[i + 1]));
                 i += 1;
             }
             "--post" => {
@@ -159,6 +808,63 @@ fn main() {
             "-k" | "--insecure" => {
                 config = config.insecure(true);
             }
+            "--compare" => {
+                compare_backends = true;
+            }
+            "--probe-spn" => {
+                probe_spn = true;
+            }
+            "-L" | "--location" => {
+                config = config.follow_redirects(true);
+            }
+            "--max-redirs" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: --max-redirs requires an argument");
+                    std::process::exit(1);
+                }
+                match args[i + 1].parse() {
+                    Ok(max) => config = config.max_redirects(max),
+                    Err(_) => {
+                        eprintln!("Error: Invalid --max-redirs '{}'", args[i + 1]);
+                        std::process::exit(1);
+                    }
+                }
+                i += 1;
+            }
+            "-o" | "--output-file" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: {} requires an argument", args[i]);
+                    std::process::exit(1);
+                }
+                output_file = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--output" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: --output requires an argument");
+                    std::process::exit(1);
+                }
+                json = match args[i + 1].as_str() {
+                    "json" => true,
+                    "text" => false,
+                    other => {
+                        eprintln!("Error: Unknown output format '{}'", other);
+                        std::process::exit(1);
+                    }
+                };
+                i += 1;
+            }
+            "-x" | "--proxy" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: {} requires an argument", args[i]);
+                    std::process::exit(1);
+                }
+                config = config.proxy(args[i + 1].clone());
+                i += 1;
+            }
+            arg if !arg.starts_with('-') => {
+                url = Some(arg.to_string());
+            }
             _ => {
                 eprintln!("Error: Unknown option '{}'", args[i]);
                 print_usage();
@@ -168,11 +874,74 @@ fn main() {
         i += 1;
     }
 
+    config.url = match url {
+        Some(url) => url,
+        None => {
+            eprintln!("Error: No URL given");
+            print_usage();
+            std::process::exit(1);
+        }
+    };
+
+    if probe_spn {
+        if compare_backends {
+            eprintln!("Error: --probe-spn can't be used with --compare");
+            std::process::exit(1);
+        }
+        let credentials = credentials
+            .as_ref()
+            .map(|(user, pass)| (user.as_str(), pass.as_str()));
+        let ok = print_probe(&config.url, credentials, json);
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    // As with curl, the last scheme flag wins, and credentials go with it
+    config = match (scheme.as_deref(), credentials) {
+        (None, None) => config,
+        (None, Some((user, pass))) | (Some("--negotiate"), Some((user, pass))) => {
+            config.negotiate_with_credentials(user, pass)
+        }
+        (Some("--negotiate"), None) => config.negotiate(),
+        (Some("--ntlm"), credentials) => config.ntlm(credentials),
+        (Some("--digest"), Some((user, pass))) => config.digest(user, pass),
+        (Some("--basic-only"), Some((user, pass))) => config.basic_auth(user, pass),
+        (Some("--anyauth"), credentials) => config.any_auth(credentials),
+        (Some(flag), _) => {
+            eprintln!("Error: {} requires -u <user:pass>", flag);
+            std::process::exit(1);
+        }
+    };
+
+    if compare_backends && output_file.is_some() {
+        eprintln!("Error: -o can't be used with --compare");
+        std::process::exit(1);
+    }
+
+    if json {
+        let backends = if compare_backends {
+            all_backends()
+        } else {
+            vec![get_backend()]
+        };
+        let ok = print_json(&backends, &config, compare_backends, output_file.as_deref());
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    if compare_backends {
+        println!("=== HTTP Negotiate Authentication Comparison ===");
+        println!("URL: {}", config.url);
+        println!("Method: {}", config.method);
+        println!("Auth: {:?}", config.auth);
+        println!();
+        let agreed = compare(&config);
+        std::process::exit(if agreed { 0 } else { 1 });
+    }
+
     // Get backend and display info
     let backend = get_backend();
     println!("=== HTTP Negotiate Authentication Test ===");
     println!("Backend: {}", backend.name());
-    println!("URL: {}", url);
+    println!("URL: {}", config.url);
     println!("Method: {}", config.method);
     println!("Auth: {:?}", config.auth);
     println!();
@@ -191,6 +960,23 @@ fn main() {
                 println!("✗ Error");
             }
 
+            if !response.auth_events.is_empty() {
+                println!();
+                println!("Exchange:");
+                for event in &response.auth_events {
+                    println!("  {}", event);
+                }
+            }
+
+            println!();
+            println!("Timing:");
+            for (phase, time) in response.timings.phases() {
+                match time {
+                    Some(time) => println!("  {:<14}{:.6}s", phase, time.as_secs_f64()),
+                    None => println!("  {:<14}-", phase),
+                }
+            }
+
             println!();
             println!("Headers:");
             for (name, value) in &response.headers {
@@ -198,13 +984,21 @@ fn main() {
             }
 
             println!();
-            let body_str = response.body_string();
-            if body_str.len() <= 1000 {
-                println!("Body ({} bytes):", body_str.len());
-                println!("{}", body_str);
+            if let Some(ref path) = output_file {
+                if let Err(e) = save_body(path, &response) {
+                    eprintln!("✗ Saving the body failed: {}", e);
+                    std::process::exit(1);
+                }
+                println!("Body ({} bytes) saved to {}", response.body.len(), path);
             } else {
-                println!("Body ({} bytes, truncated):", body_str.len());
-                println!("{}...", &body_str[..1000]);
+                let body_str = response.body_string();
+                if body_str.len() <= 1000 {
+                    println!("Body ({} bytes):", body_str.len());
+                    println!("{}", body_str);
+                } else {
+                    println!("Body ({} bytes, truncated):", body_str.len());
+                    println!("{}...", &body_str[..1000]);
+                }
             }
         }
         Err(e) => {
@@ -0,0 +1,93 @@
+//! Executing many requests with bounded concurrency.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use super::{Client, Request, Response};
+
+type Attempt = Pin<Box<dyn Future<Output = crate::Result<Response>> + Send>>;
+
+enum Slot {
+    Running(Attempt),
+    Done(crate::Result<Response>),
+}
+
+/// The stream returned by [`Client::execute_all`].
+pub(crate) struct ExecuteAll<I> {
+    client: Client,
+    requests: Option<I>,
+    in_flight: VecDeque<Slot>,
+    max_concurrency: usize,
+}
+
+impl<I> ExecuteAll<I>
+where
+    I: Iterator<Item = Request>,
+{
+    pub(crate) fn new(client: Client, requests: I, max_concurrency: usize) -> Self {
+        let max_concurrency = max_concurrency.max(1);
+        ExecuteAll {
+            client,
+            requests: Some(requests),
+            in_flight: VecDeque::with_capacity(max_concurrency),
+            max_concurrency,
+        }
+    }
+}
+
+impl<I> Stream for ExecuteAll<I>
+where
+    I: Iterator<Item = Request> + Unpin,
+{
+    type Item = crate::Result<Response>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        while this.in_flight.len() < this.max_concurrency {
+            match this.requests.as_mut().and_then(Iterator::next) {
+                Some(req) => {
+                    let attempt = this.client.execute(req);
+                    this.in_flight.push_back(Slot::Running(Box::pin(attempt)));
+                }
+                None => {
+                    this.requests = None;
+                    break;
+                }
+            }
+        }
+
+        for slot in this.in_flight.iter_mut() {
+            if let Slot::Running(attempt) = slot {
+                if let Poll::Ready(res) = attempt.as_mut().poll(cx) {
+                    *slot = Slot::Done(res);
+                }
+            }
+        }
+
+        match this.in_flight.front() {
+            Some(Slot::Done(_)) => match this.in_flight.pop_front() {
+                Some(Slot::Done(res)) => Poll::Ready(Some(res)),
+                _ => unreachable!("front slot is done"),
+            },
+            Some(Slot::Running(_)) => Poll::Pending,
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self
+            .requests
+            .as_ref()
+            .map_or((0, Some(0)), Iterator::size_hint);
+        let n = self.in_flight.len();
+        (
+            lower.saturating_add(n),
+            upper.and_then(|upper| upper.checked_add(n)),
+        )
+    }
+}
@@ -14,6 +14,8 @@ use tokio::time::Sleep;
 #[cfg(feature = "stream")]
 use tokio_util::io::ReaderStream;
 
+use super::throttle::{throttled, Throttle};
+
 /// An asynchronous request body.
 pub struct Body {
     inner: Inner,
@@ -36,6 +38,25 @@ pin_project! {
     }
 }
 
+pin_project! {
+    /// A body that errors once more than `remaining` bytes have been read.
+    pub(crate) struct LimitedBody<B> {
+        #[pin]
+        inner: B,
+        remaining: u64,
+    }
+}
+
+#[cfg(feature = "stream")]
+pin_project! {
+    /// The body of an `AsyncRead`, telling its length when it is known.
+    struct ReaderBody<B> {
+        #[pin]
+        inner: B,
+        remaining: Option<u64>,
+    }
+}
+
 pin_project! {
     pub(crate) struct ReadTimeoutBody<B> {
         #[pin]
@@ -91,6 +112,49 @@ impl Body {
         Body::stream(stream)
     }
 
+    /// Create a `Body` streaming what is read from an `AsyncRead`, such as a
+    /// file or the output of a process.
+    ///
+    /// If the `length` of what will be read is known, it is sent as the
+    /// `Content-Length` of the request, and the reader must then yield
+    /// exactly that many bytes. Otherwise the body is sent chunked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use reqwest::Body;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = tokio::fs::File::open("a_large_file.txt").await?;
+    /// let length = file.metadata().await?.len();
+    /// let body = Body::from_async_read(file, Some(length));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the `stream` feature to be enabled.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub fn from_async_read<R>(reader: R, length: Option<u64>) -> Body
+    where
+        R: tokio::io::AsyncRead + Send + 'static,
+    {
+        use futures_util::TryStreamExt;
+        use http_body::Frame;
+        use http_body_util::{BodyExt, StreamBody};
+
+        let frames = ReaderStream::new(reader).map_ok(Frame::data);
+        let inner = StreamBody::new(sync_wrapper::SyncStream::new(frames));
+        let body = ReaderBody {
+            inner,
+            remaining: length,
+        };
+        Body {
+            inner: Inner::Streaming(body.map_err(Into::into).boxed()),
+        }
+    }
+
     #[cfg(any(feature = "stream", feature = "multipart", feature = "blocking"))]
     pub(crate) fn stream<S>(stream: S) -> Body
     where
@@ -292,6 +356,13 @@ pub(crate) fn with_read_timeout<B>(body: B, timeout: Duration) -> ReadTimeoutBod
     }
 }
 
+pub(crate) fn limited<B>(body: B, max: u64) -> LimitedBody<B> {
+    LimitedBody {
+        inner: body,
+        remaining: max,
+    }
+}
+
 impl<B> hyper::body::Body for TotalTimeoutBody<B>
 where
     B: hyper::body::Body,
@@ -370,6 +441,88 @@ where
     }
 }
 
+impl<B> hyper::body::Body for LimitedBody<B>
+where
+    B: hyper::body::Body<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Data = Bytes;
+    type Error = crate::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<hyper::body::Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+
+        // Fail early if a Content-Length already tells us it's too big.
+        if this.inner.size_hint().lower() > *this.remaining {
+            return Poll::Ready(Some(Err(crate::error::body(crate::error::BodyTooLarge))));
+        }
+
+        let frame = match ready!(this.inner.poll_frame(cx)) {
+            Some(Ok(frame)) => frame,
+            Some(Err(err)) => return Poll::Ready(Some(Err(crate::error::body(err)))),
+            None => return Poll::Ready(None),
+        };
+
+        if let Some(data) = frame.data_ref() {
+            let len = data.len() as u64;
+            if len > *this.remaining {
+                return Poll::Ready(Some(Err(crate::error::body(crate::error::BodyTooLarge))));
+            }
+            *this.remaining -= len;
+        }
+        Poll::Ready(Some(Ok(frame)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+}
+
+// ===== impl ReaderBody =====
+
+#[cfg(feature = "stream")]
+impl<B> hyper::body::Body for ReaderBody<B>
+where
+    B: hyper::body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<hyper::body::Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if let (Some(Ok(frame)), Some(remaining)) = (&frame, this.remaining) {
+            if let Some(data) = frame.data_ref() {
+                *remaining = remaining.saturating_sub(data.len() as u64);
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        match self.remaining {
+            Some(remaining) => http_body::SizeHint::with_exact(remaining),
+            None => self.inner.size_hint(),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == Some(0) || self.inner.is_end_stream()
+    }
+}
+
 pub(crate) type ResponseBody =
     http_body_util::combinators::BoxBody<Bytes, Box<dyn std::error::Error + Send + Sync>>;
 
@@ -387,6 +540,24 @@ pub(crate) fn response<B>(
     body: B,
     deadline: Option<Pin<Box<Sleep>>>,
     read_timeout: Option<Duration>,
+    download_rate: Option<Throttle>,
+    max_size: Option<u64>,
+) -> ResponseBody
+where
+    B: hyper::body::Body<Data = Bytes> + Send + Sync + 'static,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    match max_size {
+        Some(max) => with_timeouts(limited(body, max), deadline, read_timeout, download_rate),
+        None => with_timeouts(body, deadline, read_timeout, download_rate),
+    }
+}
+
+fn with_timeouts<B>(
+    body: B,
+    deadline: Option<Pin<Box<Sleep>>>,
+    read_timeout: Option<Duration>,
+    download_rate: Option<Throttle>,
 ) -> ResponseBody
 where
     B: hyper::body::Body<Data = Bytes> + Send + Sync + 'static,
@@ -394,6 +565,20 @@ where
 {
     use http_body_util::BodyExt;
 
+    if let Some(throttle) = download_rate {
+        // Waiting on the throttle must not count against the read timeout,
+        // but it does count towards the total deadline.
+        let body = match read_timeout {
+            Some(read) => with_read_timeout(body, read).map_err(box_err).boxed(),
+            None => body.map_err(box_err).boxed(),
+        };
+        let body = throttled(body, throttle);
+        return match deadline {
+            Some(total) => total_timeout(body, total).map_err(box_err).boxed(),
+            None => body.boxed(),
+        };
+    }
+
     match (deadline, read_timeout) {
         (Some(total), Some(read)) => {
             let body = with_read_timeout(body, read).map_err(box_err);
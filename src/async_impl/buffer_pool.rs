@@ -0,0 +1,180 @@
+//! Reusing buffers to read whole response bodies into.
+//!
+//! A body that arrives in several chunks has to be copied into one buffer
+//! by `bytes()`, `text()` or `json()`. With a [`BufferPool`], that buffer is
+//! taken from the pool and handed back once the body is split off it. The
+//! body shares the allocation, so the buffer can only be reused after the
+//! `Bytes` given to the caller are dropped; until then, taking it from the
+//! pool allocates a new one, as without a pool.
+//!
+//! A body that arrives in one chunk is returned as it is, without a copy.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use bytes::{Bytes, BytesMut};
+use http_body::Body as HttpBody;
+use http_body_util::BodyExt;
+
+/// How many buffers are kept at most. Bodies read while all of them are in
+/// use get buffers of their own.
+const MAX_BUFFERS: usize = 64;
+
+#[derive(Clone)]
+pub(crate) struct BufferPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    initial_size: usize,
+    max_size: usize,
+    buffers: Mutex<Vec<BytesMut>>,
+}
+
+impl BufferPool {
+    /// Buffers start with room for `initial_size` bytes, and are dropped
+    /// rather than kept once they have grown past `max_size`.
+    pub(crate) fn new(initial_size: usize, max_size: usize) -> BufferPool {
+        BufferPool {
+            inner: Arc::new(Inner {
+                initial_size,
+                max_size,
+                buffers: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// An empty buffer with room for at least `size` bytes.
+    fn take(&self, size: usize) -> BytesMut {
+        let buf = self.inner.buffers.lock().unwrap().pop();
+        let size = size.max(self.inner.initial_size);
+        match buf {
+            Some(mut buf) => {
+                // Reclaims the allocation if nothing else points into it.
+                buf.reserve(size);
+                buf
+            }
+            None => BytesMut::with_capacity(size),
+        }
+    }
+
+    /// Splits the data off `buf`, and keeps what is left of it for the next
+    /// body unless it has grown too large.
+    fn give(&self, mut buf: BytesMut) -> Bytes {
+        let size = buf.capacity();
+        let data = buf.split().freeze();
+        if size <= self.inner.max_size {
+            let mut buffers = self.inner.buffers.lock().unwrap();
+            if buffers.len() < MAX_BUFFERS {
+                buffers.push(buf);
+            }
+        }
+        data
+    }
+
+    /// Reads all of `body`, into a pooled buffer if it comes in more than
+    /// one chunk. Trailers are skipped.
+    pub(crate) async fn collect<B>(&self, mut body: B) -> Result<Bytes, B::Error>
+    where
+        B: HttpBody<Data = Bytes> + Unpin,
+    {
+        let first = match next_data(&mut body).await? {
+            Some(data) => data,
+            None => return Ok(Bytes::new()),
+        };
+        let second = match next_data(&mut body).await? {
+            Some(data) => data,
+            None => return Ok(first),
+        };
+
+        let rest = body.size_hint().lower() as usize;
+        let mut buf = self.take(first.len() + second.len() + rest);
+        buf.extend_from_slice(&first);
+        buf.extend_from_slice(&second);
+        while let Some(data) = next_data(&mut body).await? {
+            buf.extend_from_slice(&data);
+        }
+        Ok(self.give(buf))
+    }
+}
+
+/// The next non-empty data frame of `body`.
+async fn next_data<B>(body: &mut B) -> Result<Option<Bytes>, B::Error>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+{
+    while let Some(frame) = body.frame().await {
+        if let Ok(data) = frame?.into_data() {
+            if !data.is_empty() {
+                return Ok(Some(data));
+            }
+        }
+    }
+    Ok(None)
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("initial_size", &self.inner.initial_size)
+            .field("max_size", &self.inner.max_size)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::StreamBody;
+
+    fn chunked(chunks: &[Bytes]) -> impl HttpBody<Data = Bytes, Error = ()> + Unpin {
+        let frames: Vec<_> = chunks
+            .iter()
+            .map(|chunk| Ok(http_body::Frame::data(chunk.clone())))
+            .collect();
+        StreamBody::new(futures_util::stream::iter(frames))
+    }
+
+    fn s(s: &'static str) -> Bytes {
+        Bytes::from_static(s.as_bytes())
+    }
+
+    #[tokio::test]
+    async fn single_chunk_is_not_copied() {
+        let pool = BufferPool::new(64, 1024);
+        let hello = s("hello");
+        let body = pool
+            .collect(chunked(&[s(""), hello.clone()]))
+            .await
+            .unwrap();
+        assert_eq!(body, "hello");
+        assert_eq!(body.as_ptr(), hello.as_ptr());
+        assert!(pool.inner.buffers.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reuses_buffer_once_body_is_dropped() {
+        let pool = BufferPool::new(64, 1024);
+        let body = pool.collect(chunked(&[s("hel"), s("lo")])).await.unwrap();
+        assert_eq!(body, "hello");
+        let first = body.as_ptr();
+        drop(body);
+
+        let body = pool.collect(chunked(&[s("wor"), s("ld")])).await.unwrap();
+        assert_eq!(body, "world");
+        assert_eq!(body.as_ptr(), first);
+
+        // Still in use by `body`, so this one gets a buffer of its own.
+        let other = pool.collect(chunked(&[s("a"), s("b")])).await.unwrap();
+        assert_ne!(other.as_ptr(), body.as_ptr());
+    }
+
+    #[tokio::test]
+    async fn drops_buffers_grown_past_max_size() {
+        let pool = BufferPool::new(4, 16);
+        let big = Bytes::from(vec![b'x'; 32]);
+        let body = pool.collect(chunked(&[big.clone(), big])).await.unwrap();
+        assert_eq!(body.len(), 64);
+        assert!(pool.inner.buffers.lock().unwrap().is_empty());
+    }
+}
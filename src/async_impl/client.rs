@@ -2,6 +2,7 @@
 use std::any::Any;
 use std::future::Future;
 use std::net::IpAddr;
+use std::ops::ControlFlow;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{ready, Context, Poll};
@@ -9,19 +10,41 @@ use std::time::Duration;
 use std::{collections::HashMap, convert::TryInto, net::SocketAddr};
 use std::{fmt, str};
 
+use bytes::Bytes;
+
+use super::buffer_pool::BufferPool;
+use super::expect_continue::{gated, Gate};
+use super::informational::Informational;
+use super::intercept::Hook;
+use super::progress::{with_progress, Progress};
+use super::rate_limit::{RateLimit, RateLimited};
 use super::request::{Request, RequestBuilder};
 use super::response::Response;
+use super::throttle::{throttled, Throttle};
 use super::Body;
 #[cfg(feature = "http3")]
+use crate::async_impl::h3_client::alt_svc::AltSvc;
+#[cfg(feature = "http3")]
 use crate::async_impl::h3_client::connect::{H3ClientConfig, H3Connector};
 #[cfg(feature = "http3")]
 use crate::async_impl::h3_client::H3Client;
-use crate::config::{RequestConfig, TotalTimeout};
+use crate::async_impl::mock::MockTransport;
+use crate::async_impl::network_change::NetworkWatch;
+use crate::async_impl::scoped_headers::ScopedHeaders;
+#[cfg(feature = "har")]
+use crate::config::Har;
+#[cfg(feature = "__rustls")]
+use crate::config::TlsVerification;
+use crate::config::{
+    Deadline, DownloadProgress, DownloadRate, ExpectContinue, IfCached, OnInformational,
+    ReadTimeout, RedirectPolicy, RequestConfig, TotalTimeout, UploadProgress, UploadRate,
+};
 #[cfg(unix)]
 use crate::connect::uds::UnixSocketProvider;
 #[cfg(target_os = "windows")]
 use crate::connect::windows_named_pipe::WindowsNamedPipeProvider;
 use crate::connect::{
+    header_case::HeaderCase,
     sealed::{Conn, Unnameable},
     BoxedConnectorLayer, BoxedConnectorService, Connector, ConnectorBuilder,
 };
@@ -31,22 +54,29 @@ use crate::cookie;
 use crate::cookie::service::CookieService;
 #[cfg(feature = "hickory-dns")]
 use crate::dns::hickory::HickoryDnsResolver;
-use crate::dns::{gai::GaiResolver, DnsResolverWithOverrides, DynResolver, Resolve};
+use crate::dns::{gai::GaiResolver, DnsResolverWithOverrides, DynResolver, Prefetched, Resolve};
 use crate::error::{self, BoxError};
+use crate::hsts::HstsService;
 use crate::into_url::try_uri;
+use crate::metrics::Timer;
 use crate::proxy::Matcher as ProxyMatcher;
-use crate::redirect::{self, TowerRedirectPolicy};
+use crate::redirect::{self, AsyncPolicyService, TowerRedirectPolicy};
+use crate::request_id::RequestId;
 #[cfg(feature = "__rustls")]
 use crate::tls::CertificateRevocationList;
 #[cfg(feature = "__tls")]
 use crate::tls::{self, TlsBackend};
+use crate::trace::Span;
 #[cfg(feature = "__tls")]
 use crate::Certificate;
 #[cfg(any(feature = "__native-tls", feature = "__rustls"))]
 use crate::Identity;
-use crate::{IntoUrl, Method, Proxy, Url};
+use crate::{IntoUrl, Method, Proxy, StatusCode, Url};
 
-use http::header::{Entry, HeaderMap, HeaderValue, ACCEPT, PROXY_AUTHORIZATION, USER_AGENT};
+use http::header::{
+    Entry, HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_LENGTH, EXPECT, PROXY_AUTHORIZATION,
+    USER_AGENT,
+};
 use http::uri::Scheme;
 use http::Uri;
 use hyper_util::client::legacy::connect::HttpConnector;
@@ -57,8 +87,9 @@ use pin_project_lite::pin_project;
 use quinn::TransportConfig;
 #[cfg(feature = "http3")]
 use quinn::VarInt;
+use sync_wrapper::SyncFuture;
 use tokio::time::Sleep;
-use tower::util::BoxCloneSyncServiceLayer;
+use tower::util::{BoxCloneSyncService, BoxCloneSyncServiceLayer};
 use tower::{Layer, Service};
 #[cfg(any(
     feature = "gzip",
@@ -88,6 +119,16 @@ use tower_http::follow_redirect::FollowRedirect;
 /// with the `pool_` prefix, such as [`ClientBuilder::pool_idle_timeout`]
 /// and [`ClientBuilder::pool_max_idle_per_host`].
 ///
+/// Connections are pooled by scheme, host and port, and only reused for
+/// requests to that same origin. HTTP/2 connections aren't coalesced across
+/// hostnames, even ones that resolve to the same address and are covered by
+/// the same certificate, so connection-based authentication and SNI-based
+/// routing see one host per connection.
+///
+/// Idle connections can be dropped with [`Client::invalidate_pool`], or
+/// whenever the network changes with
+/// [`ClientBuilder::pool_invalidate_on_network_change`].
+///
 /// [`Rc`]: std::rc::Rc
 #[derive(Clone)]
 pub struct Client {
@@ -95,11 +136,42 @@ pub struct Client {
 }
 
 /// A `ClientBuilder` can be used to create a `Client` with custom configuration.
+///
+/// Builders can be cloned, so that a configuration shared by many clients is
+/// only prepared once, and each client is built from a clone of it. With
+/// rustls, clients built with the same certificate settings also share their
+/// certificate verifier, so building one after the first is cheap.
+///
+/// Each client gets a cookie store of its own from `cookie_store(true)`, and
+/// an in-memory cache of its own from [`ClientBuilder::http_cache`]. Stores
+/// passed in, such as those of `cookie_provider` or
+/// [`CacheConfig::on_disk`](crate::cache::CacheConfig::on_disk), are shared by
+/// every client built with them.
+///
+/// # Example
+///
+/// ```
+/// # fn run() -> Result<(), reqwest::Error> {
+/// use std::time::Duration;
+/// use reqwest::header::{HeaderMap, HeaderValue};
+///
+/// let base = reqwest::Client::builder().timeout(Duration::from_secs(10));
+///
+/// for tenant in ["acme", "initech"] {
+///     let mut headers = HeaderMap::new();
+///     headers.insert("x-tenant", HeaderValue::from_static(tenant));
+///     let client = base.clone().default_headers(headers).build()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
 #[must_use]
+#[derive(Clone)]
 pub struct ClientBuilder {
     config: Config,
 }
 
+#[derive(Clone)]
 enum HttpVersionPref {
     Http1,
     #[cfg(feature = "http2")]
@@ -138,39 +210,346 @@ impl Default for Accepts {
 
 #[derive(Clone)]
 struct HyperService {
-    hyper: HyperClient,
+    hyper: Arc<HyperPool>,
+    retry_stale: bool,
+    proxy_failover: bool,
+    #[cfg(feature = "__rustls")]
+    tls_overrides: Option<Arc<TlsOverrides>>,
+}
+
+/// The hyper client, and what it takes to replace it with one whose pool is
+/// empty.
+///
+/// hyper can't drop the idle connections of its pool, but requests only
+/// hold on to the client they were sent with.
+struct HyperPool {
+    builder: hyper_util::client::legacy::Builder,
+    connector: Connector,
+    client: std::sync::RwLock<HyperClient>,
+}
+
+impl HyperPool {
+    fn client(&self) -> HyperClient {
+        self.client.read().expect("lock poisoned").clone()
+    }
+
+    fn invalidate(&self) {
+        let client = self.builder.build(self.connector.clone());
+        *self.client.write().expect("lock poisoned") = client;
+    }
+}
+
+/// The clients used by requests with their own TLS verification settings.
+///
+/// Each of them has its own connection pool, so a connection verified one
+/// way is never reused by a request expecting another. Only the most
+/// recently used are kept.
+#[cfg(feature = "__rustls")]
+struct TlsOverrides {
+    builder: hyper_util::client::legacy::Builder,
+    connector: ConnectorBuilder,
+    layers: Vec<BoxedConnectorLayer>,
+    provider: Arc<rustls::crypto::CryptoProvider>,
+    // The least recently used first.
+    clients: std::sync::Mutex<Vec<(tls::TlsOverride, HyperClient)>>,
+}
+
+#[cfg(feature = "__rustls")]
+const MAX_TLS_OVERRIDES: usize = 16;
+
+#[cfg(feature = "__rustls")]
+impl TlsOverrides {
+    fn client(&self, tls: &tls::TlsOverride) -> crate::Result<HyperClient> {
+        let mut clients = self.clients.lock().expect("lock poisoned");
+        if let Some(i) = clients.iter().position(|(t, _)| t == tls) {
+            let used = clients.remove(i);
+            let client = used.1.clone();
+            clients.push(used);
+            return Ok(client);
+        }
+
+        let mut connector = self.connector.clone();
+        connector.set_rustls_verifier(tls.verifier(self.provider.clone())?);
+        let client = self.builder.build(connector.build(self.layers.clone()));
+        if clients.len() == MAX_TLS_OVERRIDES {
+            // Its idle connections are closed, and those in use once their
+            // requests are done with them.
+            clients.remove(0);
+        }
+        clients.push((tls.clone(), client.clone()));
+        Ok(client)
+    }
+
+    fn invalidate(&self) {
+        self.clients.lock().expect("lock poisoned").clear();
+    }
+}
+
+/// The connection pools of a client.
+struct Pools {
+    hyper: Arc<HyperPool>,
+    #[cfg(feature = "__rustls")]
+    tls_overrides: Option<Arc<TlsOverrides>>,
+    #[cfg(feature = "http3")]
+    h3: Option<H3Client>,
+    network: Option<NetworkWatch>,
+}
+
+impl Pools {
+    fn invalidate(&self) {
+        self.hyper.invalidate();
+        #[cfg(feature = "__rustls")]
+        if let Some(ref overrides) = self.tls_overrides {
+            overrides.invalidate();
+        }
+        #[cfg(feature = "http3")]
+        if let Some(ref h3) = self.h3 {
+            h3.clear_pool();
+        }
+    }
+
+    /// Invalidates the pools if the network changed.
+    fn check_network(&self) {
+        if let Some(ref network) = self.network {
+            if network.changed() {
+                self.invalidate();
+            }
+        }
+    }
 }
 
 impl Service<hyper::Request<crate::async_impl::body::Body>> for HyperService {
     type Error = crate::Error;
     type Response = http::Response<hyper::body::Incoming>;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + Sync>>;
+    type Future = HyperFuture;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The client is picked when called, and hyper's is always ready.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: hyper::Request<crate::async_impl::body::Body>) -> Self::Future {
+        let replay =
+            if (self.retry_stale || self.proxy_failover) && crate::retry::is_idempotent(&req) {
+                crate::retry::replay(&req)
+            } else {
+                None
+            };
+        // Forwarded by a proxy with fallbacks, the connection is dropped
+        // if the proxy fails.
+        let captured = (self.proxy_failover && req.uri().scheme() == Some(&Scheme::HTTP))
+            .then(|| hyper_util::client::legacy::connect::capture_connection(&mut req));
+        if replay.is_none() && captured.is_none() {
+            return self.send(req);
+        }
+        let sent = self.send(req);
+        let mut this = self.clone();
+        Box::pin(async move {
+            let res = sent.await;
+            let again = match res {
+                Err(ref err) => {
+                    let stale = this.retry_stale && is_stale_connection(err);
+                    if stale {
+                        log::debug!("pooled connection was closed: {err}");
+                    }
+                    stale
+                }
+                Ok(ref res) => proxy_failed(res, captured.as_ref()),
+            };
+            // Sent again once at most, however many pooled connections
+            // or proxies there are to go through.
+            match replay {
+                Some(replay) if again => this.send(replay).await,
+                _ => res,
+            }
+        })
+    }
+}
+
+/// Whether `res` is a gateway error from a proxy with fallbacks, which is
+/// then passed over, and another one could be tried.
+fn proxy_failed<B>(
+    res: &http::Response<B>,
+    captured: Option<&hyper_util::client::legacy::connect::CaptureConnection>,
+) -> bool {
+    if !matches!(
+        res.status(),
+        StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT
+    ) {
+        return false;
+    }
+    let health = match res.extensions().get::<crate::proxy::ProxyHealth>() {
+        Some(health) => health,
+        None => return false,
+    };
+    log::debug!("proxy answered {}, passing over it", res.status());
+    health.failed();
+    if let Some(captured) = captured {
+        if let Some(connected) = captured.connection_metadata().as_ref() {
+            connected.poison();
+        }
+    }
+    health.has_alternative()
+}
+
+/// Whether `err` means a connection the server closed while it was idle
+/// in the pool, so the request wasn't processed and can be sent again.
+fn is_stale_connection(err: &crate::Error) -> bool {
+    use std::error::Error as _;
+
+    let err = match err
+        .source()
+        .and_then(|err| err.downcast_ref::<hyper_util::client::legacy::Error>())
+    {
+        Some(err) if !err.is_connect() => err,
+        _ => return false,
+    };
+    if !crate::connect::ConnectionUse::was_reused(err) {
+        return false;
+    }
+    match err
+        .source()
+        .and_then(|err| err.downcast_ref::<hyper::Error>())
+    {
+        Some(err) if err.is_incomplete_message() || err.is_canceled() => true,
+        Some(err) => err
+            .source()
+            .and_then(|err| err.downcast_ref::<std::io::Error>())
+            .map_or(false, |err| {
+                matches!(
+                    err.kind(),
+                    std::io::ErrorKind::ConnectionReset
+                        | std::io::ErrorKind::ConnectionAborted
+                        | std::io::ErrorKind::BrokenPipe
+                )
+            }),
+        None => false,
+    }
+}
+
+type HyperFuture = Pin<
+    Box<dyn Future<Output = crate::Result<http::Response<hyper::body::Incoming>>> + Send + Sync>,
+>;
+
+impl HyperService {
+    fn send(&mut self, req: hyper::Request<crate::async_impl::body::Body>) -> HyperFuture {
+        // Record the request as it goes on the wire, once per attempt.
+        #[cfg(feature = "har")]
+        let har = RequestConfig::<Har>::get(req.extensions())
+            .map(|recorder| recorder.capture(&req, req.body().as_bytes()));
+        // Throttle here, beneath the retry layer, so a replayed request
+        // still has a reusable body.
+        let req = match RequestConfig::<UploadRate>::get(req.extensions()) {
+            Some(throttle) => {
+                let throttle = throttle.clone();
+                req.map(|body| Body::wrap(throttled(body, throttle)))
+            }
+            None => req,
+        };
+        // Likewise, a replayed body reports its progress from the start.
+        let mut req = match RequestConfig::<UploadProgress>::get(req.extensions()) {
+            Some(progress) => {
+                let progress = progress.clone();
+                let total = req
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|len| len.to_str().ok())
+                    .and_then(|len| len.parse().ok())
+                    .or_else(|| http_body::Body::size_hint(req.body()).exact());
+                req.map(|body| Body::wrap(with_progress(body, progress, total)))
+            }
+            None => req,
+        };
+        let mut informational = RequestConfig::<OnInformational>::get(req.extensions()).cloned();
+        // Hold the body back until the server asks for it.
+        let gate = match RequestConfig::<ExpectContinue>::get(req.extensions()) {
+            Some(&timeout) if !http_body::Body::is_end_stream(req.body()) => {
+                let gate = Gate::new();
+                let opener = gate.clone();
+                let user = informational.take();
+                informational = Some(Informational::new(move |status, headers| {
+                    if status == StatusCode::CONTINUE {
+                        opener.open();
+                    }
+                    if let Some(ref user) = user {
+                        user.call(status, headers);
+                    }
+                }));
+                req.headers_mut()
+                    .insert(EXPECT, HeaderValue::from_static("100-continue"));
+                let body_gate = gate.clone();
+                req = req.map(|body| Body::wrap(gated(body, body_gate, timeout)));
+                Some(gate)
+            }
+            _ => None,
+        };
+        if let Some(informational) = informational {
+            informational.register(&mut req);
+        }
+        #[cfg(feature = "__rustls")]
+        if let Some(tls) = RequestConfig::<TlsVerification>::get(req.extensions()) {
+            let client = match self.tls_overrides {
+                Some(ref overrides) => overrides.client(tls),
+                None => Err(crate::error::builder(
+                    "per-request TLS settings are only supported by the rustls backend",
+                )),
+            };
+            return Box::pin(async move {
+                let res = match client {
+                    Ok(mut client) => client.call(req).await.map_err(crate::error::request),
+                    Err(err) => Err(err),
+                };
+                #[cfg(feature = "har")]
+                let res = crate::har::record(har, res);
+                let res = res?;
+                crate::metrics::response(res.extensions());
+                crate::connect::ConnectionUse::response(res.extensions());
+                Ok(reject_unsent(res, gate))
+            });
+        }
 
-    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.hyper.poll_ready(cx).map_err(crate::error::request)
+        let mut inner = self.hyper.client();
+        Box::pin(async move {
+            let res = inner.call(req).await.map_err(crate::error::request);
+            #[cfg(feature = "har")]
+            let res = crate::har::record(har, res);
+            let res = res?;
+            crate::metrics::response(res.extensions());
+            crate::connect::ConnectionUse::response(res.extensions());
+            Ok(reject_unsent(res, gate))
+        })
     }
+}
 
-    fn call(&mut self, req: hyper::Request<crate::async_impl::body::Body>) -> Self::Future {
-        let clone = self.hyper.clone();
-        let mut inner = std::mem::replace(&mut self.hyper, clone);
-        Box::pin(async move { inner.call(req).await.map_err(crate::error::request) })
+/// A final response received while the body was still held back means the
+/// body isn't wanted: it is dropped along with the response.
+fn reject_unsent<B>(mut res: http::Response<B>, gate: Option<Gate>) -> http::Response<B> {
+    if let Some(gate) = gate {
+        res.extensions_mut().insert(gate.reject());
     }
+    res
 }
 
+#[derive(Clone)]
 struct Config {
     // NOTE: When adding a new field, update `fmt::Debug for ClientBuilder`
     accepts: Accepts,
     headers: HeaderMap,
+    scoped_headers: ScopedHeaders,
     #[cfg(feature = "__tls")]
     hostname_verification: bool,
     #[cfg(feature = "__tls")]
     certs_verification: bool,
     #[cfg(feature = "__tls")]
     tls_sni: bool,
+    #[cfg(feature = "__tls")]
+    alpn_protocols: Option<Vec<String>>,
     connect_timeout: Option<Duration>,
     connection_verbose: bool,
     pool_idle_timeout: Option<Duration>,
     pool_max_idle_per_host: usize,
+    pool_invalidate_on_network_change: bool,
+    pool_retry_stale_connections: bool,
     tcp_keepalive: Option<Duration>,
     tcp_keepalive_interval: Option<Duration>,
     tcp_keepalive_retries: Option<u32>,
@@ -178,19 +557,44 @@ struct Config {
     tcp_user_timeout: Option<Duration>,
     #[cfg(any(feature = "__native-tls", feature = "__rustls"))]
     identity: Option<Identity>,
-    proxies: Vec<ProxyMatcher>,
+    #[cfg(feature = "__rustls")]
+    host_identities: Vec<(String, Identity)>,
+    proxies: Vec<Proxy>,
     auto_sys_proxy: bool,
     redirect_policy: redirect::Policy,
     retry_policy: crate::retry::Builder,
     referer: bool,
     read_timeout: Option<Duration>,
     timeout: Option<Duration>,
+    max_download_rate: Option<u64>,
+    max_upload_rate: Option<u64>,
+    max_requests_per_second: Option<u32>,
+    max_requests_per_second_per_host: Option<u32>,
+    max_response_size: Option<u64>,
+    body_buffer_pool: Option<BufferPool>,
+    #[cfg(feature = "har")]
+    har: Option<crate::har::Recorder>,
+    trace_context: Option<crate::trace_context::Propagation>,
+    request_id: Option<crate::request_id::Stamp>,
+    mock_transport: Option<MockTransport>,
     #[cfg(feature = "__tls")]
     root_certs: Vec<Certificate>,
     #[cfg(feature = "__tls")]
     tls_certs_only: bool,
     #[cfg(feature = "__rustls")]
     crls: Vec<CertificateRevocationList>,
+    #[cfg(feature = "__rustls")]
+    tls_verifier: Option<Arc<crate::tls::VerifyFn>>,
+    #[cfg(feature = "__rustls")]
+    tls_post_quantum: bool,
+    #[cfg(feature = "__rustls")]
+    tls_ocsp: tls::OcspPolicy,
+    #[cfg(feature = "__rustls")]
+    tls_session_resumption: bool,
+    #[cfg(feature = "__rustls")]
+    tls_session_tickets: bool,
+    #[cfg(feature = "__rustls")]
+    tls_session_cache: Option<tls::SessionCache>,
     #[cfg(feature = "__tls")]
     min_tls_version: Option<tls::Version>,
     #[cfg(feature = "__tls")]
@@ -200,13 +604,17 @@ struct Config {
     #[cfg(feature = "__tls")]
     tls: TlsBackend,
     connector_layers: Vec<BoxedConnectorLayer>,
+    layers: Vec<BoxedClientLayer>,
     http_version_pref: HttpVersionPref,
     http09_responses: bool,
     http1_title_case_headers: bool,
+    http1_header_case: HeaderCase,
     http1_allow_obsolete_multiline_headers_in_responses: bool,
     http1_ignore_invalid_headers_in_responses: bool,
     http1_allow_spaces_after_header_name_in_responses: bool,
     #[cfg(feature = "http2")]
+    http2_prior_knowledge_cleartext: bool,
+    #[cfg(feature = "http2")]
     http2_initial_stream_window_size: Option<u32>,
     #[cfg(feature = "http2")]
     http2_initial_connection_window_size: Option<u32>,
@@ -239,9 +647,14 @@ struct Config {
     nodelay: bool,
     #[cfg(feature = "cookies")]
     cookie_store: Option<Arc<dyn cookie::CookieStore>>,
+    // Set by `cookie_store(true)`: the jar is made when the client is
+    // built, so clients built from clones of a builder don't share it.
+    #[cfg(feature = "cookies")]
+    new_cookie_jar: bool,
     hickory_dns: bool,
-    error: Option<crate::Error>,
+    error: Option<ConfigError>,
     https_only: bool,
+    hsts: Option<crate::hsts::HstsConfig>,
     #[cfg(feature = "http3")]
     tls_enable_early_data: bool,
     #[cfg(feature = "http3")]
@@ -258,6 +671,8 @@ struct Config {
     h3_max_field_section_size: Option<u64>,
     #[cfg(feature = "http3")]
     h3_send_grease: Option<bool>,
+    #[cfg(feature = "http3")]
+    h3_alt_svc: bool,
     dns_overrides: HashMap<String, Vec<SocketAddr>>,
     dns_resolver: Option<Arc<dyn Resolve>>,
 
@@ -268,6 +683,29 @@ struct Config {
 
     #[cfg(feature = "negotiate")]
     negotiate_config: Option<crate::auth::NegotiateConfig>,
+    #[cfg(feature = "negotiate")]
+    negotiate_spn_include_port: bool,
+    #[cfg(feature = "negotiate")]
+    negotiate_ip_literal_policy: crate::negotiate::IpLiteralPolicy,
+    #[cfg(feature = "negotiate")]
+    negotiate_default_domain: Option<String>,
+    #[cfg(feature = "negotiate")]
+    negotiate_kerberos_only: bool,
+    #[cfg(feature = "negotiate")]
+    negotiate_domain_credentials: Vec<(String, crate::auth::Credentials)>,
+}
+
+/// An error from a builder method, returned by `build()` as the source of a
+/// builder error.
+///
+/// It is shared, rather than copied, by the clones of a builder.
+#[derive(Clone)]
+struct ConfigError(Arc<dyn std::error::Error + Send + Sync>);
+
+impl ConfigError {
+    fn new<E: Into<BoxError>>(err: E) -> ConfigError {
+        ConfigError(Arc::from(err.into()))
+    }
 }
 
 impl Default for ClientBuilder {
@@ -289,16 +727,21 @@ impl ClientBuilder {
                 error: None,
                 accepts: Accepts::default(),
                 headers,
+                scoped_headers: ScopedHeaders::default(),
                 #[cfg(feature = "__tls")]
                 hostname_verification: true,
                 #[cfg(feature = "__tls")]
                 certs_verification: true,
                 #[cfg(feature = "__tls")]
                 tls_sni: true,
+                #[cfg(feature = "__tls")]
+                alpn_protocols: None,
                 connect_timeout: None,
                 connection_verbose: false,
                 pool_idle_timeout: Some(Duration::from_secs(90)),
                 pool_max_idle_per_host: usize::MAX,
+                pool_invalidate_on_network_change: false,
+                pool_retry_stale_connections: true,
                 tcp_keepalive: Some(Duration::from_secs(15)),
                 tcp_keepalive_interval: Some(Duration::from_secs(15)),
                 tcp_keepalive_retries: Some(3),
@@ -311,6 +754,17 @@ impl ClientBuilder {
                 referer: true,
                 read_timeout: None,
                 timeout: None,
+                max_download_rate: None,
+                max_upload_rate: None,
+                max_requests_per_second: None,
+                max_requests_per_second_per_host: None,
+                max_response_size: None,
+                body_buffer_pool: None,
+                #[cfg(feature = "har")]
+                har: None,
+                trace_context: None,
+                request_id: None,
+                mock_transport: None,
                 #[cfg(feature = "__tls")]
                 root_certs: Vec::new(),
                 #[cfg(feature = "__tls")]
@@ -318,7 +772,21 @@ impl ClientBuilder {
                 #[cfg(any(feature = "__native-tls", feature = "__rustls"))]
                 identity: None,
                 #[cfg(feature = "__rustls")]
+                host_identities: Vec::new(),
+                #[cfg(feature = "__rustls")]
                 crls: vec![],
+                #[cfg(feature = "__rustls")]
+                tls_verifier: None,
+                #[cfg(feature = "__rustls")]
+                tls_post_quantum: false,
+                #[cfg(feature = "__rustls")]
+                tls_ocsp: tls::OcspPolicy::Off,
+                #[cfg(feature = "__rustls")]
+                tls_session_resumption: true,
+                #[cfg(feature = "__rustls")]
+                tls_session_tickets: true,
+                #[cfg(feature = "__rustls")]
+                tls_session_cache: None,
                 #[cfg(feature = "__tls")]
                 min_tls_version: None,
                 #[cfg(feature = "__tls")]
@@ -328,13 +796,17 @@ impl ClientBuilder {
                 #[cfg(feature = "__tls")]
                 tls: TlsBackend::default(),
                 connector_layers: Vec::new(),
+                layers: Vec::new(),
                 http_version_pref: HttpVersionPref::All,
                 http09_responses: false,
                 http1_title_case_headers: false,
+                http1_header_case: HeaderCase::default(),
                 http1_allow_obsolete_multiline_headers_in_responses: false,
                 http1_ignore_invalid_headers_in_responses: false,
                 http1_allow_spaces_after_header_name_in_responses: false,
                 #[cfg(feature = "http2")]
+                http2_prior_knowledge_cleartext: false,
+                #[cfg(feature = "http2")]
                 http2_initial_stream_window_size: None,
                 #[cfg(feature = "http2")]
                 http2_initial_connection_window_size: None,
@@ -368,7 +840,10 @@ impl ClientBuilder {
                 hickory_dns: cfg!(feature = "hickory-dns"),
                 #[cfg(feature = "cookies")]
                 cookie_store: None,
+                #[cfg(feature = "cookies")]
+                new_cookie_jar: false,
                 https_only: false,
+                hsts: None,
                 dns_overrides: HashMap::new(),
                 #[cfg(feature = "http3")]
                 tls_enable_early_data: false,
@@ -386,6 +861,8 @@ impl ClientBuilder {
                 h3_max_field_section_size: None,
                 #[cfg(feature = "http3")]
                 h3_send_grease: None,
+                #[cfg(feature = "http3")]
+                h3_alt_svc: false,
                 dns_resolver: None,
                 #[cfg(unix)]
                 unix_socket: None,
@@ -393,6 +870,16 @@ impl ClientBuilder {
                 windows_named_pipe: None,
                 #[cfg(feature = "negotiate")]
                 negotiate_config: None,
+                #[cfg(feature = "negotiate")]
+                negotiate_spn_include_port: false,
+                #[cfg(feature = "negotiate")]
+                negotiate_ip_literal_policy: Default::default(),
+                #[cfg(feature = "negotiate")]
+                negotiate_default_domain: None,
+                #[cfg(feature = "negotiate")]
+                negotiate_kerberos_only: false,
+                #[cfg(feature = "negotiate")]
+                negotiate_domain_credentials: Vec::new(),
             },
         }
     }
@@ -406,13 +893,20 @@ impl ClientBuilder {
     /// This method fails if a TLS backend cannot be initialized, or the resolver
     /// cannot load the system configuration.
     pub fn build(self) -> crate::Result<Client> {
+        #[cfg(feature = "cookies")]
+        let config = self.make_cookie_jar().config;
+        #[cfg(not(feature = "cookies"))]
         let config = self.config;
 
         if let Some(err) = config.error {
-            return Err(err);
+            return Err(crate::error::builder(err.0));
         }
 
-        let mut proxies = config.proxies;
+        let mut proxies: Vec<_> = config
+            .proxies
+            .into_iter()
+            .map(Proxy::into_matcher)
+            .collect();
         if config.auto_sys_proxy {
             proxies.push(ProxyMatcher::system());
         }
@@ -422,7 +916,7 @@ impl ClientBuilder {
         #[cfg(feature = "http3")]
         let mut h3_connector = None;
 
-        let resolver = {
+        let (resolver, dns_prefetch) = {
             let mut resolver: Arc<dyn Resolve> = match config.hickory_dns {
                 false => Arc::new(GaiResolver::new()),
                 #[cfg(feature = "hickory-dns")]
@@ -433,15 +927,21 @@ impl ClientBuilder {
             if let Some(dns_resolver) = config.dns_resolver {
                 resolver = dns_resolver;
             }
+            let dns_prefetch = Arc::new(Prefetched::new(resolver));
+            resolver = dns_prefetch.clone();
             if !config.dns_overrides.is_empty() {
                 resolver = Arc::new(DnsResolverWithOverrides::new(
                     resolver,
                     config.dns_overrides,
                 ));
             }
-            DynResolver::new(resolver)
+            (DynResolver::new(resolver), dns_prefetch)
         };
 
+        // Only rustls configs can be rebuilt for per-request TLS settings.
+        #[cfg(feature = "__rustls")]
+        let mut rustls_provider = None;
+
         let mut connector_builder = {
             #[cfg(feature = "__tls")]
             fn user_agent(headers: &HeaderMap) -> Option<HeaderValue> {
@@ -519,6 +1019,13 @@ impl ClientBuilder {
                     }
                 };
 
+            #[cfg(feature = "__tls")]
+            if let Some(ref protocols) = config.alpn_protocols {
+                if protocols.iter().any(|p| p.is_empty() || p.len() > 255) {
+                    return Err(crate::error::builder("invalid ALPN protocol"));
+                }
+            }
+
             #[cfg(feature = "__tls")]
             match config.tls {
                 #[cfg(feature = "__native-tls")]
@@ -543,12 +1050,63 @@ impl ClientBuilder {
                                 ]);
                             }
                         }
+
+                        if let Some(ref protocols) = config.alpn_protocols {
+                            let protocols =
+                                protocols.iter().map(|p| p.as_str()).collect::<Vec<_>>();
+                            tls.request_alpns(&protocols);
+                        }
+                    }
+
+                    #[cfg(not(all(feature = "__native-tls-alpn", not(feature = "http3"))))]
+                    if config.alpn_protocols.is_some() {
+                        return Err(crate::error::builder(
+                            "alpn_protocols() requires ALPN support in the native-tls backend",
+                        ));
                     }
 
                     tls.danger_accept_invalid_hostnames(!config.hostname_verification);
 
                     tls.danger_accept_invalid_certs(!config.certs_verification);
 
+                    #[cfg(feature = "__rustls")]
+                    if config.tls_verifier.is_some() {
+                        return Err(crate::error::builder(
+                            "tls_verifier() is only supported by the rustls backend",
+                        ));
+                    }
+
+                    #[cfg(feature = "__rustls")]
+                    if !config.tls_session_resumption
+                        || !config.tls_session_tickets
+                        || config.tls_session_cache.is_some()
+                    {
+                        return Err(crate::error::builder(
+                            "TLS session options are only supported by the rustls backend",
+                        ));
+                    }
+
+                    #[cfg(feature = "__rustls")]
+                    if config.tls_ocsp != tls::OcspPolicy::Off {
+                        return Err(crate::error::builder(
+                            "tls_ocsp() is only supported by the rustls backend",
+                        ));
+                    }
+
+                    #[cfg(feature = "__rustls")]
+                    if config.tls_post_quantum {
+                        return Err(crate::error::builder(
+                            "tls_post_quantum() is only supported by the rustls backend",
+                        ));
+                    }
+
+                    #[cfg(feature = "__rustls")]
+                    if !config.host_identities.is_empty() {
+                        return Err(crate::error::builder(
+                            "identity_for_host() is only supported by the rustls backend",
+                        ));
+                    }
+
                     tls.use_sni(config.tls_sni);
 
                     tls.disable_built_in_roots(config.tls_certs_only);
@@ -685,7 +1243,10 @@ impl ClientBuilder {
                 }
                 #[cfg(feature = "__rustls")]
                 TlsBackend::Rustls => {
-                    use crate::tls::{IgnoreHostname, NoVerifier};
+                    use crate::tls::{
+                        CustomVerifier, IgnoreHostname, NoVerifier, OcspPolicy, OcspVerifier,
+                    };
+                    use rustls::client::danger::ServerCertVerifier;
 
                     // Set TLS versions.
                     let mut versions = rustls::ALL_VERSIONS.to_vec();
@@ -714,11 +1275,29 @@ impl ClientBuilder {
                         return Err(crate::error::builder("empty supported tls versions"));
                     }
 
-                    // Allow user to have installed a runtime default.
-                    // If not, we ship with _our_ recommended default.
-                    let provider = rustls::crypto::CryptoProvider::get_default()
-                        .map(|arc| arc.clone())
-                        .unwrap_or_else(default_rustls_crypto_provider);
+                    let mut provider = tls::shared::provider();
+
+                    // Move the hybrid group to the front, so it's the one a
+                    // key share is sent for in the ClientHello.
+                    if config.tls_post_quantum {
+                        let mut kx_groups = provider.kx_groups.clone();
+                        let pq = kx_groups
+                            .iter()
+                            .position(|g| g.name() == rustls::NamedGroup::X25519MLKEM768)
+                            .ok_or_else(|| {
+                                crate::error::builder(
+                                    "TLS provider doesn't support post-quantum key exchange",
+                                )
+                            })?;
+                        let group = kx_groups.remove(pq);
+                        kx_groups.insert(0, group);
+                        provider = Arc::new(rustls::crypto::CryptoProvider {
+                            kx_groups,
+                            ..(*provider).clone()
+                        });
+                    }
+
+                    rustls_provider = Some(provider.clone());
 
                     // Build TLS config
                     let signature_algorithms = provider.signature_verification_algorithms;
@@ -727,10 +1306,71 @@ impl ClientBuilder {
                             .with_protocol_versions(&versions)
                             .map_err(|_| crate::error::builder("invalid TLS versions"))?;
 
+                    // Give the user's callback, if any, the final say.
+                    let tls_verifier = config.tls_verifier;
+                    let ocsp = if config.certs_verification {
+                        config.tls_ocsp
+                    } else {
+                        OcspPolicy::Off
+                    };
+                    let session_cache = config
+                        .tls_session_cache
+                        .filter(|_| config.tls_session_resumption);
+                    let verifier_key = crate::tls::VerifierKey {
+                        certs_verification: config.certs_verification,
+                        hostname_verification: config.hostname_verification,
+                        certs_only: config.tls_certs_only,
+                        root_certs: config
+                            .root_certs
+                            .iter()
+                            .map(|c| c.as_bytes().to_vec())
+                            .collect(),
+                        crls: config.crls.iter().map(|c| c.as_bytes().to_vec()).collect(),
+                        ocsp,
+                        callback: tls_verifier
+                            .as_ref()
+                            .map(|callback| Arc::as_ptr(callback) as *const () as usize),
+                        algorithms: signature_algorithms.all.as_ptr() as usize,
+                    };
+                    // Verifiers are shared with other clients before the OCSP
+                    // policy and callback are added.
+                    let shared_key = crate::tls::VerifierKey {
+                        ocsp: OcspPolicy::Off,
+                        callback: None,
+                        ..verifier_key.clone()
+                    };
+                    // Issuers of certificates the server doesn't send
+                    // can be among them.
+                    let ocsp_roots = match ocsp {
+                        OcspPolicy::Off => Vec::new(),
+                        _ => crate::tls::rustls_der(config.root_certs.clone())?,
+                    };
+                    let wrap = |verifier: Arc<dyn ServerCertVerifier>| {
+                        let verifier = match ocsp {
+                            OcspPolicy::Off => verifier,
+                            policy => Arc::new(OcspVerifier::new(
+                                verifier,
+                                policy,
+                                ocsp_roots,
+                                signature_algorithms,
+                            )) as Arc<_>,
+                        };
+                        let verifier = match tls_verifier {
+                            Some(ref callback) => {
+                                Arc::new(CustomVerifier::new(verifier, callback.clone())) as Arc<_>
+                            }
+                            None => verifier,
+                        };
+                        match session_cache {
+                            Some(ref cache) => cache.share_verifier(&verifier_key, verifier),
+                            None => verifier,
+                        }
+                    };
+
                     let config_builder = if !config.certs_verification {
                         config_builder
                             .dangerous()
-                            .with_custom_certificate_verifier(Arc::new(NoVerifier))
+                            .with_custom_certificate_verifier(wrap(Arc::new(NoVerifier)))
                     } else if !config.hostname_verification {
                         if !config.tls_certs_only {
                             // Should this just warn? Error for now...
@@ -739,12 +1379,15 @@ impl ClientBuilder {
                             ));
                         }
 
-                        config_builder
-                            .dangerous()
-                            .with_custom_certificate_verifier(Arc::new(IgnoreHostname::new(
+                        let verifier = tls::shared::verifier(&shared_key, || {
+                            Ok(Arc::new(IgnoreHostname::new(
                                 crate::tls::rustls_store(config.root_certs)?,
                                 signature_algorithms,
                             )))
+                        })?;
+                        config_builder
+                            .dangerous()
+                            .with_custom_certificate_verifier(wrap(verifier))
                     } else if !config.tls_certs_only {
                         // Check for some misconfigurations and report them.
                         if !config.crls.is_empty() {
@@ -753,40 +1396,48 @@ impl ClientBuilder {
                             ));
                         }
 
-                        let verifier = if config.root_certs.is_empty() {
-                            rustls_platform_verifier::Verifier::new(provider.clone())
-                                .map_err(crate::error::builder)?
-                        } else {
-                            #[cfg(any(
-                                all(unix, not(target_os = "android")),
-                                target_os = "windows"
-                            ))]
-                            {
-                                rustls_platform_verifier::Verifier::new_with_extra_roots(
-                                    crate::tls::rustls_der(config.root_certs)?,
-                                    provider.clone(),
-                                )
-                                .map_err(crate::error::builder)?
-                            }
-
-                            #[cfg(not(any(
-                                all(unix, not(target_os = "android")),
-                                target_os = "windows"
-                            )))]
-                            return Err(crate::error::builder(
-                                "rustls-platform-verifier could not load extra certs",
-                            ));
-                        };
+                        let verifier = tls::shared::verifier(&shared_key, || {
+                            let verifier = if config.root_certs.is_empty() {
+                                rustls_platform_verifier::Verifier::new(provider.clone())
+                                    .map_err(crate::error::builder)?
+                            } else {
+                                #[cfg(any(
+                                    all(unix, not(target_os = "android")),
+                                    target_os = "windows"
+                                ))]
+                                {
+                                    rustls_platform_verifier::Verifier::new_with_extra_roots(
+                                        crate::tls::rustls_der(config.root_certs)?,
+                                        provider.clone(),
+                                    )
+                                    .map_err(crate::error::builder)?
+                                }
+
+                                #[cfg(not(any(
+                                    all(unix, not(target_os = "android")),
+                                    target_os = "windows"
+                                )))]
+                                return Err(crate::error::builder(
+                                    "rustls-platform-verifier could not load extra certs",
+                                ));
+                            };
+                            Ok(Arc::new(verifier))
+                        })?;
 
                         config_builder
                             .dangerous()
-                            .with_custom_certificate_verifier(Arc::new(verifier))
+                            .with_custom_certificate_verifier(wrap(verifier))
+                    } else if config.root_certs.is_empty()
+                        && config.crls.is_empty()
+                        && tls_verifier.is_none()
+                        && ocsp == OcspPolicy::Off
+                        && session_cache.is_none()
+                    {
+                        // Nothing to parse, and nothing to share.
+                        config_builder
+                            .with_root_certificates(crate::tls::rustls_store(config.root_certs)?)
                     } else {
-                        if config.crls.is_empty() {
-                            config_builder.with_root_certificates(crate::tls::rustls_store(
-                                config.root_certs,
-                            )?)
-                        } else {
+                        let verifier = tls::shared::verifier(&shared_key, || {
                             let crls = config
                                 .crls
                                 .iter()
@@ -802,11 +1453,23 @@ impl ClientBuilder {
                                 .map_err(|_| {
                                     crate::error::builder("invalid TLS verification settings")
                                 })?;
-                            config_builder.with_webpki_verifier(verifier)
-                        }
+                            Ok(verifier)
+                        })?;
+                        config_builder
+                            .dangerous()
+                            .with_custom_certificate_verifier(wrap(verifier))
                     };
 
+                    // Per-host identities only differ in the client cert resolver,
+                    // everything else is shared with the default config.
+                    let mut host_resolvers = Vec::with_capacity(config.host_identities.len());
+                    for (host, id) in config.host_identities {
+                        let host_tls = id.add_to_rustls(config_builder.clone())?;
+                        host_resolvers.push((host, host_tls.client_auth_cert_resolver));
+                    }
+
                     // Finalize TLS config
+                    let client_auth = config.identity.is_some();
                     let mut tls = if let Some(id) = config.identity {
                         id.add_to_rustls(config_builder)?
                     } else {
@@ -815,6 +1478,25 @@ impl ClientBuilder {
 
                     tls.enable_sni = config.tls_sni;
 
+                    // Session resumption
+                    if !config.tls_session_resumption {
+                        tls.resumption = rustls::client::Resumption::disabled();
+                    } else {
+                        if let Some(cache) = session_cache {
+                            tls.resumption = rustls::client::Resumption::store(cache.as_rustls());
+                            if !client_auth {
+                                tls.client_auth_cert_resolver =
+                                    cache.share_no_client_auth(tls.client_auth_cert_resolver);
+                            }
+                        }
+                        if !config.tls_session_tickets {
+                            tls.resumption = tls
+                                .resumption
+                                .clone()
+                                .tls12_resumption(rustls::client::Tls12Resumption::SessionIdOnly);
+                        }
+                    }
+
                     // ALPN protocol
                     match config.http_version_pref {
                         HttpVersionPref::Http1 => {
@@ -836,6 +1518,10 @@ impl ClientBuilder {
                             ];
                         }
                     }
+                    if let Some(protocols) = config.alpn_protocols {
+                        tls.alpn_protocols =
+                            protocols.into_iter().map(String::into_bytes).collect();
+                    }
 
                     #[cfg(feature = "http3")]
                     {
@@ -860,7 +1546,16 @@ impl ClientBuilder {
                         )?;
                     }
 
-                    ConnectorBuilder::new_rustls_tls(
+                    let host_configs = host_resolvers
+                        .into_iter()
+                        .map(|(host, resolver)| {
+                            let mut host_tls = tls.clone();
+                            host_tls.client_auth_cert_resolver = resolver;
+                            (host, host_tls)
+                        })
+                        .collect();
+
+                    let mut connector = ConnectorBuilder::new_rustls_tls(
                         http,
                         tls,
                         proxies.clone(),
@@ -881,7 +1576,9 @@ impl ClientBuilder {
                         config.interface.as_deref(),
                         config.nodelay,
                         config.tls_info,
-                    )
+                    );
+                    connector.set_rustls_host_configs(host_configs);
+                    connector
                 }
                 #[cfg(any(feature = "__native-tls", feature = "__rustls",))]
                 TlsBackend::UnknownPreconfigured => {
@@ -915,6 +1612,14 @@ impl ClientBuilder {
 
         connector_builder.set_timeout(config.connect_timeout);
         connector_builder.set_verbose(config.connection_verbose);
+        let header_case = if config.http1_header_case.is_empty() {
+            None
+        } else {
+            Some(Arc::new(config.http1_header_case))
+        };
+        connector_builder.set_header_case(header_case.clone());
+        #[cfg(feature = "http2")]
+        connector_builder.set_h2c(config.http2_prior_knowledge_cleartext);
         connector_builder.set_keepalive(config.tcp_keepalive);
         connector_builder.set_keepalive_interval(config.tcp_keepalive_interval);
         connector_builder.set_keepalive_retries(config.tcp_keepalive_retries);
@@ -973,6 +1678,7 @@ impl ClientBuilder {
         builder.pool_timer(hyper_util::rt::TokioTimer::new());
         builder.pool_idle_timeout(config.pool_idle_timeout);
         builder.pool_max_idle_per_host(config.pool_max_idle_per_host);
+        builder.retry_canceled_requests(config.pool_retry_stale_connections);
 
         if config.http09_responses {
             builder.http09_responses(true);
@@ -1004,24 +1710,53 @@ impl ClientBuilder {
             Some(format!("{:?}", &config.redirect_policy))
         };
 
-        let hyper_client = builder.build(connector_builder.build(config.connector_layers));
+        #[cfg(feature = "__rustls")]
+        let tls_overrides = rustls_provider.map(|provider| {
+            Arc::new(TlsOverrides {
+                builder: builder.clone(),
+                connector: connector_builder.clone(),
+                layers: config.connector_layers.clone(),
+                provider,
+                clients: Default::default(),
+            })
+        });
+
+        let connector = connector_builder.build(config.connector_layers);
+        let hyper_pool = Arc::new(HyperPool {
+            client: std::sync::RwLock::new(builder.build(connector.clone())),
+            builder,
+            connector,
+        });
         let hyper_service = HyperService {
-            hyper: hyper_client,
+            hyper: hyper_pool.clone(),
+            retry_stale: config.pool_retry_stale_connections,
+            proxy_failover: proxies.iter().any(|p| p.has_fallbacks()),
+            #[cfg(feature = "__rustls")]
+            tls_overrides: tls_overrides.clone(),
         };
 
+        let scoped_headers = Arc::new(config.scoped_headers);
         let redirect_policy = {
             let mut p = TowerRedirectPolicy::new(config.redirect_policy);
             p.with_referer(config.referer)
-                .with_https_only(config.https_only);
+                .with_https_only(config.https_only)
+                .with_scoped_headers(scoped_headers.clone());
             p
         };
 
         let retry_policy = config.retry_policy.into_policy();
+        let rate_limit = RateLimit::new(
+            config.max_requests_per_second,
+            config.max_requests_per_second_per_host,
+        );
 
-        let svc = tower::retry::Retry::new(retry_policy.clone(), hyper_service);
+        let svc = RateLimited::new(hyper_service, rate_limit.clone());
+        let svc = tower::retry::Retry::new(retry_policy.clone(), svc);
 
         #[cfg(feature = "cookies")]
         let svc = CookieService::new(svc, config.cookie_store.clone());
+        let svc = HstsService::new(svc, config.hsts.clone());
+        let svc = AsyncPolicyService::new(svc);
         let hyper = FollowRedirect::with_policy(svc, redirect_policy.clone());
         #[cfg(any(
             feature = "gzip",
@@ -1045,58 +1780,157 @@ impl ClientBuilder {
         #[cfg(feature = "deflate")]
         let hyper = hyper.deflate(config.accepts.deflate);
 
+        let mock = match config.mock_transport {
+            Some(mock) => {
+                let svc = RateLimited::new(mock, rate_limit.clone());
+                let svc = tower::retry::Retry::new(retry_policy.clone(), svc);
+                #[cfg(feature = "cookies")]
+                let svc = CookieService::new(svc, config.cookie_store.clone());
+                let svc = HstsService::new(svc, config.hsts.clone());
+                let svc = AsyncPolicyService::new(svc);
+                let svc = FollowRedirect::with_policy(svc, redirect_policy.clone());
+                #[cfg(any(
+                    feature = "gzip",
+                    feature = "brotli",
+                    feature = "zstd",
+                    feature = "deflate"
+                ))]
+                let svc = Decompression::new(svc)
+                    .no_gzip()
+                    .no_deflate()
+                    .no_br()
+                    .no_zstd();
+                #[cfg(feature = "gzip")]
+                let svc = svc.gzip(config.accepts.gzip);
+                #[cfg(feature = "brotli")]
+                let svc = svc.br(config.accepts.brotli);
+                #[cfg(feature = "zstd")]
+                let svc = svc.zstd(config.accepts.zstd);
+                #[cfg(feature = "deflate")]
+                let svc = svc.deflate(config.accepts.deflate);
+                Some(svc)
+            }
+            None => None,
+        };
+
+        // Use match instead of map since config is partially moved,
+        // and it cannot be used in closure
+        #[cfg(feature = "http3")]
+        let alt_svc = (config.h3_alt_svc && h3_connector.is_some()).then(AltSvc::new);
+        #[cfg(feature = "http3")]
+        let mut h3_pool = None;
+        #[cfg(feature = "http3")]
+        let h3_client = match h3_connector {
+            Some(h3_connector) => {
+                let h3_service =
+                    H3Client::new(h3_connector, config.pool_idle_timeout, alt_svc.clone());
+                h3_pool = Some(h3_service.clone());
+                let svc = RateLimited::new(h3_service, rate_limit);
+                let svc = tower::retry::Retry::new(retry_policy, svc);
+                #[cfg(feature = "cookies")]
+                let svc = CookieService::new(svc, config.cookie_store.clone());
+                let svc = HstsService::new(svc, config.hsts.clone());
+                let svc = AsyncPolicyService::new(svc);
+                let svc = FollowRedirect::with_policy(svc, redirect_policy);
+                #[cfg(any(
+                    feature = "gzip",
+                    feature = "brotli",
+                    feature = "zstd",
+                    feature = "deflate"
+                ))]
+                let svc = Decompression::new(svc)
+                    // set everything to NO, in case tower-http has it enabled but
+                    // reqwest does not. then set to config value if cfg allows.
+                    .no_gzip()
+                    .no_deflate()
+                    .no_br()
+                    .no_zstd();
+                #[cfg(feature = "gzip")]
+                let svc = svc.gzip(config.accepts.gzip);
+                #[cfg(feature = "brotli")]
+                let svc = svc.br(config.accepts.brotli);
+                #[cfg(feature = "zstd")]
+                let svc = svc.zstd(config.accepts.zstd);
+                #[cfg(feature = "deflate")]
+                let svc = svc.deflate(config.accepts.deflate);
+                Some(svc)
+            }
+            None => None,
+        };
+
+        let layered = if config.layers.is_empty() {
+            None
+        } else {
+            let mut service = BoxCloneSyncService::new(Dispatch {
+                hyper: hyper.clone(),
+                mock: mock.clone(),
+                #[cfg(feature = "http3")]
+                h3_client: h3_client.clone(),
+            });
+            for layer in config.layers {
+                service = layer.layer(service);
+            }
+            Some(service)
+        };
+
         Ok(Client {
             inner: Arc::new(ClientRef {
                 accepts: config.accepts,
                 #[cfg(feature = "cookies")]
                 cookie_store: config.cookie_store.clone(),
-                // Use match instead of map since config is partially moved,
-                // and it cannot be used in closure
                 #[cfg(feature = "http3")]
-                h3_client: match h3_connector {
-                    Some(h3_connector) => {
-                        let h3_service = H3Client::new(h3_connector, config.pool_idle_timeout);
-                        let svc = tower::retry::Retry::new(retry_policy, h3_service);
-                        #[cfg(feature = "cookies")]
-                        let svc = CookieService::new(svc, config.cookie_store);
-                        let svc = FollowRedirect::with_policy(svc, redirect_policy);
-                        #[cfg(any(
-                            feature = "gzip",
-                            feature = "brotli",
-                            feature = "zstd",
-                            feature = "deflate"
-                        ))]
-                        let svc = Decompression::new(svc)
-                            // set everything to NO, in case tower-http has it enabled but
-                            // reqwest does not. then set to config value if cfg allows.
-                            .no_gzip()
-                            .no_deflate()
-                            .no_br()
-                            .no_zstd();
-                        #[cfg(feature = "gzip")]
-                        let svc = svc.gzip(config.accepts.gzip);
-                        #[cfg(feature = "brotli")]
-                        let svc = svc.br(config.accepts.brotli);
-                        #[cfg(feature = "zstd")]
-                        let svc = svc.zstd(config.accepts.zstd);
-                        #[cfg(feature = "deflate")]
-                        let svc = svc.deflate(config.accepts.deflate);
-                        Some(svc)
-                    }
-                    None => None,
+                h3_client,
+                #[cfg(feature = "http3")]
+                alt_svc,
+                pools: Pools {
+                    hyper: hyper_pool,
+                    #[cfg(feature = "__rustls")]
+                    tls_overrides,
+                    #[cfg(feature = "http3")]
+                    h3: h3_pool,
+                    network: config
+                        .pool_invalidate_on_network_change
+                        .then(NetworkWatch::new),
                 },
                 headers: config.headers,
+                scoped_headers,
                 referer: config.referer,
-                read_timeout: config.read_timeout,
+                read_timeout: RequestConfig::new(config.read_timeout),
                 total_timeout: RequestConfig::new(config.timeout),
+                download_rate: RequestConfig::new(config.max_download_rate.map(Throttle::new)),
+                upload_rate: RequestConfig::new(config.max_upload_rate.map(Throttle::new)),
+                max_response_size: config.max_response_size,
+                body_buffer_pool: config.body_buffer_pool,
+                #[cfg(feature = "har")]
+                har: RequestConfig::new(config.har),
+                trace_context: config.trace_context,
+                request_id: config.request_id,
                 hyper,
+                mock,
+                layered,
                 proxies,
                 proxies_maybe_http_auth,
                 proxies_maybe_http_custom_headers,
                 https_only: config.https_only,
+                hsts: config.hsts,
                 redirect_policy_desc,
+                header_case,
+                dns_prefetch,
                 #[cfg(feature = "negotiate")]
-                negotiate_config: config.negotiate_config,
+                negotiate_config: config
+                    .negotiate_config
+                    .or_else(|| {
+                        (!config.negotiate_domain_credentials.is_empty())
+                            .then(crate::auth::NegotiateConfig::current_user)
+                    })
+                    .map(|negotiate| crate::auth::NegotiateConfig {
+                        spn_include_port: config.negotiate_spn_include_port,
+                        ip_literal_policy: config.negotiate_ip_literal_policy,
+                        default_domain: config.negotiate_default_domain,
+                        kerberos_only: config.negotiate_kerberos_only,
+                        domain_credentials: config.negotiate_domain_credentials,
+                        ..negotiate
+                    }),
             }),
         })
     }
@@ -1133,7 +1967,7 @@ impl ClientBuilder {
                 self.config.headers.insert(USER_AGENT, value);
             }
             Err(e) => {
-                self.config.error = Some(crate::error::builder(e.into()));
+                self.config.error = Some(ConfigError::new::<http::Error>(e.into()));
             }
         };
         self
@@ -1168,29 +2002,69 @@ impl ClientBuilder {
         self
     }
 
-    /// Enable a persistent cookie store for the client.
+    /// Sets default headers for the requests sent to a host or URL prefix.
     ///
-    /// Cookies received in responses will be preserved and included in
-    /// additional requests.
+    /// The `scope` is either:
     ///
-    /// By default, no cookie store is used. Enabling the cookie store
-    /// with `cookie_store(true)` will set the store to a default implementation.
-    /// It is **not** necessary to call [cookie_store(true)](crate::ClientBuilder::cookie_store) if [cookie_provider(my_cookie_store)](crate::ClientBuilder::cookie_provider)
-    /// is used; calling [cookie_store(true)](crate::ClientBuilder::cookie_store) _after_ [cookie_provider(my_cookie_store)](crate::ClientBuilder::cookie_provider) will result
-    /// in the provided `my_cookie_store` being **overridden** with a default implementation.
+    /// - a host, such as `"internal.corp.com"`,
+    /// - a domain and its subdomains, with a leading dot, such as `".corp.com"`,
+    /// - or a URL prefix, such as `"https://api.example.com/v2/"`, which
+    ///   matches URLs with the same scheme, host and port, and a path under it.
     ///
-    /// # Optional
+    /// These headers take precedence over the ones of
+    /// [`default_headers()`](ClientBuilder::default_headers), and when scopes
+    /// overlap, the one set last wins. Headers set on a request are kept as
+    /// they are. A header from a scope is removed when a redirect leads out
+    /// of that scope.
     ///
-    /// This requires the optional `cookies` feature to be enabled.
-    #[cfg(feature = "cookies")]
+    /// # Errors
+    ///
+    /// Building the client fails if `scope` is empty or an invalid URL.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reqwest::header;
+    /// # fn doc() -> Result<(), reqwest::Error> {
+    /// let mut headers = header::HeaderMap::new();
+    /// let mut key = header::HeaderValue::from_static("secret");
+    /// key.set_sensitive(true);
+    /// headers.insert("x-api-key", key);
+    ///
+    /// // The key is only sent to the internal service.
+    /// let client = reqwest::Client::builder()
+    ///     .default_headers_for("internal.corp.com", headers)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn default_headers_for(mut self, scope: &str, headers: HeaderMap) -> ClientBuilder {
+        if let Err(e) = self.config.scoped_headers.insert(scope, headers) {
+            self.config.error = Some(ConfigError::new(e));
+        }
+        self
+    }
+
+    /// Enable a persistent cookie store for the client.
+    ///
+    /// Cookies received in responses will be preserved and included in
+    /// additional requests.
+    ///
+    /// By default, no cookie store is used. Enabling the cookie store
+    /// with `cookie_store(true)` will set the store to a default implementation.
+    /// It is **not** necessary to call [cookie_store(true)](crate::ClientBuilder::cookie_store) if [cookie_provider(my_cookie_store)](crate::ClientBuilder::cookie_provider)
+    /// is used; calling [cookie_store(true)](crate::ClientBuilder::cookie_store) _after_ [cookie_provider(my_cookie_store)](crate::ClientBuilder::cookie_provider) will result
+    /// in the provided `my_cookie_store` being **overridden** with a default implementation.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `cookies` feature to be enabled.
+    #[cfg(feature = "cookies")]
     #[cfg_attr(docsrs, doc(cfg(feature = "cookies")))]
     pub fn cookie_store(mut self, enable: bool) -> ClientBuilder {
-        if enable {
-            self.cookie_provider(Arc::new(cookie::Jar::default()))
-        } else {
-            self.config.cookie_store = None;
-            self
-        }
+        self.config.cookie_store = None;
+        self.config.new_cookie_jar = enable;
+        self
     }
 
     /// Set the persistent cookie store for the client.
@@ -1213,9 +2087,47 @@ impl ClientBuilder {
         cookie_store: Arc<C>,
     ) -> ClientBuilder {
         self.config.cookie_store = Some(cookie_store as _);
+        self.config.new_cookie_jar = false;
         self
     }
 
+    /// Makes the jar asked for with `cookie_store(true)`, if not made yet.
+    #[cfg(feature = "cookies")]
+    pub(crate) fn make_cookie_jar(self) -> ClientBuilder {
+        if self.config.new_cookie_jar {
+            self.cookie_provider(Arc::new(cookie::Jar::default()))
+        } else {
+            self
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn curl_defaults(&self) -> super::curl::Defaults {
+        super::curl::Defaults {
+            headers: self.config.headers.clone(),
+            scoped_headers: Arc::new(self.config.scoped_headers.clone()),
+            header_case: if self.config.http1_header_case.is_empty() {
+                None
+            } else {
+                Some(Arc::new(self.config.http1_header_case.clone()))
+            },
+            #[cfg(feature = "cookies")]
+            cookie_store: self.config.cookie_store.clone(),
+            #[cfg(feature = "negotiate")]
+            negotiate: self.config.negotiate_config.clone(),
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn trace_context_ref(&self) -> Option<crate::trace_context::Propagation> {
+        self.config.trace_context.clone()
+    }
+
+    #[cfg(all(feature = "cookies", feature = "blocking"))]
+    pub(crate) fn cookie_provider_ref(&self) -> Option<Arc<dyn cookie::CookieStore>> {
+        self.config.cookie_store.clone()
+    }
+
     /// Enable auto gzip decompression by checking the `Content-Encoding` response header.
     ///
     /// If auto gzip decompression is turned on:
@@ -1413,7 +2325,7 @@ impl ClientBuilder {
     ///
     /// Adding a proxy will disable the automatic usage of the "system" proxy.
     pub fn proxy(mut self, proxy: Proxy) -> ClientBuilder {
-        self.config.proxies.push(proxy.into_matcher());
+        self.config.proxies.push(proxy);
         self.config.auto_sys_proxy = false;
         self
     }
@@ -1450,6 +2362,9 @@ impl ClientBuilder {
     /// successful read. This is more appropriate for detecting stalled
     /// connections when the size isn't known beforehand.
     ///
+    /// It can be overridden for a single request with
+    /// `RequestBuilder::read_timeout()`.
+    ///
     /// Default is no timeout.
     pub fn read_timeout(mut self, timeout: Duration) -> ClientBuilder {
         self.config.read_timeout = Some(timeout);
@@ -1480,6 +2395,275 @@ impl ClientBuilder {
         self
     }
 
+    /// Add the trace context of the caller to every request sent, so that
+    /// distributed traces include them.
+    ///
+    /// See the [`trace_context`](crate::trace_context) module for the
+    /// headers added, and how the context is found.
+    pub fn trace_context(
+        mut self,
+        propagation: crate::trace_context::Propagation,
+    ) -> ClientBuilder {
+        self.config.trace_context = Some(propagation);
+        self
+    }
+
+    /// Stamp every request sent with an ID in the header `name`, made by
+    /// `generate`, to correlate it across services.
+    ///
+    /// The ID is in the extensions of the response, as a
+    /// [`RequestId`](crate::request_id::RequestId). See the
+    /// [`request_id`](crate::request_id) module for how retries are handled.
+    pub fn request_id<F>(mut self, name: HeaderName, generate: F) -> ClientBuilder
+    where
+        F: Fn() -> HeaderValue + Send + Sync + 'static,
+    {
+        self.config.request_id = Some(crate::request_id::Stamp::new(name, generate));
+        self
+    }
+
+    /// Record every request sent and response received into a HAR
+    /// [`Recorder`](crate::har::Recorder).
+    ///
+    /// It can be overridden for a single request with
+    /// [`RequestBuilder::har()`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `har` feature to be enabled.
+    ///
+    /// [`RequestBuilder::har()`]: crate::RequestBuilder::har
+    #[cfg(feature = "har")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "har")))]
+    pub fn har(mut self, recorder: crate::har::Recorder) -> ClientBuilder {
+        self.config.har = Some(recorder);
+        self
+    }
+
+    /// Cache responses, and reuse them as allowed by HTTP caching rules.
+    ///
+    /// The cache is a layer, like those added with [`ClientBuilder::layer()`]:
+    /// it sees decoded response bodies, and wraps the layers added before
+    /// it. See the [`cache`](crate::cache) module for what is cached.
+    pub fn http_cache(mut self, config: crate::cache::CacheConfig) -> ClientBuilder {
+        let layer = BoxCloneSyncServiceLayer::new(crate::cache::CacheLayer(config));
+        self.config.layers.push(layer);
+        self
+    }
+
+    /// Enforce HTTP Strict Transport Security, sending the `http` requests
+    /// to the hosts known to require it over `https` instead.
+    ///
+    /// See the [`hsts`](crate::hsts) module for how hosts become known.
+    pub fn hsts(mut self, config: crate::hsts::HstsConfig) -> ClientBuilder {
+        self.config.hsts = Some(config);
+        self
+    }
+
+    /// Record requests and responses into a [`Cassette`], or answer requests
+    /// from it.
+    ///
+    /// The cassette is a layer, like those added with
+    /// [`ClientBuilder::layer()`]: it wraps the layers added before it. See
+    /// the [`vcr`](crate::vcr) module for how requests are matched.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `vcr` feature to be enabled.
+    ///
+    /// [`Cassette`]: crate::vcr::Cassette
+    #[cfg(feature = "vcr")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "vcr")))]
+    pub fn cassette(mut self, cassette: crate::vcr::Cassette) -> ClientBuilder {
+        let layer = BoxCloneSyncServiceLayer::new(crate::vcr::CassetteLayer(cassette));
+        self.config.layers.push(layer);
+        self
+    }
+
+    /// Answer requests with `handler`, in-process, instead of sending them
+    /// over the network.
+    ///
+    /// This is meant for tests. The handler takes the place of the
+    /// connection: it sees each attempt as it would be sent, with its body
+    /// read, and everything above the connection still happens, such as
+    /// redirects, cookies, retries, authentication and decompression. An
+    /// error returned by the handler fails the attempt as a connection
+    /// error would.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), reqwest::Error> {
+    /// let client = reqwest::Client::builder()
+    ///     .mock_transport(|req| {
+    ///         assert_eq!(req.uri(), "http://api.example/health");
+    ///         Ok(http::Response::new("ok".into()))
+    ///     })
+    ///     .build()?;
+    ///
+    /// let body = client
+    ///     .get("http://api.example/health")
+    ///     .send()
+    ///     .await?
+    ///     .text()
+    ///     .await?;
+    /// assert_eq!(body, "ok");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mock_transport<F>(mut self, handler: F) -> ClientBuilder
+    where
+        F: Fn(http::Request<Bytes>) -> Result<http::Response<Bytes>, BoxError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.config.mock_transport = Some(MockTransport::new(handler));
+        self
+    }
+
+    // Bandwidth options
+
+    /// Limit the rate at which response bodies are read, in bytes per second.
+    ///
+    /// The limit is shared by all requests made with the `Client`, so
+    /// concurrent downloads together stay under it. It can be overridden for
+    /// a single request with [`RequestBuilder::max_download_rate()`].
+    ///
+    /// The rate is measured on the decoded body, after any decompression.
+    ///
+    /// Default is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_sec` is zero.
+    ///
+    /// [`RequestBuilder::max_download_rate()`]: crate::RequestBuilder::max_download_rate
+    pub fn max_download_rate(mut self, bytes_per_sec: u64) -> ClientBuilder {
+        assert!(
+            bytes_per_sec > 0,
+            "max_download_rate must be greater than zero"
+        );
+        self.config.max_download_rate = Some(bytes_per_sec);
+        self
+    }
+
+    /// Limit the rate at which request bodies are sent, in bytes per second.
+    ///
+    /// The limit is shared by all requests made with the `Client`, so
+    /// concurrent uploads together stay under it. It can be overridden for
+    /// a single request with [`RequestBuilder::max_upload_rate()`].
+    ///
+    /// Default is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_sec` is zero.
+    ///
+    /// [`RequestBuilder::max_upload_rate()`]: crate::RequestBuilder::max_upload_rate
+    pub fn max_upload_rate(mut self, bytes_per_sec: u64) -> ClientBuilder {
+        assert!(
+            bytes_per_sec > 0,
+            "max_upload_rate must be greater than zero"
+        );
+        self.config.max_upload_rate = Some(bytes_per_sec);
+        self
+    }
+
+    /// Limit the rate at which requests are sent, in requests per second.
+    ///
+    /// The limit is shared by all requests made with the `Client`. Requests
+    /// made faster than that wait for their turn, in the order they were
+    /// made, before being sent; the wait counts towards their `timeout`.
+    /// Every request sent counts, including retries and redirects followed.
+    ///
+    /// Up to a second's worth of requests may be sent at once, after the
+    /// client has been idle for a while.
+    ///
+    /// Default is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` is zero.
+    pub fn max_requests_per_second(mut self, requests: u32) -> ClientBuilder {
+        assert!(
+            requests > 0,
+            "max_requests_per_second must be greater than zero"
+        );
+        self.config.max_requests_per_second = Some(requests);
+        self
+    }
+
+    /// Limit the rate at which requests are sent to each host, in requests
+    /// per second.
+    ///
+    /// Like [`ClientBuilder::max_requests_per_second()`], but each host, or
+    /// rather each host and port, has a limit of its own. Both limits apply
+    /// when both are set.
+    ///
+    /// Default is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` is zero.
+    pub fn max_requests_per_second_per_host(mut self, requests: u32) -> ClientBuilder {
+        assert!(
+            requests > 0,
+            "max_requests_per_second_per_host must be greater than zero"
+        );
+        self.config.max_requests_per_second_per_host = Some(requests);
+        self
+    }
+
+    /// Set the maximum size of a response body, in bytes.
+    ///
+    /// Reading a body larger than this, whether through `bytes()`, `text()`,
+    /// `json()` or by streaming chunks, fails with an error for which
+    /// [`Error::is_body_too_large()`] returns `true`. If the response declares
+    /// a larger `Content-Length`, the error is returned before any of the body
+    /// is read.
+    ///
+    /// The size is measured after any decompression.
+    ///
+    /// Default is no limit.
+    ///
+    /// [`Error::is_body_too_large()`]: crate::Error::is_body_too_large
+    pub fn max_response_size(mut self, bytes: u64) -> ClientBuilder {
+        self.config.max_response_size = Some(bytes);
+        self
+    }
+
+    /// Reuse the buffers that response bodies are read into.
+    ///
+    /// A body that arrives in more than one chunk is copied into a single
+    /// buffer by `bytes()`, `text()` and `json()`. With this set, that buffer
+    /// comes from a pool shared by the client's responses, starting with
+    /// room for `initial_size` bytes. Buffers that grew past `max_size` are
+    /// dropped instead of going back to the pool.
+    ///
+    /// The `Bytes` returned share the buffer, so it is only reused once they
+    /// are dropped, and a small body keeps the whole buffer alive until
+    /// then. This suits clients reading many bodies and dropping them soon
+    /// after, such as a service parsing JSON responses.
+    ///
+    /// Bodies that arrive in one chunk are returned without a copy, with or
+    /// without a pool. Response headers are parsed in the read buffer of
+    /// their connection, which is reused for every response on it.
+    ///
+    /// Default is no pool.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial_size` is larger than `max_size`.
+    pub fn body_buffer_pool(mut self, initial_size: usize, max_size: usize) -> ClientBuilder {
+        assert!(
+            initial_size <= max_size,
+            "body_buffer_pool initial_size must not be larger than max_size"
+        );
+        self.config.body_buffer_pool = Some(BufferPool::new(initial_size, max_size));
+        self
+    }
+
     // HTTP options
 
     /// Set an optional timeout for idle sockets being kept-alive.
@@ -1503,12 +2687,80 @@ impl ClientBuilder {
         self
     }
 
+    /// Drop the idle connections of the pool when the network changes.
+    ///
+    /// Before sending a request, at most once a second, the client asks the
+    /// system which local addresses it would reach the internet from. When
+    /// they change, such as when moving to another network or when a VPN
+    /// comes up or goes down, the pooled connections are likely dead, or
+    /// going the wrong way, so they are dropped as with
+    /// [`Client::invalidate_pool`].
+    ///
+    /// Default is `false`.
+    pub fn pool_invalidate_on_network_change(mut self, enabled: bool) -> ClientBuilder {
+        self.config.pool_invalidate_on_network_change = enabled;
+        self
+    }
+
+    /// Send idempotent requests again when their pooled connection turns
+    /// out to be closed.
+    ///
+    /// A server may close an idle connection just as a request is sent over
+    /// it, which then fails with "connection closed before message
+    /// completed" or a reset, although the server never saw the request.
+    /// When this happens on a connection that already carried a response,
+    /// requests that are idempotent, and whose body can be sent again, are
+    /// sent again, once. This is on top of the
+    /// [`retry`](ClientBuilder::retry) policy.
+    ///
+    /// Default is `true`.
+    pub fn pool_retry_stale_connections(mut self, enabled: bool) -> ClientBuilder {
+        self.config.pool_retry_stale_connections = enabled;
+        self
+    }
+
     /// Send headers as title case instead of lowercase.
     pub fn http1_title_case_headers(mut self) -> ClientBuilder {
         self.config.http1_title_case_headers = true;
         self
     }
 
+    /// Send the given header names with exactly this casing on HTTP/1.
+    ///
+    /// Header names are otherwise sent in lowercase, which some servers that
+    /// match header names case-sensitively refuse. Any header with one of
+    /// these names, whether set on the request or by the client, is written
+    /// as spelled here. The casing also shows in
+    /// [`RequestBuilder::to_curl_command`](crate::RequestBuilder::to_curl_command)
+    /// and in the [`connection_verbose`](ClientBuilder::connection_verbose)
+    /// logs.
+    ///
+    /// This has no effect on HTTP/2, where header names are always lowercase.
+    /// Calling this again adds to the names already set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn doc() -> Result<(), reqwest::Error> {
+    /// let client = reqwest::Client::builder()
+    ///     .http1_header_case(["SOAPAction", "X-API-Key"])
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn http1_header_case<I>(mut self, names: I) -> ClientBuilder
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        for name in names {
+            if let Err(e) = self.config.http1_header_case.insert(name.as_ref()) {
+                self.config.error = Some(ConfigError::new(e));
+            }
+        }
+        self
+    }
+
     /// Set whether HTTP/1 connections will accept obsolete line folding for
     /// header values.
     ///
@@ -1563,6 +2815,23 @@ impl ClientBuilder {
         self
     }
 
+    /// Use HTTP/2 with prior knowledge for `http://` URLs only.
+    ///
+    /// Requests to `http://` URLs are sent over cleartext HTTP/2 (h2c),
+    /// without first trying HTTP/1.1, which suits internal services and
+    /// sidecars that speak HTTP/2 but don't terminate TLS. Requests to
+    /// `https://` URLs still negotiate their version with ALPN, and requests
+    /// sent through a plain HTTP proxy still use HTTP/1.1.
+    ///
+    /// Upgrading an HTTP/1.1 connection to h2c with `Upgrade: h2c` isn't
+    /// supported; RFC 9113 deprecates it.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_prior_knowledge_cleartext(mut self) -> ClientBuilder {
+        self.config.http2_prior_knowledge_cleartext = true;
+        self
+    }
+
     /// Only use HTTP/3.
     #[cfg(feature = "http3")]
     #[cfg_attr(docsrs, doc(cfg(all(reqwest_unstable, feature = "http3",))))]
@@ -1596,8 +2865,14 @@ impl ClientBuilder {
 
     /// Sets whether to use an adaptive flow control.
     ///
+    /// The window sizes are grown to match the bandwidth-delay product of the
+    /// connection, measured with PING frames, which helps throughput on
+    /// high-latency links without tuning the sizes by hand.
+    ///
     /// Enabling this will override the limits set in `http2_initial_stream_window_size` and
     /// `http2_initial_connection_window_size`.
+    ///
+    /// Default is `false`.
     #[cfg(feature = "http2")]
     #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
     pub fn http2_adaptive_window(mut self, enabled: bool) -> ClientBuilder {
@@ -1949,6 +3224,44 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the identity to be used for client certificate authentication
+    /// with a specific host.
+    ///
+    /// Connections to `host` present this identity instead of the one set by
+    /// [`ClientBuilder::identity()`]. Hosts are matched case-insensitively
+    /// against the host of the request URL, without the port. Can be called
+    /// multiple times to register identities for several hosts.
+    ///
+    /// This does not apply to HTTP/3 connections.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn doc(billing: reqwest::Identity, reports: reqwest::Identity) -> reqwest::Result<()> {
+    /// let client = reqwest::Client::builder()
+    ///     .identity_for_host("billing.internal", billing)
+    ///     .identity_for_host("reports.internal", reports)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn identity_for_host(mut self, host: &str, identity: Identity) -> ClientBuilder {
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_ascii_lowercase();
+        self.config.host_identities.retain(|(h, _)| *h != host);
+        self.config.host_identities.push((host, identity));
+        self
+    }
+
     /// Controls the use of hostname verification.
     ///
     /// Defaults to `false`.
@@ -2020,34 +3333,241 @@ impl ClientBuilder {
         self.tls_danger_accept_invalid_certs(accept_invalid_certs)
     }
 
-    /// Controls the use of TLS server name indication.
+    /// Sets a callback that decides whether a server certificate is accepted.
     ///
-    /// Defaults to `true`.
+    /// The callback runs after the built-in verification, and is passed the
+    /// certificate chain presented by the server along with the server name
+    /// being connected to. Returning `Ok(())` accepts the certificate and
+    /// returning an error rejects it, whatever the built-in verification
+    /// decided. [`CertChain::verification_error()`] tells whether the
+    /// built-in verification passed, so the callback can add extra checks
+    /// on top of it or overrule it.
+    ///
+    /// # Warning
+    ///
+    /// Accepting a chain the built-in verification rejected has the same
+    /// risks as [`ClientBuilder::tls_danger_accept_invalid_certs()`]. Only do
+    /// so after checking the chain some other way.
     ///
     /// # Optional
     ///
-    /// This requires the optional `default-tls`, `native-tls`, or `rustls(-...)`
-    /// feature to be enabled.
-    #[cfg(feature = "__tls")]
-    #[cfg_attr(
-        docsrs,
-        doc(cfg(any(feature = "default-tls", feature = "native-tls", feature = "rustls")))
-    )]
-    pub fn tls_sni(mut self, tls_sni: bool) -> ClientBuilder {
-        self.config.tls_sni = tls_sni;
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    ///
+    /// [`CertChain::verification_error()`]: crate::tls::CertChain::verification_error
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_verifier<F>(mut self, verifier: F) -> ClientBuilder
+    where
+        F: Fn(
+                &crate::tls::CertChain<'_>,
+                &str,
+            ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.config.tls_verifier = Some(Arc::new(verifier));
         self
     }
 
-    /// Set the minimum required TLS version for connections.
+    /// Controls the resumption of TLS sessions.
     ///
-    /// By default, the TLS backend's own default is used.
+    /// Resuming a session from an earlier connection to the same server
+    /// saves a full handshake. Disabling it forces every connection to do a
+    /// full handshake, which some compliance regimes require.
     ///
-    /// # Errors
+    /// Defaults to `true`.
     ///
-    /// A value of `tls::Version::TLS_1_3` will cause an error with the
-    /// `native-tls` backend. This does not mean the version
-    /// isn't supported, just that it can't be set as a minimum due to
-    /// technical limitations.
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_session_resumption(mut self, enabled: bool) -> ClientBuilder {
+        self.config.tls_session_resumption = enabled;
+        self
+    }
+
+    /// Controls the use of session tickets to resume TLS 1.2 sessions.
+    ///
+    /// When disabled, TLS 1.2 sessions are only resumed using session IDs,
+    /// which keeps session state on the server. TLS 1.3 always resumes
+    /// sessions with tickets, use [`ClientBuilder::tls_session_resumption()`]
+    /// to turn that off.
+    ///
+    /// Defaults to `true`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_session_tickets(mut self, enabled: bool) -> ClientBuilder {
+        self.config.tls_session_tickets = enabled;
+        self
+    }
+
+    /// Sets the cache TLS sessions are stored in for resumption.
+    ///
+    /// By default, each client has its own in-memory cache of 256 sessions.
+    /// Passing the same [`tls::SessionCache`] to several clients lets them
+    /// resume each other's sessions.
+    /// That only works between clients with the same certificate
+    /// verification settings, and without a client certificate.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn doc() -> reqwest::Result<()> {
+    /// let cache = reqwest::tls::SessionCache::new(1024);
+    /// let a = reqwest::Client::builder()
+    ///     .tls_session_cache(cache.clone())
+    ///     .build()?;
+    /// let b = reqwest::Client::builder()
+    ///     .tls_session_cache(cache)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_session_cache(mut self, cache: tls::SessionCache) -> ClientBuilder {
+        self.config.tls_session_cache = Some(cache);
+        self
+    }
+
+    /// Sets how server certificates are checked for revocation using OCSP.
+    ///
+    /// Only OCSP responses stapled by the server during the handshake are
+    /// checked; reqwest doesn't contact OCSP responders itself. The issuer
+    /// that signed a response must be sent by the server or added with
+    /// [`add_root_certificate()`](ClientBuilder::add_root_certificate). See
+    /// [`tls::OcspPolicy`] for the available policies.
+    ///
+    /// Defaults to [`tls::OcspPolicy::Off`]. Has no effect if certificate
+    /// verification is disabled.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_ocsp(mut self, policy: tls::OcspPolicy) -> ClientBuilder {
+        self.config.tls_ocsp = policy;
+        self
+    }
+
+    /// Prefer post-quantum hybrid key exchange for TLS connections.
+    ///
+    /// When enabled, the X25519MLKEM768 hybrid group (X25519 combined with
+    /// ML-KEM) is offered first, so handshakes with servers supporting it
+    /// are protected against "harvest now, decrypt later" attacks. Servers
+    /// that don't support it fall back to the classical groups.
+    ///
+    /// Defaults to `false`, where the hybrid group is only used if the
+    /// server asks for it.
+    ///
+    /// # Errors
+    ///
+    /// Building the client fails if the TLS crypto provider in use doesn't
+    /// implement X25519MLKEM768.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_post_quantum(mut self, enabled: bool) -> ClientBuilder {
+        self.config.tls_post_quantum = enabled;
+        self
+    }
+
+    /// Controls the use of TLS server name indication.
+    ///
+    /// Defaults to `true`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls(-...)`
+    /// feature to be enabled.
+    #[cfg(feature = "__tls")]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "default-tls", feature = "native-tls", feature = "rustls")))
+    )]
+    pub fn tls_sni(mut self, tls_sni: bool) -> ClientBuilder {
+        self.config.tls_sni = tls_sni;
+        self
+    }
+
+    /// Sets the protocols offered with ALPN during the TLS handshake, in
+    /// order of preference.
+    ///
+    /// By default, the protocols are picked from the enabled HTTP versions.
+    /// Offering only `http/1.1` keeps servers from picking HTTP/2, and custom
+    /// protocols can be negotiated for use with [`Response::upgrade()`].
+    /// A connection is only used for HTTP/2 if the server picks `h2`, it
+    /// speaks HTTP/1 otherwise.
+    ///
+    /// HTTP/3 connections always use `h3`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn doc() -> reqwest::Result<()> {
+    /// let client = reqwest::Client::builder()
+    ///     .alpn_protocols(["http/1.1", "custom/1"])
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `default-tls`, `native-tls`, or `rustls(-...)`
+    /// feature to be enabled. It isn't supported by the `native-tls-no-alpn`
+    /// features.
+    ///
+    /// [`Response::upgrade()`]: crate::Response::upgrade
+    #[cfg(feature = "__tls")]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "default-tls", feature = "native-tls", feature = "rustls")))
+    )]
+    pub fn alpn_protocols<I>(mut self, protocols: I) -> ClientBuilder
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.config.alpn_protocols = Some(
+            protocols
+                .into_iter()
+                .map(|p| p.as_ref().to_owned())
+                .collect(),
+        );
+        self
+    }
+
+    /// Set the minimum required TLS version for connections.
+    ///
+    /// By default, the TLS backend's own default is used.
+    ///
+    /// # Errors
+    ///
+    /// A value of `tls::Version::TLS_1_3` will cause an error with the
+    /// `native-tls` backend. This does not mean the version
+    /// isn't supported, just that it can't be set as a minimum due to
+    /// technical limitations.
     ///
     /// # Optional
     ///
@@ -2209,6 +3729,10 @@ impl ClientBuilder {
 
     /// Add TLS information as `TlsInfo` extension to responses.
     ///
+    /// The [`tls::TlsInfo`] describes the connection the response was
+    /// received on: the negotiated version, cipher suite and ALPN protocol,
+    /// and the certificates the server presented.
+    ///
     /// # Optional
     ///
     /// This requires the optional `default-tls`, `native-tls`, or `rustls(-...)`
@@ -2416,6 +3940,164 @@ impl ClientBuilder {
         self
     }
 
+    /// Send requests over HTTP/3 to the origins advertising it with an
+    /// `Alt-Svc` header, for as long as the advertisement lasts.
+    ///
+    /// Only requests with an idempotent method and a body that can be
+    /// cloned, not sent through a proxy, are upgraded. When the advertised
+    /// endpoint can't be used, the request is sent again over the usual
+    /// connection, and the endpoint is left alone for a few minutes.
+    ///
+    /// Default is `false`.
+    #[cfg(feature = "http3")]
+    #[cfg_attr(docsrs, doc(cfg(all(reqwest_unstable, feature = "http3",))))]
+    pub fn http3_alt_svc(mut self, enabled: bool) -> ClientBuilder {
+        self.config.h3_alt_svc = enabled;
+        self
+    }
+
+    /// Adds a new Tower [`Layer`](https://docs.rs/tower/latest/tower/trait.Layer.html) around
+    /// the [`Service`](https://docs.rs/tower/latest/tower/trait.Service.html) which sends the
+    /// requests of this client.
+    ///
+    /// The layers see each request once, as passed to [`Client::execute`] with the default
+    /// headers added, and the final response, after redirects have been followed, retries
+    /// made and the body decompressed. They can change either, or answer a request
+    /// themselves without calling the inner service, for instance from a cache. The
+    /// `timeout` and `read_timeout` of the client still apply around them.
+    ///
+    /// Each subsequent invocation of this function will wrap previous layers.
+    ///
+    /// Example usage:
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// # #[cfg(not(feature = "rustls-no-provider"))]
+    /// let client = reqwest::Client::builder()
+    ///     .layer(tower::limit::concurrency::ConcurrencyLimitLayer::new(16))
+    ///     .layer(tower::timeout::TimeoutLayer::new(Duration::from_secs(30)))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn layer<L>(mut self, layer: L) -> ClientBuilder
+    where
+        L: Layer<BoxedClientService> + Clone + Send + Sync + 'static,
+        L::Service: Service<http::Request<Body>, Response = http::Response<Body>, Error = BoxError>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<http::Request<Body>>>::Future: Send + 'static,
+    {
+        let layer = BoxCloneSyncServiceLayer::new(layer);
+
+        self.config.layers.push(layer);
+
+        self
+    }
+
+    /// Call `hook` with each request before it is sent.
+    ///
+    /// The hook can change the request, for instance to add a header, and
+    /// returns [`ControlFlow::Continue`] to send it, or answer the request
+    /// itself with [`ControlFlow::Break`], for instance from a cache. It sees
+    /// requests and responses the way the layers added with
+    /// [`ClientBuilder::layer`] do, and runs in the order of those layers and
+    /// the other hooks: the last one added sees a request first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::ops::ControlFlow;
+    ///
+    /// # #[cfg(not(feature = "rustls-no-provider"))]
+    /// let client = reqwest::Client::builder()
+    ///     .on_request(|req| {
+    ///         req.headers_mut().insert("x-request-id", "42".parse().unwrap());
+    ///         ControlFlow::Continue(())
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`ControlFlow::Continue`]: std::ops::ControlFlow::Continue
+    /// [`ControlFlow::Break`]: std::ops::ControlFlow::Break
+    pub fn on_request<F>(self, hook: F) -> ClientBuilder
+    where
+        F: Fn(&mut http::Request<Body>) -> ControlFlow<http::Response<Body>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.on_request_async(move |mut req| {
+            let flow = match hook(&mut req) {
+                ControlFlow::Continue(()) => ControlFlow::Continue(req),
+                ControlFlow::Break(res) => ControlFlow::Break(res),
+            };
+            std::future::ready(flow)
+        })
+    }
+
+    /// Call an asynchronous `hook` with each request before it is sent.
+    ///
+    /// Like [`ClientBuilder::on_request`], but the hook takes the request and
+    /// resolves to [`ControlFlow::Continue`] with the request to send, or to
+    /// [`ControlFlow::Break`] with a response to answer it with.
+    ///
+    /// [`ControlFlow::Continue`]: std::ops::ControlFlow::Continue
+    /// [`ControlFlow::Break`]: std::ops::ControlFlow::Break
+    pub fn on_request_async<F, Fut>(mut self, hook: F) -> ClientBuilder
+    where
+        F: Fn(http::Request<Body>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ControlFlow<http::Response<Body>, http::Request<Body>>>
+            + Send
+            + 'static,
+    {
+        let layer = BoxCloneSyncServiceLayer::new(Hook::on_request(hook));
+        self.config.layers.push(layer);
+        self
+    }
+
+    /// Call `hook` with each response before it is returned.
+    ///
+    /// The hook can record the response or change it, for instance to
+    /// remove a header. The response is the final one, after redirects have
+    /// been followed, and its body hasn't been read yet. Hooks run in the
+    /// order described in [`ClientBuilder::on_request`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(not(feature = "rustls-no-provider"))]
+    /// let client = reqwest::Client::builder()
+    ///     .on_response(|res| log::debug!("{} {:?}", res.status(), res.headers()))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn on_response<F>(self, hook: F) -> ClientBuilder
+    where
+        F: Fn(&mut http::Response<Body>) + Send + Sync + 'static,
+    {
+        self.on_response_async(move |mut res| {
+            hook(&mut res);
+            std::future::ready(res)
+        })
+    }
+
+    /// Call an asynchronous `hook` with each response before it is returned.
+    ///
+    /// Like [`ClientBuilder::on_response`], but the hook takes the response
+    /// and resolves to the response to return.
+    pub fn on_response_async<F, Fut>(mut self, hook: F) -> ClientBuilder
+    where
+        F: Fn(http::Response<Body>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = http::Response<Body>> + Send + 'static,
+    {
+        let layer = BoxCloneSyncServiceLayer::new(Hook::on_response(hook));
+        self.config.layers.push(layer);
+        self
+    }
+
     /// Adds a new Tower [`Layer`](https://docs.rs/tower/latest/tower/trait.Layer.html) to the
     /// base connector [`Service`](https://docs.rs/tower/latest/tower/trait.Service.html) which
     /// is responsible for connection establishment.
@@ -2549,6 +4231,155 @@ impl ClientBuilder {
         ));
         self
     }
+
+    /// Also ask for tickets to an SPN with the port, for URLs with a
+    /// non-default port.
+    ///
+    /// Services are usually registered under `HTTP/host`, which is the SPN
+    /// tried first. Some are registered under `HTTP/host:port` instead; with
+    /// this enabled, Negotiate authentication to `https://host:8443/` that
+    /// fails with `HTTP/host` is tried again with `HTTP/host:8443`.
+    ///
+    /// Default is `false`. Only has an effect with [`ClientBuilder::negotiate()`]
+    /// or [`ClientBuilder::negotiate_with_credentials()`].
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_spn_include_port(mut self, enabled: bool) -> ClientBuilder {
+        self.config.negotiate_spn_include_port = enabled;
+        self
+    }
+
+    /// Set what Negotiate authentication does for URLs whose host is an IP
+    /// address, such as `http://10.0.0.5/`.
+    ///
+    /// Kerberos can't authenticate to an IP address, so by default
+    /// ([`IpLiteralPolicy::SkipToNtlm`]) NTLM is used instead, when the
+    /// server offers it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use reqwest::negotiate::IpLiteralPolicy;
+    ///
+    /// let client = reqwest::Client::builder()
+    ///     .negotiate()
+    ///     .negotiate_ip_literal_policy(IpLiteralPolicy::ReverseLookupHostname)
+    ///     .build()?;
+    /// # Ok::<(), reqwest::Error>(())
+    /// ```
+    ///
+    /// [`IpLiteralPolicy::SkipToNtlm`]: crate::negotiate::IpLiteralPolicy::SkipToNtlm
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_ip_literal_policy(
+        mut self,
+        policy: crate::negotiate::IpLiteralPolicy,
+    ) -> ClientBuilder {
+        self.config.negotiate_ip_literal_policy = policy;
+        self
+    }
+
+    /// Set the domain for a username given to
+    /// [`ClientBuilder::negotiate_with_credentials()`] without one.
+    ///
+    /// A username such as `jdoe` is then sent to SSPI as `DOMAIN\jdoe`;
+    /// `DOMAIN\jdoe` and `jdoe@DOMAIN.COM` are left alone. Some domain
+    /// controllers reject NTLM logons that don't name the domain they
+    /// expect. Basic authentication still sends the username as given.
+    ///
+    /// The workstation name sent with NTLM is the name of the machine. SSPI
+    /// has no way to send another one.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let client = reqwest::Client::builder()
+    ///     .negotiate_with_credentials("jdoe", "password")
+    ///     .negotiate_default_domain("CORP")
+    ///     .build()?;
+    /// # Ok::<(), reqwest::Error>(())
+    /// ```
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_default_domain<D>(mut self, domain: D) -> ClientBuilder
+    where
+        D: Into<String>,
+    {
+        self.config.negotiate_default_domain = Some(domain.into());
+        self
+    }
+
+    /// Only authenticate with Kerberos, never falling back to NTLM or Basic.
+    ///
+    /// By default, when Kerberos can't be used, Negotiate quietly picks NTLM,
+    /// and explicit credentials may be sent with Basic. Where NTLM is
+    /// disabled by policy, that only hides what is misconfigured. With this
+    /// enabled, tokens are made by the Kerberos package alone, and a request
+    /// that can't be authenticated with Kerberos fails with an error telling
+    /// why: the server doesn't offer Negotiate, the URL's host is an IP
+    /// address, or no ticket could be had for its SPN.
+    ///
+    /// Default is `false`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let client = reqwest::Client::builder()
+    ///     .negotiate()
+    ///     .negotiate_kerberos_only(true)
+    ///     .build()?;
+    /// # Ok::<(), reqwest::Error>(())
+    /// ```
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_kerberos_only(mut self, enabled: bool) -> ClientBuilder {
+        self.config.negotiate_kerberos_only = enabled;
+        self
+    }
+
+    /// Authenticate with these credentials to the hosts matching `pattern`.
+    ///
+    /// A pattern such as `*.emea.corp` matches every host under `emea.corp`,
+    /// but not `emea.corp` itself; any other pattern matches only the host
+    /// of that name. Matching ignores case, and when several patterns match,
+    /// the longest wins. Other hosts get the credentials of
+    /// [`ClientBuilder::negotiate_with_credentials()`], or the current user's.
+    ///
+    /// This lets one client authenticate to services in several Active
+    /// Directory forests, each with an identity of its own. Calling this
+    /// enables Negotiate authentication, like [`ClientBuilder::negotiate()`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let client = reqwest::Client::builder()
+    ///     .negotiate_credentials_for("*.emea.corp", "EMEA\\jdoe", "password")
+    ///     .negotiate_credentials_for("*.apac.corp", "APAC\\jdoe", "password")
+    ///     .build()?;
+    /// # Ok::<(), reqwest::Error>(())
+    /// ```
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_credentials_for<D, U, P>(
+        mut self,
+        pattern: D,
+        username: U,
+        password: P,
+    ) -> ClientBuilder
+    where
+        D: Into<String>,
+        U: Into<String>,
+        P: Into<String>,
+    {
+        self.config.negotiate_domain_credentials.push((
+            pattern.into().to_ascii_lowercase(),
+            crate::auth::Credentials::Explicit {
+                username: username.into(),
+                password: password.into(),
+            },
+        ));
+        self
+    }
 }
 
 type HyperClient = hyper_util::client::legacy::Client<Connector, super::Body>;
@@ -2559,15 +4390,6 @@ impl Default for Client {
     }
 }
 
-#[cfg(feature = "__rustls")]
-fn default_rustls_crypto_provider() -> Arc<rustls::crypto::CryptoProvider> {
-    #[cfg(not(feature = "__rustls-aws-lc-rs"))]
-    panic!("No provider set");
-
-    #[cfg(feature = "__rustls-aws-lc-rs")]
-    Arc::new(rustls::crypto::aws_lc_rs::default_provider())
-}
-
 impl Client {
     /// Constructs a new `Client`.
     ///
@@ -2607,98 +4429,367 @@ impl Client {
         self.request(Method::POST, url)
     }
 
-    /// Convenience method to make a `PUT` request to a URL.
-    ///
-    /// # Errors
+    /// Convenience method to make a `PUT` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    /// Convenience method to make a `PATCH` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn patch<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    /// Convenience method to make a `DELETE` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn delete<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    /// Convenience method to make a `HEAD` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn head<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+
+    /// Start building a `Request` with the `Method` and `Url`.
+    ///
+    /// Returns a `RequestBuilder`, which will allow setting headers and
+    /// the request body before sending.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+        let req = url.into_url().map(move |url| Request::new(method, url));
+        RequestBuilder::new(self.clone(), req)
+    }
+
+    /// Executes a `Request`.
+    ///
+    /// A `Request` can be built manually with `Request::new()` or obtained
+    /// from a RequestBuilder with `RequestBuilder::build()`.
+    ///
+    /// You should prefer to use the `RequestBuilder` and
+    /// `RequestBuilder::send()`.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if there was an error while sending request,
+    /// redirect loop was detected or redirect limit was exhausted.
+    pub fn execute(
+        &self,
+        request: Request,
+    ) -> impl Future<Output = Result<Response, crate::Error>> {
+        #[cfg(feature = "negotiate")]
+        let negotiate_config = self.inner.negotiate_config.clone();
+        #[cfg(all(windows, feature = "negotiate"))]
+        let proxy_host = self.proxy_negotiate_host(request.url());
+
+        let self_ = self.clone();
+
+        async move {
+            // A proxy asking for Negotiate is authenticated to first, for
+            // each request of the origin's handshake that it asks again
+            #[cfg(all(windows, feature = "negotiate"))]
+            if let Some(proxy_host) = proxy_host {
+                let send = |req| {
+                    crate::auth::execute_through_proxy(req, &proxy_host, |req| {
+                        self_.execute_request(req)
+                    })
+                };
+                return match negotiate_config {
+                    Some(config) => {
+                        crate::auth::execute_with_negotiate(request, &config, send).await
+                    }
+                    None => send(request).await,
+                };
+            }
+
+            #[cfg(feature = "negotiate")]
+            {
+                if let Some(config) = negotiate_config {
+                    return crate::auth::execute_with_negotiate(request, &config, |req| {
+                        self_.execute_request(req)
+                    })
+                    .await;
+                }
+            }
+            self_.execute_request(request).await
+        }
+    }
+
+    /// Goes through Negotiate authentication with `url`, and reports how it
+    /// went.
+    ///
+    /// A `GET` is sent to `url`, and if the server asks for Negotiate or
+    /// NTLM, tokens are exchanged with it as [`Client::execute`] would. The
+    /// [`Report`](crate::negotiate::Report) tells which schemes were
+    /// offered, the SPN and SSPI package used, each round-trip, and how it
+    /// ended. The credentials are those given to
+    /// [`ClientBuilder::negotiate()`], or the current user's.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), reqwest::Error> {
+    /// let client = reqwest::Client::builder().negotiate().build()?;
+    /// let report = client.check_negotiate("https://intranet.corp.com/").await?;
+    /// println!("{}: {}", report.spn(), report.outcome());
+    /// for round in report.rounds() {
+    ///     println!("{} {} bytes -> {}", round.scheme(), round.token_len(), round.status());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method fails if `url` can't be parsed or has no host, or if a
+    /// request couldn't be sent. Authentication failing is not an error; it
+    /// is told by [`Report::outcome()`](crate::negotiate::Report::outcome).
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub async fn check_negotiate<U: IntoUrl>(
+        &self,
+        url: U,
+    ) -> crate::Result<crate::negotiate::Report> {
+        let url = url.into_url()?;
+        let config = self
+            .inner
+            .negotiate_config
+            .clone()
+            .unwrap_or_else(crate::auth::NegotiateConfig::current_user);
+
+        crate::auth::check(url, &config, |req| self.execute_request(req)).await
+    }
+
+    /// Executes a `Request`, sending a second copy of it if no response
+    /// arrived within `hedge_after`.
+    ///
+    /// Whichever attempt responds first is returned and the other one is
+    /// canceled. This trades some extra load for a lower tail latency, when
+    /// a few requests are much slower than the rest. If an attempt fails
+    /// after the copy was sent, the other one is still waited for; only when
+    /// both fail is an error returned.
+    ///
+    /// The copy is sent to the same URL, and may go to another address it
+    /// resolves to or over another connection. Requests whose body can't be
+    /// cloned, such as streams, are only sent once. Only hedge requests that
+    /// are safe to process twice.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # async fn run() -> Result<(), reqwest::Error> {
+    /// let client = reqwest::Client::new();
+    /// let req = client.get("https://hyper.rs").build()?;
+    /// let res = client
+    ///     .execute_hedged(req, Duration::from_millis(200))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method fails if every attempt failed, in the same ways
+    /// [`Client::execute`] does.
+    pub fn execute_hedged(
+        &self,
+        request: Request,
+        hedge_after: Duration,
+    ) -> impl Future<Output = Result<Response, crate::Error>> {
+        let hedge = request.try_clone();
+        let first = Box::pin(self.execute(request));
+        let self_ = self.clone();
+
+        async move {
+            let hedge = match hedge {
+                Some(hedge) => hedge,
+                None => return first.await,
+            };
+
+            let mut first = Some(first);
+            let mut delay = Box::pin(tokio::time::sleep(hedge_after));
+            let raced = std::future::poll_fn(|cx| {
+                let first = first.as_mut().expect("first attempt is pending");
+                if let Poll::Ready(res) = first.as_mut().poll(cx) {
+                    return Poll::Ready(Some(res));
+                }
+                delay.as_mut().poll(cx).map(|()| None)
+            })
+            .await;
+            if let Some(res) = raced {
+                return res;
+            }
+
+            log::debug!("no response after {hedge_after:?}, sending a hedged request");
+            let mut attempts = vec![
+                first.take().expect("first attempt is pending"),
+                Box::pin(self_.execute(hedge)),
+            ];
+            std::future::poll_fn(move |cx| {
+                let mut i = 0;
+                while i < attempts.len() {
+                    match attempts[i].as_mut().poll(cx) {
+                        Poll::Ready(Ok(res)) => return Poll::Ready(Ok(res)),
+                        Poll::Ready(Err(err)) => {
+                            drop(attempts.swap_remove(i));
+                            if attempts.is_empty() {
+                                return Poll::Ready(Err(err));
+                            }
+                        }
+                        Poll::Pending => i += 1,
+                    }
+                }
+                Poll::Pending
+            })
+            .await
+        }
+    }
+
+    /// Executes many `Request`s, with at most `max_concurrency` of them in
+    /// flight at once.
     ///
-    /// This method fails whenever the supplied `Url` cannot be parsed.
-    pub fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
-        self.request(Method::PUT, url)
-    }
-
-    /// Convenience method to make a `PATCH` request to a URL.
+    /// The returned stream yields the result of every request in the order
+    /// the requests were given, so they can be zipped back with whatever
+    /// identifies them. A failed request doesn't stop the others. Requests
+    /// are taken from `requests` only as earlier ones finish, so it can be a
+    /// lazy iterator over many of them. A `max_concurrency` of 0 is treated
+    /// as 1.
     ///
-    /// # Errors
+    /// # Example
     ///
-    /// This method fails whenever the supplied `Url` cannot be parsed.
-    pub fn patch<U: IntoUrl>(&self, url: U) -> RequestBuilder {
-        self.request(Method::PATCH, url)
+    /// ```no_run
+    /// # async fn run() -> Result<(), reqwest::Error> {
+    /// use futures_util::StreamExt;
+    ///
+    /// let client = reqwest::Client::new();
+    /// let urls = ["https://hyper.rs", "https://tokio.rs"];
+    /// let requests = urls
+    ///     .iter()
+    ///     .map(|url| client.get(*url).build())
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    ///
+    /// let mut results = client.execute_all(requests, 8);
+    /// let mut urls = urls.iter();
+    /// while let Some(res) = results.next().await {
+    ///     println!("{}: {:?}", urls.next().unwrap(), res.map(|res| res.status()));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn execute_all<I>(
+        &self,
+        requests: I,
+        max_concurrency: usize,
+    ) -> impl futures_core::Stream<Item = Result<Response, crate::Error>>
+    where
+        I: IntoIterator<Item = Request>,
+        I::IntoIter: Unpin,
+    {
+        super::batch::ExecuteAll::new(self.clone(), requests.into_iter(), max_concurrency)
     }
 
-    /// Convenience method to make a `DELETE` request to a URL.
+    /// Resolve `host` ahead of the first request to it.
     ///
-    /// # Errors
+    /// The addresses are kept for a minute, and used by the connections
+    /// made to `host` in that time instead of resolving it again. This lets
+    /// the lookup overlap with other startup work:
     ///
-    /// This method fails whenever the supplied `Url` cannot be parsed.
-    pub fn delete<U: IntoUrl>(&self, url: U) -> RequestBuilder {
-        self.request(Method::DELETE, url)
-    }
-
-    /// Convenience method to make a `HEAD` request to a URL.
+    /// ```
+    /// # async fn load_config() {}
+    /// # async fn run() -> Result<(), reqwest::Error> {
+    /// let client = reqwest::Client::new();
+    /// let (config, prefetched) = tokio::join!(
+    ///     load_config(),
+    ///     client.prefetch_dns("api.example.com"),
+    /// );
+    /// prefetched?;
+    /// let res = client.get("https://api.example.com/status").send().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// `host` is a host name, as in a URL, without a port. IP addresses are
+    /// accepted, and not looked up. Hosts set with
+    /// [`ClientBuilder::resolve()`] keep their addresses.
     ///
     /// # Errors
     ///
-    /// This method fails whenever the supplied `Url` cannot be parsed.
-    pub fn head<U: IntoUrl>(&self, url: U) -> RequestBuilder {
-        self.request(Method::HEAD, url)
+    /// This method fails if `host` isn't a valid name or can't be resolved.
+    pub async fn prefetch_dns(&self, host: &str) -> crate::Result<()> {
+        self.inner
+            .dns_prefetch
+            .prefetch(host)
+            .await
+            .map_err(error::request)
     }
 
-    /// Start building a `Request` with the `Method` and `Url`.
+    /// Drops the idle connections of the pool, so that the next requests
+    /// make new ones.
     ///
-    /// Returns a `RequestBuilder`, which will allow setting headers and
-    /// the request body before sending.
-    ///
-    /// # Errors
-    ///
-    /// This method fails whenever the supplied `Url` cannot be parsed.
-    pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
-        let req = url.into_url().map(move |url| Request::new(method, url));
-        RequestBuilder::new(self.clone(), req)
+    /// Requests in flight keep their connections. This is meant for when
+    /// the connections are known to be dead or to go the wrong way, for
+    /// instance after a VPN came up; see also
+    /// [`ClientBuilder::pool_invalidate_on_network_change`].
+    pub fn invalidate_pool(&self) {
+        self.inner.pools.invalidate();
     }
 
-    /// Executes a `Request`.
-    ///
-    /// A `Request` can be built manually with `Request::new()` or obtained
-    /// from a RequestBuilder with `RequestBuilder::build()`.
+    /// Returns the cookie store of this client, if it has one.
     ///
-    /// You should prefer to use the `RequestBuilder` and
-    /// `RequestBuilder::send()`.
+    /// It can be used to look at the cookies received so far, or to add and
+    /// remove cookies between requests.
     ///
-    /// # Errors
+    /// # Optional
     ///
-    /// This method fails if there was an error while sending request,
-    /// redirect loop was detected or redirect limit was exhausted.
-    pub fn execute(
-        &self,
-        request: Request,
-    ) -> impl Future<Output = Result<Response, crate::Error>> {
-        #[cfg(feature = "negotiate")]
-        let negotiate_config = self.inner.negotiate_config.clone();
-
-        let self_ = self.clone();
+    /// This requires the optional `cookies` feature to be enabled.
+    #[cfg(feature = "cookies")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cookies")))]
+    pub fn cookie_store(&self) -> Option<Arc<dyn cookie::CookieStore>> {
+        self.inner.cookie_store.clone()
+    }
 
-        async move {
+    pub(super) fn curl_defaults(&self) -> super::curl::Defaults {
+        super::curl::Defaults {
+            headers: self.inner.headers.clone(),
+            scoped_headers: self.inner.scoped_headers.clone(),
+            header_case: self.inner.header_case.clone(),
+            #[cfg(feature = "cookies")]
+            cookie_store: self.inner.cookie_store.clone(),
             #[cfg(feature = "negotiate")]
-            {
-                if let Some(config) = negotiate_config {
-                    return crate::auth::execute_with_negotiate(
-                        request,
-                        &config.credentials,
-                        |req| self_.execute_request(req),
-                    )
-                    .await;
-                }
-            }
-            self_.execute_request(request).await
+            negotiate: self.inner.negotiate_config.clone(),
         }
     }
 
     pub(super) fn execute_request(&self, req: Request) -> Pending {
-        let (method, url, mut headers, body, version, extensions) = req.pieces();
+        self.inner.pools.check_network();
+
+        let (method, mut url, mut headers, body, version, extensions) = req.pieces();
         if url.scheme() != "http" && url.scheme() != "https" {
             return Pending::new_err(error::url_bad_scheme(url));
         }
 
+        if let Some(ref hsts) = self.inner.hsts {
+            hsts.upgrade(&mut url);
+        }
+
         // check if we're in https_only mode and check the scheme of the current URL
         if self.inner.https_only && url.scheme() != "https" {
             return Pending::new_err(error::url_bad_scheme(url));
@@ -2706,12 +4797,23 @@ impl Client {
 
         // insert default headers in the request headers
         // without overwriting already appended headers.
+        self.inner.scoped_headers.apply(&url, &mut headers);
         for (key, value) in &self.inner.headers {
             if let Entry::Vacant(entry) = headers.entry(key) {
                 entry.insert(value.clone());
             }
         }
 
+        if let Some(ref propagation) = self.inner.trace_context {
+            propagation.inject(&mut headers);
+        }
+
+        // Only the IDs added here are regenerated on retries.
+        let stamped = match self.inner.request_id {
+            Some(ref stamp) if stamp.stamp(&mut headers) => Some(stamp.clone()),
+            _ => None,
+        };
+
         let uri = match try_uri(&url) {
             Ok(uri) => uri,
             _ => return Pending::new_err(error::url_invalid_uri(url)),
@@ -2722,12 +4824,114 @@ impl Client {
         self.proxy_auth(&uri, &mut headers);
         self.proxy_custom_headers(&uri, &mut headers);
 
-        let builder = hyper::Request::builder()
+        // Requests to origins advertising HTTP/3 are sent over it, keeping a
+        // copy to send over the usual connection if that fails.
+        #[cfg(feature = "http3")]
+        let mut alt_svc_body = match self.inner.alt_svc {
+            Some(ref alt_svc)
+                if version == http::Version::HTTP_11
+                    && method.is_idempotent()
+                    && self.inner.layered.is_none()
+                    && self.inner.mock.is_none()
+                    && RequestConfig::<TlsVerification>::get(&extensions).is_none()
+                    && alt_svc.is_advertised(&uri)
+                    && !self
+                        .inner
+                        .proxies
+                        .iter()
+                        .any(|p| p.intercept(&uri).is_some()) =>
+            {
+                body.try_clone()
+            }
+            _ => None,
+        };
+        #[cfg(feature = "http3")]
+        let mut alt_svc_fallback = None;
+        #[cfg(not(feature = "http3"))]
+        let alt_svc_fallback = None;
+
+        let mut builder = hyper::Request::builder()
             .method(method.clone())
             .uri(uri)
             .version(version);
 
+        if let Some(stamp) = stamped {
+            builder = builder.extension(stamp);
+        }
+
+        // Config that the inner services need to see.
+        if let Some(throttle) = self.inner.upload_rate.fetch(&extensions) {
+            builder = builder.extension(RequestConfig::<UploadRate>::new(Some(throttle.clone())));
+        }
+        if let Some(progress) = RequestConfig::<UploadProgress>::get(&extensions) {
+            builder =
+                builder.extension(RequestConfig::<UploadProgress>::new(Some(progress.clone())));
+        }
+        if let Some(informational) = RequestConfig::<OnInformational>::get(&extensions) {
+            builder = builder.extension(RequestConfig::<OnInformational>::new(Some(
+                informational.clone(),
+            )));
+        }
+        if let Some(timeout) = RequestConfig::<ExpectContinue>::get(&extensions) {
+            builder = builder.extension(RequestConfig::<ExpectContinue>::new(Some(*timeout)));
+        }
+        if let Some(policy) = RequestConfig::<RedirectPolicy>::get(&extensions) {
+            builder = builder.extension(RequestConfig::<RedirectPolicy>::new(Some(policy.clone())));
+        }
+        let deadline = RequestConfig::<Deadline>::get(&extensions).copied();
+        if deadline.is_some() {
+            builder = builder.extension(RequestConfig::<Deadline>::new(deadline));
+        }
+        #[cfg(feature = "har")]
+        if let Some(recorder) = self.inner.har.fetch(&extensions) {
+            builder = builder.extension(RequestConfig::<Har>::new(Some(recorder.clone())));
+        }
+        #[cfg(feature = "__rustls")]
+        if let Some(tls) = RequestConfig::<TlsVerification>::get(&extensions) {
+            #[cfg(feature = "http3")]
+            if version == http::Version::HTTP_3 {
+                return Pending::new_err(error::builder(
+                    "per-request TLS settings are not supported over HTTP/3",
+                ));
+            }
+            builder = builder.extension(RequestConfig::<TlsVerification>::new(Some(tls.clone())));
+        }
+
+        let span = trace_span!(
+            "request",
+            http.request.method = %method,
+            url.full = %crate::trace::url_full(&url),
+            server.address = url.host_str(),
+            server.port = url.port_or_known_default(),
+            http.response.status_code = tracing::field::Empty,
+        );
+
         let in_flight = match version {
+            _ if self.inner.layered.is_some() => {
+                let mut req = builder.body(body).expect("valid request parts");
+                *req.headers_mut() = headers.clone();
+                let mut layered = self.inner.layered.as_ref().unwrap().clone();
+                ResponseFuture::Layered(SyncFuture::new(layered.call(req)))
+            }
+            _ if self.inner.mock.is_some() => {
+                let mut req = builder.body(body).expect("valid request parts");
+                *req.headers_mut() = headers.clone();
+                let mut mock = self.inner.mock.as_ref().unwrap().clone();
+                ResponseFuture::Mock(mock.call(req))
+            }
+            #[cfg(feature = "http3")]
+            _ if alt_svc_body.is_some() => {
+                let mut req = builder.body(body).expect("valid request parts");
+                *req.headers_mut() = headers.clone();
+                let mut fallback = http::Request::new(alt_svc_body.take().unwrap());
+                *fallback.method_mut() = req.method().clone();
+                *fallback.uri_mut() = req.uri().clone();
+                *fallback.headers_mut() = req.headers().clone();
+                *fallback.extensions_mut() = req.extensions().clone();
+                alt_svc_fallback = Some(fallback);
+                let mut h3 = self.inner.h3_client.as_ref().unwrap().clone();
+                ResponseFuture::H3(h3.call(req))
+            }
             #[cfg(feature = "http3")]
             http::Version::HTTP_3 if self.inner.h3_client.is_some() => {
                 let mut req = builder.body(body).expect("valid request parts");
@@ -2743,7 +4947,7 @@ impl Client {
             }
         };
 
-        let total_timeout = self
+        let mut total_timeout = self
             .inner
             .total_timeout
             .fetch(&extensions)
@@ -2751,11 +4955,22 @@ impl Client {
             .map(tokio::time::sleep)
             .map(Box::pin);
 
-        let read_timeout_fut = self
-            .inner
-            .read_timeout
-            .map(tokio::time::sleep)
-            .map(Box::pin);
+        // A deadline bounds the whole request just like the total timeout,
+        // so only keep whichever fires first.
+        if let Some(deadline) = deadline.map(tokio::time::Instant::from_std) {
+            match total_timeout {
+                Some(ref sleep) if sleep.deadline() <= deadline => (),
+                _ => total_timeout = Some(Box::pin(tokio::time::sleep_until(deadline))),
+            }
+        }
+
+        let read_timeout = self.inner.read_timeout.fetch(&extensions).copied();
+
+        let read_timeout_fut = read_timeout.map(tokio::time::sleep).map(Box::pin);
+
+        let download_rate = self.inner.download_rate.fetch(&extensions).cloned();
+        let download_progress = RequestConfig::<DownloadProgress>::get(&extensions).cloned();
+        let if_cached = RequestConfig::<IfCached>::get(&extensions).cloned();
 
         Pending {
             inner: PendingInner::Request(Box::pin(PendingRequest {
@@ -2768,7 +4983,13 @@ impl Client {
                 in_flight,
                 total_timeout,
                 read_timeout_fut,
-                read_timeout: self.inner.read_timeout,
+                read_timeout,
+                download_rate,
+                download_progress,
+                if_cached,
+                alt_svc_fallback,
+                span,
+                timer: Timer::start(),
             })),
         }
     }
@@ -2797,6 +5018,24 @@ impl Client {
         }
     }
 
+    /// The host of the proxy to authenticate to with Negotiate, when `url`
+    /// is sent through one that forwards it.
+    ///
+    /// Proxies that tunnel, for `https` URLs, are authenticated to while
+    /// connecting instead.
+    #[cfg(all(windows, feature = "negotiate"))]
+    fn proxy_negotiate_host(&self, url: &Url) -> Option<String> {
+        if url.scheme() != "http" {
+            return None;
+        }
+        let dst = try_uri(url).ok()?;
+
+        self.inner
+            .proxies
+            .iter()
+            .find_map(|proxy| proxy.http_non_tunnel_negotiate_host(&dst))
+    }
+
     fn proxy_custom_headers(&self, dst: &Uri, headers: &mut HeaderMap) {
         if !self.inner.proxies_maybe_http_custom_headers {
             return;
@@ -2868,7 +5107,7 @@ impl Config {
 
         #[cfg(feature = "cookies")]
         {
-            if let Some(_) = self.cookie_store {
+            if self.cookie_store.is_some() || self.new_cookie_jar {
                 f.field("cookie_store", &true);
             }
         }
@@ -2889,10 +5128,18 @@ impl Config {
 
         f.field("default_headers", &self.headers);
 
+        if !self.scoped_headers.is_empty() {
+            f.field("scoped_headers", &self.scoped_headers);
+        }
+
         if self.http1_title_case_headers {
             f.field("http1_title_case_headers", &true);
         }
 
+        if !self.http1_header_case.is_empty() {
+            f.field("http1_header_case", &self.http1_header_case);
+        }
+
         if self.http1_allow_obsolete_multiline_headers_in_responses {
             f.field("http1_allow_obsolete_multiline_headers_in_responses", &true);
         }
@@ -2914,6 +5161,11 @@ impl Config {
             f.field("http2_prior_knowledge", &true);
         }
 
+        #[cfg(feature = "http2")]
+        if self.http2_prior_knowledge_cleartext {
+            f.field("http2_prior_knowledge_cleartext", &true);
+        }
+
         if let Some(ref d) = self.connect_timeout {
             f.field("connect_timeout", d);
         }
@@ -2922,6 +5174,47 @@ impl Config {
             f.field("timeout", d);
         }
 
+        if let Some(ref v) = self.max_download_rate {
+            f.field("max_download_rate", v);
+        }
+
+        if let Some(ref v) = self.max_upload_rate {
+            f.field("max_upload_rate", v);
+        }
+
+        if let Some(ref v) = self.max_requests_per_second {
+            f.field("max_requests_per_second", v);
+        }
+
+        if let Some(ref v) = self.max_requests_per_second_per_host {
+            f.field("max_requests_per_second_per_host", v);
+        }
+
+        if let Some(ref v) = self.max_response_size {
+            f.field("max_response_size", v);
+        }
+
+        if let Some(ref v) = self.body_buffer_pool {
+            f.field("body_buffer_pool", v);
+        }
+
+        #[cfg(feature = "har")]
+        if let Some(ref v) = self.har {
+            f.field("har", v);
+        }
+
+        if let Some(ref v) = self.trace_context {
+            f.field("trace_context", v);
+        }
+
+        if let Some(ref v) = self.request_id {
+            f.field("request_id", v);
+        }
+
+        if let Some(ref v) = self.mock_transport {
+            f.field("mock_transport", v);
+        }
+
         if let Some(ref v) = self.local_address {
             f.field("local_address", v);
         }
@@ -2969,9 +5262,53 @@ impl Config {
 
             f.field("tls_sni", &self.tls_sni);
 
+            if let Some(ref alpn_protocols) = self.alpn_protocols {
+                f.field("alpn_protocols", alpn_protocols);
+            }
+
             f.field("tls_info", &self.tls_info);
         }
 
+        #[cfg(feature = "__rustls")]
+        if self.tls_verifier.is_some() {
+            f.field("tls_verifier", &true);
+        }
+
+        #[cfg(feature = "__rustls")]
+        {
+            if !self.tls_session_resumption {
+                f.field("tls_session_resumption", &false);
+            }
+
+            if !self.tls_session_tickets {
+                f.field("tls_session_tickets", &false);
+            }
+
+            if let Some(ref cache) = self.tls_session_cache {
+                f.field("tls_session_cache", cache);
+            }
+        }
+
+        #[cfg(feature = "__rustls")]
+        if self.tls_ocsp != tls::OcspPolicy::Off {
+            f.field("tls_ocsp", &self.tls_ocsp);
+        }
+
+        #[cfg(feature = "__rustls")]
+        if self.tls_post_quantum {
+            f.field("tls_post_quantum", &true);
+        }
+
+        #[cfg(feature = "__rustls")]
+        if !self.host_identities.is_empty() {
+            let hosts = self
+                .host_identities
+                .iter()
+                .map(|(host, _)| host)
+                .collect::<Vec<_>>();
+            f.field("identities_for_hosts", &hosts);
+        }
+
         #[cfg(all(feature = "default-tls", feature = "__rustls"))]
         {
             f.field("tls_backend", &self.tls);
@@ -3019,28 +5356,97 @@ type MaybeDecompression<T> = Decompression<T>;
 
 type LayeredService<T> = MaybeDecompression<
     FollowRedirect<
-        MaybeCookieService<tower::retry::Retry<crate::retry::Policy, T>>,
+        AsyncPolicyService<
+            HstsService<
+                MaybeCookieService<tower::retry::Retry<crate::retry::Policy, RateLimited<T>>>,
+            >,
+        >,
         TowerRedirectPolicy,
     >,
 >;
 type LayeredFuture<T> = <LayeredService<T> as Service<http::Request<Body>>>::Future;
 
+pub(crate) type BoxedClientService =
+    BoxCloneSyncService<http::Request<Body>, http::Response<Body>, BoxError>;
+
+pub(crate) type BoxedClientLayer = BoxCloneSyncServiceLayer<
+    BoxedClientService,
+    http::Request<Body>,
+    http::Response<Body>,
+    BoxError,
+>;
+
+/// The service wrapped by the layers added with `ClientBuilder::layer`: it
+/// sends a request through the client's own stack, over HTTP/3 if asked to.
+#[derive(Clone)]
+struct Dispatch {
+    hyper: LayeredService<HyperService>,
+    mock: Option<LayeredService<MockTransport>>,
+    #[cfg(feature = "http3")]
+    h3_client: Option<LayeredService<H3Client>>,
+}
+
+impl Service<http::Request<Body>> for Dispatch {
+    type Response = http::Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        if let Some(ref mut mock) = self.mock {
+            let fut = mock.call(req);
+            return Box::pin(async move { Ok(fut.await?.map(Body::wrap)) });
+        }
+        match req.version() {
+            #[cfg(feature = "http3")]
+            http::Version::HTTP_3 if self.h3_client.is_some() => {
+                let fut = self.h3_client.as_mut().unwrap().call(req);
+                Box::pin(async move { Ok(fut.await?.map(Body::wrap)) })
+            }
+            _ => {
+                let fut = self.hyper.call(req);
+                Box::pin(async move { Ok(fut.await?.map(Body::wrap)) })
+            }
+        }
+    }
+}
+
 struct ClientRef {
     accepts: Accepts,
     #[cfg(feature = "cookies")]
     cookie_store: Option<Arc<dyn cookie::CookieStore>>,
     headers: HeaderMap,
+    scoped_headers: Arc<ScopedHeaders>,
     hyper: LayeredService<HyperService>,
+    mock: Option<LayeredService<MockTransport>>,
     #[cfg(feature = "http3")]
     h3_client: Option<LayeredService<H3Client>>,
+    #[cfg(feature = "http3")]
+    alt_svc: Option<AltSvc>,
+    pools: Pools,
+    layered: Option<BoxedClientService>,
     referer: bool,
     total_timeout: RequestConfig<TotalTimeout>,
-    read_timeout: Option<Duration>,
+    read_timeout: RequestConfig<ReadTimeout>,
+    download_rate: RequestConfig<DownloadRate>,
+    upload_rate: RequestConfig<UploadRate>,
+    max_response_size: Option<u64>,
+    body_buffer_pool: Option<BufferPool>,
+    #[cfg(feature = "har")]
+    har: RequestConfig<Har>,
+    trace_context: Option<crate::trace_context::Propagation>,
+    request_id: Option<crate::request_id::Stamp>,
     proxies: Arc<Vec<ProxyMatcher>>,
     proxies_maybe_http_auth: bool,
     proxies_maybe_http_custom_headers: bool,
     https_only: bool,
+    hsts: Option<crate::hsts::HstsConfig>,
     redirect_policy_desc: Option<String>,
+    header_case: Option<Arc<HeaderCase>>,
+    dns_prefetch: Arc<Prefetched>,
     #[cfg(feature = "negotiate")]
     negotiate_config: Option<crate::auth::NegotiateConfig>,
 }
@@ -3073,10 +5479,38 @@ impl ClientRef {
 
         f.field("default_headers", &self.headers);
 
+        if !self.scoped_headers.is_empty() {
+            f.field("scoped_headers", &self.scoped_headers);
+        }
+
         self.total_timeout.fmt_as_field(f);
 
-        if let Some(ref d) = self.read_timeout {
-            f.field("read_timeout", d);
+        self.read_timeout.fmt_as_field(f);
+
+        self.download_rate.fmt_as_field(f);
+        self.upload_rate.fmt_as_field(f);
+
+        if let Some(ref v) = self.max_response_size {
+            f.field("max_response_size", v);
+        }
+
+        if let Some(ref v) = self.body_buffer_pool {
+            f.field("body_buffer_pool", v);
+        }
+
+        #[cfg(feature = "har")]
+        self.har.fmt_as_field(f);
+
+        if let Some(ref v) = self.trace_context {
+            f.field("trace_context", v);
+        }
+
+        if let Some(ref v) = self.request_id {
+            f.field("request_id", v);
+        }
+
+        if self.mock.is_some() {
+            f.field("mock_transport", &true);
         }
     }
 }
@@ -3108,13 +5542,33 @@ pin_project! {
         #[pin]
         read_timeout_fut: Option<Pin<Box<Sleep>>>,
         read_timeout: Option<Duration>,
+        download_rate: Option<Throttle>,
+        download_progress: Option<Progress>,
+        if_cached: Option<crate::cache::CachedResponse>,
+        // The request to send over the usual connection if the HTTP/3
+        // endpoint advertised with `Alt-Svc` fails.
+        alt_svc_fallback: Option<AltSvcFallback>,
+        span: Span,
+        timer: Timer,
     }
 }
 
+#[cfg(feature = "http3")]
+type AltSvcFallback = http::Request<Body>;
+
+#[cfg(not(feature = "http3"))]
+type AltSvcFallback = std::convert::Infallible;
+
+// `PendingRequest` is boxed already, boxing the default future too would
+// only add an allocation.
+#[allow(clippy::large_enum_variant)]
 enum ResponseFuture {
     Default(LayeredFuture<HyperService>),
     #[cfg(feature = "http3")]
     H3(LayeredFuture<H3Client>),
+    Mock(LayeredFuture<MockTransport>),
+    // Tower boxes the future as `Send` only, while `Pending` is `Sync` too.
+    Layered(SyncFuture<<BoxedClientService as Service<http::Request<Body>>>::Future>),
 }
 
 impl PendingRequest {
@@ -3161,6 +5615,22 @@ impl Future for PendingRequest {
     type Output = Result<Response, crate::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let span = self.span.clone();
+        let _entered = span.enter();
+        let res = ready!(self.as_mut().poll_response(cx));
+        crate::metrics::request(&self.timer, &self.method, &res);
+        if let Ok(ref res) = res {
+            span.record("http.response.status_code", res.status().as_u16());
+        }
+        Poll::Ready(res)
+    }
+}
+
+impl PendingRequest {
+    fn poll_response(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Response, crate::Error>> {
         if let Some(delay) = self.as_mut().total_timeout().as_mut().as_pin_mut() {
             if let Poll::Ready(()) = delay.poll(cx) {
                 return Poll::Ready(Err(
@@ -3177,7 +5647,7 @@ impl Future for PendingRequest {
             }
         }
 
-        let res = match self.as_mut().in_flight().get_mut() {
+        let mut res = match self.as_mut().in_flight().get_mut() {
             ResponseFuture::Default(r) => match ready!(Pin::new(r).poll(cx)) {
                 Err(e) => {
                     return Poll::Ready(Err(e.if_no_url(|| self.url.clone())));
@@ -3187,10 +5657,36 @@ impl Future for PendingRequest {
             #[cfg(feature = "http3")]
             ResponseFuture::H3(r) => match ready!(Pin::new(r).poll(cx)) {
                 Err(e) => {
+                    if let Some(req) = self.alt_svc_fallback.take() {
+                        log::debug!("HTTP/3 request to {} failed, falling back: {e}", self.url);
+                        if let Some(ref alt_svc) = self.client.alt_svc {
+                            alt_svc.mark_broken(req.uri());
+                        }
+                        let mut hyper = self.client.hyper.clone();
+                        let fallback = ResponseFuture::Default(hyper.call(req));
+                        self.as_mut().in_flight().set(fallback);
+                        return self.poll_response(cx);
+                    }
                     return Poll::Ready(Err(crate::error::request(e).with_url(self.url.clone())));
                 }
                 Ok(res) => res.map(super::body::boxed),
             },
+            ResponseFuture::Mock(r) => match ready!(Pin::new(r).poll(cx)) {
+                Err(e) => {
+                    return Poll::Ready(Err(e.if_no_url(|| self.url.clone())));
+                }
+                Ok(res) => res.map(super::body::boxed),
+            },
+            ResponseFuture::Layered(r) => match ready!(Pin::new(r).poll(cx)) {
+                Err(e) => {
+                    let e = match e.downcast::<crate::Error>() {
+                        Ok(e) => *e,
+                        Err(e) => crate::error::request(error::cast_to_internal_error(e)),
+                    };
+                    return Poll::Ready(Err(e.if_no_url(|| self.url.clone())));
+                }
+                Ok(res) => res.map(super::body::boxed),
+            },
         };
 
         if let Some(url) = &res
@@ -3200,15 +5696,46 @@ impl Future for PendingRequest {
             self.url = match Url::parse(&url.0.to_string()) {
                 Ok(url) => url,
                 Err(e) => return Poll::Ready(Err(crate::error::decode(e))),
+            };
+            // Redirects are upgraded beneath `FollowRedirect`, which still
+            // reports the URL it was given.
+            if let Some(hsts) = self.client.hsts.clone() {
+                hsts.upgrade(&mut self.url);
             }
         };
 
-        let res = Response::new(
+        #[cfg(feature = "http3")]
+        if let Some(ref alt_svc) = self.client.alt_svc {
+            if let Ok(uri) = try_uri(&self.url) {
+                alt_svc.record(&uri, res.headers());
+            }
+        }
+
+        // Without retries, the ID wasn't put in the extensions on the way.
+        if let Some(ref stamp) = self.client.request_id {
+            if res.extensions().get::<RequestId>().is_none() {
+                if let Some(id) = stamp.get(&self.headers) {
+                    res.extensions_mut().insert(id);
+                }
+            }
+        }
+
+        if res.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = self.if_cached.take() {
+                return Poll::Ready(Ok(cached.revalidated(res.headers())));
+            }
+        }
+
+        let mut res = Response::new(
             res,
             self.url.clone(),
             self.total_timeout.take(),
             self.read_timeout,
+            self.download_rate.take(),
+            self.download_progress.take(),
+            self.client.max_response_size,
         );
+        res.buffer_pool = self.client.body_buffer_pool.clone();
         Poll::Ready(Ok(res))
     }
 }
@@ -0,0 +1,139 @@
+//! Rendering a request as the equivalent `curl` command line.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use http::header::Entry;
+use http::{HeaderMap, Method, Version};
+use url::Url;
+
+/// What a `Client` adds to every request it sends, as far as `curl` is
+/// concerned.
+#[derive(Clone)]
+pub(crate) struct Defaults {
+    pub(crate) headers: HeaderMap,
+    pub(crate) scoped_headers: Arc<super::scoped_headers::ScopedHeaders>,
+    pub(crate) header_case: Option<Arc<crate::connect::header_case::HeaderCase>>,
+    #[cfg(feature = "cookies")]
+    pub(crate) cookie_store: Option<Arc<dyn crate::cookie::CookieStore>>,
+    #[cfg(feature = "negotiate")]
+    pub(crate) negotiate: Option<crate::auth::NegotiateConfig>,
+}
+
+/// A request body, as far as `curl` is concerned.
+pub(crate) enum Body<'a> {
+    Empty,
+    Bytes(&'a [u8]),
+    Streaming,
+}
+
+impl<'a> From<Option<Option<&'a [u8]>>> for Body<'a> {
+    fn from(body: Option<Option<&'a [u8]>>) -> Body<'a> {
+        match body {
+            None => Body::Empty,
+            Some(Some(bytes)) => Body::Bytes(bytes),
+            Some(None) => Body::Streaming,
+        }
+    }
+}
+
+pub(crate) fn command(
+    defaults: &Defaults,
+    method: &Method,
+    url: &Url,
+    version: Version,
+    headers: &HeaderMap,
+    body: Body<'_>,
+) -> String {
+    let mut args: Vec<Cow<'_, str>> = vec!["curl".into()];
+
+    let has_body = !matches!(body, Body::Empty);
+    match *method {
+        Method::GET => (),
+        Method::HEAD => args.push("--head".into()),
+        // Sending a body already makes it a `POST`.
+        Method::POST if has_body => (),
+        ref method => {
+            args.push("-X".into());
+            args.push(quote(method.as_str()));
+        }
+    }
+
+    match version {
+        Version::HTTP_09 => args.push("--http0.9".into()),
+        Version::HTTP_10 => args.push("--http1.0".into()),
+        Version::HTTP_2 => args.push("--http2".into()),
+        Version::HTTP_3 => args.push("--http3".into()),
+        _ => (),
+    }
+
+    let mut all = headers.clone();
+    #[cfg(feature = "cookies")]
+    if let Some(ref cookie_store) = defaults.cookie_store {
+        if !all.contains_key(http::header::COOKIE) {
+            if let Some(cookies) = cookie_store.cookies(url) {
+                all.insert(http::header::COOKIE, cookies);
+            }
+        }
+    }
+    // The client's default headers only fill in what the request lacks.
+    defaults.scoped_headers.apply(url, &mut all);
+    for (name, value) in &defaults.headers {
+        if let Entry::Vacant(entry) = all.entry(name) {
+            entry.insert(value.clone());
+        }
+    }
+    for (name, value) in &all {
+        let name = defaults
+            .header_case
+            .as_ref()
+            .and_then(|case| case.get(name))
+            .unwrap_or(name.as_str());
+        let header = format!("{}: {}", name, String::from_utf8_lossy(value.as_bytes()));
+        args.push("-H".into());
+        args.push(quote(&header).into_owned().into());
+    }
+
+    match body {
+        Body::Empty => (),
+        Body::Bytes(bytes) => {
+            args.push("--data-binary".into());
+            match std::str::from_utf8(bytes) {
+                Ok(text) => args.push(quote(text)),
+                Err(_) => args.push("@-".into()),
+            }
+        }
+        Body::Streaming => {
+            args.push("--data-binary".into());
+            args.push("@-".into());
+        }
+    }
+
+    #[cfg(feature = "negotiate")]
+    if let Some(ref negotiate) = defaults.negotiate {
+        use crate::auth::Credentials;
+
+        args.push("--negotiate".into());
+        args.push("-u".into());
+        match negotiate.credentials {
+            Credentials::CurrentUser => args.push(":".into()),
+            Credentials::Explicit {
+                ref username,
+                ref password,
+            } => args.push(quote(&format!("{username}:{password}")).into_owned().into()),
+        }
+    }
+
+    args.push(quote(url.as_str()));
+    args.join(" ")
+}
+
+/// Quote `arg` for a POSIX shell, unless it is safe as is.
+fn quote(arg: &str) -> Cow<'_, str> {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.into()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''")).into()
+    }
+}
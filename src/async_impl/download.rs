@@ -0,0 +1,115 @@
+//! Resumable downloads to a file.
+//!
+//! While a download is in progress, the body is written to `<path>.part`,
+//! and the validator of the response, its strong `ETag` or else its
+//! `Last-Modified`, to `<path>.part.validator`. A later attempt asks for the
+//! rest of the body with `Range`, guarded by `If-Range` so a changed resource
+//! is sent in full instead of being spliced onto stale data.
+
+use std::ffi::OsString;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+use super::request::RequestBuilder;
+use super::response::Response;
+use crate::header::{HeaderValue, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use crate::StatusCode;
+
+pub(crate) async fn download_to(builder: RequestBuilder, path: &Path) -> crate::Result<u64> {
+    let part = with_suffix(path, ".part");
+    let validator_path = with_suffix(path, ".part.validator");
+
+    let offset = match fs::metadata(&part).await {
+        Ok(meta) => meta.len(),
+        Err(_) => 0,
+    };
+    let validator = match fs::read(&validator_path).await {
+        Ok(validator) => HeaderValue::from_bytes(&validator).ok(),
+        Err(_) => None,
+    };
+
+    let res = match validator {
+        Some(validator) if offset > 0 => {
+            let fallback = builder.try_clone();
+            let res = builder
+                .header(RANGE, format!("bytes={offset}-"))
+                .header(IF_RANGE, validator)
+                .send()
+                .await?;
+            match fallback {
+                // The part is no longer a prefix of the resource, start over.
+                Some(fallback) if res.status() == StatusCode::RANGE_NOT_SATISFIABLE => {
+                    fallback.send().await?
+                }
+                _ => res,
+            }
+        }
+        _ => builder.send().await?,
+    };
+    let mut res = res.error_for_status()?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&part)
+        .await
+        .map_err(crate::error::io)?;
+    let mut len = if res.status() == StatusCode::PARTIAL_CONTENT {
+        if content_range_start(&res) != Some(offset) {
+            return Err(crate::error::decode("unexpected Content-Range in response"));
+        }
+        offset
+    } else {
+        file.set_len(0).await.map_err(crate::error::io)?;
+        0
+    };
+    file.seek(SeekFrom::Start(len))
+        .await
+        .map_err(crate::error::io)?;
+
+    match validator_of(&res) {
+        Some(validator) => fs::write(&validator_path, validator.as_bytes()).await,
+        None => match fs::remove_file(&validator_path).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        },
+    }
+    .map_err(crate::error::io)?;
+
+    // Keep what was received even if the body is cut off, and make sure it
+    // is on disk before returning.
+    let copied = res.write_to(&mut file).await;
+    file.flush().await.map_err(crate::error::io)?;
+    len += copied?;
+    drop(file);
+
+    fs::rename(&part, path).await.map_err(crate::error::io)?;
+    let _ = fs::remove_file(&validator_path).await;
+    Ok(len)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// A validator `If-Range` can be used with: weak ETags aren't allowed.
+fn validator_of(res: &Response) -> Option<&HeaderValue> {
+    match res.headers().get(ETAG) {
+        Some(etag) if !etag.as_bytes().starts_with(b"W/") => Some(etag),
+        _ => res.headers().get(LAST_MODIFIED),
+    }
+}
+
+/// The first byte of a `Content-Range: bytes <start>-<end>/<len>`.
+fn content_range_start(res: &Response) -> Option<u64> {
+    let range = res.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let range = range.strip_prefix("bytes ")?;
+    let (start, _) = range.split_once('-')?;
+    start.trim().parse().ok()
+}
@@ -0,0 +1,167 @@
+//! `Expect: 100-continue` for request bodies.
+//!
+//! The body of a request sent with `Expect: 100-continue` is held back by a
+//! [`Gate`] until the server answers with `100 Continue`, or until a timeout
+//! elapses for servers that never do. If the final response arrives first,
+//! the body is not sent at all: the gate stays shut while the response is
+//! read, and fails the body once the response is dropped, which closes the
+//! connection instead of leaving it waiting on a body that isn't coming.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll, Waker};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use tokio::time::Sleep;
+
+use crate::error::BoxError;
+
+#[derive(Clone)]
+pub(crate) struct Gate {
+    shared: Arc<Mutex<Shared>>,
+}
+
+struct Shared {
+    state: State,
+    waker: Option<Waker>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Waiting,
+    Open,
+    Rejected,
+    Aborted,
+}
+
+impl Gate {
+    pub(crate) fn new() -> Gate {
+        Gate {
+            shared: Arc::new(Mutex::new(Shared {
+                state: State::Waiting,
+                waker: None,
+            })),
+        }
+    }
+
+    /// Let the body through, on `100 Continue` or once the timeout elapses.
+    pub(crate) fn open(&self) {
+        self.transition(State::Waiting, State::Open);
+    }
+
+    /// The final response arrived before the body was let through. The
+    /// returned guard fails the body once every copy of it is dropped.
+    pub(crate) fn reject(&self) -> Guard {
+        self.transition(State::Waiting, State::Rejected);
+        Guard {
+            _abort: Arc::new(Abort(self.clone())),
+        }
+    }
+
+    fn transition(&self, from: State, to: State) {
+        let mut shared = self.shared.lock().expect("gate lock poisoned");
+        if shared.state == from {
+            shared.state = to;
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    fn poll_state(&self, cx: &mut Context) -> State {
+        let mut shared = self.shared.lock().expect("gate lock poisoned");
+        if matches!(shared.state, State::Waiting | State::Rejected) {
+            shared.waker = Some(cx.waker().clone());
+        }
+        shared.state
+    }
+}
+
+/// Kept in the extensions of the response to a rejected request.
+#[derive(Clone)]
+pub(crate) struct Guard {
+    _abort: Arc<Abort>,
+}
+
+struct Abort(Gate);
+
+impl Drop for Abort {
+    fn drop(&mut self) {
+        self.0.transition(State::Rejected, State::Aborted);
+    }
+}
+
+pin_project! {
+    pub(crate) struct ContinueBody<B> {
+        #[pin]
+        inner: B,
+        gate: Gate,
+        timeout: Duration,
+        sleep: Option<Pin<Box<Sleep>>>,
+    }
+}
+
+pub(crate) fn gated<B>(body: B, gate: Gate, timeout: Duration) -> ContinueBody<B> {
+    ContinueBody {
+        inner: body,
+        gate,
+        timeout,
+        sleep: None,
+    }
+}
+
+impl<B> HttpBody for ContinueBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        loop {
+            match this.gate.poll_state(cx) {
+                State::Open => {
+                    return this
+                        .inner
+                        .poll_frame(cx)
+                        .map(|frame| frame.map(|frame| frame.map_err(Into::into)));
+                }
+                State::Waiting => {
+                    // The body is first polled once the headers are written,
+                    // so that is when the wait starts.
+                    let timeout = *this.timeout;
+                    let sleep = this
+                        .sleep
+                        .get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+                    ready!(sleep.as_mut().poll(cx));
+                    this.gate.open();
+                }
+                State::Rejected => return Poll::Pending,
+                State::Aborted => {
+                    return Poll::Ready(Some(Err(
+                        "request body not sent, the server responded without 100 Continue".into(),
+                    )));
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,215 @@
+//! The HTTP/3 endpoints origins advertise with `Alt-Svc`, as described by
+//! [RFC 7838].
+//!
+//! [RFC 7838]: https://www.rfc-editor.org/rfc/rfc7838
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use http::header::{HeaderMap, ALT_SVC};
+use http::Uri;
+use log::trace;
+use tokio::time::Instant;
+
+// How long an advertisement lasts without `ma`.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+// How long an endpoint that couldn't be used is left alone.
+const BROKEN_FOR: Duration = Duration::from_secs(5 * 60);
+
+/// An `https` origin: its lowercase host and its port.
+type Origin = (String, u16);
+
+#[derive(Clone, Default)]
+pub(crate) struct AltSvc {
+    inner: Arc<Mutex<HashMap<Origin, Alternative>>>,
+}
+
+struct Alternative {
+    // `None` for the host of the origin.
+    host: Option<String>,
+    port: u16,
+    expires: Instant,
+    broken_until: Option<Instant>,
+}
+
+impl AltSvc {
+    pub(crate) fn new() -> AltSvc {
+        AltSvc::default()
+    }
+
+    /// Records the `Alt-Svc` header of a response from `uri`.
+    pub(crate) fn record(&self, uri: &Uri, headers: &HeaderMap) {
+        let origin = match origin(uri) {
+            Some(origin) => origin,
+            None => return,
+        };
+        let value = match headers.get(ALT_SVC).and_then(|value| value.to_str().ok()) {
+            Some(value) => value,
+            None => return,
+        };
+        let mut alternatives = self.inner.lock().unwrap();
+        match parse(value) {
+            Some(Advertised::Clear) => {
+                alternatives.remove(&origin);
+            }
+            Some(Advertised::H3 {
+                host,
+                port,
+                max_age,
+            }) => {
+                let now = Instant::now();
+                let broken_until = alternatives
+                    .get(&origin)
+                    .and_then(|alt| alt.broken_until)
+                    .filter(|until| *until > now);
+                trace!("{origin:?} advertises HTTP/3 on {host:?}:{port} for {max_age:?}");
+                alternatives.insert(
+                    origin,
+                    Alternative {
+                        host,
+                        port,
+                        expires: now + max_age,
+                        broken_until,
+                    },
+                );
+            }
+            None => (),
+        }
+    }
+
+    /// Whether requests to `uri` may be sent over HTTP/3.
+    pub(crate) fn is_advertised(&self, uri: &Uri) -> bool {
+        self.endpoint(uri).is_some()
+    }
+
+    /// The host, if not that of `uri`, and the port of the HTTP/3 endpoint
+    /// advertised for `uri`.
+    pub(crate) fn endpoint(&self, uri: &Uri) -> Option<(Option<String>, u16)> {
+        let origin = origin(uri)?;
+        let mut alternatives = self.inner.lock().unwrap();
+        let now = Instant::now();
+        let alt = alternatives.get(&origin)?;
+        if alt.expires <= now {
+            alternatives.remove(&origin);
+            return None;
+        }
+        if alt.broken_until.map_or(false, |until| until > now) {
+            return None;
+        }
+        Some((alt.host.clone(), alt.port))
+    }
+
+    /// Stops using the endpoint advertised for `uri` for a while, after a
+    /// request to it failed.
+    pub(crate) fn mark_broken(&self, uri: &Uri) {
+        let origin = match origin(uri) {
+            Some(origin) => origin,
+            None => return,
+        };
+        if let Some(alt) = self.inner.lock().unwrap().get_mut(&origin) {
+            trace!("HTTP/3 endpoint of {origin:?} is broken");
+            alt.broken_until = Some(Instant::now() + BROKEN_FOR);
+        }
+    }
+}
+
+fn origin(uri: &Uri) -> Option<Origin> {
+    if uri.scheme_str() != Some("https") {
+        return None;
+    }
+    let host = uri.host()?.to_ascii_lowercase();
+    Some((host, uri.port_u16().unwrap_or(443)))
+}
+
+#[derive(Debug, PartialEq)]
+enum Advertised {
+    Clear,
+    H3 {
+        host: Option<String>,
+        port: u16,
+        max_age: Duration,
+    },
+}
+
+/// Parses an `Alt-Svc` header, keeping its first `h3` alternative.
+fn parse(value: &str) -> Option<Advertised> {
+    if value.trim() == "clear" {
+        return Some(Advertised::Clear);
+    }
+    value.split(',').find_map(|alternative| {
+        let mut params = alternative.split(';');
+        let (protocol, authority) = params.next()?.split_once('=')?;
+        if protocol.trim() != "h3" {
+            return None;
+        }
+        let authority = authority.trim().strip_prefix('"')?.strip_suffix('"')?;
+        let (host, port) = authority.rsplit_once(':')?;
+        let port = port.parse().ok()?;
+        let host = (!host.is_empty()).then(|| host.to_ascii_lowercase());
+        let max_age = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim() == "ma")
+            .and_then(|(_, secs)| secs.trim().trim_matches('"').parse().ok())
+            .map_or(DEFAULT_MAX_AGE, Duration::from_secs);
+        Some(Advertised::H3 {
+            host,
+            port,
+            max_age,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_first_h3_alternative() {
+        assert_eq!(
+            parse(r#"h2=":443", h3=":8443"; ma=60, h3="other.test:443""#),
+            Some(Advertised::H3 {
+                host: None,
+                port: 8443,
+                max_age: Duration::from_secs(60),
+            })
+        );
+        assert_eq!(
+            parse(r#"h3="[::1]:443"; persist=1"#),
+            Some(Advertised::H3 {
+                host: Some("[::1]".into()),
+                port: 443,
+                max_age: DEFAULT_MAX_AGE,
+            })
+        );
+        assert_eq!(parse("clear"), Some(Advertised::Clear));
+        assert_eq!(parse(r#"h2=":443""#), None);
+        assert_eq!(parse(r#"h3=":quic""#), None);
+    }
+
+    #[test]
+    fn forgets_broken_and_cleared_endpoints() {
+        let alt_svc = AltSvc::new();
+        let uri = Uri::from_static("https://example.test/");
+        let mut headers = HeaderMap::new();
+        headers.insert(ALT_SVC, r#"h3=":8443""#.parse().unwrap());
+
+        alt_svc.record(&Uri::from_static("http://example.test/"), &headers);
+        assert!(!alt_svc.is_advertised(&uri));
+
+        alt_svc.record(&uri, &headers);
+        assert_eq!(alt_svc.endpoint(&uri), Some((None, 8443)));
+        assert!(!alt_svc.is_advertised(&Uri::from_static("https://example.test:8443/")));
+
+        alt_svc.mark_broken(&uri);
+        assert!(!alt_svc.is_advertised(&uri));
+        alt_svc.record(&uri, &headers);
+        assert!(!alt_svc.is_advertised(&uri));
+
+        headers.insert(ALT_SVC, "clear".parse().unwrap());
+        alt_svc.record(&uri, &headers);
+        headers.insert(ALT_SVC, r#"h3=":8443""#.parse().unwrap());
+        alt_svc.record(&uri, &headers);
+        assert!(alt_svc.is_advertised(&uri));
+    }
+}
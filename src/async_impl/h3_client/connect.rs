@@ -89,13 +89,25 @@ impl H3Connector {
         })
     }
 
-    pub async fn connect(&mut self, dest: Uri) -> Result<H3Connection, BoxError> {
-        let host = dest
+    /// Connects to `dest`, or to the `alternative` host and port advertised
+    /// for it, where the certificate is still verified against `dest`.
+    pub async fn connect(
+        &mut self,
+        dest: Uri,
+        alternative: Option<(Option<String>, u16)>,
+    ) -> Result<H3Connection, BoxError> {
+        let server_name = dest
             .host()
             .ok_or("destination must have a host")?
             .trim_start_matches('[')
             .trim_end_matches(']');
-        let port = dest.port_u16().unwrap_or(443);
+        let (host, port) = match alternative {
+            Some((host, port)) => (host, port),
+            None => (None, dest.port_u16().unwrap_or(443)),
+        };
+        let host = host.as_deref().map_or(server_name, |host| {
+            host.trim_start_matches('[').trim_end_matches(']')
+        });
 
         let addrs = if let Some(addr) = IpAddr::from_str(host).ok() {
             // If the host is already an IP address, skip resolving.
@@ -109,7 +121,7 @@ impl H3Connector {
             addrs.collect()
         };
 
-        self.remote_connect(addrs, host).await
+        self.remote_connect(addrs, server_name).await
     }
 
     async fn remote_connect(
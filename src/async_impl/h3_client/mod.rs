@@ -1,11 +1,15 @@
 #![cfg(feature = "http3")]
 
+pub(crate) mod alt_svc;
 pub(crate) mod connect;
 pub(crate) mod dns;
 mod pool;
 
 use crate::async_impl::body::ResponseBody;
+use crate::async_impl::h3_client::alt_svc::AltSvc;
 use crate::async_impl::h3_client::pool::{Key, Pool, PoolClient};
+use crate::async_impl::throttle::throttled;
+use crate::config::{RequestConfig, UploadRate};
 use crate::error::{BoxError, Error, Kind};
 use crate::{error, Body};
 use connect::H3Connector;
@@ -22,16 +26,27 @@ use tower::Service;
 pub(crate) struct H3Client {
     pool: Pool,
     connector: H3Connector,
+    alt_svc: Option<AltSvc>,
 }
 
 impl H3Client {
-    pub fn new(connector: H3Connector, pool_timeout: Option<Duration>) -> Self {
+    pub fn new(
+        connector: H3Connector,
+        pool_timeout: Option<Duration>,
+        alt_svc: Option<AltSvc>,
+    ) -> Self {
         H3Client {
             pool: Pool::new(pool_timeout),
             connector,
+            alt_svc,
         }
     }
 
+    /// Drops the idle connections of the pool.
+    pub fn clear_pool(&self) {
+        self.pool.clear();
+    }
+
     async fn get_pooled_client(&mut self, key: Key) -> Result<PoolClient, BoxError> {
         if let Some(client) = self.pool.try_pool(&key) {
             trace!("getting client from pool with key {key:?}");
@@ -53,8 +68,12 @@ impl H3Client {
             }
             pool::Connecting::Acquired(lock) => lock,
         };
-        trace!("connecting to {key:?}...");
-        let (driver, tx) = self.connector.connect(dest).await?;
+        let alternative = self
+            .alt_svc
+            .as_ref()
+            .and_then(|alt_svc| alt_svc.endpoint(&dest));
+        trace!("connecting to {key:?} (alternative {alternative:?})...");
+        let (driver, tx) = self.connector.connect(dest, alternative).await?;
         trace!("saving new pooled connection to {key:?}");
         Ok(self.pool.new_connection(lock, driver, tx))
     }
@@ -99,6 +118,13 @@ impl Service<Request<Body>> for H3Client {
     }
 
     fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let req = match RequestConfig::<UploadRate>::get(req.extensions()) {
+            Some(throttle) => {
+                let throttle = throttle.clone();
+                req.map(|body| Body::wrap(throttled(body, throttle)))
+            }
+            None => req,
+        };
         self.request(req)
     }
 }
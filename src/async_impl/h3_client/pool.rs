@@ -111,6 +111,11 @@ impl Pool {
         }
     }
 
+    /// Drops the idle connections, so that new ones are made.
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().idle_conns.clear();
+    }
+
     pub fn try_pool(&self, key: &Key) -> Option<PoolClient> {
         let mut inner = self.inner.lock().unwrap();
         let timeout = inner.timeout;
@@ -0,0 +1,38 @@
+//! Callbacks for interim `1xx` responses.
+
+use std::fmt;
+use std::sync::Arc;
+
+use http::{HeaderMap, StatusCode};
+
+type Callback = dyn Fn(StatusCode, &HeaderMap) + Send + Sync;
+
+/// A callback receiving the status and headers of each interim response,
+/// such as `103 Early Hints`, received before the final one.
+#[derive(Clone)]
+pub(crate) struct Informational(Arc<Callback>);
+
+impl Informational {
+    pub(crate) fn new<F>(callback: F) -> Informational
+    where
+        F: Fn(StatusCode, &HeaderMap) + Send + Sync + 'static,
+    {
+        Informational(Arc::new(callback))
+    }
+
+    pub(crate) fn call(&self, status: StatusCode, headers: &HeaderMap) {
+        (self.0)(status, headers)
+    }
+
+    /// Register the callback with hyper on a request about to be sent.
+    pub(crate) fn register<B>(&self, req: &mut hyper::Request<B>) {
+        let callback = self.0.clone();
+        hyper::ext::on_informational(req, move |res| callback(res.status(), res.headers()));
+    }
+}
+
+impl fmt::Debug for Informational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Informational")
+    }
+}
@@ -0,0 +1,102 @@
+//! The `on_request` and `on_response` hooks of a `ClientBuilder`.
+//!
+//! Each hook is a layer like the ones added with `ClientBuilder::layer`, so
+//! hooks and layers wrap each other in the order they were added.
+
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tower::{Layer, Service};
+
+use super::Body;
+use crate::error::BoxError;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+type RequestHook = dyn Fn(http::Request<Body>) -> BoxFuture<ControlFlow<http::Response<Body>, http::Request<Body>>>
+    + Send
+    + Sync;
+
+type ResponseHook = dyn Fn(http::Response<Body>) -> BoxFuture<http::Response<Body>> + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) enum Hook {
+    Request(Arc<RequestHook>),
+    Response(Arc<ResponseHook>),
+}
+
+impl Hook {
+    pub(crate) fn on_request<F, Fut>(hook: F) -> Hook
+    where
+        F: Fn(http::Request<Body>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ControlFlow<http::Response<Body>, http::Request<Body>>>
+            + Send
+            + 'static,
+    {
+        Hook::Request(Arc::new(move |req| Box::pin(hook(req))))
+    }
+
+    pub(crate) fn on_response<F, Fut>(hook: F) -> Hook
+    where
+        F: Fn(http::Response<Body>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = http::Response<Body>> + Send + 'static,
+    {
+        Hook::Response(Arc::new(move |res| Box::pin(hook(res))))
+    }
+}
+
+impl<S> Layer<S> for Hook {
+    type Service = Intercept<S>;
+
+    fn layer(&self, inner: S) -> Intercept<S> {
+        Intercept {
+            hook: self.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Intercept<S> {
+    hook: Hook,
+    inner: S,
+}
+
+impl<S> Service<http::Request<Body>> for Intercept<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>, Error = BoxError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = http::Response<Body>;
+    type Error = BoxError;
+    type Future = BoxFuture<Result<http::Response<Body>, BoxError>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        // Keep the service that was polled ready for this request.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        match self.hook.clone() {
+            Hook::Request(hook) => Box::pin(async move {
+                match hook(req).await {
+                    ControlFlow::Continue(req) => inner.call(req).await,
+                    ControlFlow::Break(res) => Ok(res),
+                }
+            }),
+            Hook::Response(hook) => Box::pin(async move {
+                let res = inner.call(req).await?;
+                Ok(hook(res).await)
+            }),
+        }
+    }
+}
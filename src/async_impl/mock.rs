@@ -0,0 +1,67 @@
+//! An in-process transport, answering requests with a function instead of
+//! sending them over the network.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use tower::Service;
+
+use super::body::{self, Body, ResponseBody};
+use crate::error::BoxError;
+
+type Handler =
+    dyn Fn(http::Request<Bytes>) -> Result<http::Response<Bytes>, BoxError> + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct MockTransport {
+    handler: Arc<Handler>,
+}
+
+impl MockTransport {
+    pub(crate) fn new<F>(handler: F) -> MockTransport
+    where
+        F: Fn(http::Request<Bytes>) -> Result<http::Response<Bytes>, BoxError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        MockTransport {
+            handler: Arc::new(handler),
+        }
+    }
+}
+
+impl fmt::Debug for MockTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MockTransport").finish()
+    }
+}
+
+impl Service<http::Request<Body>> for MockTransport {
+    type Response = http::Response<ResponseBody>;
+    type Error = crate::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + Sync>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        let handler = self.handler.clone();
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = BodyExt::collect(body)
+                .await
+                .map_err(crate::error::request)?
+                .to_bytes();
+            let res =
+                handler(http::Request::from_parts(parts, body)).map_err(crate::error::request)?;
+            Ok(res.map(|body| body::boxed(Full::new(body))))
+        })
+    }
+}
@@ -4,11 +4,27 @@ pub use self::request::{Request, RequestBuilder};
 pub use self::response::Response;
 pub use self::upgrade::Upgraded;
 
+pub(crate) mod batch;
 pub mod body;
+pub(crate) mod buffer_pool;
 pub mod client;
+pub(crate) mod curl;
+#[cfg(feature = "stream")]
+pub(crate) mod download;
+pub(crate) mod expect_continue;
 pub mod h3_client;
+pub(crate) mod informational;
+pub(crate) mod intercept;
+pub(crate) mod mock;
 #[cfg(feature = "multipart")]
 pub mod multipart;
+pub(crate) mod network_change;
+pub(crate) mod progress;
+pub(crate) mod rate_limit;
 pub(crate) mod request;
 mod response;
+pub(crate) mod scoped_headers;
+#[cfg(feature = "sse")]
+pub mod sse;
+pub(crate) mod throttle;
 mod upgrade;
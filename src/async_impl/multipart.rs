@@ -1,4 +1,4 @@
-//! multipart/form-data
+//! multipart/form-data requests and multipart responses
 use std::borrow::Cow;
 use std::fmt;
 use std::pin::Pin;
@@ -8,7 +8,7 @@ use std::io;
 #[cfg(feature = "stream")]
 use std::path::Path;
 
-use bytes::Bytes;
+use bytes::{Buf, Bytes, BytesMut};
 use mime_guess::Mime;
 use percent_encoding::{self, AsciiSet, NON_ALPHANUMERIC};
 #[cfg(feature = "stream")]
@@ -18,8 +18,8 @@ use futures_core::Stream;
 use futures_util::{future, stream, StreamExt};
 use http_body_util::BodyExt;
 
-use super::Body;
-use crate::header::HeaderMap;
+use super::{Body, Response};
+use crate::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
 
 /// An async multipart/form-data request.
 pub struct Form {
@@ -46,6 +46,35 @@ pub(crate) struct PartMetadata {
     pub(crate) headers: HeaderMap,
 }
 
+/// The parts of a multipart response, such as `multipart/mixed` or
+/// `multipart/byteranges`.
+///
+/// Created by [`Response::multipart`]. Parts are read one at a time, in
+/// order; reading the next part skips whatever is left of the current one.
+pub struct MultipartResponse {
+    res: Response,
+    boundary: String,
+    // "\r\n--" followed by the boundary
+    delimiter: Vec<u8>,
+    buf: BytesMut,
+    state: State,
+}
+
+/// A part of a [`MultipartResponse`].
+pub struct ResponsePart<'a> {
+    headers: HeaderMap,
+    multipart: &'a mut MultipartResponse,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Preamble,
+    Delimiter,
+    Headers,
+    Body,
+    End,
+}
+
 pub(crate) trait PartProps {
     fn value_len(&self) -> Option<u64>;
     fn metadata(&self) -> &PartMetadata;
@@ -265,8 +294,27 @@ impl Part {
         Part::new(value.into(), Some(length))
     }
 
+    /// Makes a new parameter that streams the contents of `reader`.
+    ///
+    /// The reader is read as the request body is sent, so its contents are
+    /// never buffered in full. When `length` is given, the `Form` can compute
+    /// its `Content-Length` instead of using chunked transfer encoding; it
+    /// must then be the exact number of bytes the reader produces.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub fn async_read<R>(reader: R, length: Option<u64>) -> Part
+    where
+        R: tokio::io::AsyncRead + Send + 'static,
+    {
+        let body = Body::wrap_stream(tokio_util::io::ReaderStream::new(reader));
+        Part::new(body, length)
+    }
+
     /// Makes a file parameter.
     ///
+    /// The file is streamed as the request is sent, and its size is used as
+    /// the length of the part when it is known.
+    ///
     /// # Errors
     ///
     /// Errors when the file cannot be opened.
@@ -281,11 +329,7 @@ impl Part {
         let mime = mime_guess::from_ext(ext).first_or_octet_stream();
         let file = File::open(path).await?;
         let len = file.metadata().await.map(|m| m.len()).ok();
-        let field = match len {
-            Some(len) => Part::stream_with_length(file, len),
-            None => Part::stream(file),
-        }
-        .mime(mime);
+        let field = Part::async_read(file, len).mime(mime);
 
         Ok(if let Some(file_name) = file_name {
             field.file_name(file_name)
@@ -588,6 +632,228 @@ impl PercentEncoding {
     }
 }
 
+// ===== impl MultipartResponse =====
+
+impl MultipartResponse {
+    pub(crate) fn new(res: Response) -> crate::Result<MultipartResponse> {
+        let boundary = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<Mime>().ok())
+            .filter(|mime| mime.type_() == mime_guess::mime::MULTIPART)
+            .and_then(|mime| {
+                mime.get_param(mime_guess::mime::BOUNDARY)
+                    .map(|boundary| boundary.as_str().to_owned())
+            })
+            .ok_or_else(|| crate::error::decode("response is not multipart"))?;
+
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(boundary.as_bytes());
+        Ok(MultipartResponse {
+            res,
+            boundary,
+            delimiter,
+            buf: BytesMut::new(),
+            state: State::Preamble,
+        })
+    }
+
+    /// Get the boundary that separates the parts.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Read the headers of the next part.
+    ///
+    /// When all parts have been read, this will return `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::get("https://hyper.rs/batch").await?;
+    /// let mut parts = res.multipart()?;
+    ///
+    /// while let Some(mut part) = parts.next_part().await? {
+    ///     println!("Part: {:?}", part.headers());
+    ///     while let Some(chunk) = part.chunk().await? {
+    ///         println!("Chunk: {chunk:?}");
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn next_part(&mut self) -> crate::Result<Option<ResponsePart<'_>>> {
+        loop {
+            match self.state {
+                State::Preamble => {
+                    // The first delimiter may start the body, without a
+                    // preceding line break.
+                    if self.buf.starts_with(&self.delimiter[2..]) {
+                        self.buf.advance(self.delimiter.len() - 2);
+                        self.state = State::Delimiter;
+                        continue;
+                    }
+                    if let Some(i) = find(&self.buf, &self.delimiter) {
+                        self.buf.advance(i + self.delimiter.len());
+                        self.state = State::Delimiter;
+                        continue;
+                    }
+                    // Keep what could be the start of a delimiter.
+                    let keep = self.delimiter.len() - 1;
+                    if self.buf.len() > keep {
+                        self.buf.advance(self.buf.len() - keep);
+                    }
+                    self.fill("missing multipart boundary").await?;
+                }
+                State::Delimiter => {
+                    if self.buf.starts_with(b"--") {
+                        self.state = State::End;
+                    } else if let Some(i) = find(&self.buf, b"\r\n") {
+                        // skip any transport padding after the boundary
+                        self.buf.advance(i + 2);
+                        self.state = State::Headers;
+                    } else {
+                        self.fill("incomplete multipart boundary").await?;
+                    }
+                }
+                State::Headers => {
+                    let (headers, len) = if self.buf.starts_with(b"\r\n") {
+                        (HeaderMap::new(), 2)
+                    } else if let Some(i) = find(&self.buf, b"\r\n\r\n") {
+                        (parse_headers(&self.buf[..i])?, i + 4)
+                    } else {
+                        self.fill("incomplete multipart headers").await?;
+                        continue;
+                    };
+                    self.buf.advance(len);
+                    self.state = State::Body;
+                    return Ok(Some(ResponsePart {
+                        headers,
+                        multipart: self,
+                    }));
+                }
+                State::Body => while self.body_chunk().await?.is_some() {},
+                State::End => return Ok(None),
+            }
+        }
+    }
+
+    async fn body_chunk(&mut self) -> crate::Result<Option<Bytes>> {
+        while self.state == State::Body {
+            if let Some(i) = find(&self.buf, &self.delimiter) {
+                let chunk = self.buf.split_to(i).freeze();
+                self.buf.advance(self.delimiter.len());
+                self.state = State::Delimiter;
+                if !chunk.is_empty() {
+                    return Ok(Some(chunk));
+                }
+                break;
+            }
+            // Hold back what could be the start of a delimiter.
+            let ready = self.buf.len().saturating_sub(self.delimiter.len() - 1);
+            if ready > 0 {
+                return Ok(Some(self.buf.split_to(ready).freeze()));
+            }
+            self.fill("incomplete multipart body").await?;
+        }
+        Ok(None)
+    }
+
+    async fn fill(&mut self, eof_msg: &'static str) -> crate::Result<()> {
+        match self.res.chunk().await? {
+            Some(chunk) => {
+                self.buf.extend_from_slice(&chunk);
+                Ok(())
+            }
+            None => Err(crate::error::decode(eof_msg)),
+        }
+    }
+}
+
+impl fmt::Debug for MultipartResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MultipartResponse")
+            .field("url", self.res.url())
+            .field("boundary", &self.boundary)
+            .finish()
+    }
+}
+
+// ===== impl ResponsePart =====
+
+impl ResponsePart<'_> {
+    /// Get the headers of this part.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Stream a chunk of the body of this part.
+    ///
+    /// When the body of this part has been exhausted, this will return
+    /// `None`.
+    pub async fn chunk(&mut self) -> crate::Result<Option<Bytes>> {
+        self.multipart.body_chunk().await
+    }
+
+    /// Get the full body of this part as `Bytes`.
+    pub async fn bytes(mut self) -> crate::Result<Bytes> {
+        let mut body = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    }
+
+    /// Get the full body of this part as text.
+    ///
+    /// Invalid UTF-8 sequences are replaced with
+    /// [`char::REPLACEMENT_CHARACTER`].
+    pub async fn text(self) -> crate::Result<String> {
+        let body = self.bytes().await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+impl fmt::Debug for ResponsePart<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResponsePart")
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn parse_headers(block: &[u8]) -> crate::Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for line in block.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let colon = line
+            .iter()
+            .position(|&b| b == b':')
+            .ok_or_else(|| crate::error::decode("invalid multipart part header"))?;
+        let name = HeaderName::from_bytes(&line[..colon]).map_err(crate::error::decode)?;
+        let value = &line[colon + 1..];
+        let start = value
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(value.len());
+        let end = value
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+            .map_or(start, |i| i + 1);
+        let value = HeaderValue::from_bytes(&value[start..end]).map_err(crate::error::decode)?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
 fn gen_boundary() -> String {
     use crate::util::fast_random as random;
 
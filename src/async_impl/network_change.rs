@@ -0,0 +1,66 @@
+//! Noticing that the network a client is on changed.
+//!
+//! The system is asked which local address it would send packets to the
+//! internet from, over IPv4 and IPv6. Connecting a UDP socket sends
+//! nothing, it only picks a route, so this is cheap enough to do before
+//! requests. The address changes when the machine moves to another network,
+//! or when a VPN comes up or goes down, which is when pooled connections
+//! are likely dead or going the wrong way.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+// How often the route is looked up at most.
+const CHECK_EVERY: Duration = Duration::from_secs(1);
+
+/// The local addresses of the routes to the internet.
+type Routes = (Option<IpAddr>, Option<IpAddr>);
+
+pub(crate) struct NetworkWatch {
+    last: Mutex<(Instant, Routes)>,
+}
+
+impl NetworkWatch {
+    pub(crate) fn new() -> NetworkWatch {
+        NetworkWatch {
+            last: Mutex::new((Instant::now(), routes())),
+        }
+    }
+
+    /// Whether the network changed since the last time it was looked at.
+    pub(crate) fn changed(&self) -> bool {
+        let mut last = self.last.lock().expect("network watch lock poisoned");
+        let now = Instant::now();
+        if now.duration_since(last.0) < CHECK_EVERY {
+            return false;
+        }
+        let routes = routes();
+        let changed = routes != last.1;
+        if changed {
+            log::debug!("network changed: routes from {:?} to {:?}", last.1, routes);
+        }
+        *last = (now, routes);
+        changed
+    }
+}
+
+fn routes() -> Routes {
+    // Addresses reached through the default route, only used to pick it.
+    // They are reserved for documentation, so nothing is ever sent there.
+    let v4 = Ipv4Addr::new(192, 0, 2, 1);
+    let v6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+    (local_addr((v4, 9).into()), local_addr((v6, 9).into()))
+}
+
+fn local_addr(probe: SocketAddr) -> Option<IpAddr> {
+    let any: SocketAddr = match probe {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(any).ok()?;
+    socket.connect(probe).ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
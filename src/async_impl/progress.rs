@@ -0,0 +1,88 @@
+//! Progress reporting for request and response bodies.
+//!
+//! A [`ProgressBody`] counts the data frames passing through it and reports
+//! the running total, along with the size of the whole body when it is
+//! known, to a [`Progress`] callback.
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use bytes::Bytes;
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use pin_project_lite::pin_project;
+
+/// A callback receiving the number of bytes transferred so far, and the total
+/// number of bytes if it is known.
+#[derive(Clone)]
+pub(crate) struct Progress(Arc<dyn Fn(u64, Option<u64>) + Send + Sync>);
+
+impl Progress {
+    pub(crate) fn new<F>(callback: F) -> Progress
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        Progress(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Progress")
+    }
+}
+
+pin_project! {
+    pub(crate) struct ProgressBody<B> {
+        #[pin]
+        inner: B,
+        progress: Progress,
+        transferred: u64,
+        total: Option<u64>,
+    }
+}
+
+pub(crate) fn with_progress<B>(body: B, progress: Progress, total: Option<u64>) -> ProgressBody<B> {
+    ProgressBody {
+        inner: body,
+        progress,
+        transferred: 0,
+        total,
+    }
+}
+
+impl<B> HttpBody for ProgressBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if let Some(Ok(ref frame)) = frame {
+            if let Some(data) = frame.data_ref() {
+                if !data.is_empty() {
+                    *this.transferred += data.len() as u64;
+                    (this.progress.0)(*this.transferred, *this.total);
+                }
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,181 @@
+//! Limiting the rate at which a client sends requests.
+//!
+//! Each request takes a token from a [`Throttle`] counting requests instead
+//! of bytes, one shared by the whole client and one per host. A request that
+//! finds a bucket empty still takes its token, putting the bucket in debt,
+//! and waits until that debt is paid off, so requests queue up in the order
+//! they were made.
+//!
+//! A host's throttle is dropped once its bucket has refilled, since a new one
+//! would behave the same, so a client visiting many hosts only keeps the
+//! throttles of those it sent requests to within the last second or so.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use pin_project_lite::pin_project;
+use tokio::time::Sleep;
+use tower::Service;
+
+use super::throttle::Throttle;
+
+#[derive(Clone)]
+pub(crate) struct RateLimit {
+    global: Option<Throttle>,
+    per_host: Option<PerHost>,
+}
+
+#[derive(Clone)]
+struct PerHost {
+    rate: u64,
+    hosts: Arc<Mutex<HashMap<String, Throttle>>>,
+}
+
+impl RateLimit {
+    pub(crate) fn new(global: Option<u32>, per_host: Option<u32>) -> RateLimit {
+        RateLimit {
+            global: global.map(|rate| Throttle::new(rate.into())),
+            per_host: per_host.map(|rate| PerHost {
+                rate: rate.into(),
+                hosts: Arc::new(Mutex::new(HashMap::new())),
+            }),
+        }
+    }
+
+    /// Take a token for a request to `uri`, returning how long to wait
+    /// before sending it.
+    fn reserve(&self, uri: &http::Uri) -> Option<Duration> {
+        let global = self
+            .global
+            .as_ref()
+            .and_then(|throttle| throttle.consume(1));
+        let host = self.per_host.as_ref().and_then(|per_host| {
+            let key = uri.authority().map_or("", |authority| authority.as_str());
+            let mut hosts = per_host.hosts.lock().expect("rate limit lock poisoned");
+            if !hosts.contains_key(key) {
+                hosts.retain(|_, throttle| !throttle.is_full());
+            }
+            let throttle = hosts
+                .entry(key.to_owned())
+                .or_insert_with(|| Throttle::new(per_host.rate));
+            throttle.consume(1)
+        });
+        global.max(host)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct RateLimited<S> {
+    inner: S,
+    limit: RateLimit,
+}
+
+impl<S> RateLimited<S> {
+    pub(crate) fn new(inner: S, limit: RateLimit) -> RateLimited<S> {
+        RateLimited { inner, limit }
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for RateLimited<S>
+where
+    S: Service<http::Request<B>> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RateLimitedFuture<S, http::Request<B>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        match self.limit.reserve(req.uri()) {
+            Some(wait) => {
+                // Keep the service that was polled ready for this request.
+                let clone = self.inner.clone();
+                let inner = std::mem::replace(&mut self.inner, clone);
+                RateLimitedFuture::Waiting {
+                    sleep: Box::pin(tokio::time::sleep(wait)),
+                    call: Some((inner, req)),
+                }
+            }
+            None => RateLimitedFuture::Called {
+                future: self.inner.call(req),
+            },
+        }
+    }
+}
+
+pin_project! {
+    #[project = RateLimitedProj]
+    pub(crate) enum RateLimitedFuture<S, Req>
+    where
+        S: Service<Req>,
+    {
+        Waiting {
+            sleep: Pin<Box<Sleep>>,
+            call: Option<(S, Req)>,
+        },
+        Called {
+            #[pin]
+            future: S::Future,
+        },
+    }
+}
+
+impl<S, Req> Future for RateLimitedFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match self.as_mut().project() {
+                RateLimitedProj::Waiting { sleep, call } => {
+                    ready!(sleep.as_mut().poll(cx));
+                    let (mut inner, req) = call.take().expect("polled after completion");
+                    let future = inner.call(req);
+                    self.set(RateLimitedFuture::Called { future });
+                }
+                RateLimitedProj::Called { future } => return future.poll(cx),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host_count(limit: &RateLimit) -> usize {
+        let per_host = limit.per_host.as_ref().unwrap();
+        per_host.hosts.lock().unwrap().len()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_hosts_are_forgotten() {
+        let limit = RateLimit::new(None, Some(1));
+        let a: http::Uri = "http://a.test/".parse().unwrap();
+        let b: http::Uri = "http://b.test/".parse().unwrap();
+
+        assert_eq!(limit.reserve(&a), None);
+        assert_eq!(limit.reserve(&a), Some(Duration::from_secs(1)));
+        assert_eq!(limit.reserve(&b), None);
+        assert_eq!(host_count(&limit), 2);
+
+        // a.test still owes a token, b.test doesn't
+        tokio::time::advance(Duration::from_millis(1500)).await;
+        assert_eq!(limit.reserve(&"http://c.test/".parse().unwrap()), None);
+        assert_eq!(host_count(&limit), 2);
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(limit.reserve(&"http://d.test/".parse().unwrap()), None);
+        assert_eq!(host_count(&limit), 1);
+    }
+}
@@ -1,7 +1,8 @@
 use std::convert::TryFrom;
 use std::fmt;
 use std::future::Future;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[cfg(any(feature = "query", feature = "form", feature = "json"))]
 use serde::Serialize;
@@ -10,16 +11,29 @@ use serde_json;
 
 use super::body::Body;
 use super::client::{Client, Pending};
+use super::informational::Informational;
 #[cfg(feature = "multipart")]
 use super::multipart;
+use super::progress::Progress;
 use super::response::Response;
-use crate::config::{RequestConfig, TotalTimeout};
+use super::throttle::Throttle;
+use crate::cache::CachedResponse;
+#[cfg(feature = "har")]
+use crate::config::Har;
+#[cfg(feature = "__rustls")]
+use crate::config::TlsVerification;
+use crate::config::{
+    Deadline, DownloadProgress, DownloadRate, ExpectContinue, IfCached, OnInformational,
+    ReadTimeout, RedirectPolicy, RequestConfig, TotalTimeout, UploadProgress, UploadRate,
+};
 #[cfg(feature = "multipart")]
 use crate::header::CONTENT_LENGTH;
 #[cfg(any(feature = "multipart", feature = "form", feature = "json"))]
 use crate::header::CONTENT_TYPE;
 use crate::header::{HeaderMap, HeaderName, HeaderValue};
-use crate::{Method, Url};
+#[cfg(feature = "__rustls")]
+use crate::tls::TlsOverride;
+use crate::{redirect, Method, StatusCode, Url};
 use http::{request::Parts, Extensions, Request as HttpRequest, Version};
 
 /// A request which can be executed with `Client::execute()`.
@@ -127,6 +141,18 @@ impl Request {
         RequestConfig::<TotalTimeout>::get_mut(&mut self.extensions)
     }
 
+    /// Get the deadline.
+    #[inline]
+    pub fn deadline(&self) -> Option<&Instant> {
+        RequestConfig::<Deadline>::get(&self.extensions)
+    }
+
+    /// Get a mutable reference to the deadline.
+    #[inline]
+    pub fn deadline_mut(&mut self) -> &mut Option<Instant> {
+        RequestConfig::<Deadline>::get_mut(&mut self.extensions)
+    }
+
     /// Get the http version.
     #[inline]
     pub fn version(&self) -> Version {
@@ -278,6 +304,51 @@ impl RequestBuilder {
         self.header_sensitive(crate::header::AUTHORIZATION, header_value, true)
     }
 
+    /// Set the priority of this request, as the `Priority` header of
+    /// RFC 9218.
+    ///
+    /// `urgency` ranges from `0`, the most urgent, to `7`; servers treat
+    /// requests without a priority as `3`. An `incremental` response is
+    /// useful to the client as it arrives, so the server may interleave it
+    /// with other incremental responses of the same urgency rather than
+    /// sending them one after the other.
+    ///
+    /// Servers that implement RFC 9218 schedule the streams of a shared
+    /// HTTP/2 or HTTP/3 connection by this priority, which lets interactive
+    /// requests go ahead of bulk transfers. Over HTTP/1.1 it is only a hint.
+    ///
+    /// # Errors
+    ///
+    /// Sending the request fails if `urgency` is greater than `7`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::Client::new()
+    ///     .get("https://hyper.rs/search?q=reqwest")
+    ///     .priority(1, false)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn priority(mut self, urgency: u8, incremental: bool) -> RequestBuilder {
+        let mut error = None;
+        if let Ok(ref mut req) = self.request {
+            match crate::util::priority(urgency, incremental) {
+                Ok(value) => {
+                    req.headers_mut().insert(crate::util::PRIORITY, value);
+                }
+                Err(err) => error = Some(err),
+            }
+        }
+        if let Some(err) = error {
+            self.request = Err(err);
+        }
+        self
+    }
+
     /// Set the request body.
     pub fn body<T: Into<Body>>(mut self, body: T) -> RequestBuilder {
         if let Ok(ref mut req) = self.request {
@@ -298,6 +369,341 @@ impl RequestBuilder {
         self
     }
 
+    /// Sets a deadline for this request.
+    ///
+    /// Unlike a timeout, the deadline is a fixed point in time: every attempt
+    /// made on behalf of this request, including redirects, retries and
+    /// authentication round-trips, must finish reading the response body
+    /// before it passes. No further retries are started once it has passed.
+    /// If a timeout is also set, whichever ends first applies.
+    ///
+    /// The deadline is also visible to inner services as a request extension,
+    /// so that middleware can take the remaining time into account.
+    pub fn deadline(mut self, deadline: Instant) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.deadline_mut() = Some(deadline);
+        }
+        self
+    }
+
+    /// Enables a read timeout.
+    ///
+    /// The timeout applies to each read operation, and resets after a
+    /// successful read. It affects only this request and overrides the
+    /// timeout configured using `ClientBuilder::read_timeout()`.
+    pub fn read_timeout(mut self, timeout: Duration) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<ReadTimeout>::get_mut(req.extensions_mut()) = Some(timeout);
+        }
+        self
+    }
+
+    /// Set the redirect `Policy` for this request only.
+    ///
+    /// It replaces the policy configured using `ClientBuilder::redirect()`,
+    /// for instance to not follow redirects when fetching a presigned URL
+    /// from a client that otherwise does.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::Client::new()
+    ///     .get("https://hyper.rs")
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn redirect(mut self, policy: redirect::Policy) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<RedirectPolicy>::get_mut(req.extensions_mut()) =
+                Some(Arc::new(policy));
+        }
+        self
+    }
+
+    /// Limit the rate at which the response body is read, in bytes per second.
+    ///
+    /// The limit applies only to this request and replaces the one configured
+    /// using `ClientBuilder::max_download_rate()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn max_download_rate(mut self, bytes_per_sec: u64) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<DownloadRate>::get_mut(req.extensions_mut()) =
+                Some(Throttle::new(bytes_per_sec));
+        }
+        self
+    }
+
+    /// Limit the rate at which the request body is sent, in bytes per second.
+    ///
+    /// The limit applies only to this request and replaces the one configured
+    /// using `ClientBuilder::max_upload_rate()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn max_upload_rate(mut self, bytes_per_sec: u64) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<UploadRate>::get_mut(req.extensions_mut()) =
+                Some(Throttle::new(bytes_per_sec));
+        }
+        self
+    }
+
+    /// Call `callback` as the response body is read, with the number of bytes
+    /// received so far and the size of the whole body if it is known.
+    ///
+    /// The size is known when the response has a `Content-Length` and isn't
+    /// decompressed. Bytes are counted as they are handed to the caller, so
+    /// the callback isn't called for a body that is never read.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let body = reqwest::Client::new()
+    ///     .get("https://hyper.rs")
+    ///     .on_download_progress(|received, total| match total {
+    ///         Some(total) => println!("{received}/{total} bytes"),
+    ///         None => println!("{received} bytes"),
+    ///     })
+    ///     .send()
+    ///     .await?
+    ///     .bytes()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_download_progress<F>(mut self, callback: F) -> RequestBuilder
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<DownloadProgress>::get_mut(req.extensions_mut()) =
+                Some(Progress::new(callback));
+        }
+        self
+    }
+
+    /// Call `callback` as the request body is sent, with the number of bytes
+    /// sent so far and the size of the whole body if it is known.
+    ///
+    /// Bytes are counted as they are handed to the connection, so this
+    /// includes streamed bodies and multipart forms. If the body is sent
+    /// again, such as after a redirect, the count starts over.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::Client::new()
+    ///     .post("https://hyper.rs")
+    ///     .body(vec![0; 1024 * 1024])
+    ///     .on_upload_progress(|sent, total| match total {
+    ///         Some(total) => println!("{sent}/{total} bytes"),
+    ///         None => println!("{sent} bytes"),
+    ///     })
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_upload_progress<F>(mut self, callback: F) -> RequestBuilder
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<UploadProgress>::get_mut(req.extensions_mut()) =
+                Some(Progress::new(callback));
+        }
+        self
+    }
+
+    /// Send the request with `Expect: 100-continue`, holding the body back
+    /// until the server answers with `100 Continue`.
+    ///
+    /// This lets a server refuse a request, for instance with a `401` asking
+    /// for authentication, before a large body is uploaded for nothing. If the
+    /// server doesn't answer within `timeout`, the body is sent anyway, since
+    /// not every server supports the expectation. If the final response
+    /// arrives first, the body is never sent and the connection is closed
+    /// once the response is dropped.
+    ///
+    /// Requests without a body are sent as usual. `100 Continue` is only
+    /// received on HTTP/1.1 connections; on other versions the body is sent
+    /// once the timeout elapses.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::Client::new()
+    ///     .put("https://hyper.rs/upload")
+    ///     .body(vec![0; 64 * 1024 * 1024])
+    ///     .expect_continue(Duration::from_secs(1))
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expect_continue(mut self, timeout: Duration) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<ExpectContinue>::get_mut(req.extensions_mut()) = Some(timeout);
+        }
+        self
+    }
+
+    /// Call `callback` with the status and headers of each interim `1xx`
+    /// response received before the final response, such as
+    /// `103 Early Hints`.
+    ///
+    /// This lets a client act on the `Link` headers of early hints, for
+    /// instance to preconnect or prefetch, while the server is still
+    /// preparing the final response. Interim responses are only reported on
+    /// HTTP/1.1 connections; `101 Switching Protocols` is a final response
+    /// and isn't reported here.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::Client::new()
+    ///     .get("https://hyper.rs")
+    ///     .on_informational(|status, headers| {
+    ///         if status == reqwest::StatusCode::EARLY_HINTS {
+    ///             for link in headers.get_all(reqwest::header::LINK) {
+    ///                 println!("early hint: {link:?}");
+    ///             }
+    ///         }
+    ///     })
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_informational<F>(mut self, callback: F) -> RequestBuilder
+    where
+        F: Fn(StatusCode, &HeaderMap) + Send + Sync + 'static,
+    {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<OnInformational>::get_mut(req.extensions_mut()) =
+                Some(Informational::new(callback));
+        }
+        self
+    }
+
+    /// Controls the use of certificate validation for this request only.
+    ///
+    /// The client's other requests are unaffected: this request is sent on
+    /// connections of its own, which aren't shared with them.
+    ///
+    /// # Warning
+    ///
+    /// You should think very carefully before using this method. If
+    /// invalid certificates are trusted, *any* certificate for *any* site
+    /// will be trusted for use. This includes expired certificates. This
+    /// introduces significant vulnerabilities, and should only be used
+    /// as a last resort.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend. Otherwise, sending the request
+    /// fails.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            let tls = RequestConfig::<TlsVerification>::get_mut(req.extensions_mut());
+            if accept_invalid_certs {
+                *tls = Some(TlsOverride::AcceptInvalidCerts);
+            } else if *tls == Some(TlsOverride::AcceptInvalidCerts) {
+                *tls = None;
+            }
+        }
+        self
+    }
+
+    /// Trusts only the given root certificates for this request.
+    ///
+    /// The client's trust roots, and any other verification settings, are
+    /// ignored for this request. Like
+    /// [`danger_accept_invalid_certs()`](RequestBuilder::danger_accept_invalid_certs),
+    /// the request is sent on connections of its own. A client keeps
+    /// connections for the 16 sets of roots it used last.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend. Otherwise, sending the request
+    /// fails.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_certs_only(
+        mut self,
+        certs: impl IntoIterator<Item = crate::Certificate>,
+    ) -> RequestBuilder {
+        let mut error = None;
+        if let Ok(ref mut req) = self.request {
+            match crate::tls::rustls_der(certs.into_iter().collect()) {
+                Ok(certs) => {
+                    *RequestConfig::<TlsVerification>::get_mut(req.extensions_mut()) =
+                        Some(TlsOverride::CertsOnly(certs));
+                }
+                Err(err) => error = Some(err),
+            }
+        }
+        if let Some(err) = error {
+            self.request = Err(err);
+        }
+        self
+    }
+
+    /// Make the request conditional on `cached` having changed.
+    ///
+    /// The `If-None-Match` and `If-Modified-Since` headers are set from the
+    /// `ETag` and `Last-Modified` of `cached`. If the server answers
+    /// `304 Not Modified`, the response returned has the status, headers
+    /// and body of `cached` instead, with the headers updated from the
+    /// `304`.
+    ///
+    /// Nothing is changed if `cached` has neither validator.
+    pub fn if_cached(mut self, cached: &CachedResponse) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            if cached.has_validators() {
+                cached.add_conditions(req.headers_mut());
+                *RequestConfig::<IfCached>::get_mut(req.extensions_mut()) = Some(cached.clone());
+            }
+        }
+        self
+    }
+
+    /// Record this request, and the response to it, into a HAR
+    /// [`Recorder`](crate::har::Recorder).
+    ///
+    /// This overrides the recorder set with [`ClientBuilder::har()`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `har` feature to be enabled.
+    ///
+    /// [`ClientBuilder::har()`]: crate::ClientBuilder::har
+    #[cfg(feature = "har")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "har")))]
+    pub fn har(mut self, recorder: crate::har::Recorder) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<Har>::get_mut(req.extensions_mut()) = Some(recorder);
+        }
+        self
+    }
+
     /// Sends a multipart/form-data body.
     ///
     /// ```
@@ -385,6 +791,48 @@ impl RequestBuilder {
         self
     }
 
+    /// Modify the query string of the URL, with control over how arrays,
+    /// `None`s and existing parameters are handled.
+    ///
+    /// With default [`Options`][crate::query::Options], this is the same as
+    /// [`query()`][Self::query], except that sequences of values are
+    /// accepted as well.
+    ///
+    /// ```rust
+    /// # fn run() -> Result<(), reqwest::Error> {
+    /// use reqwest::query::{ArrayFormat, Options};
+    ///
+    /// let req = reqwest::Client::new()
+    ///     .get("https://example.com/items")
+    ///     .query_with(&[("id", [1, 2])], Options::new().arrays(ArrayFormat::Brackets))
+    ///     .build()?;
+    /// assert_eq!(req.url().query(), Some("id%5B%5D=1&id%5B%5D=2"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `query` feature to be enabled.
+    ///
+    /// # Errors
+    /// This method will fail if the object you provide cannot be serialized
+    /// into a query string, such as a nested map.
+    #[cfg(feature = "query")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "query")))]
+    pub fn query_with<T: Serialize + ?Sized>(
+        mut self,
+        query: &T,
+        options: crate::query::Options,
+    ) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            if let Err(err) = crate::query::apply(req.url_mut(), query, options) {
+                self.request = Err(err);
+            }
+        }
+        self
+    }
+
     /// Set HTTP version
     pub fn version(mut self, version: Version) -> RequestBuilder {
         if let Ok(ref mut req) = self.request {
@@ -521,6 +969,66 @@ impl RequestBuilder {
         }
     }
 
+    /// Send the request and save the response body to the file at `path`,
+    /// resuming an earlier download that was interrupted.
+    ///
+    /// The body is written to `<path>.part`, which replaces `path` once the
+    /// whole body has been received. If the download fails, the part is kept,
+    /// with the response's `ETag` or `Last-Modified` in
+    /// `<path>.part.validator`. Calling `download_to` again with the same
+    /// `path` then asks for only the missing bytes with a `Range` request.
+    ///
+    /// The server sends the whole body instead if the resource has changed
+    /// since, or if it doesn't support ranges, in which case the part is
+    /// started over. A part without a validator is always started over.
+    ///
+    /// Returns the size of the downloaded file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = reqwest::Client::new();
+    /// let len = client
+    ///     .get("https://hyper.rs/large.iso")
+    ///     .download_to("large.iso")
+    ///     .await?;
+    /// println!("downloaded {len} bytes");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the request fails, the response has an error
+    /// status, or the file can't be written, in which case
+    /// [`Error::is_io`](crate::Error::is_io) is true.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `stream` feature to be enabled.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub async fn download_to<P: AsRef<std::path::Path>>(self, path: P) -> crate::Result<u64> {
+        super::download::download_to(self, path.as_ref()).await
+    }
+
+    /// Receive Server-Sent Events from this request, reconnecting when the
+    /// connection drops.
+    ///
+    /// The request is sent when the returned stream is first polled, and
+    /// again to reconnect, so it must be possible to clone it. See
+    /// [`EventSource`](super::sse::EventSource) for how reconnection works.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `sse` feature to be enabled.
+    #[cfg(feature = "sse")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sse")))]
+    pub fn event_source(self) -> super::sse::EventSource {
+        super::sse::EventSource::new(self)
+    }
+
     /// Attempt to clone the RequestBuilder.
     ///
     /// `None` is returned if the RequestBuilder can not be cloned,
@@ -550,6 +1058,47 @@ impl RequestBuilder {
                 request: Ok(req),
             })
     }
+
+    /// Returns the `curl` command that sends the same request, quoted for a
+    /// POSIX shell.
+    ///
+    /// The method, URL, HTTP version, headers and body are carried over,
+    /// along with the client's default headers and cookies, and
+    /// `--negotiate -u :` when the client uses Negotiate authentication. A
+    /// body that isn't UTF-8 text, or is streamed, is read from standard
+    /// input with `--data-binary @-`. Other client settings, such as
+    /// proxies, timeouts and redirects, are left out.
+    ///
+    /// `None` is returned if building the request failed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = reqwest::Client::builder().build()?;
+    /// let curl = client
+    ///     .post("https://hyper.rs/login")
+    ///     .header("x-trace", "it's me")
+    ///     .body("name=me")
+    ///     .to_curl_command();
+    /// assert_eq!(
+    ///     curl.as_deref(),
+    ///     Some("curl -H 'x-trace: it'\\''s me' -H 'accept: */*' --data-binary name=me https://hyper.rs/login")
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_curl_command(&self) -> Option<String> {
+        let req = self.request.as_ref().ok()?;
+        Some(super::curl::command(
+            &self.client.curl_defaults(),
+            req.method(),
+            req.url(),
+            req.version(),
+            req.headers(),
+            req.body().map(Body::as_bytes).into(),
+        ))
+    }
 }
 
 impl fmt::Debug for Request {
@@ -940,6 +1489,47 @@ mod tests {
         builder.build().unwrap();
     }
 
+    #[test]
+    fn to_curl_command() {
+        let client = Client::builder()
+            .user_agent("reqwest-test")
+            .build()
+            .unwrap();
+
+        let curl = client
+            .put("https://example.com/a b?q=1")
+            .header("accept", "text/plain")
+            .version(Version::HTTP_2)
+            .body(vec![0xff, 0xfe])
+            .to_curl_command()
+            .unwrap();
+        assert_eq!(
+            curl,
+            "curl -X PUT --http2 -H 'accept: text/plain' -H 'user-agent: reqwest-test' \
+             --data-binary @- 'https://example.com/a%20b?q=1'"
+        );
+
+        assert_eq!(
+            client
+                .get("https://example.com/")
+                .to_curl_command()
+                .unwrap(),
+            "curl -H 'accept: */*' -H 'user-agent: reqwest-test' https://example.com/"
+        );
+
+        assert!(client.get("not a url").to_curl_command().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "negotiate")]
+    fn to_curl_command_negotiate() {
+        let client = Client::builder().negotiate().build().unwrap();
+        assert_eq!(
+            client.head("http://intranet/").to_curl_command().unwrap(),
+            "curl --head -H 'accept: */*' --negotiate -u : http://intranet/"
+        );
+    }
+
     /*
     use {body, Method};
     use super::Client;
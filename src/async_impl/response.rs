@@ -1,6 +1,9 @@
 use std::fmt;
 use std::net::SocketAddr;
+#[cfg(feature = "stream")]
+use std::path::Path;
 use std::pin::Pin;
+use std::task::{ready, Context, Poll};
 use std::time::Duration;
 
 use bytes::Bytes;
@@ -15,6 +18,9 @@ use tokio::time::Sleep;
 use url::Url;
 
 use super::body::Body;
+use super::buffer_pool::BufferPool;
+use super::progress::{with_progress, Progress};
+use super::throttle::Throttle;
 use crate::async_impl::body::ResponseBody;
 #[cfg(feature = "cookies")]
 use crate::cookie;
@@ -30,6 +36,10 @@ pub struct Response {
     // Boxed to save space (11 words to 1 word), and it's not accessed
     // frequently internally.
     url: Box<Url>,
+    // Trailers seen by `chunk()`, kept for `trailers()`.
+    trailers: Option<Box<HeaderMap>>,
+    // Where `bytes()` gets a buffer to gather a chunked body in.
+    pub(super) buffer_pool: Option<BufferPool>,
 }
 
 impl Response {
@@ -38,16 +48,34 @@ impl Response {
         url: Url,
         total_timeout: Option<Pin<Box<Sleep>>>,
         read_timeout: Option<Duration>,
+        download_rate: Option<Throttle>,
+        download_progress: Option<Progress>,
+        max_size: Option<u64>,
     ) -> Response {
         let (parts, body) = res.into_parts();
-        let res = hyper::Response::from_parts(
-            parts,
-            super::body::response(body, total_timeout, read_timeout),
-        );
+        let body =
+            super::body::response(body, total_timeout, read_timeout, download_rate, max_size);
+        let body = match download_progress {
+            Some(progress) => {
+                let total = http_body::Body::size_hint(&body).exact();
+                with_progress(body, progress, total).boxed()
+            }
+            None => body,
+        };
+        #[cfg(feature = "har")]
+        let body = match parts.extensions.get::<crate::har::Capture>() {
+            Some(capture) => capture.clone().body(body).boxed(),
+            None => body,
+        };
+        #[cfg(feature = "tracing")]
+        let body = crate::trace::body(body).boxed();
+        let res = hyper::Response::from_parts(parts, body);
 
         Response {
             res,
             url: Box::new(url),
+            trailers: None,
+            buffer_pool: None,
         }
     }
 
@@ -206,6 +234,106 @@ impl Response {
     #[cfg(feature = "charset")]
     #[cfg_attr(docsrs, doc(cfg(feature = "charset")))]
     pub async fn text_with_charset(self, default_encoding: &str) -> crate::Result<String> {
+        let encoding = self.encoding(default_encoding);
+
+        let full = self.bytes().await?;
+
+        let (text, _, _) = encoding.decode(&full);
+        Ok(text.into_owned())
+    }
+
+    /// Convert the response into a `Stream` of text decoded from the body.
+    ///
+    /// The body is decoded as it arrives, like [`Response::text`] decodes it
+    /// all at once: the encoding is taken from the `charset` parameter of the
+    /// `Content-Type` header, a BOM overrides it and is stripped, and
+    /// malformed sequences are replaced with the
+    /// [`char::REPLACEMENT_CHARACTER`]. A character split between chunks of
+    /// the body is only yielded once it is complete.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut stream = reqwest::get("http://httpbin.org/range/26")
+    ///     .await?
+    ///     .text_stream();
+    ///
+    /// while let Some(text) = stream.next().await {
+    ///     print!("{}", text?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `stream` and `charset` features to be
+    /// enabled.
+    #[cfg(all(feature = "stream", feature = "charset"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "stream", feature = "charset"))))]
+    pub fn text_stream(self) -> impl futures_core::Stream<Item = crate::Result<String>> {
+        self.text_stream_with_charset("utf-8")
+    }
+
+    /// Convert the response into a `Stream` of text decoded from the body,
+    /// given a default encoding.
+    ///
+    /// Like [`Response::text_stream`], with `default_encoding` used when the
+    /// `Content-Type` header has no `charset`, as in
+    /// [`Response::text_with_charset`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `stream` and `charset` features to be
+    /// enabled.
+    #[cfg(all(feature = "stream", feature = "charset"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "stream", feature = "charset"))))]
+    pub fn text_stream_with_charset(
+        self,
+        default_encoding: &str,
+    ) -> impl futures_core::Stream<Item = crate::Result<String>> {
+        use futures_util::StreamExt;
+
+        fn decode(decoder: &mut encoding_rs::Decoder, src: &[u8], last: bool) -> String {
+            let len = decoder
+                .max_utf8_buffer_length(src.len())
+                .unwrap_or(src.len());
+            let mut text = String::with_capacity(len);
+            let _ = decoder.decode_to_string(src, &mut text, last);
+            text
+        }
+
+        // `new_decoder` sniffs and strips a BOM.
+        let decoder = self.encoding(default_encoding).new_decoder();
+        let body = self.bytes_stream();
+        Box::pin(futures_util::stream::unfold(
+            Some((body, decoder)),
+            |state| async move {
+                let (mut body, mut decoder) = state?;
+                loop {
+                    match body.next().await {
+                        Some(Ok(chunk)) => {
+                            let text = decode(&mut decoder, &chunk, false);
+                            if !text.is_empty() {
+                                return Some((Ok(text), Some((body, decoder))));
+                            }
+                        }
+                        Some(Err(err)) => return Some((Err(err), None)),
+                        None => {
+                            let text = decode(&mut decoder, &[], true);
+                            return (!text.is_empty()).then(|| (Ok(text), None));
+                        }
+                    }
+                }
+            },
+        ))
+    }
+
+    #[cfg(feature = "charset")]
+    fn encoding(&self, default_encoding: &str) -> &'static Encoding {
         let content_type = self
             .headers()
             .get(crate::header::CONTENT_TYPE)
@@ -215,12 +343,7 @@ impl Response {
             .as_ref()
             .and_then(|mime| mime.get_param("charset").map(|charset| charset.as_str()))
             .unwrap_or(default_encoding);
-        let encoding = Encoding::for_label(encoding_name.as_bytes()).unwrap_or(UTF_8);
-
-        let full = self.bytes().await?;
-
-        let (text, _, _) = encoding.decode(&full);
-        Ok(text.into_owned())
+        Encoding::for_label(encoding_name.as_bytes()).unwrap_or(UTF_8)
     }
 
     /// Try to deserialize the response body as JSON.
@@ -269,7 +392,107 @@ impl Response {
     pub async fn json<T: DeserializeOwned>(self) -> crate::Result<T> {
         let full = self.bytes().await?;
 
-        serde_json::from_slice(&full).map_err(crate::error::decode)
+        serde_json::from_slice(&full).map_err(|e| {
+            let head = full.slice(..full.len().min(DECODE_ERROR_BODY_LIMIT));
+            crate::error::decode(e).with_body(head)
+        })
+    }
+
+    /// Convert the response into a `Stream` of values deserialized from
+    /// newline-delimited JSON, also known as JSON Lines or NDJSON.
+    ///
+    /// Each line of the body is deserialized as soon as it has been received.
+    /// Blank lines are skipped, and a line that can't be deserialized yields
+    /// an error without ending the stream.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use serde::Deserialize;
+    /// use futures_util::StreamExt;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Event {
+    ///     message: String,
+    /// }
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut events = reqwest::get("http://httpbin.org/events")
+    ///     .await?
+    ///     .json_lines::<Event>();
+    ///
+    /// while let Some(event) = events.next().await {
+    ///     println!("{}", event?.message);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` and `stream` features to be
+    /// enabled.
+    #[cfg(all(feature = "json", feature = "stream"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "json", feature = "stream"))))]
+    pub fn json_lines<T: DeserializeOwned>(
+        self,
+    ) -> impl futures_core::Stream<Item = crate::Result<T>> {
+        use bytes::{Buf, BytesMut};
+        use futures_util::StreamExt;
+
+        fn next_line(buf: &mut BytesMut, eof: bool) -> Option<Bytes> {
+            match buf.iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    let line = buf.split_to(i).freeze();
+                    buf.advance(1);
+                    Some(line)
+                }
+                None if eof && !buf.is_empty() => Some(buf.split().freeze()),
+                None => None,
+            }
+        }
+
+        let body = self.bytes_stream();
+        let state = (body, BytesMut::new(), false);
+        Box::pin(futures_util::stream::unfold(
+            Some(state),
+            |state| async move {
+                let (mut body, mut buf, mut eof) = state?;
+                loop {
+                    while let Some(line) = next_line(&mut buf, eof) {
+                        if line.iter().all(u8::is_ascii_whitespace) {
+                            continue;
+                        }
+                        let value = serde_json::from_slice(&line).map_err(crate::error::decode);
+                        return Some((value, Some((body, buf, eof))));
+                    }
+                    if eof {
+                        return None;
+                    }
+                    match body.next().await {
+                        Some(Ok(chunk)) => buf.extend_from_slice(&chunk),
+                        Some(Err(err)) => return Some((Err(err), None)),
+                        None => eof = true,
+                    }
+                }
+            },
+        ))
+    }
+
+    /// Read the response as a stream of Server-Sent Events.
+    ///
+    /// The stream ends when the response body does. To reconnect when that
+    /// happens, see [`RequestBuilder::event_source()`].
+    ///
+    /// [`RequestBuilder::event_source()`]: crate::RequestBuilder::event_source
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `sse` feature to be enabled.
+    #[cfg(feature = "sse")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sse")))]
+    pub fn events(self) -> super::sse::EventStream {
+        super::sse::EventStream::new(self)
     }
 
     /// Get the full response body as `Bytes`.
@@ -287,13 +510,63 @@ impl Response {
     /// # Ok(())
     /// # }
     /// ```
+    ///
+    /// # Errors
+    ///
+    /// If reading the body fails part way, the error keeps the first bytes
+    /// read, available from [`Error::body()`](crate::Error::body). The same
+    /// goes for [`text()`](Response::text) and [`json()`](Response::json),
+    /// which also keep them if the body isn't valid JSON.
     pub async fn bytes(self) -> crate::Result<Bytes> {
         use http_body_util::BodyExt;
 
-        BodyExt::collect(self.res.into_body())
-            .await
-            .map(|buf| buf.to_bytes())
-            .map_err(crate::error::decode)
+        let mut body = Head::new(self.res.into_body());
+        let full = match self.buffer_pool {
+            Some(pool) => pool.collect(&mut body).await,
+            None => BodyExt::collect(&mut body).await.map(|buf| buf.to_bytes()),
+        };
+        full.map_err(|e| {
+            let err = crate::error::decode(e);
+            match body.into_bytes() {
+                Some(head) => err.with_body(head),
+                None => err,
+            }
+        })
+    }
+
+    /// Read the full response body, and keep it with the status and headers
+    /// for a later conditional request.
+    ///
+    /// See [`RequestBuilder::if_cached()`](crate::RequestBuilder::if_cached).
+    pub async fn into_cached(self) -> crate::Result<crate::cache::CachedResponse> {
+        let status = self.status();
+        let version = self.version();
+        let headers = self.headers().clone();
+        let url = self.url().clone();
+        let body = self.bytes().await?;
+        Ok(crate::cache::CachedResponse::new(
+            status, version, headers, url, body,
+        ))
+    }
+
+    /// Read the response as a multipart body, such as `multipart/mixed` or
+    /// `multipart/byteranges`.
+    ///
+    /// The boundary is taken from the `Content-Type` header. The parts and
+    /// their bodies are streamed as they are read.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the response isn't `multipart/*` with a
+    /// boundary.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `multipart` feature enabled.
+    #[cfg(feature = "multipart")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "multipart")))]
+    pub fn multipart(self) -> crate::Result<super::multipart::MultipartResponse> {
+        super::multipart::MultipartResponse::new(self)
     }
 
     /// Stream a chunk of the response body.
@@ -319,8 +592,12 @@ impl Response {
         loop {
             if let Some(res) = self.res.body_mut().frame().await {
                 let frame = res.map_err(crate::error::decode)?;
-                if let Ok(buf) = frame.into_data() {
-                    return Ok(Some(buf));
+                let frame = match frame.into_data() {
+                    Ok(buf) => return Ok(Some(buf)),
+                    Err(frame) => frame,
+                };
+                if let Ok(trailers) = frame.into_trailers() {
+                    self.trailers = Some(Box::new(trailers));
                 }
                 // else continue
             } else {
@@ -329,6 +606,39 @@ impl Response {
         }
     }
 
+    /// Get the trailers of the response, sent after its body.
+    ///
+    /// Trailers are sent in chunked HTTP/1.1 responses and in HTTP/2 and
+    /// HTTP/3 responses, such as `grpc-status` or a checksum of the body.
+    /// Read the body with [`Response::chunk`] first: any part of the body
+    /// that hasn't been read yet is discarded.
+    ///
+    /// Returns `None` if the response had no trailers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut res = reqwest::get("https://hyper.rs").await?;
+    ///
+    /// while let Some(chunk) = res.chunk().await? {
+    ///     println!("Chunk: {chunk:?}");
+    /// }
+    /// if let Some(trailers) = res.trailers().await? {
+    ///     println!("Trailers: {trailers:?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn trailers(&mut self) -> crate::Result<Option<HeaderMap>> {
+        while self.trailers.is_none() {
+            if self.chunk().await?.is_none() {
+                break;
+            }
+        }
+        Ok(self.trailers.take().map(|trailers| *trailers))
+    }
+
     /// Convert the response into a `Stream` of `Bytes` from the body.
     ///
     /// # Example
@@ -359,6 +669,103 @@ impl Response {
 
     // util methods
 
+    /// Stream the response body into the file at `path`, returning the number
+    /// of bytes written.
+    ///
+    /// The body is first written to a new temporary file next to `path`,
+    /// named after it with a random suffix and `.tmp` appended, which then
+    /// replaces `path` once the whole body has been received. If anything
+    /// fails, the temporary file is removed and `path` is left as it was.
+    /// Failing to write the file gives an error for which
+    /// [`Error::is_io`](crate::Error::is_io) is true.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let written = reqwest::get("https://hyper.rs/logo.svg")
+    ///     .await?
+    ///     .error_for_status()?
+    ///     .save_to("logo.svg")
+    ///     .await?;
+    /// println!("saved {written} bytes");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `stream` feature to be enabled.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub async fn save_to<P: AsRef<Path>>(self, path: P) -> crate::Result<u64> {
+        self.save(path.as_ref(), false).await
+    }
+
+    /// Like [`Response::save_to`], but the file is also flushed to disk
+    /// before it replaces `path`, so its contents survive a crash or power
+    /// loss.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `stream` feature to be enabled.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub async fn save_to_synced<P: AsRef<Path>>(self, path: P) -> crate::Result<u64> {
+        self.save(path.as_ref(), true).await
+    }
+
+    #[cfg(feature = "stream")]
+    async fn save(mut self, path: &Path, sync: bool) -> crate::Result<u64> {
+        use tokio::io::AsyncWriteExt;
+
+        let (tmp, mut file) = loop {
+            let tmp = crate::util::temp_path(path);
+            let opened = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&tmp)
+                .await;
+            match opened {
+                Ok(file) => break (tmp, file),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(crate::error::io(e)),
+            }
+        };
+
+        let result = async {
+            let written = self.write_to(&mut file).await?;
+            file.flush().await.map_err(crate::error::io)?;
+            if sync {
+                file.sync_all().await.map_err(crate::error::io)?;
+            }
+            drop(file);
+            tokio::fs::rename(&tmp, path)
+                .await
+                .map_err(crate::error::io)?;
+            Ok(written)
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+        }
+        result
+    }
+
+    /// Write the rest of the body to `file`, returning how many bytes were
+    /// written. The file isn't flushed.
+    #[cfg(feature = "stream")]
+    pub(super) async fn write_to(&mut self, file: &mut tokio::fs::File) -> crate::Result<u64> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut written = 0;
+        while let Some(chunk) = self.chunk().await? {
+            file.write_all(&chunk).await.map_err(crate::error::io)?;
+            written += chunk.len() as u64;
+        }
+        Ok(written)
+    }
+
     /// Turn a response into an error if the server returned an error.
     ///
     /// # Example
@@ -390,6 +797,47 @@ impl Response {
         }
     }
 
+    /// Turn a response into an error if the server returned an error, keeping
+    /// up to `limit` bytes of its body in the error.
+    ///
+    /// The status code alone often says little, such as when a proxy or
+    /// server returns an HTML error page. The kept bytes are available from
+    /// [`Error::body()`][crate::Error::body] and are shown when the error is
+    /// displayed. If reading the body fails, the bytes read so far are kept.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), reqwest::Error> {
+    /// let res = reqwest::get("https://hyper.rs")
+    ///     .await?
+    ///     .error_for_status_with_body(1024)
+    ///     .await;
+    /// if let Err(err) = res {
+    ///     eprintln!("{err}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn error_for_status_with_body(mut self, limit: usize) -> crate::Result<Self> {
+        let status = self.status();
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(self);
+        }
+        let reason = self.extensions().get::<hyper::ext::ReasonPhrase>().cloned();
+        let mut body = Vec::new();
+        while body.len() < limit {
+            match self.chunk().await {
+                Ok(Some(chunk)) => {
+                    let n = chunk.len().min(limit - body.len());
+                    body.extend_from_slice(&chunk[..n]);
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
+        Err(crate::error::status_code(*self.url, status, reason).with_body(body.into()))
+    }
+
     /// Turn a reference to a response into an error if the server returned an error.
     ///
     /// # Example
@@ -434,6 +882,73 @@ impl Response {
     }
 }
 
+/// How many bytes of the body a decode error keeps.
+const DECODE_ERROR_BODY_LIMIT: usize = 4096;
+
+/// A body that keeps its first data frames, for the error if reading the
+/// rest of it fails. The frames are shared, not copied.
+struct Head<B> {
+    inner: B,
+    frames: Vec<Bytes>,
+    len: usize,
+}
+
+impl<B> Head<B> {
+    fn new(inner: B) -> Head<B> {
+        Head {
+            inner,
+            frames: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Up to `DECODE_ERROR_BODY_LIMIT` bytes of what was read, if anything.
+    fn into_bytes(self) -> Option<Bytes> {
+        match self.frames.len() {
+            0 => None,
+            1 => {
+                let frame = self.frames.into_iter().next().expect("one frame");
+                Some(frame.slice(..frame.len().min(DECODE_ERROR_BODY_LIMIT)))
+            }
+            _ => {
+                let mut head = self.frames.concat();
+                head.truncate(DECODE_ERROR_BODY_LIMIT);
+                Some(head.into())
+            }
+        }
+    }
+}
+
+impl<B> http_body::Body for Head<B>
+where
+    B: http_body::Body<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<http_body::Frame<Bytes>, B::Error>>> {
+        let frame = ready!(Pin::new(&mut self.inner).poll_frame(cx));
+        if let Some(data) = frame.as_ref().and_then(|f| f.as_ref().ok()?.data_ref()) {
+            if self.len < DECODE_ERROR_BODY_LIMIT && !data.is_empty() {
+                self.len += data.len();
+                self.frames.push(data.clone());
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
 impl fmt::Debug for Response {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Response")
@@ -468,6 +983,8 @@ impl<T: Into<Body>> From<http::Response<T>> for Response {
         Response {
             res,
             url: Box::new(url),
+            trailers: None,
+            buffer_pool: None,
         }
     }
 }
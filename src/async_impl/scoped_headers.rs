@@ -0,0 +1,164 @@
+//! Default headers that only apply to some hosts or URLs.
+
+use http::header::{Entry, HeaderMap};
+use url::Url;
+
+use crate::error::BoxError;
+
+/// Where a set of default headers applies.
+#[derive(Clone, Debug)]
+enum Scope {
+    /// A host, or with a leading `.`, a domain and its subdomains.
+    Host(String),
+    /// Every URL under this one.
+    Prefix(Url),
+}
+
+impl Scope {
+    fn parse(scope: &str) -> Result<Scope, BoxError> {
+        if scope.contains("://") {
+            let url = Url::parse(scope)?;
+            if url.host_str().is_none() {
+                return Err("default headers scope has no host".into());
+            }
+            Ok(Scope::Prefix(url))
+        } else if scope.is_empty() {
+            Err("default headers scope is empty".into())
+        } else {
+            Ok(Scope::Host(scope.to_ascii_lowercase()))
+        }
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        match *self {
+            Scope::Host(ref scope) => {
+                let host = match url.host_str() {
+                    Some(host) => host.to_ascii_lowercase(),
+                    None => return false,
+                };
+                match scope.strip_prefix('.') {
+                    Some(domain) => {
+                        host == domain
+                            || host
+                                .strip_suffix(domain)
+                                .map_or(false, |sub| sub.ends_with('.'))
+                    }
+                    None => host == *scope,
+                }
+            }
+            Scope::Prefix(ref prefix) => {
+                if url.scheme() != prefix.scheme()
+                    || url.host_str() != prefix.host_str()
+                    || url.port_or_known_default() != prefix.port_or_known_default()
+                {
+                    return false;
+                }
+                let base = prefix.path().trim_end_matches('/');
+                match url.path().strip_prefix(base) {
+                    Some(rest) => rest.is_empty() || rest.starts_with('/'),
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+/// Default headers, each set only sent to URLs in its scope.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ScopedHeaders {
+    scopes: Vec<(Scope, HeaderMap)>,
+}
+
+impl ScopedHeaders {
+    pub(crate) fn insert(&mut self, scope: &str, headers: HeaderMap) -> Result<(), BoxError> {
+        self.scopes.push((Scope::parse(scope)?, headers));
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    /// Adds the headers for `url` that `headers` doesn't have yet.
+    ///
+    /// The scopes added last come first, so that a narrower scope set after
+    /// a wider one wins.
+    pub(crate) fn apply(&self, url: &Url, headers: &mut HeaderMap) {
+        for (scope, defaults) in self.scopes.iter().rev() {
+            if !scope.matches(url) {
+                continue;
+            }
+            for (name, value) in defaults {
+                if let Entry::Vacant(entry) = headers.entry(name) {
+                    entry.insert(value.clone());
+                }
+            }
+        }
+    }
+
+    /// Removes the headers added for other URLs than `url`, so that they
+    /// don't follow a redirect out of their scope.
+    ///
+    /// A header is only removed if it still has the default value.
+    pub(crate) fn remove_out_of_scope(&self, url: &Url, headers: &mut HeaderMap) {
+        for (scope, defaults) in &self.scopes {
+            if scope.matches(url) {
+                continue;
+            }
+            for (name, value) in defaults {
+                if headers.get(name) == Some(value) {
+                    headers.remove(name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn scopes_match_hosts_domains_and_prefixes() {
+        let host = Scope::parse("Internal.corp.com").unwrap();
+        assert!(host.matches(&url("https://internal.corp.com/a")));
+        assert!(!host.matches(&url("https://api.internal.corp.com/a")));
+
+        let domain = Scope::parse(".corp.com").unwrap();
+        assert!(domain.matches(&url("http://corp.com/")));
+        assert!(domain.matches(&url("http://api.corp.com/")));
+        assert!(!domain.matches(&url("http://evilcorp.com/")));
+
+        let prefix = Scope::parse("https://api.example.com/v2/").unwrap();
+        assert!(prefix.matches(&url("https://api.example.com/v2")));
+        assert!(prefix.matches(&url("https://api.example.com/v2/users?page=2")));
+        assert!(!prefix.matches(&url("https://api.example.com/v2beta")));
+        assert!(!prefix.matches(&url("http://api.example.com/v2/users")));
+        assert!(!prefix.matches(&url("https://api.example.com:8443/v2/users")));
+
+        assert!(Scope::parse("").is_err());
+        assert!(Scope::parse("data://").is_err());
+    }
+
+    #[test]
+    fn narrower_scope_set_last_wins() {
+        let mut scoped = ScopedHeaders::default();
+        let mut wide = HeaderMap::new();
+        wide.insert("x-api-key", "wide".parse().unwrap());
+        let mut narrow = HeaderMap::new();
+        narrow.insert("x-api-key", "narrow".parse().unwrap());
+        scoped.insert(".corp.com", wide).unwrap();
+        scoped.insert("https://billing.corp.com/", narrow).unwrap();
+
+        let mut headers = HeaderMap::new();
+        scoped.apply(&url("https://billing.corp.com/invoices"), &mut headers);
+        assert_eq!(headers["x-api-key"], "narrow");
+
+        scoped.remove_out_of_scope(&url("https://hr.corp.com/"), &mut headers);
+        assert!(headers.is_empty());
+    }
+}
@@ -0,0 +1,497 @@
+//! Server-Sent Events
+//!
+//! A response with a `text/event-stream` body can be read as a stream of
+//! [`Event`]s with [`Response::events()`]. To keep receiving events when the
+//! connection drops, use [`RequestBuilder::event_source()`] instead: the
+//! returned [`EventSource`] reconnects, sending the `Last-Event-ID` header so
+//! the server can resume where it left off.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use futures_util::StreamExt;
+//!
+//! # async fn run() -> Result<(), reqwest::Error> {
+//! let mut events = reqwest::Client::new()
+//!     .get("https://hyper.rs/events")
+//!     .event_source();
+//!
+//! while let Some(event) = events.next().await {
+//!     let event = event?;
+//!     println!("{}: {}", event.event(), event.data());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`RequestBuilder::event_source()`]: crate::RequestBuilder::event_source
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use bytes::{Buf, BytesMut};
+use futures_core::Stream;
+use http_body::Body as HttpBody;
+use tokio::time::Sleep;
+
+use super::request::RequestBuilder;
+use super::response::Response;
+use crate::header::{HeaderValue, ACCEPT, CACHE_CONTROL, CONTENT_TYPE};
+use crate::StatusCode;
+
+/// The reconnection delay used until the server sets one with `retry`.
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+/// The longest an [`EventSource`] waits between failed reconnections.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// An event received from a server.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    id: String,
+    event: String,
+    data: String,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// The ID of the last event that set one, which is also what an
+    /// [`EventSource`] sends in `Last-Event-ID` when it reconnects.
+    ///
+    /// This is empty if no event has had an ID yet.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The type of the event, `"message"` unless the server set another.
+    pub fn event(&self) -> &str {
+        if self.event.is_empty() {
+            "message"
+        } else {
+            &self.event
+        }
+    }
+
+    /// The data of the event, with the lines of multi-line data joined by
+    /// `'\n'`.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// The reconnection delay requested by the server along with this event.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+}
+
+/// A stream of [`Event`]s read from a response.
+///
+/// Created by [`Response::events()`]. The stream ends when the response
+/// body does.
+pub struct EventStream {
+    res: Response,
+    parser: Parser,
+    done: bool,
+}
+
+impl EventStream {
+    pub(crate) fn new(res: Response) -> EventStream {
+        EventStream {
+            res,
+            parser: Parser::default(),
+            done: false,
+        }
+    }
+
+    /// The ID of the last event that set one.
+    pub fn last_event_id(&self) -> &str {
+        &self.parser.last_id
+    }
+
+    /// The reconnection delay last requested by the server.
+    pub fn retry(&self) -> Option<Duration> {
+        self.parser.retry
+    }
+}
+
+impl Stream for EventStream {
+    type Item = crate::Result<Event>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(event) = this.parser.next_event(this.done) {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let body = Pin::new(this.res.res.body_mut());
+            match ready!(body.poll_frame(cx)) {
+                Some(Ok(frame)) => {
+                    if let Ok(data) = frame.into_data() {
+                        this.parser.buf.extend_from_slice(&data);
+                    }
+                }
+                Some(Err(err)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(crate::error::decode(err))));
+                }
+                None => this.done = true,
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("url", self.res.url())
+            .field("last_event_id", &self.parser.last_id)
+            .finish()
+    }
+}
+
+/// A stream of [`Event`]s that reconnects when the connection drops.
+///
+/// Created by [`RequestBuilder::event_source()`].
+///
+/// After the connection is lost, an error is yielded if there was one, and
+/// the request is sent again once the reconnection delay has passed, with
+/// the `Last-Event-ID` header if an event had an ID. The delay is 3 seconds
+/// unless the server set another with `retry`, and doubles after each failed
+/// attempt, up to a minute.
+///
+/// The stream ends without reconnecting if the server responds with
+/// `204 No Content`. It yields an error and ends if the server responds with
+/// another status than `200 OK` or a body that isn't `text/event-stream`, or
+/// if the request can't be cloned.
+///
+/// [`RequestBuilder::event_source()`]: crate::RequestBuilder::event_source
+pub struct EventSource {
+    builder: RequestBuilder,
+    state: State,
+    last_id: String,
+    retry: Duration,
+    attempts: u32,
+}
+
+enum State {
+    Connecting(Pin<Box<dyn Future<Output = crate::Result<Response>> + Send>>),
+    Open(Box<EventStream>),
+    Waiting(Pin<Box<Sleep>>),
+    Failed(crate::Error),
+    Closed,
+}
+
+impl EventSource {
+    pub(crate) fn new(builder: RequestBuilder) -> EventSource {
+        let mut source = EventSource {
+            builder,
+            state: State::Closed,
+            last_id: String::new(),
+            retry: DEFAULT_RETRY,
+            attempts: 0,
+        };
+        source.connect();
+        source
+    }
+
+    /// The ID of the last event that set one.
+    pub fn last_event_id(&self) -> &str {
+        &self.last_id
+    }
+
+    /// Stop receiving events and don't reconnect.
+    pub fn close(&mut self) {
+        self.state = State::Closed;
+    }
+
+    fn connect(&mut self) {
+        let builder = match self.builder.try_clone() {
+            Some(builder) => builder,
+            None => {
+                let err = crate::error::builder("event source request can't be cloned");
+                self.state = State::Failed(err);
+                return;
+            }
+        };
+        let mut builder = builder
+            .header(ACCEPT, HeaderValue::from_static("text/event-stream"))
+            .header(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        if !self.last_id.is_empty() {
+            builder = builder.header("last-event-id", self.last_id.as_str());
+        }
+        self.state = State::Connecting(Box::pin(builder.send()));
+    }
+
+    fn reconnect_later(&mut self) {
+        let backoff = self
+            .retry
+            .checked_mul(1 << self.attempts.min(16))
+            .map_or(MAX_BACKOFF, |delay| delay.min(MAX_BACKOFF.max(self.retry)));
+        self.attempts += 1;
+        self.state = State::Waiting(Box::pin(tokio::time::sleep(backoff)));
+    }
+}
+
+impl Stream for EventSource {
+    type Item = crate::Result<Event>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            match this.state {
+                State::Connecting(ref mut pending) => match ready!(pending.as_mut().poll(cx)) {
+                    Ok(res) => {
+                        if res.status() == StatusCode::NO_CONTENT {
+                            this.state = State::Closed;
+                            continue;
+                        }
+                        if let Err(err) = check_response(&res) {
+                            this.state = State::Closed;
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        let mut events = res.events();
+                        events.parser.last_id = this.last_id.clone();
+                        this.state = State::Open(Box::new(events));
+                    }
+                    Err(err) => {
+                        this.reconnect_later();
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                },
+                State::Open(ref mut events) => {
+                    let next = ready!(Pin::new(&mut **events).poll_next(cx));
+                    // A `retry` may come without an event to carry it.
+                    if let Some(retry) = events.retry() {
+                        this.retry = retry;
+                    }
+                    match next {
+                        Some(Ok(event)) => {
+                            this.attempts = 0;
+                            this.last_id = event.id.clone();
+                            return Poll::Ready(Some(Ok(event)));
+                        }
+                        Some(Err(err)) => {
+                            this.reconnect_later();
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        None => this.reconnect_later(),
+                    }
+                }
+                State::Waiting(ref mut sleep) => {
+                    ready!(sleep.as_mut().poll(cx));
+                    this.connect();
+                }
+                State::Failed(_) => {
+                    if let State::Failed(err) = std::mem::replace(&mut this.state, State::Closed) {
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                State::Closed => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EventSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventSource")
+            .field("builder", &self.builder)
+            .field("last_event_id", &self.last_id)
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
+
+fn check_response(res: &Response) -> crate::Result<()> {
+    if res.status() != StatusCode::OK {
+        return Err(crate::error::status_code(
+            res.url().clone(),
+            res.status(),
+            None,
+        ));
+    }
+    let is_event_stream = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map_or(false, |mime| {
+            mime.trim().eq_ignore_ascii_case("text/event-stream")
+        });
+    if !is_event_stream {
+        return Err(
+            crate::error::decode("response is not an event stream").with_url(res.url().clone())
+        );
+    }
+    Ok(())
+}
+
+/// Parses the `text/event-stream` format, as described in
+/// <https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation>.
+#[derive(Default)]
+struct Parser {
+    buf: BytesMut,
+    started: bool,
+    // Whether the last line ended with a '\r', which may be half of a "\r\n".
+    after_cr: bool,
+    event: String,
+    data: String,
+    has_data: bool,
+    retry: Option<Duration>,
+    block_retry: Option<Duration>,
+    last_id: String,
+}
+
+impl Parser {
+    fn next_event(&mut self, eof: bool) -> Option<Event> {
+        if !self.started {
+            if self.buf.len() < 3 && !eof && b"\xef\xbb\xbf".starts_with(&self.buf[..]) {
+                return None;
+            }
+            if self.buf.starts_with(b"\xef\xbb\xbf") {
+                self.buf.advance(3);
+            }
+            self.started = true;
+        }
+        while let Some(line) = self.next_line() {
+            if line.is_empty() {
+                if let Some(event) = self.dispatch() {
+                    return Some(event);
+                }
+            } else {
+                self.field(&line);
+            }
+        }
+        // An event without its closing blank line at the end of the stream is
+        // discarded.
+        None
+    }
+
+    fn next_line(&mut self) -> Option<String> {
+        if self.after_cr {
+            match self.buf.first() {
+                Some(b'\n') => self.buf.advance(1),
+                Some(_) => (),
+                None => return None,
+            }
+            self.after_cr = false;
+        }
+        let end = self.buf.iter().position(|&b| b == b'\n' || b == b'\r')?;
+        let line = String::from_utf8_lossy(&self.buf[..end]).into_owned();
+        self.after_cr = self.buf[end] == b'\r';
+        self.buf.advance(end + 1);
+        Some(line)
+    }
+
+    fn field(&mut self, line: &str) {
+        let (name, value) = match line.find(':') {
+            Some(0) => return,
+            Some(i) => {
+                let value = &line[i + 1..];
+                (&line[..i], value.strip_prefix(' ').unwrap_or(value))
+            }
+            None => (line, ""),
+        };
+        match name {
+            "event" => self.event = value.to_owned(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_id = value.to_owned(),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(Duration::from_millis(ms));
+                    self.block_retry = self.retry;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn dispatch(&mut self) -> Option<Event> {
+        let retry = self.block_retry.take();
+        let event = std::mem::take(&mut self.event);
+        if !self.has_data {
+            self.data.clear();
+            return None;
+        }
+        self.has_data = false;
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        Some(Event {
+            id: self.last_id.clone(),
+            event,
+            data,
+            retry,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&str]) -> Vec<Event> {
+        let mut parser = Parser::default();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            parser.buf.extend_from_slice(chunk.as_bytes());
+            while let Some(event) = parser.next_event(false) {
+                events.push(event);
+            }
+        }
+        while let Some(event) = parser.next_event(true) {
+            events.push(event);
+        }
+        events
+    }
+
+    fn event(id: &str, event: &str, data: &str) -> Event {
+        Event {
+            id: id.to_owned(),
+            event: event.to_owned(),
+            data: data.to_owned(),
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn fields() {
+        let events = parse(&[
+            "\u{feff}: comment\nevent: add\ndata: first\ndata:second\nid: 1\n\n",
+            "data\nretry: 500\n\n",
+            "event: ignored\n\n",
+        ]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], event("1", "add", "first\nsecond"));
+        assert_eq!(events[1].id(), "1");
+        assert_eq!(events[1].event(), "message");
+        assert_eq!(events[1].data(), "");
+        assert_eq!(events[1].retry(), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn line_endings_split_across_chunks() {
+        let events = parse(&["data: a\r", "\ndata: b\r\r", "data: c\r\n", "\r\n"]);
+        assert_eq!(events, [event("", "", "a\nb"), event("", "", "c")]);
+    }
+
+    #[test]
+    fn unterminated_event_is_discarded() {
+        assert!(parse(&["data: partial"]).is_empty());
+    }
+
+    #[test]
+    fn id_with_null_is_ignored() {
+        let events = parse(&["id: 1\ndata: a\n\nid: 2\0\ndata: b\n\nid\ndata: c\n\n"]);
+        let ids = events.iter().map(Event::id).collect::<Vec<_>>();
+        assert_eq!(ids, ["1", "1", ""]);
+    }
+}
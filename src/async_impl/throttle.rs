@@ -0,0 +1,246 @@
+//! Bandwidth limiting for request and response bodies.
+//!
+//! A [`Throttle`] is a token bucket measured in bytes. Bodies wrapped with
+//! [`ThrottledBody`] split data frames into small chunks, charge each chunk
+//! to the bucket, and hold it back until the bucket is out of debt. Not
+//! polling the inner body in the meantime is what applies backpressure to
+//! the connection.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use tokio::time::{Instant, Sleep};
+
+/// A shared token bucket limiting throughput to a number of bytes per second.
+///
+/// Clones share the same bucket, so a `Throttle` configured on a `Client`
+/// limits the combined rate of every request using it.
+#[derive(Clone)]
+pub(crate) struct Throttle {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+struct Bucket {
+    rate: u64,
+    // Bytes currently available. Negative while chunks that were charged
+    // beyond the budget are waiting to be let through.
+    available: f64,
+    last: Instant,
+}
+
+impl Throttle {
+    pub(crate) fn new(bytes_per_sec: u64) -> Throttle {
+        assert!(bytes_per_sec > 0, "rate limit must be greater than zero");
+        Throttle {
+            bucket: Arc::new(Mutex::new(Bucket {
+                rate: bytes_per_sec,
+                available: bytes_per_sec as f64,
+                last: Instant::now(),
+            })),
+        }
+    }
+
+    /// The largest chunk to let through at once, about 100ms worth of data.
+    fn chunk_size(&self) -> usize {
+        let rate = self.bucket.lock().expect("throttle lock poisoned").rate;
+        usize::try_from(rate / 10).unwrap_or(usize::MAX).max(1)
+    }
+
+    /// Charge `n` bytes to the bucket, returning how long to wait before
+    /// they may be transferred.
+    pub(crate) fn consume(&self, n: usize) -> Option<Duration> {
+        self.bucket
+            .lock()
+            .expect("throttle lock poisoned")
+            .consume(n, Instant::now())
+    }
+
+    /// Whether the bucket has refilled completely, so that replacing it with
+    /// a new `Throttle` would make no difference.
+    pub(crate) fn is_full(&self) -> bool {
+        self.bucket
+            .lock()
+            .expect("throttle lock poisoned")
+            .is_full(Instant::now())
+    }
+}
+
+impl Bucket {
+    fn consume(&mut self, n: usize, now: Instant) -> Option<Duration> {
+        let rate = self.rate as f64;
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        // Allow bursting up to one second's worth of data.
+        self.available = (self.available + elapsed * rate).min(rate);
+        self.available -= n as f64;
+
+        if self.available < 0.0 {
+            Some(Duration::from_secs_f64(-self.available / rate))
+        } else {
+            None
+        }
+    }
+
+    fn is_full(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.available + elapsed * self.rate as f64 >= self.rate as f64
+    }
+}
+
+impl fmt::Debug for Throttle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rate = self.bucket.lock().map(|b| b.rate).unwrap_or_default();
+        f.debug_tuple("Throttle").field(&rate).finish()
+    }
+}
+
+pin_project! {
+    pub(crate) struct ThrottledBody<B> {
+        #[pin]
+        inner: B,
+        throttle: Throttle,
+        sleep: Option<Pin<Box<Sleep>>>,
+        // A chunk that has been paid for, waiting on `sleep`.
+        ready: Option<Bytes>,
+        // The rest of the current data frame, not yet paid for.
+        rest: Bytes,
+    }
+}
+
+pub(crate) fn throttled<B>(body: B, throttle: Throttle) -> ThrottledBody<B> {
+    ThrottledBody {
+        inner: body,
+        throttle,
+        sleep: None,
+        ready: None,
+        rest: Bytes::new(),
+    }
+}
+
+impl<B> HttpBody for ThrottledBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(sleep) = this.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                *this.sleep = None;
+            }
+
+            if let Some(chunk) = this.ready.take() {
+                return Poll::Ready(Some(Ok(Frame::data(chunk))));
+            }
+
+            if this.rest.is_empty() {
+                match ready!(this.inner.as_mut().poll_frame(cx)) {
+                    Some(Ok(frame)) => match frame.into_data() {
+                        Ok(data) => *this.rest = data,
+                        Err(frame) => return Poll::Ready(Some(Ok(frame))),
+                    },
+                    other => return Poll::Ready(other),
+                }
+            }
+
+            let len = this.rest.len().min(this.throttle.chunk_size());
+            let chunk = this.rest.split_to(len);
+            match this.throttle.consume(chunk.len()) {
+                Some(wait) => {
+                    *this.sleep = Some(Box::pin(tokio::time::sleep(wait)));
+                    *this.ready = Some(chunk);
+                }
+                None => return Poll::Ready(Some(Ok(Frame::data(chunk)))),
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        let mut hint = self.inner.size_hint();
+        let buffered = self.rest.len() + self.ready.as_ref().map_or(0, Bytes::len);
+        if buffered > 0 {
+            hint.set_lower(hint.lower() + buffered as u64);
+            if let Some(upper) = hint.upper() {
+                hint.set_upper(upper + buffered as u64);
+            }
+        }
+        hint
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.ready.is_none() && self.rest.is_empty() && self.inner.is_end_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_one_second_burst() {
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            rate: 1000,
+            available: 1000.0,
+            last: start,
+        };
+
+        assert_eq!(bucket.consume(1000, start), None);
+        assert_eq!(bucket.consume(500, start), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            rate: 1000,
+            available: 0.0,
+            last: start,
+        };
+
+        assert_eq!(
+            bucket.consume(250, start + Duration::from_millis(250)),
+            None
+        );
+        // never refills past the burst size
+        assert_eq!(bucket.consume(1000, start + Duration::from_secs(10)), None);
+        assert_eq!(
+            bucket.consume(100, start + Duration::from_secs(10)),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn bucket_is_full_once_debt_is_paid() {
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            rate: 1000,
+            available: 1000.0,
+            last: start,
+        };
+
+        assert!(bucket.is_full(start));
+        assert_eq!(
+            bucket.consume(1500, start),
+            Some(Duration::from_millis(500))
+        );
+        assert!(!bucket.is_full(start + Duration::from_secs(1)));
+        assert!(bucket.is_full(start + Duration::from_millis(1500)));
+    }
+}
@@ -14,7 +14,9 @@ pub(crate) mod sspi;
 mod negotiate;
 
 #[cfg(feature = "negotiate")]
-pub(crate) use negotiate::execute_with_negotiate;
+pub(crate) use negotiate::{check, execute_with_negotiate};
+#[cfg(all(windows, feature = "negotiate"))]
+pub(crate) use negotiate::execute_through_proxy;
 
 use std::task::{Context, Poll};
 use tower::{Layer, Service};
@@ -44,21 +46,84 @@ pub(crate) enum Credentials {
 #[derive(Clone, Debug)]
 pub(crate) struct NegotiateConfig {
     pub(crate) credentials: Credentials,
+    /// Also try `HTTP/host:port` when the URL has a non-default port.
+    pub(crate) spn_include_port: bool,
+    /// What to do for URLs whose host is an IP address.
+    pub(crate) ip_literal_policy: crate::negotiate::IpLiteralPolicy,
+    /// Domain for explicit usernames that don't name one.
+    pub(crate) default_domain: Option<String>,
+    /// Fail rather than fall back to NTLM or Basic.
+    pub(crate) kerberos_only: bool,
+    /// Credentials for the hosts matching a pattern, `*.suffix` or a host
+    /// name, in place of `credentials`.
+    pub(crate) domain_credentials: Vec<(String, Credentials)>,
 }
 
 impl NegotiateConfig {
     pub(crate) fn current_user() -> Self {
         Self {
             credentials: Credentials::CurrentUser,
+            spn_include_port: false,
+            ip_literal_policy: Default::default(),
+            default_domain: None,
+            kerberos_only: false,
+            domain_credentials: Vec::new(),
         }
     }
 
     pub(crate) fn with_credentials(username: String, password: String) -> Self {
         Self {
             credentials: Credentials::Explicit { username, password },
+            spn_include_port: false,
+            ip_literal_policy: Default::default(),
+            default_domain: None,
+            kerberos_only: false,
+            domain_credentials: Vec::new(),
+        }
+    }
+
+    /// The credentials for `url`: those of the longest pattern its host
+    /// matches, or the default ones.
+    pub(crate) fn credentials_for(&self, url: &url::Url) -> &Credentials {
+        let host = match url.host_str() {
+            Some(host) => host,
+            None => return &self.credentials,
+        };
+
+        self.domain_credentials
+            .iter()
+            .filter(|(pattern, _)| domain_matches(pattern, host))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map_or(&self.credentials, |(_, credentials)| credentials)
+    }
+
+    /// The credentials to give SSPI for `url`: an explicit username without
+    /// a domain gets the default domain, as `DOMAIN\user`.
+    pub(crate) fn sspi_credentials(&self, url: &url::Url) -> Credentials {
+        match (self.credentials_for(url), &self.default_domain) {
+            (Credentials::Explicit { username, password }, Some(domain))
+                if !username.is_empty() && !username.contains(['\\', '@']) =>
+            {
+                Credentials::Explicit {
+                    username: format!("{}\\{}", domain, username),
+                    password: password.clone(),
+                }
+            }
+            (credentials, _) => credentials.clone(),
         }
     }
 }
 
+/// Whether `host` matches `pattern`: `*.emea.corp` matches the hosts under
+/// `emea.corp`, and any other pattern only the host of that name.
+fn domain_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host
+            .strip_suffix(suffix)
+            .map_or(false, |name| name.len() > 1 && name.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
 // Negotiate authentication is integrated directly in the Client execute flow
 // rather than as Tower middleware to avoid circular dependencies
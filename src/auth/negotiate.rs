@@ -7,10 +7,13 @@
 
 #![allow(unused)]
 
-use super::Credentials;
+use super::{Credentials, NegotiateConfig};
+use crate::trace::Instrument;
 use crate::{Client, Request, Response, Result};
+use crate::negotiate::IpLiteralPolicy;
 use http::{HeaderMap, HeaderValue};
 use base64::Engine as _;
+use std::net::IpAddr;
 
 #[cfg(windows)]
 use super::sspi::SspiContext;
@@ -34,20 +37,42 @@ pub(crate) fn derive_spn(url: &url::Url) -> Result<String> {
     Ok(format!("HTTP/{}", host))
 }
 
+/// The SPNs to try for `url`, in order.
+///
+/// With `include_port`, a URL with a non-default port also gets the SPN
+/// with the port, `HTTP/<hostname>:<port>`, tried after the one without.
+fn spn_candidates(url: &url::Url, include_port: bool) -> Result<Vec<String>> {
+    let spn = derive_spn(url)?;
+    let with_port = url
+        .port()
+        .filter(|_| include_port)
+        .map(|port| format!("{}:{}", spn, port));
+
+    Ok(std::iter::once(spn).chain(with_port).collect())
+}
+
+/// The Negotiate token, NTLM token and whether Basic is offered, as parsed
+/// by `parse_www_authenticate`.
+type Challenges = (Option<Option<Vec<u8>>>, Option<Option<Vec<u8>>>, bool);
+
 /// Parse WWW-Authenticate header to extract authentication challenges.
 ///
 /// Returns a tuple of (negotiate_token, ntlm_token, has_basic) where:
 /// - negotiate_token: Some(None) if bare "Negotiate", Some(Some(token)) if "Negotiate <token>"
 /// - ntlm_token: Some(None) if bare "NTLM", Some(Some(token)) if "NTLM <token>"
 /// - has_basic: true if "Basic" challenge present
-fn parse_www_authenticate(
-    headers: &HeaderMap,
-) -> (Option<Option<Vec<u8>>>, Option<Option<Vec<u8>>>, bool) {
+fn parse_www_authenticate(headers: &HeaderMap) -> Challenges {
+    parse_challenges(headers, http::header::WWW_AUTHENTICATE)
+}
+
+/// Parse the challenges in the `name` headers, `WWW-Authenticate` or
+/// `Proxy-Authenticate`, as `parse_www_authenticate` does.
+fn parse_challenges(headers: &HeaderMap, name: http::header::HeaderName) -> Challenges {
     let mut negotiate_token = None;
     let mut ntlm_token = None;
     let mut has_basic = false;
 
-    for value in headers.get_all(http::header::WWW_AUTHENTICATE) {
+    for value in headers.get_all(name) {
         if let Ok(value_str) = value.to_str() {
             let trimmed = value_str.trim();
 
@@ -84,14 +109,14 @@ fn parse_www_authenticate(
 ///
 /// # Arguments
 /// * `request` - The request to execute with authentication
-/// * `creds` - Credentials (current user or explicit username/password)
+/// * `config` - Credentials (current user or explicit username/password) and SPN options
 /// * `execute_fn` - Function to execute HTTP requests (avoids recursion)
 ///
 /// # Returns
 /// The final response after authentication, or an error if authentication fails
 pub(crate) async fn execute_with_negotiate<F, Fut>(
     request: Request,
-    creds: &Credentials,
+    config: &NegotiateConfig,
     mut execute_fn: F,
 ) -> Result<Response>
 where
@@ -116,30 +141,60 @@ where
     let (negotiate_challenge, ntlm_challenge, has_basic) =
         parse_www_authenticate(response.headers());
 
-    // Derive SPN from URL
-    let spn = derive_spn(original_request.url())?;
+    // Derive SPNs from URL
+    let creds = config.credentials_for(original_request.url());
+    let sspi_creds = config.sspi_credentials(original_request.url());
+    let spns = spn_candidates(original_request.url(), config.spn_include_port)?;
+
+    if config.kerberos_only {
+        return try_kerberos_only(
+            &original_request,
+            config,
+            negotiate_challenge.is_some(),
+            &mut execute_fn,
+        )
+        .await;
+    }
 
     // Try authentication methods in priority order
 
     // 1. Try Negotiate (Kerberos/NTLM via SSPI)
     #[cfg(windows)]
     if negotiate_challenge.is_some() {
-        match try_negotiate_auth(&original_request, &spn, creds, &mut execute_fn).await {
-            Ok(response) => return Ok(response),
-            Err(e) => {
-                log::debug!("Negotiate authentication failed: {:?}", e);
-                // Continue to fallback
+        let negotiate_spns = negotiate_spns(original_request.url(), config)
+            .await
+            .map_err(crate::error::negotiate)?;
+        for spn in negotiate_spns.iter().flatten() {
+            match try_negotiate_auth(
+                &original_request,
+                spn,
+                "Negotiate",
+                &sspi_creds,
+                &mut execute_fn,
+            )
+            .await
+            {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    trace_debug!("Negotiate authentication for {} failed: {:?}", spn, e);
+                    // Continue with the next SPN
+                }
             }
         }
+        if negotiate_spns.is_some() {
+            crate::metrics::auth_fallback("Negotiate");
+        }
+        // Continue to fallback
     }
 
     // 2. Try NTLM directly (if server supports it)
     #[cfg(windows)]
     if ntlm_challenge.is_some() {
-        match try_ntlm_auth(&original_request, &spn, creds, &mut execute_fn).await {
+        match try_ntlm_auth(&original_request, &spns[0], &sspi_creds, &mut execute_fn).await {
             Ok(response) => return Ok(response),
             Err(e) => {
-                log::debug!("NTLM authentication failed: {:?}", e);
+                trace_debug!("NTLM authentication failed: {:?}", e);
+                crate::metrics::auth_fallback("NTLM");
                 // Continue to fallback
             }
         }
@@ -158,11 +213,160 @@ where
     ))
 }
 
+/// Send `request`, first authenticating to the proxy at `proxy_host` with
+/// Negotiate if it answers `407 Proxy Authentication Required`.
+///
+/// The proxy gets an SSPI context and SPN of its own, apart from the
+/// origin's, and its tokens go in `Proxy-Authorization`, leaving any
+/// `Authorization` header of an origin handshake as it is. Each request of
+/// that handshake can so go through a proxy handshake of its own, as when it
+/// is sent on a new connection.
+#[cfg(windows)]
+pub(crate) async fn execute_through_proxy<F, Fut>(
+    request: Request,
+    proxy_host: &str,
+    mut execute_fn: F,
+) -> Result<Response>
+where
+    F: FnMut(Request) -> Fut,
+    Fut: std::future::Future<Output = Result<Response>>,
+{
+    // A request that can't be sent again can't be authenticated
+    let original_request = match request.try_clone() {
+        Some(original_request) => original_request,
+        None => return execute_fn(request).await,
+    };
+
+    let response = execute_fn(request).await?;
+    if response.status() != http::StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+        return Ok(response);
+    }
+
+    // Without a Negotiate challenge, the 407 is the response
+    let (negotiate_challenge, _, _) =
+        parse_challenges(response.headers(), http::header::PROXY_AUTHENTICATE);
+    let mut input_token = match negotiate_challenge {
+        Some(token) => token,
+        None => return Ok(response),
+    };
+
+    let spn = format!("HTTP/{}", proxy_host);
+    let mut ctx = SspiContext::new("Negotiate");
+    ctx.acquire_credentials(&Credentials::CurrentUser)
+        .map_err(|code| {
+            crate::error::negotiate(format!("SSPI AcquireCredentials failed: 0x{:08X}", code))
+        })?;
+
+    for round in 0..MAX_ROUNDTRIPS {
+        let (output_token, _) = ctx
+            .initialize_context(&spn, input_token.as_deref())
+            .map_err(|code| {
+                crate::error::negotiate(format!("SSPI InitializeContext failed: 0x{:08X}", code))
+            })?;
+        let token_base64 = base64::engine::general_purpose::STANDARD.encode(&output_token);
+
+        let mut auth_request = original_request
+            .try_clone()
+            .ok_or_else(|| crate::error::negotiate("Request not clonable"))?;
+        auth_request.headers_mut().insert(
+            http::header::PROXY_AUTHORIZATION,
+            HeaderValue::from_str(&format!("Negotiate {}", token_base64))
+                .map_err(|_| crate::error::negotiate("Invalid authorization header"))?,
+        );
+
+        let span = trace_span!(
+            "auth",
+            http.auth.scheme = "Negotiate",
+            http.auth.round = round
+        );
+        let response = execute_fn(auth_request).instrument(span).await?;
+        if response.status() != http::StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+            return Ok(response);
+        }
+
+        match parse_challenges(response.headers(), http::header::PROXY_AUTHENTICATE).0 {
+            Some(Some(proxy_token)) => input_token = Some(proxy_token),
+            _ => {
+                return Err(crate::error::negotiate(
+                    "Proxy returned 407 without new challenge token",
+                ))
+            }
+        }
+    }
+
+    Err(crate::error::negotiate(
+        "Too many proxy authentication round-trips",
+    ))
+}
+
+/// Authenticate with Kerberos alone, for `negotiate_kerberos_only`.
+///
+/// Nothing falls back to NTLM or Basic: each way this can't work is an
+/// error telling why.
+async fn try_kerberos_only<F, Fut>(
+    original_request: &Request,
+    config: &NegotiateConfig,
+    negotiate_offered: bool,
+    execute_fn: &mut F,
+) -> Result<Response>
+where
+    F: FnMut(Request) -> Fut,
+    Fut: std::future::Future<Output = Result<Response>>,
+{
+    let kerberos_only = |reason: String| {
+        crate::error::negotiate(format!(
+            "Kerberos authentication failed, and falling back to NTLM or Basic is disabled: {}",
+            reason
+        ))
+    };
+
+    if !negotiate_offered {
+        return Err(kerberos_only("the server doesn't offer Negotiate".into()));
+    }
+
+    #[cfg(not(windows))]
+    return Err(kerberos_only(
+        "Kerberos needs SSPI, which is only available on Windows".into(),
+    ));
+
+    #[cfg(windows)]
+    {
+        let spns = match negotiate_spns(original_request.url(), config).await {
+            Ok(Some(spns)) => spns,
+            Ok(None) => {
+                return Err(kerberos_only(
+                    "the URL's host is an IP address, which Kerberos can't authenticate to".into(),
+                ))
+            }
+            Err(reason) => return Err(kerberos_only(reason)),
+        };
+
+        let creds = config.sspi_credentials(original_request.url());
+        let mut reason = String::new();
+        for spn in &spns {
+            // The Kerberos package, unlike Negotiate, never picks NTLM
+            match try_negotiate_auth(original_request, spn, "Kerberos", &creds, execute_fn).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    let source = std::error::Error::source(&e)
+                        .map_or_else(|| e.to_string(), ToString::to_string);
+                    reason = format!("{} (SPN {})", source, spn);
+                }
+            }
+        }
+        Err(kerberos_only(reason))
+    }
+}
+
 /// Try Negotiate authentication using Windows SSPI.
+///
+/// `package` is the SSPI package making the tokens, `Negotiate` or
+/// `Kerberos`; they are sent with the Negotiate scheme either way.
 #[cfg(windows)]
 async fn try_negotiate_auth<F, Fut>(
     original_request: &Request,
     spn: &str,
+    package: &str,
     creds: &Credentials,
     execute_fn: &mut F,
 ) -> Result<Response>
@@ -170,7 +374,7 @@ where
     F: FnMut(Request) -> Fut,
     Fut: std::future::Future<Output = Result<Response>>,
 {
-    let mut ctx = SspiContext::new("Negotiate");
+    let mut ctx = SspiContext::new(package);
 
     // Acquire credentials
     ctx.acquire_credentials(creds)
@@ -206,7 +410,12 @@ where
             );
 
         // Send authenticated request
-        let response = execute_fn(auth_request).await?;
+        let span = trace_span!(
+            "auth",
+            http.auth.scheme = "Negotiate",
+            http.auth.round = round
+        );
+        let response = execute_fn(auth_request).instrument(span).await?;
 
         // Check response status
         match response.status() {
@@ -229,7 +438,7 @@ where
                 // Optionally validate mutual authentication token
                 if let Some(Some(server_token)) = parse_www_authenticate(response.headers()).0 {
                     // Server sent final token for mutual auth validation
-                    log::debug!("Mutual authentication token received from server");
+                    trace_debug!("Mutual authentication token received from server");
                 }
                 return Ok(response);
             }
@@ -289,7 +498,8 @@ where
             );
 
         // Send authenticated request
-        let response = execute_fn(auth_request).await?;
+        let span = trace_span!("auth", http.auth.scheme = "NTLM", http.auth.round = round);
+        let response = execute_fn(auth_request).instrument(span).await?;
 
         // Check response status
         match response.status() {
@@ -346,7 +556,278 @@ where
         );
 
     // Send authenticated request
-    execute_fn(auth_request).await
+    let span = trace_span!("auth", http.auth.scheme = "Basic", http.auth.round = 0);
+    execute_fn(auth_request).instrument(span).await
+}
+
+/// The SPNs to try Negotiate with for `url`, or `None` to skip it.
+///
+/// Kerberos can't authenticate to an IP address, so for those the policy
+/// decides between giving up with a reason, skipping Negotiate, or the SPNs
+/// of the address's host name.
+async fn negotiate_spns(
+    url: &url::Url,
+    config: &NegotiateConfig,
+) -> std::result::Result<Option<Vec<String>>, String> {
+    let ip = match url.host() {
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip),
+        _ => {
+            return spn_candidates(url, config.spn_include_port)
+                .map(Some)
+                .map_err(|e| e.to_string())
+        }
+    };
+
+    match config.ip_literal_policy {
+        IpLiteralPolicy::FailFast => Err(format!(
+            "Kerberos can't authenticate to the IP address {}",
+            ip
+        )),
+        IpLiteralPolicy::SkipToNtlm => Ok(None),
+        IpLiteralPolicy::ReverseLookupHostname => {
+            let host = reverse_lookup(ip)
+                .await
+                .ok_or_else(|| format!("no host name found for {}", ip))?;
+            let mut named = url.clone();
+            named.set_host(Some(&host)).map_err(|e| e.to_string())?;
+            spn_candidates(&named, config.spn_include_port)
+                .map(Some)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Look up the host name of `ip`, off the async runtime's threads.
+#[cfg(windows)]
+async fn reverse_lookup(ip: IpAddr) -> Option<String> {
+    tokio::task::spawn_blocking(move || super::sspi::host_name(ip))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Without SSPI there are no tickets to ask for, so no lookup is made.
+#[cfg(not(windows))]
+async fn reverse_lookup(_ip: IpAddr) -> Option<String> {
+    None
+}
+
+/// Go through the Negotiate handshake with `url`, and report on each step.
+///
+/// Unlike `execute_with_negotiate`, nothing here fails the check: what went
+/// wrong is recorded in the report's outcome instead. When several SPNs are
+/// tried, the report is of the last one.
+pub(crate) async fn check<F, Fut>(
+    url: url::Url,
+    config: &NegotiateConfig,
+    mut execute_fn: F,
+) -> Result<crate::negotiate::Report>
+where
+    F: FnMut(Request) -> Fut,
+    Fut: std::future::Future<Output = Result<Response>>,
+{
+    use crate::negotiate::{Outcome, Report};
+
+    let mut spns = spn_candidates(&url, config.spn_include_port)?.into_iter();
+    let response = execute_fn(Request::new(http::Method::GET, url.clone())).await?;
+
+    let mut report = Report {
+        url,
+        challenges: challenge_schemes(response.headers()),
+        spn: spns.next().expect("at least one SPN"),
+        package: None,
+        rounds: Vec::new(),
+        status: response.status(),
+        outcome: Outcome::NotRequired,
+    };
+
+    if response.status() != http::StatusCode::UNAUTHORIZED {
+        return Ok(report);
+    }
+
+    let (negotiate_challenge, ntlm_challenge, _) = parse_www_authenticate(response.headers());
+    let scheme = if negotiate_challenge.is_some() {
+        match negotiate_spns(&report.url, config).await {
+            Ok(Some(negotiate_spns)) => {
+                spns = negotiate_spns.into_iter();
+                report.spn = spns.next().expect("at least one SPN");
+                "Negotiate"
+            }
+            Ok(None) if ntlm_challenge.is_some() => "NTLM",
+            Ok(None) => {
+                report.outcome = Outcome::Failed(
+                    "Kerberos skipped for an IP address, and NTLM not offered".into(),
+                );
+                return Ok(report);
+            }
+            Err(reason) => {
+                report.outcome = Outcome::Failed(reason);
+                return Ok(report);
+            }
+        }
+    } else if ntlm_challenge.is_some() {
+        "NTLM"
+    } else {
+        report.outcome = Outcome::Unsupported;
+        return Ok(report);
+    };
+    if scheme == "NTLM" {
+        if config.kerberos_only {
+            report.outcome = Outcome::Failed("NTLM is disabled by negotiate_kerberos_only".into());
+            return Ok(report);
+        }
+        // NTLM doesn't ask for tickets, so other SPNs won't do better
+        spns = Vec::new().into_iter();
+    }
+    // The Kerberos package, unlike Negotiate, never picks NTLM
+    let package = if config.kerberos_only {
+        "Kerberos"
+    } else {
+        scheme
+    };
+
+    #[cfg(windows)]
+    let creds = config.sspi_credentials(&report.url);
+    #[cfg(windows)]
+    loop {
+        check_rounds(&mut report, scheme, package, &creds, &mut execute_fn).await?;
+        if report.outcome == Outcome::Authenticated {
+            break;
+        }
+        match spns.next() {
+            Some(spn) => {
+                report.spn = spn;
+                report.package = None;
+                report.rounds.clear();
+            }
+            None => break,
+        }
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = (scheme, package);
+        report.outcome = Outcome::Failed("SSPI is only available on Windows".into());
+    }
+
+    Ok(report)
+}
+
+/// The schemes of each challenge in the `WWW-Authenticate` headers.
+///
+/// A header may hold several challenges separated by commas, so parameters
+/// (`realm="x"`) and tokens are told apart from schemes and skipped.
+fn challenge_schemes(headers: &HeaderMap) -> Vec<String> {
+    let mut schemes = Vec::new();
+
+    for value in headers.get_all(http::header::WWW_AUTHENTICATE) {
+        let value_str = match value.to_str() {
+            Ok(value_str) => value_str,
+            Err(_) => continue,
+        };
+
+        for part in value_str.split(',') {
+            let word = match part.split_whitespace().next() {
+                Some(word) => word,
+                None => continue,
+            };
+            let is_scheme = word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+            if is_scheme
+                && !schemes
+                    .iter()
+                    .any(|s: &String| s.eq_ignore_ascii_case(word))
+            {
+                schemes.push(word.to_string());
+            }
+        }
+    }
+
+    schemes
+}
+
+/// The token exchange of `check`, made with SSPI.
+#[cfg(windows)]
+async fn check_rounds<F, Fut>(
+    report: &mut crate::negotiate::Report,
+    scheme: &'static str,
+    package: &str,
+    creds: &Credentials,
+    execute_fn: &mut F,
+) -> Result<()>
+where
+    F: FnMut(Request) -> Fut,
+    Fut: std::future::Future<Output = Result<Response>>,
+{
+    use crate::negotiate::{Outcome, Round};
+
+    let mut ctx = SspiContext::new(package);
+    if let Err(code) = ctx.acquire_credentials(creds) {
+        report.outcome = Outcome::Failed(format!("SSPI AcquireCredentials failed: 0x{:08X}", code));
+        return Ok(());
+    }
+
+    let mut input_token = None;
+
+    for round in 0..MAX_ROUNDTRIPS {
+        let (output_token, _) = match ctx.initialize_context(&report.spn, input_token.as_deref()) {
+            Ok(output) => output,
+            Err(code) => {
+                report.outcome =
+                    Outcome::Failed(format!("SSPI InitializeContext failed: 0x{:08X}", code));
+                return Ok(());
+            }
+        };
+        report.package = Some(
+            ctx.negotiated_package()
+                .unwrap_or_else(|| package.to_string()),
+        );
+
+        let token_base64 = base64::engine::general_purpose::STANDARD.encode(&output_token);
+        let mut auth_request = Request::new(http::Method::GET, report.url.clone());
+        auth_request.headers_mut().insert(
+            http::header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("{} {}", scheme, token_base64))
+                .map_err(|_| crate::error::negotiate("Invalid authorization header"))?,
+        );
+
+        let span = trace_span!("auth", http.auth.scheme = scheme, http.auth.round = round);
+        let response = execute_fn(auth_request).instrument(span).await?;
+
+        let (negotiate_token, ntlm_token, _) = parse_www_authenticate(response.headers());
+        let server_token = if scheme == "Negotiate" {
+            negotiate_token
+        } else {
+            ntlm_token
+        }
+        .flatten();
+
+        report.rounds.push(Round {
+            scheme,
+            sent: output_token.len(),
+            status: response.status(),
+            received: server_token.as_ref().map(Vec::len),
+        });
+        report.status = response.status();
+
+        if response.status() != http::StatusCode::UNAUTHORIZED {
+            report.outcome = Outcome::Authenticated;
+            return Ok(());
+        }
+
+        match server_token {
+            Some(server_token) => input_token = Some(server_token),
+            None => {
+                report.outcome = Outcome::Rejected;
+                return Ok(());
+            }
+        }
+    }
+
+    report.outcome = Outcome::Failed("too many authentication round-trips".into());
+    Ok(())
 }
 
 #[cfg(test)]
@@ -362,6 +843,82 @@ mod tests {
         assert_eq!(derive_spn(&url).unwrap(), "HTTP/server.corp.com");
     }
 
+    #[test]
+    fn test_sspi_credentials_default_domain() {
+        let url = url::Url::parse("http://server.corp.com/").unwrap();
+        let username = |config: NegotiateConfig| match config.sspi_credentials(&url) {
+            Credentials::Explicit { username, .. } => username,
+            Credentials::CurrentUser => unreachable!(),
+        };
+        let config = |username: &str| NegotiateConfig {
+            default_domain: Some("CORP".into()),
+            ..NegotiateConfig::with_credentials(username.into(), "password".into())
+        };
+
+        assert_eq!(username(config("jdoe")), "CORP\\jdoe");
+        assert_eq!(username(config("OTHER\\jdoe")), "OTHER\\jdoe");
+        assert_eq!(username(config("jdoe@OTHER.COM")), "jdoe@OTHER.COM");
+        assert!(matches!(
+            NegotiateConfig {
+                default_domain: Some("CORP".into()),
+                ..NegotiateConfig::current_user()
+            }
+            .sspi_credentials(&url),
+            Credentials::CurrentUser
+        ));
+    }
+
+    #[test]
+    fn test_credentials_for_domain() {
+        let explicit = |username: &str| Credentials::Explicit {
+            username: username.into(),
+            password: "password".into(),
+        };
+        let config = NegotiateConfig {
+            domain_credentials: vec![
+                ("*.emea.corp".into(), explicit("EMEA\\jdoe")),
+                ("*.apac.corp".into(), explicit("APAC\\jdoe")),
+                ("*.lab.emea.corp".into(), explicit("LAB\\jdoe")),
+                ("intranet.corp".into(), explicit("CORP\\jdoe")),
+            ],
+            ..NegotiateConfig::current_user()
+        };
+        let username = |url: &str| match config.credentials_for(&url::Url::parse(url).unwrap()) {
+            Credentials::Explicit { username, .. } => Some(username.clone()),
+            Credentials::CurrentUser => None,
+        };
+
+        assert_eq!(username("http://app.emea.corp/").unwrap(), "EMEA\\jdoe");
+        assert_eq!(username("http://APP.APAC.CORP/").unwrap(), "APAC\\jdoe");
+        assert_eq!(username("http://app.lab.emea.corp/").unwrap(), "LAB\\jdoe");
+        assert_eq!(username("http://intranet.corp/").unwrap(), "CORP\\jdoe");
+        // The suffix pattern doesn't match the domain itself, nor a host that
+        // only ends with the same letters
+        assert_eq!(username("http://emea.corp/"), None);
+        assert_eq!(username("http://appemea.corp/"), None);
+        assert_eq!(username("http://app.intranet.corp/"), None);
+    }
+
+    #[test]
+    fn test_spn_candidates() {
+        let url = url::Url::parse("https://server.corp.com:8443/api").unwrap();
+        assert_eq!(
+            spn_candidates(&url, false).unwrap(),
+            ["HTTP/server.corp.com"]
+        );
+        assert_eq!(
+            spn_candidates(&url, true).unwrap(),
+            ["HTTP/server.corp.com", "HTTP/server.corp.com:8443"]
+        );
+
+        // The default port is never part of the SPN
+        let url = url::Url::parse("https://server.corp.com:443/api").unwrap();
+        assert_eq!(
+            spn_candidates(&url, true).unwrap(),
+            ["HTTP/server.corp.com"]
+        );
+    }
+
     #[test]
     fn test_parse_www_authenticate_negotiate() {
         let mut headers = HeaderMap::new();
@@ -420,4 +977,19 @@ mod tests {
         assert_eq!(ntlm, Some(None));
         assert!(has_basic);
     }
+
+    #[test]
+    fn test_challenge_schemes() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::WWW_AUTHENTICATE,
+            HeaderValue::from_static("Negotiate"),
+        );
+        headers.append(
+            http::header::WWW_AUTHENTICATE,
+            HeaderValue::from_static("Basic realm=\"a, b\", charset=\"UTF-8\", NTLM"),
+        );
+
+        assert_eq!(challenge_schemes(&headers), ["Negotiate", "Basic", "NTLM"]);
+    }
 }
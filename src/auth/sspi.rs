@@ -12,10 +12,15 @@ use std::ptr;
 
 use windows::core::PCWSTR;
 use windows::Win32::Foundation::{SEC_E_OK, SEC_I_CONTINUE_NEEDED};
+use windows::Win32::Networking::WinSock::{
+    socklen_t, GetNameInfoW, AF_INET, AF_INET6, IN6_ADDR, IN6_ADDR_0, IN_ADDR, IN_ADDR_0,
+    NI_MAXHOST, NI_NAMEREQD, SOCKADDR, SOCKADDR_IN, SOCKADDR_IN6,
+};
 use windows::Win32::Security::Authentication::Identity::{
-    AcquireCredentialsHandleW, DeleteSecurityContext, FreeCredentialsHandle,
-    InitializeSecurityContextW, SecBuffer, SecBufferDesc, ISC_REQ_FLAGS, SECBUFFER_TOKEN,
-    SECBUFFER_VERSION, SECPKG_CRED_OUTBOUND, SECURITY_NATIVE_DREP,
+    AcquireCredentialsHandleW, DeleteSecurityContext, FreeContextBuffer, FreeCredentialsHandle,
+    InitializeSecurityContextW, QueryContextAttributesW, SecBuffer, SecBufferDesc,
+    SecPkgContext_NegotiationInfoW, ISC_REQ_FLAGS, SECBUFFER_TOKEN, SECBUFFER_VERSION,
+    SECPKG_ATTR_NEGOTIATION_INFO, SECPKG_CRED_OUTBOUND, SECURITY_NATIVE_DREP,
 };
 use windows::Win32::Security::Credentials::SecHandle;
 
@@ -278,6 +283,43 @@ impl SspiContext {
     }
 }
 
+impl SspiContext {
+    /// The package Negotiate settled on, such as `Kerberos` or `NTLM`.
+    ///
+    /// Only known once a token was made, and only for the `Negotiate`
+    /// package.
+    pub(crate) fn negotiated_package(&self) -> Option<String> {
+        if !self.has_ctx {
+            return None;
+        }
+
+        unsafe {
+            let mut info = SecPkgContext_NegotiationInfoW::default();
+            QueryContextAttributesW(
+                &self.ctx_handle,
+                SECPKG_ATTR_NEGOTIATION_INFO,
+                &mut info as *mut SecPkgContext_NegotiationInfoW as *mut c_void,
+            )
+            .ok()?;
+            if info.PackageInfo.is_null() {
+                return None;
+            }
+
+            let name = (*info.PackageInfo).Name;
+            let package = if name.is_null() {
+                None
+            } else {
+                let len = (0..).take_while(|&i| *name.add(i) != 0).count();
+                Some(String::from_utf16_lossy(std::slice::from_raw_parts(
+                    name, len,
+                )))
+            };
+            let _ = FreeContextBuffer(info.PackageInfo as *mut c_void);
+            package
+        }
+    }
+}
+
 impl Drop for SspiContext {
     fn drop(&mut self) {
         unsafe {
@@ -360,3 +402,57 @@ mod tests {
         }
     }
 }
+
+/// Look up the host name of an IP address, with `GetNameInfoW`.
+///
+/// This blocks until the name is resolved, or the lookup fails.
+pub(crate) fn host_name(ip: std::net::IpAddr) -> Option<String> {
+    let mut host = [0u16; NI_MAXHOST as usize];
+
+    let code = match ip {
+        std::net::IpAddr::V4(ip) => {
+            let addr = SOCKADDR_IN {
+                sin_family: AF_INET,
+                sin_addr: IN_ADDR {
+                    S_un: IN_ADDR_0 {
+                        S_addr: u32::from_ne_bytes(ip.octets()),
+                    },
+                },
+                ..Default::default()
+            };
+            unsafe {
+                GetNameInfoW(
+                    &addr as *const SOCKADDR_IN as *const SOCKADDR,
+                    socklen_t(std::mem::size_of::<SOCKADDR_IN>() as i32),
+                    Some(&mut host),
+                    None,
+                    NI_NAMEREQD as i32,
+                )
+            }
+        }
+        std::net::IpAddr::V6(ip) => {
+            let addr = SOCKADDR_IN6 {
+                sin6_family: AF_INET6,
+                sin6_addr: IN6_ADDR {
+                    u: IN6_ADDR_0 { Byte: ip.octets() },
+                },
+                ..Default::default()
+            };
+            unsafe {
+                GetNameInfoW(
+                    &addr as *const SOCKADDR_IN6 as *const SOCKADDR,
+                    socklen_t(std::mem::size_of::<SOCKADDR_IN6>() as i32),
+                    Some(&mut host),
+                    None,
+                    NI_NAMEREQD as i32,
+                )
+            }
+        }
+    };
+    if code != 0 {
+        return None;
+    }
+
+    let len = host.iter().position(|&c| c == 0).unwrap_or(host.len());
+    Some(String::from_utf16_lossy(&host[..len]))
+}
@@ -83,6 +83,33 @@ impl Body {
         }
     }
 
+    /// Create a `Body` that streams from a `Read`, without loading it into
+    /// memory.
+    ///
+    /// With a `len`, the body is sent with a `Content-Length` and no more
+    /// than `len` bytes are read. Without one, it is sent with
+    /// `Transfer-Encoding: chunked` until the reader is exhausted.
+    ///
+    /// Like [`Body::new`], such a body can't be sent again to follow a 307
+    /// or 308 redirect, or to retry.
+    ///
+    /// ```rust
+    /// # use std::fs::File;
+    /// # use reqwest::blocking::Body;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = File::open("a_large_file.txt")?;
+    /// let len = file.metadata().ok().map(|meta| meta.len());
+    /// let body = Body::from_reader(file, len);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_reader<R: Read + Send + 'static>(reader: R, len: Option<u64>) -> Body {
+        match len {
+            Some(len) => Body::sized(reader.take(len), len),
+            None => Body::new(reader),
+        }
+    }
+
     /// Returns the body as a byte slice if the body is already buffered in
     /// memory. For streamed requests this method returns `None`.
     pub fn as_bytes(&self) -> Option<&[u8]> {
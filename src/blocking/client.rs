@@ -5,11 +5,13 @@ use std::fmt;
 use std::future::Future;
 use std::net::IpAddr;
 use std::net::SocketAddr;
+use std::ops::ControlFlow;
 use std::sync::Arc;
 use std::task::{ready, Poll};
 use std::thread;
 use std::time::Duration;
 
+use bytes::Bytes;
 use http::header::HeaderValue;
 use log::{error, trace};
 use tokio::sync::{mpsc, oneshot};
@@ -19,6 +21,7 @@ use tower::Service;
 use super::request::{Request, RequestBuilder};
 use super::response::Response;
 use super::wait;
+use crate::async_impl::client::BoxedClientService;
 use crate::connect::sealed::{Conn, Unnameable};
 #[cfg(unix)]
 use crate::connect::uds::UnixSocketProvider;
@@ -77,9 +80,11 @@ pub struct Client {
 /// # }
 /// ```
 #[must_use]
+#[derive(Clone)]
 pub struct ClientBuilder {
     inner: async_impl::ClientBuilder,
     timeout: Timeout,
+    runtime: Option<tokio::runtime::Handle>,
 }
 
 impl Default for ClientBuilder {
@@ -96,6 +101,7 @@ impl ClientBuilder {
         ClientBuilder {
             inner: async_impl::ClientBuilder::new(),
             timeout: Timeout::default(),
+            runtime: None,
         }
     }
 }
@@ -116,6 +122,34 @@ impl ClientBuilder {
         ClientHandle::new(self).map(|handle| Client { inner: handle })
     }
 
+    /// Drive requests on an existing Tokio runtime.
+    ///
+    /// By default, each `Client` starts a thread running its own runtime.
+    /// With a `Handle` to a runtime the application already has, the
+    /// requests run as tasks on it instead, and no thread is started.
+    ///
+    /// The runtime must keep running for as long as the `Client` is used,
+    /// and a multi-thread runtime is needed if requests are sent from
+    /// threads it owns. The `Client` still blocks the calling thread, so
+    /// it must not be used from within an async context of that runtime.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let rt = tokio::runtime::Runtime::new()?;
+    ///
+    /// let client = reqwest::blocking::Client::builder()
+    ///     .runtime(rt.handle().clone())
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn runtime(mut self, handle: tokio::runtime::Handle) -> ClientBuilder {
+        self.runtime = Some(handle);
+        self
+    }
+
     // Higher-level options
 
     /// Sets the `User-Agent` header to be used by this client.
@@ -174,6 +208,13 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.default_headers(headers))
     }
 
+    /// Sets default headers for the requests sent to a host or URL prefix.
+    ///
+    /// See [`crate::ClientBuilder::default_headers_for()`] for details.
+    pub fn default_headers_for(self, scope: &str, headers: header::HeaderMap) -> ClientBuilder {
+        self.with_inner(move |inner| inner.default_headers_for(scope, headers))
+    }
+
     /// Enable a persistent cookie store for the client.
     ///
     /// Cookies received in responses will be preserved and included in
@@ -418,6 +459,168 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.connection_verbose(verbose))
     }
 
+    /// Add the trace context of the caller to every request sent, so that
+    /// distributed traces include them.
+    ///
+    /// The context is looked up on the thread calling `send()`. See
+    /// [`crate::ClientBuilder::trace_context()`] for details.
+    pub fn trace_context(self, propagation: crate::trace_context::Propagation) -> ClientBuilder {
+        self.with_inner(move |inner| inner.trace_context(propagation))
+    }
+
+    /// Stamp every request sent with an ID in the header `name`, made by
+    /// `generate`, to correlate it across services.
+    ///
+    /// See [`crate::ClientBuilder::request_id()`] for details.
+    pub fn request_id<F>(self, name: http::header::HeaderName, generate: F) -> ClientBuilder
+    where
+        F: Fn() -> HeaderValue + Send + Sync + 'static,
+    {
+        self.with_inner(move |inner| inner.request_id(name, generate))
+    }
+
+    /// Record every request sent and response received into a HAR
+    /// [`Recorder`](crate::har::Recorder).
+    ///
+    /// It can be overridden for a single request with
+    /// [`RequestBuilder::har()`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `har` feature to be enabled.
+    ///
+    /// [`RequestBuilder::har()`]: crate::blocking::RequestBuilder::har
+    #[cfg(feature = "har")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "har")))]
+    pub fn har(self, recorder: crate::har::Recorder) -> ClientBuilder {
+        self.with_inner(move |inner| inner.har(recorder))
+    }
+
+    /// Cache responses, and reuse them as allowed by HTTP caching rules.
+    ///
+    /// See [`crate::ClientBuilder::http_cache()`] for details.
+    pub fn http_cache(self, config: crate::cache::CacheConfig) -> ClientBuilder {
+        self.with_inner(move |inner| inner.http_cache(config))
+    }
+
+    /// Enforce HTTP Strict Transport Security, sending the `http` requests
+    /// to the hosts known to require it over `https` instead.
+    ///
+    /// See [`crate::ClientBuilder::hsts()`] for details.
+    pub fn hsts(self, config: crate::hsts::HstsConfig) -> ClientBuilder {
+        self.with_inner(move |inner| inner.hsts(config))
+    }
+
+    /// Record requests and responses into a [`Cassette`], or answer requests
+    /// from it.
+    ///
+    /// See [`crate::ClientBuilder::cassette()`] for details.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `vcr` feature to be enabled.
+    ///
+    /// [`Cassette`]: crate::vcr::Cassette
+    #[cfg(feature = "vcr")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "vcr")))]
+    pub fn cassette(self, cassette: crate::vcr::Cassette) -> ClientBuilder {
+        self.with_inner(move |inner| inner.cassette(cassette))
+    }
+
+    /// Answer requests with `handler`, in-process, instead of sending them
+    /// over the network.
+    ///
+    /// The handler is called on the client's background runtime. See
+    /// [`crate::ClientBuilder::mock_transport()`] for details.
+    pub fn mock_transport<F>(self, handler: F) -> ClientBuilder
+    where
+        F: Fn(http::Request<Bytes>) -> Result<http::Response<Bytes>, BoxError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.with_inner(move |inner| inner.mock_transport(handler))
+    }
+
+    // Bandwidth options
+
+    /// Limit the rate at which response bodies are read, in bytes per second.
+    ///
+    /// The limit is shared by all requests made with the `Client`.
+    ///
+    /// Default is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn max_download_rate(self, bytes_per_sec: u64) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_download_rate(bytes_per_sec))
+    }
+
+    /// Limit the rate at which request bodies are sent, in bytes per second.
+    ///
+    /// The limit is shared by all requests made with the `Client`.
+    ///
+    /// Default is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn max_upload_rate(self, bytes_per_sec: u64) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_upload_rate(bytes_per_sec))
+    }
+
+    /// Limit the rate at which requests are sent, in requests per second.
+    ///
+    /// The limit is shared by all requests made with the `Client`. Requests
+    /// made faster than that wait for their turn before being sent; the wait
+    /// counts towards their `timeout`.
+    ///
+    /// Default is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` is zero.
+    pub fn max_requests_per_second(self, requests: u32) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_requests_per_second(requests))
+    }
+
+    /// Limit the rate at which requests are sent to each host, in requests
+    /// per second.
+    ///
+    /// Default is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` is zero.
+    pub fn max_requests_per_second_per_host(self, requests: u32) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_requests_per_second_per_host(requests))
+    }
+
+    /// Set the maximum size of a response body, in bytes.
+    ///
+    /// Reading a larger body fails with an error for which
+    /// [`Error::is_body_too_large()`] returns `true`.
+    ///
+    /// Default is no limit.
+    ///
+    /// [`Error::is_body_too_large()`]: crate::Error::is_body_too_large
+    pub fn max_response_size(self, bytes: u64) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_response_size(bytes))
+    }
+
+    /// Reuse the buffers that response bodies are read into, starting with
+    /// room for `initial_size` bytes and dropped once grown past `max_size`.
+    ///
+    /// Default is no pool.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial_size` is larger than `max_size`.
+    pub fn body_buffer_pool(self, initial_size: usize, max_size: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.body_buffer_pool(initial_size, max_size))
+    }
+
     // HTTP options
 
     /// Set an optional timeout for idle sockets being kept-alive.
@@ -437,11 +640,37 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.pool_max_idle_per_host(max))
     }
 
+    /// Drop the idle connections of the pool when the network changes.
+    ///
+    /// See [`crate::ClientBuilder::pool_invalidate_on_network_change()`] for details.
+    pub fn pool_invalidate_on_network_change(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(move |inner| inner.pool_invalidate_on_network_change(enabled))
+    }
+
+    /// Send idempotent requests again when their pooled connection turns
+    /// out to be closed.
+    ///
+    /// See [`crate::ClientBuilder::pool_retry_stale_connections()`] for details.
+    pub fn pool_retry_stale_connections(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(move |inner| inner.pool_retry_stale_connections(enabled))
+    }
+
     /// Send headers as title case instead of lowercase.
     pub fn http1_title_case_headers(self) -> ClientBuilder {
         self.with_inner(|inner| inner.http1_title_case_headers())
     }
 
+    /// Send the given header names with exactly this casing on HTTP/1.
+    ///
+    /// See [`crate::ClientBuilder::http1_header_case()`] for details.
+    pub fn http1_header_case<I>(self, names: I) -> ClientBuilder
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.with_inner(|inner| inner.http1_header_case(names))
+    }
+
     /// Set whether HTTP/1 connections will accept obsolete line folding for
     /// header values.
     ///
@@ -482,6 +711,15 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.http2_prior_knowledge())
     }
 
+    /// Use HTTP/2 with prior knowledge for `http://` URLs only.
+    ///
+    /// Requests to `https://` URLs still negotiate their version with ALPN.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_prior_knowledge_cleartext(self) -> ClientBuilder {
+        self.with_inner(|inner| inner.http2_prior_knowledge_cleartext())
+    }
+
     /// Sets the `SETTINGS_INITIAL_WINDOW_SIZE` option for HTTP2 stream-level flow control.
     ///
     /// Default is currently 65,535 but may change internally to optimize for common uses.
@@ -502,8 +740,14 @@ impl ClientBuilder {
 
     /// Sets whether to use an adaptive flow control.
     ///
+    /// The window sizes are grown to match the bandwidth-delay product of the
+    /// connection, measured with PING frames, which helps throughput on
+    /// high-latency links without tuning the sizes by hand.
+    ///
     /// Enabling this will override the limits set in `http2_initial_stream_window_size` and
     /// `http2_initial_connection_window_size`.
+    ///
+    /// Default is `false`.
     #[cfg(feature = "http2")]
     #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
     pub fn http2_adaptive_window(self, enabled: bool) -> ClientBuilder {
@@ -528,6 +772,39 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.http2_max_header_list_size(max_header_size_bytes))
     }
 
+    /// Sets an interval for HTTP2 Ping frames should be sent to keep a connection alive.
+    ///
+    /// Pass `None` to disable HTTP2 keep-alive.
+    /// Default is currently disabled.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_keep_alive_interval(self, interval: impl Into<Option<Duration>>) -> ClientBuilder {
+        self.with_inner(|inner| inner.http2_keep_alive_interval(interval))
+    }
+
+    /// Sets a timeout for receiving an acknowledgement of the keep-alive ping.
+    ///
+    /// If the ping is not acknowledged within the timeout, the connection will be closed.
+    /// Does nothing if `http2_keep_alive_interval` is disabled.
+    /// Default is currently disabled.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_keep_alive_timeout(self, timeout: Duration) -> ClientBuilder {
+        self.with_inner(|inner| inner.http2_keep_alive_timeout(timeout))
+    }
+
+    /// Sets whether HTTP2 keep-alive should apply while the connection is idle.
+    ///
+    /// If disabled, keep-alive pings are only sent while there are open request/responses streams.
+    /// If enabled, pings are also sent when no streams are active.
+    /// Does nothing if `http2_keep_alive_interval` is disabled.
+    /// Default is `false`.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_keep_alive_while_idle(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.http2_keep_alive_while_idle(enabled))
+    }
+
     /// This requires the optional `http3` feature to be
     /// enabled.
     #[cfg(feature = "http3")]
@@ -635,6 +912,16 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.http3_send_grease(enabled))
     }
 
+    /// Send requests over HTTP/3 to the origins advertising it with an
+    /// `Alt-Svc` header.
+    ///
+    /// See [`crate::ClientBuilder::http3_alt_svc()`] for details.
+    #[cfg(feature = "http3")]
+    #[cfg_attr(docsrs, doc(cfg(all(reqwest_unstable, feature = "http3",))))]
+    pub fn http3_alt_svc(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.http3_alt_svc(enabled))
+    }
+
     // TCP options
 
     /// Set whether sockets have `TCP_NODELAY` enabled.
@@ -870,6 +1157,21 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.identity(identity))
     }
 
+    /// Sets the identity to be used for client certificate authentication
+    /// with a specific host.
+    ///
+    /// See [`crate::ClientBuilder::identity_for_host()`] for details.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn identity_for_host(self, host: &str, identity: Identity) -> ClientBuilder {
+        self.with_inner(move |inner| inner.identity_for_host(host, identity))
+    }
+
     /// Controls the use of hostname verification.
     ///
     /// Defaults to `false`.
@@ -934,6 +1236,76 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.danger_accept_invalid_certs(accept_invalid_certs))
     }
 
+    /// Controls the resumption of TLS sessions.
+    ///
+    /// See [`crate::ClientBuilder::tls_session_resumption()`] for details.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_session_resumption(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.tls_session_resumption(enabled))
+    }
+
+    /// Controls the use of session tickets to resume TLS 1.2 sessions.
+    ///
+    /// See [`crate::ClientBuilder::tls_session_tickets()`] for details.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_session_tickets(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.tls_session_tickets(enabled))
+    }
+
+    /// Sets the cache TLS sessions are stored in for resumption.
+    ///
+    /// See [`crate::ClientBuilder::tls_session_cache()`] for details.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_session_cache(self, cache: crate::tls::SessionCache) -> ClientBuilder {
+        self.with_inner(|inner| inner.tls_session_cache(cache))
+    }
+
+    /// Sets how server certificates are checked for revocation using OCSP.
+    ///
+    /// See [`crate::ClientBuilder::tls_ocsp()`] for details.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_ocsp(self, policy: crate::tls::OcspPolicy) -> ClientBuilder {
+        self.with_inner(|inner| inner.tls_ocsp(policy))
+    }
+
+    /// Prefer post-quantum hybrid key exchange for TLS connections.
+    ///
+    /// See [`crate::ClientBuilder::tls_post_quantum()`] for details.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_post_quantum(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.tls_post_quantum(enabled))
+    }
+
     /// Controls the use of TLS server name indication.
     ///
     /// Defaults to `true`.
@@ -946,6 +1318,23 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.tls_sni(tls_sni))
     }
 
+    /// Sets the protocols offered with ALPN during the TLS handshake, in
+    /// order of preference.
+    ///
+    /// See [`crate::ClientBuilder::alpn_protocols()`] for details.
+    #[cfg(feature = "__tls")]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "default-tls", feature = "native-tls", feature = "rustls")))
+    )]
+    pub fn alpn_protocols<I>(self, protocols: I) -> ClientBuilder
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.with_inner(|inner| inner.alpn_protocols(protocols))
+    }
+
     /// Set the minimum required TLS version for connections.
     ///
     /// By default, the TLS backend's own default is used.
@@ -1146,6 +1535,74 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.dns_resolver(resolver))
     }
 
+    /// Adds a new Tower [`Layer`](https://docs.rs/tower/latest/tower/trait.Layer.html) around
+    /// the [`Service`](https://docs.rs/tower/latest/tower/trait.Service.html) which sends the
+    /// requests of this client.
+    ///
+    /// The layers run on the client's background runtime. They see each request once, with
+    /// the default headers added, and the final response, after redirects have been followed,
+    /// retries made and the body decompressed.
+    ///
+    /// Each subsequent invocation of this function will wrap previous layers.
+    ///
+    /// Example usage:
+    /// ```
+    /// let client = reqwest::blocking::Client::builder()
+    ///     .layer(tower::limit::concurrency::ConcurrencyLimitLayer::new(16))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn layer<L>(self, layer: L) -> ClientBuilder
+    where
+        L: Layer<BoxedClientService> + Clone + Send + Sync + 'static,
+        L::Service: Service<
+                http::Request<async_impl::Body>,
+                Response = http::Response<async_impl::Body>,
+                Error = BoxError,
+            > + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<http::Request<async_impl::Body>>>::Future: Send + 'static,
+    {
+        self.with_inner(|inner| inner.layer(layer))
+    }
+
+    /// Call `hook` with each request before it is sent.
+    ///
+    /// The hook can change the request, for instance to add a header, and
+    /// returns [`ControlFlow::Continue`] to send it, or answer the request
+    /// itself with [`ControlFlow::Break`]. It runs on the client's background
+    /// runtime.
+    ///
+    /// See [`crate::ClientBuilder::on_request`] for more details.
+    ///
+    /// [`ControlFlow::Continue`]: std::ops::ControlFlow::Continue
+    /// [`ControlFlow::Break`]: std::ops::ControlFlow::Break
+    pub fn on_request<F>(self, hook: F) -> ClientBuilder
+    where
+        F: Fn(
+                &mut http::Request<async_impl::Body>,
+            ) -> ControlFlow<http::Response<async_impl::Body>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.with_inner(|inner| inner.on_request(hook))
+    }
+
+    /// Call `hook` with each response before it is returned.
+    ///
+    /// The response is the final one, after redirects have been followed, and
+    /// its body hasn't been read yet. The hook runs on the client's
+    /// background runtime.
+    pub fn on_response<F>(self, hook: F) -> ClientBuilder
+    where
+        F: Fn(&mut http::Response<async_impl::Body>) + Send + Sync + 'static,
+    {
+        self.with_inner(|inner| inner.on_response(hook))
+    }
+
     /// Adds a new Tower [`Layer`](https://docs.rs/tower/latest/tower/trait.Layer.html) to the
     /// base connector [`Service`](https://docs.rs/tower/latest/tower/trait.Service.html) which
     /// is responsible for connection establishment.
@@ -1265,6 +1722,75 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.negotiate_with_credentials(username, password))
     }
 
+    /// Also ask for tickets to an SPN with the port, for URLs with a
+    /// non-default port.
+    ///
+    /// Default is `false`.
+    ///
+    /// See [`crate::ClientBuilder::negotiate_spn_include_port()`] for details.
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_spn_include_port(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.negotiate_spn_include_port(enabled))
+    }
+
+    /// Set what Negotiate authentication does for URLs whose host is an IP
+    /// address.
+    ///
+    /// See [`crate::ClientBuilder::negotiate_ip_literal_policy()`] for details.
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_ip_literal_policy(
+        self,
+        policy: crate::negotiate::IpLiteralPolicy,
+    ) -> ClientBuilder {
+        self.with_inner(|inner| inner.negotiate_ip_literal_policy(policy))
+    }
+
+    /// Set the domain for a username given to
+    /// [`ClientBuilder::negotiate_with_credentials()`] without one.
+    ///
+    /// See [`crate::ClientBuilder::negotiate_default_domain()`] for details.
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_default_domain<D>(self, domain: D) -> ClientBuilder
+    where
+        D: Into<String>,
+    {
+        self.with_inner(|inner| inner.negotiate_default_domain(domain))
+    }
+
+    /// Only authenticate with Kerberos, never falling back to NTLM or Basic.
+    ///
+    /// Default is `false`.
+    ///
+    /// See [`crate::ClientBuilder::negotiate_kerberos_only()`] for details.
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_kerberos_only(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.negotiate_kerberos_only(enabled))
+    }
+
+    /// Authenticate with these credentials to the hosts matching `pattern`,
+    /// such as `*.emea.corp`.
+    ///
+    /// See [`crate::ClientBuilder::negotiate_credentials_for()`] for details.
+    #[cfg(feature = "negotiate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "negotiate")))]
+    pub fn negotiate_credentials_for<D, U, P>(
+        self,
+        pattern: D,
+        username: U,
+        password: P,
+    ) -> ClientBuilder
+    where
+        D: Into<String>,
+        U: Into<String>,
+        P: Into<String>,
+    {
+        self.with_inner(|inner| inner.negotiate_credentials_for(pattern, username, password))
+    }
+
     // private
 
     fn with_inner<F>(mut self, func: F) -> ClientBuilder
@@ -1281,6 +1807,7 @@ impl From<async_impl::ClientBuilder> for ClientBuilder {
         Self {
             inner: builder,
             timeout: Timeout::default(),
+            runtime: None,
         }
     }
 }
@@ -1397,6 +1924,30 @@ impl Client {
     pub fn execute(&self, request: Request) -> crate::Result<Response> {
         self.inner.execute_request(request)
     }
+
+    /// Returns the cookie store of this client, if it has one.
+    ///
+    /// See [`crate::Client::cookie_store()`] for details.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `cookies` feature to be enabled.
+    #[cfg(feature = "cookies")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cookies")))]
+    pub fn cookie_store(&self) -> Option<Arc<dyn crate::cookie::CookieStore>> {
+        self.inner.cookie_store.clone()
+    }
+
+    /// Drops the idle connections of the pool.
+    ///
+    /// See [`crate::Client::invalidate_pool()`] for details.
+    pub fn invalidate_pool(&self) {
+        self.inner.pools.invalidate_pool();
+    }
+
+    pub(super) fn curl_defaults(&self) -> &async_impl::curl::Defaults {
+        &self.inner.curl
+    }
 }
 
 impl fmt::Debug for Client {
@@ -1419,6 +1970,12 @@ impl fmt::Debug for ClientBuilder {
 struct ClientHandle {
     timeout: Timeout,
     inner: Arc<InnerClientHandle>,
+    curl: Arc<async_impl::curl::Defaults>,
+    trace_context: Option<crate::trace_context::Propagation>,
+    #[cfg(feature = "cookies")]
+    cookie_store: Option<Arc<dyn crate::cookie::CookieStore>>,
+    // Shares its pools with the client on the runtime thread.
+    pools: async_impl::Client,
 }
 
 type OneshotResponse = oneshot::Sender<crate::Result<async_impl::Response>>;
@@ -1431,11 +1988,15 @@ struct InnerClientHandle {
 
 impl Drop for InnerClientHandle {
     fn drop(&mut self) {
-        let id = self
-            .thread
-            .as_ref()
-            .map(|h| h.thread().id())
-            .expect("thread not dropped yet");
+        let id = match self.thread.as_ref() {
+            Some(h) => h.thread().id(),
+            None => {
+                // Running on a runtime owned by the caller; closing the
+                // channel ends the task.
+                self.tx.take();
+                return;
+            }
+        };
 
         trace!("closing runtime thread ({id:?})");
         self.tx.take();
@@ -1448,9 +2009,40 @@ impl Drop for InnerClientHandle {
 impl ClientHandle {
     fn new(builder: ClientBuilder) -> crate::Result<ClientHandle> {
         let timeout = builder.timeout;
+        let runtime = builder.runtime;
         let builder = builder.inner;
+        // Made here, for the async client and curl requests to share it.
+        #[cfg(feature = "cookies")]
+        let builder = builder.make_cookie_jar();
+        #[cfg(feature = "cookies")]
+        let cookie_store = builder.cookie_provider_ref();
+        let curl = Arc::new(builder.curl_defaults());
+        let trace_context = builder.trace_context_ref();
         let (tx, rx) = mpsc::unbounded_channel::<(async_impl::Request, OneshotResponse)>();
-        let (spawn_tx, spawn_rx) = oneshot::channel::<crate::Result<()>>();
+
+        if let Some(runtime) = runtime {
+            let client = {
+                let _enter = runtime.enter();
+                builder.build()?
+            };
+            let pools = client.clone();
+            runtime.spawn(serve(client, rx));
+
+            return Ok(ClientHandle {
+                timeout,
+                inner: Arc::new(InnerClientHandle {
+                    tx: Some(tx),
+                    thread: None,
+                }),
+                curl,
+                trace_context,
+                #[cfg(feature = "cookies")]
+                cookie_store,
+                pools,
+            });
+        }
+
+        let (spawn_tx, spawn_rx) = oneshot::channel::<crate::Result<async_impl::Client>>();
         let handle = thread::Builder::new()
             .name("reqwest-internal-sync-runtime".into())
             .spawn(move || {
@@ -1479,17 +2071,12 @@ impl ClientHandle {
                         }
                         Ok(v) => v,
                     };
-                    if let Err(e) = spawn_tx.send(Ok(())) {
+                    if let Err(e) = spawn_tx.send(Ok(client.clone())) {
                         error!("Failed to communicate successful startup: {e:?}");
                         return;
                     }
 
-                    let mut rx = rx;
-
-                    while let Some((req, req_tx)) = rx.recv().await {
-                        let req_fut = client.execute(req);
-                        tokio::spawn(forward(req_fut, req_tx));
-                    }
+                    serve(client, rx).await;
 
                     trace!("({:?}) Receiver is shutdown", thread::current().id());
                 };
@@ -1503,11 +2090,11 @@ impl ClientHandle {
             .map_err(crate::error::builder)?;
 
         // Wait for the runtime thread to start up...
-        match wait::timeout(spawn_rx, None) {
-            Ok(Ok(())) => (),
+        let pools = match wait::timeout(spawn_rx, None) {
+            Ok(Ok(client)) => client,
             Ok(Err(err)) => return Err(err),
             Err(_canceled) => event_loop_panicked(),
-        }
+        };
 
         let inner_handle = Arc::new(InnerClientHandle {
             tx: Some(tx),
@@ -1517,10 +2104,20 @@ impl ClientHandle {
         Ok(ClientHandle {
             timeout,
             inner: inner_handle,
+            curl,
+            trace_context,
+            #[cfg(feature = "cookies")]
+            cookie_store,
+            pools,
         })
     }
 
-    fn execute_request(&self, req: Request) -> crate::Result<Response> {
+    fn execute_request(&self, mut req: Request) -> crate::Result<Response> {
+        // The context of the caller, before the request moves to the
+        // runtime thread.
+        if let Some(ref propagation) = self.trace_context {
+            propagation.inject(req.headers_mut());
+        }
         let (tx, rx) = oneshot::channel();
         let (req, body) = req.into_async();
         let url = req.url().clone();
@@ -1558,6 +2155,16 @@ impl ClientHandle {
     }
 }
 
+async fn serve(
+    client: async_impl::Client,
+    mut rx: mpsc::UnboundedReceiver<(async_impl::Request, OneshotResponse)>,
+) {
+    while let Some((req, req_tx)) = rx.recv().await {
+        let req_fut = client.execute(req);
+        tokio::spawn(forward(req_fut, req_tx));
+    }
+}
+
 async fn forward<F>(fut: F, mut tx: OneshotResponse)
 where
     F: Future<Output = crate::Result<async_impl::Response>>,
@@ -1,6 +1,7 @@
 use std::convert::TryFrom;
 use std::fmt;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use http::{request::Parts, Request as HttpRequest, Version};
 #[cfg(any(feature = "query", feature = "form", feature = "json"))]
@@ -12,10 +13,24 @@ use super::body::{self, Body};
 #[cfg(feature = "multipart")]
 use super::multipart;
 use super::Client;
+use crate::async_impl::informational::Informational;
+use crate::async_impl::progress::Progress;
+use crate::async_impl::throttle::Throttle;
+use crate::cache::CachedResponse;
+#[cfg(feature = "har")]
+use crate::config::Har;
+#[cfg(feature = "__rustls")]
+use crate::config::TlsVerification;
+use crate::config::{
+    DownloadProgress, DownloadRate, ExpectContinue, IfCached, OnInformational, RedirectPolicy,
+    RequestConfig, UploadProgress, UploadRate,
+};
 #[cfg(any(feature = "multipart", feature = "form", feature = "json"))]
 use crate::header::CONTENT_TYPE;
 use crate::header::{HeaderMap, HeaderName, HeaderValue};
-use crate::{async_impl, Method, Url};
+#[cfg(feature = "__rustls")]
+use crate::tls::TlsOverride;
+use crate::{async_impl, redirect, Method, StatusCode, Url};
 
 /// A request which can be executed with `Client::execute()`.
 pub struct Request {
@@ -115,6 +130,18 @@ impl Request {
         self.inner.timeout_mut()
     }
 
+    /// Get the deadline.
+    #[inline]
+    pub fn deadline(&self) -> Option<&Instant> {
+        self.inner.deadline()
+    }
+
+    /// Get a mutable reference to the deadline.
+    #[inline]
+    pub fn deadline_mut(&mut self) -> &mut Option<Instant> {
+        self.inner.deadline_mut()
+    }
+
     /// Attempts to clone the `Request`.
     ///
     /// None is returned if a body is which can not be cloned. This can be because the body is a
@@ -304,6 +331,50 @@ impl RequestBuilder {
         self.header_sensitive(crate::header::AUTHORIZATION, &*header_value, true)
     }
 
+    /// Set the priority of this request, as the `Priority` header of
+    /// RFC 9218.
+    ///
+    /// `urgency` ranges from `0`, the most urgent, to `7`; servers treat
+    /// requests without a priority as `3`. An `incremental` response is
+    /// useful to the client as it arrives, so the server may interleave it
+    /// with other incremental responses of the same urgency rather than
+    /// sending them one after the other.
+    ///
+    /// Servers that implement RFC 9218 schedule the streams of a shared
+    /// HTTP/2 or HTTP/3 connection by this priority, which lets interactive
+    /// requests go ahead of bulk transfers. Over HTTP/1.1 it is only a hint.
+    ///
+    /// # Errors
+    ///
+    /// Sending the request fails if `urgency` is greater than `7`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::blocking::Client::new()
+    ///     .get("https://hyper.rs/search?q=reqwest")
+    ///     .priority(1, false)
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn priority(mut self, urgency: u8, incremental: bool) -> RequestBuilder {
+        let mut error = None;
+        if let Ok(ref mut req) = self.request {
+            match crate::util::priority(urgency, incremental) {
+                Ok(value) => {
+                    req.headers_mut().insert(crate::util::PRIORITY, value);
+                }
+                Err(err) => error = Some(err),
+            }
+        }
+        if let Some(err) = error {
+            self.request = Err(err);
+        }
+        self
+    }
+
     /// Set the request body.
     ///
     /// # Examples
@@ -366,6 +437,310 @@ impl RequestBuilder {
         self
     }
 
+    /// Sets a deadline for this request.
+    ///
+    /// Every attempt made on behalf of this request, including redirects and
+    /// retries, must finish before the deadline passes.
+    pub fn deadline(mut self, deadline: Instant) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.deadline_mut() = Some(deadline);
+        }
+        self
+    }
+
+    /// Set the redirect `Policy` for this request only.
+    ///
+    /// It replaces the policy configured using `ClientBuilder::redirect()`,
+    /// for instance to not follow redirects when fetching a presigned URL
+    /// from a client that otherwise does.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::blocking::Client::new()
+    ///     .get("https://hyper.rs")
+    ///     .redirect(reqwest::redirect::Policy::none())
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn redirect(mut self, policy: redirect::Policy) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<RedirectPolicy>::get_mut(req.inner.extensions_mut()) =
+                Some(Arc::new(policy));
+        }
+        self
+    }
+
+    /// Limit the rate at which the response body is read, in bytes per second.
+    ///
+    /// The limit applies only to this request and replaces the one configured
+    /// using `ClientBuilder::max_download_rate()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn max_download_rate(mut self, bytes_per_sec: u64) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<DownloadRate>::get_mut(req.inner.extensions_mut()) =
+                Some(Throttle::new(bytes_per_sec));
+        }
+        self
+    }
+
+    /// Limit the rate at which the request body is sent, in bytes per second.
+    ///
+    /// The limit applies only to this request and replaces the one configured
+    /// using `ClientBuilder::max_upload_rate()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn max_upload_rate(mut self, bytes_per_sec: u64) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<UploadRate>::get_mut(req.inner.extensions_mut()) =
+                Some(Throttle::new(bytes_per_sec));
+        }
+        self
+    }
+
+    /// Call `callback` as the response body is read, with the number of bytes
+    /// received so far and the size of the whole body if it is known.
+    ///
+    /// The size is known when the response has a `Content-Length` and isn't
+    /// decompressed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let body = reqwest::blocking::Client::new()
+    ///     .get("https://hyper.rs")
+    ///     .on_download_progress(|received, total| match total {
+    ///         Some(total) => println!("{received}/{total} bytes"),
+    ///         None => println!("{received} bytes"),
+    ///     })
+    ///     .send()?
+    ///     .bytes()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_download_progress<F>(mut self, callback: F) -> RequestBuilder
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<DownloadProgress>::get_mut(req.inner.extensions_mut()) =
+                Some(Progress::new(callback));
+        }
+        self
+    }
+
+    /// Call `callback` as the request body is sent, with the number of bytes
+    /// sent so far and the size of the whole body if it is known.
+    ///
+    /// Bytes are counted as they are handed to the connection, so this
+    /// includes streamed bodies and multipart forms. If the body is sent
+    /// again, such as after a redirect, the count starts over.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::blocking::Client::new()
+    ///     .post("https://hyper.rs")
+    ///     .body(vec![0; 1024 * 1024])
+    ///     .on_upload_progress(|sent, total| match total {
+    ///         Some(total) => println!("{sent}/{total} bytes"),
+    ///         None => println!("{sent} bytes"),
+    ///     })
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_upload_progress<F>(mut self, callback: F) -> RequestBuilder
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<UploadProgress>::get_mut(req.inner.extensions_mut()) =
+                Some(Progress::new(callback));
+        }
+        self
+    }
+
+    /// Send the request with `Expect: 100-continue`, holding the body back
+    /// until the server answers with `100 Continue`.
+    ///
+    /// This lets a server refuse a request, for instance with a `401` asking
+    /// for authentication, before a large body is uploaded for nothing. If the
+    /// server doesn't answer within `timeout`, the body is sent anyway, since
+    /// not every server supports the expectation. If the final response
+    /// arrives first, the body is never sent and the connection is closed
+    /// once the response is dropped.
+    ///
+    /// Requests without a body are sent as usual. `100 Continue` is only
+    /// received on HTTP/1.1 connections; on other versions the body is sent
+    /// once the timeout elapses.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::blocking::Client::new()
+    ///     .put("https://hyper.rs/upload")
+    ///     .body(vec![0; 64 * 1024 * 1024])
+    ///     .expect_continue(Duration::from_secs(1))
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expect_continue(mut self, timeout: Duration) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<ExpectContinue>::get_mut(req.inner.extensions_mut()) = Some(timeout);
+        }
+        self
+    }
+
+    /// Call `callback` with the status and headers of each interim `1xx`
+    /// response received before the final response, such as
+    /// `103 Early Hints`.
+    ///
+    /// This lets a client act on the `Link` headers of early hints, for
+    /// instance to preconnect or prefetch, while the server is still
+    /// preparing the final response. Interim responses are only reported on
+    /// HTTP/1.1 connections; `101 Switching Protocols` is a final response
+    /// and isn't reported here.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::blocking::Client::new()
+    ///     .get("https://hyper.rs")
+    ///     .on_informational(|status, headers| {
+    ///         if status == reqwest::StatusCode::EARLY_HINTS {
+    ///             for link in headers.get_all(reqwest::header::LINK) {
+    ///                 println!("early hint: {link:?}");
+    ///             }
+    ///         }
+    ///     })
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_informational<F>(mut self, callback: F) -> RequestBuilder
+    where
+        F: Fn(StatusCode, &HeaderMap) + Send + Sync + 'static,
+    {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<OnInformational>::get_mut(req.inner.extensions_mut()) =
+                Some(Informational::new(callback));
+        }
+        self
+    }
+
+    /// Controls the use of certificate validation for this request only.
+    ///
+    /// The client's other requests are unaffected: this request is sent on
+    /// connections of its own, which aren't shared with them.
+    ///
+    /// # Warning
+    ///
+    /// You should think very carefully before using this method. If
+    /// invalid certificates are trusted, *any* certificate for *any* site
+    /// will be trusted for use. This includes expired certificates. This
+    /// introduces significant vulnerabilities, and should only be used
+    /// as a last resort.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend. Otherwise, sending the request
+    /// fails.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            let tls = RequestConfig::<TlsVerification>::get_mut(req.inner.extensions_mut());
+            if accept_invalid_certs {
+                *tls = Some(TlsOverride::AcceptInvalidCerts);
+            } else if *tls == Some(TlsOverride::AcceptInvalidCerts) {
+                *tls = None;
+            }
+        }
+        self
+    }
+
+    /// Trusts only the given root certificates for this request.
+    ///
+    /// The client's trust roots, and any other verification settings, are
+    /// ignored for this request. Like
+    /// [`danger_accept_invalid_certs()`](RequestBuilder::danger_accept_invalid_certs),
+    /// the request is sent on connections of its own.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled, and only
+    /// applies when using the rustls backend. Otherwise, sending the request
+    /// fails.
+    #[cfg(feature = "__rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub fn tls_certs_only(
+        mut self,
+        certs: impl IntoIterator<Item = crate::Certificate>,
+    ) -> RequestBuilder {
+        let mut error = None;
+        if let Ok(ref mut req) = self.request {
+            match crate::tls::rustls_der(certs.into_iter().collect()) {
+                Ok(certs) => {
+                    *RequestConfig::<TlsVerification>::get_mut(req.inner.extensions_mut()) =
+                        Some(TlsOverride::CertsOnly(certs));
+                }
+                Err(err) => error = Some(err),
+            }
+        }
+        if let Some(err) = error {
+            self.request = Err(err);
+        }
+        self
+    }
+
+    /// Make the request conditional on `cached` having changed.
+    ///
+    /// See [`crate::RequestBuilder::if_cached()`] for details.
+    pub fn if_cached(mut self, cached: &CachedResponse) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            if cached.has_validators() {
+                cached.add_conditions(req.headers_mut());
+                *RequestConfig::<IfCached>::get_mut(req.inner.extensions_mut()) =
+                    Some(cached.clone());
+            }
+        }
+        self
+    }
+
+    /// Record this request, and the response to it, into a HAR
+    /// [`Recorder`](crate::har::Recorder).
+    ///
+    /// This overrides the recorder set with [`ClientBuilder::har()`].
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `har` feature to be enabled.
+    ///
+    /// [`ClientBuilder::har()`]: crate::blocking::ClientBuilder::har
+    #[cfg(feature = "har")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "har")))]
+    pub fn har(mut self, recorder: crate::har::Recorder) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *RequestConfig::<Har>::get_mut(req.inner.extensions_mut()) = Some(recorder);
+        }
+        self
+    }
+
     /// Modify the query string of the URL.
     ///
     /// Modifies the URL of this request, adding the parameters provided.
@@ -424,6 +799,48 @@ impl RequestBuilder {
         self
     }
 
+    /// Modify the query string of the URL, with control over how arrays,
+    /// `None`s and existing parameters are handled.
+    ///
+    /// With default [`Options`][crate::query::Options], this is the same as
+    /// [`query()`][Self::query], except that sequences of values are
+    /// accepted as well.
+    ///
+    /// ```rust
+    /// # fn run() -> Result<(), reqwest::Error> {
+    /// use reqwest::query::{ArrayFormat, Options};
+    ///
+    /// let req = reqwest::blocking::Client::new()
+    ///     .get("https://example.com/items")
+    ///     .query_with(&[("id", [1, 2])], Options::new().arrays(ArrayFormat::Brackets))
+    ///     .build()?;
+    /// assert_eq!(req.url().query(), Some("id%5B%5D=1&id%5B%5D=2"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `query` feature to be enabled.
+    ///
+    /// # Errors
+    /// This method will fail if the object you provide cannot be serialized
+    /// into a query string, such as a nested map.
+    #[cfg(feature = "query")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "query")))]
+    pub fn query_with<T: Serialize + ?Sized>(
+        mut self,
+        query: &T,
+        options: crate::query::Options,
+    ) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            if let Err(err) = crate::query::apply(req.url_mut(), query, options) {
+                self.request = Err(err);
+            }
+        }
+        self
+    }
+
     /// Set HTTP version
     pub fn version(mut self, version: Version) -> RequestBuilder {
         if let Ok(ref mut req) = self.request {
@@ -651,6 +1068,37 @@ impl RequestBuilder {
                 request: Ok(req),
             })
     }
+
+    /// Returns the `curl` command that sends the same request, quoted for a
+    /// POSIX shell.
+    ///
+    /// See [`crate::RequestBuilder::to_curl_command()`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let curl = reqwest::blocking::Client::new()
+    ///     .delete("https://hyper.rs/items/1")
+    ///     .to_curl_command();
+    /// assert_eq!(
+    ///     curl.as_deref(),
+    ///     Some("curl -X DELETE -H 'accept: */*' https://hyper.rs/items/1")
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_curl_command(&self) -> Option<String> {
+        let req = self.request.as_ref().ok()?;
+        Some(crate::async_impl::curl::command(
+            self.client.curl_defaults(),
+            req.method(),
+            req.url(),
+            req.version(),
+            req.headers(),
+            req.body().map(Body::as_bytes).into(),
+        ))
+    }
 }
 
 impl<T> TryFrom<HttpRequest<T>> for Request
@@ -1,7 +1,8 @@
 use std::fmt;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::mem;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::pin::Pin;
 use std::time::Duration;
 
@@ -271,6 +272,17 @@ impl Response {
         })
     }
 
+    /// Read the full response body, and keep it with the status and headers
+    /// for a later conditional request.
+    ///
+    /// See [`RequestBuilder::if_cached()`](crate::blocking::RequestBuilder::if_cached).
+    pub fn into_cached(self) -> crate::Result<crate::cache::CachedResponse> {
+        wait::timeout(self.inner.into_cached(), self.timeout).map_err(|e| match e {
+            wait::Waited::TimedOut(e) => crate::error::decode(e),
+            wait::Waited::Inner(e) => e,
+        })
+    }
+
     /// Get the response text.
     ///
     /// This method decodes the response body with BOM sniffing
@@ -361,6 +373,78 @@ impl Response {
         io::copy(self, w).map_err(crate::error::decode_io)
     }
 
+    /// Stream the response body into the file at `path`, returning the number
+    /// of bytes written.
+    ///
+    /// The body is first written to a new temporary file next to `path`,
+    /// named after it with a random suffix and `.tmp` appended, which then
+    /// replaces `path` once the whole body has been received. If anything
+    /// fails, the temporary file is removed and `path` is left as it was.
+    /// Failing to write the file gives an error for which
+    /// [`Error::is_io`](crate::Error::is_io) is true.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let written = reqwest::blocking::get("https://hyper.rs/logo.svg")?
+    ///     .error_for_status()?
+    ///     .save_to("logo.svg")?;
+    /// println!("saved {written} bytes");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save_to<P: AsRef<Path>>(self, path: P) -> crate::Result<u64> {
+        self.save(path.as_ref(), false)
+    }
+
+    /// Like [`Response::save_to`], but the file is also flushed to disk
+    /// before it replaces `path`, so its contents survive a crash or power
+    /// loss.
+    pub fn save_to_synced<P: AsRef<Path>>(self, path: P) -> crate::Result<u64> {
+        self.save(path.as_ref(), true)
+    }
+
+    fn save(mut self, path: &Path, sync: bool) -> crate::Result<u64> {
+        let (tmp, mut file) = loop {
+            let tmp = crate::util::temp_path(path);
+            let opened = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&tmp);
+            match opened {
+                Ok(file) => break (tmp, file),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(crate::error::io(e)),
+            }
+        };
+
+        let result = (|| {
+            let mut buf = [0; 8 * 1024];
+            let mut written = 0;
+            loop {
+                let n = match self.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(crate::error::decode_io(e)),
+                };
+                file.write_all(&buf[..n]).map_err(crate::error::io)?;
+                written += n as u64;
+            }
+            if sync {
+                file.sync_all().map_err(crate::error::io)?;
+            }
+            drop(file);
+            std::fs::rename(&tmp, path).map_err(crate::error::io)?;
+            Ok(written)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        result
+    }
+
     /// Turn a response into an error if the server returned an error.
     ///
     /// # Example
@@ -392,6 +476,42 @@ impl Response {
         })
     }
 
+    /// Turn a response into an error if the server returned an error, keeping
+    /// up to `limit` bytes of its body in the error.
+    ///
+    /// See [`crate::Response::error_for_status_with_body`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = reqwest::blocking::get("http://httpbin.org/status/400")?
+    ///     .error_for_status_with_body(1024);
+    /// if let Err(err) = res {
+    ///     println!("{:?}", err.body());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn error_for_status_with_body(self, limit: usize) -> crate::Result<Self> {
+        let Response {
+            body,
+            inner,
+            timeout,
+            _thread_handle,
+        } = self;
+        match wait::timeout(inner.error_for_status_with_body(limit), timeout) {
+            Ok(inner) => Ok(Response {
+                inner,
+                body,
+                timeout,
+                _thread_handle,
+            }),
+            Err(wait::Waited::TimedOut(e)) => Err(crate::error::decode(e)),
+            Err(wait::Waited::Inner(e)) => Err(e),
+        }
+    }
+
     /// Turn a reference to a response into an error if the server returned an error.
     ///
     /// # Example
@@ -0,0 +1,111 @@
+use bytes::Bytes;
+use http::header::{self, HeaderMap};
+use http::{StatusCode, Version};
+use url::Url;
+
+use crate::async_impl::{Body, Response};
+use crate::response::ResponseUrl;
+
+/// A response kept by the application, to make a conditional request for
+/// the same resource later.
+///
+/// A `CachedResponse` is made with [`Response::into_cached()`], which reads
+/// the body, and is given to [`RequestBuilder::if_cached()`]. If the
+/// server answers `304 Not Modified`, the response returned in its place
+/// has the status, headers and body of the `CachedResponse`, with the
+/// headers updated from the `304`. This suits a client polling a
+/// resource:
+///
+/// ```
+/// # async fn run() -> Result<(), reqwest::Error> {
+/// let client = reqwest::Client::new();
+/// let mut last: Option<reqwest::cache::CachedResponse> = None;
+/// for _ in 0..3 {
+///     let mut req = client.get("https://hyper.rs/feed.xml");
+///     if let Some(ref cached) = last {
+///         req = req.if_cached(cached);
+///     }
+///     let cached = req.send().await?.into_cached().await?;
+///     println!("{} bytes", cached.body().len());
+///     last = Some(cached);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Response::into_cached()`]: crate::Response::into_cached
+/// [`RequestBuilder::if_cached()`]: crate::RequestBuilder::if_cached
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    url: Url,
+    body: Bytes,
+}
+
+impl CachedResponse {
+    pub(crate) fn new(
+        status: StatusCode,
+        version: Version,
+        headers: HeaderMap,
+        url: Url,
+        body: Bytes,
+    ) -> CachedResponse {
+        CachedResponse {
+            status,
+            version,
+            headers,
+            url,
+            body,
+        }
+    }
+
+    /// Get the `StatusCode` of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the headers of the response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get the final `Url` of the response.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Get the body of the response.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Whether the response has an `ETag` or a `Last-Modified` date, without
+    /// which a request can't be made conditional.
+    pub fn has_validators(&self) -> bool {
+        self.headers.contains_key(header::ETAG) || self.headers.contains_key(header::LAST_MODIFIED)
+    }
+
+    /// Add the conditional headers validating the response to `headers`.
+    pub(crate) fn add_conditions(&self, headers: &mut HeaderMap) {
+        if let Some(etag) = self.headers.get(header::ETAG) {
+            headers.insert(header::IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(modified) = self.headers.get(header::LAST_MODIFIED) {
+            headers.insert(header::IF_MODIFIED_SINCE, modified.clone());
+        }
+    }
+
+    /// The response standing for `not_modified`, the `304` validating this
+    /// one.
+    pub(crate) fn revalidated(mut self, not_modified: &HeaderMap) -> Response {
+        super::policy::update_headers(&mut self.headers, not_modified);
+        let mut res = http::Response::new(Body::from(self.body));
+        *res.status_mut() = self.status;
+        *res.version_mut() = self.version;
+        *res.headers_mut() = self.headers;
+        res.extensions_mut().insert(ResponseUrl(self.url));
+        Response::from(res)
+    }
+}
@@ -0,0 +1,538 @@
+//! HTTP caching.
+//!
+//! A client set up with [`ClientBuilder::http_cache()`] keeps the responses
+//! to its `GET` requests and reuses them as allowed by [RFC 9111]: while
+//! they are fresh, as told by `Cache-Control`, `Expires` or, lacking
+//! those, a heuristic based on `Last-Modified`, and after validating them
+//! with the server through their `ETag` or `Last-Modified` otherwise. The
+//! `Vary` header of a response is honored, and the `Cache-Control`
+//! directives of a request, such as `no-cache`, `max-age` or
+//! `only-if-cached`, are too. One response is kept per URL: a response
+//! varying on a request header replaces the one stored for another value
+//! of it.
+//!
+//! Stale responses can also be served while they are revalidated in the
+//! background, or when revalidating them fails, as allowed by the
+//! `stale-while-revalidate` and `stale-if-error` directives of [RFC 5861]
+//! or by [`CacheConfig::stale_while_revalidate()`] and
+//! [`CacheConfig::stale_if_error()`].
+//!
+//! A response served from the cache has an `Age` header. Sending a request
+//! with an unsafe method, such as `POST`, removes the response stored for
+//! its URL. Requests with their own conditional or `Range` headers are
+//! sent as they are.
+//!
+//! Responses are kept in a [`CacheStorage`]: in memory with
+//! [`CacheConfig::in_memory()`], in a directory with
+//! [`CacheConfig::on_disk()`], or anywhere else by implementing the trait.
+//!
+//! # Example
+//!
+//! ```
+//! use reqwest::cache::CacheConfig;
+//!
+//! # #[cfg(not(feature = "rustls-no-provider"))]
+//! let client = reqwest::Client::builder()
+//!     .http_cache(CacheConfig::in_memory(64 * 1024 * 1024))
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+//! [`ClientBuilder::http_cache()`]: crate::ClientBuilder::http_cache
+//! [RFC 9111]: https://www.rfc-editor.org/rfc/rfc9111
+//! [RFC 5861]: https://www.rfc-editor.org/rfc/rfc5861
+
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, SystemTime};
+
+use bytes::{Bytes, BytesMut};
+use http::header::{self, HeaderMap, HeaderValue};
+use http::{Method, StatusCode};
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use http_body_util::BodyExt;
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+
+use crate::async_impl::Body;
+use crate::error::BoxError;
+
+mod conditional;
+mod policy;
+mod storage;
+
+pub use self::conditional::CachedResponse;
+pub use self::storage::{CacheStorage, FileStorage, MemoryStorage};
+
+use self::policy::{CacheControl, Entry, Stale};
+
+/// How a client caches responses.
+#[derive(Clone)]
+pub struct CacheConfig {
+    storage: Arc<dyn CacheStorage>,
+    // The size of an in-memory storage, made anew for each client built,
+    // so that clients built from clones of a builder don't share responses.
+    in_memory: Option<usize>,
+    shared: bool,
+    heuristics: bool,
+    max_entry_size: usize,
+    stale_while_revalidate: Option<Duration>,
+    stale_if_error: Option<Duration>,
+    // The URLs being revalidated in the background.
+    revalidating: Arc<Mutex<HashSet<String>>>,
+}
+
+impl CacheConfig {
+    /// Keep responses in memory, up to `max_bytes` of them, evicting the
+    /// least recently used first.
+    ///
+    /// Each client built with this configuration has a storage of its own.
+    pub fn in_memory(max_bytes: usize) -> CacheConfig {
+        CacheConfig {
+            in_memory: Some(max_bytes),
+            ..CacheConfig::with_storage(Arc::new(MemoryStorage::new(max_bytes)))
+        }
+    }
+
+    /// Keep responses in files of the directory `dir`.
+    ///
+    /// Clients built with this configuration share the responses kept there.
+    pub fn on_disk(dir: impl Into<PathBuf>) -> CacheConfig {
+        CacheConfig::with_storage(Arc::new(FileStorage::new(dir)))
+    }
+
+    /// Keep responses in `storage`.
+    ///
+    /// Clients built with this configuration share the responses kept there.
+    pub fn with_storage<S: CacheStorage + 'static>(storage: Arc<S>) -> CacheConfig {
+        CacheConfig {
+            storage,
+            in_memory: None,
+            shared: false,
+            heuristics: true,
+            max_entry_size: 8 * 1024 * 1024,
+            stale_while_revalidate: None,
+            stale_if_error: None,
+            revalidating: Arc::default(),
+        }
+    }
+
+    /// Behave as a shared cache, such as a proxy, rather than a private
+    /// one: responses marked `private`, and responses to requests with an
+    /// `Authorization` header unless allowed, aren't stored, and
+    /// `s-maxage` is honored.
+    ///
+    /// Default is `false`.
+    pub fn shared(mut self, enabled: bool) -> CacheConfig {
+        self.shared = enabled;
+        self
+    }
+
+    /// Reuse responses without explicit freshness for a tenth of the time
+    /// since their `Last-Modified` date.
+    ///
+    /// Default is `true`.
+    pub fn heuristics(mut self, enabled: bool) -> CacheConfig {
+        self.heuristics = enabled;
+        self
+    }
+
+    /// Serve stale responses for up to `max` past their freshness lifetime,
+    /// while revalidating them in the background.
+    ///
+    /// This applies to responses without a `stale-while-revalidate`
+    /// directive; the directive is always honored. Responses marked
+    /// `must-revalidate` or `no-cache` are never served stale.
+    ///
+    /// Default is `Duration::ZERO`, only honoring the directive.
+    pub fn stale_while_revalidate(mut self, max: Duration) -> CacheConfig {
+        self.stale_while_revalidate = Some(max).filter(|max| !max.is_zero());
+        self
+    }
+
+    /// Serve stale responses for up to `max` past their freshness lifetime
+    /// when revalidating them fails, with an error or a `500`, `502`, `503`
+    /// or `504` status.
+    ///
+    /// This applies to responses without a `stale-if-error` directive; the
+    /// directive is always honored. Responses marked `must-revalidate` or
+    /// `no-cache` are never served stale.
+    ///
+    /// Default is `Duration::ZERO`, only honoring the directive.
+    pub fn stale_if_error(mut self, max: Duration) -> CacheConfig {
+        self.stale_if_error = Some(max).filter(|max| !max.is_zero());
+        self
+    }
+
+    /// Don't store responses whose body is larger than `max_bytes`.
+    ///
+    /// Default is 8 MiB.
+    pub fn max_entry_size(mut self, max_bytes: usize) -> CacheConfig {
+        self.max_entry_size = max_bytes;
+        self
+    }
+}
+
+impl fmt::Debug for CacheConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CacheConfig")
+            .field("shared", &self.shared)
+            .field("heuristics", &self.heuristics)
+            .field("max_entry_size", &self.max_entry_size)
+            .field("stale_while_revalidate", &self.stale_while_revalidate)
+            .field("stale_if_error", &self.stale_if_error)
+            .finish()
+    }
+}
+
+pub(crate) struct CacheLayer(pub(crate) CacheConfig);
+
+impl<S> Layer<S> for CacheLayer {
+    type Service = HttpCache<S>;
+
+    fn layer(&self, inner: S) -> HttpCache<S> {
+        let mut config = self.0.clone();
+        if let Some(max_bytes) = config.in_memory {
+            config.storage = Arc::new(MemoryStorage::new(max_bytes));
+            config.revalidating = Arc::default();
+        }
+        HttpCache { inner, config }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct HttpCache<S> {
+    inner: S,
+    config: CacheConfig,
+}
+
+impl<S> Service<http::Request<Body>> for HttpCache<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>, Error = BoxError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = http::Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        // The ready service is taken, leaving a clone in its place.
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(cached(self.config.clone(), inner, req))
+    }
+}
+
+async fn cached<S>(
+    config: CacheConfig,
+    mut inner: S,
+    req: http::Request<Body>,
+) -> Result<http::Response<Body>, BoxError>
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>, Error = BoxError>
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    let key = req.uri().to_string();
+    if !policy::is_safe(req.method()) {
+        let res = inner.call(req).await?;
+        if !res.status().is_client_error() && !res.status().is_server_error() {
+            config.storage.remove(&key);
+        }
+        return Ok(res);
+    }
+    if req.method() != Method::GET || bypasses_cache(req.headers()) {
+        return inner.call(req).await;
+    }
+
+    let cc = CacheControl::request(req.headers());
+    let now = crate::util::now();
+    let stored = config
+        .storage
+        .get(&key)
+        .and_then(Entry::decode)
+        .filter(|entry| entry.matches(req.headers()));
+    let stored = match stored {
+        Some(entry) if entry.usable(&cc, now, config.shared, config.heuristics) => {
+            return Ok(respond(entry, now));
+        }
+        _ if cc.only_if_cached => {
+            let mut res = http::Response::new(Body::empty());
+            *res.status_mut() = StatusCode::GATEWAY_TIMEOUT;
+            return Ok(res);
+        }
+        stored => stored,
+    };
+
+    let stale = Stale::WhileRevalidate(config.stale_while_revalidate);
+    if let Some(entry) = stored.as_ref().filter(|entry| {
+        !cc.no_cache && entry.serves_stale(stale, now, config.shared, config.heuristics)
+    }) {
+        let entry = entry.clone();
+        revalidate_in_background(&config, inner, req, key, stored);
+        return Ok(respond(entry, now));
+    }
+
+    let fallback = stored.as_ref().and_then(|entry| {
+        let stale = Stale::IfError(config.stale_if_error);
+        if entry.serves_stale(stale, now, config.shared, config.heuristics) {
+            Some(entry.clone())
+        } else {
+            None
+        }
+    });
+    let result = fetch(&config, &mut inner, req, key, stored).await;
+    match fallback {
+        Some(entry)
+            if result
+                .as_ref()
+                .map_or(true, |res| is_upstream_error(res.status())) =>
+        {
+            log::debug!("cache serving a stale response: upstream failed");
+            Ok(respond(entry, crate::util::now()))
+        }
+        _ => result,
+    }
+}
+
+/// Send `req`, validating the `stored` response if it can, and store the
+/// response.
+async fn fetch<S>(
+    config: &CacheConfig,
+    inner: &mut S,
+    mut req: http::Request<Body>,
+    key: String,
+    stored: Option<Entry>,
+) -> Result<http::Response<Body>, BoxError>
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>, Error = BoxError>,
+{
+    let mut validating = false;
+    if let Some(ref entry) = stored {
+        if let Some(etag) = entry.headers.get(header::ETAG) {
+            req.headers_mut()
+                .insert(header::IF_NONE_MATCH, etag.clone());
+            validating = true;
+        }
+        if let Some(modified) = entry.headers.get(header::LAST_MODIFIED) {
+            req.headers_mut()
+                .insert(header::IF_MODIFIED_SINCE, modified.clone());
+            validating = true;
+        }
+    }
+
+    let uri = req.uri().clone();
+    let req_headers = req.headers().clone();
+    let request_time = crate::util::now();
+    let res = inner.call(req).await?;
+    let response_time = crate::util::now();
+
+    // The response to another URL, after a redirect, is left alone.
+    let redirected = res
+        .extensions()
+        .get::<tower_http::follow_redirect::RequestUri>()
+        .map_or(false, |final_uri| final_uri.0 != uri);
+    if redirected {
+        return Ok(res);
+    }
+
+    match stored {
+        Some(mut entry) if validating && res.status() == StatusCode::NOT_MODIFIED => {
+            entry.freshen(res.headers(), request_time, response_time);
+            config.storage.put(&key, entry.encode());
+            Ok(respond(entry, response_time))
+        }
+        _ if Entry::storable(&req_headers, res.status(), res.headers(), config.shared) => {
+            let entry = Entry::new(
+                &req_headers,
+                res.status(),
+                res.headers().clone(),
+                request_time,
+                response_time,
+            );
+            Ok(store(config.clone(), key, entry, res))
+        }
+        _ => Ok(res),
+    }
+}
+
+/// Revalidate the `stored` response to `req` on a task of its own, unless
+/// it is already being revalidated.
+fn revalidate_in_background<S>(
+    config: &CacheConfig,
+    mut inner: S,
+    req: http::Request<Body>,
+    key: String,
+    stored: Option<Entry>,
+) where
+    S: Service<http::Request<Body>, Response = http::Response<Body>, Error = BoxError>
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    if !config.revalidating.lock().unwrap().insert(key.clone()) {
+        return;
+    }
+    let guard = Revalidating {
+        config: config.clone(),
+        key,
+    };
+    tokio::spawn(async move {
+        let key = guard.key.clone();
+        match fetch(&guard.config, &mut inner, req, key, stored).await {
+            // The response is stored as its body is read.
+            Ok(res) => {
+                if let Err(err) = BodyExt::collect(res.into_body()).await {
+                    log::debug!("cache revalidation of {} failed: {err}", guard.key);
+                }
+            }
+            Err(err) => log::debug!("cache revalidation of {} failed: {err}", guard.key),
+        }
+    });
+}
+
+/// Marks a URL as being revalidated, until dropped.
+struct Revalidating {
+    config: CacheConfig,
+    key: String,
+}
+
+impl Drop for Revalidating {
+    fn drop(&mut self) {
+        self.config.revalidating.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// Whether a status tells of a failure of the origin, rather than of the
+/// request.
+fn is_upstream_error(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 500 | 502 | 503 | 504)
+}
+
+/// Whether the headers of a request ask for something the cache doesn't
+/// handle itself.
+fn bypasses_cache(headers: &HeaderMap) -> bool {
+    [
+        header::RANGE,
+        header::IF_MATCH,
+        header::IF_NONE_MATCH,
+        header::IF_MODIFIED_SINCE,
+        header::IF_UNMODIFIED_SINCE,
+        header::IF_RANGE,
+    ]
+    .iter()
+    .any(|name| headers.contains_key(name))
+}
+
+fn respond(entry: Entry, now: SystemTime) -> http::Response<Body> {
+    let age = entry.age(now).as_secs();
+    let mut res = http::Response::new(Body::from(entry.body));
+    *res.status_mut() = entry.status;
+    *res.headers_mut() = entry.headers;
+    res.headers_mut()
+        .insert(header::AGE, HeaderValue::from(age));
+    res
+}
+
+/// Store `entry` with the body of `res`, once it has been read.
+fn store(
+    config: CacheConfig,
+    key: String,
+    entry: Entry,
+    res: http::Response<Body>,
+) -> http::Response<Body> {
+    if res.body().is_end_stream() {
+        config.storage.put(&key, entry.encode());
+        return res;
+    }
+    if res.body().size_hint().lower() > config.max_entry_size as u64 {
+        return res;
+    }
+    res.map(|body| {
+        Body::wrap(StoringBody {
+            inner: body,
+            pending: Some(Pending {
+                config,
+                key,
+                entry,
+                buf: BytesMut::new(),
+            }),
+        })
+    })
+}
+
+struct Pending {
+    config: CacheConfig,
+    key: String,
+    entry: Entry,
+    buf: BytesMut,
+}
+
+pin_project! {
+    /// A response body, stored once it has been read to the end.
+    struct StoringBody {
+        #[pin]
+        inner: Body,
+        pending: Option<Pending>,
+    }
+}
+
+impl HttpBody for StoringBody {
+    type Data = Bytes;
+    type Error = crate::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+        let frame = ready!(this.inner.as_mut().poll_frame(cx));
+        match frame {
+            Some(Ok(ref frame)) => {
+                if let (Some(data), Some(pending)) = (frame.data_ref(), this.pending.as_mut()) {
+                    if pending.buf.len() + data.len() > pending.config.max_entry_size {
+                        *this.pending = None;
+                    } else {
+                        pending.buf.extend_from_slice(data);
+                    }
+                }
+            }
+            Some(Err(_)) => *this.pending = None,
+            None => (),
+        }
+        // Readers may stop at the last frame, rather than polling past it.
+        if frame.is_none() || this.inner.is_end_stream() {
+            if let Some(mut pending) = this.pending.take() {
+                pending.entry.body = pending.buf.freeze();
+                pending
+                    .config
+                    .storage
+                    .put(&pending.key, pending.entry.encode());
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
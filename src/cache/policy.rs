@@ -0,0 +1,568 @@
+//! The rules of RFC 9111 for storing and reusing responses.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::{Method, StatusCode};
+
+/// The directives of the `Cache-Control` headers of a request or a
+/// response.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct CacheControl {
+    pub(super) no_store: bool,
+    pub(super) no_cache: bool,
+    pub(super) private: bool,
+    pub(super) public: bool,
+    pub(super) must_revalidate: bool,
+    pub(super) proxy_revalidate: bool,
+    pub(super) only_if_cached: bool,
+    pub(super) max_age: Option<u64>,
+    pub(super) s_maxage: Option<u64>,
+    pub(super) min_fresh: Option<u64>,
+    /// `Some(None)` for a `max-stale` without a value, accepting any
+    /// staleness.
+    pub(super) max_stale: Option<Option<u64>>,
+    pub(super) stale_while_revalidate: Option<u64>,
+    pub(super) stale_if_error: Option<u64>,
+}
+
+impl CacheControl {
+    pub(super) fn parse(headers: &HeaderMap) -> CacheControl {
+        let mut cc = CacheControl::default();
+        let directives = headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for directive in directives {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            // An invalid delta is taken as zero, the safe choice.
+            let seconds = || Some(value.and_then(|v| v.parse().ok()).unwrap_or(0));
+            match &*name.to_ascii_lowercase() {
+                "no-store" => cc.no_store = true,
+                "no-cache" => cc.no_cache = true,
+                "private" => cc.private = true,
+                "public" => cc.public = true,
+                "must-revalidate" => cc.must_revalidate = true,
+                "proxy-revalidate" => cc.proxy_revalidate = true,
+                "only-if-cached" => cc.only_if_cached = true,
+                "max-age" => cc.max_age = seconds(),
+                "s-maxage" => cc.s_maxage = seconds(),
+                "min-fresh" => cc.min_fresh = seconds(),
+                "max-stale" => cc.max_stale = Some(value.and_then(|v| v.parse().ok())),
+                "stale-while-revalidate" => cc.stale_while_revalidate = seconds(),
+                "stale-if-error" => cc.stale_if_error = seconds(),
+                _ => (),
+            }
+        }
+        cc
+    }
+
+    /// The directives of a request, where `Pragma: no-cache` stands for
+    /// `Cache-Control: no-cache` if there is no `Cache-Control` header.
+    pub(super) fn request(headers: &HeaderMap) -> CacheControl {
+        let mut cc = CacheControl::parse(headers);
+        if !headers.contains_key(header::CACHE_CONTROL) {
+            cc.no_cache = headers
+                .get_all(header::PRAGMA)
+                .iter()
+                .any(|value| value.as_bytes().eq_ignore_ascii_case(b"no-cache"));
+        }
+        cc
+    }
+}
+
+/// When a stale response may be served, as of RFC 5861, with how long past
+/// its freshness the client allows it for directive-less responses.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Stale {
+    WhileRevalidate(Option<Duration>),
+    IfError(Option<Duration>),
+}
+
+/// Whether a stored response with the directives `cc` must be validated
+/// once stale.
+fn forbids_stale(cc: &CacheControl, shared: bool) -> bool {
+    cc.must_revalidate || (shared && (cc.proxy_revalidate || cc.s_maxage.is_some()))
+}
+
+/// Whether a response with `status` may be reused without explicit
+/// freshness information.
+fn heuristically_cacheable(status: StatusCode) -> bool {
+    matches!(
+        status.as_u16(),
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+/// Whether `method` is safe, as of RFC 9110, and so leaves stored
+/// responses valid.
+pub(super) fn is_safe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// The names of the request headers a response varies on, or `None` for
+/// `Vary: *`, which never matches.
+pub(super) fn vary_names(headers: &HeaderMap) -> Option<Vec<HeaderName>> {
+    let mut names = Vec::new();
+    for value in headers.get_all(header::VARY) {
+        for name in value.to_str().ok()?.split(',') {
+            let name = name.trim();
+            if name == "*" {
+                return None;
+            }
+            if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+                names.push(name);
+            }
+        }
+    }
+    Some(names)
+}
+
+/// The time in the date header `name`.
+fn date(headers: &HeaderMap, name: HeaderName) -> Option<SystemTime> {
+    let value = headers.get(name)?.to_str().ok()?;
+    httpdate::parse_http_date(value).ok()
+}
+
+/// A response kept by a cache.
+#[derive(Clone, Debug)]
+pub(super) struct Entry {
+    pub(super) status: StatusCode,
+    pub(super) headers: HeaderMap,
+    /// The value of each header the response varies on, in the request it
+    /// answered.
+    pub(super) vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    pub(super) request_time: SystemTime,
+    pub(super) response_time: SystemTime,
+    pub(super) body: Bytes,
+}
+
+const MAGIC: &[u8] = b"reqwest-cache 1\n";
+
+impl Entry {
+    /// Whether a response may be stored, given the request it answers.
+    pub(super) fn storable(
+        req_headers: &HeaderMap,
+        status: StatusCode,
+        headers: &HeaderMap,
+        shared: bool,
+    ) -> bool {
+        let cc = CacheControl::parse(headers);
+        if cc.no_store || CacheControl::parse(req_headers).no_store {
+            return false;
+        }
+        if shared && cc.private {
+            return false;
+        }
+        if shared
+            && req_headers.contains_key(header::AUTHORIZATION)
+            && !(cc.public || cc.must_revalidate || cc.s_maxage.is_some())
+        {
+            return false;
+        }
+        if status == StatusCode::PARTIAL_CONTENT || status.is_informational() {
+            return false;
+        }
+        if vary_names(headers).is_none() {
+            return false;
+        }
+        let explicit = cc.max_age.is_some()
+            || (shared && cc.s_maxage.is_some())
+            || headers.contains_key(header::EXPIRES)
+            || cc.public;
+        let validated =
+            headers.contains_key(header::ETAG) || headers.contains_key(header::LAST_MODIFIED);
+        // Without explicit freshness, a response can still be validated
+        // later, or be given a heuristic lifetime from its `Last-Modified`.
+        explicit || (heuristically_cacheable(status) && validated)
+    }
+
+    pub(super) fn new(
+        req_headers: &HeaderMap,
+        status: StatusCode,
+        headers: HeaderMap,
+        request_time: SystemTime,
+        response_time: SystemTime,
+    ) -> Entry {
+        let vary = vary_names(&headers)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|name| {
+                let value = req_headers.get(&name).cloned();
+                (name, value)
+            })
+            .collect();
+        Entry {
+            status,
+            headers,
+            vary,
+            request_time,
+            response_time,
+            body: Bytes::new(),
+        }
+    }
+
+    /// Whether the entry answers a request with `req_headers`.
+    pub(super) fn matches(&self, req_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| req_headers.get(name) == value.as_ref())
+    }
+
+    /// How long the response is fresh for, from when it was generated.
+    pub(super) fn freshness_lifetime(&self, shared: bool, heuristics: bool) -> Duration {
+        let cc = CacheControl::parse(&self.headers);
+        if let (true, Some(secs)) = (shared, cc.s_maxage) {
+            return Duration::from_secs(secs);
+        }
+        if let Some(secs) = cc.max_age {
+            return Duration::from_secs(secs);
+        }
+        let generated = date(&self.headers, header::DATE).unwrap_or(self.response_time);
+        if self.headers.contains_key(header::EXPIRES) {
+            // An invalid date, such as `0`, means already expired.
+            return date(&self.headers, header::EXPIRES)
+                .and_then(|expires| expires.duration_since(generated).ok())
+                .unwrap_or_default();
+        }
+        if heuristics && heuristically_cacheable(self.status) {
+            // A tenth of how long the resource had been unchanged, as
+            // suggested by RFC 9111 section 4.2.2.
+            if let Some(modified) = date(&self.headers, header::LAST_MODIFIED) {
+                return generated.duration_since(modified).unwrap_or_default() / 10;
+            }
+        }
+        Duration::ZERO
+    }
+
+    /// How old the response is at `now`, as of RFC 9111 section 4.2.3.
+    pub(super) fn age(&self, now: SystemTime) -> Duration {
+        let age_value = self
+            .headers
+            .get(header::AGE)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let generated = date(&self.headers, header::DATE).unwrap_or(self.response_time);
+        let apparent_age = self
+            .response_time
+            .duration_since(generated)
+            .unwrap_or_default();
+        let response_delay = self
+            .response_time
+            .duration_since(self.request_time)
+            .unwrap_or_default();
+        let corrected_initial_age = apparent_age.max(age_value + response_delay);
+        let resident_time = now.duration_since(self.response_time).unwrap_or_default();
+        corrected_initial_age + resident_time
+    }
+
+    /// Whether the entry may answer a request with `req` directives at
+    /// `now` without being validated with the origin.
+    pub(super) fn usable(
+        &self,
+        req: &CacheControl,
+        now: SystemTime,
+        shared: bool,
+        heuristics: bool,
+    ) -> bool {
+        let cc = CacheControl::parse(&self.headers);
+        if req.no_cache || cc.no_cache {
+            return false;
+        }
+        let age = self.age(now);
+        let lifetime = self.freshness_lifetime(shared, heuristics);
+        if req
+            .max_age
+            .map_or(false, |max| age > Duration::from_secs(max))
+        {
+            return false;
+        }
+        if let Some(min) = req.min_fresh {
+            return lifetime >= age + Duration::from_secs(min);
+        }
+        if lifetime > age {
+            return true;
+        }
+        if forbids_stale(&cc, shared) {
+            return false;
+        }
+        match req.max_stale {
+            Some(None) => true,
+            Some(Some(max)) => age - lifetime <= Duration::from_secs(max),
+            None => false,
+        }
+    }
+
+    /// Whether the entry may be served at `now`, stale, in the case of
+    /// `stale`.
+    pub(super) fn serves_stale(
+        &self,
+        stale: Stale,
+        now: SystemTime,
+        shared: bool,
+        heuristics: bool,
+    ) -> bool {
+        let cc = CacheControl::parse(&self.headers);
+        if cc.no_cache || forbids_stale(&cc, shared) {
+            return false;
+        }
+        let (directive, default) = match stale {
+            Stale::WhileRevalidate(default) => (cc.stale_while_revalidate, default),
+            Stale::IfError(default) => (cc.stale_if_error, default),
+        };
+        let window = match directive.map(Duration::from_secs).or(default) {
+            Some(window) => window,
+            None => return false,
+        };
+        let age = self.age(now);
+        let lifetime = self.freshness_lifetime(shared, heuristics);
+        age.saturating_sub(lifetime) <= window
+    }
+
+    /// Update the entry with the headers of a `304 Not Modified` response
+    /// validating it, as of RFC 9111 section 4.3.4.
+    pub(super) fn freshen(
+        &mut self,
+        headers: &HeaderMap,
+        request_time: SystemTime,
+        response_time: SystemTime,
+    ) {
+        update_headers(&mut self.headers, headers);
+        self.request_time = request_time;
+        self.response_time = response_time;
+    }
+
+    pub(super) fn encode(&self) -> Bytes {
+        let secs = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        };
+        let mut buf = BytesMut::with_capacity(self.body.len() + 512);
+        buf.put_slice(MAGIC);
+        buf.put_slice(
+            format!(
+                "{} {} {}\n{}\n",
+                secs(self.request_time),
+                secs(self.response_time),
+                self.status.as_u16(),
+                self.vary.len(),
+            )
+            .as_bytes(),
+        );
+        for (name, value) in &self.vary {
+            put_header(&mut buf, name, value.as_ref());
+        }
+        buf.put_slice(format!("{}\n", self.headers.len()).as_bytes());
+        for (name, value) in &self.headers {
+            put_header(&mut buf, name, Some(value));
+        }
+        buf.put_slice(&self.body);
+        buf.freeze()
+    }
+
+    /// Decodes an entry, or `None` if it is corrupt or from another
+    /// version of the format.
+    pub(super) fn decode(mut bytes: Bytes) -> Option<Entry> {
+        if !bytes.starts_with(MAGIC) {
+            return None;
+        }
+        let _ = bytes.split_to(MAGIC.len());
+        let line = next_line(&mut bytes)?;
+        let mut fields = std::str::from_utf8(&line).ok()?.split(' ');
+        let mut time = || -> Option<SystemTime> {
+            Some(UNIX_EPOCH + Duration::from_secs(fields.next()?.parse().ok()?))
+        };
+        let request_time = time()?;
+        let response_time = time()?;
+        let status = StatusCode::from_u16(fields.next()?.parse().ok()?).ok()?;
+
+        let mut vary = Vec::new();
+        for _ in 0..count(&mut bytes)? {
+            vary.push(next_header(&mut bytes)?);
+        }
+        let mut headers = HeaderMap::new();
+        for _ in 0..count(&mut bytes)? {
+            let (name, value) = next_header(&mut bytes)?;
+            headers.append(name, value?);
+        }
+        Some(Entry {
+            status,
+            headers,
+            vary,
+            request_time,
+            response_time,
+            body: bytes,
+        })
+    }
+}
+
+/// Replace the headers of a stored response with those of the `304 Not
+/// Modified` validating it.
+pub(super) fn update_headers(stored: &mut HeaderMap, not_modified: &HeaderMap) {
+    for name in not_modified.keys() {
+        // The length is of the `304`, not of the stored body.
+        if *name == header::CONTENT_LENGTH {
+            continue;
+        }
+        stored.remove(name);
+        for value in not_modified.get_all(name) {
+            stored.append(name.clone(), value.clone());
+        }
+    }
+}
+
+// Headers are encoded a line each, as `name:value`, or just `name` for an
+// absent header. Header values can't hold a line feed.
+fn put_header(buf: &mut BytesMut, name: &HeaderName, value: Option<&HeaderValue>) {
+    buf.put_slice(name.as_str().as_bytes());
+    if let Some(value) = value {
+        buf.put_u8(b':');
+        buf.put_slice(value.as_bytes());
+    }
+    buf.put_u8(b'\n');
+}
+
+fn next_line(bytes: &mut Bytes) -> Option<Bytes> {
+    let end = bytes.iter().position(|&b| b == b'\n')?;
+    let line = bytes.split_to(end);
+    let _ = bytes.split_to(1);
+    Some(line)
+}
+
+fn count(bytes: &mut Bytes) -> Option<usize> {
+    std::str::from_utf8(&next_line(bytes)?).ok()?.parse().ok()
+}
+
+fn next_header(bytes: &mut Bytes) -> Option<(HeaderName, Option<HeaderValue>)> {
+    let line = next_line(bytes)?;
+    match line.iter().position(|&b| b == b':') {
+        Some(colon) => Some((
+            HeaderName::from_bytes(&line[..colon]).ok()?,
+            Some(HeaderValue::from_bytes(&line[colon + 1..]).ok()?),
+        )),
+        None => Some((HeaderName::from_bytes(&line).ok()?, None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|&(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_cache_control() {
+        let cc = CacheControl::parse(&headers(&[
+            ("cache-control", "Max-Age=60, no-cache"),
+            ("cache-control", "s-maxage=\"30\", max-stale, private"),
+        ]));
+        assert_eq!(
+            cc,
+            CacheControl {
+                no_cache: true,
+                private: true,
+                max_age: Some(60),
+                s_maxage: Some(30),
+                max_stale: Some(None),
+                ..CacheControl::default()
+            }
+        );
+
+        assert!(CacheControl::request(&headers(&[("pragma", "no-cache")])).no_cache);
+        assert!(
+            !CacheControl::request(&headers(&[
+                ("pragma", "no-cache"),
+                ("cache-control", "max-age=5"),
+            ]))
+            .no_cache
+        );
+    }
+
+    #[test]
+    fn computes_age_and_freshness() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let entry = Entry::new(
+            &HeaderMap::new(),
+            StatusCode::OK,
+            headers(&[
+                ("age", "10"),
+                ("date", "Mon, 12 Jan 1970 13:46:40 GMT"),
+                ("last-modified", "Mon, 12 Jan 1970 11:00:00 GMT"),
+            ]),
+            now - Duration::from_secs(2),
+            now,
+        );
+        assert_eq!(
+            entry.age(now + Duration::from_secs(5)),
+            Duration::from_secs(17)
+        );
+        assert_eq!(entry.freshness_lifetime(false, false), Duration::ZERO);
+        assert_eq!(
+            entry.freshness_lifetime(false, true),
+            Duration::from_secs(1000)
+        );
+
+        let stale = CacheControl {
+            max_stale: Some(Some(20)),
+            ..CacheControl::default()
+        };
+        assert!(!entry.usable(&CacheControl::default(), now, false, false));
+        assert!(entry.usable(&stale, now, false, false));
+
+        let window = Some(Duration::from_secs(12));
+        assert!(entry.serves_stale(Stale::IfError(window), now, false, false));
+        assert!(!entry.serves_stale(Stale::IfError(None), now, false, false));
+        assert!(!entry.serves_stale(
+            Stale::WhileRevalidate(window),
+            now + Duration::from_secs(1),
+            false,
+            false
+        ));
+    }
+
+    #[test]
+    fn round_trips_entries() {
+        let mut entry = Entry::new(
+            &headers(&[("accept-language", "fr")]),
+            StatusCode::NOT_FOUND,
+            headers(&[
+                ("vary", "Accept-Language, Accept-Encoding"),
+                ("set-cookie", "a=1"),
+                ("set-cookie", "b=2"),
+            ]),
+            UNIX_EPOCH + Duration::from_secs(7),
+            UNIX_EPOCH + Duration::from_secs(8),
+        );
+        entry.body = Bytes::from_static(b"\nbody:\n");
+
+        let decoded = Entry::decode(entry.encode()).unwrap();
+        assert_eq!(decoded.status, entry.status);
+        assert_eq!(decoded.headers, entry.headers);
+        assert_eq!(decoded.vary, entry.vary);
+        assert_eq!(decoded.request_time, entry.request_time);
+        assert_eq!(decoded.response_time, entry.response_time);
+        assert_eq!(decoded.body, entry.body);
+
+        assert!(Entry::decode(Bytes::from_static(b"reqwest-cache 0\n")).is_none());
+    }
+}
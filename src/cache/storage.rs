@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use bytes::Bytes;
+
+/// Where a cache keeps its entries.
+///
+/// Entries are opaque bytes to a storage, looked up by a key derived from
+/// the request URL. A storage is shared by all the requests of a client,
+/// and may be shared by several clients.
+///
+/// Errors are left to the storage to handle: a failed `get` is a miss, and
+/// a failed `put` leaves the response uncached.
+pub trait CacheStorage: Send + Sync {
+    /// Returns the entry stored for `key`, if any.
+    fn get(&self, key: &str) -> Option<Bytes>;
+
+    /// Stores `entry` for `key`, replacing any previous entry.
+    fn put(&self, key: &str, entry: Bytes);
+
+    /// Removes the entry stored for `key`, if any.
+    fn remove(&self, key: &str);
+}
+
+/// An in-memory storage, evicting the least recently used entries once its
+/// size limit is reached.
+pub struct MemoryStorage {
+    max_bytes: usize,
+    inner: Mutex<Lru>,
+}
+
+#[derive(Default)]
+struct Lru {
+    entries: HashMap<String, (Bytes, u64)>,
+    // The key of each entry, by when it was last used.
+    recency: BTreeMap<u64, String>,
+    size: usize,
+    tick: u64,
+}
+
+impl MemoryStorage {
+    /// Create a storage holding up to `max_bytes` of entries.
+    pub fn new(max_bytes: usize) -> MemoryStorage {
+        MemoryStorage {
+            max_bytes,
+            inner: Mutex::new(Lru::default()),
+        }
+    }
+}
+
+impl Lru {
+    fn touch(&mut self, key: &str) -> Option<Bytes> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (entry, used) = self.entries.get_mut(key)?;
+        self.recency.remove(used);
+        *used = tick;
+        self.recency.insert(tick, key.to_owned());
+        Some(entry.clone())
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((entry, used)) = self.entries.remove(key) {
+            self.recency.remove(&used);
+            self.size -= entry.len();
+        }
+    }
+}
+
+impl CacheStorage for MemoryStorage {
+    fn get(&self, key: &str) -> Option<Bytes> {
+        self.inner.lock().unwrap().touch(key)
+    }
+
+    fn put(&self, key: &str, entry: Bytes) {
+        let mut lru = self.inner.lock().unwrap();
+        lru.remove(key);
+        if entry.len() > self.max_bytes {
+            return;
+        }
+        while lru.size + entry.len() > self.max_bytes {
+            let oldest = match lru.recency.keys().next() {
+                Some(&tick) => lru.recency[&tick].clone(),
+                None => break,
+            };
+            lru.remove(&oldest);
+        }
+        lru.tick += 1;
+        let tick = lru.tick;
+        lru.size += entry.len();
+        lru.recency.insert(tick, key.to_owned());
+        lru.entries.insert(key.to_owned(), (entry, tick));
+    }
+
+    fn remove(&self, key: &str) {
+        self.inner.lock().unwrap().remove(key);
+    }
+}
+
+impl fmt::Debug for MemoryStorage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lru = self.inner.lock().unwrap();
+        f.debug_struct("MemoryStorage")
+            .field("max_bytes", &self.max_bytes)
+            .field("size", &lru.size)
+            .field("entries", &lru.entries.len())
+            .finish()
+    }
+}
+
+/// A storage keeping each entry in a file of a directory, so that it
+/// outlives the process.
+///
+/// The directory is created when the first entry is stored. Nothing is
+/// ever evicted: clearing the directory is left to the application.
+#[derive(Debug)]
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    /// Create a storage in the directory `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> FileStorage {
+        FileStorage { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", fnv1a(key.as_bytes())))
+    }
+
+    fn write(&self, key: &str, entry: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        // Written aside then renamed, so readers never see half an entry.
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(key.as_bytes())?;
+        file.write_all(b"\n")?;
+        file.write_all(entry)?;
+        drop(file);
+        fs::rename(&tmp, &path).map_err(|err| {
+            let _ = fs::remove_file(&tmp);
+            err
+        })
+    }
+}
+
+impl CacheStorage for FileStorage {
+    fn get(&self, key: &str) -> Option<Bytes> {
+        let mut file = Bytes::from(fs::read(self.path(key)).ok()?);
+        // Keys are hashed for the file name, so the key is stored too, in
+        // case of a collision.
+        let stored = file.split_to(file.iter().position(|&b| b == b'\n')? + 1);
+        if &stored[..stored.len() - 1] != key.as_bytes() {
+            return None;
+        }
+        Some(file)
+    }
+
+    fn put(&self, key: &str, entry: Bytes) {
+        if let Err(err) = self.write(key, &entry) {
+            log::debug!("cache entry for {key} not written: {err}");
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.path(key));
+    }
+}
+
+/// The 64-bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_storage_evicts_least_recently_used() {
+        let storage = MemoryStorage::new(10);
+        storage.put("a", Bytes::from_static(b"aaaa"));
+        storage.put("b", Bytes::from_static(b"bbbb"));
+        assert!(storage.get("a").is_some());
+
+        storage.put("c", Bytes::from_static(b"cccc"));
+        assert!(storage.get("b").is_none());
+        assert_eq!(storage.get("a").unwrap(), "aaaa");
+        assert_eq!(storage.get("c").unwrap(), "cccc");
+
+        storage.put("d", Bytes::from_static(b"too large for it"));
+        assert!(storage.get("d").is_none());
+        assert!(storage.get("a").is_some());
+    }
+}
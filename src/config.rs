@@ -26,10 +26,16 @@
 
 use std::any::type_name;
 use std::fmt::Debug;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use http::Extensions;
 
+use crate::async_impl::informational::Informational;
+use crate::async_impl::progress::Progress;
+use crate::async_impl::throttle::Throttle;
+use crate::redirect::Policy;
+
 /// This trait is empty and is only used to associate a configuration key type with its
 /// corresponding value type.
 pub(crate) trait RequestConfigValue: Copy + Clone + 'static {
@@ -108,3 +114,91 @@ pub(crate) struct TotalTimeout;
 impl RequestConfigValue for TotalTimeout {
     type Value = Duration;
 }
+
+#[derive(Clone, Copy)]
+pub(crate) struct Deadline;
+
+impl RequestConfigValue for Deadline {
+    type Value = Instant;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct ReadTimeout;
+
+impl RequestConfigValue for ReadTimeout {
+    type Value = Duration;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct DownloadRate;
+
+impl RequestConfigValue for DownloadRate {
+    type Value = Throttle;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct UploadRate;
+
+impl RequestConfigValue for UploadRate {
+    type Value = Throttle;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct DownloadProgress;
+
+impl RequestConfigValue for DownloadProgress {
+    type Value = Progress;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct UploadProgress;
+
+impl RequestConfigValue for UploadProgress {
+    type Value = Progress;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct RedirectPolicy;
+
+impl RequestConfigValue for RedirectPolicy {
+    type Value = Arc<Policy>;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct ExpectContinue;
+
+impl RequestConfigValue for ExpectContinue {
+    type Value = Duration;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct OnInformational;
+
+impl RequestConfigValue for OnInformational {
+    type Value = Informational;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct IfCached;
+
+impl RequestConfigValue for IfCached {
+    type Value = crate::cache::CachedResponse;
+}
+
+#[cfg(feature = "__rustls")]
+#[derive(Clone, Copy)]
+pub(crate) struct TlsVerification;
+
+#[cfg(feature = "__rustls")]
+impl RequestConfigValue for TlsVerification {
+    type Value = crate::tls::TlsOverride;
+}
+
+#[cfg(feature = "har")]
+#[derive(Clone, Copy)]
+pub(crate) struct Har;
+
+#[cfg(feature = "har")]
+impl RequestConfigValue for Har {
+    type Value = crate::har::Recorder;
+}
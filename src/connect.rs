@@ -1,6 +1,6 @@
 #[cfg(feature = "__tls")]
 use http::header::HeaderValue;
-#[cfg(feature = "__tls")]
+#[cfg(any(feature = "__tls", feature = "http2"))]
 use http::uri::Scheme;
 use http::Uri;
 use hyper::rt::{Read, ReadBufCursor, Write};
@@ -14,6 +14,8 @@ use tower::util::{BoxCloneSyncServiceLayer, MapRequestLayer};
 use tower::{timeout::TimeoutLayer, util::BoxCloneSyncService, ServiceBuilder};
 use tower_service::Service;
 
+#[cfg(feature = "__rustls")]
+use std::collections::HashMap;
 use std::future::Future;
 use std::io::{self, IoSlice};
 use std::net::IpAddr;
@@ -28,9 +30,14 @@ use self::native_tls_conn::NativeTlsConn;
 use self::rustls_tls_conn::RustlsTlsConn;
 use crate::dns::DynResolver;
 use crate::error::{cast_to_internal_error, BoxError};
-use crate::proxy::{Intercepted, Matcher as ProxyMatcher};
+use crate::proxy::{Intercepted, Matcher as ProxyMatcher, ProxyHealth};
+use crate::trace::Instrument;
 use sealed::{Conn, Unnameable};
 
+mod chain;
+pub(crate) mod header_case;
+mod tunnel;
+
 pub(crate) type HttpConnector = hyper_util::client::legacy::connect::HttpConnector<DynResolver>;
 
 #[derive(Clone)]
@@ -67,11 +74,15 @@ pub(crate) type BoxedConnectorService = BoxCloneSyncService<Unnameable, Conn, Bo
 pub(crate) type BoxedConnectorLayer =
     BoxCloneSyncServiceLayer<BoxedConnectorService, Unnameable, Conn, BoxError>;
 
+#[derive(Clone)]
 pub(crate) struct ConnectorBuilder {
     inner: Inner,
     proxies: Arc<Vec<ProxyMatcher>>,
     verbose: verbose::Wrapper,
+    header_case: Option<Arc<header_case::HeaderCase>>,
     timeout: Option<Duration>,
+    #[cfg(feature = "http2")]
+    h2c: bool,
     #[cfg(feature = "__tls")]
     nodelay: bool,
     #[cfg(feature = "__tls")]
@@ -94,6 +105,9 @@ where {
             inner: self.inner,
             proxies: self.proxies,
             verbose: self.verbose,
+            header_case: self.header_case,
+            #[cfg(feature = "http2")]
+            h2c: self.h2c,
             #[cfg(feature = "__tls")]
             nodelay: self.nodelay,
             #[cfg(feature = "__tls")]
@@ -212,7 +226,10 @@ where {
             inner: Inner::Http(http),
             proxies,
             verbose: verbose::OFF,
+            header_case: None,
             timeout: None,
+            #[cfg(feature = "http2")]
+            h2c: false,
             #[cfg(feature = "socks")]
             resolver: None,
             #[cfg(unix)]
@@ -322,10 +339,13 @@ where {
             inner: Inner::NativeTls(http, tls),
             proxies,
             verbose: verbose::OFF,
+            header_case: None,
             nodelay,
             tls_info,
             user_agent,
             timeout: None,
+            #[cfg(feature = "http2")]
+            h2c: false,
             #[cfg(feature = "socks")]
             resolver: None,
             #[cfg(unix)]
@@ -394,13 +414,17 @@ where {
                 http,
                 tls,
                 tls_proxy,
+                tls_hosts: Arc::new(HashMap::new()),
             },
             proxies,
             verbose: verbose::OFF,
+            header_case: None,
             nodelay,
             tls_info,
             user_agent,
             timeout: None,
+            #[cfg(feature = "http2")]
+            h2c: false,
             #[cfg(feature = "socks")]
             resolver: None,
             #[cfg(unix)]
@@ -410,6 +434,49 @@ where {
         }
     }
 
+    /// Use a different TLS config when connecting to each of these hosts.
+    #[cfg(feature = "__rustls")]
+    pub(crate) fn set_rustls_host_configs(&mut self, hosts: HashMap<String, rustls::ClientConfig>) {
+        match &mut self.inner {
+            Inner::RustlsTls { tls_hosts, .. } => {
+                *tls_hosts = Arc::new(
+                    hosts
+                        .into_iter()
+                        .map(|(host, tls)| (host, Arc::new(tls)))
+                        .collect(),
+                );
+            }
+            #[cfg(feature = "__native-tls")]
+            Inner::NativeTls(..) => (),
+        }
+    }
+
+    /// Replaces the verifier of the TLS configs used to reach origins.
+    #[cfg(feature = "__rustls")]
+    pub(crate) fn set_rustls_verifier(
+        &mut self,
+        verifier: Arc<dyn rustls::client::danger::ServerCertVerifier>,
+    ) {
+        let with_verifier = |tls: &Arc<rustls::ClientConfig>| {
+            let mut tls = (**tls).clone();
+            tls.dangerous().set_certificate_verifier(verifier.clone());
+            Arc::new(tls)
+        };
+        match &mut self.inner {
+            Inner::RustlsTls { tls, tls_hosts, .. } => {
+                *tls = with_verifier(tls);
+                *tls_hosts = Arc::new(
+                    tls_hosts
+                        .iter()
+                        .map(|(host, tls)| (host.clone(), with_verifier(tls)))
+                        .collect(),
+                );
+            }
+            #[cfg(feature = "__native-tls")]
+            Inner::NativeTls(..) => (),
+        }
+    }
+
     pub(crate) fn set_timeout(&mut self, timeout: Option<Duration>) {
         self.timeout = timeout;
     }
@@ -418,6 +485,15 @@ where {
         self.verbose.0 = enabled;
     }
 
+    pub(crate) fn set_header_case(&mut self, case: Option<Arc<header_case::HeaderCase>>) {
+        self.header_case = case;
+    }
+
+    #[cfg(feature = "http2")]
+    pub(crate) fn set_h2c(&mut self, enabled: bool) {
+        self.h2c = enabled;
+    }
+
     pub(crate) fn set_keepalive(&mut self, dur: Option<Duration>) {
         match &mut self.inner {
             #[cfg(feature = "__native-tls")]
@@ -485,11 +561,16 @@ pub(crate) struct ConnectorService {
     inner: Inner,
     proxies: Arc<Vec<ProxyMatcher>>,
     verbose: verbose::Wrapper,
+    /// Header names to write with their own casing on HTTP/1.
+    header_case: Option<Arc<header_case::HeaderCase>>,
     /// When there is a single timeout layer and no other layers,
     /// we embed it directly inside our base Service::call().
     /// This lets us avoid an extra `Box::pin` indirection layer
     /// since `tokio::time::Timeout` is `Unpin`
     simple_timeout: Option<Duration>,
+    /// Use HTTP/2 with prior knowledge on cleartext connections.
+    #[cfg(feature = "http2")]
+    h2c: bool,
     #[cfg(feature = "__tls")]
     nodelay: bool,
     #[cfg(feature = "__tls")]
@@ -516,11 +597,25 @@ enum Inner {
         http: HttpConnector,
         tls: Arc<rustls::ClientConfig>,
         tls_proxy: Arc<rustls::ClientConfig>,
+        tls_hosts: Arc<HashMap<String, Arc<rustls::ClientConfig>>>,
     },
 }
 
+/// The TLS config to use for `dst`, preferring one registered for its host.
+#[cfg(feature = "__rustls")]
+fn rustls_config_for(
+    dst: &Uri,
+    tls: &Arc<rustls::ClientConfig>,
+    tls_hosts: &HashMap<String, Arc<rustls::ClientConfig>>,
+) -> Arc<rustls::ClientConfig> {
+    dst.host()
+        .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+        .and_then(|host| tls_hosts.get(&host.to_ascii_lowercase()))
+        .unwrap_or(tls)
+        .clone()
+}
+
 impl Inner {
-    #[cfg(feature = "socks")]
     fn get_http_connector(&mut self) -> &mut crate::connect::HttpConnector {
         match self {
             #[cfg(feature = "__native-tls")]
@@ -536,6 +631,7 @@ impl Inner {
 impl ConnectorService {
     #[cfg(feature = "socks")]
     async fn connect_socks(mut self, dst: Uri, proxy: Intercepted) -> Result<Conn, BoxError> {
+        let proxy_dst = proxy.uri().clone();
         let dns = match proxy.uri().scheme_str() {
             Some("socks4") | Some("socks5") => socks::DnsResolve::Local,
             Some("socks4a") | Some("socks5h") => socks::DnsResolve::Proxy,
@@ -549,11 +645,14 @@ impl ConnectorService {
             Inner::NativeTls(http, tls) => {
                 if dst.scheme() == Some(&Scheme::HTTPS) {
                     let host = dst.host().ok_or("no host in url")?.to_string();
-                    let conn = socks::connect(proxy, dst, dns, &self.resolver, http).await?;
+                    let conn = socks::connect(proxy, dst, dns, &self.resolver, http)
+                        .await
+                        .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
                     let conn = TokioIo::new(conn);
                     let conn = TokioIo::new(conn);
                     let tls_connector = tokio_native_tls::TlsConnector::from(tls.clone());
-                    let io = tls_connector.connect(&host, conn).await?;
+                    let span = trace_span!("tls", server.address = host.as_str());
+                    let io = tls_connector.connect(&host, conn).instrument(span).await?;
                     let io = TokioIo::new(io);
                     return Ok(Conn {
                         inner: self.verbose.wrap(NativeTlsConn { inner: io }),
@@ -563,21 +662,30 @@ impl ConnectorService {
                 }
             }
             #[cfg(feature = "__rustls")]
-            Inner::RustlsTls { http, tls, .. } => {
+            Inner::RustlsTls {
+                http,
+                tls,
+                tls_hosts,
+                ..
+            } => {
                 if dst.scheme() == Some(&Scheme::HTTPS) {
                     use std::convert::TryFrom;
                     use tokio_rustls::TlsConnector as RustlsConnector;
 
-                    let tls = tls.clone();
+                    let tls = rustls_config_for(&dst, tls, tls_hosts);
                     let host = dst.host().ok_or("no host in url")?.to_string();
-                    let conn = socks::connect(proxy, dst, dns, &self.resolver, http).await?;
+                    let conn = socks::connect(proxy, dst, dns, &self.resolver, http)
+                        .await
+                        .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
                     let conn = TokioIo::new(conn);
                     let conn = TokioIo::new(conn);
                     let server_name =
                         rustls_pki_types::ServerName::try_from(host.as_str().to_owned())
                             .map_err(|_| "Invalid Server Name")?;
+                    let span = trace_span!("tls", server.address = host.as_str());
                     let io = RustlsConnector::from(tls)
                         .connect(server_name, conn)
+                        .instrument(span)
                         .await?;
                     let io = TokioIo::new(io);
                     return Ok(Conn {
@@ -589,7 +697,9 @@ impl ConnectorService {
             }
             #[cfg(not(feature = "__tls"))]
             Inner::Http(http) => {
-                let conn = socks::connect(proxy, dst, dns, &self.resolver, http).await?;
+                let conn = socks::connect(proxy, dst, dns, &self.resolver, http)
+                    .await
+                    .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
                 return Ok(Conn {
                     inner: self.verbose.wrap(TokioIo::new(conn)),
                     is_proxy: false,
@@ -607,7 +717,7 @@ impl ConnectorService {
                 is_proxy: false,
                 tls_info: false,
             })
-            .map_err(Into::into)
+            .map_err(|e| crate::error::proxy(&proxy_dst, e))
     }
 
     async fn connect_with_maybe_proxy(self, dst: Uri, is_proxy: bool) -> Result<Conn, BoxError> {
@@ -633,6 +743,7 @@ impl ConnectorService {
                 }
 
                 let tls_connector = tokio_native_tls::TlsConnector::from(tls.clone());
+                let http = crate::trace::tls_handshake(http);
                 let mut http = hyper_tls::HttpsConnector::from((http, tls_connector));
                 let io = http.call(dst).await?;
 
@@ -661,7 +772,12 @@ impl ConnectorService {
                 }
             }
             #[cfg(feature = "__rustls")]
-            Inner::RustlsTls { http, tls, .. } => {
+            Inner::RustlsTls {
+                http,
+                tls,
+                tls_hosts,
+                ..
+            } => {
                 let mut http = http.clone();
 
                 // Disable Nagle's algorithm for TLS handshake
@@ -671,7 +787,9 @@ impl ConnectorService {
                     http.set_nodelay(true);
                 }
 
-                let mut http = hyper_rustls::HttpsConnector::from((http, tls.clone()));
+                let tls = rustls_config_for(&dst, &tls, &tls_hosts);
+                let http = crate::trace::tls_handshake(http);
+                let mut http = hyper_rustls::HttpsConnector::from((http, tls));
                 let io = http.call(dst).await?;
 
                 if let hyper_rustls::MaybeHttpsStream::Https(stream) = io {
@@ -741,6 +859,7 @@ impl ConnectorService {
             #[cfg(feature = "__native-tls")]
             Inner::NativeTls(_, tls) => {
                 let tls_connector = tokio_native_tls::TlsConnector::from(tls.clone());
+                let svc = crate::trace::tls_handshake(svc);
                 let mut http = hyper_tls::HttpsConnector::from((svc, tls_connector));
                 let io = http.call(dst).await?;
 
@@ -759,8 +878,10 @@ impl ConnectorService {
                 }
             }
             #[cfg(feature = "__rustls")]
-            Inner::RustlsTls { tls, .. } => {
-                let mut http = hyper_rustls::HttpsConnector::from((svc, tls.clone()));
+            Inner::RustlsTls { tls, tls_hosts, .. } => {
+                let tls = rustls_config_for(&dst, &tls, &tls_hosts);
+                let svc = crate::trace::tls_handshake(svc);
+                let mut http = hyper_rustls::HttpsConnector::from((svc, tls));
                 let io = http.call(dst).await?;
 
                 if let hyper_rustls::MaybeHttpsStream::Https(stream) = io {
@@ -780,9 +901,45 @@ impl ConnectorService {
         }
     }
 
+    /// Connects through the first of `proxies` that works, passing over
+    /// those that don't for a while.
+    async fn connect_via_failover(
+        self,
+        dst: Uri,
+        proxies: Vec<Intercepted>,
+        timeout: Option<Duration>,
+    ) -> Result<Conn, BoxError> {
+        let mut last_err = None;
+        for proxy in proxies {
+            let health = proxy.health().cloned();
+            let connecting = self.clone().connect_via_proxy(dst.clone(), proxy);
+            match with_timeout(connecting, timeout).await {
+                Ok(conn) => {
+                    return Ok(match health {
+                        // Its gateway errors are answers to requests.
+                        Some(health) if conn.is_proxy => conn.through_proxy(health),
+                        _ => conn,
+                    });
+                }
+                Err(err) => {
+                    log::debug!("proxy failed, trying the next one: {err}");
+                    if let Some(health) = health {
+                        health.failed();
+                    }
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.expect("failover has several proxies"))
+    }
+
     async fn connect_via_proxy(self, dst: Uri, proxy: Intercepted) -> Result<Conn, BoxError> {
         log::debug!("proxy({proxy:?}) intercepts '{dst:?}'");
 
+        if !proxy.chain().is_empty() {
+            return self.connect_chained(dst, proxy).await;
+        }
+
         #[cfg(feature = "socks")]
         match proxy.uri().scheme_str().ok_or("proxy scheme expected")? {
             "socks4" | "socks4a" | "socks5" | "socks5h" => {
@@ -796,7 +953,7 @@ impl ConnectorService {
         let auth = proxy.basic_auth().cloned();
 
         #[cfg(feature = "__tls")]
-        let misc = proxy.custom_headers().clone();
+        let misc = proxy.connect_headers();
 
         #[cfg(all(windows, feature = "negotiate", feature = "__tls"))]
         let is_negotiate = proxy.is_negotiate();
@@ -812,11 +969,11 @@ impl ConnectorService {
                         let mut http_connector = http.clone();
                         std::future::poll_fn(|cx| http_connector.poll_ready(cx))
                             .await
-                            .map_err(|e| -> BoxError { Box::new(e) })?;
+                            .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
                         let mut proxy_conn = http_connector
                             .call(proxy_dst.clone())
                             .await
-                            .map_err(|e| -> BoxError { Box::new(e) })?;
+                            .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
 
                         let target_host = dst.host().ok_or("no host in url")?;
                         let target_port = dst.port_u16().unwrap_or(443);
@@ -827,9 +984,8 @@ impl ConnectorService {
                             extra_headers.insert(http::header::USER_AGENT, ua.clone());
                         }
                         if let Some(ref custom_headers) = misc {
-                            extra_headers.extend(
-                                custom_headers.iter().map(|(k, v)| (k.clone(), v.clone())),
-                            );
+                            extra_headers
+                                .extend(custom_headers.iter().map(|(k, v)| (k.clone(), v.clone())));
                         }
 
                         tunnel_negotiate_with_spn(
@@ -839,13 +995,15 @@ impl ConnectorService {
                             proxy_host_name,
                             &extra_headers,
                         )
-                        .await?;
+                        .await
+                        .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
 
                         // Wrap tunneled connection in TLS for the target
-                        let tls_connector =
-                            tokio_native_tls::TlsConnector::from(tls.clone());
+                        let tls_connector = tokio_native_tls::TlsConnector::from(tls.clone());
+                        let span = trace_span!("tls", server.address = target_host);
                         let io = tls_connector
                             .connect(target_host, TokioIo::new(proxy_conn))
+                            .instrument(span)
                             .await?;
                         return Ok(Conn {
                             inner: self.verbose.wrap(NativeTlsConn {
@@ -860,8 +1018,7 @@ impl ConnectorService {
                     let tls_connector = tokio_native_tls::TlsConnector::from(tls.clone());
                     let inner =
                         hyper_tls::HttpsConnector::from((http.clone(), tls_connector.clone()));
-                    let mut tunnel =
-                        hyper_util::client::legacy::connect::proxy::Tunnel::new(proxy_dst, inner);
+                    let mut tunnel = tunnel::Tunnel::new(proxy_dst.clone(), inner);
                     if let Some(auth) = auth {
                         tunnel = tunnel.with_auth(auth);
                     }
@@ -871,12 +1028,18 @@ impl ConnectorService {
                         tunnel = tunnel.with_headers(headers);
                     }
                     if let Some(custom_headers) = misc {
-                        tunnel = tunnel.with_headers(custom_headers.clone());
+                        tunnel = tunnel.with_headers(custom_headers);
                     }
-                    let tunneled = tunnel.call(dst.clone()).await?;
+                    let tunneled = tunnel
+                        .call(dst.clone())
+                        .await
+                        .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
                     let tls_connector = tokio_native_tls::TlsConnector::from(tls.clone());
+                    let host = dst.host().ok_or("no host in url")?;
+                    let span = trace_span!("tls", server.address = host);
                     let io = tls_connector
-                        .connect(dst.host().ok_or("no host in url")?, TokioIo::new(tunneled))
+                        .connect(host, TokioIo::new(tunneled))
+                        .instrument(span)
                         .await?;
                     return Ok(Conn {
                         inner: self.verbose.wrap(NativeTlsConn {
@@ -892,12 +1055,15 @@ impl ConnectorService {
                 http,
                 tls,
                 tls_proxy,
+                tls_hosts,
             } => {
                 if dst.scheme() == Some(&Scheme::HTTPS) {
                     use rustls_pki_types::ServerName;
                     use std::convert::TryFrom;
                     use tokio_rustls::TlsConnector as RustlsConnector;
 
+                    let tls = rustls_config_for(&dst, tls, tls_hosts);
+
                     // Negotiate (SPNEGO/Kerberos) proxy tunnel
                     #[cfg(all(windows, feature = "negotiate"))]
                     if is_negotiate {
@@ -905,11 +1071,11 @@ impl ConnectorService {
                         let mut http_connector = http.clone();
                         std::future::poll_fn(|cx| http_connector.poll_ready(cx))
                             .await
-                            .map_err(|e| -> BoxError { Box::new(e) })?;
+                            .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
                         let mut proxy_conn = http_connector
                             .call(proxy_dst.clone())
                             .await
-                            .map_err(|e| -> BoxError { Box::new(e) })?;
+                            .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
 
                         let target_host = dst.host().ok_or("no host in url")?;
                         let target_port = dst.port_u16().unwrap_or(443);
@@ -920,9 +1086,8 @@ impl ConnectorService {
                             extra_headers.insert(http::header::USER_AGENT, ua.clone());
                         }
                         if let Some(ref custom_headers) = misc {
-                            extra_headers.extend(
-                                custom_headers.iter().map(|(k, v)| (k.clone(), v.clone())),
-                            );
+                            extra_headers
+                                .extend(custom_headers.iter().map(|(k, v)| (k.clone(), v.clone())));
                         }
 
                         tunnel_negotiate_with_spn(
@@ -932,14 +1097,17 @@ impl ConnectorService {
                             proxy_host_name,
                             &extra_headers,
                         )
-                        .await?;
+                        .await
+                        .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
 
                         // Wrap tunneled connection in TLS for the target
                         let host = target_host.to_string();
                         let server_name = ServerName::try_from(host.as_str().to_owned())
                             .map_err(|_| "Invalid Server Name")?;
+                        let span = trace_span!("tls", server.address = target_host);
                         let io = RustlsConnector::from(tls.clone())
                             .connect(server_name, TokioIo::new(proxy_conn))
+                            .instrument(span)
                             .await?;
 
                         return Ok(Conn {
@@ -954,25 +1122,29 @@ impl ConnectorService {
                     log::trace!("tunneling HTTPS over proxy");
                     let http = http.clone();
                     let inner = hyper_rustls::HttpsConnector::from((http, tls_proxy.clone()));
-                    let mut tunnel =
-                        hyper_util::client::legacy::connect::proxy::Tunnel::new(proxy_dst, inner);
+                    let mut tunnel = tunnel::Tunnel::new(proxy_dst.clone(), inner);
                     if let Some(auth) = auth {
                         tunnel = tunnel.with_auth(auth);
                     }
                     if let Some(custom_headers) = misc {
-                        tunnel = tunnel.with_headers(custom_headers.clone());
+                        tunnel = tunnel.with_headers(custom_headers);
                     }
                     if let Some(ua) = self.user_agent {
                         let mut headers = http::HeaderMap::new();
                         headers.insert(http::header::USER_AGENT, ua);
                         tunnel = tunnel.with_headers(headers);
                     }
-                    let tunneled = tunnel.call(dst.clone()).await?;
+                    let tunneled = tunnel
+                        .call(dst.clone())
+                        .await
+                        .map_err(|e| crate::error::proxy(&proxy_dst, e))?;
                     let host = dst.host().ok_or("no host in url")?.to_string();
                     let server_name = ServerName::try_from(host.as_str().to_owned())
                         .map_err(|_| "Invalid Server Name")?;
+                    let span = trace_span!("tls", server.address = host.as_str());
                     let io = RustlsConnector::from(tls.clone())
                         .connect(server_name, TokioIo::new(tunneled))
+                        .instrument(span)
                         .await?;
 
                     return Ok(Conn {
@@ -988,7 +1160,72 @@ impl ConnectorService {
             Inner::Http(_) => (),
         }
 
-        self.connect_with_maybe_proxy(proxy_dst, true).await
+        self.connect_with_maybe_proxy(proxy_dst.clone(), true)
+            .await
+            .map_err(|e| crate::error::proxy(&proxy_dst, e))
+    }
+
+    /// Connects through the proxies chained before `proxy`, then through it.
+    async fn connect_chained(mut self, dst: Uri, proxy: Intercepted) -> Result<Conn, BoxError> {
+        #[cfg(feature = "__tls")]
+        let user_agent = self.user_agent.clone();
+        #[cfg(not(feature = "__tls"))]
+        let user_agent = None;
+        #[cfg(feature = "socks")]
+        let resolver = self.resolver.clone();
+        #[cfg(not(feature = "socks"))]
+        let resolver = ();
+        let http = self.inner.get_http_connector();
+        let (conn, is_proxy) = chain::connect(&proxy, &dst, user_agent, &resolver, http).await?;
+
+        #[cfg(feature = "__tls")]
+        if dst.scheme() == Some(&Scheme::HTTPS) {
+            let host = dst.host().ok_or("no host in url")?.to_string();
+            let span = trace_span!("tls", server.address = host.as_str());
+            match &self.inner {
+                #[cfg(feature = "__native-tls")]
+                Inner::NativeTls(_, tls) => {
+                    let tls_connector = tokio_native_tls::TlsConnector::from(tls.clone());
+                    let io = tls_connector
+                        .connect(&host, TokioIo::new(conn))
+                        .instrument(span)
+                        .await?;
+                    return Ok(Conn {
+                        inner: self.verbose.wrap(NativeTlsConn {
+                            inner: TokioIo::new(io),
+                        }),
+                        is_proxy: false,
+                        tls_info: self.tls_info,
+                    });
+                }
+                #[cfg(feature = "__rustls")]
+                Inner::RustlsTls { tls, tls_hosts, .. } => {
+                    use std::convert::TryFrom;
+                    use tokio_rustls::TlsConnector as RustlsConnector;
+
+                    let tls = rustls_config_for(&dst, tls, tls_hosts);
+                    let server_name = rustls_pki_types::ServerName::try_from(host.clone())
+                        .map_err(|_| "Invalid Server Name")?;
+                    let io = RustlsConnector::from(tls)
+                        .connect(server_name, TokioIo::new(conn))
+                        .instrument(span)
+                        .await?;
+                    return Ok(Conn {
+                        inner: self.verbose.wrap(RustlsTlsConn {
+                            inner: TokioIo::new(io),
+                        }),
+                        is_proxy: false,
+                        tls_info: self.tls_info,
+                    });
+                }
+            }
+        }
+
+        Ok(Conn {
+            inner: self.verbose.wrap(conn),
+            is_proxy,
+            tls_info: false,
+        })
     }
 
     #[cfg(any(unix, target_os = "windows"))]
@@ -1027,6 +1264,40 @@ impl Service<Uri> for ConnectorService {
 
     fn call(&mut self, dst: Uri) -> Self::Future {
         log::debug!("starting new connection: {dst:?}");
+        let span = trace_span!(
+            "connect",
+            server.address = dst.host(),
+            server.port = crate::trace::server_port(&dst),
+        );
+        let header_case = self.header_case.clone();
+        #[cfg(feature = "http2")]
+        if self.h2c && dst.scheme() == Some(&Scheme::HTTP) {
+            let connecting = self.clone().connect(dst);
+            return Box::pin(
+                async move {
+                    let conn = connecting.await?;
+                    // A plain HTTP proxy is still spoken to in HTTP/1.1.
+                    if conn.is_proxy {
+                        Ok(conn.header_case(header_case))
+                    } else {
+                        Ok(conn.h2c())
+                    }
+                }
+                .instrument(span),
+            );
+        }
+        if header_case.is_some() {
+            let connecting = self.connect(dst);
+            return Box::pin(
+                async move { Ok(connecting.await?.header_case(header_case)) }.instrument(span),
+            );
+        }
+        Box::pin(self.connect(dst).instrument(span))
+    }
+}
+
+impl ConnectorService {
+    fn connect(&mut self, dst: Uri) -> Connecting {
         let timeout = self.simple_timeout;
 
         // Local transports (UDS, Windows Named Pipes) skip proxies
@@ -1039,7 +1310,11 @@ impl Service<Uri> for ConnectorService {
         }
 
         for prox in self.proxies.iter() {
-            if let Some(intercepted) = prox.intercept(&dst) {
+            let mut proxies = prox.intercept_all(&dst);
+            if proxies.len() > 1 {
+                return Box::pin(self.clone().connect_via_failover(dst, proxies, timeout));
+            }
+            if let Some(intercepted) = proxies.pop() {
                 return Box::pin(with_timeout(
                     self.clone().connect_via_proxy(dst, intercepted),
                     timeout,
@@ -1078,13 +1353,7 @@ impl TlsInfoFactory for tokio::net::TcpStream {
 #[cfg(feature = "__native-tls")]
 impl TlsInfoFactory for tokio_native_tls::TlsStream<TokioIo<TokioIo<tokio::net::TcpStream>>> {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .peer_certificate()
-            .ok()
-            .flatten()
-            .and_then(|c| c.to_der().ok());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_native_tls(self.get_ref()))
     }
 }
 
@@ -1095,13 +1364,7 @@ impl TlsInfoFactory
     >
 {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .peer_certificate()
-            .ok()
-            .flatten()
-            .and_then(|c| c.to_der().ok());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_native_tls(self.get_ref()))
     }
 }
 
@@ -1118,13 +1381,7 @@ impl TlsInfoFactory for hyper_tls::MaybeHttpsStream<TokioIo<tokio::net::TcpStrea
 #[cfg(feature = "__rustls")]
 impl TlsInfoFactory for tokio_rustls::client::TlsStream<TokioIo<TokioIo<tokio::net::TcpStream>>> {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .1
-            .peer_certificates()
-            .and_then(|certs| certs.first())
-            .map(|c| c.to_vec());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_rustls(self.get_ref().1))
     }
 }
 
@@ -1135,13 +1392,7 @@ impl TlsInfoFactory
     >
 {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .1
-            .peer_certificates()
-            .and_then(|certs| certs.first())
-            .map(|c| c.to_vec());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_rustls(self.get_ref().1))
     }
 }
 
@@ -1169,13 +1420,7 @@ impl TlsInfoFactory for tokio::net::UnixStream {
 #[cfg(unix)]
 impl TlsInfoFactory for tokio_native_tls::TlsStream<TokioIo<TokioIo<tokio::net::UnixStream>>> {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .peer_certificate()
-            .ok()
-            .flatten()
-            .and_then(|c| c.to_der().ok());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_native_tls(self.get_ref()))
     }
 }
 
@@ -1187,13 +1432,7 @@ impl TlsInfoFactory
     >
 {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .peer_certificate()
-            .ok()
-            .flatten()
-            .and_then(|c| c.to_der().ok());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_native_tls(self.get_ref()))
     }
 }
 
@@ -1212,13 +1451,7 @@ impl TlsInfoFactory for hyper_tls::MaybeHttpsStream<TokioIo<tokio::net::UnixStre
 #[cfg(unix)]
 impl TlsInfoFactory for tokio_rustls::client::TlsStream<TokioIo<TokioIo<tokio::net::UnixStream>>> {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .1
-            .peer_certificates()
-            .and_then(|certs| certs.first())
-            .map(|c| c.to_vec());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_rustls(self.get_ref().1))
     }
 }
 
@@ -1230,13 +1463,7 @@ impl TlsInfoFactory
     >
 {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .1
-            .peer_certificates()
-            .and_then(|certs| certs.first())
-            .map(|c| c.to_vec());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_rustls(self.get_ref().1))
     }
 }
 
@@ -1269,13 +1496,7 @@ impl TlsInfoFactory
     >
 {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .peer_certificate()
-            .ok()
-            .flatten()
-            .and_then(|c| c.to_der().ok());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_native_tls(self.get_ref()))
     }
 }
 
@@ -1289,13 +1510,7 @@ impl TlsInfoFactory
     >
 {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .peer_certificate()
-            .ok()
-            .flatten()
-            .and_then(|c| c.to_der().ok());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_native_tls(self.get_ref()))
     }
 }
 
@@ -1320,13 +1535,7 @@ impl TlsInfoFactory
     >
 {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .1
-            .peer_certificates()
-            .and_then(|certs| certs.first())
-            .map(|c| c.to_vec());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_rustls(self.get_ref().1))
     }
 }
 
@@ -1342,13 +1551,7 @@ impl TlsInfoFactory
     >
 {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .get_ref()
-            .1
-            .peer_certificates()
-            .and_then(|certs| certs.first())
-            .map(|c| c.to_vec());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_rustls(self.get_ref().1))
     }
 }
 
@@ -1404,9 +1607,9 @@ async fn tunnel_negotiate_with_spn<T>(
 where
     T: Read + Write + Unpin,
 {
-    use hyper::rt::ReadBuf;
+    use crate::auth::{sspi::SspiContext, Credentials};
     use base64::Engine as _;
-    use crate::auth::{Credentials, sspi::SspiContext};
+    use hyper::rt::ReadBuf;
 
     const MAX_ROUNDS: usize = 5;
 
@@ -1459,9 +1662,7 @@ where
         None
     }
 
-    async fn read_response<R: Read + Unpin>(
-        io: &mut R,
-    ) -> Result<(u16, Option<String>), BoxError> {
+    async fn read_response<R: Read + Unpin>(io: &mut R) -> Result<(u16, Option<String>), BoxError> {
         let mut buf = [0u8; 8192];
         let mut pos = 0;
 
@@ -1532,9 +1733,7 @@ where
     let proxy_auth_val =
         proxy_auth.ok_or("proxy returned 407 without Proxy-Authenticate header")?;
     if !proxy_auth_val.to_lowercase().starts_with("negotiate") {
-        return Err(
-            format!("proxy requires unsupported auth method: {proxy_auth_val}").into(),
-        );
+        return Err(format!("proxy requires unsupported auth method: {proxy_auth_val}").into());
     }
 
     let server_token = extract_negotiate_token(&proxy_auth_val)?;
@@ -1546,7 +1745,7 @@ where
 
     let mut input_token = server_token;
 
-    for _round in 0..MAX_ROUNDS {
+    for round in 0..MAX_ROUNDS {
         let (output_token, _is_complete) = ctx
             .initialize_context(&spn, input_token.as_deref())
             .map_err(|code| format!("SSPI InitializeSecurityContext failed: 0x{code:08X}"))?;
@@ -1555,9 +1754,17 @@ where
         let auth_header = format!("Negotiate {token_b64}");
 
         let req = build_connect(target_host, target_port, extra_headers, Some(&auth_header));
-        write_all(conn, &req).await?;
-
-        let (status, proxy_auth) = read_response(conn).await?;
+        let span = trace_span!(
+            "auth",
+            http.auth.scheme = "Negotiate",
+            http.auth.round = round
+        );
+        let (status, proxy_auth) = async {
+            write_all(conn, &req).await?;
+            read_response(conn).await
+        }
+        .instrument(span)
+        .await?;
 
         if status == 200 {
             return Ok(());
@@ -1579,6 +1786,33 @@ where
     Err("proxy negotiate: too many authentication rounds".into())
 }
 
+/// Kept by each connection, and copied into the extensions of every
+/// response received over it, to tell a reused connection apart when a
+/// request fails.
+#[derive(Clone, Default)]
+pub(crate) struct ConnectionUse(Arc<std::sync::atomic::AtomicBool>);
+
+impl ConnectionUse {
+    /// Records that a response was received over a connection.
+    pub(crate) fn response(extensions: &http::Extensions) {
+        if let Some(conn) = extensions.get::<ConnectionUse>() {
+            conn.0.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Whether the connection `err` happened on had already been used.
+    pub(crate) fn was_reused(err: &hyper_util::client::legacy::Error) -> bool {
+        let mut extensions = http::Extensions::new();
+        match err.connect_info() {
+            Some(connected) => connected.get_extras(&mut extensions),
+            None => return false,
+        }
+        extensions.get::<ConnectionUse>().map_or(false, |conn| {
+            conn.0.load(std::sync::atomic::Ordering::Relaxed)
+        })
+    }
+}
+
 pub(crate) mod sealed {
     use super::*;
     #[derive(Debug)]
@@ -1601,7 +1835,13 @@ pub(crate) mod sealed {
 
     impl Connection for Conn {
         fn connected(&self) -> Connected {
-            let connected = self.inner.connected().proxy(self.is_proxy);
+            let connected = self
+                .inner
+                .connected()
+                .proxy(self.is_proxy)
+                .extra(super::ConnectionUse::default());
+            #[cfg(feature = "metrics")]
+            let connected = connected.extra(crate::metrics::Connection::new());
             #[cfg(feature = "__tls")]
             if self.tls_info {
                 if let Some(tls_info) = self.inner.tls_info() {
@@ -1661,6 +1901,162 @@ pub(crate) mod sealed {
             Write::poll_shutdown(this.inner, cx)
         }
     }
+
+    impl Conn {
+        /// Write the header names in `case` with their casing, unless HTTP/2
+        /// was negotiated.
+        pub(super) fn header_case(self, case: Option<Arc<header_case::HeaderCase>>) -> Conn {
+            match case {
+                Some(case) if !self.inner.connected().is_negotiated_h2() => Conn {
+                    inner: Box::new(header_case::Cased::new(self.inner, case)),
+                    ..self
+                },
+                _ => self,
+            }
+        }
+    }
+
+    impl Conn {
+        /// Tell requests forwarded by the proxy which one it is, so its
+        /// gateway errors can make it pass over.
+        pub(super) fn through_proxy(self, health: ProxyHealth) -> Conn {
+            Conn {
+                inner: Box::new(ThroughProxy(self.inner, health)),
+                ..self
+            }
+        }
+    }
+
+    struct ThroughProxy(BoxConn, ProxyHealth);
+
+    impl Connection for ThroughProxy {
+        fn connected(&self) -> Connected {
+            self.0.connected().extra(self.1.clone())
+        }
+    }
+
+    impl Read for ThroughProxy {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: ReadBufCursor<'_>,
+        ) -> Poll<io::Result<()>> {
+            Read::poll_read(Pin::new(&mut self.0), cx, buf)
+        }
+    }
+
+    impl Write for ThroughProxy {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: &[u8],
+        ) -> Poll<Result<usize, io::Error>> {
+            Write::poll_write(Pin::new(&mut self.0), cx, buf)
+        }
+
+        fn poll_write_vectored(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            bufs: &[IoSlice<'_>],
+        ) -> Poll<Result<usize, io::Error>> {
+            Write::poll_write_vectored(Pin::new(&mut self.0), cx, bufs)
+        }
+
+        fn is_write_vectored(&self) -> bool {
+            self.0.is_write_vectored()
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
+            Write::poll_flush(Pin::new(&mut self.0), cx)
+        }
+
+        fn poll_shutdown(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+        ) -> Poll<Result<(), io::Error>> {
+            Write::poll_shutdown(Pin::new(&mut self.0), cx)
+        }
+    }
+
+    #[cfg(feature = "__tls")]
+    impl TlsInfoFactory for ThroughProxy {
+        fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
+            self.0.tls_info()
+        }
+    }
+
+    #[cfg(feature = "http2")]
+    impl Conn {
+        /// Speak HTTP/2 on this connection without negotiating it first.
+        pub(super) fn h2c(self) -> Conn {
+            Conn {
+                inner: Box::new(PriorKnowledge(self.inner)),
+                ..self
+            }
+        }
+    }
+
+    #[cfg(feature = "http2")]
+    struct PriorKnowledge(BoxConn);
+
+    #[cfg(feature = "http2")]
+    impl Connection for PriorKnowledge {
+        fn connected(&self) -> Connected {
+            self.0.connected().negotiated_h2()
+        }
+    }
+
+    #[cfg(feature = "http2")]
+    impl Read for PriorKnowledge {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: ReadBufCursor<'_>,
+        ) -> Poll<io::Result<()>> {
+            Read::poll_read(Pin::new(&mut self.0), cx, buf)
+        }
+    }
+
+    #[cfg(feature = "http2")]
+    impl Write for PriorKnowledge {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: &[u8],
+        ) -> Poll<Result<usize, io::Error>> {
+            Write::poll_write(Pin::new(&mut self.0), cx, buf)
+        }
+
+        fn poll_write_vectored(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            bufs: &[IoSlice<'_>],
+        ) -> Poll<Result<usize, io::Error>> {
+            Write::poll_write_vectored(Pin::new(&mut self.0), cx, bufs)
+        }
+
+        fn is_write_vectored(&self) -> bool {
+            self.0.is_write_vectored()
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
+            Write::poll_flush(Pin::new(&mut self.0), cx)
+        }
+
+        fn poll_shutdown(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+        ) -> Poll<Result<(), io::Error>> {
+            Write::poll_shutdown(Pin::new(&mut self.0), cx)
+        }
+    }
+
+    #[cfg(all(feature = "http2", feature = "__tls"))]
+    impl TlsInfoFactory for PriorKnowledge {
+        fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
+            self.0.tls_info()
+        }
+    }
 }
 
 // Some sealed things for UDS
@@ -2127,7 +2523,9 @@ mod socks {
 
     use http::uri::Scheme;
     use http::Uri;
+    use hyper::rt::{Read, Write};
     use hyper_util::client::legacy::connect::proxy::{SocksV4, SocksV5};
+    use hyper_util::rt::TokioIo;
     use tokio::net::TcpStream;
 
     use super::BoxError;
@@ -2152,6 +2550,25 @@ mod socks {
         resolver: &crate::dns::DynResolver,
         http_connector: &mut crate::connect::HttpConnector,
     ) -> Result<TcpStream, SocksProxyError> {
+        handshake(&proxy, dst, dns_mode, resolver, http_connector)
+            .await
+            .map(TokioIo::into_inner)
+    }
+
+    /// Asks `proxy`, reached with `connector`, for a connection to `dst`.
+    pub(super) async fn handshake<C>(
+        proxy: &Intercepted,
+        dst: Uri,
+        dns_mode: DnsResolve,
+        resolver: &crate::dns::DynResolver,
+        connector: C,
+    ) -> Result<C::Response, SocksProxyError>
+    where
+        C: Service<Uri>,
+        C::Future: Send + 'static,
+        C::Response: Read + Write + Unpin + Send + 'static,
+        C::Error: std::fmt::Debug + std::fmt::Display + Send + Sync + 'static,
+    {
         let https = dst.scheme() == Some(&Scheme::HTTPS);
         let original_host = dst.host().ok_or(SocksProxyError::SocksNoHostInUrl)?;
         let mut host = original_host.to_owned();
@@ -2193,23 +2610,21 @@ mod socks {
         // TODO: can `Scheme::from_static()` be const fn, compare with a SOCKS5 constant?
         match proxy.uri().scheme_str() {
             Some("socks4") | Some("socks4a") => {
-                let mut svc = SocksV4::new(proxy_uri, http_connector);
-                let stream = Service::call(&mut svc, dst_uri)
+                let mut svc = SocksV4::new(proxy_uri, connector);
+                Service::call(&mut svc, dst_uri)
                     .await
-                    .map_err(|e| SocksProxyError::SocksConnect(e.into()))?;
-                Ok(stream.into_inner())
+                    .map_err(|e| SocksProxyError::SocksConnect(e.into()))
             }
             Some("socks5") | Some("socks5h") => {
                 let mut svc = if let Some((username, password)) = proxy.raw_auth() {
-                    SocksV5::new(proxy_uri, http_connector)
+                    SocksV5::new(proxy_uri, connector)
                         .with_auth(username.to_string(), password.to_string())
                 } else {
-                    SocksV5::new(proxy_uri, http_connector)
+                    SocksV5::new(proxy_uri, connector)
                 };
-                let stream = Service::call(&mut svc, dst_uri)
+                Service::call(&mut svc, dst_uri)
                     .await
-                    .map_err(|e| SocksProxyError::SocksConnect(e.into()))?;
-                Ok(stream.into_inner())
+                    .map_err(|e| SocksProxyError::SocksConnect(e.into()))
             }
             _ => unreachable!(),
         }
@@ -2384,3 +2799,35 @@ mod verbose {
         }
     }
 }
+
+#[cfg(all(test, feature = "__rustls-aws-lc-rs"))]
+mod tests {
+    use super::*;
+
+    fn rustls_config() -> Arc<rustls::ClientConfig> {
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let tls = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(rustls::RootCertStore::empty())
+            .with_no_client_auth();
+        Arc::new(tls)
+    }
+
+    #[test]
+    fn rustls_config_for_host() {
+        let default = rustls_config();
+        let billing = rustls_config();
+        let v6 = rustls_config();
+        let mut hosts = HashMap::new();
+        hosts.insert("billing.internal".to_owned(), billing.clone());
+        hosts.insert("::1".to_owned(), v6.clone());
+
+        let pick = |uri: &str| rustls_config_for(&uri.parse().unwrap(), &default, &hosts);
+
+        assert!(Arc::ptr_eq(&pick("https://billing.internal/"), &billing));
+        assert!(Arc::ptr_eq(&pick("https://BILLING.internal/"), &billing));
+        assert!(Arc::ptr_eq(&pick("https://[::1]:8443/"), &v6));
+        assert!(Arc::ptr_eq(&pick("https://reports.internal/"), &default));
+    }
+}
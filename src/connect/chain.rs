@@ -0,0 +1,131 @@
+//! Tunneling through several proxies in turn.
+//!
+//! A TCP connection is made to the first proxy of the chain, then each proxy
+//! is asked, with a `CONNECT` or a SOCKS handshake over that same connection,
+//! to open a tunnel to the next one, and the last one to the destination.
+
+use std::future::{ready, Ready};
+use std::io;
+use std::task::{Context, Poll};
+
+use http::header::{HeaderValue, USER_AGENT};
+use http::uri::{Scheme, Uri};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpStream;
+use tower_service::Service;
+
+use super::tunnel::Tunnel;
+use super::HttpConnector;
+use crate::error::BoxError;
+use crate::proxy::Intercepted;
+
+/// Resolves host names for the SOCKS proxies that don't.
+#[cfg(feature = "socks")]
+type Resolver = crate::dns::DynResolver;
+#[cfg(not(feature = "socks"))]
+type Resolver = ();
+
+/// Connects to `dst` through the proxies chained before `proxy`, then
+/// `proxy` itself.
+///
+/// Returns whether the connection goes to `proxy` rather than `dst`, which
+/// is when `proxy` forwards requests to `http` URLs.
+pub(super) async fn connect(
+    proxy: &Intercepted,
+    dst: &Uri,
+    user_agent: Option<HeaderValue>,
+    #[cfg_attr(not(feature = "socks"), allow(unused_variables))] resolver: &Resolver,
+    connector: &mut HttpConnector,
+) -> Result<(TokioIo<TcpStream>, bool), BoxError> {
+    let hops = proxy.chain().iter().chain(Some(proxy)).collect::<Vec<_>>();
+    let first = hops[0].uri();
+    std::future::poll_fn(|cx| connector.poll_ready(cx))
+        .await
+        .map_err(|e| crate::error::proxy(first, e))?;
+    let mut conn = connector
+        .call(first.clone())
+        .await
+        .map_err(|e| crate::error::proxy(first, e))?;
+
+    let forward = dst.scheme() != Some(&Scheme::HTTPS) && proxy.uri().scheme_str() == Some("http");
+    for (i, hop) in hops.iter().enumerate() {
+        let target = match hops.get(i + 1) {
+            Some(next) => next.uri(),
+            None if forward => break,
+            None => dst,
+        };
+        let target = with_port(target)?;
+        log::trace!("tunneling through proxy {:?} to {target}", hop.uri());
+        conn = match hop.uri().scheme_str() {
+            Some("http") => {
+                let mut tunnel = Tunnel::new(hop.uri().clone(), Established(Some(conn)));
+                if let Some(auth) = hop.basic_auth() {
+                    tunnel = tunnel.with_auth(auth.clone());
+                }
+                if let Some(headers) = hop.connect_headers() {
+                    tunnel = tunnel.with_headers(headers);
+                }
+                if let Some(ref ua) = user_agent {
+                    let mut headers = http::HeaderMap::new();
+                    headers.insert(USER_AGENT, ua.clone());
+                    tunnel = tunnel.with_headers(headers);
+                }
+                tunnel
+                    .call(target)
+                    .await
+                    .map_err(|e| crate::error::proxy(hop.uri(), e))?
+            }
+            #[cfg(feature = "socks")]
+            Some(scheme @ ("socks4" | "socks4a" | "socks5" | "socks5h")) => {
+                let dns = match scheme {
+                    "socks4" | "socks5" => super::socks::DnsResolve::Local,
+                    _ => super::socks::DnsResolve::Proxy,
+                };
+                super::socks::handshake(hop, target, dns, resolver, Established(Some(conn)))
+                    .await
+                    .map_err(|e| crate::error::proxy(hop.uri(), e))?
+            }
+            _ => {
+                let err = "only http and SOCKS proxies can be chained";
+                return Err(crate::error::proxy(hop.uri(), err));
+            }
+        };
+    }
+    Ok((conn, forward))
+}
+
+/// `uri` with its port spelled out, as tunnels are opened to a port.
+fn with_port(uri: &Uri) -> Result<Uri, BoxError> {
+    if uri.port().is_some() {
+        return Ok(uri.clone());
+    }
+    let port = match uri.scheme_str() {
+        Some("https") => 443,
+        Some("socks4" | "socks4a" | "socks5" | "socks5h") => 1080,
+        _ => 80,
+    };
+    let host = uri.host().ok_or("no host in url")?;
+    let uri = format!("{}://{host}:{port}", uri.scheme_str().unwrap_or("http")).parse()?;
+    Ok(uri)
+}
+
+/// Hands out a connection made already, for a handshake over it.
+struct Established<T>(Option<T>);
+
+impl<T> Service<Uri> for Established<T> {
+    type Response = T;
+    type Error = io::Error;
+    type Future = Ready<Result<T, io::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _dst: Uri) -> Self::Future {
+        ready(
+            self.0
+                .take()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "connection already used")),
+        )
+    }
+}
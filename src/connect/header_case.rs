@@ -0,0 +1,401 @@
+//! Writing user-chosen header name casing on HTTP/1 connections.
+//!
+//! hyper always writes header names in lowercase (or title case). Some
+//! servers match header names case-sensitively, so the request bytes are
+//! rewritten on their way to the socket: each header name of the message
+//! head that is in the list is replaced with its configured spelling. Only
+//! the case of ASCII letters changes, so lengths and framing are untouched.
+
+use std::collections::HashMap;
+use std::io::{self, IoSlice};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http::header::{HeaderName, InvalidHeaderName};
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper_util::client::legacy::connect::{Connected, Connection};
+
+use super::BoxConn;
+
+/// Header names, keyed by their lowercase form, with the casing to send.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HeaderCase {
+    names: HashMap<HeaderName, Box<str>>,
+}
+
+impl HeaderCase {
+    pub(crate) fn insert(&mut self, name: &str) -> Result<(), InvalidHeaderName> {
+        let key = HeaderName::from_bytes(name.as_bytes())?;
+        self.names.insert(key, name.into());
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The casing to send `name` with, if one was configured.
+    pub(crate) fn get(&self, name: &HeaderName) -> Option<&str> {
+        self.names.get(name).map(|name| &**name)
+    }
+
+    fn get_bytes(&self, name: &[u8]) -> Option<&str> {
+        HeaderName::from_bytes(name)
+            .ok()
+            .and_then(|name| self.get(&name))
+    }
+}
+
+pub(super) struct Cased {
+    inner: BoxConn,
+    case: Arc<HeaderCase>,
+    framing: Framing,
+    scratch: Vec<u8>,
+}
+
+impl Cased {
+    pub(super) fn new(inner: BoxConn, case: Arc<HeaderCase>) -> Cased {
+        Cased {
+            inner,
+            case,
+            framing: Framing::Head(Head::message()),
+            scratch: Vec::new(),
+        }
+    }
+}
+
+/// Where in the outgoing stream of HTTP/1 messages the next byte is.
+#[derive(Clone, Debug)]
+enum Framing {
+    /// In a message head or a trailer section.
+    Head(Head),
+    /// In a body of known length, with this many bytes left.
+    Body(u64),
+    /// In a chunked body.
+    Chunked(Chunk),
+    /// After a `CONNECT`, an upgrade or the HTTP/2 preface, nothing written
+    /// is HTTP/1 anymore.
+    Opaque,
+}
+
+#[derive(Clone, Debug)]
+struct Head {
+    /// Whether the next line is a request line.
+    first: bool,
+    trailers: bool,
+    /// The current line, so far.
+    line: Vec<u8>,
+    length: u64,
+    chunked: bool,
+    upgrade: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Chunk {
+    /// In a chunk size line.
+    Size { size: u64, ext: bool },
+    /// In chunk data, with this many bytes left.
+    Data(u64),
+    /// In the line ending after chunk data.
+    DataEnd,
+}
+
+impl Head {
+    fn message() -> Head {
+        Head {
+            first: true,
+            trailers: false,
+            line: Vec::new(),
+            length: 0,
+            chunked: false,
+            upgrade: false,
+        }
+    }
+
+    fn trailers() -> Head {
+        Head {
+            first: false,
+            trailers: true,
+            ..Head::message()
+        }
+    }
+
+    /// Handles a complete line, without its line ending, and returns what
+    /// follows it.
+    fn end_line(&mut self, line: &[u8]) -> Option<Framing> {
+        if self.first {
+            self.first = false;
+            if line.starts_with(b"CONNECT ") {
+                self.upgrade = true;
+            } else if line.starts_with(b"PRI * HTTP/2.0") {
+                return Some(Framing::Opaque);
+            }
+            return None;
+        }
+
+        if line.is_empty() {
+            return Some(if self.trailers {
+                Framing::Head(Head::message())
+            } else if self.upgrade {
+                Framing::Opaque
+            } else if self.chunked {
+                Framing::Chunked(Chunk::Size {
+                    size: 0,
+                    ext: false,
+                })
+            } else if self.length > 0 {
+                Framing::Body(self.length)
+            } else {
+                Framing::Head(Head::message())
+            });
+        }
+
+        let colon = line.iter().position(|&b| b == b':')?;
+        let name = &line[..colon];
+        let value = String::from_utf8_lossy(&line[colon + 1..]);
+        let value = value.trim();
+        if name.eq_ignore_ascii_case(b"content-length") {
+            self.length = value.parse().unwrap_or(0);
+        } else if name.eq_ignore_ascii_case(b"transfer-encoding") {
+            self.chunked = value
+                .rsplit(',')
+                .next()
+                .map_or(false, |last| last.trim().eq_ignore_ascii_case("chunked"));
+        } else if name.eq_ignore_ascii_case(b"upgrade") {
+            self.upgrade = true;
+        }
+        None
+    }
+}
+
+impl Framing {
+    /// Rewrites the header names in `buf`, and returns how many of its bytes
+    /// were looked at.
+    ///
+    /// With `split`, this stops early rather than let a header name be cut
+    /// across two writes, which would leave it unrewritten.
+    fn scan(&mut self, buf: &mut [u8], case: &HeaderCase, split: bool) -> usize {
+        let mut i = 0;
+        while i < buf.len() {
+            match self {
+                Framing::Head(head) => {
+                    let end = buf[i..].iter().position(|&b| b == b'\n').map(|n| i + n);
+                    let rest = end.unwrap_or(buf.len());
+                    if !head.first && head.line.is_empty() {
+                        match buf[i..rest].iter().position(|&b| b == b':') {
+                            Some(colon) => {
+                                let name = &mut buf[i..i + colon];
+                                if let Some(cased) = case.get_bytes(name) {
+                                    if cased.len() == name.len() {
+                                        name.copy_from_slice(cased.as_bytes());
+                                    }
+                                }
+                            }
+                            None if end.is_none() && split && i > 0 => return i,
+                            None => (),
+                        }
+                    }
+                    head.line.extend_from_slice(&buf[i..rest]);
+                    match end {
+                        Some(end) => {
+                            i = end + 1;
+                            let mut line = std::mem::take(&mut head.line);
+                            if line.last() == Some(&b'\r') {
+                                line.pop();
+                            }
+                            if let Some(next) = head.end_line(&line) {
+                                *self = next;
+                            }
+                        }
+                        None => i = buf.len(),
+                    }
+                }
+                Framing::Body(left) => {
+                    let n = (*left).min((buf.len() - i) as u64);
+                    i += n as usize;
+                    *left -= n;
+                    if *left == 0 {
+                        *self = Framing::Head(Head::message());
+                    }
+                }
+                Framing::Chunked(Chunk::Size { size, ext }) => {
+                    let b = buf[i];
+                    i += 1;
+                    match (b as char).to_digit(16) {
+                        _ if b == b'\n' => {
+                            *self = if *size == 0 {
+                                Framing::Head(Head::trailers())
+                            } else {
+                                Framing::Chunked(Chunk::Data(*size))
+                            };
+                        }
+                        Some(digit) if !*ext => {
+                            *size = size.saturating_mul(16).saturating_add(digit.into());
+                        }
+                        _ => *ext = true,
+                    }
+                }
+                Framing::Chunked(Chunk::Data(left)) => {
+                    let n = (*left).min((buf.len() - i) as u64);
+                    i += n as usize;
+                    *left -= n;
+                    if *left == 0 {
+                        *self = Framing::Chunked(Chunk::DataEnd);
+                    }
+                }
+                Framing::Chunked(Chunk::DataEnd) => {
+                    let b = buf[i];
+                    i += 1;
+                    if b == b'\n' {
+                        *self = Framing::Chunked(Chunk::Size {
+                            size: 0,
+                            ext: false,
+                        });
+                    }
+                }
+                Framing::Opaque => return buf.len(),
+            }
+        }
+        i
+    }
+}
+
+impl Connection for Cased {
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
+}
+
+impl Read for Cased {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Read::poll_read(Pin::new(&mut self.inner), cx, buf)
+    }
+}
+
+impl Write for Cased {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = &mut *self;
+        if let Framing::Opaque = this.framing {
+            return Write::poll_write(Pin::new(&mut this.inner), cx, buf);
+        }
+
+        this.scratch.clear();
+        this.scratch.extend_from_slice(buf);
+        let mut next = this.framing.clone();
+        let limit = next.scan(&mut this.scratch, &this.case, true);
+
+        let n = match Write::poll_write(Pin::new(&mut this.inner), cx, &this.scratch[..limit]) {
+            Poll::Ready(Ok(n)) => n,
+            other => return other,
+        };
+        if n == limit {
+            this.framing = next;
+        } else {
+            this.framing.scan(&mut this.scratch[..n], &this.case, false);
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        let buf = bufs
+            .iter()
+            .find(|b| !b.is_empty())
+            .map_or(&[][..], |b| &**b);
+        self.poll_write(cx, buf)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        false
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
+        Write::poll_flush(Pin::new(&mut self.inner), cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
+        Write::poll_shutdown(Pin::new(&mut self.inner), cx)
+    }
+}
+
+#[cfg(feature = "__tls")]
+impl super::TlsInfoFactory for Cased {
+    fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
+        self.inner.tls_info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(writes: &[&[u8]]) -> Vec<u8> {
+        let mut case = HeaderCase::default();
+        case.insert("SOAPAction").unwrap();
+        case.insert("X-API-Key").unwrap();
+
+        let mut framing = Framing::Head(Head::message());
+        let mut out = Vec::new();
+        for write in writes {
+            let mut buf = write.to_vec();
+            framing.scan(&mut buf, &case, false);
+            out.extend_from_slice(&buf);
+        }
+        out
+    }
+
+    #[test]
+    fn rewrites_names_in_head() {
+        let out = rewrite(&[b"POST / HTTP/1.1\r\nsoapaction: x\r\nx-api-key: k\r\ncontent-length: 14\r\n\r\nsoapaction: no"]);
+        assert_eq!(
+            out,
+            b"POST / HTTP/1.1\r\nSOAPAction: x\r\nX-API-Key: k\r\ncontent-length: 14\r\n\r\nsoapaction: no"
+        );
+    }
+
+    #[test]
+    fn skips_chunked_body_and_rewrites_next_message() {
+        let out = rewrite(&[
+            b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n",
+            b"b\r\nsoapaction:\r\n0\r\n\r\n",
+            b"GET / HTTP/1.1\r\nx-api-key: k\r\n\r\n",
+        ]);
+        assert_eq!(
+            out,
+            b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\nb\r\nsoapaction:\r\n0\r\n\r\nGET / HTTP/1.1\r\nX-API-Key: k\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn stops_before_split_name() {
+        let mut case = HeaderCase::default();
+        case.insert("X-API-Key").unwrap();
+        let mut framing = Framing::Head(Head::message());
+        let mut buf = b"GET / HTTP/1.1\r\nx-api-".to_vec();
+        assert_eq!(framing.scan(&mut buf, &case, true), 16);
+    }
+
+    #[test]
+    fn leaves_upgraded_connection_alone() {
+        let out = rewrite(&[
+            b"GET / HTTP/1.1\r\nupgrade: websocket\r\n\r\n",
+            b"soapaction: x\r\n",
+        ]);
+        assert_eq!(
+            out,
+            b"GET / HTTP/1.1\r\nupgrade: websocket\r\n\r\nsoapaction: x\r\n"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Opening tunnels through HTTP proxies with `CONNECT`.
+//!
+//! This works like hyper-util's `Tunnel`, except that a proxy refusing to
+//! open the tunnel is reported with its response, as proxies usually explain
+//! in the body why they refused.
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::Bytes;
+use http::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, PROXY_AUTHORIZATION, TRANSFER_ENCODING,
+};
+use http::{StatusCode, Uri, Version};
+use hyper::rt::{Read, ReadBuf, Write};
+use tower_service::Service;
+
+use crate::error::{BoxError, TunnelRejected};
+
+// How long the head of the proxy's response can be.
+const MAX_HEAD: usize = 8192;
+const MAX_HEADERS: usize = 100;
+// How much of the body of a refusal is kept.
+const MAX_BODY: usize = 4096;
+
+/// Connects to a proxy with `C`, then asks it for a tunnel to the `Uri`
+/// it is called with.
+#[derive(Clone)]
+pub(super) struct Tunnel<C> {
+    headers: HeaderMap,
+    inner: C,
+    proxy_dst: Uri,
+}
+
+impl<C> Tunnel<C> {
+    pub(super) fn new(proxy_dst: Uri, connector: C) -> Self {
+        Tunnel {
+            headers: HeaderMap::new(),
+            inner: connector,
+            proxy_dst,
+        }
+    }
+
+    /// Sends `auth` as the `Proxy-Authorization` of the `CONNECT`.
+    pub(super) fn with_auth(mut self, mut auth: HeaderValue) -> Self {
+        auth.set_sensitive(true);
+        self.headers.insert(PROXY_AUTHORIZATION, auth);
+        self
+    }
+
+    /// Sends `headers` with the `CONNECT` too, replacing those of the same
+    /// names set before.
+    pub(super) fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+}
+
+impl<C> Service<Uri> for Tunnel<C>
+where
+    C: Service<Uri>,
+    C::Future: Send + 'static,
+    C::Response: Read + Write + Unpin + Send + 'static,
+    C::Error: Into<BoxError>,
+{
+    type Response = C::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<C::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let connecting = self.inner.call(self.proxy_dst.clone());
+        let headers = self.headers.clone();
+        Box::pin(async move {
+            let conn = connecting.await.map_err(Into::into)?;
+            let host = dst.host().ok_or("no host in url")?;
+            handshake(conn, host, dst.port_u16().unwrap_or(443), &headers).await
+        })
+    }
+}
+
+async fn handshake<T>(
+    mut conn: T,
+    host: &str,
+    port: u16,
+    headers: &HeaderMap,
+) -> Result<T, BoxError>
+where
+    T: Read + Write + Unpin,
+{
+    let mut req = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n").into_bytes();
+    for (name, value) in headers {
+        req.extend_from_slice(name.as_str().as_bytes());
+        req.extend_from_slice(b": ");
+        req.extend_from_slice(value.as_bytes());
+        req.extend_from_slice(b"\r\n");
+    }
+    req.extend_from_slice(b"\r\n");
+    write_all(&mut conn, &req).await?;
+
+    let mut buf = vec![0; MAX_HEAD];
+    let mut pos = 0;
+    let (head, len) = loop {
+        let n = read(&mut conn, &mut buf[pos..]).await?;
+        if n == 0 {
+            return Err("unexpected end of file from proxy".into());
+        }
+        pos += n;
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut res = httparse::Response::new(&mut headers);
+        if let httparse::Status::Complete(len) = res.parse(&buf[..pos])? {
+            break (head(&res)?, len);
+        }
+        if pos == buf.len() {
+            return Err("proxy response headers too long".into());
+        }
+    };
+
+    if head.status().is_success() {
+        // Whatever goes through the tunnel is started by the client, so
+        // bytes after the response can't be meant for it and would otherwise
+        // be lost.
+        if pos > len {
+            return Err("proxy sent data before the tunnel was used".into());
+        }
+        return Ok(conn);
+    }
+    buf.truncate(pos);
+    let body = read_body(&mut conn, buf.split_off(len), head.headers()).await;
+    Err(Box::new(TunnelRejected(head.map(|()| body))))
+}
+
+fn head(res: &httparse::Response<'_, '_>) -> Result<http::Response<()>, BoxError> {
+    let mut head = http::Response::new(());
+    *head.status_mut() = StatusCode::from_u16(res.code.unwrap_or_default())?;
+    if res.version == Some(0) {
+        *head.version_mut() = Version::HTTP_10;
+    }
+    for header in res.headers.iter() {
+        let name = HeaderName::from_bytes(header.name.as_bytes());
+        let value = HeaderValue::from_bytes(header.value);
+        if let (Ok(name), Ok(value)) = (name, value) {
+            head.headers_mut().append(name, value);
+        }
+    }
+    Ok(head)
+}
+
+/// Reads what it can of the body of a refusal, `raw` being its beginning as read.
+///
+/// The body is only there to be shown, so reading it stops quietly on
+/// errors.
+async fn read_body<T>(conn: &mut T, mut raw: Vec<u8>, headers: &HeaderMap) -> Bytes
+where
+    T: Read + Unpin,
+{
+    let chunked = headers
+        .get(TRANSFER_ENCODING)
+        .map_or(false, |te| te.as_bytes().eq_ignore_ascii_case(b"chunked"));
+    // Without a length, the body goes on until the proxy closes the
+    // connection.
+    let length = headers
+        .get(CONTENT_LENGTH)
+        .filter(|_| !chunked)
+        .and_then(|len| len.to_str().ok()?.parse().ok())
+        .unwrap_or(usize::MAX)
+        .min(MAX_BODY);
+
+    let mut buf = [0; 1024];
+    while raw.len() < length && !(chunked && raw.ends_with(b"0\r\n\r\n")) {
+        match read(conn, &mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => raw.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    let mut body = if chunked { dechunk(&raw) } else { raw };
+    body.truncate(length);
+    body.into()
+}
+
+/// Decodes a chunked body, as much of it as is there.
+fn dechunk(mut chunked: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    while let Some(end) = chunked.windows(2).position(|crlf| crlf == b"\r\n") {
+        let size = std::str::from_utf8(&chunked[..end])
+            .ok()
+            .and_then(|line| usize::from_str_radix(line.split(';').next()?.trim(), 16).ok());
+        let size = match size {
+            Some(0) | None => break,
+            Some(size) => size,
+        };
+        let chunk = &chunked[end + 2..];
+        body.extend_from_slice(&chunk[..size.min(chunk.len())]);
+        chunked = chunk.get(size + 2..).unwrap_or_default();
+    }
+    body
+}
+
+async fn write_all<T: Write + Unpin>(io: &mut T, mut buf: &[u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        let n = std::future::poll_fn(|cx| Pin::new(&mut *io).poll_write(cx, buf)).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        buf = &buf[n..];
+    }
+    std::future::poll_fn(|cx| Pin::new(&mut *io).poll_flush(cx)).await
+}
+
+async fn read<T: Read + Unpin>(io: &mut T, buf: &mut [u8]) -> io::Result<usize> {
+    std::future::poll_fn(|cx| {
+        let mut read_buf = ReadBuf::new(&mut *buf);
+        ready!(Pin::new(&mut *io).poll_read(cx, read_buf.unfilled()))?;
+        Poll::Ready(Ok(read_buf.filled().len()))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dechunk;
+
+    #[test]
+    fn dechunks_what_was_read() {
+        assert_eq!(
+            dechunk(b"5\r\nhello\r\n6;x=y\r\n world\r\n0\r\n\r\n"),
+            b"hello world"
+        );
+        assert_eq!(dechunk(b"5\r\nhello\r\nA\r\n wor"), b"hello wor");
+        assert_eq!(dechunk(b"nope\r\n"), b"");
+    }
+}
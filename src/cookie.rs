@@ -4,6 +4,8 @@ use crate::header::{HeaderValue, SET_COOKIE};
 use bytes::Bytes;
 use std::convert::TryInto;
 use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
 use std::sync::RwLock;
 use std::time::SystemTime;
 
@@ -13,6 +15,37 @@ pub trait CookieStore: Send + Sync {
     fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &url::Url);
     /// Get any Cookie values in the store for `url`
     fn cookies(&self, url: &url::Url) -> Option<HeaderValue>;
+
+    /// List the cookies in the store that haven't expired.
+    ///
+    /// The domain of each cookie is always set, for host-only cookies it is
+    /// the host they were received from.
+    ///
+    /// The default implementation returns no cookies.
+    fn all_cookies(&self) -> Vec<Cookie<'static>> {
+        Vec::new()
+    }
+
+    /// Store a cookie as if it was received from `url` in a `Set-Cookie`
+    /// header.
+    fn add_cookie_str(&self, cookie: &str, url: &url::Url) {
+        if let Ok(value) = HeaderValue::from_str(cookie) {
+            self.set_cookies(&mut std::iter::once(&value), url);
+        }
+    }
+
+    /// Remove the cookie with the given domain, path and name, returning
+    /// whether there was one.
+    ///
+    /// The default implementation removes nothing.
+    fn remove_cookie(&self, _domain: &str, _path: &str, _name: &str) -> bool {
+        false
+    }
+
+    /// Remove all cookies.
+    ///
+    /// The default implementation removes nothing.
+    fn clear(&self) {}
 }
 
 /// A single HTTP cookie.
@@ -30,6 +63,58 @@ pub struct Cookie<'a>(cookie_crate::Cookie<'a>);
 #[derive(Debug, Default)]
 pub struct Jar(RwLock<cookie_store::CookieStore>);
 
+/// A [Public Suffix List](https://publicsuffix.org/) used to reject cookies
+/// set for public suffixes.
+///
+/// Without a list, a server at `example.co.uk` can set a cookie with
+/// `Domain=co.uk`, which is then sent to every other `.co.uk` site. Browsers
+/// reject such cookies, and a [`Jar`] does too once it has a list, see
+/// [`Jar::with_public_suffix_list`].
+///
+/// The list isn't bundled, as it changes over time. Download a current copy
+/// from <https://publicsuffix.org/list/public_suffix_list.dat>.
+#[derive(Clone, Debug)]
+pub struct PublicSuffixList(publicsuffix::List);
+
+/// A [`Jar`] that is loaded from and saved to a file.
+///
+/// The file is read when opened, and written back when the `FileJar` is
+/// dropped, so sessions survive restarts of the program. Only persistent
+/// cookies that haven't expired are saved; session cookies end with the
+/// program, like they do in a browser.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use reqwest::cookie::{CookieFileFormat, FileJar};
+///
+/// # fn run() -> std::io::Result<()> {
+/// let jar = Arc::new(FileJar::open("cookies.json", CookieFileFormat::Json)?);
+/// let client = reqwest::Client::builder()
+///     .cookie_provider(jar.clone())
+///     .build()
+///     .unwrap();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FileJar {
+    jar: Jar,
+    path: PathBuf,
+    format: CookieFileFormat,
+}
+
+/// The format of a saved cookie jar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CookieFileFormat {
+    /// The JSON format of the `cookie_store` crate.
+    Json,
+    /// The Netscape `cookies.txt` format, used by curl and wget.
+    Netscape,
+}
+
 // ===== impl Cookie =====
 
 impl<'a> Cookie<'a> {
@@ -161,6 +246,52 @@ impl Jar {
             .into_iter();
         self.0.write().unwrap().store_response_cookies(cookies, url);
     }
+
+    /// Reject cookies whose domain is a public suffix in `list`.
+    ///
+    /// A cookie with such a domain is still stored as a host-only cookie when
+    /// it comes from that exact host, as browsers do.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use reqwest::cookie::{Jar, PublicSuffixList};
+    ///
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let list = PublicSuffixList::from_bytes(&std::fs::read("public_suffix_list.dat")?)?;
+    /// let jar = Jar::default().with_public_suffix_list(list);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_public_suffix_list(mut self, list: PublicSuffixList) -> Jar {
+        self.set_public_suffix_list(list);
+        self
+    }
+
+    fn set_public_suffix_list(&mut self, list: PublicSuffixList) {
+        let store = self.0.get_mut().unwrap();
+        *store = std::mem::take(store).with_suffix_list(list.0);
+    }
+
+    /// Load a jar from `reader`, skipping expired cookies.
+    pub fn load<R: BufRead>(reader: R, format: CookieFileFormat) -> io::Result<Jar> {
+        let store = match format {
+            CookieFileFormat::Json => cookie_store::serde::json::load(reader)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            CookieFileFormat::Netscape => netscape::load(reader)?,
+        };
+        Ok(Jar(RwLock::new(store)))
+    }
+
+    /// Save the persistent cookies of this jar that haven't expired to `writer`.
+    pub fn save<W: Write>(&self, writer: &mut W, format: CookieFileFormat) -> io::Result<()> {
+        let store = self.0.read().unwrap();
+        match format {
+            CookieFileFormat::Json => cookie_store::serde::json::save(&store, writer)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e)),
+            CookieFileFormat::Netscape => netscape::save(&store, writer),
+        }
+    }
 }
 
 impl CookieStore for Jar {
@@ -171,6 +302,33 @@ impl CookieStore for Jar {
         self.0.write().unwrap().store_response_cookies(iter, url);
     }
 
+    fn all_cookies(&self) -> Vec<Cookie<'static>> {
+        self.0
+            .read()
+            .unwrap()
+            .iter_unexpired()
+            .map(|cookie| {
+                let mut raw = (**cookie).clone();
+                if let Some(domain) = cookie.domain.as_cow() {
+                    raw.set_domain(domain.into_owned());
+                }
+                raw.set_path(String::from(&cookie.path));
+                if let cookie_store::CookieExpiration::AtUtc(at) = cookie.expires {
+                    raw.set_expires(at);
+                }
+                Cookie(raw)
+            })
+            .collect()
+    }
+
+    fn remove_cookie(&self, domain: &str, path: &str, name: &str) -> bool {
+        self.0.write().unwrap().remove(domain, path, name).is_some()
+    }
+
+    fn clear(&self) {
+        self.0.write().unwrap().clear()
+    }
+
     fn cookies(&self, url: &url::Url) -> Option<HeaderValue> {
         let s = self
             .0
@@ -189,6 +347,197 @@ impl CookieStore for Jar {
     }
 }
 
+// ===== impl PublicSuffixList =====
+
+impl PublicSuffixList {
+    /// Parse a list in the format of
+    /// <https://publicsuffix.org/list/public_suffix_list.dat>.
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<PublicSuffixList> {
+        publicsuffix::List::from_bytes(bytes)
+            .map(PublicSuffixList)
+            .map_err(crate::error::builder)
+    }
+}
+
+// ===== impl FileJar =====
+
+impl FileJar {
+    /// Open the jar saved at `path`, or an empty one if there is no such file.
+    pub fn open<P: Into<PathBuf>>(path: P, format: CookieFileFormat) -> io::Result<FileJar> {
+        let path = path.into();
+        let jar = match std::fs::File::open(&path) {
+            Ok(file) => Jar::load(io::BufReader::new(file), format)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Jar::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(FileJar { jar, path, format })
+    }
+
+    /// Reject cookies whose domain is a public suffix in `list`.
+    ///
+    /// See [`Jar::with_public_suffix_list`].
+    pub fn with_public_suffix_list(mut self, list: PublicSuffixList) -> FileJar {
+        self.jar.set_public_suffix_list(list);
+        self
+    }
+
+    /// The jar the cookies are kept in.
+    pub fn jar(&self) -> &Jar {
+        &self.jar
+    }
+
+    /// The path the jar is saved to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save the jar now, instead of waiting for it to be dropped.
+    ///
+    /// The cookies are written to a temporary file that then replaces the
+    /// previous one, so the file is never left half written.
+    pub fn save(&self) -> io::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let result = std::fs::File::create(&tmp).and_then(|file| {
+            let mut writer = io::BufWriter::new(file);
+            self.jar.save(&mut writer, self.format)?;
+            writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+        });
+        match result.and_then(|()| std::fs::rename(&tmp, &self.path)) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let _ = std::fs::remove_file(&tmp);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl CookieStore for FileJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &url::Url) {
+        self.jar.set_cookies(cookie_headers, url)
+    }
+
+    fn cookies(&self, url: &url::Url) -> Option<HeaderValue> {
+        self.jar.cookies(url)
+    }
+
+    fn all_cookies(&self) -> Vec<Cookie<'static>> {
+        self.jar.all_cookies()
+    }
+
+    fn remove_cookie(&self, domain: &str, path: &str, name: &str) -> bool {
+        self.jar.remove_cookie(domain, path, name)
+    }
+
+    fn clear(&self) {
+        CookieStore::clear(&self.jar)
+    }
+}
+
+impl Drop for FileJar {
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            log::warn!("failed to save cookies to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// The Netscape `cookies.txt` format: one cookie per line, with tab separated
+/// domain, subdomain flag, path, secure flag, expiry, name and value.
+mod netscape {
+    use cookie_store::{CookieDomain, CookieExpiration, CookieStore, RawCookie};
+    use std::io::{self, BufRead, Write};
+
+    const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+    pub(super) fn load<R: BufRead>(reader: R) -> io::Result<CookieStore> {
+        let mut store = CookieStore::default();
+        for line in reader.lines() {
+            let line = line?;
+            let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+                Some(line) => (line, true),
+                None => (&line[..], false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<_>>();
+            let (domain, subdomains, path, secure, expires, name, value) = match fields[..] {
+                [domain, subdomains, path, secure, expires, name, value] => {
+                    (domain, subdomains, path, secure, expires, name, value)
+                }
+                _ => return Err(invalid(line)),
+            };
+            let expires = expires.parse::<i64>().map_err(|_| invalid(line))?;
+            let host = domain.trim_start_matches('.');
+
+            let mut cookie = RawCookie::build((name.to_owned(), value.to_owned()))
+                .path(path.to_owned())
+                .secure(secure == "TRUE")
+                .http_only(http_only);
+            if subdomains == "TRUE" {
+                cookie = cookie.domain(host.to_owned());
+            }
+            // an expiry of 0 marks a session cookie
+            if expires != 0 {
+                let expires = cookie_crate::time::OffsetDateTime::from_unix_timestamp(expires)
+                    .map_err(|_| invalid(line))?;
+                cookie = cookie.expires(expires);
+            }
+
+            let url =
+                url::Url::parse(&format!("https://{host}{path}")).map_err(|_| invalid(line))?;
+            // expired cookies are dropped here
+            let _ = store.insert_raw(&cookie.build(), &url);
+        }
+        Ok(store)
+    }
+
+    pub(super) fn save<W: Write>(store: &CookieStore, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "# Netscape HTTP Cookie File")?;
+        for cookie in store.iter_unexpired() {
+            let expires = match cookie.expires {
+                CookieExpiration::AtUtc(at) => at.unix_timestamp(),
+                CookieExpiration::SessionEnd => continue,
+            };
+            let (domain, subdomains) = match cookie.domain {
+                CookieDomain::HostOnly(ref host) => (host.clone(), "FALSE"),
+                CookieDomain::Suffix(ref suffix) => (format!(".{suffix}"), "TRUE"),
+                CookieDomain::NotPresent | CookieDomain::Empty => continue,
+            };
+            let prefix = if cookie.http_only().unwrap_or(false) {
+                HTTP_ONLY_PREFIX
+            } else {
+                ""
+            };
+            let secure = if cookie.secure().unwrap_or(false) {
+                "TRUE"
+            } else {
+                "FALSE"
+            };
+            writeln!(
+                writer,
+                "{prefix}{domain}\t{subdomains}\t{}\t{secure}\t{expires}\t{}\t{}",
+                &*cookie.path,
+                cookie.name(),
+                cookie.value(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn invalid(line: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid cookies.txt line: {line:?}"),
+        )
+    }
+}
+
 pub(crate) mod service {
     use crate::cookie;
     use http::{Request, Response};
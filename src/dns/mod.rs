@@ -1,5 +1,6 @@
 //! DNS resolution
 
+pub(crate) use prefetch::Prefetched;
 pub use resolve::{Addrs, Name, Resolve, Resolving};
 pub(crate) use resolve::{DnsResolverWithOverrides, DynResolver};
 
@@ -9,4 +10,5 @@ pub use resolve::IntoResolve;
 pub(crate) mod gai;
 #[cfg(feature = "hickory-dns")]
 pub(crate) mod hickory;
+pub(crate) mod prefetch;
 pub(crate) mod resolve;
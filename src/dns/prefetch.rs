@@ -0,0 +1,130 @@
+//! Addresses resolved ahead of the first request to a host.
+//!
+//! [`Prefetched`] wraps the client's resolver. Names resolved by
+//! [`Prefetched::prefetch()`] are answered from its cache for [`TTL`], and
+//! every other name is passed on to the resolver. Nothing else is cached, so
+//! without a prefetch, resolution works as it did.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::dns::{Addrs, Name, Resolve, Resolving};
+use crate::error::BoxError;
+
+/// How long prefetched addresses are used for. The system resolver doesn't
+/// tell the TTL of the records, so this is kept short.
+const TTL: Duration = Duration::from_secs(60);
+
+pub(crate) struct Prefetched {
+    resolver: Arc<dyn Resolve>,
+    cache: Mutex<HashMap<String, (Instant, Vec<SocketAddr>)>>,
+}
+
+impl Prefetched {
+    pub(crate) fn new(resolver: Arc<dyn Resolve>) -> Prefetched {
+        Prefetched {
+            resolver,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolves `host`, and keeps the addresses for the requests that follow.
+    pub(crate) async fn prefetch(&self, host: &str) -> Result<(), BoxError> {
+        // Hosts are parsed as in a URL, so that the name is the one the
+        // connector asks for. Addresses aren't looked up at all.
+        let host = match url::Host::parse(host)? {
+            url::Host::Domain(domain) => domain,
+            url::Host::Ipv4(_) | url::Host::Ipv6(_) => return Ok(()),
+        };
+        let addrs: Vec<SocketAddr> = self.resolver.resolve(host.parse()?).await?.collect();
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(host, (Instant::now() + TTL, addrs));
+        Ok(())
+    }
+
+    fn cached(&self, name: &str) -> Option<Vec<SocketAddr>> {
+        let mut cache = self.cache.lock().unwrap();
+        let (expires, addrs) = cache.get(name)?;
+        if *expires > Instant::now() {
+            return Some(addrs.clone());
+        }
+        cache.remove(name);
+        None
+    }
+}
+
+impl Resolve for Prefetched {
+    fn resolve(&self, name: Name) -> Resolving {
+        match self.cached(name.as_str()) {
+            Some(addrs) => {
+                let addrs: Addrs = Box::new(addrs.into_iter());
+                Box::pin(std::future::ready(Ok(addrs)))
+            }
+            None => self.resolver.resolve(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counting(AtomicUsize);
+
+    impl Resolve for Counting {
+        fn resolve(&self, _: Name) -> Resolving {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let addrs: Addrs = Box::new(std::iter::once(SocketAddr::from(([10, 0, 0, 1], 0))));
+            Box::pin(std::future::ready(Ok(addrs)))
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_prefetched_names_from_cache() {
+        let counting = Arc::new(Counting(AtomicUsize::new(0)));
+        let prefetched = Prefetched::new(counting.clone());
+
+        prefetched.prefetch("API.example.com").await.unwrap();
+        prefetched.prefetch("127.0.0.1").await.unwrap();
+        prefetched.prefetch("[::1]").await.unwrap();
+        assert!(prefetched.prefetch("not a host").await.is_err());
+        assert_eq!(counting.0.load(Ordering::SeqCst), 1);
+
+        for _ in 0..2 {
+            let addrs: Vec<_> = prefetched
+                .resolve("api.example.com".parse().unwrap())
+                .await
+                .unwrap()
+                .collect();
+            assert_eq!(addrs, [SocketAddr::from(([10, 0, 0, 1], 0))]);
+        }
+        assert_eq!(counting.0.load(Ordering::SeqCst), 1);
+
+        let _ = prefetched
+            .resolve("other.example.com".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(counting.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn prefetched_names_expire() {
+        let counting = Arc::new(Counting(AtomicUsize::new(0)));
+        let prefetched = Prefetched::new(counting.clone());
+        prefetched.prefetch("api.example.com").await.unwrap();
+
+        tokio::time::advance(TTL).await;
+        let _ = prefetched
+            .resolve("api.example.com".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(counting.0.load(Ordering::SeqCst), 2);
+    }
+}
@@ -10,6 +10,7 @@ use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use crate::error::BoxError;
+use crate::trace::Instrument;
 
 /// Alias for an `Iterator` trait object over `SocketAddr`.
 pub type Addrs = Box<dyn Iterator<Item = SocketAddr> + Send>;
@@ -118,7 +119,8 @@ impl Service<HyperName> for DynResolver {
     }
 
     fn call(&mut self, name: HyperName) -> Self::Future {
-        self.resolver.resolve(Name(name))
+        let span = trace_span!("resolve", server.address = name.as_str());
+        Box::pin(self.resolver.resolve(Name(name)).instrument(span))
     }
 }
 
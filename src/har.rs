@@ -0,0 +1,503 @@
+//! HTTP Archive (HAR) capture.
+//!
+//! A [`Recorder`] keeps the requests sent and the responses received, to be
+//! exported as [HAR 1.2] JSON and opened in a browser's developer tools or
+//! any other HAR viewer. This makes for a trace that can be shared when
+//! chasing authentication problems, without the credentials in it.
+//!
+//! Every exchange on the wire is recorded: each redirect, retry and
+//! authentication round-trip gets its own entry. The response body is
+//! captured as it is read, so it is missing from the entries of responses
+//! whose body was never read.
+//!
+//! Recording is enabled for all requests of a client with
+//! [`ClientBuilder::har()`], or for a single request with
+//! [`RequestBuilder::har()`].
+//!
+//! # Redaction
+//!
+//! The values of the `Authorization`, `Proxy-Authorization`,
+//! `WWW-Authenticate`, `Proxy-Authenticate`, `Cookie` and `Set-Cookie`
+//! headers are replaced with `[REDACTED]`. The authentication scheme, such
+//! as `Negotiate` or `Basic`, is kept. More headers and query parameters can
+//! be redacted with [`Recorder::redact_header()`] and
+//! [`Recorder::redact_query_param()`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let har = reqwest::har::Recorder::new();
+//! let client = reqwest::Client::builder().har(har.clone()).build()?;
+//!
+//! client.get("https://hyper.rs").send().await?.text().await?;
+//!
+//! std::fs::write("hyper.har", har.to_json())?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [HAR 1.2]: http://www.softwareishard.com/blog/har-12-spec/
+//! [`ClientBuilder::har()`]: crate::ClientBuilder::har
+//! [`RequestBuilder::har()`]: crate::RequestBuilder::har
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::Engine as _;
+use bytes::Bytes;
+use http::header::{self, HeaderMap, HeaderName};
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use serde_json::{json, Value};
+
+use crate::util::{redact_header, REDACTED, SENSITIVE_HEADERS};
+
+/// Records requests and responses, to export them as HAR.
+///
+/// Clones of a `Recorder` share the entries recorded so far.
+#[derive(Clone)]
+pub struct Recorder {
+    entries: Arc<Mutex<Vec<Arc<Mutex<Entry>>>>>,
+    rules: Arc<Rules>,
+}
+
+#[derive(Clone)]
+struct Rules {
+    headers: Vec<HeaderName>,
+    query: Vec<String>,
+    body_limit: usize,
+}
+
+struct Entry {
+    started: SystemTime,
+    request: Value,
+    response: Option<Value>,
+    error: Option<String>,
+    wait: Duration,
+    receive: Duration,
+}
+
+impl Recorder {
+    /// Create a `Recorder`, redacting the headers listed in the
+    /// [module documentation](self).
+    pub fn new() -> Recorder {
+        Recorder {
+            entries: Arc::new(Mutex::new(Vec::new())),
+            rules: Arc::new(Rules {
+                headers: SENSITIVE_HEADERS.to_vec(),
+                query: Vec::new(),
+                body_limit: 64 * 1024,
+            }),
+        }
+    }
+
+    /// Replace the value of the header `name` with `[REDACTED]`, in requests
+    /// and responses.
+    ///
+    /// Redaction rules only apply to the `Recorder` they are added to, and
+    /// to the clones made of it afterwards.
+    pub fn redact_header(mut self, name: HeaderName) -> Recorder {
+        Arc::make_mut(&mut self.rules).headers.push(name);
+        self
+    }
+
+    /// Replace the value of the query parameter `name` with `[REDACTED]`,
+    /// in the recorded URLs.
+    pub fn redact_query_param(mut self, name: impl Into<String>) -> Recorder {
+        Arc::make_mut(&mut self.rules).query.push(name.into());
+        self
+    }
+
+    /// Keep at most `limit` bytes of each request and response body.
+    ///
+    /// The size of a longer body is still recorded. Set to `0` to leave
+    /// bodies out. Default is 64 KiB.
+    pub fn body_limit(mut self, limit: usize) -> Recorder {
+        Arc::make_mut(&mut self.rules).body_limit = limit;
+        self
+    }
+
+    /// Returns the number of entries recorded.
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("har lock poisoned").len()
+    }
+
+    /// Returns `true` if nothing was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the entries recorded so far.
+    pub fn clear(&self) {
+        self.entries.lock().expect("har lock poisoned").clear();
+    }
+
+    /// Export the entries recorded so far as HAR 1.2 JSON.
+    pub fn to_json(&self) -> String {
+        let entries = self
+            .entries
+            .lock()
+            .expect("har lock poisoned")
+            .iter()
+            .map(|entry| entry.lock().expect("har lock poisoned").to_json())
+            .collect::<Vec<_>>();
+        let har = json!({
+            "log": {
+                "version": "1.2",
+                "creator": {
+                    "name": "reqwest",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "entries": entries,
+            }
+        });
+        serde_json::to_string_pretty(&har).expect("har is valid json")
+    }
+
+    /// Start recording a request about to be sent, whose body is `body` if
+    /// it is known upfront.
+    pub(crate) fn capture<B>(&self, req: &http::Request<B>, body: Option<&[u8]>) -> Capture {
+        let rules = &self.rules;
+        let (url, query) = rules.url(&req.uri().to_string());
+        let mut request = json!({
+            "method": req.method().as_str(),
+            "url": url,
+            "httpVersion": format!("{:?}", req.version()),
+            "cookies": [],
+            "headers": rules.headers(req.headers()),
+            "queryString": query,
+            "headersSize": -1,
+            "bodySize": body.map_or(-1, |body| body.len() as i64),
+        });
+        if let Some(body) = body.filter(|body| !body.is_empty()) {
+            let mut post_data = rules.content(req.headers(), body, body.len() as u64);
+            post_data
+                .as_object_mut()
+                .expect("content is an object")
+                .remove("size");
+            request["postData"] = post_data;
+        }
+
+        let entry = Arc::new(Mutex::new(Entry {
+            started: SystemTime::now(),
+            request,
+            response: None,
+            error: None,
+            wait: Duration::ZERO,
+            receive: Duration::ZERO,
+        }));
+        self.entries
+            .lock()
+            .expect("har lock poisoned")
+            .push(entry.clone());
+        Capture {
+            entry,
+            rules: self.rules.clone(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Recorder {
+        Recorder::new()
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+impl Rules {
+    fn redacts(&self, name: &HeaderName) -> bool {
+        self.headers.contains(name)
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> Value {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.redacts(name) {
+                    redact_header(name, value)
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                json!({ "name": name.as_str(), "value": value })
+            })
+            .collect()
+    }
+
+    /// The redacted URL, and its query string.
+    fn url(&self, uri: &str) -> (String, Value) {
+        let mut url = match url::Url::parse(uri) {
+            Ok(url) => url,
+            Err(_) => return (uri.to_owned(), json!([])),
+        };
+        let pairs = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if self.query.iter().any(|redacted| *redacted == name) {
+                    REDACTED.into()
+                } else {
+                    value
+                };
+                (name.into_owned(), value.into_owned())
+            })
+            .collect::<Vec<_>>();
+        if url.query().is_some() && !self.query.is_empty() {
+            url.query_pairs_mut().clear().extend_pairs(&pairs);
+        }
+        let query = pairs
+            .into_iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect();
+        (url.into(), query)
+    }
+
+    fn content(&self, headers: &HeaderMap, body: &[u8], size: u64) -> Value {
+        let mime_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        let mut content = json!({ "size": size, "mimeType": mime_type });
+        if self.body_limit == 0 {
+            return content;
+        }
+        let kept = &body[..body.len().min(self.body_limit)];
+        match std::str::from_utf8(kept) {
+            Ok(text) => content["text"] = text.into(),
+            Err(_) => {
+                content["text"] = base64::engine::general_purpose::STANDARD
+                    .encode(kept)
+                    .into();
+                content["encoding"] = "base64".into();
+            }
+        }
+        if (kept.len() as u64) < size {
+            content["comment"] = format!("truncated to {} bytes", kept.len()).into();
+        }
+        content
+    }
+}
+
+impl Entry {
+    fn to_json(&self) -> Value {
+        let wait = millis(self.wait);
+        let receive = millis(self.receive);
+        let response = self.response.clone().unwrap_or_else(|| {
+            json!({
+                "status": 0,
+                "statusText": "",
+                "httpVersion": "",
+                "cookies": [],
+                "headers": [],
+                "content": { "size": 0, "mimeType": "" },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": -1,
+            })
+        });
+        let mut entry = json!({
+            "startedDateTime": rfc3339(self.started),
+            "time": wait + receive,
+            "request": self.request,
+            "response": response,
+            "cache": {},
+            "timings": { "send": 0, "wait": wait, "receive": receive },
+        });
+        if let Some(ref error) = self.error {
+            entry["_error"] = error.as_str().into();
+        }
+        entry
+    }
+}
+
+/// Record the outcome of a request, if it is being captured.
+pub(crate) fn record<B>(
+    capture: Option<Capture>,
+    result: crate::Result<http::Response<B>>,
+) -> crate::Result<http::Response<B>> {
+    match capture {
+        Some(capture) => capture.finish(result),
+        None => result,
+    }
+}
+
+/// A request being recorded, completed once its response arrives.
+#[derive(Clone)]
+pub(crate) struct Capture {
+    entry: Arc<Mutex<Entry>>,
+    rules: Arc<Rules>,
+    started: Instant,
+}
+
+impl Capture {
+    /// Record the outcome of the request, keeping the capture in the
+    /// response so that its body can be recorded as it is read.
+    fn finish<B>(
+        self,
+        result: crate::Result<http::Response<B>>,
+    ) -> crate::Result<http::Response<B>> {
+        let mut entry = self.entry.lock().expect("har lock poisoned");
+        entry.wait = self.started.elapsed();
+        match result {
+            Ok(mut res) => {
+                let redirect = res
+                    .headers()
+                    .get(header::LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or("");
+                entry.response = Some(json!({
+                    "status": res.status().as_u16(),
+                    "statusText": res.status().canonical_reason().unwrap_or(""),
+                    "httpVersion": format!("{:?}", res.version()),
+                    "cookies": [],
+                    "headers": self.rules.headers(res.headers()),
+                    "content": self.rules.content(res.headers(), &[], 0),
+                    "redirectURL": redirect,
+                    "headersSize": -1,
+                    "bodySize": -1,
+                }));
+                drop(entry);
+                res.extensions_mut().insert(self);
+                Ok(res)
+            }
+            Err(err) => {
+                let mut message = err.to_string();
+                let mut source = std::error::Error::source(&err);
+                while let Some(err) = source {
+                    message.push_str(": ");
+                    message.push_str(&err.to_string());
+                    source = err.source();
+                }
+                entry.error = Some(message);
+                Err(err)
+            }
+        }
+    }
+
+    /// Record the response body as it is read.
+    pub(crate) fn body<B>(self, body: B) -> CapturedBody<B> {
+        CapturedBody {
+            inner: body,
+            capture: Some(self),
+            kept: Vec::new(),
+            size: 0,
+        }
+    }
+
+    fn body_done(&self, kept: &[u8], size: u64) {
+        let mut entry = self.entry.lock().expect("har lock poisoned");
+        entry.receive = self.started.elapsed().saturating_sub(entry.wait);
+        let rules = &self.rules;
+        if let Some(ref mut response) = entry.response {
+            let content_type = response["content"]["mimeType"].clone();
+            let mut headers = HeaderMap::new();
+            if let Some(value) = content_type.as_str().and_then(|value| value.parse().ok()) {
+                headers.insert(header::CONTENT_TYPE, value);
+            }
+            response["content"] = rules.content(&headers, kept, size);
+            response["bodySize"] = size.into();
+        }
+    }
+}
+
+pin_project! {
+    /// A response body, recorded once it has been read or dropped.
+    pub(crate) struct CapturedBody<B> {
+        #[pin]
+        inner: B,
+        capture: Option<Capture>,
+        kept: Vec<u8>,
+        size: u64,
+    }
+
+    impl<B> PinnedDrop for CapturedBody<B> {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+            if let Some(capture) = this.capture.take() {
+                capture.body_done(this.kept, *this.size);
+            }
+        }
+    }
+}
+
+impl<B> HttpBody for CapturedBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        match frame {
+            Some(Ok(ref frame)) => {
+                if let (Some(data), Some(capture)) = (frame.data_ref(), this.capture.as_ref()) {
+                    *this.size += data.len() as u64;
+                    let room = capture.rules.body_limit.saturating_sub(this.kept.len());
+                    this.kept.extend_from_slice(&data[..data.len().min(room)]);
+                }
+            }
+            Some(Err(_)) => {}
+            None => {
+                if let Some(capture) = this.capture.take() {
+                    capture.body_done(this.kept, *this.size);
+                }
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Format a time as `2024-01-02T03:04:05.678Z`.
+fn rfc3339(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // Howard Hinnant's `civil_from_days`, for days after the epoch.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60,
+        since.subsec_millis(),
+    )
+}
@@ -0,0 +1,412 @@
+//! HTTP Strict Transport Security.
+//!
+//! A client set up with [`ClientBuilder::hsts()`] remembers the hosts that
+//! sent a `Strict-Transport-Security` header over `https`, as described by
+//! [RFC 6797], and sends the later `http` requests to them, including those
+//! of redirects, over `https` instead. A host asking for
+//! `includeSubDomains` has its subdomains upgraded too, and one sending
+//! `max-age=0` is forgotten. The header is ignored on `http` responses and
+//! from IP addresses.
+//!
+//! Hosts can also be preloaded with [`HstsConfig::preload()`], so that even
+//! the first request to them is upgraded.
+//!
+//! The hosts are kept in an [`HstsStore`]: in memory with
+//! [`HstsConfig::in_memory()`], or anywhere else, such as a file that
+//! outlives the process, by implementing the trait.
+//!
+//! # Example
+//!
+//! ```
+//! use reqwest::hsts::HstsConfig;
+//!
+//! # #[cfg(not(feature = "rustls-no-provider"))]
+//! let client = reqwest::Client::builder()
+//!     .hsts(HstsConfig::in_memory().preload("example.com", true))
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+//! [`ClientBuilder::hsts()`]: crate::ClientBuilder::hsts
+//! [RFC 6797]: https://www.rfc-editor.org/rfc/rfc6797
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, SystemTime};
+
+use http::header::{HeaderMap, STRICT_TRANSPORT_SECURITY};
+use http::Uri;
+use pin_project_lite::pin_project;
+use tower::Service;
+use url::{Host, Url};
+
+/// Where a client keeps the hosts known to require `https`.
+///
+/// Hosts are lowercase domain names, without a trailing dot. A store is
+/// shared by all the requests of a client, and may be shared by several
+/// clients.
+pub trait HstsStore: Send + Sync {
+    /// Returns the policy stored for `host`, if any, even if it expired.
+    fn get(&self, host: &str) -> Option<HstsPolicy>;
+
+    /// Stores `policy` for `host`, replacing any previous one.
+    fn put(&self, host: &str, policy: HstsPolicy);
+
+    /// Removes the policy stored for `host`, if any.
+    fn remove(&self, host: &str);
+}
+
+/// What a host asked for with its `Strict-Transport-Security` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HstsPolicy {
+    expires: SystemTime,
+    include_subdomains: bool,
+}
+
+impl HstsPolicy {
+    /// Create a policy lasting until `expires`, applying to the subdomains
+    /// of its host as well if `include_subdomains` is set.
+    pub fn new(expires: SystemTime, include_subdomains: bool) -> HstsPolicy {
+        HstsPolicy {
+            expires,
+            include_subdomains,
+        }
+    }
+
+    /// When the policy expires.
+    pub fn expires(&self) -> SystemTime {
+        self.expires
+    }
+
+    /// Whether the policy applies to the subdomains of its host.
+    pub fn include_subdomains(&self) -> bool {
+        self.include_subdomains
+    }
+}
+
+/// An in-memory store.
+#[derive(Default)]
+pub struct MemoryStore {
+    hosts: Mutex<HashMap<String, HstsPolicy>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+impl HstsStore for MemoryStore {
+    fn get(&self, host: &str) -> Option<HstsPolicy> {
+        self.hosts.lock().unwrap().get(host).copied()
+    }
+
+    fn put(&self, host: &str, policy: HstsPolicy) {
+        self.hosts.lock().unwrap().insert(host.to_owned(), policy);
+    }
+
+    fn remove(&self, host: &str) {
+        self.hosts.lock().unwrap().remove(host);
+    }
+}
+
+impl fmt::Debug for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoryStore")
+            .field("hosts", &self.hosts.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// How a client enforces HTTP Strict Transport Security.
+#[derive(Clone)]
+pub struct HstsConfig {
+    store: Arc<dyn HstsStore>,
+    // Preloaded hosts, and whether their subdomains are included. They
+    // never expire, whatever the hosts send.
+    preload: Arc<HashMap<String, bool>>,
+}
+
+impl HstsConfig {
+    /// Keep the known hosts in memory.
+    pub fn in_memory() -> HstsConfig {
+        HstsConfig::with_store(Arc::new(MemoryStore::new()))
+    }
+
+    /// Keep the known hosts in `store`.
+    pub fn with_store<S: HstsStore + 'static>(store: Arc<S>) -> HstsConfig {
+        HstsConfig {
+            store,
+            preload: Arc::default(),
+        }
+    }
+
+    /// Always upgrade requests to `host`, and to its subdomains if
+    /// `include_subdomains` is set, as a browser does with the hosts of its
+    /// preload list.
+    pub fn preload(mut self, host: &str, include_subdomains: bool) -> HstsConfig {
+        let host = normalize(host);
+        Arc::make_mut(&mut self.preload).insert(host, include_subdomains);
+        self
+    }
+
+    /// Whether requests to `host` must be sent over `https`.
+    fn is_known(&self, host: &str) -> bool {
+        let now = crate::util::now();
+        let mut domain = host;
+        let mut exact = true;
+        loop {
+            if let Some(&include_subdomains) = self.preload.get(domain) {
+                if exact || include_subdomains {
+                    return true;
+                }
+            }
+            if let Some(policy) = self.store.get(domain) {
+                if policy.expires <= now {
+                    self.store.remove(domain);
+                } else if exact || policy.include_subdomains {
+                    return true;
+                }
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) if !parent.is_empty() => domain = parent,
+                _ => return false,
+            }
+            exact = false;
+        }
+    }
+
+    /// Switches `url` to `https` if its host is known to require it.
+    pub(crate) fn upgrade(&self, url: &mut Url) -> bool {
+        if url.scheme() != "http" {
+            return false;
+        }
+        match url.host() {
+            Some(Host::Domain(host)) if self.is_known(&normalize(host)) => (),
+            _ => return false,
+        }
+        // `http` on port 80 has no explicit port, so it ends up on 443.
+        url.set_scheme("https").expect("http and https are special");
+        true
+    }
+
+    fn upgrade_uri(&self, uri: &mut Uri) -> bool {
+        if uri.scheme_str() != Some("http") {
+            return false;
+        }
+        let mut url = match Url::parse(&uri.to_string()) {
+            Ok(url) => url,
+            Err(_) => return false,
+        };
+        if !self.upgrade(&mut url) {
+            return false;
+        }
+        match Uri::try_from(url.as_str()) {
+            Ok(upgraded) => {
+                *uri = upgraded;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Records the `Strict-Transport-Security` header of a response from
+    /// `host`, received over `https`.
+    fn record(&self, host: &str, headers: &HeaderMap) {
+        // Only the first header is honored.
+        let value = match headers.get(STRICT_TRANSPORT_SECURITY) {
+            Some(value) => value,
+            None => return,
+        };
+        let (max_age, include_subdomains) = match value.to_str().ok().and_then(parse) {
+            Some(directives) => directives,
+            None => return,
+        };
+        if max_age.is_zero() {
+            self.store.remove(host);
+            return;
+        }
+        if let Some(expires) = crate::util::now().checked_add(max_age) {
+            self.store
+                .put(host, HstsPolicy::new(expires, include_subdomains));
+        }
+    }
+}
+
+impl fmt::Debug for HstsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HstsConfig")
+            .field("preload", &self.preload.len())
+            .finish()
+    }
+}
+
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Parses the `max-age` and `includeSubDomains` directives of a
+/// `Strict-Transport-Security` header, which is invalid without the former
+/// or with either twice.
+fn parse(value: &str) -> Option<(Duration, bool)> {
+    let mut max_age = None;
+    let mut include_subdomains = false;
+    for directive in value.split(';') {
+        let (name, value) = match directive.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (directive.trim(), None),
+        };
+        if name.eq_ignore_ascii_case("max-age") {
+            let secs = value?.parse().ok()?;
+            if max_age.replace(Duration::from_secs(secs)).is_some() {
+                return None;
+            }
+        } else if name.eq_ignore_ascii_case("includesubdomains") {
+            if include_subdomains {
+                return None;
+            }
+            include_subdomains = true;
+        }
+    }
+    Some((max_age?, include_subdomains))
+}
+
+/// Upgrades the requests to known hosts, and records the policies of the
+/// responses received over `https`.
+///
+/// This sits beneath `FollowRedirect`, so that redirects are upgraded too.
+#[derive(Clone)]
+pub(crate) struct HstsService<S> {
+    inner: S,
+    config: Option<HstsConfig>,
+}
+
+impl<S> HstsService<S> {
+    pub(crate) fn new(inner: S, config: Option<HstsConfig>) -> Self {
+        HstsService { inner, config }
+    }
+}
+
+impl<ReqBody, ResBody, S> Service<http::Request<ReqBody>> for HstsService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>> + Clone,
+{
+    type Response = http::Response<ResBody>;
+    type Error = S::Error;
+    type Future = HstsFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let record = self.config.as_ref().and_then(|config| {
+            config.upgrade_uri(req.uri_mut());
+            if req.uri().scheme_str() != Some("https") {
+                return None;
+            }
+            let host = req.uri().host()?;
+            // The header is ignored from IP addresses.
+            match Host::parse(host).ok()? {
+                Host::Domain(host) => Some((config.clone(), normalize(&host))),
+                _ => None,
+            }
+        });
+        HstsFuture {
+            future: inner.call(req),
+            record,
+        }
+    }
+}
+
+pin_project! {
+    pub(crate) struct HstsFuture<F> {
+        #[pin]
+        future: F,
+        record: Option<(HstsConfig, String)>,
+    }
+}
+
+impl<F, B, E> Future for HstsFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Output = Result<http::Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.future.poll(cx))?;
+        if let Some((config, host)) = this.record.take() {
+            config.record(&host, res.headers());
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_directives() {
+        assert_eq!(
+            parse("max-age=31536000; includeSubDomains"),
+            Some((Duration::from_secs(31536000), true))
+        );
+        assert_eq!(
+            parse("MAX-AGE=\"60\""),
+            Some((Duration::from_secs(60), false))
+        );
+        assert_eq!(
+            parse("max-age=60; preload"),
+            Some((Duration::from_secs(60), false))
+        );
+        assert_eq!(parse("includeSubDomains"), None);
+        assert_eq!(parse("max-age=60; max-age=120"), None);
+        assert_eq!(parse("max-age=soon"), None);
+    }
+
+    #[test]
+    fn upgrades_known_hosts_and_their_subdomains() {
+        let config = HstsConfig::in_memory().preload("preloaded.test", false);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            STRICT_TRANSPORT_SECURITY,
+            "max-age=60; includeSubDomains".parse().unwrap(),
+        );
+        config.record("example.test", &headers);
+
+        let upgraded = |url: &str| {
+            let mut url = Url::parse(url).unwrap();
+            config.upgrade(&mut url);
+            url.to_string()
+        };
+        assert_eq!(upgraded("http://example.test/a"), "https://example.test/a");
+        assert_eq!(
+            upgraded("http://www.example.test:8080/"),
+            "https://www.example.test:8080/"
+        );
+        assert_eq!(
+            upgraded("http://Preloaded.test./"),
+            "https://preloaded.test./"
+        );
+        assert_eq!(
+            upgraded("http://www.preloaded.test/"),
+            "http://www.preloaded.test/"
+        );
+        assert_eq!(upgraded("http://other.test/"), "http://other.test/");
+        assert_eq!(upgraded("http://127.0.0.1/"), "http://127.0.0.1/");
+
+        headers.insert(STRICT_TRANSPORT_SECURITY, "max-age=0".parse().unwrap());
+        config.record("example.test", &headers);
+        assert_eq!(upgraded("http://example.test/a"), "http://example.test/a");
+    }
+}
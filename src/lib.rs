@@ -175,6 +175,17 @@
 //!
 //! See more details in the [`tls`] module.
 //!
+//! ## Testing with paused time
+//!
+//! All time a `Client` keeps is taken from Tokio's clock, so tests can run
+//! it under [`tokio::time::pause()`][pause] and move time along with
+//! `advance()` instead of waiting. This covers connect, read and total
+//! timeouts, retry backoff and maximum elapsed time, rate limits, and the
+//! age of cached responses: the wall-clock time compared against `Date`,
+//! `Expires` and `Retry-After` headers moves forward with Tokio's clock.
+//!
+//! [pause]: https://docs.rs/tokio/1/tokio/time/fn.pause.html
+//!
 //! ## WASM
 //!
 //! The Client implementation automatically switches to the WASM one when the target_arch is wasm32,
@@ -194,7 +205,8 @@
 //!   over HTTPS.
 //! - **rustls**: Enables TLS functionality provided by `rustls`.
 //! - **rustls-no-provider**: Enables TLS provided by `rustls` without specifying a crypto provider.
-//! - **native-tls**: Enables TLS functionality provided by `native-tls`.
+//! - **native-tls**: Enables TLS functionality provided by `native-tls`,
+//!   which is Schannel on Windows.
 //! - **native-tls-vendored**: Enables the `vendored` feature of `native-tls`.
 //! - **native-tls-no-alpn**: Enables `native-tls` without its `alpn` feature.
 //! - **native-tls-vendored-no-alpn**: Enables `native-tls-vendored` without its `alpn` feature.
@@ -210,9 +222,20 @@
 //! - **json**: Provides serialization and deserialization for JSON bodies.
 //! - **multipart**: Provides functionality for multipart forms.
 //! - **stream**: Adds support for `futures::Stream`.
+//! - **sse**: Provides a Server-Sent Events client.
 //! - **socks**: Provides SOCKS5 proxy support.
 //! - **hickory-dns**: Enables a hickory-dns async resolver instead of default
 //!   threadpool using `getaddrinfo`.
+//! - **tracing**: Emits [`tracing`](https://docs.rs/tracing) spans for
+//!   requests, DNS resolution, connecting, TLS handshakes, authentication
+//!   round-trips and response bodies.
+//! - **metrics**: Reports request counts, latencies, connection reuse,
+//!   retries and authentication fallbacks through the
+//!   [`metrics`](https://docs.rs/metrics) crate.
+//! - **har**: Records requests and responses as HTTP Archive (HAR) JSON, to
+//!   share reproducible traces.
+//! - **vcr**: Records interactions to files and replays them, to test against
+//!   captured server behavior offline.
 //! - **system-proxy** *(enabled by default)*: Use Windows and macOS system
 //!   proxy settings automatically.
 //!
@@ -286,11 +309,18 @@ pub use url::Url;
 // universal mods
 #[macro_use]
 mod error;
+#[cfg(not(target_arch = "wasm32"))]
+#[macro_use]
+mod trace;
+#[cfg(not(target_arch = "wasm32"))]
+mod metrics;
 // TODO: remove `if_hyper` if wasm has been migrated to new config system.
 if_hyper! {
     mod config;
 }
 mod into_url;
+#[cfg(feature = "query")]
+pub mod query;
 mod response;
 
 pub use self::error::{Error, Result};
@@ -369,6 +399,8 @@ if_hyper! {
     pub use tls::{Certificate, Identity};
     #[cfg(feature = "multipart")]
     pub use self::async_impl::multipart;
+    #[cfg(feature = "sse")]
+    pub use self::async_impl::sse;
 
 
     mod async_impl;
@@ -376,16 +408,27 @@ if_hyper! {
     pub(crate) mod auth;
     #[cfg(feature = "blocking")]
     pub mod blocking;
+    pub mod cache;
     mod connect;
     #[cfg(feature = "cookies")]
     pub mod cookie;
     pub mod dns;
+    #[cfg(feature = "har")]
+    pub mod har;
+    pub mod hsts;
+    pub mod logging;
+    #[cfg(feature = "negotiate")]
+    pub mod negotiate;
     mod proxy;
     pub mod redirect;
+    pub mod request_id;
     pub mod retry;
     #[cfg(feature = "__tls")]
     pub mod tls;
+    pub mod trace_context;
     mod util;
+    #[cfg(feature = "vcr")]
+    pub mod vcr;
 
     #[cfg(docsrs)]
     pub use connect::uds::UnixSocketProvider;
@@ -0,0 +1,378 @@
+//! Logging of requests and responses.
+//!
+//! A [`LoggingLayer`], added to a client with [`ClientBuilder::layer()`],
+//! logs every request sent and the response to it through the
+//! [`log`](https://docs.rs/log) crate, at the `DEBUG` level with the
+//! `reqwest::logging` target. Each line is a message followed by
+//! `key=value` pairs, with the same `id` for a request and its response:
+//!
+//! ```text
+//! request id=7 method=GET url="https://hyper.rs/"
+//! response id=7 status=200 elapsed_ms=38
+//! ```
+//!
+//! The headers and the start of the bodies can be added to these lines.
+//! The layer sees each request once, and its final response: redirects and
+//! authentication round-trips made by the client are not logged.
+//!
+//! # Redaction
+//!
+//! The values of the `Authorization`, `Proxy-Authorization`,
+//! `WWW-Authenticate`, `Proxy-Authenticate`, `Cookie` and `Set-Cookie`
+//! headers are replaced with `[REDACTED]`, keeping the authentication
+//! scheme, such as `Bearer`. More headers can be redacted with
+//! [`LoggingLayer::redact_header()`]. Credentials in the URL are left out.
+//!
+//! # Example
+//!
+//! ```
+//! use reqwest::logging::LoggingLayer;
+//!
+//! # #[cfg(not(feature = "rustls-no-provider"))]
+//! let client = reqwest::Client::builder()
+//!     .layer(LoggingLayer::new().headers(true).bodies(1024))
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+//! [`ClientBuilder::layer()`]: crate::ClientBuilder::layer
+
+use std::fmt::{self, Write as _};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Instant;
+
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName};
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+
+use crate::async_impl::Body;
+use crate::util::{redact_header, SENSITIVE_HEADERS};
+
+const TARGET: &str = "reqwest::logging";
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A Tower layer logging requests and responses.
+///
+/// See the [module documentation](self) for what is logged.
+#[derive(Clone)]
+pub struct LoggingLayer {
+    config: Arc<Config>,
+}
+
+#[derive(Clone)]
+struct Config {
+    headers: bool,
+    body_limit: usize,
+    redacted: Vec<HeaderName>,
+}
+
+impl LoggingLayer {
+    /// Create a layer logging the request line and the response status.
+    pub fn new() -> LoggingLayer {
+        LoggingLayer {
+            config: Arc::new(Config {
+                headers: false,
+                body_limit: 0,
+                redacted: SENSITIVE_HEADERS.to_vec(),
+            }),
+        }
+    }
+
+    /// Also log the request and response headers.
+    ///
+    /// Default is `false`.
+    pub fn headers(mut self, enabled: bool) -> LoggingLayer {
+        Arc::make_mut(&mut self.config).headers = enabled;
+        self
+    }
+
+    /// Also log up to `max_bytes` of the request and response bodies.
+    ///
+    /// A request body is logged if it is held in memory, rather than
+    /// streamed. A response body is logged once it has been read, in a
+    /// `response.body` line. Bytes that aren't UTF-8 are escaped.
+    ///
+    /// Default is `0`, logging no bodies.
+    pub fn bodies(mut self, max_bytes: usize) -> LoggingLayer {
+        Arc::make_mut(&mut self.config).body_limit = max_bytes;
+        self
+    }
+
+    /// Replace the value of the header `name` with `[REDACTED]`.
+    pub fn redact_header(mut self, name: HeaderName) -> LoggingLayer {
+        Arc::make_mut(&mut self.config).redacted.push(name);
+        self
+    }
+}
+
+impl Default for LoggingLayer {
+    fn default() -> LoggingLayer {
+        LoggingLayer::new()
+    }
+}
+
+impl fmt::Debug for LoggingLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoggingLayer")
+            .field("headers", &self.config.headers)
+            .field("bodies", &self.config.body_limit)
+            .field("redacted", &self.config.redacted)
+            .finish()
+    }
+}
+
+impl<S> Layer<S> for LoggingLayer {
+    type Service = Logging<S>;
+
+    fn layer(&self, inner: S) -> Logging<S> {
+        Logging {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// A service logging requests and responses, created by a [`LoggingLayer`].
+#[derive(Clone)]
+pub struct Logging<S> {
+    inner: S,
+    config: Arc<Config>,
+}
+
+impl<S> fmt::Debug for Logging<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Logging").finish()
+    }
+}
+
+impl<S> Service<http::Request<Body>> for Logging<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>>,
+    S::Error: fmt::Display,
+{
+    type Response = http::Response<Body>;
+    type Error = S::Error;
+    type Future = LoggingFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        if !log::log_enabled!(target: TARGET, log::Level::Debug) {
+            return LoggingFuture {
+                inner: self.inner.call(req),
+                logged: None,
+            };
+        }
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let config = &self.config;
+        let mut line = format!(
+            "request id={id} method={} url={:?}",
+            req.method(),
+            url(req.uri()),
+        );
+        if req.version() != http::Version::HTTP_11 {
+            let _ = write!(line, " version={:?}", req.version());
+        }
+        config.write_headers(&mut line, req.headers());
+        if config.body_limit > 0 {
+            if let Some(bytes) = req.body().as_bytes() {
+                config.write_body(&mut line, bytes, bytes.len() as u64);
+            }
+        }
+        log::debug!(target: TARGET, "{line}");
+
+        LoggingFuture {
+            inner: self.inner.call(req),
+            logged: Some((id, Instant::now(), self.config.clone())),
+        }
+    }
+}
+
+impl Config {
+    fn write_headers(&self, line: &mut String, headers: &HeaderMap) {
+        if !self.headers {
+            return;
+        }
+        for (name, value) in headers {
+            let value = if self.redacted.contains(name) {
+                redact_header(name, value)
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            let _ = write!(line, " header.{name}={value:?}");
+        }
+    }
+
+    fn write_body(&self, line: &mut String, bytes: &[u8], size: u64) {
+        let kept = &bytes[..bytes.len().min(self.body_limit)];
+        let _ = write!(line, " body.size={size} body={:?}", Escaped(kept));
+        if (kept.len() as u64) < size {
+            line.push_str(" body.truncated=true");
+        }
+    }
+}
+
+/// A URL without the credentials in it.
+fn url(uri: &http::Uri) -> String {
+    match uri.authority() {
+        Some(authority) if authority.as_str().contains('@') => {
+            let host = authority.as_str().rsplit('@').next().unwrap_or_default();
+            let mut url = format!("{}://{}", uri.scheme_str().unwrap_or("http"), host);
+            url.push_str(uri.path_and_query().map_or("/", |p| p.as_str()));
+            url
+        }
+        _ => uri.to_string(),
+    }
+}
+
+/// Bytes shown as text, escaping what isn't UTF-8.
+struct Escaped<'a>(&'a [u8]);
+
+impl fmt::Debug for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match std::str::from_utf8(self.0) {
+            Ok(text) => fmt::Debug::fmt(text, f),
+            Err(_) => write!(f, "\"{}\"", self.0.escape_ascii()),
+        }
+    }
+}
+
+pin_project! {
+    /// The response future of a [`Logging`] service.
+    pub struct LoggingFuture<F> {
+        #[pin]
+        inner: F,
+        logged: Option<(u64, Instant, Arc<Config>)>,
+    }
+}
+
+impl<F, E> Future for LoggingFuture<F>
+where
+    F: Future<Output = Result<http::Response<Body>, E>>,
+    E: fmt::Display,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx));
+        let (id, started, config) = match this.logged.take() {
+            Some(logged) => logged,
+            None => return Poll::Ready(result),
+        };
+        let elapsed_ms = started.elapsed().as_millis();
+        match result {
+            Ok(res) => {
+                let mut line = format!("response id={id} status={}", res.status().as_u16());
+                let _ = write!(line, " elapsed_ms={elapsed_ms}");
+                config.write_headers(&mut line, res.headers());
+                log::debug!(target: TARGET, "{line}");
+                if config.body_limit == 0 {
+                    return Poll::Ready(Ok(res));
+                }
+                Poll::Ready(Ok(res.map(|body| {
+                    Body::wrap(LoggedBody {
+                        inner: body,
+                        id,
+                        config: Some(config),
+                        kept: Vec::new(),
+                        size: 0,
+                    })
+                })))
+            }
+            Err(err) => {
+                log::debug!(
+                    target: TARGET,
+                    "error id={id} elapsed_ms={elapsed_ms} error={:?}",
+                    err.to_string(),
+                );
+                Poll::Ready(Err(err))
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// A response body, logged once it has been read or dropped.
+    struct LoggedBody {
+        #[pin]
+        inner: Body,
+        id: u64,
+        config: Option<Arc<Config>>,
+        kept: Vec<u8>,
+        size: u64,
+    }
+
+    impl PinnedDrop for LoggedBody {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+            if let Some(config) = this.config.take() {
+                log_body(*this.id, &config, this.kept, *this.size);
+            }
+        }
+    }
+}
+
+fn log_body(id: u64, config: &Config, kept: &[u8], size: u64) {
+    let mut line = format!("response.body id={id}");
+    config.write_body(&mut line, kept, size);
+    log::debug!(target: TARGET, "{line}");
+}
+
+impl HttpBody for LoggedBody {
+    type Data = Bytes;
+    type Error = crate::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        match frame {
+            Some(Ok(ref frame)) => {
+                if let (Some(data), Some(config)) = (frame.data_ref(), this.config.as_ref()) {
+                    *this.size += data.len() as u64;
+                    let room = config.body_limit.saturating_sub(this.kept.len());
+                    this.kept.extend_from_slice(&data[..data.len().min(room)]);
+                }
+            }
+            Some(Err(ref err)) => {
+                if this.config.take().is_some() {
+                    log::debug!(
+                        target: TARGET,
+                        "response.body id={} error={:?}",
+                        this.id,
+                        err.to_string(),
+                    );
+                }
+            }
+            None => {
+                if let Some(config) = this.config.take() {
+                    log_body(*this.id, &config, this.kept, *this.size);
+                }
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,139 @@
+//! Request metrics, reported through the `metrics` crate with the `metrics`
+//! feature.
+//!
+//! - `http.client.requests`: a counter of the requests sent, by
+//!   `http.request.method` and `http.response.status_code`, or `error.type`
+//!   when no response was received.
+//! - `http.client.request.duration`: a histogram of the seconds until the
+//!   response headers were received, with the same labels.
+//! - `http.client.connections.opened`: a counter of the new connections, as
+//!   their first response is received.
+//! - `http.client.connections.reused`: a counter of the responses received
+//!   over a connection taken from the pool.
+//! - `http.client.retries`: a counter of the requests retried.
+//! - `http.client.auth.fallbacks`: a counter of the authentication schemes
+//!   given up on for the next one, by `http.auth.scheme`.
+//!
+//! A request that is redirected or retried is counted once, with its final
+//! response, while connections are counted for every attempt.
+//!
+//! Without the feature, these functions do nothing.
+
+#[cfg(feature = "metrics")]
+pub(crate) use self::enabled::*;
+#[cfg(not(feature = "metrics"))]
+pub(crate) use self::noop::*;
+
+#[cfg(feature = "metrics")]
+mod enabled {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use http::{Extensions, Method};
+
+    use crate::Response;
+
+    /// When a request was started, to report its duration.
+    pub(crate) struct Timer(Instant);
+
+    impl Timer {
+        pub(crate) fn start() -> Timer {
+            Timer(Instant::now())
+        }
+    }
+
+    pub(crate) fn request(timer: &Timer, method: &Method, result: &crate::Result<Response>) {
+        let elapsed = timer.0.elapsed().as_secs_f64();
+        let method = method.to_string();
+        let (key, value) = match result {
+            Ok(res) => (
+                "http.response.status_code",
+                res.status().as_str().to_owned(),
+            ),
+            Err(err) => ("error.type", error_type(err).to_owned()),
+        };
+        ::metrics::counter!(
+            "http.client.requests",
+            "http.request.method" => method.clone(),
+            key => value.clone(),
+        )
+        .increment(1);
+        ::metrics::histogram!(
+            "http.client.request.duration",
+            "http.request.method" => method,
+            key => value,
+        )
+        .record(elapsed);
+    }
+
+    fn error_type(err: &crate::Error) -> &'static str {
+        if err.is_timeout() {
+            "timeout"
+        } else if err.is_connect() {
+            "connect"
+        } else if err.is_redirect() {
+            "redirect"
+        } else if err.is_body() || err.is_decode() {
+            "body"
+        } else {
+            "request"
+        }
+    }
+
+    /// Kept by each connection, and copied into the extensions of every
+    /// response received over it, to tell a reused connection apart.
+    #[derive(Clone)]
+    pub(crate) struct Connection(Arc<AtomicUsize>);
+
+    impl Connection {
+        pub(crate) fn new() -> Connection {
+            Connection(Arc::new(AtomicUsize::new(0)))
+        }
+    }
+
+    /// A response was received, over a new or a reused connection.
+    pub(crate) fn response(extensions: &Extensions) {
+        if let Some(conn) = extensions.get::<Connection>() {
+            if conn.0.fetch_add(1, Ordering::Relaxed) == 0 {
+                ::metrics::counter!("http.client.connections.opened").increment(1);
+            } else {
+                ::metrics::counter!("http.client.connections.reused").increment(1);
+            }
+        }
+    }
+
+    pub(crate) fn retry() {
+        ::metrics::counter!("http.client.retries").increment(1);
+    }
+
+    #[cfg(all(windows, feature = "negotiate"))]
+    pub(crate) fn auth_fallback(scheme: &'static str) {
+        ::metrics::counter!("http.client.auth.fallbacks", "http.auth.scheme" => scheme)
+            .increment(1);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod noop {
+    use http::{Extensions, Method};
+
+    use crate::Response;
+
+    pub(crate) struct Timer;
+
+    impl Timer {
+        pub(crate) fn start() -> Timer {
+            Timer
+        }
+    }
+
+    pub(crate) fn request(_timer: &Timer, _method: &Method, _result: &crate::Result<Response>) {}
+
+    pub(crate) fn response(_extensions: &Extensions) {}
+
+    pub(crate) fn retry() {}
+
+    #[cfg(all(windows, feature = "negotiate"))]
+    pub(crate) fn auth_fallback(_scheme: &'static str) {}
+}
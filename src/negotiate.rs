@@ -0,0 +1,155 @@
+//! Diagnostics for Negotiate authentication.
+//!
+//! When Negotiate doesn't work, the reason is seldom in the final response:
+//! the server may not offer it, the ticket may be asked for the wrong SPN, or
+//! SSPI may quietly pick NTLM rather than Kerberos. [`Client::check_negotiate()`]
+//! goes through the handshake with a server, and returns a [`Report`] of each
+//! step of it.
+//!
+//! [`Client::check_negotiate()`]: crate::Client::check_negotiate()
+
+use std::fmt;
+
+use http::StatusCode;
+use url::Url;
+
+/// What happened while authenticating to a URL with Negotiate.
+///
+/// Made by [`Client::check_negotiate()`](crate::Client::check_negotiate()).
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub(crate) url: Url,
+    pub(crate) challenges: Vec<String>,
+    pub(crate) spn: String,
+    pub(crate) package: Option<String>,
+    pub(crate) rounds: Vec<Round>,
+    pub(crate) status: StatusCode,
+    pub(crate) outcome: Outcome,
+}
+
+/// A request sent with an `Authorization` token, and the response to it.
+#[derive(Clone, Debug)]
+pub struct Round {
+    pub(crate) scheme: &'static str,
+    pub(crate) sent: usize,
+    pub(crate) status: StatusCode,
+    pub(crate) received: Option<usize>,
+}
+
+/// How authenticating with Negotiate ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Outcome {
+    /// The server answered without asking for authentication.
+    NotRequired,
+    /// The server offered neither Negotiate nor NTLM.
+    Unsupported,
+    /// The server accepted the last token.
+    Authenticated,
+    /// The server answered a token with `401 Unauthorized`, and no token to
+    /// go on with.
+    Rejected,
+    /// No token could be made, or the handshake took too many rounds. The
+    /// message tells why.
+    Failed(String),
+}
+
+/// What to do when Negotiate is asked for by a server at an IP address.
+///
+/// Kerberos tickets are issued for host names, so a URL such as
+/// `http://10.0.0.5/` can't be authenticated with Kerberos: there is no
+/// service registered as `HTTP/10.0.0.5`.
+///
+/// Set with [`ClientBuilder::negotiate_ip_literal_policy()`].
+///
+/// [`ClientBuilder::negotiate_ip_literal_policy()`]: crate::ClientBuilder::negotiate_ip_literal_policy()
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IpLiteralPolicy {
+    /// Fail the request with an error, rather than send a token.
+    FailFast,
+    /// Don't try Kerberos, and authenticate with NTLM if the server offers
+    /// it. This is the default.
+    #[default]
+    SkipToNtlm,
+    /// Look up the host name of the address, and ask for tickets to it.
+    ///
+    /// The request fails if the address has no host name. The lookup is
+    /// only made on Windows.
+    ReverseLookupHostname,
+}
+
+impl Report {
+    /// The URL that was checked.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The schemes offered in the `WWW-Authenticate` headers of the first
+    /// response, such as `Negotiate`, `NTLM` or `Basic`.
+    pub fn challenges(&self) -> &[String] {
+        &self.challenges
+    }
+
+    /// The service principal name tickets were asked for.
+    pub fn spn(&self) -> &str {
+        &self.spn
+    }
+
+    /// The SSPI package that made the tokens, if any were made.
+    ///
+    /// For Negotiate, this is the package it settled on, `Kerberos` or
+    /// `NTLM`, when SSPI tells.
+    pub fn package(&self) -> Option<&str> {
+        self.package.as_deref()
+    }
+
+    /// The requests sent with a token, in order.
+    pub fn rounds(&self) -> &[Round] {
+        &self.rounds
+    }
+
+    /// The status of the last response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// How it ended.
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+}
+
+impl Round {
+    /// The scheme of the `Authorization` header sent, `Negotiate` or `NTLM`.
+    pub fn scheme(&self) -> &str {
+        self.scheme
+    }
+
+    /// The size of the token sent, in bytes.
+    pub fn token_len(&self) -> usize {
+        self.sent
+    }
+
+    /// The status the server answered with.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The size of the token the server answered with, if it sent one.
+    pub fn server_token_len(&self) -> Option<usize> {
+        self.received
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::NotRequired => f.write_str("authentication not required"),
+            Outcome::Unsupported => f.write_str("neither Negotiate nor NTLM offered"),
+            Outcome::Authenticated => f.write_str("authenticated"),
+            Outcome::Rejected => f.write_str("rejected by the server"),
+            Outcome::Failed(reason) => write!(f, "failed: {reason}"),
+        }
+    }
+}
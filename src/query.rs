@@ -0,0 +1,796 @@
+//! Query string serialization options
+//!
+//! [`RequestBuilder::query()`][crate::RequestBuilder::query] serializes flat
+//! key-value pairs, appending them to the URL. When an API expects arrays,
+//! `null`s or existing parameters to be handled differently, pass
+//! [`Options`] to `query_with()` instead:
+//!
+//! ```rust
+//! # fn run() -> Result<(), reqwest::Error> {
+//! use reqwest::query::{ArrayFormat, MergeMode, NullFormat, Options};
+//!
+//! #[derive(serde::Serialize)]
+//! struct Search<'a> {
+//!     tags: Vec<&'a str>,
+//!     page: Option<u32>,
+//! }
+//!
+//! let options = Options::new()
+//!     .arrays(ArrayFormat::Comma)
+//!     .nulls(NullFormat::Skip)
+//!     .merge(MergeMode::Replace);
+//! let req = reqwest::Client::new()
+//!     .get("https://example.com/search?page=1&tags=x")
+//!     .query_with(&Search { tags: vec!["rust", "http"], page: None }, options)
+//!     .build()?;
+//! assert_eq!(req.url().query(), Some("page=1&tags=rust,http"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use serde::ser::{self, Impossible, Serialize};
+use url::form_urlencoded::byte_serialize;
+use url::Url;
+
+/// Options for serializing a query string with `query_with()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    arrays: ArrayFormat,
+    nulls: NullFormat,
+    merge: MergeMode,
+}
+
+/// How sequences are written to the query string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArrayFormat {
+    /// Repeat the key for every element: `a=1&a=2`.
+    #[default]
+    Repeat,
+    /// Join the elements with commas: `a=1,2`.
+    Comma,
+    /// Repeat the key with brackets appended: `a[]=1&a[]=2`.
+    Brackets,
+}
+
+/// How `None` and unit values are written to the query string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NullFormat {
+    /// Leave the parameter out.
+    #[default]
+    Skip,
+    /// Write the key with an empty value: `a=`.
+    Empty,
+    /// Write the key alone: `a`.
+    KeyOnly,
+}
+
+/// What happens to parameters already in the URL.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MergeMode {
+    /// Keep them, adding the new parameters after them.
+    #[default]
+    Append,
+    /// Remove those with a key that is written again, and keep the rest.
+    Replace,
+    /// Remove the whole existing query string.
+    ReplaceAll,
+}
+
+impl Options {
+    /// Creates the options `query()` uses: arrays repeat their key, `None`s
+    /// are left out, and new parameters are appended.
+    pub fn new() -> Options {
+        Options::default()
+    }
+
+    /// Sets how sequences are written.
+    pub fn arrays(mut self, format: ArrayFormat) -> Options {
+        self.arrays = format;
+        self
+    }
+
+    /// Sets how `None` and unit values are written.
+    pub fn nulls(mut self, format: NullFormat) -> Options {
+        self.nulls = format;
+        self
+    }
+
+    /// Sets what happens to parameters already in the URL.
+    pub fn merge(mut self, mode: MergeMode) -> Options {
+        self.merge = mode;
+        self
+    }
+}
+
+/// Serializes `query` into the query string of `url`.
+pub(crate) fn apply<T: Serialize + ?Sized>(
+    url: &mut Url,
+    query: &T,
+    options: Options,
+) -> crate::Result<()> {
+    let mut params = Vec::new();
+    query
+        .serialize(TopSerializer(&mut params))
+        .map_err(crate::error::builder)?;
+
+    let mut out = String::new();
+    if let Some(existing) = url.query() {
+        for segment in existing.split('&').filter(|s| !s.is_empty()) {
+            let keep = match options.merge {
+                MergeMode::Append => true,
+                MergeMode::Replace => {
+                    let key = segment.split('=').next().unwrap_or_default();
+                    let key: String = url::form_urlencoded::parse(key.as_bytes())
+                        .map(|(k, _)| k.into_owned())
+                        .next()
+                        .unwrap_or_default();
+                    !params
+                        .iter()
+                        .any(|(k, v)| options.writes(v) && options.key(k, v) == key)
+                }
+                MergeMode::ReplaceAll => false,
+            };
+            if keep {
+                push_segment(&mut out, segment);
+            }
+        }
+    }
+
+    for (key, value) in &params {
+        let name = options.key(key, value);
+        match value {
+            Value::Null => options.push_null(&mut out, &name),
+            Value::Scalar(v) => push_pair(&mut out, &name, v),
+            Value::Seq(elems) if options.arrays == ArrayFormat::Comma => {
+                let joined = elems
+                    .iter()
+                    .filter(|e| e.is_some() || options.nulls != NullFormat::Skip)
+                    .map(|e| encode(e.as_deref().unwrap_or_default()))
+                    .collect::<Vec<_>>();
+                if !joined.is_empty() {
+                    push_segment(&mut out, &format!("{}={}", encode(&name), joined.join(",")));
+                }
+            }
+            Value::Seq(elems) => {
+                for elem in elems {
+                    match elem {
+                        Some(v) => push_pair(&mut out, &name, v),
+                        None => options.push_null(&mut out, &name),
+                    }
+                }
+            }
+        }
+    }
+
+    url.set_query(if out.is_empty() { None } else { Some(&out) });
+    Ok(())
+}
+
+impl Options {
+    /// Whether `value` adds anything to the query string.
+    fn writes(&self, value: &Value) -> bool {
+        match value {
+            Value::Null => self.nulls != NullFormat::Skip,
+            Value::Scalar(_) => true,
+            Value::Seq(elems) => elems
+                .iter()
+                .any(|e| e.is_some() || self.nulls != NullFormat::Skip),
+        }
+    }
+
+    fn key(&self, key: &str, value: &Value) -> String {
+        match value {
+            Value::Seq(_) if self.arrays == ArrayFormat::Brackets => format!("{key}[]"),
+            _ => key.to_owned(),
+        }
+    }
+
+    fn push_null(&self, out: &mut String, key: &str) {
+        match self.nulls {
+            NullFormat::Skip => (),
+            NullFormat::Empty => push_pair(out, key, ""),
+            NullFormat::KeyOnly => push_segment(out, &encode(key)),
+        }
+    }
+}
+
+fn push_pair(out: &mut String, key: &str, value: &str) {
+    push_segment(out, &format!("{}={}", encode(key), encode(value)));
+}
+
+fn push_segment(out: &mut String, segment: &str) {
+    if !out.is_empty() {
+        out.push('&');
+    }
+    out.push_str(segment);
+}
+
+fn encode(s: &str) -> String {
+    byte_serialize(s.as_bytes()).collect()
+}
+
+// ===== serialization =====
+
+enum Value {
+    Null,
+    Scalar(String),
+    Seq(Vec<Option<String>>),
+}
+
+#[derive(Debug)]
+struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error(msg.to_string())
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, Error> {
+    Err(Error(format!(
+        "{what} cannot be serialized into a query string"
+    )))
+}
+
+/// Forwards every scalar `serialize_*` method to `scalar()`, and rejects
+/// the compound ones.
+macro_rules! scalars {
+    () => {
+        fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_i8(self, v: i8) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_i16(self, v: i16) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_i32(self, v: i32) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_i64(self, v: i64) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_u8(self, v: u8) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_u16(self, v: u16) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_u32(self, v: u32) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_u64(self, v: u64) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_f32(self, v: f32) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_f64(self, v: f64) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_string())
+        }
+        fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+            self.scalar(v.to_owned())
+        }
+        fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Error> {
+            match std::str::from_utf8(v) {
+                Ok(v) => self.scalar(v.to_owned()),
+                Err(_) => unsupported("non-UTF-8 bytes"),
+            }
+        }
+        fn serialize_unit_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+        ) -> Result<Self::Ok, Error> {
+            self.scalar(variant.to_owned())
+        }
+        fn serialize_newtype_variant<T: Serialize + ?Sized>(
+            self,
+            _name: &'static str,
+            _index: u32,
+            _variant: &'static str,
+            _value: &T,
+        ) -> Result<Self::Ok, Error> {
+            unsupported("an enum newtype variant")
+        }
+        fn serialize_tuple_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeTupleVariant, Error> {
+            unsupported("an enum tuple variant")
+        }
+        fn serialize_struct_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeStructVariant, Error> {
+            unsupported("an enum struct variant")
+        }
+    };
+}
+
+/// Serializes the whole query: a map, a struct, or a sequence of pairs.
+struct TopSerializer<'a>(&'a mut Vec<(String, Value)>);
+
+impl<'a> TopSerializer<'a> {
+    fn scalar(self, _: String) -> Result<(), Error> {
+        unsupported("a single value")
+    }
+}
+
+impl<'a> ser::Serializer for TopSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    scalars!();
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+}
+
+impl<'a> TopSerializer<'a> {
+    fn pair<T: Serialize + ?Sized>(&mut self, pair: &T) -> Result<(), Error> {
+        let mut key_value = Vec::with_capacity(2);
+        pair.serialize(PairSerializer(&mut key_value))?;
+        let value = key_value.pop();
+        match (key_value.pop(), value) {
+            (Some(Value::Scalar(key)), Some(value)) => {
+                self.0.push((key, value));
+                Ok(())
+            }
+            _ => unsupported("a sequence element other than a key-value pair"),
+        }
+    }
+}
+
+impl<'a> ser::SerializeSeq for TopSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.pair(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTuple for TopSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.pair(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for TopSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.pair(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeMap for TopSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ValueSerializer)? {
+            Value::Scalar(key) => {
+                self.0.push((key, Value::Null));
+                Ok(())
+            }
+            _ => unsupported("a map key other than a string or number"),
+        }
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let value = value.serialize(ValueSerializer)?;
+        if let Some(last) = self.0.last_mut() {
+            last.1 = value;
+        }
+        Ok(())
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for TopSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let value = value.serialize(ValueSerializer)?;
+        self.0.push((key.to_owned(), value));
+        Ok(())
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Serializes a `(key, value)` element of a top-level sequence.
+struct PairSerializer<'a>(&'a mut Vec<Value>);
+
+impl<'a> PairSerializer<'a> {
+    fn scalar(self, _: String) -> Result<(), Error> {
+        unsupported("a sequence element other than a key-value pair")
+    }
+}
+
+impl<'a> ser::Serializer for PairSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    scalars!();
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.scalar(String::new())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.scalar(String::new())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.scalar(String::new())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        unsupported("a sequence element other than a key-value pair")
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Self, Error> {
+        if len == 2 {
+            Ok(self)
+        } else {
+            unsupported("a sequence element other than a key-value pair")
+        }
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        unsupported("a sequence element other than a key-value pair")
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        unsupported("a sequence element other than a key-value pair")
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        unsupported("a sequence element other than a key-value pair")
+    }
+}
+
+impl<'a> ser::SerializeTuple for PairSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.0.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Serializes the value of a parameter: a scalar, a null or a sequence.
+struct ValueSerializer;
+
+impl ValueSerializer {
+    fn scalar(self, v: String) -> Result<Value, Error> {
+        Ok(Value::Scalar(v))
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = Impossible<Value, Error>;
+    type SerializeStruct = Impossible<Value, Error>;
+    type SerializeStructVariant = Impossible<Value, Error>;
+
+    scalars!();
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or(0))))
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer(Vec::with_capacity(len)))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer(Vec::with_capacity(len)))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        unsupported("a nested map")
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        unsupported("a nested struct")
+    }
+}
+
+struct SeqSerializer(Vec<Option<String>>);
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        match value.serialize(ValueSerializer)? {
+            Value::Null => self.0.push(None),
+            Value::Scalar(v) => self.0.push(Some(v)),
+            Value::Seq(_) => return unsupported("a nested sequence"),
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Seq(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Seq(self.0))
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Seq(self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn query<T: Serialize + ?Sized>(url: &str, query: &T, options: Options) -> Option<String> {
+        let mut url = Url::parse(url).unwrap();
+        apply(&mut url, query, options).unwrap();
+        url.query().map(str::to_owned)
+    }
+
+    #[derive(serde::Serialize)]
+    struct Params {
+        ids: Vec<u32>,
+        name: &'static str,
+        page: Option<u32>,
+    }
+
+    const PARAMS: Params = Params {
+        ids: Vec::new(),
+        name: "a b",
+        page: None,
+    };
+
+    #[test]
+    fn array_formats() {
+        let params = Params {
+            ids: vec![1, 2],
+            ..PARAMS
+        };
+        let url = "http://example.com/";
+        assert_eq!(
+            query(url, &params, Options::new()).as_deref(),
+            Some("ids=1&ids=2&name=a+b")
+        );
+        assert_eq!(
+            query(url, &params, Options::new().arrays(ArrayFormat::Comma)).as_deref(),
+            Some("ids=1,2&name=a+b")
+        );
+        assert_eq!(
+            query(url, &params, Options::new().arrays(ArrayFormat::Brackets)).as_deref(),
+            Some("ids%5B%5D=1&ids%5B%5D=2&name=a+b")
+        );
+    }
+
+    #[test]
+    fn null_formats() {
+        let url = "http://example.com/";
+        let nulls = |format| query(url, &PARAMS, Options::new().nulls(format));
+        assert_eq!(nulls(NullFormat::Skip).as_deref(), Some("name=a+b"));
+        assert_eq!(nulls(NullFormat::Empty).as_deref(), Some("name=a+b&page="));
+        assert_eq!(nulls(NullFormat::KeyOnly).as_deref(), Some("name=a+b&page"));
+
+        let ids = [Some(1), None, Some(3)];
+        let pairs = [("id", &ids)];
+        let comma = Options::new().arrays(ArrayFormat::Comma);
+        assert_eq!(query(url, &pairs, comma).as_deref(), Some("id=1,3"));
+        assert_eq!(
+            query(url, &pairs, comma.nulls(NullFormat::Empty)).as_deref(),
+            Some("id=1,,3")
+        );
+    }
+
+    #[test]
+    fn merge_modes() {
+        let url = "http://example.com/?name=old&keep=%2F&page";
+        let merge = |mode| query(url, &PARAMS, Options::new().merge(mode));
+        assert_eq!(
+            merge(MergeMode::Append).as_deref(),
+            Some("name=old&keep=%2F&page&name=a+b")
+        );
+        assert_eq!(
+            merge(MergeMode::Replace).as_deref(),
+            Some("keep=%2F&page&name=a+b")
+        );
+        assert_eq!(merge(MergeMode::ReplaceAll).as_deref(), Some("name=a+b"));
+
+        let empty: [(&str, &str); 0] = [];
+        assert_eq!(
+            query(url, &empty, Options::new().merge(MergeMode::ReplaceAll)),
+            None
+        );
+    }
+
+    #[test]
+    fn maps_and_pairs() {
+        let mut map = BTreeMap::new();
+        map.insert("b", vec!["x", "y"]);
+        map.insert("a", vec![]);
+        assert_eq!(
+            query("http://example.com/", &map, Options::new()).as_deref(),
+            Some("b=x&b=y")
+        );
+        assert_eq!(
+            query(
+                "http://example.com/",
+                &[("k", "v"), ("k", "w")],
+                Options::new()
+            )
+            .as_deref(),
+            Some("k=v&k=w")
+        );
+    }
+
+    #[test]
+    fn rejects_nested_values() {
+        let mut url = Url::parse("http://example.com/").unwrap();
+        let nested = [("a", [[1]])];
+        assert!(apply(&mut url, &nested, Options::new()).is_err());
+        assert!(apply(&mut url, &"scalar", Options::new()).is_err());
+    }
+}
@@ -5,12 +5,26 @@
 //! `redirect::Policy` can be used with a `ClientBuilder`.
 
 use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{ready, Context, Poll};
 use std::{error::Error as StdError, sync::Arc};
 
-use crate::header::{AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, REFERER, WWW_AUTHENTICATE};
-use http::{HeaderMap, HeaderValue};
+use crate::header::{
+    AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION,
+    PROXY_AUTHORIZATION, REFERER, WWW_AUTHENTICATE,
+};
+use http::{HeaderMap, HeaderName, HeaderValue, Method};
 use hyper::StatusCode;
-
+use pin_project_lite::pin_project;
+use sync_wrapper::SyncFuture;
+use tower::Service;
+
+use crate::async_impl::scoped_headers::ScopedHeaders;
+#[cfg(feature = "har")]
+use crate::config::Har;
+use crate::config::{RedirectPolicy, RequestConfig};
 use crate::{async_impl, Url};
 use tower_http::follow_redirect::policy::{
     Action as TowerAction, Attempt as TowerAttempt, Policy as TowerPolicy,
@@ -25,8 +39,16 @@ use tower_http::follow_redirect::policy::{
 ///   the allowed maximum redirect hops in a chain.
 /// - `none` can be used to disable all redirect behavior.
 /// - `custom` can be used to create a customized policy.
+/// - `custom_async` can be used to create a customized policy that awaits
+///   before deciding.
+///
+/// Clones of a custom policy share its function.
+#[derive(Clone)]
 pub struct Policy {
     inner: PolicyKind,
+    preserve_method: bool,
+    sensitive_headers: Option<Vec<HeaderName>>,
+    trusted_hosts: Vec<String>,
 }
 
 /// A type that holds information on the next request and previous requests
@@ -38,6 +60,16 @@ pub struct Attempt<'a> {
     previous: &'a [Url],
 }
 
+/// The information of an [`Attempt`], owned so that it can be held across
+/// `.await` points by an asynchronous policy.
+#[derive(Debug)]
+pub struct AsyncAttempt {
+    status: StatusCode,
+    next: Url,
+    previous: Vec<Url>,
+    headers: HeaderMap,
+}
+
 /// An action to perform when a redirect status code is found.
 #[derive(Debug)]
 pub struct Action {
@@ -51,6 +83,9 @@ impl Policy {
     pub fn limited(max: usize) -> Self {
         Self {
             inner: PolicyKind::Limit(max),
+            preserve_method: false,
+            sensitive_headers: None,
+            trusted_hosts: Vec::new(),
         }
     }
 
@@ -58,6 +93,9 @@ impl Policy {
     pub fn none() -> Self {
         Self {
             inner: PolicyKind::None,
+            preserve_method: false,
+            sensitive_headers: None,
+            trusted_hosts: Vec::new(),
         }
     }
 
@@ -104,7 +142,135 @@ impl Policy {
         T: Fn(Attempt) -> Action + Send + Sync + 'static,
     {
         Self {
-            inner: PolicyKind::Custom(Box::new(policy)),
+            inner: PolicyKind::Custom(Arc::new(policy)),
+            preserve_method: false,
+            sensitive_headers: None,
+            trusted_hosts: Vec::new(),
+        }
+    }
+
+    /// Keep the method and body of a `POST` request when following a `301`
+    /// or `302` redirect.
+    ///
+    /// By default such a request is sent again as a `GET` without a body,
+    /// as RFC 9110 allows and browsers do. Some legacy services expect the
+    /// `POST` to be repeated at the new location instead. Only a body that
+    /// can be sent again is kept: a streaming body still turns the request
+    /// into a `GET`. A `303 See Other` always does.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use reqwest::{Error, redirect};
+    /// #
+    /// # fn run() -> Result<(), Error> {
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(redirect::Policy::default().preserve_method(true))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preserve_method(mut self, preserve: bool) -> Self {
+        self.preserve_method = preserve;
+        self
+    }
+
+    /// Set the headers removed from a request redirected to another host.
+    ///
+    /// This replaces the default list: `Authorization`, `Cookie`, `Cookie2`,
+    /// `Proxy-Authorization` and `WWW-Authenticate`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use reqwest::{Error, redirect};
+    /// use reqwest::header::{AUTHORIZATION, HeaderName};
+    /// #
+    /// # fn run() -> Result<(), Error> {
+    /// let policy = redirect::Policy::default().sensitive_headers([
+    ///     AUTHORIZATION,
+    ///     HeaderName::from_static("x-api-key"),
+    /// ]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sensitive_headers<I>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = HeaderName>,
+    {
+        self.sensitive_headers = Some(headers.into_iter().collect());
+        self
+    }
+
+    /// Keep sensitive headers on requests redirected to `host`.
+    ///
+    /// Hosts are compared exactly, except that a host starting with a `.`
+    /// also matches any of its subdomains. This can be called multiple times
+    /// to trust several hosts, such as the ones an SSO flow bounces between.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use reqwest::{Error, redirect};
+    /// #
+    /// # fn run() -> Result<(), Error> {
+    /// let policy = redirect::Policy::default()
+    ///     .trust_host("sso.corp.example")
+    ///     .trust_host(".apps.corp.example");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn trust_host(mut self, host: impl Into<String>) -> Self {
+        self.trusted_hosts.push(host.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Create a custom `Policy` using the passed asynchronous function.
+    ///
+    /// This works like [`Policy::custom`], except that the policy can await,
+    /// for instance to consult an allow-list or a token service, before
+    /// deciding whether to follow the redirect. The [`AsyncAttempt`] also
+    /// gives access to the headers of the request being redirected.
+    ///
+    /// As with `Policy::custom`, redirect loops aren't caught for you.
+    ///
+    /// # Note
+    ///
+    /// An asynchronous policy can't be applied with [`Policy::redirect`],
+    /// which returns an error action for it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use reqwest::{Error, redirect};
+    /// #
+    /// # async fn is_allowed(_host: Option<&str>) -> bool { true }
+    /// # fn run() -> Result<(), Error> {
+    /// let custom = redirect::Policy::custom_async(|attempt| async move {
+    ///     if attempt.previous().len() > 5 {
+    ///         attempt.error("too many redirects")
+    ///     } else if is_allowed(attempt.url().host_str()).await {
+    ///         attempt.follow()
+    ///     } else {
+    ///         attempt.stop()
+    ///     }
+    /// });
+    /// let client = reqwest::Client::builder()
+    ///     .redirect(custom)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn custom_async<T, F>(policy: T) -> Self
+    where
+        T: Fn(AsyncAttempt) -> F + Send + Sync + 'static,
+        F: Future<Output = Action> + Send + 'static,
+    {
+        Self {
+            inner: PolicyKind::CustomAsync(Arc::new(move |attempt| Box::pin(policy(attempt)))),
+            preserve_method: false,
+            sensitive_headers: None,
+            trusted_hosts: Vec::new(),
         }
     }
 
@@ -131,6 +297,9 @@ impl Policy {
     pub fn redirect(&self, attempt: Attempt) -> Action {
         match self.inner {
             PolicyKind::Custom(ref custom) => custom(attempt),
+            PolicyKind::CustomAsync(..) => {
+                attempt.error("an asynchronous redirect policy can't be applied synchronously")
+            }
             PolicyKind::Limit(max) => {
                 // The first URL in the previous is the initial URL and not a redirection. It needs to be excluded.
                 if attempt.previous.len() > max {
@@ -152,8 +321,54 @@ impl Policy {
         .inner
     }
 
+    pub(crate) fn is_async(&self) -> bool {
+        matches!(self.inner, PolicyKind::CustomAsync(..))
+    }
+
     pub(crate) fn is_default(&self) -> bool {
         matches!(self.inner, PolicyKind::Limit(10))
+            && !self.preserve_method
+            && self.sensitive_headers.is_none()
+            && self.trusted_hosts.is_empty()
+    }
+
+    pub(crate) fn remove_sensitive_headers(
+        &self,
+        headers: &mut HeaderMap,
+        next: &Url,
+        previous: &[Url],
+    ) {
+        if self.is_trusted(next) {
+            return;
+        }
+        match self.sensitive_headers {
+            Some(ref names) => {
+                if is_cross_host(next, previous) {
+                    for name in names {
+                        headers.remove(name);
+                    }
+                }
+            }
+            None => remove_sensitive_headers(headers, next, previous),
+        }
+    }
+
+    fn is_trusted(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        self.trusted_hosts
+            .iter()
+            .any(|trusted| match trusted.strip_prefix('.') {
+                Some(domain) => {
+                    host == domain
+                        || host
+                            .strip_suffix(domain)
+                            .map_or(false, |sub| sub.ends_with('.'))
+                }
+                None => host == *trusted,
+            })
     }
 }
 
@@ -205,15 +420,80 @@ impl<'a> Attempt<'a> {
     }
 }
 
+impl AsyncAttempt {
+    /// Get the type of redirect.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the next URL to redirect to.
+    pub fn url(&self) -> &Url {
+        &self.next
+    }
+
+    /// Get the list of previous URLs that have already been requested in this chain.
+    pub fn previous(&self) -> &[Url] {
+        &self.previous
+    }
+
+    /// Get the headers of the request being redirected.
+    ///
+    /// These are the headers the next request starts from, before any
+    /// sensitive headers are removed from it.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns an action meaning reqwest should follow the next URL.
+    pub fn follow(self) -> Action {
+        Action {
+            inner: ActionKind::Follow,
+        }
+    }
+
+    /// Returns an action meaning reqwest should not follow the next URL.
+    ///
+    /// The 30x response will be returned as the `Ok` result.
+    pub fn stop(self) -> Action {
+        Action {
+            inner: ActionKind::Stop,
+        }
+    }
+
+    /// Returns an action failing the redirect with an error.
+    ///
+    /// The `Error` will be returned for the result of the sent request.
+    pub fn error<E: Into<Box<dyn StdError + Send + Sync>>>(self, error: E) -> Action {
+        Action {
+            inner: ActionKind::Error(error.into()),
+        }
+    }
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+#[derive(Clone)]
 enum PolicyKind {
-    Custom(Box<dyn Fn(Attempt) -> Action + Send + Sync + 'static>),
+    Custom(Arc<dyn Fn(Attempt) -> Action + Send + Sync + 'static>),
+    CustomAsync(Arc<dyn Fn(AsyncAttempt) -> BoxFuture<Action> + Send + Sync + 'static>),
     Limit(usize),
     None,
 }
 
 impl fmt::Debug for Policy {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_tuple("Policy").field(&self.inner).finish()
+        let mut t = f.debug_tuple("Policy");
+        t.field(&self.inner);
+        if self.preserve_method {
+            t.field(&"preserve_method");
+        }
+        if let Some(ref headers) = self.sensitive_headers {
+            t.field(headers);
+        }
+        if !self.trusted_hosts.is_empty() {
+            t.field(&self.trusted_hosts);
+        }
+        t.finish()
     }
 }
 
@@ -221,6 +501,7 @@ impl fmt::Debug for PolicyKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             PolicyKind::Custom(..) => f.pad("Custom"),
+            PolicyKind::CustomAsync(..) => f.pad("CustomAsync"),
             PolicyKind::Limit(max) => f.debug_tuple("Limit").field(&max).finish(),
             PolicyKind::None => f.pad("None"),
         }
@@ -237,16 +518,22 @@ pub(crate) enum ActionKind {
 }
 
 pub(crate) fn remove_sensitive_headers(headers: &mut HeaderMap, next: &Url, previous: &[Url]) {
-    if let Some(previous) = previous.last() {
-        let cross_host = next.host_str() != previous.host_str()
-            || next.port_or_known_default() != previous.port_or_known_default();
-        if cross_host {
-            headers.remove(AUTHORIZATION);
-            headers.remove(COOKIE);
-            headers.remove("cookie2");
-            headers.remove(PROXY_AUTHORIZATION);
-            headers.remove(WWW_AUTHENTICATE);
+    if is_cross_host(next, previous) {
+        headers.remove(AUTHORIZATION);
+        headers.remove(COOKIE);
+        headers.remove("cookie2");
+        headers.remove(PROXY_AUTHORIZATION);
+        headers.remove(WWW_AUTHENTICATE);
+    }
+}
+
+fn is_cross_host(next: &Url, previous: &[Url]) -> bool {
+    match previous.last() {
+        Some(previous) => {
+            next.host_str() != previous.host_str()
+                || next.port_or_known_default() != previous.port_or_known_default()
         }
+        None => false,
     }
 }
 
@@ -267,6 +554,23 @@ pub(crate) struct TowerRedirectPolicy {
     referer: bool,
     urls: Vec<Url>,
     https_only: bool,
+    scoped_headers: Arc<ScopedHeaders>,
+    // The body and payload headers of a `POST`, kept when the policy
+    // preserves the method, and whether the next request should get them back.
+    post: Option<Arc<Post>>,
+    repost: bool,
+    // Where `AsyncPolicyService` leaves the decision of an asynchronous policy.
+    decision: Option<Decision>,
+    // Extensions don't follow redirects, so the recorder is handed on here.
+    #[cfg(feature = "har")]
+    har: Option<crate::har::Recorder>,
+}
+
+type Decision = Arc<Mutex<Option<ActionKind>>>;
+
+struct Post {
+    body: async_impl::body::Body,
+    headers: HeaderMap,
 }
 
 impl TowerRedirectPolicy {
@@ -276,6 +580,12 @@ impl TowerRedirectPolicy {
             referer: false,
             urls: Vec::new(),
             https_only: false,
+            scoped_headers: Arc::default(),
+            post: None,
+            repost: false,
+            decision: None,
+            #[cfg(feature = "har")]
+            har: None,
         }
     }
 
@@ -288,6 +598,143 @@ impl TowerRedirectPolicy {
         self.https_only = https_only;
         self
     }
+
+    pub(crate) fn with_scoped_headers(&mut self, scoped_headers: Arc<ScopedHeaders>) -> &mut Self {
+        self.scoped_headers = scoped_headers;
+        self
+    }
+}
+
+/// Asks an asynchronous policy about a redirect on behalf of the
+/// `TowerRedirectPolicy` that sent the request.
+#[derive(Clone)]
+struct AsyncCheck {
+    policy: Arc<Policy>,
+    urls: Vec<Url>,
+    decision: Decision,
+}
+
+/// Runs asynchronous redirect policies.
+///
+/// tower-http asks its policy synchronously, so this sits beneath
+/// `FollowRedirect`: it awaits the policy once a redirect response is
+/// received, before handing the response up, and leaves the decision where
+/// the `TowerRedirectPolicy` that sent the request picks it up.
+#[derive(Clone)]
+pub(crate) struct AsyncPolicyService<S> {
+    inner: S,
+}
+
+impl<S> AsyncPolicyService<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        AsyncPolicyService { inner }
+    }
+}
+
+impl<ReqBody, ResBody, S> Service<http::Request<ReqBody>> for AsyncPolicyService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>> + Clone,
+{
+    type Response = http::Response<ResBody>;
+    type Error = S::Error;
+    type Future = AsyncPolicyFuture<S::Future, ResBody>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let check = req.extensions().get::<AsyncCheck>().and_then(|check| {
+            let url = Url::parse(&req.uri().to_string()).ok()?;
+            Some(Box::new((check.clone(), url, req.headers().clone())))
+        });
+        AsyncPolicyFuture {
+            future: inner.call(req),
+            check,
+            deciding: None,
+        }
+    }
+}
+
+pin_project! {
+    pub(crate) struct AsyncPolicyFuture<F, B> {
+        #[pin]
+        future: F,
+        check: Option<Box<(AsyncCheck, Url, HeaderMap)>>,
+        deciding: Option<(SyncFuture<BoxFuture<Action>>, http::Response<B>)>,
+    }
+}
+
+impl<F, B, E> Future for AsyncPolicyFuture<F, B>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Output = Result<http::Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            if let Some((decide, _)) = this.deciding.as_mut() {
+                let action = ready!(Pin::new(decide).poll(cx));
+                let (_, res) = this.deciding.take().expect("polled after completion");
+                if let Some(check) = this.check.take() {
+                    *check
+                        .0
+                        .decision
+                        .lock()
+                        .expect("redirect decision lock poisoned") = Some(action.inner);
+                }
+                return Poll::Ready(Ok(res));
+            }
+
+            let res = ready!(this.future.as_mut().poll(cx))?;
+            let attempt = match this.check.as_ref() {
+                Some(check) if is_redirect(res.status()) => {
+                    let (ref check, ref url, ref headers) = **check;
+                    res.headers()
+                        .get(LOCATION)
+                        .and_then(|location| location.to_str().ok())
+                        .and_then(|location| url.join(location).ok())
+                        .map(|next| {
+                            let mut previous = check.urls.clone();
+                            previous.push(url.clone());
+                            let attempt = AsyncAttempt {
+                                status: res.status(),
+                                next,
+                                previous,
+                                headers: headers.clone(),
+                            };
+                            (check.policy.clone(), attempt)
+                        })
+                }
+                _ => None,
+            };
+            match attempt {
+                Some((policy, attempt)) => {
+                    let decide = match policy.inner {
+                        PolicyKind::CustomAsync(ref custom) => custom(attempt),
+                        _ => unreachable!("only asynchronous policies are checked here"),
+                    };
+                    *this.deciding = Some((SyncFuture::new(decide), res));
+                }
+                None => return Poll::Ready(Ok(res)),
+            }
+        }
+    }
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
 }
 
 fn make_referer(next: &Url, previous: &Url) -> Option<HeaderValue> {
@@ -314,8 +761,27 @@ impl TowerPolicy<async_impl::body::Body, crate::Error> for TowerRedirectPolicy {
 
         self.urls.push(previous_url.clone());
 
-        match self.policy.check(attempt.status(), &next_url, &self.urls) {
+        let action = match self.decision {
+            Some(ref decision) => decision
+                .lock()
+                .expect("redirect decision lock poisoned")
+                .take()
+                .unwrap_or(ActionKind::Stop),
+            None => self.policy.check(attempt.status(), &next_url, &self.urls),
+        };
+        match action {
             ActionKind::Follow => {
+                match attempt.status() {
+                    StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => {
+                        // The method was already changed to `GET` if it was a
+                        // `POST`; a request that was reposted stays one.
+                        self.repost |= attempt.previous_method() == Method::POST;
+                        self.repost &= self.post.is_some();
+                    }
+                    StatusCode::SEE_OTHER => self.repost = false,
+                    _ => (),
+                }
+
                 if next_url.scheme() != "http" && next_url.scheme() != "https" {
                     return Err(crate::error::url_bad_scheme(next_url));
                 }
@@ -334,8 +800,50 @@ impl TowerPolicy<async_impl::body::Body, crate::Error> for TowerRedirectPolicy {
     }
 
     fn on_request(&mut self, req: &mut http::Request<async_impl::body::Body>) {
+        if let Some(policy) = RequestConfig::<RedirectPolicy>::get(req.extensions()) {
+            self.policy = policy.clone();
+        }
+        if self.policy.is_async() {
+            let decision = self.decision.get_or_insert_with(Default::default).clone();
+            req.extensions_mut().insert(AsyncCheck {
+                policy: self.policy.clone(),
+                urls: self.urls.clone(),
+                decision,
+            });
+        }
+        #[cfg(feature = "har")]
+        match RequestConfig::<Har>::get(req.extensions()) {
+            Some(recorder) => self.har = Some(recorder.clone()),
+            None => *RequestConfig::<Har>::get_mut(req.extensions_mut()) = self.har.clone(),
+        }
+        if self.urls.is_empty() {
+            if self.policy.preserve_method && req.method() == Method::POST {
+                self.post = req.body().try_clone().map(|body| {
+                    let mut headers = HeaderMap::new();
+                    for name in &[CONTENT_TYPE, CONTENT_LENGTH, CONTENT_ENCODING] {
+                        if let Some(value) = req.headers().get(name) {
+                            headers.insert(name, value.clone());
+                        }
+                    }
+                    Arc::new(Post { body, headers })
+                });
+            }
+        } else if self.repost {
+            if let Some(post) = self.post.as_ref() {
+                if let Some(body) = post.body.try_clone() {
+                    *req.method_mut() = Method::POST;
+                    *req.body_mut() = body;
+                    req.headers_mut().extend(post.headers.clone());
+                }
+            }
+        }
         if let Ok(next_url) = Url::parse(&req.uri().to_string()) {
-            remove_sensitive_headers(req.headers_mut(), &next_url, &self.urls);
+            self.policy
+                .remove_sensitive_headers(req.headers_mut(), &next_url, &self.urls);
+            if !self.urls.is_empty() {
+                self.scoped_headers
+                    .remove_out_of_scope(&next_url, req.headers_mut());
+            }
             if self.referer {
                 if let Some(previous_url) = self.urls.last() {
                     if let Some(v) = make_referer(&next_url, previous_url) {
@@ -431,3 +939,37 @@ fn test_remove_sensitive_headers() {
     remove_sensitive_headers(&mut headers, &next, &prev);
     assert_eq!(headers, filtered_headers);
 }
+
+#[test]
+fn test_policy_sensitive_headers_and_trusted_hosts() {
+    use hyper::header::{HeaderValue, AUTHORIZATION, COOKIE};
+
+    let api_key = HeaderName::from_static("x-api-key");
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, HeaderValue::from_static("let me in"));
+    headers.insert(COOKIE, HeaderValue::from_static("foo=bar"));
+    headers.insert(&api_key, HeaderValue::from_static("secret"));
+
+    let prev = vec![Url::parse("https://login.corp.example/").unwrap()];
+    let policy = Policy::default()
+        .sensitive_headers([AUTHORIZATION, api_key.clone()])
+        .trust_host("sso.corp.example")
+        .trust_host(".apps.corp.example");
+
+    for trusted in [
+        "https://sso.corp.example/",
+        "https://apps.corp.example/",
+        "https://wiki.apps.corp.example/",
+    ] {
+        let mut filtered = headers.clone();
+        policy.remove_sensitive_headers(&mut filtered, &Url::parse(trusted).unwrap(), &prev);
+        assert_eq!(filtered, headers, "{trusted}");
+    }
+
+    let mut filtered = headers.clone();
+    let next = Url::parse("https://evilapps.corp.example/").unwrap();
+    policy.remove_sensitive_headers(&mut filtered, &next, &prev);
+    assert_eq!(filtered.get(AUTHORIZATION), None);
+    assert_eq!(filtered.get(&api_key), None);
+    assert_eq!(filtered[COOKIE], "foo=bar");
+}
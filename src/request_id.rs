@@ -0,0 +1,130 @@
+//! Correlation IDs for requests.
+//!
+//! A client set up with [`ClientBuilder::request_id()`] stamps every request
+//! it sends with a header carrying a fresh ID, so that the request can be
+//! found in the logs of the services it goes through:
+//!
+//! ```
+//! use reqwest::header::{HeaderName, HeaderValue};
+//!
+//! # fn doc() -> Result<(), reqwest::Error> {
+//! let client = reqwest::Client::builder()
+//!     .request_id(HeaderName::from_static("x-request-id"), || {
+//!         HeaderValue::from(rand_id())
+//!     })
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! # fn rand_id() -> u64 { 4 }
+//! ```
+//!
+//! The ID a response was received for is in its extensions, as a
+//! [`RequestId`]:
+//!
+//! ```
+//! # async fn run(client: reqwest::Client) -> Result<(), reqwest::Error> {
+//! use reqwest::request_id::RequestId;
+//!
+//! let res = client.get("https://hyper.rs").send().await?;
+//! if let Some(id) = res.extensions().get::<RequestId>() {
+//!     println!("request {:?} got {}", id.value(), res.status());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Retries resend the ID of the first attempt, unless the retry policy asks
+//! for a new one with
+//! [`retry::Builder::regenerate_request_id()`](crate::retry::Builder::regenerate_request_id).
+//! A header already set on a request is left as it is, and kept on retries.
+//!
+//! [`ClientBuilder::request_id()`]: crate::ClientBuilder::request_id
+
+use std::fmt;
+use std::sync::Arc;
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// The ID a request was sent with, in the extensions of its response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(HeaderValue);
+
+impl RequestId {
+    /// The value of the request ID header.
+    pub fn value(&self) -> &HeaderValue {
+        &self.0
+    }
+}
+
+/// The header a client stamps requests with, and how IDs are made.
+#[derive(Clone)]
+pub(crate) struct Stamp {
+    name: HeaderName,
+    generate: Arc<dyn Fn() -> HeaderValue + Send + Sync>,
+}
+
+impl Stamp {
+    pub(crate) fn new<F>(name: HeaderName, generate: F) -> Stamp
+    where
+        F: Fn() -> HeaderValue + Send + Sync + 'static,
+    {
+        Stamp {
+            name,
+            generate: Arc::new(generate),
+        }
+    }
+
+    /// Adds a new ID to `headers`, unless they already have one. Returns
+    /// whether one was added.
+    pub(crate) fn stamp(&self, headers: &mut HeaderMap) -> bool {
+        if headers.contains_key(&self.name) {
+            return false;
+        }
+        headers.insert(self.name.clone(), (self.generate)());
+        true
+    }
+
+    /// Replaces the ID in `headers` with a new one.
+    pub(crate) fn regenerate(&self, headers: &mut HeaderMap) {
+        headers.insert(self.name.clone(), (self.generate)());
+    }
+
+    /// The ID in `headers`, if any.
+    pub(crate) fn get(&self, headers: &HeaderMap) -> Option<RequestId> {
+        headers.get(&self.name).cloned().map(RequestId)
+    }
+}
+
+impl fmt::Debug for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RequestId")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_existing_header() {
+        let stamp = Stamp::new(HeaderName::from_static("x-request-id"), || {
+            HeaderValue::from_static("new")
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-request-id",
+            HeaderValue::from_static("set by the caller"),
+        );
+        assert!(!stamp.stamp(&mut headers));
+        assert_eq!(headers["x-request-id"], "set by the caller");
+
+        let mut headers = HeaderMap::new();
+        assert!(stamp.stamp(&mut headers));
+        assert_eq!(
+            stamp.get(&headers),
+            Some(RequestId(HeaderValue::from_static("new")))
+        );
+    }
+}
@@ -35,21 +35,40 @@
 //!
 //! Some common properties to check include if the request method is
 //! idempotent, or if the response status code indicates a transient error.
+//! [`Builder::classify_transient()`] provides a classifier doing just that.
+//!
+//! # Backoff
+//!
+//! Retries are sent right away unless a [`Builder::backoff()`] is set, in
+//! which case they wait an exponentially growing, randomized delay, or as
+//! long as the server asks for with `Retry-After`. A
+//! [`Builder::max_elapsed()`] bounds how long a request keeps being retried.
 
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
 
+use http::header::RETRY_AFTER;
+use http::HeaderMap;
+use tokio::time::{Instant, Sleep};
 use tower::retry::budget::{Budget as _, TpsBudget as Budget};
 
+use crate::config::{Deadline, RequestConfig};
+use crate::request_id::Stamp;
+
 /// Builder to configure retries
 ///
 /// Construct with [`for_host()`].
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Builder {
-    //backoff: Backoff,
+    backoff: Option<Backoff>,
     budget: Option<f32>,
     classifier: classify::Classifier,
+    max_elapsed: Option<Duration>,
     max_retries_per_request: u32,
+    regenerate_request_id: bool,
     scope: scope::Scoped,
 }
 
@@ -57,15 +76,21 @@ pub struct Builder {
 /// tower::retry::Policy privately.
 #[derive(Clone, Debug)]
 pub(crate) struct Policy {
+    backoff: Option<Backoff>,
     budget: Option<Arc<Budget>>,
     classifier: classify::Classifier,
+    max_elapsed: Option<Duration>,
     max_retries_per_request: u32,
+    regenerate_request_id: bool,
     retry_cnt: u32,
     scope: scope::Scoped,
 }
 
-//#[derive(Debug)]
-//struct Backoff;
+#[derive(Clone, Copy, Debug)]
+struct Backoff {
+    base: Duration,
+    max: Duration,
+}
 
 /// Create a retry builder with a request scope.
 ///
@@ -101,9 +126,12 @@ impl Builder {
     /// For a more convenient constructor, see [`for_host()`].
     pub fn scoped(scope: impl scope::Scope) -> Self {
         Self {
+            backoff: None,
             budget: Some(0.2),
             classifier: classify::Classifier::Never,
+            max_elapsed: None,
             max_retries_per_request: 2, // on top of the original
+            regenerate_request_id: false,
             scope: scope::Scoped::Dyn(Arc::new(scope)),
         }
     }
@@ -144,6 +172,31 @@ impl Builder {
 
     // pub fn max_replay_body
 
+    /// Wait before each retry, with exponential backoff and full jitter.
+    ///
+    /// The delay before the `n`th retry is picked at random between zero and
+    /// `base * 2^(n - 1)`, capped at `max`, which spreads out the retries of
+    /// many clients failing at once. When the response being retried has a
+    /// `Retry-After` header, the delay it asks for is used instead.
+    ///
+    /// By default, requests are retried right away.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.backoff = Some(Backoff { base, max });
+        self
+    }
+
+    /// Stop retrying a request once `max` has elapsed since it was first sent.
+    ///
+    /// A retry that would only be sent after `max`, because of its backoff
+    /// delay or a `Retry-After`, isn't made either; the last response or
+    /// error is returned instead.
+    ///
+    /// Default is no limit.
+    pub fn max_elapsed(mut self, max: Duration) -> Self {
+        self.max_elapsed = Some(max);
+        self
+    }
+
     /// Set the max retries allowed per request.
     ///
     /// For each logical (initial) request, only retry up to `max` times.
@@ -160,6 +213,18 @@ impl Builder {
         self
     }
 
+    /// Send each retry with a new request ID, instead of the ID of the first
+    /// attempt.
+    ///
+    /// This only applies to IDs added by
+    /// [`ClientBuilder::request_id()`](crate::ClientBuilder::request_id).
+    ///
+    /// Default is `false`.
+    pub fn regenerate_request_id(mut self, enabled: bool) -> Self {
+        self.regenerate_request_id = enabled;
+        self
+    }
+
     /// Provide a classifier to determine if a request should be retried.
     ///
     /// # Example
@@ -189,12 +254,47 @@ impl Builder {
         self
     }
 
+    /// Retry transient failures, with safeguards for requests that aren't
+    /// idempotent.
+    ///
+    /// Any request is retried if it couldn't connect, was refused with a
+    /// protocol NACK, or got a `429 Too Many Requests`, since the server
+    /// didn't process it. Other errors, and the `408 Request Timeout`,
+    /// `500 Internal Server Error`, `502 Bad Gateway`,
+    /// `503 Service Unavailable` and `504 Gateway Timeout` statuses, are only
+    /// retried for idempotent requests: those with a `GET`, `HEAD`,
+    /// `OPTIONS`, `TRACE`, `PUT` or `DELETE` method, or an `Idempotency-Key`
+    /// header.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let retries = reqwest::retry::for_host("api.example.com")
+    ///     .classify_transient()
+    ///     .backoff(Duration::from_millis(100), Duration::from_secs(5))
+    ///     .max_elapsed(Duration::from_secs(30));
+    ///
+    /// # #[cfg(not(feature = "rustls-no-provider"))]
+    /// let client = reqwest::Client::builder()
+    ///     .retry(retries)
+    ///     .build();
+    /// ```
+    pub fn classify_transient(mut self) -> Self {
+        self.classifier = classify::Classifier::Transient;
+        self
+    }
+
     pub(crate) fn default() -> Builder {
         Self {
+            backoff: None,
             // unscoped protocols nacks doesn't need a budget
             budget: None,
             classifier: classify::Classifier::ProtocolNacks,
+            max_elapsed: None,
             max_retries_per_request: 2, // on top of the original
+            regenerate_request_id: false,
             scope: scope::Scoped::Unscoped,
         }
     }
@@ -204,9 +304,12 @@ impl Builder {
             .budget
             .map(|p| Arc::new(Budget::new(Duration::from_secs(10), 10, p)));
         Policy {
+            backoff: self.backoff,
             budget,
             classifier: self.classifier,
+            max_elapsed: self.max_elapsed,
             max_retries_per_request: self.max_retries_per_request,
+            regenerate_request_id: self.regenerate_request_id,
             retry_cnt: 0,
             scope: self.scope,
         }
@@ -217,15 +320,80 @@ impl Builder {
 
 type Req = http::Request<crate::async_impl::body::Body>;
 
+/// When a request was first sent, kept in its extensions.
+#[derive(Clone, Copy)]
+struct Started(Instant);
+
+impl Policy {
+    fn delay<B>(&self, result: &crate::Result<http::Response<B>>) -> Duration {
+        let backoff = match self.backoff {
+            Some(backoff) => backoff,
+            None => return Duration::ZERO,
+        };
+        if let Some(after) = result
+            .as_ref()
+            .ok()
+            .and_then(|res| retry_after(res.headers()))
+        {
+            return after;
+        }
+        let factor = 1u32.checked_shl(self.retry_cnt).unwrap_or(u32::MAX);
+        let cap = backoff.base.saturating_mul(factor).min(backoff.max);
+        cap.mul_f64(crate::util::fast_random() as f64 / u64::MAX as f64)
+    }
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(crate::util::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Waits out the backoff before a retry.
+pub(crate) struct Delay(Option<Pin<Box<Sleep>>>);
+
+impl Delay {
+    fn new(delay: Duration) -> Delay {
+        if delay.is_zero() {
+            Delay(None)
+        } else {
+            Delay(Some(Box::pin(tokio::time::sleep(delay))))
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        match self.0.as_mut() {
+            Some(sleep) => sleep.as_mut().poll(cx),
+            None => Poll::Ready(()),
+        }
+    }
+}
+
 impl<B> tower::retry::Policy<Req, http::Response<B>, crate::Error> for Policy {
-    // TODO? backoff futures...
-    type Future = std::future::Ready<()>;
+    type Future = Delay;
 
     fn retry(
         &mut self,
         req: &mut Req,
         result: &mut crate::Result<http::Response<B>>,
     ) -> Option<Self::Future> {
+        let stamp = req.extensions().get::<Stamp>();
+        if let (Some(stamp), Ok(res)) = (stamp, result.as_mut()) {
+            if let Some(id) = stamp.get(req.headers()) {
+                res.extensions_mut().insert(id);
+            }
+        }
+
         match self.classifier.classify(req, result) {
             classify::Action::Success => {
                 log::trace!("shouldn't retry!");
@@ -236,9 +404,26 @@ impl<B> tower::retry::Policy<Req, http::Response<B>, crate::Error> for Policy {
             }
             classify::Action::Retryable => {
                 log::trace!("could retry!");
+                let delay = self.delay(result);
+                if let Some(max) = self.max_elapsed {
+                    let elapsed = req
+                        .extensions()
+                        .get::<Started>()
+                        .map_or(Duration::ZERO, |started| started.0.elapsed());
+                    if elapsed + delay >= max {
+                        log::debug!("retryable but max elapsed time would be exceeded");
+                        return None;
+                    }
+                }
                 if self.budget.as_ref().map(|b| b.withdraw()).unwrap_or(true) {
                     self.retry_cnt += 1;
-                    Some(std::future::ready(()))
+                    crate::metrics::retry();
+                    if self.regenerate_request_id {
+                        if let Some(stamp) = req.extensions().get::<Stamp>().cloned() {
+                            stamp.regenerate(req.headers_mut());
+                        }
+                    }
+                    Some(Delay::new(delay))
                 } else {
                     log::debug!("retryable but could not withdraw from budget");
                     None
@@ -255,18 +440,46 @@ impl<B> tower::retry::Policy<Req, http::Response<B>, crate::Error> for Policy {
             log::trace!("max_retries_per_request hit");
             return None;
         }
-        let body = req.body().try_clone()?;
-        let mut new = http::Request::new(body);
-        *new.method_mut() = req.method().clone();
-        *new.uri_mut() = req.uri().clone();
-        *new.version_mut() = req.version();
-        *new.headers_mut() = req.headers().clone();
-        *new.extensions_mut() = req.extensions().clone();
+        if let Some(deadline) = RequestConfig::<Deadline>::get(req.extensions()) {
+            if Instant::from_std(*deadline) <= Instant::now() {
+                log::trace!("request deadline passed");
+                return None;
+            }
+        }
+        let mut new = replay(req)?;
+        if self.max_elapsed.is_some() && new.extensions().get::<Started>().is_none() {
+            new.extensions_mut().insert(Started(Instant::now()));
+        }
 
         Some(new)
     }
 }
 
+/// A copy of `req` to send again, if its body can be.
+pub(crate) fn replay(req: &Req) -> Option<Req> {
+    let body = req.body().try_clone()?;
+    let mut new = http::Request::new(body);
+    *new.method_mut() = req.method().clone();
+    *new.uri_mut() = req.uri().clone();
+    *new.version_mut() = req.version();
+    *new.headers_mut() = req.headers().clone();
+    *new.extensions_mut() = req.extensions().clone();
+    Some(new)
+}
+
+/// Whether sending `req` twice has the same effect as sending it once.
+pub(crate) fn is_idempotent(req: &Req) -> bool {
+    matches!(
+        *req.method(),
+        http::Method::GET
+            | http::Method::HEAD
+            | http::Method::OPTIONS
+            | http::Method::TRACE
+            | http::Method::PUT
+            | http::Method::DELETE
+    ) || req.headers().contains_key("idempotency-key")
+}
+
 fn is_retryable_error(err: &crate::Error) -> bool {
     use std::error::Error as _;
 
@@ -428,6 +641,29 @@ mod classify {
                 .map(|&e| super::is_retryable_error(e))
                 .unwrap_or(false)
         }
+
+        fn is_idempotent(&self) -> bool {
+            super::is_idempotent(self.0)
+        }
+
+        fn is_transient(&self) -> bool {
+            match self.1 {
+                Err(err) => {
+                    err.is_connect()
+                        || self.is_protocol_nack()
+                        || (err.is_request() && self.is_idempotent())
+                }
+                Ok(status) => match status {
+                    http::StatusCode::TOO_MANY_REQUESTS => true,
+                    http::StatusCode::REQUEST_TIMEOUT
+                    | http::StatusCode::INTERNAL_SERVER_ERROR
+                    | http::StatusCode::BAD_GATEWAY
+                    | http::StatusCode::SERVICE_UNAVAILABLE
+                    | http::StatusCode::GATEWAY_TIMEOUT => self.is_idempotent(),
+                    _ => false,
+                },
+            }
+        }
     }
 
     #[must_use]
@@ -441,6 +677,7 @@ mod classify {
     pub(super) enum Classifier {
         Never,
         ProtocolNacks,
+        Transient,
         Dyn(std::sync::Arc<dyn Classify>),
     }
 
@@ -460,6 +697,13 @@ mod classify {
                         Action::Success
                     }
                 }
+                Self::Transient => {
+                    if req_rep.is_transient() {
+                        Action::Retryable
+                    } else {
+                        Action::Success
+                    }
+                }
                 Self::Dyn(c) => c.classify(req_rep),
             }
         }
@@ -470,6 +714,7 @@ mod classify {
             match self {
                 Self::Never => f.write_str("Never"),
                 Self::ProtocolNacks => f.write_str("ProtocolNacks"),
+                Self::Transient => f.write_str("Transient"),
                 Self::Dyn(_) => f.write_str("Classifier"),
             }
         }
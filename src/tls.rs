@@ -43,16 +43,28 @@
 //!
 //! [rustls]: https://crates.io/crates/rustls
 
+#[cfg(feature = "__rustls")]
+mod ocsp;
+#[cfg(feature = "__rustls")]
+pub(crate) mod shared;
+
+#[cfg(feature = "__rustls")]
+pub use self::ocsp::OcspPolicy;
+#[cfg(feature = "__rustls")]
+pub(crate) use self::ocsp::OcspVerifier;
+
 #[cfg(feature = "__rustls")]
 use rustls::{
     client::danger::HandshakeSignatureValid, client::danger::ServerCertVerified,
     client::danger::ServerCertVerifier, crypto::WebPkiSupportedAlgorithms,
-    server::ParsedCertificate, DigitallySignedStruct, Error as TLSError, RootCertStore,
-    SignatureScheme,
+    server::ParsedCertificate, CertificateError, DigitallySignedStruct, Error as TLSError,
+    OtherError, RootCertStore, SignatureScheme,
 };
 use rustls_pki_types::pem::PemObject;
 #[cfg(feature = "__rustls")]
 use rustls_pki_types::{ServerName, UnixTime};
+#[cfg(feature = "__rustls")]
+use std::sync::Arc;
 use std::{
     fmt,
     io::{BufRead, BufReader},
@@ -60,6 +72,7 @@ use std::{
 
 /// Represents a X509 certificate revocation list.
 #[cfg(feature = "__rustls")]
+#[derive(Clone)]
 pub struct CertificateRevocationList {
     #[cfg(feature = "__rustls")]
     inner: rustls_pki_types::CertificateRevocationListDer<'static>,
@@ -213,6 +226,13 @@ impl Certificate {
         tls.add_root_certificate(self.native);
     }
 
+    #[cfg(feature = "__rustls")]
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        match self.original {
+            Cert::Der(ref buf) | Cert::Pem(ref buf) => buf,
+        }
+    }
+
     #[cfg(feature = "__rustls")]
     pub(crate) fn add_to_rustls(
         self,
@@ -451,9 +471,35 @@ impl CertificateRevocationList {
     /// This requires the `rustls(-...)` Cargo feature enabled.
     #[cfg(feature = "__rustls")]
     pub fn from_pem(pem: &[u8]) -> crate::Result<CertificateRevocationList> {
+        rustls_pki_types::CertificateRevocationListDer::from_pem_slice(pem)
+            .map(|inner| CertificateRevocationList { inner })
+            .map_err(|_| crate::error::builder("invalid crl encoding"))
+    }
+
+    /// Creates a `CertificateRevocationList` from a DER encoded CRL.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs::File;
+    /// # use std::io::Read;
+    /// # fn crl() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut buf = Vec::new();
+    /// File::open("my_crl.der")?
+    ///     .read_to_end(&mut buf)?;
+    /// let crl = reqwest::tls::CertificateRevocationList::from_der(&buf)?;
+    /// # drop(crl);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the `rustls(-...)` Cargo feature enabled.
+    #[cfg(feature = "__rustls")]
+    pub fn from_der(der: &[u8]) -> crate::Result<CertificateRevocationList> {
         Ok(CertificateRevocationList {
-            #[cfg(feature = "__rustls")]
-            inner: rustls_pki_types::CertificateRevocationListDer::from(pem.to_vec()),
+            inner: rustls_pki_types::CertificateRevocationListDer::from(der.to_vec()),
         })
     }
 
@@ -492,6 +538,11 @@ impl CertificateRevocationList {
     pub(crate) fn as_rustls_crl<'a>(&self) -> rustls_pki_types::CertificateRevocationListDer<'a> {
         self.inner.clone()
     }
+
+    #[cfg(feature = "__rustls")]
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.inner.as_ref()
+    }
 }
 
 impl fmt::Debug for Certificate {
@@ -513,6 +564,109 @@ impl fmt::Debug for CertificateRevocationList {
     }
 }
 
+/// A cache of TLS sessions, used to resume them on later connections.
+///
+/// Resuming a session skips most of the handshake. Clones share the same
+/// cache, so giving one to several clients lets each of them resume sessions
+/// the others started.
+///
+/// Sessions are only resumed between clients with the same certificate
+/// verification settings, and without a client certificate. Only the first
+/// 32 distinct settings are shared this way; clients with others still
+/// resume their own sessions.
+#[cfg(feature = "__rustls")]
+#[derive(Clone)]
+pub struct SessionCache {
+    inner: Arc<SessionCacheInner>,
+}
+
+#[cfg(feature = "__rustls")]
+const MAX_SHARED_VERIFIERS: usize = 32;
+
+#[cfg(feature = "__rustls")]
+struct SessionCacheInner {
+    store: Arc<dyn rustls::client::ClientSessionStore>,
+    // rustls only resumes a session through the same verifier and client
+    // cert resolver instances that established it, so clients sharing the
+    // cache need to share those too.
+    verifiers: std::sync::Mutex<Vec<(VerifierKey, Arc<dyn ServerCertVerifier>)>>,
+    no_client_auth: std::sync::Mutex<Option<Arc<dyn rustls::client::ResolvesClientCert>>>,
+}
+
+/// The settings that determine how a client verifies server certificates.
+#[cfg(feature = "__rustls")]
+#[derive(Clone, PartialEq)]
+pub(crate) struct VerifierKey {
+    pub(crate) certs_verification: bool,
+    pub(crate) hostname_verification: bool,
+    pub(crate) certs_only: bool,
+    pub(crate) root_certs: Vec<Vec<u8>>,
+    pub(crate) crls: Vec<Vec<u8>>,
+    pub(crate) ocsp: OcspPolicy,
+    // Identifies the callback and algorithms by address.
+    pub(crate) callback: Option<usize>,
+    pub(crate) algorithms: usize,
+}
+
+#[cfg(feature = "__rustls")]
+impl SessionCache {
+    /// Creates an in-memory cache holding up to `size` sessions.
+    pub fn new(size: usize) -> SessionCache {
+        SessionCache {
+            inner: Arc::new(SessionCacheInner {
+                // rustls sizes its cache in servers of up to 8 sessions each,
+                // and a cache sized for a single server never keeps one.
+                store: Arc::new(rustls::client::ClientSessionMemoryCache::new(size.max(16))),
+                verifiers: Default::default(),
+                no_client_auth: Default::default(),
+            }),
+        }
+    }
+
+    pub(crate) fn as_rustls(&self) -> Arc<dyn rustls::client::ClientSessionStore> {
+        self.inner.store.clone()
+    }
+
+    /// Returns the verifier already registered for `key`, or registers
+    /// `verifier` for it.
+    pub(crate) fn share_verifier(
+        &self,
+        key: &VerifierKey,
+        verifier: Arc<dyn ServerCertVerifier>,
+    ) -> Arc<dyn ServerCertVerifier> {
+        let mut verifiers = self.inner.verifiers.lock().expect("lock poisoned");
+        if let Some((_, shared)) = verifiers.iter().find(|(k, _)| k == key) {
+            return shared.clone();
+        }
+        // Clients with their own callback each have a key of their own, so
+        // past a few, the verifiers of new ones aren't kept alive here.
+        if verifiers.len() < MAX_SHARED_VERIFIERS {
+            verifiers.push((key.clone(), verifier.clone()));
+        }
+        verifier
+    }
+
+    /// Returns the resolver shared by clients not sending a certificate.
+    pub(crate) fn share_no_client_auth(
+        &self,
+        resolver: Arc<dyn rustls::client::ResolvesClientCert>,
+    ) -> Arc<dyn rustls::client::ResolvesClientCert> {
+        self.inner
+            .no_client_auth
+            .lock()
+            .expect("lock poisoned")
+            .get_or_insert(resolver)
+            .clone()
+    }
+}
+
+#[cfg(feature = "__rustls")]
+impl fmt::Debug for SessionCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SessionCache").finish()
+    }
+}
+
 /// A TLS protocol version.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Version(InnerVersion);
@@ -562,6 +716,7 @@ impl Version {
     }
 }
 
+#[derive(Clone)]
 pub(crate) enum TlsBackend {
     // This is the default and HTTP/3 feature does not use it so suppress it.
     #[allow(dead_code)]
@@ -622,7 +777,6 @@ pub(crate) fn rustls_store(certs: Vec<Certificate>) -> crate::Result<RootCertSto
 }
 
 #[cfg(feature = "__rustls")]
-#[cfg(any(all(unix, not(target_os = "android")), target_os = "windows"))]
 pub(crate) fn rustls_der(
     certs: Vec<Certificate>,
 ) -> crate::Result<Vec<rustls_pki_types::CertificateDer<'static>>> {
@@ -642,6 +796,39 @@ pub(crate) fn rustls_der(
     Ok(ders)
 }
 
+/// TLS verification settings a single request uses instead of the client's.
+#[cfg(feature = "__rustls")]
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TlsOverride {
+    AcceptInvalidCerts,
+    CertsOnly(Vec<rustls_pki_types::CertificateDer<'static>>),
+}
+
+#[cfg(feature = "__rustls")]
+impl TlsOverride {
+    pub(crate) fn verifier(
+        &self,
+        provider: Arc<rustls::crypto::CryptoProvider>,
+    ) -> crate::Result<Arc<dyn ServerCertVerifier>> {
+        match self {
+            TlsOverride::AcceptInvalidCerts => Ok(Arc::new(NoVerifier)),
+            TlsOverride::CertsOnly(certs) => {
+                let mut roots = RootCertStore::empty();
+                for cert in certs {
+                    roots.add(cert.clone()).map_err(crate::error::builder)?;
+                }
+                let verifier = rustls::client::WebPkiServerVerifier::builder_with_provider(
+                    Arc::new(roots),
+                    provider,
+                )
+                .build()
+                .map_err(|_| crate::error::builder("invalid TLS verification settings"))?;
+                Ok(verifier)
+            }
+        }
+    }
+}
+
 #[cfg(feature = "__rustls")]
 #[derive(Debug)]
 pub(crate) struct NoVerifier;
@@ -761,23 +948,235 @@ impl ServerCertVerifier for IgnoreHostname {
     }
 }
 
+/// The certificate chain presented by a server.
+///
+/// Passed to the callback set with [`ClientBuilder::tls_verifier()`][].
+///
+/// [`ClientBuilder::tls_verifier()`]: crate::ClientBuilder::tls_verifier()
+#[cfg(feature = "__rustls")]
+pub struct CertChain<'a> {
+    end_entity: &'a rustls_pki_types::CertificateDer<'a>,
+    intermediates: &'a [rustls_pki_types::CertificateDer<'a>],
+    error: Option<&'a TLSError>,
+}
+
+#[cfg(feature = "__rustls")]
+impl CertChain<'_> {
+    /// Get the DER encoded leaf certificate of the server.
+    pub fn end_entity(&self) -> &[u8] {
+        self.end_entity
+    }
+
+    /// Get the DER encoded intermediate certificates, in the order the
+    /// server sent them.
+    pub fn intermediates(&self) -> impl Iterator<Item = &[u8]> {
+        self.intermediates.iter().map(|der| &der[..])
+    }
+
+    /// Get the reason the built-in verification rejected this chain.
+    ///
+    /// Returns `None` if the chain passed the built-in verification.
+    pub fn verification_error(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.map(|err| err as _)
+    }
+}
+
+#[cfg(feature = "__rustls")]
+impl fmt::Debug for CertChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CertChain")
+            .field("intermediates", &self.intermediates.len())
+            .field("verification_error", &self.error)
+            .finish()
+    }
+}
+
+#[cfg(feature = "__rustls")]
+pub(crate) type VerifyFn =
+    dyn Fn(&CertChain<'_>, &str) -> Result<(), crate::error::BoxError> + Send + Sync;
+
+/// Runs a user callback after the built-in verifier, letting it have the
+/// final say on whether the server certificate is accepted.
+#[cfg(feature = "__rustls")]
+pub(crate) struct CustomVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    callback: Arc<VerifyFn>,
+}
+
+#[cfg(feature = "__rustls")]
+impl CustomVerifier {
+    pub(crate) fn new(inner: Arc<dyn ServerCertVerifier>, callback: Arc<VerifyFn>) -> Self {
+        Self { inner, callback }
+    }
+}
+
+#[cfg(feature = "__rustls")]
+impl fmt::Debug for CustomVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomVerifier")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[cfg(feature = "__rustls")]
+impl ServerCertVerifier for CustomVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls_pki_types::CertificateDer<'_>,
+        intermediates: &[rustls_pki_types::CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, TLSError> {
+        let result = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        );
+        let chain = CertChain {
+            end_entity,
+            intermediates,
+            error: result.as_ref().err(),
+        };
+        match (self.callback)(&chain, &server_name.to_str()) {
+            Ok(()) => Ok(ServerCertVerified::assertion()),
+            Err(err) => Err(TLSError::InvalidCertificate(CertificateError::Other(
+                OtherError(Arc::from(err)),
+            ))),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TLSError> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TLSError> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+
+    fn requires_raw_public_keys(&self) -> bool {
+        self.inner.requires_raw_public_keys()
+    }
+
+    fn root_hint_subjects(&self) -> Option<&[rustls::DistinguishedName]> {
+        self.inner.root_hint_subjects()
+    }
+}
+
 /// Hyper extension carrying extra TLS layer information.
 /// Made available to clients on responses when `tls_info` is set.
 #[derive(Clone)]
 pub struct TlsInfo {
-    pub(crate) peer_certificate: Option<Vec<u8>>,
+    pub(crate) peer_certificates: Vec<Vec<u8>>,
+    pub(crate) version: Option<Version>,
+    pub(crate) cipher_suite: Option<&'static str>,
+    pub(crate) alpn_protocol: Option<Vec<u8>>,
 }
 
 impl TlsInfo {
     /// Get the DER encoded leaf certificate of the peer.
     pub fn peer_certificate(&self) -> Option<&[u8]> {
-        self.peer_certificate.as_ref().map(|der| &der[..])
+        self.peer_certificates.first().map(|der| &der[..])
+    }
+
+    /// Get the DER encoded certificate chain presented by the peer, starting
+    /// with the leaf certificate.
+    ///
+    /// With native-tls, only the leaf certificate is available.
+    pub fn peer_certificate_chain(&self) -> impl Iterator<Item = &[u8]> {
+        self.peer_certificates.iter().map(|der| &der[..])
+    }
+
+    /// Get the negotiated TLS version.
+    ///
+    /// This is only available with rustls.
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Get the name of the negotiated cipher suite, such as
+    /// `TLS13_AES_128_GCM_SHA256`.
+    ///
+    /// This is only available with rustls.
+    pub fn cipher_suite(&self) -> Option<&str> {
+        self.cipher_suite
+    }
+
+    /// Get the protocol negotiated with ALPN, such as `h2`.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    #[cfg(feature = "__rustls")]
+    pub(crate) fn from_rustls(conn: &rustls::ClientConnection) -> TlsInfo {
+        TlsInfo {
+            peer_certificates: conn
+                .peer_certificates()
+                .unwrap_or_default()
+                .iter()
+                .map(|c| c.to_vec())
+                .collect(),
+            version: conn.protocol_version().and_then(Version::from_rustls),
+            cipher_suite: conn
+                .negotiated_cipher_suite()
+                .and_then(|suite| suite.suite().as_str()),
+            alpn_protocol: conn.alpn_protocol().map(|p| p.to_vec()),
+        }
+    }
+
+    #[cfg(feature = "__native-tls")]
+    pub(crate) fn from_native_tls<S>(stream: &native_tls_crate::TlsStream<S>) -> TlsInfo
+    where
+        S: std::io::Read + std::io::Write,
+    {
+        TlsInfo {
+            peer_certificates: stream
+                .peer_certificate()
+                .ok()
+                .flatten()
+                .and_then(|c| c.to_der().ok())
+                .into_iter()
+                .collect(),
+            version: None,
+            cipher_suite: None,
+            #[cfg(feature = "__native-tls-alpn")]
+            alpn_protocol: stream.negotiated_alpn().ok().flatten(),
+            #[cfg(not(feature = "__native-tls-alpn"))]
+            alpn_protocol: None,
+        }
     }
 }
 
 impl std::fmt::Debug for TlsInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        f.debug_struct("TlsInfo").finish()
+        f.debug_struct("TlsInfo")
+            .field("version", &self.version)
+            .field("cipher_suite", &self.cipher_suite)
+            .field(
+                "alpn_protocol",
+                &self
+                    .alpn_protocol
+                    .as_ref()
+                    .map(|p| String::from_utf8_lossy(p)),
+            )
+            .finish()
     }
 }
 
@@ -868,6 +1267,49 @@ mod tests {
         CertificateRevocationList::from_pem(pem).unwrap();
     }
 
+    #[cfg(feature = "__rustls")]
+    #[test]
+    fn crl_from_pem_decodes() {
+        let pem = std::fs::read("tests/support/revocation/ca.crl.pem").unwrap();
+
+        let crl = CertificateRevocationList::from_pem(&pem).unwrap();
+        assert_eq!(crl.as_rustls_crl().first(), Some(&0x30));
+
+        CertificateRevocationList::from_pem(b"not pem").unwrap_err();
+    }
+
+    #[cfg(feature = "__rustls-aws-lc-rs")]
+    #[test]
+    fn crl_revokes_certificate() {
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let ca = std::fs::read("tests/support/revocation/ca.der").unwrap();
+        let roots = rustls_store(vec![Certificate::from_der(&ca).unwrap()]).unwrap();
+        let pem = std::fs::read("tests/support/revocation/ca.crl.pem").unwrap();
+        let crl = CertificateRevocationList::from_pem(&pem).unwrap();
+        let verifier =
+            rustls::client::WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+                .with_crls([crl.as_rustls_crl()])
+                .build()
+                .unwrap();
+
+        let verify = |leaf: &str| {
+            let leaf = std::fs::read(format!("tests/support/revocation/{leaf}")).unwrap();
+            verifier.verify_server_cert(
+                &leaf.into(),
+                &[],
+                &ServerName::try_from("localhost").unwrap(),
+                &[],
+                UnixTime::now(),
+            )
+        };
+
+        verify("leaf-must-staple.der").unwrap();
+        assert_eq!(
+            verify("leaf.der").unwrap_err(),
+            TLSError::InvalidCertificate(CertificateError::Revoked)
+        );
+    }
+
     #[cfg(feature = "__rustls")]
     #[test]
     fn crl_from_pem_bundle() {
@@ -879,4 +1321,37 @@ mod tests {
         let result = result.unwrap();
         assert_eq!(result.len(), 1);
     }
+
+    #[cfg(feature = "__rustls")]
+    #[test]
+    fn custom_verifier_has_final_say() {
+        let cert = rustls_pki_types::CertificateDer::from(&b"not a real cert"[..]);
+        let name = ServerName::try_from("example.com").unwrap();
+
+        let reject = CustomVerifier::new(
+            Arc::new(NoVerifier),
+            Arc::new(|chain, name| {
+                assert!(chain.verification_error().is_none());
+                assert_eq!(chain.end_entity(), b"not a real cert");
+                assert_eq!(name, "example.com");
+                Err("pinned key mismatch".into())
+            }),
+        );
+        let err = reject
+            .verify_server_cert(&cert, &[], &name, &[], UnixTime::now())
+            .unwrap_err();
+        assert!(err.to_string().contains("pinned key mismatch"), "{err}");
+
+        // overrule the rejection above
+        let accept = CustomVerifier::new(
+            Arc::new(reject),
+            Arc::new(|chain, _| {
+                assert!(chain.verification_error().is_some());
+                Ok(())
+            }),
+        );
+        accept
+            .verify_server_cert(&cert, &[], &name, &[], UnixTime::now())
+            .unwrap();
+    }
 }
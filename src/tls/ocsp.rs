@@ -0,0 +1,749 @@
+//! OCSP revocation checking for the rustls backend.
+//!
+//! Only responses stapled by the server are checked. Fetching responses from
+//! the responder is deliberately not supported: certificates are verified
+//! synchronously during the handshake, where a request to the responder
+//! would stall the connection, and the request would tell the responder
+//! which sites the client visits.
+//!
+//! A response is trusted if it's signed by the certificate's issuer, or by a
+//! responder certificate the issuer delegated OCSP signing to. It applies to
+//! the certificate when its CertID has the certificate's serial number and
+//! the hashes of that issuer's name and key.
+//!
+//! The issuer is looked for among the certificates sent by the server, then
+//! the root certificates added to the client. Every certificate with the
+//! right subject that signed the certificate is tried, as cross-signed CAs
+//! share a subject.
+
+use std::fmt;
+use std::sync::Arc;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::{CertificateError, DigitallySignedStruct, Error as TLSError, SignatureScheme};
+use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+use sha1::Digest;
+
+/// How the revocation status of server certificates is checked using OCSP.
+///
+/// Whatever the policy, a certificate with the OCSP must-staple extension is
+/// rejected unless the server staples a valid response for it, as long as
+/// checking isn't turned [`Off`](OcspPolicy::Off).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OcspPolicy {
+    /// Don't check OCSP responses. This is the default.
+    #[default]
+    Off,
+    /// Check the response stapled by the server, if any.
+    ///
+    /// Certificates the response says are revoked are rejected. Missing,
+    /// invalid or stale responses are ignored.
+    BestEffort,
+    /// Require the server to staple a valid response saying the certificate
+    /// is good.
+    ///
+    /// Responses aren't fetched from the OCSP responder, so servers that
+    /// don't staple one are rejected.
+    Required,
+}
+
+/// Checks the stapled OCSP response after the wrapped verifier accepted the
+/// certificate chain.
+pub(crate) struct OcspVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    policy: OcspPolicy,
+    roots: Vec<CertificateDer<'static>>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl OcspVerifier {
+    pub(crate) fn new(
+        inner: Arc<dyn ServerCertVerifier>,
+        policy: OcspPolicy,
+        roots: Vec<CertificateDer<'static>>,
+        algorithms: WebPkiSupportedAlgorithms,
+    ) -> Self {
+        Self {
+            inner,
+            policy,
+            roots,
+            algorithms,
+        }
+    }
+
+    fn check(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        staple: &[u8],
+        now: UnixTime,
+    ) -> Result<(), TLSError> {
+        let cert = Cert::parse(end_entity)
+            .map_err(|_| TLSError::InvalidCertificate(CertificateError::BadEncoding))?;
+        let strict = self.policy == OcspPolicy::Required || cert.must_staple();
+
+        let reason = if staple.is_empty() {
+            "no OCSP response stapled"
+        } else {
+            let issuers = intermediates.iter().chain(&self.roots);
+            match status(&cert, issuers, staple, now, &self.algorithms) {
+                Ok(Status::Good) => return Ok(()),
+                Ok(Status::Revoked) => {
+                    return Err(TLSError::InvalidCertificate(CertificateError::Revoked))
+                }
+                Ok(Status::Unknown) => "OCSP responder doesn't know the certificate",
+                Err(reason) => reason,
+            }
+        };
+
+        if strict {
+            log::debug!("rejecting certificate: {reason}");
+            Err(TLSError::InvalidCertificate(
+                CertificateError::UnknownRevocationStatus,
+            ))
+        } else {
+            log::trace!("ignoring OCSP status: {reason}");
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Debug for OcspVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OcspVerifier")
+            .field("inner", &self.inner)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl ServerCertVerifier for OcspVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, TLSError> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        self.check(end_entity, intermediates, ocsp_response, now)?;
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TLSError> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TLSError> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+
+    fn requires_raw_public_keys(&self) -> bool {
+        self.inner.requires_raw_public_keys()
+    }
+
+    fn root_hint_subjects(&self) -> Option<&[rustls::DistinguishedName]> {
+        self.inner.root_hint_subjects()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Status {
+    Good,
+    Revoked,
+    Unknown,
+}
+
+// Responses are allowed to be this far in the future or past, in seconds.
+const MAX_CLOCK_SKEW: u64 = 5 * 60;
+
+/// The status a stapled response gives `cert`, once it's authenticated by
+/// one of the `issuers` that signed `cert`.
+fn status<'a>(
+    cert: &Cert<'_>,
+    issuers: impl Iterator<Item = &'a CertificateDer<'a>>,
+    staple: &[u8],
+    now: UnixTime,
+    algorithms: &WebPkiSupportedAlgorithms,
+) -> Result<Status, &'static str> {
+    let response = Response::parse(staple)?;
+    let now = now.as_secs();
+
+    let mut issuers = issuers
+        .filter_map(|der| Cert::parse(der).ok())
+        .filter(|issuer| issuer.subject == cert.issuer && issuer.signed(cert, algorithms))
+        .peekable();
+    if issuers.peek().is_none() {
+        return Err("issuer certificate not found");
+    }
+    let issuer = issuers
+        .find(|issuer| response.is_signed_for(issuer, now, algorithms))
+        .ok_or("OCSP response isn't signed by the certificate's issuer")?;
+
+    let single = response
+        .responses
+        .into_iter()
+        .find(|single| single.cert_id.identifies(cert, &issuer))
+        .ok_or("OCSP response doesn't cover the certificate")?;
+    if single.this_update > now + MAX_CLOCK_SKEW {
+        return Err("OCSP response isn't valid yet");
+    }
+    if let Some(next_update) = single.next_update {
+        if next_update + MAX_CLOCK_SKEW < now {
+            return Err("OCSP response has expired");
+        }
+    }
+    Ok(single.status)
+}
+
+fn verify_signature(
+    signer: &Cert<'_>,
+    alg: &[u8],
+    message: &[u8],
+    signature: &[u8],
+    algorithms: &WebPkiSupportedAlgorithms,
+) -> bool {
+    algorithms
+        .all
+        .iter()
+        .filter(|a| &*a.signature_alg_id() == alg && &*a.public_key_alg_id() == signer.spki_alg)
+        .any(|a| {
+            a.verify_signature(signer.public_key, message, signature)
+                .is_ok()
+        })
+}
+
+// DER tags
+const BOOLEAN: u8 = 0x01;
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const ENUMERATED: u8 = 0x0a;
+const SEQUENCE: u8 = 0x30;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const CONTEXT_0: u8 = 0xa0;
+const CONTEXT_1: u8 = 0xa1;
+const CONTEXT_3: u8 = 0xa3;
+
+// id-pkix-ocsp-basic, 1.3.6.1.5.5.7.48.1.1
+const ID_PKIX_OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+// id-sha1, 1.3.14.3.2.26
+const ID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+// id-sha256, id-sha384 and id-sha512, 2.16.840.1.101.3.4.2.1 to 3
+const ID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+const ID_SHA384: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+const ID_SHA512: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+// id-pe-tlsfeature, 1.3.6.1.5.5.7.1.24
+const ID_PE_TLSFEATURE: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x01, 0x18];
+// id-ce-extKeyUsage, 2.5.29.37
+const ID_CE_EXT_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x25];
+// id-kp-OCSPSigning, 1.3.6.1.5.5.7.3.9
+const ID_KP_OCSP_SIGNING: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x09];
+
+const MALFORMED: &str = "malformed DER";
+
+/// A minimal DER reader, just enough for certificates and OCSP responses.
+#[derive(Clone, Copy)]
+struct Der<'a>(&'a [u8]);
+
+impl<'a> Der<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Reads the next element, returning its tag, contents and encoding.
+    fn read_any(&mut self) -> Result<(u8, &'a [u8], &'a [u8]), &'static str> {
+        let (&tag, rest) = self.0.split_first().ok_or(MALFORMED)?;
+        let (&first, mut rest) = rest.split_first().ok_or(MALFORMED)?;
+        let len = if first < 0x80 {
+            usize::from(first)
+        } else {
+            let n = usize::from(first & 0x7f);
+            if n == 0 || n > 4 || rest.len() < n {
+                return Err(MALFORMED);
+            }
+            let (bytes, tail) = rest.split_at(n);
+            rest = tail;
+            bytes
+                .iter()
+                .fold(0usize, |len, &b| (len << 8) | usize::from(b))
+        };
+        if rest.len() < len {
+            return Err(MALFORMED);
+        }
+        let header = self.0.len() - rest.len();
+        let raw = &self.0[..header + len];
+        let (contents, tail) = rest.split_at(len);
+        self.0 = tail;
+        Ok((tag, contents, raw))
+    }
+
+    /// Reads the contents of the next element, which must have `tag`.
+    fn read(&mut self, tag: u8) -> Result<&'a [u8], &'static str> {
+        match self.read_any()? {
+            (t, contents, _) if t == tag => Ok(contents),
+            _ => Err(MALFORMED),
+        }
+    }
+
+    /// Reads the whole encoding of the next element, which must have `tag`.
+    fn read_raw(&mut self, tag: u8) -> Result<&'a [u8], &'static str> {
+        match self.read_any()? {
+            (t, _, raw) if t == tag => Ok(raw),
+            _ => Err(MALFORMED),
+        }
+    }
+
+    fn read_optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, &'static str> {
+        if self.0.first() == Some(&tag) {
+            self.read(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn read_bit_string(&mut self) -> Result<&'a [u8], &'static str> {
+        match self.read(BIT_STRING)?.split_first() {
+            Some((0, bits)) => Ok(bits),
+            _ => Err(MALFORMED),
+        }
+    }
+
+    fn read_time(&mut self) -> Result<u64, &'static str> {
+        let (tag, contents, _) = self.read_any()?;
+        parse_time(tag, contents).ok_or(MALFORMED)
+    }
+}
+
+/// The parts of an X.509 certificate OCSP checking looks at.
+struct Cert<'a> {
+    tbs: &'a [u8],
+    signature_alg: &'a [u8],
+    signature: &'a [u8],
+    serial: &'a [u8],
+    issuer: &'a [u8],
+    subject: &'a [u8],
+    not_before: u64,
+    not_after: u64,
+    spki_alg: &'a [u8],
+    public_key: &'a [u8],
+    extensions: Option<&'a [u8]>,
+}
+
+impl<'a> Cert<'a> {
+    fn parse(der: &'a [u8]) -> Result<Self, &'static str> {
+        let mut cert = Der(Der(der).read(SEQUENCE)?);
+        let tbs = cert.read_raw(SEQUENCE)?;
+        let signature_alg = cert.read(SEQUENCE)?;
+        let signature = cert.read_bit_string()?;
+
+        let mut fields = Der(Der(tbs).read(SEQUENCE)?);
+        fields.read_optional(CONTEXT_0)?;
+        let serial = fields.read(INTEGER)?;
+        fields.read(SEQUENCE)?;
+        let issuer = fields.read_raw(SEQUENCE)?;
+        let mut validity = Der(fields.read(SEQUENCE)?);
+        let not_before = validity.read_time()?;
+        let not_after = validity.read_time()?;
+        let subject = fields.read_raw(SEQUENCE)?;
+        let mut spki = Der(fields.read(SEQUENCE)?);
+        let spki_alg = spki.read(SEQUENCE)?;
+        let public_key = spki.read_bit_string()?;
+        // issuerUniqueID and subjectUniqueID
+        fields.read_optional(0x81)?;
+        fields.read_optional(0x82)?;
+        let extensions = fields.read_optional(CONTEXT_3)?;
+
+        Ok(Cert {
+            tbs,
+            signature_alg,
+            signature,
+            serial,
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            spki_alg,
+            public_key,
+            extensions,
+        })
+    }
+
+    fn extension(&self, id: &[u8]) -> Option<&'a [u8]> {
+        let mut extensions = Der(Der(self.extensions?).read(SEQUENCE).ok()?);
+        while !extensions.is_empty() {
+            let mut extension = Der(extensions.read(SEQUENCE).ok()?);
+            if extension.read(OID).ok()? == id {
+                extension.read_optional(BOOLEAN).ok()?;
+                return extension.read(OCTET_STRING).ok();
+            }
+        }
+        None
+    }
+
+    /// Whether the certificate has the TLS feature extension asking for
+    /// `status_request`, also known as OCSP must-staple.
+    fn must_staple(&self) -> bool {
+        self.extension(ID_PE_TLSFEATURE)
+            .and_then(|value| Der(value).read(SEQUENCE).ok())
+            .map_or(false, |features| {
+                let mut features = Der(features);
+                std::iter::from_fn(|| features.read(INTEGER).ok()).any(|f| f == [5])
+            })
+    }
+
+    /// Whether `self` signed `cert`.
+    fn signed(&self, cert: &Cert<'_>, algorithms: &WebPkiSupportedAlgorithms) -> bool {
+        verify_signature(
+            self,
+            cert.signature_alg,
+            cert.tbs,
+            cert.signature,
+            algorithms,
+        )
+    }
+
+    fn is_ocsp_signer(&self) -> bool {
+        self.extension(ID_CE_EXT_KEY_USAGE)
+            .and_then(|value| Der(value).read(SEQUENCE).ok())
+            .map_or(false, |usages| {
+                let mut usages = Der(usages);
+                std::iter::from_fn(|| usages.read(OID).ok()).any(|u| u == ID_KP_OCSP_SIGNING)
+            })
+    }
+}
+
+/// A successful BasicOCSPResponse.
+struct Response<'a> {
+    tbs: &'a [u8],
+    signature_alg: &'a [u8],
+    signature: &'a [u8],
+    certs: Vec<&'a [u8]>,
+    responses: Vec<SingleResponse<'a>>,
+}
+
+struct SingleResponse<'a> {
+    cert_id: CertId<'a>,
+    status: Status,
+    this_update: u64,
+    next_update: Option<u64>,
+}
+
+impl<'a> Response<'a> {
+    fn parse(der: &'a [u8]) -> Result<Self, &'static str> {
+        let mut response = Der(Der(der).read(SEQUENCE)?);
+        if response.read(ENUMERATED)? != [0] {
+            return Err("OCSP responder returned an error");
+        }
+        let mut bytes = Der(Der(response.read(CONTEXT_0)?).read(SEQUENCE)?);
+        if bytes.read(OID)? != ID_PKIX_OCSP_BASIC {
+            return Err("unsupported OCSP response type");
+        }
+
+        let mut basic = Der(Der(bytes.read(OCTET_STRING)?).read(SEQUENCE)?);
+        let tbs = basic.read_raw(SEQUENCE)?;
+        let signature_alg = basic.read(SEQUENCE)?;
+        let signature = basic.read_bit_string()?;
+        let mut certs = Vec::new();
+        if let Some(list) = basic.read_optional(CONTEXT_0)? {
+            let mut list = Der(Der(list).read(SEQUENCE)?);
+            while !list.is_empty() {
+                certs.push(list.read_raw(SEQUENCE)?);
+            }
+        }
+
+        let mut data = Der(Der(tbs).read(SEQUENCE)?);
+        data.read_optional(CONTEXT_0)?;
+        // responderID
+        data.read_any()?;
+        // producedAt
+        data.read(GENERALIZED_TIME)?;
+        let mut list = Der(data.read(SEQUENCE)?);
+        let mut responses = Vec::new();
+        while !list.is_empty() {
+            responses.push(SingleResponse::parse(list.read(SEQUENCE)?)?);
+        }
+
+        Ok(Response {
+            tbs,
+            signature_alg,
+            signature,
+            certs,
+            responses,
+        })
+    }
+}
+
+impl Response<'_> {
+    /// Whether the response was signed by `issuer`, or by a responder it
+    /// delegated OCSP signing to.
+    fn is_signed_for(
+        &self,
+        issuer: &Cert<'_>,
+        now: u64,
+        algorithms: &WebPkiSupportedAlgorithms,
+    ) -> bool {
+        let signed_by = |signer: &Cert<'_>| {
+            verify_signature(
+                signer,
+                self.signature_alg,
+                self.tbs,
+                self.signature,
+                algorithms,
+            )
+        };
+        let delegated = |responder: &Cert<'_>| {
+            responder.issuer == issuer.subject
+                && responder.is_ocsp_signer()
+                && responder.not_before <= now
+                && now <= responder.not_after
+                && issuer.signed(responder, algorithms)
+                && signed_by(responder)
+        };
+        signed_by(issuer)
+            || self
+                .certs
+                .iter()
+                .filter_map(|der| Cert::parse(der).ok())
+                .any(|responder| delegated(&responder))
+    }
+}
+
+impl<'a> SingleResponse<'a> {
+    fn parse(der: &'a [u8]) -> Result<Self, &'static str> {
+        let mut single = Der(der);
+        let mut cert_id = Der(single.read(SEQUENCE)?);
+        let cert_id = CertId {
+            hash_alg: Der(cert_id.read(SEQUENCE)?).read(OID)?,
+            issuer_name_hash: cert_id.read(OCTET_STRING)?,
+            issuer_key_hash: cert_id.read(OCTET_STRING)?,
+            serial: cert_id.read(INTEGER)?,
+        };
+        let status = match single.read_any()?.0 {
+            0x80 => Status::Good,
+            CONTEXT_1 => Status::Revoked,
+            0x82 => Status::Unknown,
+            _ => return Err(MALFORMED),
+        };
+        let this_update = single.read_time()?;
+        let next_update = match single.read_optional(CONTEXT_0)? {
+            Some(next_update) => Some(Der(next_update).read_time()?),
+            None => None,
+        };
+
+        Ok(SingleResponse {
+            cert_id,
+            status,
+            this_update,
+            next_update,
+        })
+    }
+}
+
+/// Identifies the certificate a single response is about, by its serial
+/// number and hashes of its issuer's name and key.
+struct CertId<'a> {
+    hash_alg: &'a [u8],
+    issuer_name_hash: &'a [u8],
+    issuer_key_hash: &'a [u8],
+    serial: &'a [u8],
+}
+
+impl CertId<'_> {
+    /// Whether this is `cert`, as issued by `issuer`.
+    fn identifies(&self, cert: &Cert<'_>, issuer: &Cert<'_>) -> bool {
+        let hash = |data: &[u8]| -> Option<Vec<u8>> {
+            Some(match self.hash_alg {
+                ID_SHA1 => sha1::Sha1::digest(data).to_vec(),
+                ID_SHA256 => sha2::Sha256::digest(data).to_vec(),
+                ID_SHA384 => sha2::Sha384::digest(data).to_vec(),
+                ID_SHA512 => sha2::Sha512::digest(data).to_vec(),
+                _ => return None,
+            })
+        };
+        // The name hashed is the issuer's subject, in its DER encoding, and
+        // the key is the subjectPublicKey bits without their unused bit count.
+        self.serial == cert.serial
+            && hash(issuer.subject).as_deref() == Some(self.issuer_name_hash)
+            && hash(issuer.public_key).as_deref() == Some(self.issuer_key_hash)
+    }
+}
+
+/// Parses an ASN.1 UTCTime or GeneralizedTime into seconds since the epoch.
+fn parse_time(tag: u8, value: &[u8]) -> Option<u64> {
+    let value = std::str::from_utf8(value).ok()?;
+    let (year, rest) = match tag {
+        UTC_TIME => {
+            let year = value.get(..2)?.parse::<u64>().ok()?;
+            let year = if year < 50 { 2000 + year } else { 1900 + year };
+            (year, value.get(2..)?)
+        }
+        GENERALIZED_TIME => (value.get(..4)?.parse().ok()?, value.get(4..)?),
+        _ => return None,
+    };
+    let rest = rest.strip_suffix('Z')?;
+    if rest.len() != 10 || !rest.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |i: usize| rest[i..i + 2].parse::<u64>().ok();
+    let (month, day) = (field(0)?, field(2)?);
+    let (hour, minute, second) = (field(4)?, field(6)?, field(8)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let yoe = y % 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe).checked_sub(719_468)?;
+
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+#[cfg(all(test, feature = "__rustls-aws-lc-rs"))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        std::fs::read(format!("tests/support/revocation/{name}")).unwrap()
+    }
+
+    // 2027-01-01, after the fixture responses were produced.
+    fn now() -> UnixTime {
+        UnixTime::since_unix_epoch(Duration::from_secs(1_798_761_600))
+    }
+
+    fn verifier(policy: OcspPolicy, roots: &[&str]) -> OcspVerifier {
+        let algorithms =
+            rustls::crypto::aws_lc_rs::default_provider().signature_verification_algorithms;
+        let roots = roots.iter().map(|root| fixture(root).into()).collect();
+        OcspVerifier::new(Arc::new(crate::tls::NoVerifier), policy, roots, algorithms)
+    }
+
+    fn check(policy: OcspPolicy, leaf: &str, staple: &[u8]) -> Result<(), TLSError> {
+        let leaf = CertificateDer::from(fixture(leaf));
+        let ca = CertificateDer::from(fixture("ca.der"));
+        verifier(policy, &[]).check(&leaf, &[ca], staple, now())
+    }
+
+    #[test]
+    fn parse_times() {
+        assert_eq!(parse_time(GENERALIZED_TIME, b"19700101000000Z"), Some(0));
+        assert_eq!(parse_time(UTC_TIME, b"270101000000Z"), Some(1_798_761_600));
+        assert_eq!(
+            parse_time(GENERALIZED_TIME, b"20240229123456Z"),
+            Some(1_709_210_096)
+        );
+        assert_eq!(parse_time(GENERALIZED_TIME, b"20240229123456"), None);
+    }
+
+    #[test]
+    fn good_staple() {
+        let good = fixture("good.der");
+        check(OcspPolicy::Required, "leaf-must-staple.der", &good).unwrap();
+        check(OcspPolicy::BestEffort, "leaf-must-staple.der", &good).unwrap();
+    }
+
+    #[test]
+    fn issuer_among_roots() {
+        let good = fixture("good.der");
+        let leaf = CertificateDer::from(fixture("leaf-must-staple.der"));
+        verifier(OcspPolicy::Required, &["ca.der"])
+            .check(&leaf, &[], &good, now())
+            .unwrap();
+        verifier(OcspPolicy::Required, &[])
+            .check(&leaf, &[], &good, now())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn issuers_sharing_a_subject() {
+        // Another CA with the same name comes first, and didn't sign the
+        // leaf.
+        let good = fixture("good.der");
+        let leaf = CertificateDer::from(fixture("leaf-must-staple.der"));
+        let other = CertificateDer::from(fixture("ca-same-name.der"));
+        verifier(OcspPolicy::Required, &["ca.der"])
+            .check(&leaf, &[other.clone()], &good, now())
+            .unwrap();
+        verifier(OcspPolicy::Required, &[])
+            .check(&leaf, &[other], &good, now())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn cert_id_names_the_issuer() {
+        let good = fixture("good.der");
+        let response = Response::parse(&good).unwrap();
+        let cert_id = &response.responses[0].cert_id;
+        let leaf = fixture("leaf-must-staple.der");
+        let leaf = Cert::parse(&leaf).unwrap();
+        let ca = fixture("ca.der");
+        assert!(cert_id.identifies(&leaf, &Cert::parse(&ca).unwrap()));
+        // Same name, another key.
+        let other = fixture("ca-same-name.der");
+        assert!(!cert_id.identifies(&leaf, &Cert::parse(&other).unwrap()));
+    }
+
+    #[test]
+    fn revoked_staple() {
+        let revoked = fixture("revoked.der");
+        for policy in [OcspPolicy::BestEffort, OcspPolicy::Required] {
+            let err = check(policy, "leaf.der", &revoked).unwrap_err();
+            assert_eq!(err, TLSError::InvalidCertificate(CertificateError::Revoked));
+        }
+    }
+
+    #[test]
+    fn missing_staple() {
+        check(OcspPolicy::BestEffort, "leaf.der", &[]).unwrap();
+        check(OcspPolicy::Required, "leaf.der", &[]).unwrap_err();
+        check(OcspPolicy::BestEffort, "leaf-must-staple.der", &[]).unwrap_err();
+    }
+
+    #[test]
+    fn tampered_staple() {
+        // Change a digit of the producedAt time.
+        let mut good = fixture("good.der");
+        let produced_at = good.windows(8).position(|w| w == b"20261016").unwrap();
+        good[produced_at + 13] ^= 1;
+        check(OcspPolicy::BestEffort, "leaf.der", &good).unwrap();
+        check(OcspPolicy::Required, "leaf-must-staple.der", &good).unwrap_err();
+    }
+
+    #[test]
+    fn staple_for_another_certificate() {
+        // The good response is for the must-staple certificate.
+        let good = fixture("good.der");
+        check(OcspPolicy::Required, "leaf.der", &good).unwrap_err();
+    }
+}
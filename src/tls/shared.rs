@@ -0,0 +1,117 @@
+//! TLS state shared by all clients in the process.
+//!
+//! Most of the cost of building a client with rustls is in its certificate
+//! verifier: root certificates are parsed into a store, and the platform
+//! verifier loads the system roots the first time it's used. Verifiers are
+//! kept here, keyed by the settings they were made for, so that clients
+//! built with the same settings share one, and only the first of them pays
+//! for it.
+//!
+//! Verifiers are kept for good, up to a bound; past it, clients make their
+//! own. System roots are only loaded once, so certificates added to the
+//! system later are only trusted by clients with other settings.
+
+use std::sync::{Arc, Mutex};
+
+use rustls::client::danger::ServerCertVerifier;
+use rustls::crypto::CryptoProvider;
+
+use super::VerifierKey;
+
+const MAX_VERIFIERS: usize = 32;
+
+static VERIFIERS: Mutex<Vec<(VerifierKey, Arc<dyn ServerCertVerifier>)>> = Mutex::new(Vec::new());
+static PROVIDER: Mutex<Option<Arc<CryptoProvider>>> = Mutex::new(None);
+
+/// The verifier for `key`, made with `make` if there is none yet.
+///
+/// Only verifiers without an OCSP policy or callback are shared, so `key`
+/// is expected to have neither.
+pub(crate) fn verifier<F>(key: &VerifierKey, make: F) -> crate::Result<Arc<dyn ServerCertVerifier>>
+where
+    F: FnOnce() -> crate::Result<Arc<dyn ServerCertVerifier>>,
+{
+    if let Some(verifier) = find(&VERIFIERS.lock().unwrap_or_else(|e| e.into_inner()), key) {
+        return Ok(verifier);
+    }
+
+    // Made without the lock, so clients with other settings aren't held up.
+    // Clients racing to make the same verifier all end up with the first.
+    let verifier = make()?;
+    let mut verifiers = VERIFIERS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(shared) = find(&verifiers, key) {
+        return Ok(shared);
+    }
+    if verifiers.len() < MAX_VERIFIERS {
+        verifiers.push((key.clone(), verifier.clone()));
+    }
+    Ok(verifier)
+}
+
+fn find(
+    verifiers: &[(VerifierKey, Arc<dyn ServerCertVerifier>)],
+    key: &VerifierKey,
+) -> Option<Arc<dyn ServerCertVerifier>> {
+    verifiers
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, verifier)| verifier.clone())
+}
+
+/// The crypto provider installed as the process default, or else ours,
+/// made once.
+pub(crate) fn provider() -> Arc<CryptoProvider> {
+    // Allow user to have installed a runtime default.
+    if let Some(provider) = CryptoProvider::get_default() {
+        return provider.clone();
+    }
+    // If not, we ship with _our_ recommended default.
+    PROVIDER
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(default_provider)
+        .clone()
+}
+
+fn default_provider() -> Arc<CryptoProvider> {
+    #[cfg(not(feature = "__rustls-aws-lc-rs"))]
+    panic!("No provider set");
+
+    #[cfg(feature = "__rustls-aws-lc-rs")]
+    Arc::new(rustls::crypto::aws_lc_rs::default_provider())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::{NoVerifier, OcspPolicy};
+
+    fn key(root: &[u8]) -> VerifierKey {
+        VerifierKey {
+            certs_verification: true,
+            hostname_verification: true,
+            certs_only: true,
+            root_certs: vec![root.to_vec()],
+            crls: Vec::new(),
+            ocsp: OcspPolicy::Off,
+            callback: None,
+            algorithms: 0,
+        }
+    }
+
+    #[test]
+    fn shares_verifiers_made_for_the_same_settings() {
+        let made = || Ok(Arc::new(NoVerifier) as Arc<dyn ServerCertVerifier>);
+        let first = verifier(&key(b"shared test root"), made).unwrap();
+        let again = verifier(&key(b"shared test root"), || panic!("made twice")).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+
+        let other = verifier(&key(b"other test root"), made).unwrap();
+        assert!(!Arc::ptr_eq(&first, &other));
+
+        let failed = verifier(&key(b"failing test root"), || {
+            Err(crate::error::builder("bad roots"))
+        });
+        assert!(failed.is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Instrumentation with `tracing`, behind the `tracing` feature.
+//!
+//! Spans are emitted at the `DEBUG` level for a request, and nested in it,
+//! for DNS resolution, connecting, the TLS handshake, each authentication
+//! round-trip and reading the response body. Fields are named after the
+//! OpenTelemetry semantic conventions for HTTP clients.
+//!
+//! Without the feature, [`Span`] and [`Instrument`] do nothing and the
+//! macros below don't evaluate their fields, so call sites don't need a
+//! `cfg` of their own.
+
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::{Instrument, Span};
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use self::noop::{Instrument, Span};
+
+/// Create a `DEBUG` span, or a disabled one without the feature.
+macro_rules! trace_span {
+    ($name:literal $(, $($fields:tt)*)?) => {{
+        #[cfg(feature = "tracing")]
+        let span = ::tracing::debug_span!($name $(, $($fields)*)?);
+        #[cfg(not(feature = "tracing"))]
+        let span = $crate::trace::Span::none();
+        span
+    }};
+}
+
+/// Emit a `DEBUG` event, falling back to `log` without the feature.
+#[allow(unused_macros)]
+macro_rules! trace_debug {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!($($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        ::log::debug!($($arg)+);
+    }};
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) use self::enabled::*;
+#[cfg(all(feature = "__tls", feature = "tracing"))]
+pub(crate) use self::handshake::*;
+
+#[cfg(all(feature = "__tls", not(feature = "tracing")))]
+pub(crate) fn tls_handshake<S>(connector: S) -> S {
+    connector
+}
+
+#[cfg(feature = "tracing")]
+mod enabled {
+    use std::pin::Pin;
+    use std::task::{ready, Context, Poll};
+
+    use bytes::Bytes;
+    use http::Uri;
+    use http_body::{Body as HttpBody, Frame, SizeHint};
+    use pin_project_lite::pin_project;
+    use url::Url;
+
+    use super::Span;
+
+    /// The `url.full` of a request, without any credentials in it.
+    pub(crate) fn url_full(url: &Url) -> String {
+        if url.username().is_empty() && url.password().is_none() {
+            return url.as_str().to_owned();
+        }
+        let mut url = url.clone();
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        url.into()
+    }
+
+    /// The `server.port` of a URI, filling in the default of its scheme.
+    pub(crate) fn server_port(uri: &Uri) -> Option<u16> {
+        uri.port_u16().or(match uri.scheme_str() {
+            Some("https") => Some(443),
+            Some("http") => Some(80),
+            _ => None,
+        })
+    }
+
+    pin_project! {
+        /// A response body, read within a `body` span.
+        pub(crate) struct TracedBody<B> {
+            #[pin]
+            inner: B,
+            span: Span,
+            size: u64,
+        }
+    }
+
+    pub(crate) fn body<B>(body: B) -> TracedBody<B> {
+        TracedBody {
+            inner: body,
+            span: trace_span!("body", http.response.body.size = tracing::field::Empty),
+            size: 0,
+        }
+    }
+
+    impl<B> HttpBody for TracedBody<B>
+    where
+        B: HttpBody<Data = Bytes>,
+    {
+        type Data = Bytes;
+        type Error = B::Error;
+
+        fn poll_frame(
+            self: Pin<&mut Self>,
+            cx: &mut Context,
+        ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+            let this = self.project();
+            let _entered = this.span.enter();
+            let frame = ready!(this.inner.poll_frame(cx));
+            match frame {
+                Some(Ok(ref frame)) => {
+                    if let Some(data) = frame.data_ref() {
+                        *this.size += data.len() as u64;
+                    }
+                }
+                Some(Err(_)) => {}
+                None => {
+                    this.span.record("http.response.body.size", *this.size);
+                }
+            }
+            Poll::Ready(frame)
+        }
+
+        #[inline]
+        fn is_end_stream(&self) -> bool {
+            self.inner.is_end_stream()
+        }
+
+        #[inline]
+        fn size_hint(&self) -> SizeHint {
+            self.inner.size_hint()
+        }
+    }
+}
+
+#[cfg(all(feature = "__tls", feature = "tracing"))]
+mod handshake {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    use http::Uri;
+    use tower_service::Service;
+
+    use super::Span;
+
+    /// Wrap the connector an `HttpsConnector` establishes its connections
+    /// with, opening a `tls` span once a connection to an `https` URI is up.
+    ///
+    /// The handshake happens inside the `HttpsConnector`, so the span is
+    /// closed when it is dropped, right after it returns.
+    pub(crate) fn tls_handshake<S>(connector: S) -> TlsHandshake<S> {
+        TlsHandshake {
+            inner: connector,
+            span: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    #[derive(Clone)]
+    pub(crate) struct TlsHandshake<S> {
+        inner: S,
+        span: Arc<Mutex<Option<Span>>>,
+    }
+
+    impl<S> Service<Uri> for TlsHandshake<S>
+    where
+        S: Service<Uri>,
+        S::Future: Send + 'static,
+    {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.inner.poll_ready(cx)
+        }
+
+        fn call(&mut self, dst: Uri) -> Self::Future {
+            let https = dst.scheme() == Some(&http::uri::Scheme::HTTPS);
+            let host = dst.host().map(str::to_owned);
+            let slot = self.span.clone();
+            let connecting = self.inner.call(dst);
+            Box::pin(async move {
+                let io = connecting.await?;
+                if https {
+                    let span = trace_span!("tls", server.address = host.as_deref());
+                    *slot.lock().expect("tls span lock poisoned") = Some(span);
+                }
+                Ok(io)
+            })
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod noop {
+    /// Stands in for `tracing::Span` without the `tracing` feature.
+    #[derive(Clone, Debug)]
+    pub(crate) struct Span;
+
+    pub(crate) struct Entered;
+
+    impl Span {
+        pub(crate) fn none() -> Span {
+            Span
+        }
+
+        pub(crate) fn enter(&self) -> Entered {
+            Entered
+        }
+
+        pub(crate) fn record<V>(&self, _field: &str, _value: V) -> &Self {
+            self
+        }
+    }
+
+    /// Stands in for `tracing::Instrument` without the `tracing` feature.
+    pub(crate) trait Instrument: Sized {
+        fn instrument(self, _span: Span) -> Self {
+            self
+        }
+    }
+
+    impl<T> Instrument for T {}
+}
@@ -0,0 +1,198 @@
+//! Distributed trace context propagation.
+//!
+//! A client set up with [`ClientBuilder::trace_context()`] adds the
+//! [W3C Trace Context] `traceparent` and `tracestate` headers to the
+//! requests it sends, and optionally the [B3] `b3` header, so that the
+//! services it calls continue the trace of the caller.
+//!
+//! The context is looked up when a request is sent, through a function
+//! given to [`Propagation::new()`]. This is where the current span of
+//! OpenTelemetry, or of any other tracing system, is read:
+//!
+//! ```ignore
+//! use opentelemetry::trace::TraceContextExt;
+//! use reqwest::trace_context::{Propagation, TraceContext};
+//!
+//! let propagation = Propagation::new(|| {
+//!     let cx = opentelemetry::Context::current();
+//!     let span = cx.span().span_context().clone();
+//!     if !span.is_valid() {
+//!         return None;
+//!     }
+//!     let trace_id = u128::from_be_bytes(span.trace_id().to_bytes());
+//!     let span_id = u64::from_be_bytes(span.span_id().to_bytes());
+//!     Some(
+//!         TraceContext::new(trace_id, span_id)
+//!             .sampled(span.is_sampled())
+//!             .trace_state(span.trace_state().header()),
+//!     )
+//! });
+//! ```
+//!
+//! Headers already set on a request are left as they are.
+//!
+//! [`ClientBuilder::trace_context()`]: crate::ClientBuilder::trace_context
+//! [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+//! [B3]: https://github.com/openzipkin/b3-propagation
+
+use std::fmt;
+use std::sync::Arc;
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+
+const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+const B3: HeaderName = HeaderName::from_static("b3");
+
+/// The trace a request is part of, and the span it is sent from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    parent_id: u64,
+    sampled: bool,
+    trace_state: Option<String>,
+}
+
+impl TraceContext {
+    /// Create a sampled context, for the trace `trace_id` and the span
+    /// `parent_id`.
+    ///
+    /// No headers are added for a context with an ID of zero, which is
+    /// invalid.
+    pub fn new(trace_id: u128, parent_id: u64) -> TraceContext {
+        TraceContext {
+            trace_id,
+            parent_id,
+            sampled: true,
+            trace_state: None,
+        }
+    }
+
+    /// Set whether the caller records the trace.
+    ///
+    /// Default is `true`.
+    pub fn sampled(mut self, sampled: bool) -> TraceContext {
+        self.sampled = sampled;
+        self
+    }
+
+    /// Set the vendor-specific `tracestate` sent along, such as
+    /// `congo=t61rcWkgMzE`.
+    ///
+    /// An empty state, or one that isn't a valid header value, isn't sent.
+    pub fn trace_state(mut self, state: impl Into<String>) -> TraceContext {
+        self.trace_state = Some(state.into());
+        self
+    }
+
+    fn is_valid(&self) -> bool {
+        self.trace_id != 0 && self.parent_id != 0
+    }
+
+    fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id,
+            self.parent_id,
+            u8::from(self.sampled),
+        )
+    }
+
+    fn b3(&self) -> String {
+        format!(
+            "{:032x}-{:016x}-{}",
+            self.trace_id,
+            self.parent_id,
+            u8::from(self.sampled),
+        )
+    }
+}
+
+/// How a client finds the current trace context, and which headers it
+/// sends it in.
+#[derive(Clone)]
+pub struct Propagation {
+    current: Arc<dyn Fn() -> Option<TraceContext> + Send + Sync>,
+    b3: bool,
+}
+
+impl Propagation {
+    /// Propagate the context returned by `current`, called as each request
+    /// is sent. Nothing is added to requests sent while it returns `None`.
+    pub fn new<F>(current: F) -> Propagation
+    where
+        F: Fn() -> Option<TraceContext> + Send + Sync + 'static,
+    {
+        Propagation {
+            current: Arc::new(current),
+            b3: false,
+        }
+    }
+
+    /// Also send the context in the B3 single `b3` header, for services
+    /// traced with Zipkin.
+    ///
+    /// Default is `false`.
+    pub fn b3(mut self, enabled: bool) -> Propagation {
+        self.b3 = enabled;
+        self
+    }
+
+    pub(crate) fn inject(&self, headers: &mut HeaderMap) {
+        if headers.contains_key(TRACEPARENT) {
+            return;
+        }
+        let cx = match (self.current)() {
+            Some(cx) if cx.is_valid() => cx,
+            _ => return,
+        };
+
+        headers.insert(
+            TRACEPARENT,
+            HeaderValue::try_from(cx.traceparent()).expect("traceparent is a valid header value"),
+        );
+        if let Some(state) = cx.trace_state.as_deref().filter(|state| !state.is_empty()) {
+            if let Ok(state) = HeaderValue::try_from(state) {
+                headers.insert(TRACESTATE, state);
+            }
+        }
+        if self.b3 && !headers.contains_key(B3) {
+            headers.insert(
+                B3,
+                HeaderValue::try_from(cx.b3()).expect("b3 is a valid header value"),
+            );
+        }
+    }
+}
+
+impl fmt::Debug for Propagation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Propagation").field("b3", &self.b3).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_existing_headers_and_skips_invalid_contexts() {
+        let propagation = Propagation::new(|| Some(TraceContext::new(1, 2).sampled(false)));
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT, HeaderValue::from_static("set by the caller"));
+        propagation.inject(&mut headers);
+        assert_eq!(headers["traceparent"], "set by the caller");
+
+        let mut headers = HeaderMap::new();
+        propagation.inject(&mut headers);
+        assert_eq!(
+            headers["traceparent"],
+            "00-00000000000000000000000000000001-0000000000000002-00"
+        );
+        assert!(!headers.contains_key("b3"));
+
+        let mut headers = HeaderMap::new();
+        Propagation::new(|| Some(TraceContext::new(0, 2))).inject(&mut headers);
+        assert!(headers.is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::header::HeaderName;
 use crate::header::{Entry, HeaderMap, HeaderValue, OccupiedEntry};
 use std::fmt;
 
@@ -24,6 +26,88 @@ where
     header
 }
 
+/// The `Priority` header of RFC 9218.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const PRIORITY: HeaderName = HeaderName::from_static("priority");
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn priority(urgency: u8, incremental: bool) -> crate::Result<HeaderValue> {
+    if urgency > 7 {
+        return Err(crate::error::builder(
+            "priority urgency must be between 0 and 7",
+        ));
+    }
+    let value = if incremental {
+        format!("u={urgency}, i")
+    } else {
+        format!("u={urgency}")
+    };
+    Ok(HeaderValue::try_from(value).expect("priority is always a valid HeaderValue"))
+}
+
+/// What the values of sensitive headers are replaced with in logs.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// The headers carrying credentials, redacted by default in logs.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const SENSITIVE_HEADERS: [HeaderName; 6] = [
+    http::header::AUTHORIZATION,
+    http::header::PROXY_AUTHORIZATION,
+    http::header::WWW_AUTHENTICATE,
+    http::header::PROXY_AUTHENTICATE,
+    http::header::COOKIE,
+    http::header::SET_COOKIE,
+];
+
+/// Redact a header value, keeping the authentication scheme of the
+/// `Authorization` family of headers, such as `Negotiate [REDACTED]`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn redact_header(name: &HeaderName, value: &HeaderValue) -> String {
+    use http::header;
+
+    let scheme = match *name {
+        header::AUTHORIZATION
+        | header::PROXY_AUTHORIZATION
+        | header::WWW_AUTHENTICATE
+        | header::PROXY_AUTHENTICATE => value
+            .to_str()
+            .ok()
+            .and_then(|value| value.split_once(' '))
+            .map(|(scheme, _)| scheme),
+        _ => None,
+    };
+    match scheme {
+        Some(scheme) => format!("{scheme} {REDACTED}"),
+        None => REDACTED.to_owned(),
+    }
+}
+
+/// The current wall-clock time, moved along with Tokio's clock.
+///
+/// When time is paused with `tokio::time::pause()`, this is shifted by how
+/// far Tokio's clock is from the real one, so that `advance()` also ages
+/// cached responses and `Retry-After` dates.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now() -> std::time::SystemTime {
+    let tokio_now = tokio::time::Instant::now().into_std();
+    let std_now = std::time::Instant::now();
+    let wall = std::time::SystemTime::now();
+    match tokio_now.checked_duration_since(std_now) {
+        Some(ahead) => wall + ahead,
+        None => wall - std_now.duration_since(tokio_now),
+    }
+}
+
+/// A new name for a temporary file next to `path`, to be renamed to `path`
+/// once it has been written.
+#[cfg(any(feature = "stream", feature = "blocking"))]
+pub(crate) fn temp_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{:016x}.tmp", fast_random()));
+    tmp.into()
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) fn fast_random() -> u64 {
     use std::cell::Cell;
@@ -0,0 +1,601 @@
+//! Record and replay HTTP interactions.
+//!
+//! A [`Cassette`] saves the requests a client sends and the responses it
+//! receives to a JSON file, and answers the same requests from that file
+//! later, without touching the network. Tests can then exercise an exchange
+//! captured once against a real server, such as the rounds of a
+//! `Negotiate` or NTLM authentication with IIS, deterministically and
+//! offline.
+//!
+//! A cassette is added to a client with [`ClientBuilder::cassette()`]. It
+//! sees each request as it is sent by the client, before redirects are
+//! followed and retries made: the response recorded is the one the request
+//! ended up with. Each authentication round is a request of its own, and
+//! is recorded as such.
+//!
+//! # Matching
+//!
+//! A request is answered by the first interaction of the cassette with the
+//! same method and URL not used yet. Interactions are replayed in the order
+//! they were recorded, so the same request can get different responses,
+//! such as a `401` before a `200`. The values of more headers, and the
+//! body, can be required to match with [`Cassette::match_header()`] and
+//! [`Cassette::match_body()`]. A request matching no interaction fails.
+//!
+//! # Redaction
+//!
+//! The values of the `Authorization`, `Proxy-Authorization` and `Cookie`
+//! request headers are replaced with `[REDACTED]` before being saved. The
+//! authentication scheme, such as `Negotiate` or `Basic`, is kept.
+//! Responses are saved as they are, for the challenges and cookies in them
+//! to be replayed. More headers can be redacted with
+//! [`Cassette::redact_header()`], and secrets anywhere in URLs, headers and
+//! bodies with [`Cassette::redact_text()`]. Requests being replayed are
+//! redacted the same way before being matched.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use reqwest::vcr::Cassette;
+//!
+//! // Records into the file on the first run, replays it afterwards.
+//! let cassette = Cassette::new("tests/cassettes/login.json")?
+//!     .redact_text("hunter2", "PASSWORD");
+//! let client = reqwest::Client::builder().cassette(cassette).build()?;
+//!
+//! client
+//!     .post("https://intranet.example/login")
+//!     .body("user=alice&password=hunter2")
+//!     .send()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ClientBuilder::cassette()`]: crate::ClientBuilder::cassette
+
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use http::{Method, StatusCode, Uri};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tower::{Layer, Service};
+use tower_http::follow_redirect::RequestUri;
+
+use crate::async_impl::Body;
+use crate::error::BoxError;
+use crate::util::redact_header;
+
+/// Interactions recorded to, or replayed from, a file.
+///
+/// Clones of a `Cassette` share the interactions and the file.
+#[derive(Clone)]
+pub struct Cassette {
+    state: Arc<Mutex<State>>,
+    rules: Arc<Rules>,
+}
+
+struct State {
+    path: PathBuf,
+    mode: Mode,
+    interactions: Vec<Interaction>,
+    used: Vec<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+#[derive(Clone)]
+struct Rules {
+    headers: Vec<HeaderName>,
+    texts: Vec<(String, String)>,
+    match_headers: Vec<HeaderName>,
+    match_body: bool,
+}
+
+struct Interaction {
+    request: Recorded,
+    response: Recorded,
+}
+
+/// A request or a response, as saved.
+#[derive(Clone)]
+struct Recorded {
+    // The method of a request, the status of a response.
+    line: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Bytes,
+}
+
+impl Cassette {
+    /// Replay the interactions saved in `path` if the file exists, record
+    /// them into it otherwise.
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Cassette> {
+        let path = path.into();
+        if path.exists() {
+            Cassette::replay(path)
+        } else {
+            Ok(Cassette::record(path))
+        }
+    }
+
+    /// Send requests, and save them with their responses to `path`.
+    ///
+    /// The file is replaced, and written again after each interaction.
+    pub fn record(path: impl Into<PathBuf>) -> Cassette {
+        Cassette::with_state(State {
+            path: path.into(),
+            mode: Mode::Record,
+            interactions: Vec::new(),
+            used: Vec::new(),
+        })
+    }
+
+    /// Answer requests with the interactions saved in `path`, never sending
+    /// them.
+    pub fn replay(path: impl Into<PathBuf>) -> io::Result<Cassette> {
+        let path = path.into();
+        let interactions = load(&path)?;
+        Ok(Cassette::with_state(State {
+            path,
+            mode: Mode::Replay,
+            used: vec![false; interactions.len()],
+            interactions,
+        }))
+    }
+
+    fn with_state(state: State) -> Cassette {
+        Cassette {
+            state: Arc::new(Mutex::new(state)),
+            rules: Arc::new(Rules {
+                headers: vec![
+                    header::AUTHORIZATION,
+                    header::PROXY_AUTHORIZATION,
+                    header::COOKIE,
+                ],
+                texts: Vec::new(),
+                match_headers: Vec::new(),
+                match_body: false,
+            }),
+        }
+    }
+
+    /// Replace the value of the header `name` with `[REDACTED]`, in requests
+    /// and responses.
+    pub fn redact_header(mut self, name: HeaderName) -> Cassette {
+        Arc::make_mut(&mut self.rules).headers.push(name);
+        self
+    }
+
+    /// Replace `secret` with `placeholder` wherever it appears: in URLs,
+    /// header values and text bodies, of requests and responses.
+    pub fn redact_text(
+        mut self,
+        secret: impl Into<String>,
+        placeholder: impl Into<String>,
+    ) -> Cassette {
+        let secret = secret.into();
+        if !secret.is_empty() {
+            Arc::make_mut(&mut self.rules)
+                .texts
+                .push((secret, placeholder.into()));
+        }
+        self
+    }
+
+    /// Only replay an interaction for a request with the same values of the
+    /// header `name`, once redacted.
+    pub fn match_header(mut self, name: HeaderName) -> Cassette {
+        Arc::make_mut(&mut self.rules).match_headers.push(name);
+        self
+    }
+
+    /// Only replay an interaction for a request with the same body, once
+    /// redacted.
+    ///
+    /// Default is `false`.
+    pub fn match_body(mut self, enabled: bool) -> Cassette {
+        Arc::make_mut(&mut self.rules).match_body = enabled;
+        self
+    }
+
+    /// Returns `true` if requests are answered from the file rather than
+    /// sent.
+    pub fn is_replaying(&self) -> bool {
+        self.state.lock().expect("cassette lock poisoned").mode == Mode::Replay
+    }
+
+    /// Returns the number of interactions recorded or loaded.
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .expect("cassette lock poisoned")
+            .interactions
+            .len()
+    }
+
+    /// Returns `true` if the cassette has no interactions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if every interaction loaded has been replayed.
+    ///
+    /// Tests can check it to make sure the client still makes all the
+    /// requests it was recorded making.
+    pub fn is_exhausted(&self) -> bool {
+        let state = self.state.lock().expect("cassette lock poisoned");
+        state.used.iter().all(|used| *used)
+    }
+
+    fn push(&self, interaction: Interaction) {
+        let mut state = self.state.lock().expect("cassette lock poisoned");
+        state.interactions.push(interaction);
+        state.used.push(true);
+        if let Err(err) = save(&state.path, &state.interactions) {
+            log::warn!("failed to save cassette {}: {err}", state.path.display());
+        }
+    }
+
+    /// Take the first unused interaction matching `request`.
+    fn take(&self, request: &Recorded) -> Option<Recorded> {
+        let mut state = self.state.lock().expect("cassette lock poisoned");
+        let state = &mut *state;
+        let found =
+            state
+                .interactions
+                .iter()
+                .zip(&state.used)
+                .position(|(interaction, used)| {
+                    !used && self.rules.matches(&interaction.request, request)
+                })?;
+        state.used[found] = true;
+        Some(state.interactions[found].response.clone())
+    }
+}
+
+impl fmt::Debug for Cassette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock().expect("cassette lock poisoned");
+        f.debug_struct("Cassette")
+            .field("path", &state.path)
+            .field("mode", &state.mode)
+            .field("interactions", &state.interactions.len())
+            .finish()
+    }
+}
+
+impl Rules {
+    fn text(&self, text: &str) -> String {
+        self.texts
+            .iter()
+            .fold(text.to_owned(), |text, (secret, placeholder)| {
+                text.replace(secret.as_str(), placeholder)
+            })
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.headers.contains(name) {
+                    redact_header(name, value)
+                } else {
+                    self.text(&String::from_utf8_lossy(value.as_bytes()))
+                };
+                (name.as_str().to_owned(), value)
+            })
+            .collect()
+    }
+
+    fn body(&self, body: Bytes) -> Bytes {
+        if self.texts.is_empty() {
+            return body;
+        }
+        match std::str::from_utf8(&body) {
+            Ok(text) => Bytes::from(self.text(text)),
+            Err(_) => body,
+        }
+    }
+
+    fn request(&self, method: &Method, uri: &Uri, headers: &HeaderMap, body: Bytes) -> Recorded {
+        Recorded {
+            line: method.as_str().to_owned(),
+            url: self.text(&uri.to_string()),
+            headers: self.headers(headers),
+            body: self.body(body),
+        }
+    }
+
+    fn matches(&self, recorded: &Recorded, request: &Recorded) -> bool {
+        recorded.line == request.line
+            && recorded.url == request.url
+            && self
+                .match_headers
+                .iter()
+                .all(|name| recorded.values(name) == request.values(name))
+            && (!self.match_body || recorded.body == request.body)
+    }
+}
+
+impl Recorded {
+    fn values(&self, name: &HeaderName) -> Vec<&str> {
+        self.headers
+            .iter()
+            .filter(|(n, _)| n.eq_ignore_ascii_case(name.as_str()))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    fn to_json(&self, key: &str) -> Value {
+        let line = match self.line.parse::<u16>() {
+            Ok(status) if key == "status" => Value::from(status),
+            _ => Value::from(self.line.as_str()),
+        };
+        let body = match std::str::from_utf8(&self.body) {
+            Ok(text) => Value::from(text),
+            Err(_) => Value::from(self.body.to_vec()),
+        };
+        json!({
+            key: line,
+            "url": self.url,
+            "headers": self.headers,
+            "body": body,
+        })
+    }
+
+    fn from_json(value: &Value, line: &str) -> Option<Recorded> {
+        let line = match &value[line] {
+            Value::String(method) => method.clone(),
+            Value::Number(status) => status.to_string(),
+            _ => return None,
+        };
+        let headers = value["headers"]
+            .as_array()?
+            .iter()
+            .map(|pair| {
+                Some((
+                    pair.get(0)?.as_str()?.to_owned(),
+                    pair.get(1)?.as_str()?.to_owned(),
+                ))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let body = match &value["body"] {
+            Value::String(text) => Bytes::from(text.clone()),
+            Value::Array(bytes) => bytes
+                .iter()
+                .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect::<Option<Vec<u8>>>()?
+                .into(),
+            _ => return None,
+        };
+        Some(Recorded {
+            line,
+            url: value["url"].as_str()?.to_owned(),
+            headers,
+            body,
+        })
+    }
+
+    fn into_response(self) -> Result<http::Response<Body>, BoxError> {
+        let mut res = http::Response::new(Body::from(self.body));
+        *res.status_mut() = StatusCode::from_bytes(self.line.as_bytes())?;
+        for (name, value) in self.headers {
+            res.headers_mut().append(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::try_from(value)?,
+            );
+        }
+        let uri = self.url.parse::<Uri>()?;
+        res.extensions_mut().insert(RequestUri(uri));
+        Ok(res)
+    }
+}
+
+fn load(path: &Path) -> io::Result<Vec<Interaction>> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid cassette {}", path.display()),
+        )
+    };
+    let value: Value = serde_json::from_slice(&fs::read(path)?)?;
+    value["interactions"]
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|interaction| {
+            Some(Interaction {
+                request: Recorded::from_json(&interaction["request"], "method")?,
+                response: Recorded::from_json(&interaction["response"], "status")?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(invalid)
+}
+
+fn save(path: &Path, interactions: &[Interaction]) -> io::Result<()> {
+    let interactions = interactions
+        .iter()
+        .map(|interaction| {
+            json!({
+                "request": interaction.request.to_json("method"),
+                "response": interaction.response.to_json("status"),
+            })
+        })
+        .collect::<Vec<_>>();
+    let cassette = json!({
+        "version": 1,
+        "interactions": interactions,
+    });
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(&cassette).expect("cassette is valid json");
+    fs::write(path, json)
+}
+
+pub(crate) struct CassetteLayer(pub(crate) Cassette);
+
+impl<S> Layer<S> for CassetteLayer {
+    type Service = CassetteService<S>;
+
+    fn layer(&self, inner: S) -> CassetteService<S> {
+        CassetteService {
+            inner,
+            cassette: self.0.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct CassetteService<S> {
+    inner: S,
+    cassette: Cassette,
+}
+
+impl<S> Service<http::Request<Body>> for CassetteService<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>, Error = BoxError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = http::Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        // The ready service is taken, leaving a clone in its place.
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        let cassette = self.cassette.clone();
+        if cassette.is_replaying() {
+            Box::pin(replay(cassette, req))
+        } else {
+            Box::pin(record(cassette, inner, req))
+        }
+    }
+}
+
+async fn replay(
+    cassette: Cassette,
+    req: http::Request<Body>,
+) -> Result<http::Response<Body>, BoxError> {
+    let (parts, body) = req.into_parts();
+    let body = BodyExt::collect(body).await?.to_bytes();
+    let request = cassette
+        .rules
+        .request(&parts.method, &parts.uri, &parts.headers, body);
+    match cassette.take(&request) {
+        Some(response) => response.into_response(),
+        None => Err(format!(
+            "no recorded interaction matches {} {}",
+            parts.method, parts.uri
+        )
+        .into()),
+    }
+}
+
+async fn record<S>(
+    cassette: Cassette,
+    mut inner: S,
+    req: http::Request<Body>,
+) -> Result<http::Response<Body>, BoxError>
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>, Error = BoxError>,
+{
+    let (parts, body) = req.into_parts();
+    let body = BodyExt::collect(body).await?.to_bytes();
+    let request = cassette
+        .rules
+        .request(&parts.method, &parts.uri, &parts.headers, body.clone());
+    let res = inner
+        .call(http::Request::from_parts(parts, Body::from(body)))
+        .await?;
+
+    let (parts, body) = res.into_parts();
+    let body = BodyExt::collect(body).await?.to_bytes();
+    let url = match parts.extensions.get::<RequestUri>() {
+        Some(uri) => cassette.rules.text(&uri.0.to_string()),
+        None => request.url.clone(),
+    };
+    cassette.push(Interaction {
+        response: Recorded {
+            line: parts.status.as_str().to_owned(),
+            url,
+            headers: cassette.rules.headers(&parts.headers),
+            body: cassette.rules.body(body.clone()),
+        },
+        request,
+    });
+    Ok(http::Response::from_parts(parts, Body::from(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_and_matches() {
+        let rules = Cassette::record("unused")
+            .redact_text("s3cret", "SECRET")
+            .match_header(header::ACCEPT)
+            .rules;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            "Basic dXNlcjpzM2NyZXQ=".parse().unwrap(),
+        );
+        headers.insert(header::ACCEPT, "text/plain".parse().unwrap());
+        let uri = "http://api.example/?token=s3cret".parse().unwrap();
+        let recorded = rules.request(&Method::GET, &uri, &headers, Bytes::from("s3cret"));
+
+        assert_eq!(recorded.url, "http://api.example/?token=SECRET");
+        assert_eq!(
+            recorded.values(&header::AUTHORIZATION),
+            ["Basic [REDACTED]"]
+        );
+        assert_eq!(recorded.body, "SECRET");
+        assert!(rules.matches(&recorded, &recorded.clone()));
+
+        headers.insert(header::ACCEPT, "text/html".parse().unwrap());
+        let other = rules.request(&Method::GET, &uri, &headers, Bytes::new());
+        assert!(!rules.matches(&recorded, &other));
+    }
+
+    #[test]
+    fn round_trips_binary_bodies() {
+        let recorded = Recorded {
+            line: "200".into(),
+            url: "http://api.example/".into(),
+            headers: vec![("www-authenticate".into(), "Negotiate".into())],
+            body: Bytes::from_static(&[0xff, 0x00]),
+        };
+        let json = recorded.to_json("status");
+        assert_eq!(json["status"], 200);
+        let parsed = Recorded::from_json(&json, "status").unwrap();
+        assert_eq!(parsed.line, "200");
+        assert_eq!(parsed.body, recorded.body);
+        assert_eq!(parsed.headers, recorded.headers);
+    }
+}
@@ -2,7 +2,11 @@
 use super::multipart::Form;
 /// dox
 use bytes::Bytes;
+#[cfg(feature = "stream")]
+use futures_util::stream::{Stream, StreamExt, TryStreamExt};
 use js_sys::Uint8Array;
+#[cfg(feature = "stream")]
+use std::pin::Pin;
 use std::{borrow::Cow, fmt};
 use wasm_bindgen::JsValue;
 
@@ -22,8 +26,17 @@ enum Inner {
     /// MultipartForm holds a multipart/form-data body.
     #[cfg(feature = "multipart")]
     MultipartForm(Form),
+    /// Stream holds a body that is sent as it is produced.
+    #[cfg(feature = "stream")]
+    Stream(BodyStream),
 }
 
+#[cfg(feature = "stream")]
+type BoxStream = Pin<Box<dyn Stream<Item = Result<Bytes, crate::error::BoxError>> + Send>>;
+
+#[cfg(feature = "stream")]
+type BodyStream = sync_wrapper::SyncStream<BoxStream>;
+
 #[derive(Clone)]
 pub(crate) enum Single {
     Bytes(Bytes),
@@ -59,15 +72,44 @@ impl Single {
 }
 
 impl Body {
+    /// Wrap a futures `Stream` in a box inside `Body`.
+    ///
+    /// Where the browser supports streaming request bodies, the stream is
+    /// sent as a `ReadableStream` while it is read. Otherwise, it is read
+    /// to the end and sent once complete.
+    ///
+    /// Browsers only stream request bodies over HTTP/2 or later, and always
+    /// send a CORS preflight for them.
+    ///
+    /// # Optional
+    ///
+    /// This requires the `stream` feature to be enabled.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub fn wrap_stream<S>(stream: S) -> Body
+    where
+        S: futures_core::stream::TryStream + Send + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        Bytes: From<S::Ok>,
+    {
+        let stream: BoxStream = Box::pin(stream.map_ok(Bytes::from).map_err(Into::into));
+        Body {
+            inner: Inner::Stream(sync_wrapper::SyncStream::new(stream)),
+        }
+    }
+
     /// Returns a reference to the internal data of the `Body`.
     ///
-    /// `None` is returned, if the underlying data is a multipart form.
+    /// `None` is returned, if the underlying data is a multipart form or a
+    /// stream.
     #[inline]
     pub fn as_bytes(&self) -> Option<&[u8]> {
         match &self.inner {
             Inner::Single(single) => Some(single.as_bytes()),
             #[cfg(feature = "multipart")]
             Inner::MultipartForm(_) => None,
+            #[cfg(feature = "stream")]
+            Inner::Stream(_) => None,
         }
     }
 
@@ -80,14 +122,57 @@ impl Body {
                 let js_value: &JsValue = form_data.as_ref();
                 Ok(js_value.to_owned())
             }
+            #[cfg(feature = "stream")]
+            Inner::Stream(_) => Err(crate::error::builder(
+                "a stream body can only be sent as a request body",
+            )),
         }
     }
 
+    /// Sets this body on `init`, streaming it if it is a stream and the
+    /// browser can.
+    // Can use new methods in web-sys when requiring v0.2.93.
+    #[allow(deprecated)]
+    pub(crate) async fn set_on(self, init: &mut web_sys::RequestInit) -> crate::Result<()> {
+        #[cfg(feature = "stream")]
+        if let Inner::Stream(stream) = self.inner {
+            let stream = stream.into_inner();
+            if supports_request_streams() {
+                let stream = stream.map(|chunk| match chunk {
+                    Ok(chunk) => Ok(Uint8Array::from(&chunk[..]).into()),
+                    Err(err) => Err(js_sys::Error::new(&err.to_string()).into()),
+                });
+                let readable: JsValue = wasm_streams::ReadableStream::from_stream(stream)
+                    .into_raw()
+                    .into();
+                init.body(Some(&readable));
+                js_sys::Reflect::set(init, &"duplex".into(), &"half".into())
+                    .map_err(crate::error::wasm)
+                    .map_err(crate::error::builder)?;
+            } else {
+                let buf = stream
+                    .try_fold(Vec::new(), |mut buf, chunk| async move {
+                        buf.extend_from_slice(&chunk);
+                        Ok(buf)
+                    })
+                    .await
+                    .map_err(crate::error::body)?;
+                init.body(Some(&Uint8Array::from(&buf[..]).into()));
+            }
+            return Ok(());
+        }
+
+        init.body(Some(&self.to_js_value()?));
+        Ok(())
+    }
+
     #[cfg(feature = "multipart")]
     pub(crate) fn as_single(&self) -> Option<&Single> {
         match &self.inner {
             Inner::Single(single) => Some(single),
             Inner::MultipartForm(_) => None,
+            #[cfg(feature = "stream")]
+            Inner::Stream(_) => None,
         }
     }
 
@@ -109,6 +194,10 @@ impl Body {
             Inner::MultipartForm(form) => Self {
                 inner: Inner::MultipartForm(form),
             },
+            #[cfg(feature = "stream")]
+            Inner::Stream(stream) => Self {
+                inner: Inner::Stream(stream),
+            },
         }
     }
 
@@ -117,6 +206,8 @@ impl Body {
             Inner::Single(single) => single.is_empty(),
             #[cfg(feature = "multipart")]
             Inner::MultipartForm(form) => form.is_empty(),
+            #[cfg(feature = "stream")]
+            Inner::Stream(_) => false,
         }
     }
 
@@ -127,10 +218,60 @@ impl Body {
             }),
             #[cfg(feature = "multipart")]
             Inner::MultipartForm(_) => None,
+            #[cfg(feature = "stream")]
+            Inner::Stream(_) => None,
         }
     }
 }
 
+/// Whether `fetch()` can send a `ReadableStream` as a request body.
+///
+/// A browser that can reads the `duplex` option, and doesn't turn the
+/// stream into the text `[object ReadableStream]`, with a `Content-Type`.
+#[cfg(feature = "stream")]
+fn supports_request_streams() -> bool {
+    thread_local! {
+        static SUPPORTED: bool = detect_request_streams();
+    }
+    SUPPORTED.with(|supported| *supported)
+}
+
+#[cfg(feature = "stream")]
+#[allow(deprecated)]
+fn detect_request_streams() -> bool {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use wasm_bindgen::closure::Closure;
+
+    let read = Rc::new(Cell::new(false));
+    let getter = {
+        let read = read.clone();
+        Closure::<dyn Fn() -> JsValue>::new(move || {
+            read.set(true);
+            JsValue::from_str("half")
+        })
+    };
+    let duplex = js_sys::Object::new();
+    if js_sys::Reflect::set(&duplex, &"get".into(), getter.as_ref()).is_err() {
+        return false;
+    }
+
+    let mut init = web_sys::RequestInit::new();
+    init.method("POST");
+    js_sys::Object::define_property(&init, &"duplex".into(), &duplex);
+    let empty: JsValue = wasm_streams::ReadableStream::from_stream(futures_util::stream::empty::<
+        Result<JsValue, JsValue>,
+    >())
+    .into_raw()
+    .into();
+    init.body(Some(&empty));
+
+    match web_sys::Request::new_with_str_and_init("", &init) {
+        Ok(req) => read.get() && !req.headers().has("content-type").unwrap_or(true),
+        Err(_) => false,
+    }
+}
+
 impl From<Bytes> for Body {
     #[inline]
     fn from(bytes: Bytes) -> Body {
@@ -262,6 +403,33 @@ mod tests {
         assert_eq!(text.as_string().expect("text is not a string"), body_value);
     }
 
+    #[cfg(feature = "stream")]
+    #[wasm_bindgen_test]
+    async fn test_body_js_stream() {
+        let chunks: Vec<Result<_, std::io::Error>> = vec![Ok("hello"), Ok(" "), Ok("world")];
+        let body = Body::wrap_stream(futures_util::stream::iter(chunks));
+        assert!(body.as_bytes().is_none());
+        assert!(body.try_clone().is_none());
+
+        let mut init = web_sys::RequestInit::new();
+        init.method("POST");
+        body.set_on(&mut init)
+            .await
+            .expect("could not set stream body");
+
+        let js_req = web_sys::Request::new_with_str_and_init("", &init)
+            .expect("could not create JS request");
+        let text_promise = js_req.text().expect("could not get text promise");
+        let text = crate::wasm::promise::<JsValue>(text_promise)
+            .await
+            .expect("could not get request body as text");
+
+        assert_eq!(
+            text.as_string().expect("text is not a string"),
+            "hello world"
+        );
+    }
+
     #[wasm_bindgen_test]
     async fn test_body_js_static_u8_slice() {
         let body_value: &'static [u8] = b"\x00\x42";
@@ -2,6 +2,7 @@ use http::header::USER_AGENT;
 use http::{HeaderMap, HeaderValue, Method};
 use js_sys::Promise;
 use std::convert::TryInto;
+use std::time::Duration;
 use std::{fmt, future::Future, sync::Arc};
 use url::Url;
 use wasm_bindgen::prelude::{wasm_bindgen, UnwrapThrowExt as _};
@@ -163,6 +164,9 @@ impl Client {
         mut req: Request,
     ) -> impl Future<Output = crate::Result<Response>> {
         self.merge_headers(&mut req);
+        if req.timeout().is_none() {
+            *req.timeout_mut() = self.config.timeout;
+        }
         fetch(req)
     }
 }
@@ -193,7 +197,7 @@ impl fmt::Debug for ClientBuilder {
 // > `init.method(m)` to `init.set_method(m)`
 // For now, ignore their deprecation.
 #[allow(deprecated)]
-async fn fetch(req: Request) -> crate::Result<Response> {
+async fn fetch(mut req: Request) -> crate::Result<Response> {
     // Build the js Request
     let mut init = web_sys::RequestInit::new();
     init.method(req.method().as_str());
@@ -227,9 +231,13 @@ async fn fetch(req: Request) -> crate::Result<Response> {
         init.set_cache(cache);
     }
 
-    if let Some(body) = req.body() {
+    if let Some(redirect) = req.redirect {
+        init.redirect(redirect);
+    }
+
+    if let Some(body) = req.body_mut().take() {
         if !body.is_empty() {
-            init.body(Some(body.to_js_value()?.as_ref()));
+            body.set_on(&mut init).await?;
         }
     }
 
@@ -247,15 +255,18 @@ async fn fetch(req: Request) -> crate::Result<Response> {
     let p = js_fetch(&js_req);
     let js_resp = super::promise::<web_sys::Response>(p)
         .await
-        .map_err(|error| {
-            if error.to_string() == "JsValue(\"reqwest::errors::TimedOut\")" {
-                crate::error::TimedOut.into()
-            } else {
-                error
-            }
-        })
+        .map_err(super::map_timeout)
         .map_err(crate::error::request)?;
 
+    // With the 'manual' redirect mode, the browser hides the redirect
+    // response behind an opaque one, which has no status to report.
+    if js_resp.type_() == web_sys::ResponseType::Opaqueredirect {
+        return Err(crate::error::redirect(
+            "redirect response is opaque in 'manual' redirect mode",
+            req.url().clone(),
+        ));
+    }
+
     // Convert from the js Response
     let mut resp = http::Response::builder().status(js_resp.status());
 
@@ -332,6 +343,18 @@ impl ClientBuilder {
         }
         self
     }
+
+    /// Enables a total request timeout.
+    ///
+    /// The timeout is applied from when the request is sent until the
+    /// response body has finished, by aborting the fetch. A timeout set
+    /// with `RequestBuilder::timeout()` takes precedence.
+    ///
+    /// Default is no timeout.
+    pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.timeout = Some(timeout);
+        self
+    }
 }
 
 impl Default for ClientBuilder {
@@ -343,6 +366,7 @@ impl Default for ClientBuilder {
 #[derive(Debug)]
 struct Config {
     headers: HeaderMap,
+    timeout: Option<Duration>,
     error: Option<crate::Error>,
 }
 
@@ -350,6 +374,7 @@ impl Default for Config {
     fn default() -> Config {
         Config {
             headers: HeaderMap::new(),
+            timeout: None,
             error: None,
         }
     }
@@ -358,6 +383,10 @@ impl Default for Config {
 impl Config {
     fn fmt_fields(&self, f: &mut fmt::DebugStruct<'_, '_>) {
         f.field("default_headers", &self.headers);
+
+        if let Some(ref d) = self.timeout {
+            f.field("timeout", d);
+        }
     }
 }
 
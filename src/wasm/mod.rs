@@ -41,6 +41,19 @@ where
         .map_err(|_js_val| "promise resolved to unexpected type".into())
 }
 
+/// The reason a fetch is aborted with when its timeout expires.
+const TIMED_OUT: &str = "reqwest::errors::TimedOut";
+
+/// Turns the error of a fetch that was aborted by its timeout into
+/// `TimedOut`, so that `Error::is_timeout()` sees it.
+fn map_timeout(error: crate::error::BoxError) -> crate::error::BoxError {
+    if error.to_string() == format!("JsValue(\"{TIMED_OUT}\")") {
+        crate::error::TimedOut.into()
+    } else {
+        error
+    }
+}
+
 /// A guard that cancels a fetch request when dropped.
 struct AbortGuard {
     ctrl: AbortController,
@@ -63,11 +76,10 @@ impl AbortGuard {
 
     fn timeout(&mut self, timeout: Duration) {
         let ctrl = self.ctrl.clone();
-        let abort =
-            Closure::once(move || ctrl.abort_with_reason(&"reqwest::errors::TimedOut".into()));
+        let abort = Closure::once(move || ctrl.abort_with_reason(&TIMED_OUT.into()));
         let timeout = set_timeout(
             abort.as_ref().unchecked_ref::<js_sys::Function>(),
-            timeout.as_millis().try_into().expect("timeout"),
+            timeout.as_millis().try_into().unwrap_or(i32::MAX),
         );
         if let Some((id, _)) = self.timeout.replace((timeout, abort)) {
             clear_timeout(id);
@@ -186,10 +186,9 @@ impl Part {
         name: &str,
         form: &web_sys::FormData,
     ) -> Result<(), wasm_bindgen::JsValue> {
-        let single = self
-            .value
-            .as_single()
-            .expect("A part's body can't be multipart itself");
+        let single = self.value.as_single().ok_or_else(|| {
+            wasm_bindgen::JsValue::from_str("A part's body can't be multipart or a stream")
+        })?;
 
         let mut mime_type = self.metadata().mime.as_ref();
 
@@ -226,7 +225,9 @@ impl Part {
         let js_value = self
             .value
             .as_single()
-            .expect("A part's body can't be set to a multipart body")
+            .ok_or_else(|| {
+                crate::error::builder("A part's body can't be set to a multipart or stream body")
+            })?
             .to_js_value();
 
         let body_array = js_sys::Array::new();
@@ -9,7 +9,7 @@ use serde::Serialize;
 #[cfg(feature = "json")]
 use serde_json;
 use url::Url;
-use web_sys::{RequestCache, RequestCredentials};
+use web_sys::{RequestCache, RequestCredentials, RequestRedirect};
 
 use super::{Body, Client, Response};
 #[cfg(any(feature = "form", feature = "json"))]
@@ -26,6 +26,7 @@ pub struct Request {
     pub(super) cors: bool,
     pub(super) credentials: Option<RequestCredentials>,
     pub(super) cache: Option<RequestCache>,
+    pub(super) redirect: Option<RequestRedirect>,
 }
 
 /// A builder to construct the properties of a `Request`.
@@ -47,6 +48,7 @@ impl Request {
             cors: true,
             credentials: None,
             cache: None,
+            redirect: None,
         }
     }
 
@@ -128,6 +130,7 @@ impl Request {
             cors: self.cors,
             credentials: self.credentials,
             cache: self.cache,
+            redirect: self.redirect,
         })
     }
 }
@@ -191,6 +194,48 @@ impl RequestBuilder {
         self
     }
 
+    /// Modify the query string of the URL, with control over how arrays,
+    /// `None`s and existing parameters are handled.
+    ///
+    /// With default [`Options`][crate::query::Options], this is the same as
+    /// [`query()`][Self::query], except that sequences of values are
+    /// accepted as well.
+    ///
+    /// ```rust
+    /// # fn run() -> Result<(), reqwest::Error> {
+    /// use reqwest::query::{ArrayFormat, Options};
+    ///
+    /// let req = reqwest::Client::new()
+    ///     .get("https://example.com/items")
+    ///     .query_with(&[("id", [1, 2])], Options::new().arrays(ArrayFormat::Brackets))
+    ///     .build()?;
+    /// assert_eq!(req.url().query(), Some("id%5B%5D=1&id%5B%5D=2"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `query` feature to be enabled.
+    ///
+    /// # Errors
+    /// This method will fail if the object you provide cannot be serialized
+    /// into a query string, such as a nested map.
+    #[cfg(feature = "query")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "query")))]
+    pub fn query_with<T: Serialize + ?Sized>(
+        mut self,
+        query: &T,
+        options: crate::query::Options,
+    ) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            if let Err(err) = crate::query::apply(req.url_mut(), query, options) {
+                self.request = Err(err);
+            }
+        }
+        self
+    }
+
     /// Send a form body.
     ///
     /// Sets the body to the url encoded serialization of the passed value,
@@ -490,6 +535,57 @@ impl RequestBuilder {
         self
     }
 
+    /// Set fetch redirect mode to 'follow'.
+    ///
+    /// # WASM
+    ///
+    /// This option is only effective with WebAssembly target.
+    ///
+    /// The [request redirect][mdn] will be set to 'follow'.
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/Request/redirect
+    pub fn fetch_redirect_follow(mut self) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.redirect = Some(RequestRedirect::Follow);
+        }
+        self
+    }
+
+    /// Set fetch redirect mode to 'error'.
+    ///
+    /// # WASM
+    ///
+    /// This option is only effective with WebAssembly target.
+    ///
+    /// The [request redirect][mdn] will be set to 'error', so a redirect
+    /// response fails the request.
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/Request/redirect
+    pub fn fetch_redirect_error(mut self) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.redirect = Some(RequestRedirect::Error);
+        }
+        self
+    }
+
+    /// Set fetch redirect mode to 'manual'.
+    ///
+    /// # WASM
+    ///
+    /// This option is only effective with WebAssembly target.
+    ///
+    /// The [request redirect][mdn] will be set to 'manual'. Browsers don't
+    /// expose the redirect response itself, so a redirect is returned as an
+    /// error for which `Error::is_redirect()` is true.
+    ///
+    /// [mdn]: https://developer.mozilla.org/en-US/docs/Web/API/Request/redirect
+    pub fn fetch_redirect_manual(mut self) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.redirect = Some(RequestRedirect::Manual);
+        }
+        self
+    }
+
     /// Build a `Request`, which can be inspected, modified and executed with
     /// `Client::execute()`.
     pub fn build(self) -> crate::Result<Request> {
@@ -609,6 +705,7 @@ where
             cors: true,
             credentials: None,
             cache: None,
+            redirect: None,
         })
     }
 }
@@ -105,6 +105,7 @@ impl Response {
             .map_err(crate::error::decode)?;
         let js_val = super::promise::<wasm_bindgen::JsValue>(p)
             .await
+            .map_err(super::map_timeout)
             .map_err(crate::error::decode)?;
         if let Some(s) = js_val.as_string() {
             Ok(s)
@@ -124,6 +125,7 @@ impl Response {
 
         let buf_js = super::promise::<wasm_bindgen::JsValue>(p)
             .await
+            .map_err(super::map_timeout)
             .map_err(crate::error::decode)?;
 
         let buffer = Uint8Array::new(&buf_js);
@@ -149,6 +151,7 @@ impl Response {
                 let buffer = Uint8Array::new(
                     &buf_js
                         .map_err(crate::error::wasm)
+                        .map_err(super::map_timeout)
                         .map_err(crate::error::decode)?,
                 );
                 let mut bytes = vec![0; buffer.length() as usize];
@@ -178,6 +178,25 @@ fn test_error_for_status_4xx() {
     assert_eq!(err.status(), Some(reqwest::StatusCode::BAD_REQUEST));
 }
 
+/// Calling `Response::error_for_status_with_body` keeps the start of the
+/// body in the error.
+#[test]
+fn test_error_for_status_with_body() {
+    let server = server::http(move |_req| async {
+        http::Response::builder()
+            .status(502)
+            .body("upstream unavailable".into())
+            .unwrap()
+    });
+
+    let url = format!("http://{}/1", server.addr());
+    let res = reqwest::blocking::get(&url).unwrap();
+
+    let err = res.error_for_status_with_body(8).unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::BAD_GATEWAY));
+    assert_eq!(err.body(), Some(&b"upstream"[..]));
+}
+
 /// Calling `Response::error_for_status`` on a response with status in 5xx
 /// returns an error.
 #[test]
@@ -412,3 +431,157 @@ fn test_response_no_tls_info_for_http() {
     let body = res.text().unwrap();
     assert_eq!(b"Hello", body.as_bytes());
 }
+
+#[cfg(feature = "cookies")]
+#[test]
+fn test_blocking_cookie_store() {
+    let client = reqwest::blocking::Client::builder()
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let store = client.cookie_store().expect("cookie store");
+    store.add_cookie_str("key=val", &"http://example.com".parse().unwrap());
+    assert_eq!(store.all_cookies()[0].name(), "key");
+}
+
+#[test]
+fn test_response_save_to() {
+    let server = server::http(move |_req| async { http::Response::new("saved".into()) });
+
+    let path =
+        std::env::temp_dir().join(format!("reqwest-{}-blocking-save_to", std::process::id()));
+    let written = reqwest::blocking::get(format!("http://{}/file", server.addr()))
+        .unwrap()
+        .save_to(&path)
+        .unwrap();
+    assert_eq!(written, 5);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "saved");
+
+    let err = reqwest::blocking::get(format!("http://{}/file", server.addr()))
+        .unwrap()
+        .save_to(path.join("not-a-directory"))
+        .unwrap_err();
+    assert!(err.is_io(), "{err:?}");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_trace_context_from_calling_thread() {
+    use reqwest::trace_context::{Propagation, TraceContext};
+    use std::cell::Cell;
+
+    thread_local! {
+        static TRACE_ID: Cell<u128> = Cell::new(0);
+    }
+
+    let server = server::http(move |req| async move {
+        assert_eq!(
+            req.headers()["traceparent"],
+            "00-000000000000000000000000000000ab-00000000000000cd-01"
+        );
+        http::Response::default()
+    });
+
+    let propagation = Propagation::new(|| {
+        let trace_id = TRACE_ID.with(Cell::get);
+        Some(TraceContext::new(trace_id, 0xcd))
+    });
+    let client = reqwest::blocking::Client::builder()
+        .trace_context(propagation)
+        .build()
+        .unwrap();
+
+    TRACE_ID.with(|id| id.set(0xab));
+    let res = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[test]
+fn test_if_cached_not_modified() {
+    let server = server::http(move |req| async move {
+        if req.headers().contains_key("if-none-match") {
+            assert_eq!(req.headers()["if-none-match"], "\"v1\"");
+            http::Response::builder()
+                .status(304)
+                .body(Default::default())
+                .unwrap()
+        } else {
+            http::Response::builder()
+                .header("etag", "\"v1\"")
+                .body("cached".into())
+                .unwrap()
+        }
+    });
+
+    let client = reqwest::blocking::Client::new();
+    let url = format!("http://{}/", server.addr());
+    let cached = client.get(&url).send().unwrap().into_cached().unwrap();
+
+    let res = client.get(&url).if_cached(&cached).send().unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.text().unwrap(), "cached");
+}
+
+#[test]
+fn test_mock_transport() {
+    let client = reqwest::blocking::Client::builder()
+        .mock_transport(|req| {
+            assert_eq!(req.uri(), "http://api.example/");
+            Ok(http::Response::new("mocked".into()))
+        })
+        .build()
+        .unwrap();
+
+    let res = client.get("http://api.example/").send().unwrap();
+    assert_eq!(res.text().unwrap(), "mocked");
+}
+
+#[test]
+fn test_body_from_reader() {
+    let server = server::http(move |req| async move {
+        let framing = match req.headers().get("content-length") {
+            Some(len) => format!("length {}", len.to_str().unwrap()),
+            None => format!("{:?}", req.headers()["transfer-encoding"]),
+        };
+        let data = req.into_body().collect().await.unwrap().to_bytes();
+        http::Response::new(format!("{framing}: {}", String::from_utf8_lossy(&data)).into())
+    });
+
+    let client = reqwest::blocking::Client::new();
+    let url = format!("http://{}/upload", server.addr());
+    let upload = |body| client.post(&url).body(body).send().unwrap().text().unwrap();
+
+    let sized = reqwest::blocking::Body::from_reader(&b"Hello, world"[..], Some(5));
+    assert_eq!(upload(sized), "length 5: Hello");
+
+    let chunked = reqwest::blocking::Body::from_reader(&b"Hello, world"[..], None);
+    assert_eq!(upload(chunked), "\"chunked\": Hello, world");
+}
+
+#[test]
+fn test_runtime_handle() {
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .unwrap();
+    let client = reqwest::blocking::Client::builder()
+        .runtime(rt.handle().clone())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/", server.addr());
+    for _ in 0..2 {
+        let res = client.get(&url).send().unwrap();
+        assert_eq!(res.text().unwrap(), "Hello");
+    }
+
+    drop(client);
+    rt.shutdown_timeout(std::time::Duration::from_secs(1));
+}
@@ -0,0 +1,394 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+mod support;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::cache::CacheConfig;
+use support::server;
+
+fn client(config: CacheConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .http_cache(config)
+        .no_proxy()
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn reuses_fresh_responses() {
+    let _ = env_logger::try_init();
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        let hit = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        async move {
+            http::Response::builder()
+                .header("cache-control", "max-age=60")
+                .body(format!("hit {hit}").into())
+                .unwrap()
+        }
+    });
+
+    let client = client(CacheConfig::in_memory(1024 * 1024));
+    let url = format!("http://{}/fresh", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert!(res.headers().get("age").is_none());
+    assert_eq!(res.text().await.unwrap(), "hit 1");
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.headers()["age"], "0");
+    assert_eq!(res.text().await.unwrap(), "hit 1");
+
+    let res = client
+        .get(&url)
+        .header("cache-control", "no-cache")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "hit 2");
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn responses_age_with_paused_time() {
+    let _ = env_logger::try_init();
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        let hit = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        async move {
+            http::Response::builder()
+                .header("cache-control", "max-age=60")
+                .body(format!("hit {hit}").into())
+                .unwrap()
+        }
+    });
+
+    // No idle pool timer, so paused time only moves when advanced.
+    let client = reqwest::Client::builder()
+        .http_cache(CacheConfig::in_memory(1024 * 1024))
+        .pool_idle_timeout(None)
+        .no_proxy()
+        .build()
+        .unwrap();
+    let url = format!("http://{}/aging", server.addr());
+
+    tokio::time::pause();
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "hit 1");
+
+    tokio::time::advance(Duration::from_secs(30)).await;
+    let res = client.get(&url).send().await.unwrap();
+    let age: u64 = res.headers()["age"].to_str().unwrap().parse().unwrap();
+    assert!((30..60).contains(&age), "age {age}");
+    assert_eq!(res.text().await.unwrap(), "hit 1");
+
+    tokio::time::advance(Duration::from_secs(31)).await;
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "hit 2");
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn revalidates_with_etag() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        if req.headers().get("if-none-match").map(|v| v.as_bytes()) == Some(b"\"v1\"") {
+            http::Response::builder()
+                .status(304)
+                .header("etag", "\"v1\"")
+                .header("x-validated", "yes")
+                .body(Default::default())
+                .unwrap()
+        } else {
+            http::Response::builder()
+                .header("cache-control", "no-cache")
+                .header("etag", "\"v1\"")
+                .body("original".into())
+                .unwrap()
+        }
+    });
+
+    let client = client(CacheConfig::in_memory(1024 * 1024));
+    let url = format!("http://{}/etag", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "original");
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.headers()["x-validated"], "yes");
+    assert_eq!(res.text().await.unwrap(), "original");
+
+    // A request with its own validators gets the server's answer.
+    let res = client
+        .get(&url)
+        .header("if-none-match", "\"v1\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn honors_vary_and_no_store() {
+    let _ = env_logger::try_init();
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |req| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            let cache_control = if req.uri().path() == "/secret" {
+                "no-store"
+            } else {
+                "max-age=60"
+            };
+            let language = req
+                .headers()
+                .get("accept-language")
+                .map_or("none", |v| v.to_str().unwrap())
+                .to_owned();
+            http::Response::builder()
+                .header("cache-control", cache_control)
+                .header("vary", "Accept-Language")
+                .body(language.into())
+                .unwrap()
+        }
+    });
+
+    let client = client(CacheConfig::in_memory(1024 * 1024));
+    let url = format!("http://{}/greeting", server.addr());
+
+    for language in ["fr", "fr", "de", "de"] {
+        let body = client
+            .get(&url)
+            .header("accept-language", language)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, language);
+    }
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    let secret = format!("http://{}/secret", server.addr());
+    client
+        .get(&secret)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    client
+        .get(&secret)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(hits.load(Ordering::SeqCst), 4);
+
+    // Nothing stored answers a request only wanting the cache.
+    let res = client
+        .get(&secret)
+        .header("cache-control", "only-if-cached")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(hits.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn unsafe_methods_invalidate() {
+    let _ = env_logger::try_init();
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            http::Response::builder()
+                .header("cache-control", "max-age=60")
+                .body("item".into())
+                .unwrap()
+        }
+    });
+
+    let client = client(CacheConfig::in_memory(1024 * 1024));
+    let url = format!("http://{}/item", server.addr());
+
+    client.get(&url).send().await.unwrap().text().await.unwrap();
+    client.get(&url).send().await.unwrap().text().await.unwrap();
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    client.put(&url).body("new").send().await.unwrap();
+    client.get(&url).send().await.unwrap().text().await.unwrap();
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn file_storage_outlives_the_client() {
+    let _ = env_logger::try_init();
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            http::Response::builder()
+                .header("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT")
+                .body("kept on disk".into())
+                .unwrap()
+        }
+    });
+
+    let dir = std::env::temp_dir().join(format!("reqwest-cache-{}", std::process::id()));
+    let url = format!("http://{}/disk", server.addr());
+
+    for _ in 0..2 {
+        let body = client(CacheConfig::on_disk(&dir))
+            .get(&url)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "kept on disk");
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // Fresh from the `Last-Modified` heuristic.
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn serves_stale_while_revalidating() {
+    let _ = env_logger::try_init();
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        let hit = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        async move {
+            http::Response::builder()
+                .header("cache-control", "max-age=0, stale-while-revalidate=60")
+                .body(format!("hit {hit}").into())
+                .unwrap()
+        }
+    });
+
+    let client = client(CacheConfig::in_memory(1024 * 1024));
+    let url = format!("http://{}/stale", server.addr());
+
+    let text = |res: reqwest::Response| async move { res.text().await.unwrap() };
+    assert_eq!(text(client.get(&url).send().await.unwrap()).await, "hit 1");
+    assert_eq!(text(client.get(&url).send().await.unwrap()).await, "hit 1");
+
+    // The revalidation stores the next response in the background.
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        if hits.load(Ordering::SeqCst) == 2 {
+            break;
+        }
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(text(client.get(&url).send().await.unwrap()).await, "hit 2");
+}
+
+#[tokio::test]
+async fn serves_stale_if_error() {
+    let _ = env_logger::try_init();
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        let hit = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        async move {
+            if hit == 1 {
+                http::Response::builder()
+                    .header("cache-control", "max-age=0")
+                    .header("etag", "\"v1\"")
+                    .body("good".into())
+                    .unwrap()
+            } else {
+                http::Response::builder()
+                    .status(503)
+                    .body("down".into())
+                    .unwrap()
+            }
+        }
+    });
+
+    let client =
+        client(CacheConfig::in_memory(1024 * 1024).stale_if_error(Duration::from_secs(60)));
+    let url = format!("http://{}/flaky", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "good");
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "good");
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn if_cached_returns_cached_body_on_not_modified() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        let modified = "Mon, 01 Jan 2024 00:00:00 GMT";
+        if req.headers().get("if-modified-since").map(|v| v.as_bytes()) == Some(modified.as_bytes())
+        {
+            assert_eq!(req.headers()["if-none-match"], "\"feed-1\"");
+            http::Response::builder()
+                .status(304)
+                .header("etag", "\"feed-1\"")
+                .header("x-polled", "2")
+                .body(Default::default())
+                .unwrap()
+        } else {
+            http::Response::builder()
+                .header("etag", "\"feed-1\"")
+                .header("last-modified", modified)
+                .header("x-polled", "1")
+                .body("the feed".into())
+                .unwrap()
+        }
+    });
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let url = format!("http://{}/feed", server.addr());
+
+    let first = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .into_cached()
+        .await
+        .unwrap();
+    assert_eq!(first.body(), "the feed");
+    assert!(first.has_validators());
+
+    let res = client.get(&url).if_cached(&first).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.url().as_str(), url);
+    assert_eq!(res.headers()["x-polled"], "2");
+    assert_eq!(res.text().await.unwrap(), "the feed");
+}
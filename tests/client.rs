@@ -105,6 +105,41 @@ async fn user_agent() {
     assert_eq!(res.status(), reqwest::StatusCode::OK);
 }
 
+#[test]
+fn pending_is_send_sync() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let pending = Client::new().get("http://localhost/").send();
+    assert_send_sync(&pending);
+}
+
+#[tokio::test]
+async fn request_priority() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["priority"], "u=1, i");
+        http::Response::default()
+    });
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/priority", server.addr());
+    let res = client
+        .get(&url)
+        .header("priority", "u=5")
+        .priority(1, true)
+        .send()
+        .await
+        .expect("request");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let err = client
+        .get(&url)
+        .priority(8, false)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
 #[tokio::test]
 async fn response_text() {
     let _ = env_logger::try_init();
@@ -141,6 +176,118 @@ async fn response_bytes() {
     assert_eq!("Hello", bytes);
 }
 
+#[tokio::test]
+async fn response_max_size() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let url = format!("http://{}/bytes", server.addr());
+
+    let client = Client::builder()
+        .max_response_size(5)
+        .no_proxy()
+        .build()
+        .unwrap();
+    let text = client.get(&url).send().await.unwrap().text().await;
+    assert_eq!("Hello", text.expect("within limit"));
+
+    let client = Client::builder()
+        .max_response_size(4)
+        .no_proxy()
+        .build()
+        .unwrap();
+    let err = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap_err();
+    assert!(err.is_body_too_large(), "{err:?}");
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn response_max_size_streaming() {
+    use futures_util::StreamExt;
+
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async {
+        // chunked, so the size isn't known up front
+        let chunks = futures_util::stream::iter(["Hello", ", ", "world"])
+            .map(Ok::<_, std::convert::Infallible>);
+        http::Response::new(reqwest::Body::wrap_stream(chunks))
+    });
+
+    let client = Client::builder()
+        .max_response_size(6)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let mut res = client
+        .get(format!("http://{}/chunked", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.content_length(), None);
+
+    let mut received = Vec::new();
+    let err = loop {
+        match res.chunk().await {
+            Ok(Some(chunk)) => received.extend_from_slice(&chunk),
+            Ok(None) => panic!("body should have exceeded the limit"),
+            Err(err) => break err,
+        }
+    };
+    assert!(err.is_body_too_large(), "{err:?}");
+    assert!(received.len() <= 6);
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn response_body_buffer_pool() {
+    use futures_util::StreamExt;
+
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async {
+        let chunks = futures_util::stream::iter(["Hello", ", ", "world"])
+            .map(Ok::<_, std::convert::Infallible>);
+        http::Response::new(reqwest::Body::wrap_stream(chunks))
+    });
+    let url = format!("http://{}/chunked", server.addr());
+
+    let client = Client::builder()
+        .body_buffer_pool(4, 64)
+        .no_proxy()
+        .build()
+        .unwrap();
+    for _ in 0..3 {
+        let text = client.get(&url).send().await.unwrap().text().await;
+        assert_eq!("Hello, world", text.expect("pooled body"));
+    }
+
+    let client = Client::builder()
+        .body_buffer_pool(4, 64)
+        .max_response_size(6)
+        .no_proxy()
+        .build()
+        .unwrap();
+    let err = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap_err();
+    assert!(err.is_body_too_large(), "{err:?}");
+}
+
 #[tokio::test]
 #[cfg(feature = "json")]
 async fn response_json() {
@@ -207,6 +354,38 @@ async fn body_pipe_response() {
     assert_eq!(res2.status(), reqwest::StatusCode::OK);
 }
 
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn body_from_async_read() {
+    use http_body_util::BodyExt;
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        if req.headers()["x-sized"] == "yes" {
+            assert_eq!(req.headers()["content-length"], "7");
+        } else {
+            assert_eq!(req.headers()["transfer-encoding"], "chunked");
+        }
+        let full = req.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(full, "read me");
+
+        http::Response::default()
+    });
+
+    let client = Client::new();
+    let url = format!("http://{}/read", server.addr());
+    for (length, sized) in [(Some(7), "yes"), (None, "no")] {
+        let res = client
+            .post(&url)
+            .header("x-sized", sized)
+            .body(reqwest::Body::from_async_read(&b"read me"[..], length))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+    }
+}
+
 #[tokio::test]
 async fn overridden_dns_resolution_with_gai() {
     let _ = env_logger::builder().is_test(true).try_init();
@@ -264,6 +443,46 @@ async fn overridden_dns_resolution_with_gai_multiple() {
     assert_eq!("Hello", text);
 }
 
+#[tokio::test]
+async fn prefetched_dns_is_used_by_requests() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Counting(std::net::SocketAddr, Arc<AtomicUsize>);
+
+    impl reqwest::dns::Resolve for Counting {
+        fn resolve(&self, _: reqwest::dns::Name) -> reqwest::dns::Resolving {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            let addrs: reqwest::dns::Addrs = Box::new(std::iter::once(self.0));
+            Box::pin(std::future::ready(Ok(addrs)))
+        }
+    }
+
+    let _ = env_logger::builder().is_test(true).try_init();
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .dns_resolver(Counting(server.addr(), lookups.clone()))
+        .build()
+        .expect("client builder");
+
+    client
+        .prefetch_dns("prefetch.test")
+        .await
+        .expect("prefetch");
+    assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+    let url = format!("http://prefetch.test:{}/", server.addr().port());
+    let res = client.get(&url).send().await.expect("request");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+    let err = client.prefetch_dns("not a host").await.unwrap_err();
+    assert!(err.is_request(), "{err:?}");
+}
+
 #[cfg(feature = "hickory-dns")]
 #[tokio::test]
 async fn overridden_dns_resolution_with_hickory_dns() {
@@ -371,6 +590,15 @@ fn use_preconfigured_rustls_default() {
         .expect("preconfigured rustls tls");
 }
 
+#[cfg(feature = "rustls")] // needs a TLS provider
+#[test]
+fn tls_post_quantum() {
+    let builder = reqwest::Client::builder().tls_post_quantum(true);
+    assert!(format!("{builder:?}").contains("tls_post_quantum"));
+
+    builder.build().expect("post-quantum tls");
+}
+
 #[cfg(all(feature = "__tls", not(any(feature = "http2", feature = "http3")),))]
 #[tokio::test]
 async fn http1_only() {
@@ -407,6 +635,31 @@ async fn http2_upgrade() {
     assert_eq!(res.version(), reqwest::Version::HTTP_2);
 }
 
+// The connection needs a second worker thread to be driven to a graceful
+// shutdown while dropping the server blocks the test's thread.
+#[cfg(feature = "http2")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn http2_prior_knowledge_cleartext() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.version(), http::Version::HTTP_2);
+        http::Response::default()
+    });
+
+    let client = reqwest::Client::builder()
+        .http2_prior_knowledge_cleartext()
+        .build()
+        .expect("client builder");
+    for _ in 0..2 {
+        let res = client
+            .get(format!("http://{}/", server.addr()))
+            .send()
+            .await
+            .expect("request");
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        assert_eq!(res.version(), reqwest::Version::HTTP_2);
+    }
+}
+
 #[cfg(feature = "default-tls")]
 #[cfg_attr(feature = "http3", ignore = "enabling http3 seems to break this, why?")]
 #[tokio::test]
@@ -513,6 +766,117 @@ async fn close_connection_after_idle_timeout() {
         .any(|e| matches!(e, server::Event::ConnectionClosed)));
 }
 
+#[tokio::test]
+async fn invalidate_pool_closes_idle_connections() {
+    let mut server = server::http(move |_| async move { http::Response::default() });
+
+    let client = reqwest::Client::builder()
+        .pool_invalidate_on_network_change(true)
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}", server.addr());
+
+    client.get(&url).send().await.unwrap();
+    assert!(server.events().is_empty());
+
+    client.invalidate_pool();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    assert!(server
+        .events()
+        .iter()
+        .any(|e| matches!(e, server::Event::ConnectionClosed)));
+
+    client.get(&url).send().await.unwrap();
+}
+
+fn closes_second_request_server() -> server::Server {
+    let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    server::low_level_with_response(move |_raw_request, client_socket| {
+        let n = requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Box::new(async move {
+            if n == 1 {
+                // closed while idle, as the request came in
+                client_socket.shutdown().await.expect("shutdown");
+            } else {
+                client_socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                    .await
+                    .expect("response write_all failed");
+            }
+        })
+    })
+}
+
+#[tokio::test]
+async fn retries_idempotent_request_on_stale_connection() {
+    let server = closes_second_request_server();
+    let client = Client::new();
+    let url = format!("http://{}/", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "ok");
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn stale_connections_are_retried_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let server = server::low_level_with_response(move |_raw_request, client_socket| {
+        let n = counter.fetch_add(1, Ordering::SeqCst);
+        Box::new(async move {
+            if n < 3 {
+                // Held until the three of them came in, over a connection each.
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                client_socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                    .await
+                    .expect("response write_all failed");
+            } else {
+                client_socket.shutdown().await.expect("shutdown");
+            }
+        })
+    });
+    let client = reqwest::Client::builder()
+        .retry(reqwest::retry::never())
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let send = || async { client.get(&url).send().await.unwrap().text().await.unwrap() };
+    tokio::join!(send(), send(), send());
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    // Every pooled connection is now closed, but only one is tried again.
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_request(), "{err:?}");
+    assert_eq!(requests.load(Ordering::SeqCst), 5);
+}
+
+#[tokio::test]
+async fn stale_connection_retry_can_be_disabled() {
+    let server = closes_second_request_server();
+    let client = reqwest::Client::builder()
+        .pool_retry_stale_connections(false)
+        .retry(reqwest::retry::never())
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "ok");
+
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_request(), "{err:?}");
+}
+
 #[tokio::test]
 async fn http1_reason_phrase() {
     let server = server::low_level_with_response(|_raw_request, client_socket| {
@@ -541,9 +905,806 @@ async fn http1_reason_phrase() {
     );
 }
 
+#[tokio::test]
+async fn error_for_status_with_body() {
+    let server = server::http(move |req| async move {
+        let status = if req.uri().path() == "/ok" { 200 } else { 401 };
+        http::Response::builder()
+            .status(status)
+            .body("<html><body>Access denied</body></html>".into())
+            .unwrap()
+    });
+
+    let client = Client::new();
+
+    let res = client
+        .get(format!("http://{}/denied", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    let err = res.error_for_status_with_body(25).await.unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::UNAUTHORIZED));
+    assert_eq!(err.body(), Some(&b"<html><body>Access denied"[..]));
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "HTTP status client error (401 Unauthorized) for url (http://{}/denied): \"<html><body>Access denied\"",
+            server.addr()
+        )
+    );
+
+    let res = client
+        .get(format!("http://{}/ok", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    let res = res.error_for_status_with_body(25).await.unwrap();
+    assert_eq!(
+        res.text().await.unwrap(),
+        "<html><body>Access denied</body></html>"
+    );
+}
+
+#[tokio::test]
+async fn decode_errors_keep_the_body_read() {
+    // Claims a longer body than it sends, then closes the connection.
+    let truncated = server::low_level_with_response(|_raw_request, client_socket| {
+        Box::new(async move {
+            client_socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nshort")
+                .await
+                .expect("response write_all failed");
+            client_socket.shutdown().await.expect("shutdown failed");
+        })
+    });
+
+    let err = reqwest::get(format!("http://{}/", truncated.addr()))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap_err();
+    assert!(err.is_decode(), "{err:?}");
+    assert_eq!(err.body(), Some(&b"short"[..]));
+    assert!(!err.to_string().contains("short"), "{err}");
+
+    #[cfg(feature = "json")]
+    {
+        let server =
+            server::http(move |_req| async move { http::Response::new(r#"{"id": oops}"#.into()) });
+        let err = reqwest::get(format!("http://{}/", server.addr()))
+            .await
+            .unwrap()
+            .json::<HashMap<String, u32>>()
+            .await
+            .unwrap_err();
+        assert!(err.is_decode(), "{err:?}");
+        assert_eq!(err.body(), Some(&br#"{"id": oops}"#[..]));
+    }
+}
+
 #[tokio::test]
 async fn error_has_url() {
     let u = "http://does.not.exist.local/ever";
     let err = reqwest::get(u).await.unwrap_err();
     assert_eq!(err.url().map(AsRef::as_ref), Some(u), "{err:?}");
 }
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn response_save_to() {
+    let server =
+        server::http(move |_req| async move { http::Response::new(vec![b'x'; 50_000].into()) });
+    // Claims a longer body than it sends, then closes the connection.
+    let truncated = server::low_level_with_response(|_raw_request, client_socket| {
+        Box::new(async move {
+            client_socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nshort")
+                .await
+                .expect("response write_all failed");
+            client_socket.shutdown().await.expect("shutdown failed");
+        })
+    });
+
+    let name = format!("reqwest-{}-save_to", std::process::id());
+    let path = std::env::temp_dir().join(&name);
+    std::fs::write(&path, "previous").unwrap();
+    // An unrelated file that happens to have the old temporary name.
+    let mut unrelated = path.clone().into_os_string();
+    unrelated.push(".tmp");
+    std::fs::write(&unrelated, "unrelated").unwrap();
+
+    let written = reqwest::get(format!("http://{}/file", server.addr()))
+        .await
+        .unwrap()
+        .save_to_synced(&path)
+        .await
+        .unwrap();
+    assert_eq!(written, 50_000);
+    assert_eq!(std::fs::read(&path).unwrap(), vec![b'x'; 50_000]);
+
+    // A failed download leaves the previous file in place.
+    let err = reqwest::get(format!("http://{}/truncated", truncated.addr()))
+        .await
+        .unwrap()
+        .save_to(&path)
+        .await
+        .unwrap_err();
+    assert!(err.is_decode() || err.is_body(), "{err:?}");
+    assert!(!err.is_io(), "{err:?}");
+    assert_eq!(std::fs::read(&path).unwrap().len(), 50_000);
+    let leftovers = std::fs::read_dir(std::env::temp_dir())
+        .unwrap()
+        .filter(|entry| {
+            let file_name = entry.as_ref().unwrap().file_name();
+            let file_name = file_name.to_string_lossy();
+            file_name.starts_with(&format!("{name}.")) && file_name.ends_with(".tmp")
+        })
+        .count();
+    assert_eq!(leftovers, 1, "only the unrelated file is left");
+    assert_eq!(std::fs::read_to_string(&unrelated).unwrap(), "unrelated");
+
+    // Failing to write the file isn't a problem with the response.
+    let err = reqwest::get(format!("http://{}/file", server.addr()))
+        .await
+        .unwrap()
+        .save_to(path.join("not-a-directory"))
+        .await
+        .unwrap_err();
+    assert!(err.is_io(), "{err:?}");
+    assert!(!err.is_decode(), "{err:?}");
+
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&unrelated).unwrap();
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn request_download_to_resumes() {
+    const CONTENT: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    let server = server::http(move |req| async move {
+        let range = req
+            .headers()
+            .get("range")
+            .map(|v| v.to_str().unwrap().to_owned());
+        let if_range = req.headers().get("if-range").cloned();
+        match range {
+            Some(range) if if_range.as_ref().map_or(false, |v| v == "\"v1\"") => {
+                let start: usize = range
+                    .strip_prefix("bytes=")
+                    .and_then(|r| r.strip_suffix('-'))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                http::Response::builder()
+                    .status(206)
+                    .header("etag", "\"v1\"")
+                    .header(
+                        "content-range",
+                        format!("bytes {start}-{}/{}", CONTENT.len() - 1, CONTENT.len()),
+                    )
+                    .body(CONTENT[start..].to_vec().into())
+                    .unwrap()
+            }
+            _ => http::Response::builder()
+                .header("etag", "\"v1\"")
+                .body(CONTENT.to_vec().into())
+                .unwrap(),
+        }
+    });
+
+    let path = std::env::temp_dir().join(format!("reqwest-{}-download_to", std::process::id()));
+    let mut part = path.clone().into_os_string();
+    part.push(".part");
+    let mut validator = part.clone();
+    validator.push(".validator");
+
+    let client = Client::new();
+    let url = format!("http://{}/file", server.addr());
+
+    // A part of the current version is completed with a range request.
+    std::fs::write(&part, &CONTENT[..10]).unwrap();
+    std::fs::write(&validator, "\"v1\"").unwrap();
+    let len = client.get(&url).download_to(&path).await.unwrap();
+    assert_eq!(len, CONTENT.len() as u64);
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+    assert!(!std::path::Path::new(&part).exists());
+    assert!(!std::path::Path::new(&validator).exists());
+
+    // A part of an older version is started over.
+    std::fs::write(&part, b"stale data").unwrap();
+    std::fs::write(&validator, "\"v0\"").unwrap();
+    let len = client.get(&url).download_to(&path).await.unwrap();
+    assert_eq!(len, CONTENT.len() as u64);
+    assert_eq!(std::fs::read(&path).unwrap(), CONTENT);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn request_download_to_keeps_part() {
+    let server = server::low_level_with_response(|_raw_request, client_socket| {
+        Box::new(async move {
+            client_socket
+                .write_all(b"HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 100\r\n\r\npartial")
+                .await
+                .expect("response write_all failed");
+            client_socket.shutdown().await.expect("shutdown failed");
+        })
+    });
+
+    let path = std::env::temp_dir().join(format!("reqwest-{}-download_part", std::process::id()));
+    let mut part = path.clone().into_os_string();
+    part.push(".part");
+    let mut validator = part.clone();
+    validator.push(".validator");
+
+    let err = Client::new()
+        .get(format!("http://{}/file", server.addr()))
+        .download_to(&path)
+        .await
+        .unwrap_err();
+    assert!(!err.is_io(), "{err:?}");
+    assert!(!path.exists());
+    assert_eq!(std::fs::read(&part).unwrap(), b"partial");
+    assert_eq!(std::fs::read(&validator).unwrap(), b"\"v1\"");
+
+    std::fs::remove_file(&part).unwrap();
+    std::fs::remove_file(&validator).unwrap();
+
+    let err = Client::new()
+        .get(format!("http://{}/file", server.addr()))
+        .download_to(path.join("not-a-directory"))
+        .await
+        .unwrap_err();
+    assert!(err.is_io(), "{err:?}");
+}
+
+#[cfg(all(feature = "stream", feature = "charset"))]
+#[tokio::test]
+async fn response_text_stream() {
+    use futures_util::StreamExt;
+
+    let server = server::http(move |req| async move {
+        let (content_type, chunks): (_, Vec<&'static [u8]>) = if req.uri() == "/latin1" {
+            ("text/plain; charset=windows-1252", vec![b"caf\xe9"])
+        } else {
+            // A BOM, then "héllo" with the "é" split between chunks.
+            ("text/plain", vec![b"\xef\xbb\xbfh\xc3", b"\xa9llo"])
+        };
+        let chunks = futures_util::stream::iter(chunks.into_iter().map(Ok::<_, std::io::Error>));
+        http::Response::builder()
+            .header("content-type", content_type)
+            .body(reqwest::Body::wrap_stream(chunks))
+            .unwrap()
+    });
+
+    let texts = reqwest::get(format!("http://{}/utf8", server.addr()))
+        .await
+        .unwrap()
+        .text_stream()
+        .map(|text| text.unwrap())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(texts.concat(), "héllo");
+    assert!(texts
+        .iter()
+        .all(|text| !text.contains(char::REPLACEMENT_CHARACTER)));
+
+    let text = reqwest::get(format!("http://{}/latin1", server.addr()))
+        .await
+        .unwrap()
+        .text_stream()
+        .map(|text| text.unwrap())
+        .collect::<String>()
+        .await;
+    assert_eq!(text, "café");
+}
+
+#[cfg(all(feature = "json", feature = "stream"))]
+#[tokio::test]
+async fn response_json_lines() {
+    use futures_util::StreamExt;
+
+    let server = server::http(move |_req| async move {
+        // Lines split across chunks, a blank line, an invalid line, and a
+        // last line without a trailing newline.
+        let chunks = futures_util::stream::iter(
+            vec!["{\"n\":1}\r\n{\"n\"", ":2}\n\n", "oops\n{\"n\":3}"]
+                .into_iter()
+                .map(Ok::<_, std::io::Error>),
+        );
+        http::Response::new(reqwest::Body::wrap_stream(chunks))
+    });
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Line {
+        n: u32,
+    }
+
+    let lines = reqwest::get(format!("http://{}/ndjson", server.addr()))
+        .await
+        .unwrap()
+        .json_lines::<Line>()
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0].as_ref().unwrap(), &Line { n: 1 });
+    assert_eq!(lines[1].as_ref().unwrap(), &Line { n: 2 });
+    assert!(lines[2].as_ref().unwrap_err().is_decode());
+    assert_eq!(lines[3].as_ref().unwrap(), &Line { n: 3 });
+}
+
+#[tokio::test]
+async fn response_trailers() {
+    use http_body_util::StreamBody;
+
+    let server = server::http(move |_req| async move {
+        let mut trailers = http::HeaderMap::new();
+        trailers.insert("checksum", "abc".parse().unwrap());
+        let frames = futures_util::stream::iter(vec![
+            Ok::<_, std::io::Error>(hyper::body::Frame::data(bytes::Bytes::from("body"))),
+            Ok(hyper::body::Frame::trailers(trailers)),
+        ]);
+        http::Response::builder()
+            .header("trailer", "checksum")
+            .body(reqwest::Body::wrap(StreamBody::new(frames)))
+            .unwrap()
+    });
+
+    let mut res = Client::new()
+        .get(format!("http://{}/trailers", server.addr()))
+        .header("te", "trailers")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.chunk().await.unwrap().unwrap(), "body");
+    let trailers = res.trailers().await.unwrap().unwrap();
+    assert_eq!(trailers["checksum"], "abc");
+    assert!(res.trailers().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn request_on_informational() {
+    use std::sync::{Arc, Mutex};
+
+    let server = server::low_level_with_response(|_raw_request, client_socket| {
+        Box::new(async move {
+            client_socket
+                .write_all(
+                    b"HTTP/1.1 103 Early Hints\r\n\
+                      Link: </style.css>; rel=preload; as=style\r\n\r\n\
+                      HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+                )
+                .await
+                .expect("response write_all failed");
+        })
+    });
+
+    let hints = Arc::new(Mutex::new(Vec::new()));
+    let seen = hints.clone();
+    let res = Client::new()
+        .get(format!("http://{}/", server.addr()))
+        .on_informational(move |status, headers| {
+            seen.lock()
+                .unwrap()
+                .push((status, headers.get("link").cloned()));
+        })
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "hello");
+    assert_eq!(
+        *hints.lock().unwrap(),
+        vec![(
+            reqwest::StatusCode::EARLY_HINTS,
+            Some("</style.css>; rel=preload; as=style".parse().unwrap())
+        )]
+    );
+}
+
+#[tokio::test]
+async fn request_expect_continue() {
+    use http_body_util::BodyExt;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["expect"], "100-continue");
+        if req.uri().path() == "/private" {
+            return http::Response::builder()
+                .status(401)
+                .body("unauthorized".into())
+                .unwrap();
+        }
+        // Reading the body makes the server send `100 Continue`.
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        http::Response::new(body.len().to_string().into())
+    });
+
+    let client = Client::new();
+
+    let start = Instant::now();
+    let res = client
+        .put(format!("http://{}/upload", server.addr()))
+        .body(vec![0; 100_000])
+        .expect_continue(Duration::from_secs(30))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "100000");
+    assert!(start.elapsed() < Duration::from_secs(30));
+
+    // The body isn't sent when the server answers without `100 Continue`.
+    let sent = Arc::new(AtomicU64::new(0));
+    let counter = sent.clone();
+    let res = client
+        .put(format!("http://{}/private", server.addr()))
+        .body(vec![0; 100_000])
+        .expect_continue(Duration::from_secs(30))
+        .on_upload_progress(move |n, _| counter.store(n, Ordering::SeqCst))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    assert_eq!(res.text().await.unwrap(), "unauthorized");
+    assert_eq!(sent.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn request_expect_continue_timeout() {
+    use tokio::io::AsyncReadExt;
+
+    // A server that ignores the expectation and waits for the body.
+    let server = server::low_level_with_response(|raw_request, client_socket| {
+        Box::new(async move {
+            assert!(String::from_utf8_lossy(raw_request)
+                .to_lowercase()
+                .contains("expect: 100-continue"));
+            let mut body = [0; 5];
+            client_socket.read_exact(&mut body).await.unwrap();
+            assert_eq!(&body, b"hello");
+            client_socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .await
+                .expect("response write_all failed");
+        })
+    });
+
+    let res = Client::new()
+        .post(format!("http://{}/", server.addr()))
+        .body("hello")
+        .expect_continue(std::time::Duration::from_millis(100))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn execute_hedged() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    let cnt = Arc::new(AtomicUsize::new(0));
+    let counter = cnt.clone();
+    let server = server::http(move |_req| {
+        let first = counter.fetch_add(1, Ordering::SeqCst) == 0;
+        async move {
+            if first {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                http::Response::new("slow".into())
+            } else {
+                http::Response::new("fast".into())
+            }
+        }
+    });
+
+    let client = Client::new();
+    let url = format!("http://{}/hedged", server.addr());
+
+    let start = Instant::now();
+    let req = client.get(&url).build().unwrap();
+    let res = client
+        .execute_hedged(req, Duration::from_millis(100))
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "fast");
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(cnt.load(Ordering::SeqCst), 2);
+
+    // A response within the threshold isn't hedged.
+    let req = client.get(&url).build().unwrap();
+    let res = client
+        .execute_hedged(req, Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "fast");
+    assert_eq!(cnt.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn execute_all_in_order_with_bounded_concurrency() {
+    use futures_util::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let active = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (counter, max) = (active.clone(), peak.clone());
+    let server = server::http(move |req| {
+        let now = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        max.fetch_max(now, Ordering::SeqCst);
+        let counter = counter.clone();
+        async move {
+            let n: u64 = req.uri().path()[1..].parse().unwrap();
+            // Later requests finish first.
+            tokio::time::sleep(Duration::from_millis(50 * (6 - n))).await;
+            counter.fetch_sub(1, Ordering::SeqCst);
+            http::Response::new(n.to_string().into())
+        }
+    });
+
+    let client = Client::new();
+    let mut requests = (1..=5)
+        .map(|n| {
+            client
+                .get(format!("http://{}/{n}", server.addr()))
+                .build()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    // A request that fails doesn't stop the others.
+    requests.insert(2, client.get("http://[::1]:0/").build().unwrap());
+
+    let results = client.execute_all(requests, 2).collect::<Vec<_>>().await;
+    assert_eq!(results.len(), 6);
+    assert!(results[2].is_err());
+
+    let mut bodies = Vec::new();
+    for res in results.into_iter().filter_map(Result::ok) {
+        bodies.push(res.text().await.unwrap());
+    }
+    assert_eq!(bodies, ["1", "2", "3", "4", "5"]);
+    assert!(peak.load(Ordering::SeqCst) <= 2);
+}
+
+#[tokio::test]
+async fn trace_context_headers() {
+    use reqwest::trace_context::{Propagation, TraceContext};
+
+    let server = server::http(move |req| async move {
+        assert_eq!(
+            req.headers()["traceparent"],
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00"
+        );
+        assert_eq!(req.headers()["tracestate"], "congo=t61rcWkgMzE");
+        assert_eq!(
+            req.headers()["b3"],
+            "4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-0"
+        );
+        http::Response::default()
+    });
+
+    let propagation = Propagation::new(|| {
+        Some(
+            TraceContext::new(0x4bf92f3577b34da6a3ce929d0e0e4736, 0x00f067aa0ba902b7)
+                .sampled(false)
+                .trace_state("congo=t61rcWkgMzE"),
+        )
+    })
+    .b3(true);
+    let client = Client::builder()
+        .trace_context(propagation)
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn http1_header_case_is_kept_on_the_wire() {
+    let server = server::low_level_with_response(|raw_request, client_socket| {
+        Box::new(async move {
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                raw_request.len()
+            );
+            client_socket
+                .write_all(&[head.as_bytes(), raw_request].concat())
+                .await
+                .expect("response write_all failed");
+            client_socket.flush().await.expect("response flush failed");
+        })
+    });
+
+    let client = Client::builder()
+        .http1_header_case(["SOAPAction", "X-API-Key"])
+        .build()
+        .unwrap();
+    for _ in 0..2 {
+        let raw = client
+            .post(format!("http://{}/", server.addr()))
+            .header("soapaction", "urn:Ping")
+            .header("x-api-key", "secret")
+            .header("x-other", "1")
+            .body("x-api-key: in the body")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert!(raw.contains("\r\nSOAPAction: urn:Ping\r\n"), "{raw}");
+        assert!(raw.contains("\r\nX-API-Key: secret\r\n"), "{raw}");
+        assert!(raw.contains("\r\nx-other: 1\r\n"), "{raw}");
+    }
+
+    let curl = client
+        .get("http://example.com/")
+        .header("soapaction", "urn:Ping")
+        .to_curl_command()
+        .unwrap();
+    assert!(curl.contains("-H 'SOAPAction: urn:Ping'"), "{curl}");
+}
+
+#[test]
+fn http1_header_case_rejects_invalid_names() {
+    let err = Client::builder()
+        .http1_header_case(["Bad Name"])
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn cloned_builders_build_clients_of_their_own() {
+    let server = server::http(move |req| async move {
+        let tenant = req.headers()["x-tenant"].to_str().unwrap().to_owned();
+        let agent = req.headers()["user-agent"].to_str().unwrap().to_owned();
+        http::Response::new(format!("{tenant} {agent}").into())
+    });
+    let url = format!("http://{}/", server.addr());
+
+    let base = Client::builder().user_agent("shared-agent").no_proxy();
+    for tenant in ["acme", "initech"] {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-tenant", tenant.parse().unwrap());
+        let client = base.clone().default_headers(headers).build().unwrap();
+        let text = client.get(&url).send().await.unwrap().text().await.unwrap();
+        assert_eq!(text, format!("{tenant} shared-agent"));
+    }
+
+    // Errors from the original builder are kept by its clones.
+    let invalid = Client::builder().http1_header_case(["Bad Name"]);
+    let err = invalid.clone().build().unwrap_err();
+    assert!(err.is_builder());
+    let source = std::error::Error::source(&err).unwrap().to_string();
+    let err = invalid.build().unwrap_err();
+    assert!(err.is_builder());
+    assert_eq!(std::error::Error::source(&err).unwrap().to_string(), source);
+}
+
+#[cfg(feature = "cookies")]
+#[tokio::test]
+async fn cloned_builders_dont_share_cookies_or_cache() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |req| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            let cookie = req
+                .headers()
+                .get("cookie")
+                .map_or("", |cookie| cookie.to_str().unwrap())
+                .to_owned();
+            http::Response::builder()
+                .header("set-cookie", "session=secret")
+                .header("cache-control", "max-age=60")
+                .body(cookie.into())
+                .unwrap()
+        }
+    });
+
+    let base = Client::builder()
+        .cookie_store(true)
+        .http_cache(reqwest::cache::CacheConfig::in_memory(1024 * 1024))
+        .no_proxy();
+    for _ in 0..2 {
+        let client = base.clone().build().unwrap();
+        let url = format!("http://{}/", server.addr());
+        let text = client.get(&url).send().await.unwrap().text().await.unwrap();
+        assert_eq!(text, "");
+        let url = format!("http://{}/again", server.addr());
+        let text = client.get(&url).send().await.unwrap().text().await.unwrap();
+        assert_eq!(text, "session=secret");
+    }
+    assert_eq!(hits.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn request_id_is_stamped_unless_set() {
+    let server = server::http(move |req| async move {
+        http::Response::new(
+            req.headers()["x-request-id"]
+                .to_str()
+                .unwrap()
+                .to_owned()
+                .into(),
+        )
+    });
+
+    let client = Client::builder()
+        .request_id(http::HeaderName::from_static("x-request-id"), || {
+            http::HeaderValue::from_static("generated")
+        })
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    let id = res.extensions().get::<reqwest::request_id::RequestId>();
+    assert_eq!(id.unwrap().value(), "generated");
+    assert_eq!(res.text().await.unwrap(), "generated");
+
+    let res = client
+        .get(&url)
+        .header("x-request-id", "mine")
+        .send()
+        .await
+        .unwrap();
+    let id = res.extensions().get::<reqwest::request_id::RequestId>();
+    assert_eq!(id.unwrap().value(), "mine");
+    assert_eq!(res.text().await.unwrap(), "mine");
+}
+
+#[tokio::test]
+async fn default_headers_for_scope() {
+    let server = server::http(move |req| async move {
+        let key = req
+            .headers()
+            .get("x-api-key")
+            .map(|v| v.as_bytes().to_vec());
+        http::Response::new(key.unwrap_or_default().into())
+    });
+
+    let mut all = reqwest::header::HeaderMap::new();
+    all.insert("x-api-key", "everyone".parse().unwrap());
+    let mut scoped = reqwest::header::HeaderMap::new();
+    scoped.insert("x-api-key", "internal".parse().unwrap());
+    let client = Client::builder()
+        .default_headers(all)
+        .default_headers_for(&format!("http://{}/internal", server.addr()), scoped)
+        .build()
+        .unwrap();
+
+    let key = |path: &'static str| {
+        let req = client.get(format!("http://{}{path}", server.addr()));
+        async move { req.send().await.unwrap().text().await.unwrap() }
+    };
+    assert_eq!(key("/internal/users").await, "internal");
+    assert_eq!(key("/public").await, "everyone");
+
+    let err = Client::builder()
+        .default_headers_for("", reqwest::header::HeaderMap::new())
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
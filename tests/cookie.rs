@@ -201,3 +201,170 @@ async fn cookie_store_path() {
     let url = format!("http://{}/subpath", server.addr());
     client.get(&url).send().await.unwrap();
 }
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("reqwest-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[tokio::test]
+async fn file_jar_persists_across_clients() {
+    use reqwest::cookie::{CookieFileFormat, FileJar};
+    use std::sync::Arc;
+
+    let server = server::http(move |req| async move {
+        if req.uri() == "/2" {
+            assert_eq!(req.headers()["cookie"], "persistent=1");
+        }
+        http::Response::builder()
+            .header("Set-Cookie", "persistent=1; Max-Age=3600")
+            .header("Set-Cookie", "session=1")
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let path = temp_path("file-jar");
+    for format in [CookieFileFormat::Json, CookieFileFormat::Netscape] {
+        let jar = Arc::new(FileJar::open(&path, format).unwrap());
+        let client = reqwest::Client::builder()
+            .cookie_provider(jar)
+            .build()
+            .unwrap();
+        client
+            .get(format!("http://{}/", server.addr()))
+            .send()
+            .await
+            .unwrap();
+        // saved when the last reference to the jar goes away
+        drop(client);
+        assert!(path.exists());
+
+        let jar = Arc::new(FileJar::open(&path, format).unwrap());
+        let client = reqwest::Client::builder()
+            .cookie_provider(jar)
+            .build()
+            .unwrap();
+        client
+            .get(format!("http://{}/2", server.addr()))
+            .send()
+            .await
+            .unwrap();
+        drop(client);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn jar_load_netscape() {
+    use reqwest::cookie::{CookieFileFormat, CookieStore, Jar};
+
+    let file = "# Netscape HTTP Cookie File\n\
+        \n\
+        example.com\tFALSE\t/\tFALSE\t4102444800\thost\tonly\n\
+        .example.com\tTRUE\t/\tTRUE\t4102444800\tsub\tdomains\n\
+        #HttpOnly_example.com\tFALSE\t/api\tFALSE\t0\tsession\tyes\n\
+        example.com\tFALSE\t/\tFALSE\t1\texpired\tgone\n";
+    let jar = Jar::load(file.as_bytes(), CookieFileFormat::Netscape).unwrap();
+
+    let cookies = |url: &str| {
+        let header = jar.cookies(&url.parse().unwrap()).unwrap();
+        // the order between domains isn't defined
+        let mut cookies = header
+            .to_str()
+            .unwrap()
+            .split("; ")
+            .map(String::from)
+            .collect::<Vec<_>>();
+        cookies.sort_unstable();
+        cookies.join("; ")
+    };
+    assert_eq!(cookies("http://example.com/"), "host=only");
+    assert_eq!(
+        cookies("https://example.com/api"),
+        "host=only; session=yes; sub=domains"
+    );
+    assert_eq!(cookies("https://www.example.com/"), "sub=domains");
+
+    // session cookies aren't saved
+    let mut saved = Vec::new();
+    jar.save(&mut saved, CookieFileFormat::Netscape).unwrap();
+    let saved = String::from_utf8(saved).unwrap();
+    let mut lines = saved.lines().skip(1).collect::<Vec<_>>();
+    lines.sort_unstable();
+    assert_eq!(
+        lines,
+        [
+            ".example.com\tTRUE\t/\tTRUE\t4102444800\tsub\tdomains",
+            "example.com\tFALSE\t/\tFALSE\t4102444800\thost\tonly",
+        ]
+    );
+
+    let err = Jar::load(&b"example.com\tFALSE\n"[..], CookieFileFormat::Netscape).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn client_cookie_store_inspection() {
+    let server = server::http(move |req| async move {
+        let cookies = req
+            .headers()
+            .get("cookie")
+            .map(|v| v.to_str().unwrap().to_owned())
+            .unwrap_or_default();
+        http::Response::builder()
+            .header("Set-Cookie", "key=val; Max-Age=60")
+            .body(cookies.into())
+            .unwrap()
+    });
+
+    let client = reqwest::Client::builder()
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let store = client.cookie_store().expect("cookie store");
+    let url = format!("http://{}/", server.addr());
+    client.get(&url).send().await.unwrap();
+
+    let cookies = store.all_cookies();
+    assert_eq!(cookies.len(), 1);
+    assert_eq!(cookies[0].name(), "key");
+    assert_eq!(cookies[0].value(), "val");
+    assert_eq!(cookies[0].domain(), Some("127.0.0.1"));
+    assert_eq!(cookies[0].path(), Some("/"));
+    assert!(cookies[0].expires().is_some());
+
+    assert!(store.remove_cookie("127.0.0.1", "/", "key"));
+    assert!(!store.remove_cookie("127.0.0.1", "/", "key"));
+    store.add_cookie_str("added=1", &url.parse().unwrap());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "added=1");
+
+    store.clear();
+    assert!(store.all_cookies().is_empty());
+
+    let client = reqwest::Client::new();
+    assert!(client.cookie_store().is_none());
+}
+
+#[test]
+fn jar_public_suffix_list() {
+    use reqwest::cookie::{CookieStore, Jar, PublicSuffixList};
+
+    let list = PublicSuffixList::from_bytes(
+        b"// ===BEGIN ICANN DOMAINS===\nuk\nco.uk\n// ===END ICANN DOMAINS===\n",
+    )
+    .unwrap();
+    let jar = Jar::default().with_public_suffix_list(list);
+
+    let url = "http://example.co.uk/".parse().unwrap();
+    jar.add_cookie_str("suffix=1; Domain=co.uk", &url);
+    jar.add_cookie_str("site=1; Domain=example.co.uk", &url);
+
+    let other = "http://other.co.uk/".parse().unwrap();
+    assert!(jar.cookies(&other).is_none());
+    assert_eq!(jar.cookies(&url).unwrap(), "site=1");
+
+    assert!(PublicSuffixList::from_bytes(b"").is_err());
+}
@@ -0,0 +1,143 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+#![cfg(feature = "har")]
+mod support;
+
+use reqwest::har::Recorder;
+use serde_json::Value;
+use support::server;
+
+fn entries(har: &Recorder) -> Vec<Value> {
+    let json: Value = serde_json::from_str(&har.to_json()).unwrap();
+    assert_eq!(json["log"]["version"], "1.2");
+    assert_eq!(json["log"]["creator"]["name"], "reqwest");
+    json["log"]["entries"].as_array().unwrap().clone()
+}
+
+fn header<'a>(headers: &'a Value, name: &str) -> Option<&'a str> {
+    headers
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|header| header["name"] == name)
+        .map(|header| header["value"].as_str().unwrap())
+}
+
+#[tokio::test]
+async fn records_redirects_and_bodies() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        if req.uri().path() == "/login" {
+            http::Response::builder()
+                .status(302)
+                .header("location", "/home")
+                .header("set-cookie", "session=secret")
+                .body(Default::default())
+                .unwrap()
+        } else {
+            http::Response::builder()
+                .header("content-type", "text/plain")
+                .body("welcome".into())
+                .unwrap()
+        }
+    });
+
+    let har = Recorder::new().redact_query_param("token");
+    let client = reqwest::Client::builder()
+        .har(har.clone())
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let text = client
+        .post(format!("http://{}/login?token=abc&user=me", server.addr()))
+        .bearer_auth("hunter2")
+        .body("hello")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(text, "welcome");
+
+    let entries = entries(&har);
+    assert_eq!(entries.len(), 2);
+
+    let login = &entries[0];
+    assert_eq!(login["request"]["method"], "POST");
+    assert_eq!(
+        login["request"]["url"],
+        format!(
+            "http://{}/login?token=%5BREDACTED%5D&user=me",
+            server.addr()
+        )
+    );
+    assert_eq!(login["request"]["queryString"][0]["value"], "[REDACTED]");
+    assert_eq!(login["request"]["queryString"][1]["value"], "me");
+    assert_eq!(
+        header(&login["request"]["headers"], "authorization"),
+        Some("Bearer [REDACTED]")
+    );
+    assert_eq!(login["request"]["postData"]["text"], "hello");
+    assert_eq!(login["response"]["status"], 302);
+    assert_eq!(login["response"]["redirectURL"], "/home");
+    assert_eq!(
+        header(&login["response"]["headers"], "set-cookie"),
+        Some("[REDACTED]")
+    );
+
+    let home = &entries[1];
+    assert_eq!(home["request"]["method"], "GET");
+    assert_eq!(home["response"]["status"], 200);
+    assert_eq!(home["response"]["content"]["mimeType"], "text/plain");
+    assert_eq!(home["response"]["content"]["text"], "welcome");
+    assert_eq!(home["response"]["content"]["size"], 7);
+}
+
+#[tokio::test]
+async fn per_request_recorder_and_errors() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async move { http::Response::default() });
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let har = Recorder::new().redact_header(http::header::HeaderName::from_static("x-api-key"));
+
+    client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert!(har.is_empty());
+
+    client
+        .get(format!("http://{}/", server.addr()))
+        .header("x-api-key", "secret")
+        .har(har.clone())
+        .send()
+        .await
+        .unwrap();
+    client
+        .get("http://127.0.0.1:1/")
+        .har(har.clone())
+        .send()
+        .await
+        .unwrap_err();
+
+    let entries = entries(&har);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        header(&entries[0]["request"]["headers"], "x-api-key"),
+        Some("[REDACTED]")
+    );
+    assert_eq!(entries[1]["response"]["status"], 0);
+    assert!(entries[1]["_error"]
+        .as_str()
+        .unwrap()
+        .starts_with("error sending request"));
+
+    har.clear();
+    assert!(har.is_empty());
+}
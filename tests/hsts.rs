@@ -0,0 +1,77 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(feature = "rustls")]
+mod support;
+
+use reqwest::hsts::HstsConfig;
+use support::server;
+
+fn client(server: &server::Server, config: HstsConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .tls_certs_only([server::cert()])
+        .resolve("localhost", server.addr())
+        .hsts(config)
+        .http1_only()
+        .no_proxy()
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn upgrades_hosts_after_the_header() {
+    let _ = env_logger::try_init();
+
+    let server = server::https(|_req| async {
+        http::Response::builder()
+            .header("strict-transport-security", "max-age=60")
+            .body("secure".into())
+            .unwrap()
+    });
+    let client = client(&server, HstsConfig::in_memory());
+    let port = server.addr().port();
+
+    let res = client
+        .get(format!("https://localhost:{port}/"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+
+    let res = client
+        .get(format!("http://localhost:{port}/upgraded"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.url().as_str(),
+        format!("https://localhost:{port}/upgraded")
+    );
+    assert_eq!(res.text().await.unwrap(), "secure");
+}
+
+#[tokio::test]
+async fn upgrades_preloaded_hosts_and_redirects() {
+    let _ = env_logger::try_init();
+
+    let server = server::https(|req| async move {
+        if req.uri().path() == "/redirect" {
+            let host = req.headers()["host"].to_str().unwrap();
+            let location = format!("http://{host}/dst");
+            return http::Response::builder()
+                .status(302)
+                .header("location", location)
+                .body(Default::default())
+                .unwrap();
+        }
+        http::Response::new(req.uri().path().to_owned().into())
+    });
+    let client = client(&server, HstsConfig::in_memory().preload("localhost", false));
+    let port = server.addr().port();
+
+    let res = client
+        .get(format!("http://localhost:{port}/redirect"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.url().as_str(), format!("https://localhost:{port}/dst"));
+    assert_eq!(res.text().await.unwrap(), "/dst");
+}
@@ -297,3 +297,65 @@ async fn http3_request_stream_error() {
         .unwrap();
     assert!(err.is_body());
 }
+
+#[tokio::test]
+async fn http3_alt_svc_upgrade() {
+    let h3 = server::Http3::new().build(|_| async { http::Response::new("over h3".into()) });
+    let h3_port = h3.addr().port();
+    let server = server::https(move |_| async move {
+        http::Response::builder()
+            .header("alt-svc", format!(r#"h3="[::1]:{h3_port}"; ma=60"#))
+            .body("over tcp".into())
+            .unwrap()
+    });
+
+    let url = format!("https://{}/", server.addr());
+    let client = reqwest::Client::builder()
+        .http3_alt_svc(true)
+        // don't keep a connection the server would wait on when dropped
+        .http1_only()
+        .pool_max_idle_per_host(0)
+        .danger_accept_invalid_certs(true)
+        .build()
+        .expect("client builder");
+
+    let res = client.get(&url).send().await.expect("request");
+    assert_ne!(res.version(), http::Version::HTTP_3);
+    assert_eq!(res.text().await.unwrap(), "over tcp");
+
+    let res = client.get(&url).send().await.expect("request");
+    assert_eq!(res.version(), http::Version::HTTP_3);
+    assert_eq!(res.text().await.unwrap(), "over h3");
+
+    // Only idempotent requests are upgraded.
+    let res = client.post(&url).send().await.expect("request");
+    assert_ne!(res.version(), http::Version::HTTP_3);
+}
+
+#[tokio::test]
+async fn http3_alt_svc_falls_back() {
+    let h3_port = find_free_tcp_addr().await.port();
+    let server = server::https(move |_| async move {
+        http::Response::builder()
+            .header("alt-svc", format!(r#"h3="[::1]:{h3_port}""#))
+            .body("over tcp".into())
+            .unwrap()
+    });
+
+    let url = format!("https://{}/", server.addr());
+    let client = reqwest::Client::builder()
+        .http3_alt_svc(true)
+        // don't keep a connection the server would wait on when dropped
+        .http1_only()
+        .pool_max_idle_per_host(0)
+        .http3_max_idle_timeout(std::time::Duration::from_millis(20))
+        .danger_accept_invalid_certs(true)
+        .build()
+        .expect("client builder");
+
+    for _ in 0..3 {
+        let res = client.get(&url).send().await.expect("request");
+        assert_ne!(res.version(), http::Version::HTTP_3);
+        assert_eq!(res.text().await.unwrap(), "over tcp");
+    }
+}
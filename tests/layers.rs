@@ -0,0 +1,183 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+mod support;
+
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tower::layer::layer_fn;
+use tower::service_fn;
+use tower::timeout::TimeoutLayer;
+use tower::util::{MapRequestLayer, MapResponseLayer};
+
+use support::server;
+
+#[tokio::test]
+async fn layer_maps_request_and_response() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["x-layer"], "1");
+        http::Response::new("hello".into())
+    });
+
+    let client = reqwest::Client::builder()
+        .layer(MapRequestLayer::new(
+            |mut req: http::Request<reqwest::Body>| {
+                req.headers_mut()
+                    .insert("x-layer", http::HeaderValue::from_static("1"));
+                req
+            },
+        ))
+        .layer(MapResponseLayer::new(
+            |mut res: http::Response<reqwest::Body>| {
+                res.headers_mut()
+                    .insert("x-seen", http::HeaderValue::from_static("1"));
+                res
+            },
+        ))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("http://{}/layer", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.headers()["x-seen"], "1");
+    assert_eq!(res.text().await.unwrap(), "hello");
+}
+
+#[tokio::test]
+async fn layer_answers_without_sending() {
+    let _ = env_logger::try_init();
+
+    let client = reqwest::Client::builder()
+        .layer(layer_fn(|_inner| {
+            service_fn(|_req: http::Request<reqwest::Body>| async {
+                Ok::<_, Box<dyn std::error::Error + Send + Sync>>(http::Response::new(
+                    reqwest::Body::from("cached"),
+                ))
+            })
+        }))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    // nothing listens here
+    let url = "http://127.0.0.1:1/cached";
+    let res = client.get(url).send().await.unwrap();
+
+    assert_eq!(res.url().as_str(), url);
+    assert_eq!(res.text().await.unwrap(), "cached");
+}
+
+#[tokio::test]
+async fn layer_timeout() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        http::Response::default()
+    });
+
+    let client = reqwest::Client::builder()
+        .layer(TimeoutLayer::new(Duration::from_millis(50)))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/slow", server.addr());
+    let err = client.get(&url).send().await.unwrap_err();
+
+    assert!(err.is_timeout());
+    assert_eq!(err.url().map(|u| u.as_str()), Some(url.as_str()));
+}
+
+#[tokio::test]
+async fn on_request_and_on_response() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["x-hook"], "1");
+        http::Response::default()
+    });
+
+    let seen = Arc::new(AtomicUsize::new(0));
+    let counter = seen.clone();
+    let client = reqwest::Client::builder()
+        .on_request(|req| {
+            if req.uri().path() == "/cached" {
+                return ControlFlow::Break(http::Response::new("cached".into()));
+            }
+            req.headers_mut()
+                .insert("x-hook", http::HeaderValue::from_static("1"));
+            ControlFlow::Continue(())
+        })
+        .on_response(move |_res| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("http://{}/hook", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client
+        .get(format!("http://{}/cached", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "cached");
+
+    // The response hook was added last, so it wraps the request hook and
+    // sees the cached response too.
+    assert_eq!(seen.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn on_request_async_and_on_response_async() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["x-hook"], "async");
+        http::Response::builder()
+            .header("x-secret", "hunter2")
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let client = reqwest::Client::builder()
+        .on_request_async(|mut req| async move {
+            tokio::task::yield_now().await;
+            req.headers_mut()
+                .insert("x-hook", http::HeaderValue::from_static("async"));
+            ControlFlow::Continue(req)
+        })
+        .on_response_async(|mut res| async move {
+            tokio::task::yield_now().await;
+            res.headers_mut().remove("x-secret");
+            res
+        })
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("http://{}/hook", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert!(res.headers().get("x-secret").is_none());
+}
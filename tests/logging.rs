@@ -0,0 +1,108 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+mod support;
+
+use std::sync::Mutex;
+
+use reqwest::logging::LoggingLayer;
+use support::server;
+
+/// Keeps the lines logged by the logging layer.
+struct Lines(Mutex<Vec<String>>);
+
+impl log::Log for Lines {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.target() == "reqwest::logging"
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if self.enabled(record.metadata()) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LINES: Lines = Lines(Mutex::new(Vec::new()));
+
+#[tokio::test]
+async fn logs_requests_and_responses_with_redaction() {
+    log::set_logger(&LINES).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .status(201)
+            .header("set-cookie", "session=secret")
+            .header("x-secret", "hidden")
+            .body("created, with a long body".into())
+            .unwrap()
+    });
+
+    let client = reqwest::Client::builder()
+        .layer(
+            LoggingLayer::new()
+                .headers(true)
+                .bodies(7)
+                .redact_header(http::HeaderName::from_static("x-secret")),
+        )
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let body = client
+        .post(format!("http://user:pass@{}/items", server.addr()))
+        .header(
+            "authorization",
+            "Negotiate YIIGhgYGKwYBBQUCoIIGejCCBnagMDAu",
+        )
+        .body("name=me")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(body, "created, with a long body");
+
+    let lines = LINES.0.lock().unwrap().clone();
+    assert_eq!(lines.len(), 3, "{lines:#?}");
+
+    let request = &lines[0];
+    assert!(
+        request.starts_with(&format!(
+            "request id=1 method=POST url=\"http://{}/items\"",
+            server.addr()
+        )),
+        "{request}"
+    );
+    assert!(
+        request.contains(" header.authorization=\"Negotiate [REDACTED]\""),
+        "{request}"
+    );
+    assert!(!request.contains("pass"), "{request}");
+    assert!(
+        request.ends_with(" body.size=7 body=\"name=me\""),
+        "{request}"
+    );
+
+    let response = &lines[1];
+    assert!(
+        response.starts_with("response id=1 status=201 elapsed_ms="),
+        "{response}"
+    );
+    assert!(
+        response.contains(" header.set-cookie=\"[REDACTED]\""),
+        "{response}"
+    );
+    assert!(
+        response.contains(" header.x-secret=\"[REDACTED]\""),
+        "{response}"
+    );
+
+    assert_eq!(
+        lines[2],
+        "response.body id=1 body.size=25 body=\"created\" body.truncated=true"
+    );
+}
@@ -0,0 +1,202 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+#![cfg(feature = "metrics")]
+mod support;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use metrics::{
+    Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+    SharedString, Unit,
+};
+
+use support::server;
+
+/// Sums up every counter and counts the samples of every histogram, by
+/// name and labels, such as `http.client.requests{http.request.method=GET}`.
+#[derive(Clone, Default)]
+struct Recorded(Arc<Mutex<HashMap<String, u64>>>);
+
+impl Recorded {
+    fn get(&self, key: &str) -> u64 {
+        self.0.lock().unwrap().get(key).copied().unwrap_or(0)
+    }
+
+    fn metric(&self, key: &Key) -> Arc<Metric> {
+        let mut labels = key
+            .labels()
+            .map(|label| format!("{}={}", label.key(), label.value()))
+            .collect::<Vec<_>>();
+        labels.sort();
+        let key = if labels.is_empty() {
+            key.name().to_owned()
+        } else {
+            format!("{}{{{}}}", key.name(), labels.join(","))
+        };
+        Arc::new(Metric {
+            key,
+            recorded: self.clone(),
+        })
+    }
+}
+
+struct Metric {
+    key: String,
+    recorded: Recorded,
+}
+
+impl Metric {
+    fn add(&self, value: u64) {
+        *self
+            .recorded
+            .0
+            .lock()
+            .unwrap()
+            .entry(self.key.clone())
+            .or_default() += value;
+    }
+}
+
+impl CounterFn for Metric {
+    fn increment(&self, value: u64) {
+        self.add(value);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.recorded
+            .0
+            .lock()
+            .unwrap()
+            .insert(self.key.clone(), value);
+    }
+}
+
+impl HistogramFn for Metric {
+    fn record(&self, _: f64) {
+        self.add(1);
+    }
+}
+
+impl Recorder for Recorded {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.metric(key))
+    }
+
+    fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(self.metric(key))
+    }
+}
+
+#[tokio::test]
+async fn requests_and_connection_reuse() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        let status = if req.uri().path() == "/missing" {
+            http::StatusCode::NOT_FOUND
+        } else {
+            http::StatusCode::OK
+        };
+        http::Response::builder()
+            .status(status)
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let recorded = Recorded::default();
+    let _guard = metrics::set_default_local_recorder(&recorded);
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    for path in ["/", "/", "/missing"] {
+        let url = format!("http://{}{}", server.addr(), path);
+        client.get(url).send().await.unwrap().bytes().await.unwrap();
+    }
+    let err = client.get("http://127.0.0.1:1/").send().await.unwrap_err();
+    assert!(err.is_connect());
+
+    assert_eq!(
+        recorded.get("http.client.requests{http.request.method=GET,http.response.status_code=200}"),
+        2
+    );
+    assert_eq!(
+        recorded.get("http.client.requests{http.request.method=GET,http.response.status_code=404}"),
+        1
+    );
+    assert_eq!(
+        recorded.get("http.client.requests{error.type=connect,http.request.method=GET}"),
+        1
+    );
+    assert_eq!(
+        recorded.get(
+            "http.client.request.duration{http.request.method=GET,http.response.status_code=200}"
+        ),
+        2
+    );
+    assert_eq!(recorded.get("http.client.connections.opened"), 1);
+    assert_eq!(recorded.get("http.client.connections.reused"), 2);
+}
+
+#[tokio::test]
+async fn retries() {
+    let _ = env_logger::try_init();
+
+    let cnt = Arc::new(AtomicUsize::new(0));
+    let server = server::http(move |_req| {
+        let cnt = cnt.clone();
+        async move {
+            let status = if cnt.fetch_add(1, Ordering::Relaxed) == 0 {
+                http::StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                http::StatusCode::OK
+            };
+            http::Response::builder()
+                .status(status)
+                .body(Default::default())
+                .unwrap()
+        }
+    });
+
+    let recorded = Recorded::default();
+    let _guard = metrics::set_default_local_recorder(&recorded);
+
+    let retries = reqwest::retry::for_host(server.addr().ip().to_string()).classify_fn(|req_rep| {
+        if req_rep.status() == Some(http::StatusCode::SERVICE_UNAVAILABLE) {
+            req_rep.retryable()
+        } else {
+            req_rep.success()
+        }
+    });
+    let res = reqwest::Client::builder()
+        .retry(retries)
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+
+    assert_eq!(recorded.get("http.client.retries"), 1);
+    // Only the final response is counted.
+    assert_eq!(
+        recorded.get("http.client.requests{http.request.method=GET,http.response.status_code=200}"),
+        1
+    );
+    assert_eq!(
+        recorded.get("http.client.requests{http.request.method=GET,http.response.status_code=503}"),
+        0
+    );
+}
@@ -0,0 +1,108 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn mock_transport_sees_the_request_on_the_wire() {
+    let client = reqwest::Client::builder()
+        .user_agent("mock-test")
+        .mock_transport(|req| {
+            if req.uri().path() == "/old" {
+                return Ok(http::Response::builder()
+                    .status(301)
+                    .header("location", "/new")
+                    .body(Default::default())
+                    .unwrap());
+            }
+            assert_eq!(req.uri(), "http://api.example/new");
+            assert_eq!(req.headers()["user-agent"], "mock-test");
+            assert_eq!(req.method(), "GET");
+            Ok(http::Response::builder()
+                .header("content-type", "text/plain")
+                .body("moved here".into())
+                .unwrap())
+        })
+        .build()
+        .unwrap();
+
+    let res = client.get("http://api.example/old").send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.url().as_str(), "http://api.example/new");
+    assert_eq!(res.text().await.unwrap(), "moved here");
+
+    let client = reqwest::Client::builder()
+        .mock_transport(|req| {
+            assert_eq!(req.body().as_ref(), b"ping");
+            Err("connection reset".into())
+        })
+        .build()
+        .unwrap();
+    let err = client
+        .post("http://api.example/")
+        .body("ping")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_request(), "{err:?}");
+    assert_eq!(err.url().unwrap().as_str(), "http://api.example/");
+}
+
+#[tokio::test]
+async fn mock_transport_is_retried() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = attempts.clone();
+    let retries = reqwest::retry::for_host("api.example").classify_fn(|req_rep| {
+        if req_rep.status() == Some(http::StatusCode::SERVICE_UNAVAILABLE) {
+            req_rep.retryable()
+        } else {
+            req_rep.success()
+        }
+    });
+    let client = reqwest::Client::builder()
+        .retry(retries)
+        .mock_transport(move |_req| {
+            let status = if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                http::StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                http::StatusCode::OK
+            };
+            Ok(http::Response::builder()
+                .status(status)
+                .body(Default::default())
+                .unwrap())
+        })
+        .build()
+        .unwrap();
+
+    let res = client.get("http://api.example/").send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(all(feature = "negotiate", not(windows)))]
+#[tokio::test]
+async fn mock_transport_negotiate_falls_back_to_basic() {
+    let client = reqwest::Client::builder()
+        .negotiate_with_credentials("user", "secret")
+        .mock_transport(|req| match req.headers().get("authorization") {
+            Some(auth) => {
+                assert_eq!(auth, "Basic dXNlcjpzZWNyZXQ=");
+                Ok(http::Response::new("welcome".into()))
+            }
+            None => Ok(http::Response::builder()
+                .status(401)
+                .header("www-authenticate", "Negotiate")
+                .header("www-authenticate", "Basic realm=\"api\"")
+                .body(Default::default())
+                .unwrap()),
+        })
+        .build()
+        .unwrap();
+
+    // Negotiate is applied by `Client::execute()`.
+    let req = client.get("http://api.example/").build().unwrap();
+    let res = client.execute(req).await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "welcome");
+}
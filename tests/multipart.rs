@@ -234,3 +234,185 @@ async fn async_impl_file_part() {
     assert_eq!(res.url().as_str(), &url);
     assert_eq!(res.status(), reqwest::StatusCode::OK);
 }
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn async_read_part() {
+    let _ = env_logger::try_init();
+
+    let form = reqwest::multipart::Form::new()
+        .part(
+            "sized",
+            reqwest::multipart::Part::async_read(&b"known length"[..], Some(12)),
+        )
+        .part(
+            "unsized",
+            reqwest::multipart::Part::async_read(&b"unknown length"[..], None),
+        );
+
+    let expected_body = format!(
+        "\
+         --{0}\r\n\
+         Content-Disposition: form-data; name=\"sized\"\r\n\
+         \r\n\
+         known length\r\n\
+         --{0}\r\n\
+         Content-Disposition: form-data; name=\"unsized\"\r\n\
+         \r\n\
+         unknown length\r\n\
+         --{0}--\r\n\
+         ",
+        form.boundary()
+    );
+
+    let server = server::http(move |req| {
+        let expected_body = expected_body.clone();
+        async move {
+            // one part has no known length, so neither does the form
+            assert_eq!(req.headers()["transfer-encoding"], "chunked");
+            let full = req.collect().await.unwrap().to_bytes();
+
+            assert_eq!(full, expected_body.as_bytes());
+
+            http::Response::default()
+        }
+    });
+
+    let sized = reqwest::multipart::Form::new().part(
+        "sized",
+        reqwest::multipart::Part::async_read(&b"known length"[..], Some(12)),
+    );
+    let expected_len = format!(
+        "--{0}\r\nContent-Disposition: form-data; name=\"sized\"\r\n\r\nknown length\r\n--{0}--\r\n",
+        sized.boundary()
+    )
+    .len();
+    let sized_server = server::http(move |req| async move {
+        assert_eq!(req.headers()["content-length"], expected_len.to_string());
+        assert_eq!(req.collect().await.unwrap().to_bytes().len(), expected_len);
+        http::Response::default()
+    });
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(format!("http://{}/multipart/4", server.addr()))
+        .multipart(form)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client
+        .post(format!("http://{}/multipart/5", sized_server.addr()))
+        .multipart(sized)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn multipart_response() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .header("content-type", "multipart/mixed; boundary=\"sep\"")
+            .body(
+                "preamble\r\n\
+                 --sep\r\n\
+                 Content-Type: text/plain\r\n\
+                 X-Id: 1\r\n\
+                 \r\n\
+                 first\r\n\
+                 --sep \r\n\
+                 \r\n\
+                 second\r\n\
+                 --sep--\r\n\
+                 epilogue"
+                    .into(),
+            )
+            .unwrap()
+    });
+
+    let res = reqwest::get(format!("http://{}/multipart", server.addr()))
+        .await
+        .unwrap();
+    let mut parts = res.multipart().unwrap();
+    assert_eq!(parts.boundary(), "sep");
+
+    let part = parts.next_part().await.unwrap().unwrap();
+    assert_eq!(part.headers()["content-type"], "text/plain");
+    assert_eq!(part.headers()["x-id"], "1");
+    assert_eq!(part.text().await.unwrap(), "first");
+
+    let part = parts.next_part().await.unwrap().unwrap();
+    assert!(part.headers().is_empty());
+    assert_eq!(part.text().await.unwrap(), "second");
+
+    assert!(parts.next_part().await.unwrap().is_none());
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn multipart_response_byteranges_split() {
+    use futures_util::stream;
+
+    let body = "--range\r\n\
+                Content-Range: bytes 0-4/10\r\n\
+                \r\n\
+                hello\r\n\
+                --range\r\n\
+                Content-Range: bytes 5-9/10\r\n\
+                \r\n\
+                world\r\n\
+                --range--\r\n";
+    // deliver the body a byte at a time
+    let chunks = body
+        .bytes()
+        .map(|b| Ok::<_, std::io::Error>(vec![b]))
+        .collect::<Vec<_>>();
+    let res = reqwest::Response::from(
+        http::Response::builder()
+            .header("content-type", "multipart/byteranges; boundary=range")
+            .body(reqwest::Body::wrap_stream(stream::iter(chunks)))
+            .unwrap(),
+    );
+
+    let mut parts = res.multipart().unwrap();
+
+    // the first part is skipped without reading its body
+    let part = parts.next_part().await.unwrap().unwrap();
+    assert_eq!(part.headers()["content-range"], "bytes 0-4/10");
+
+    let mut part = parts.next_part().await.unwrap().unwrap();
+    assert_eq!(part.headers()["content-range"], "bytes 5-9/10");
+    let mut data = Vec::new();
+    while let Some(chunk) = part.chunk().await.unwrap() {
+        data.extend_from_slice(&chunk);
+    }
+    assert_eq!(data, b"world");
+
+    assert!(parts.next_part().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn multipart_response_errors() {
+    let res = reqwest::Response::from(
+        http::Response::builder()
+            .header("content-type", "text/plain")
+            .body("--sep--")
+            .unwrap(),
+    );
+    assert!(res.multipart().unwrap_err().is_decode());
+
+    let res = reqwest::Response::from(
+        http::Response::builder()
+            .header("content-type", "multipart/mixed; boundary=sep")
+            .body("--sep\r\n\r\ntruncated")
+            .unwrap(),
+    );
+    let mut parts = res.multipart().unwrap();
+    let part = parts.next_part().await.unwrap().unwrap();
+    assert!(part.bytes().await.unwrap_err().is_decode());
+}
@@ -8,8 +8,13 @@
 #![cfg(all(not(target_arch = "wasm32"), feature = "negotiate"))]
 
 mod support;
+use support::negotiate::{self, Authenticator};
 use support::server;
 
+use reqwest::negotiate::{IpLiteralPolicy, Outcome};
+
+use base64::Engine as _;
+
 use http::StatusCode;
 use std::sync::{Arc, Mutex};
 
@@ -228,6 +233,454 @@ async fn test_negotiate_disabled_by_default() {
     assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
 }
 
+fn challenge_token(resp: &reqwest::Response, scheme: &str) -> Vec<u8> {
+    let value = resp
+        .headers()
+        .get(http::header::WWW_AUTHENTICATE)
+        .expect("challenge")
+        .to_str()
+        .unwrap();
+    let token = value.strip_prefix(scheme).expect("challenge scheme").trim();
+    base64::engine::general_purpose::STANDARD
+        .decode(token)
+        .unwrap()
+}
+
+fn authorization(scheme: &str, token: &[u8]) -> String {
+    format!(
+        "{} {}",
+        scheme,
+        base64::engine::general_purpose::STANDARD.encode(token)
+    )
+}
+
+#[tokio::test]
+async fn test_mock_ntlm_handshake() {
+    // Drive the mock NTLM server by hand, as SSPI would
+    let auth = Authenticator::ntlm();
+    let server = auth.serve(|_req| async { http::Response::new("authenticated".into()) });
+    let url = format!("http://{}/", server.addr());
+    let client = reqwest::Client::new();
+
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.headers()[http::header::WWW_AUTHENTICATE], "NTLM");
+
+    let resp = client
+        .get(&url)
+        .header(
+            http::header::AUTHORIZATION,
+            authorization("NTLM", &negotiate::type1()),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let type2 = challenge_token(&resp, "NTLM");
+    assert_eq!(&type2[..8], negotiate::NTLMSSP);
+    assert_eq!(negotiate::ntlm_message_type(&type2), Some(2));
+    assert_eq!(type2[24..32], negotiate::SERVER_CHALLENGE);
+
+    let resp = client
+        .get(&url)
+        .header(
+            http::header::AUTHORIZATION,
+            authorization("NTLM", &negotiate::type3()),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.text().await.unwrap(), "authenticated");
+
+    let seen = auth.seen();
+    assert_eq!(seen.len(), 3);
+    assert!(seen[0].scheme.is_none());
+    assert_eq!(
+        negotiate::ntlm_message_type(seen[2].token.as_ref().unwrap()),
+        Some(3)
+    );
+}
+
+#[tokio::test]
+async fn test_mock_ntlm_proxy_and_origin_handshake() {
+    // The proxy challenges with 407 before the origin sees anything
+    let proxy = Authenticator::ntlm();
+    let origin = Authenticator::ntlm().behind_proxy(proxy.clone());
+    let server = origin.serve(|_req| async { http::Response::new("authenticated".into()) });
+    let url = format!("http://{}/", server.addr());
+    let client = reqwest::Client::new();
+
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::PROXY_AUTHENTICATION_REQUIRED);
+    assert_eq!(resp.headers()[http::header::PROXY_AUTHENTICATE], "NTLM");
+
+    let proxy_type3 = authorization("NTLM", &negotiate::type3());
+    let resp = client
+        .get(&url)
+        .header(http::header::PROXY_AUTHORIZATION, &proxy_type3)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.headers()[http::header::WWW_AUTHENTICATE], "NTLM");
+
+    // Each header is only read by its own side
+    let resp = client
+        .get(&url)
+        .header(http::header::PROXY_AUTHORIZATION, &proxy_type3)
+        .header(
+            http::header::AUTHORIZATION,
+            authorization("NTLM", &negotiate::type3()),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(proxy.seen().len(), 3);
+    let origin_seen = origin.seen();
+    assert_eq!(origin_seen.len(), 2);
+    assert!(origin_seen[0].scheme.is_none());
+}
+
+#[tokio::test]
+async fn test_mock_negotiate_multi_round_with_mutual_auth() {
+    // Several 401 rounds carrying SPNEGO tokens, then a final mutual-auth token
+    let auth = Authenticator::negotiate().rounds(2).mutual_auth(true);
+    let server = auth.serve(|_req| async { http::Response::new("authenticated".into()) });
+    let url = format!("http://{}/", server.addr());
+    let client = reqwest::Client::new();
+
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.headers()[http::header::WWW_AUTHENTICATE], "Negotiate");
+
+    let mut tokens = Vec::new();
+    let mut resp = resp;
+    for round in 1..=3 {
+        resp = client
+            .get(&url)
+            .header(
+                http::header::AUTHORIZATION,
+                authorization("Negotiate", &[round; 8]),
+            )
+            .send()
+            .await
+            .unwrap();
+        tokens.push(challenge_token(&resp, "Negotiate"));
+        if resp.status() == StatusCode::OK {
+            break;
+        }
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(tokens.len(), 3);
+    // The first continuation token wraps the NTLM challenge
+    assert_eq!(tokens[0][0], 0xa1);
+    assert_eq!(negotiate::ntlm_message_type(&tokens[0]), Some(2));
+    assert_eq!(negotiate::ntlm_message_type(&tokens[1]), None);
+    // The final token only says the negotiation completed
+    assert_eq!(tokens[2], negotiate::neg_token_resp(0, None));
+    assert_eq!(auth.seen().len(), 4);
+}
+
+#[cfg(windows)]
+#[tokio::test]
+async fn test_ntlm_full_loop_against_mock() {
+    // SSPI answers the mock Type 2 challenge with a Type 3 message
+    let auth = Authenticator::ntlm();
+    let server = auth.serve(|_req| async { http::Response::new("authenticated".into()) });
+
+    let client = reqwest::Client::builder()
+        .negotiate_with_credentials("MOCK\\testuser", "testpass")
+        .build()
+        .unwrap();
+
+    let req = client
+        .get(format!("http://{}/", server.addr()))
+        .build()
+        .unwrap();
+    let resp = client.execute(req).await.unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let types: Vec<_> = auth
+        .seen()
+        .iter()
+        .map(|seen| seen.token.as_deref().and_then(negotiate::ntlm_message_type))
+        .collect();
+    assert_eq!(types, [None, Some(1), Some(3)]);
+}
+
+#[cfg(windows)]
+#[tokio::test]
+async fn test_negotiate_full_loop_against_mock() {
+    // Without a KDC, SSPI negotiates NTLM inside SPNEGO
+    let auth = Authenticator::negotiate().mutual_auth(true);
+    let server = auth.serve(|_req| async { http::Response::new("authenticated".into()) });
+
+    let client = reqwest::Client::builder()
+        .negotiate_with_credentials("MOCK\\testuser", "testpass")
+        .build()
+        .unwrap();
+
+    let req = client
+        .get(format!("http://localhost:{}/", server.addr().port()))
+        .build()
+        .unwrap();
+    let resp = client.execute(req).await.unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp
+        .headers()
+        .get(http::header::WWW_AUTHENTICATE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("Negotiate "));
+    assert_eq!(auth.seen().len(), 3);
+}
+
+#[tokio::test]
+async fn test_check_negotiate_not_required() {
+    let server = server::http(move |_req| async { http::Response::new("open".into()) });
+
+    let report = reqwest::Client::new()
+        .check_negotiate(format!("http://{}/", server.addr()))
+        .await
+        .unwrap();
+
+    assert_eq!(*report.outcome(), Outcome::NotRequired);
+    assert_eq!(report.status(), StatusCode::OK);
+    assert!(report.challenges().is_empty());
+    assert!(report.rounds().is_empty());
+}
+
+#[tokio::test]
+async fn test_check_negotiate_reports_challenges() {
+    let auth = Authenticator::negotiate().offer_ntlm(true);
+    let server = auth.serve(|_req| async { http::Response::new("authenticated".into()) });
+
+    let client = reqwest::Client::builder()
+        .negotiate_with_credentials("MOCK\\testuser", "testpass")
+        .build()
+        .unwrap();
+    let report = client
+        .check_negotiate(format!("http://localhost:{}/", server.addr().port()))
+        .await
+        .unwrap();
+
+    assert_eq!(report.challenges(), ["Negotiate", "NTLM"]);
+    assert_eq!(report.spn(), "HTTP/localhost");
+
+    if cfg!(windows) {
+        // Without a KDC, SSPI negotiates NTLM inside SPNEGO
+        assert_eq!(*report.outcome(), Outcome::Authenticated);
+        assert_eq!(report.status(), StatusCode::OK);
+        assert_eq!(report.rounds().len(), 2);
+        assert!(report
+            .rounds()
+            .iter()
+            .all(|round| round.scheme() == "Negotiate"));
+        assert_eq!(report.rounds()[0].status(), StatusCode::UNAUTHORIZED);
+        assert!(report.rounds()[0].server_token_len().is_some());
+    } else {
+        assert!(matches!(report.outcome(), Outcome::Failed(_)));
+        assert_eq!(report.status(), StatusCode::UNAUTHORIZED);
+        assert!(report.rounds().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_check_negotiate_unsupported() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .header("WWW-Authenticate", "Basic realm=\"test\"")
+            .body("unauthorized".into())
+            .unwrap()
+    });
+
+    let report = reqwest::Client::new()
+        .check_negotiate(format!("http://{}/", server.addr()))
+        .await
+        .unwrap();
+
+    assert_eq!(*report.outcome(), Outcome::Unsupported);
+    assert_eq!(report.challenges(), ["Basic"]);
+}
+
+#[tokio::test]
+async fn test_check_negotiate_ip_literal_fail_fast() {
+    let auth = Authenticator::negotiate();
+    let server = auth.serve(|_req| async { http::Response::new("authenticated".into()) });
+
+    let client = reqwest::Client::builder()
+        .negotiate()
+        .negotiate_ip_literal_policy(IpLiteralPolicy::FailFast)
+        .build()
+        .unwrap();
+    let report = client
+        .check_negotiate(format!("http://{}/", server.addr()))
+        .await
+        .unwrap();
+
+    match report.outcome() {
+        Outcome::Failed(reason) => assert!(reason.contains("127.0.0.1"), "{}", reason),
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+    // No token was sent
+    assert!(report.rounds().is_empty());
+    assert_eq!(auth.seen().len(), 1);
+}
+
+#[tokio::test]
+async fn test_check_negotiate_ip_literal_skips_to_ntlm() {
+    // Negotiate only, so there is nothing to skip to
+    let auth = Authenticator::negotiate();
+    let server = auth.serve(|_req| async { http::Response::new("authenticated".into()) });
+
+    let report = reqwest::Client::new()
+        .check_negotiate(format!("http://{}/", server.addr()))
+        .await
+        .unwrap();
+
+    assert!(matches!(report.outcome(), Outcome::Failed(_)));
+    assert!(report.rounds().is_empty());
+    assert_eq!(auth.seen().len(), 1);
+}
+
+#[tokio::test]
+async fn test_kerberos_only_does_not_fall_back_to_basic() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let server = server::http(move |req| {
+        seen_clone
+            .lock()
+            .unwrap()
+            .push(req.headers().get(http::header::AUTHORIZATION).cloned());
+        async move {
+            http::Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header("WWW-Authenticate", "Basic realm=\"test\"")
+                .body("unauthorized".into())
+                .unwrap()
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .negotiate_with_credentials("testuser", "testpass")
+        .negotiate_kerberos_only(true)
+        .build()
+        .unwrap();
+
+    let req = client
+        .get(format!("http://{}/", server.addr()))
+        .build()
+        .unwrap();
+    let err = client.execute(req).await.unwrap_err();
+
+    assert!(err.is_negotiate());
+    let source = std::error::Error::source(&err).unwrap().to_string();
+    assert!(source.contains("doesn't offer Negotiate"), "{}", source);
+    // The credentials were never sent
+    assert_eq!(*seen.lock().unwrap(), [None]);
+}
+
+#[tokio::test]
+async fn test_negotiate_credentials_for_domain() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let server = server::http(move |req| {
+        let authorization = req.headers().get(http::header::AUTHORIZATION).cloned();
+        seen_clone.lock().unwrap().push(authorization.clone());
+        async move {
+            match authorization {
+                Some(_) => http::Response::new("authenticated".into()),
+                None => http::Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .header("WWW-Authenticate", "Basic realm=\"test\"")
+                    .body("unauthorized".into())
+                    .unwrap(),
+            }
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .negotiate_with_credentials("testuser", "testpass")
+        .negotiate_credentials_for("*.emea.corp", "EMEA\\other", "otherpass")
+        .negotiate_credentials_for("LOCALHOST", "LOCAL\\user", "localpass")
+        .build()
+        .unwrap();
+
+    let req = client
+        .get(format!("http://localhost:{}/", server.addr().port()))
+        .build()
+        .unwrap();
+    let resp = client.execute(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    let basic = seen[1].as_ref().unwrap().to_str().unwrap();
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(basic.strip_prefix("Basic ").unwrap())
+        .unwrap();
+    assert_eq!(decoded, b"LOCAL\\user:localpass");
+}
+
+#[tokio::test]
+async fn test_check_negotiate_kerberos_only_rejects_ntlm() {
+    let auth = Authenticator::ntlm();
+    let server = auth.serve(|_req| async { http::Response::new("authenticated".into()) });
+
+    let client = reqwest::Client::builder()
+        .negotiate()
+        .negotiate_kerberos_only(true)
+        .build()
+        .unwrap();
+    let report = client
+        .check_negotiate(format!("http://localhost:{}/", server.addr().port()))
+        .await
+        .unwrap();
+
+    assert_eq!(report.challenges(), ["NTLM"]);
+    assert!(matches!(report.outcome(), Outcome::Failed(_)));
+    assert!(report.rounds().is_empty());
+}
+
+#[cfg(windows)]
+#[tokio::test]
+async fn test_negotiate_through_negotiate_proxy() {
+    // The proxy and the origin each ask for Negotiate, in the same server
+    let proxy = Authenticator::negotiate();
+    let origin = Authenticator::negotiate().behind_proxy(proxy.clone());
+    let server = origin.serve(|_req| async { http::Response::new("authenticated".into()) });
+
+    let client = reqwest::Client::builder()
+        .proxy(
+            reqwest::Proxy::http(format!("http://{}", server.addr()))
+                .unwrap()
+                .negotiate_auth(),
+        )
+        .negotiate_with_credentials("MOCK\\testuser", "testpass")
+        .build()
+        .unwrap();
+
+    let req = client.get("http://origin.test/").build().unwrap();
+    let resp = client.execute(req).await.unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    // The origin saw its whole handshake
+    let origin_tokens: Vec<_> = origin.seen().iter().map(|s| s.token.is_some()).collect();
+    assert_eq!(origin_tokens, [false, true, true]);
+    // And the proxy, which keeps no state, authenticated each of those
+    // requests with a handshake of its own
+    let proxy_tokens: Vec<_> = proxy.seen().iter().map(|s| s.token.is_some()).collect();
+    assert_eq!(proxy_tokens, [false, true, true].repeat(3));
+}
+
 #[cfg(windows)]
 #[test]
 fn test_windows_platform_available() {
@@ -235,3 +688,60 @@ fn test_windows_platform_available() {
     assert!(cfg!(windows));
     assert!(cfg!(feature = "negotiate"));
 }
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_blocking_negotiate_with_credentials_fallback_to_basic() {
+    // The blocking client runs the same loop as the async one
+    let server = server::http(move |req| async move {
+        match req.headers().get(http::header::AUTHORIZATION) {
+            Some(auth) if auth.to_str().unwrap().starts_with("Basic ") => {
+                http::Response::new("authenticated".into())
+            }
+            _ => http::Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header("WWW-Authenticate", "Basic realm=\"test\"")
+                .body("unauthorized".into())
+                .unwrap(),
+        }
+    });
+
+    let client = reqwest::blocking::Client::builder()
+        .negotiate_with_credentials("testuser", "testpass")
+        .build()
+        .unwrap();
+
+    let resp = client
+        .post(format!("http://{}/", server.addr()))
+        .body("payload")
+        .send()
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.text().unwrap(), "authenticated");
+}
+
+#[cfg(all(windows, feature = "blocking"))]
+#[test]
+fn test_blocking_ntlm_full_loop_against_mock() {
+    let auth = Authenticator::ntlm();
+    let server = auth.serve(|_req| async { http::Response::new("authenticated".into()) });
+
+    let client = reqwest::blocking::Client::builder()
+        .negotiate_with_credentials("MOCK\\testuser", "testpass")
+        .build()
+        .unwrap();
+
+    let resp = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let types: Vec<_> = auth
+        .seen()
+        .iter()
+        .map(|seen| seen.token.as_deref().and_then(negotiate::ntlm_message_type))
+        .collect();
+    assert_eq!(types, [None, Some(1), Some(3)]);
+}
@@ -0,0 +1,131 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+mod support;
+use support::server;
+
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn download_progress() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async { http::Response::new(vec![b'x'; 20_000].into()) });
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let recorded = calls.clone();
+    let body = reqwest::Client::new()
+        .get(format!("http://{}/download", server.addr()))
+        .on_download_progress(move |received, total| {
+            recorded.lock().unwrap().push((received, total));
+        })
+        .send()
+        .await
+        .expect("response")
+        .bytes()
+        .await
+        .expect("body");
+    assert_eq!(body.len(), 20_000);
+
+    let calls = calls.lock().unwrap();
+    assert!(!calls.is_empty());
+    assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(calls.iter().all(|&(_, total)| total == Some(20_000)));
+    assert_eq!(calls.last(), Some(&(20_000, Some(20_000))));
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn download_progress_unknown_length() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async {
+        let chunks =
+            futures_util::stream::iter(vec![Ok::<_, std::io::Error>("hello "), Ok("world")]);
+        http::Response::new(reqwest::Body::wrap_stream(chunks))
+    });
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let recorded = calls.clone();
+    let text = reqwest::Client::new()
+        .get(format!("http://{}/chunked", server.addr()))
+        .on_download_progress(move |received, total| {
+            recorded.lock().unwrap().push((received, total));
+        })
+        .send()
+        .await
+        .expect("response")
+        .text()
+        .await
+        .expect("body");
+    assert_eq!(text, "hello world");
+
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.last(), Some(&(11, None)));
+}
+
+#[tokio::test]
+async fn upload_progress() {
+    use http_body_util::BodyExt;
+
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.len(), 100_000);
+        http::Response::default()
+    });
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let recorded = calls.clone();
+    let res = reqwest::Client::new()
+        .post(format!("http://{}/upload", server.addr()))
+        .body(vec![b'x'; 100_000])
+        .on_upload_progress(move |sent, total| {
+            recorded.lock().unwrap().push((sent, total));
+        })
+        .send()
+        .await
+        .expect("response");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let calls = calls.lock().unwrap();
+    assert!(calls.iter().all(|&(_, total)| total == Some(100_000)));
+    assert_eq!(calls.last(), Some(&(100_000, Some(100_000))));
+}
+
+#[cfg(feature = "multipart")]
+#[tokio::test]
+async fn upload_progress_multipart() {
+    use http_body_util::BodyExt;
+
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        let len = req.headers()["content-length"].to_str().unwrap().to_owned();
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        http::Response::new(format!("{len} {}", body.len()).into())
+    });
+
+    let form = reqwest::multipart::Form::new()
+        .text("a", "x".repeat(10_000))
+        .text("b", "y".repeat(10_000));
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let recorded = calls.clone();
+    let res = reqwest::Client::new()
+        .post(format!("http://{}/multipart", server.addr()))
+        .multipart(form)
+        .on_upload_progress(move |sent, total| {
+            recorded.lock().unwrap().push((sent, total));
+        })
+        .send()
+        .await
+        .expect("response");
+    let text = res.text().await.unwrap();
+    let (len, received) = text.split_once(' ').unwrap();
+    assert_eq!(len, received);
+    let len: u64 = len.parse().unwrap();
+
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.last(), Some(&(len, Some(len))));
+}
@@ -37,6 +37,53 @@ async fn http_proxy() {
     assert_eq!(res.status(), reqwest::StatusCode::OK);
 }
 
+#[tokio::test]
+async fn tunnel_rejects_data_sent_with_its_response() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let url = "http://hyper.rs.local/prox";
+    let last = server::http(move |_req| async { http::Response::default() });
+    let last_addr = last.addr();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let first_addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut client, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            client.read_exact(&mut byte).await.unwrap();
+            head.push(byte[0]);
+        }
+        client
+            .write_all(b"HTTP/1.1 200 OK\r\n\r\nHTTP/1.1 204 No Content\r\n\r\n")
+            .await
+            .unwrap();
+        let mut upstream = tokio::net::TcpStream::connect(last_addr).await.unwrap();
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+    });
+
+    let proxy = reqwest::Proxy::chained([
+        reqwest::Proxy::all(format!("http://{first_addr}")).unwrap(),
+        reqwest::Proxy::http(format!("http://{last_addr}")).unwrap(),
+    ])
+    .unwrap();
+    let err = reqwest::Client::builder()
+        .proxy(proxy)
+        .build()
+        .unwrap()
+        .get(url)
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_proxy(), "{err:?}");
+    assert!(
+        format!("{err:?}").contains("before the tunnel was used"),
+        "{err:?}"
+    );
+}
+
 #[tokio::test]
 async fn http_proxy_basic_auth() {
     let url = "http://hyper.rs.local/prox";
@@ -304,6 +351,83 @@ async fn tunnel_detects_auth_required() {
     );
 }
 
+#[cfg(feature = "__tls")]
+#[tokio::test]
+async fn tunnel_failures_are_proxy_errors() {
+    let server = server::http(move |_req| async {
+        let mut res = http::Response::default();
+        *res.status_mut() = http::StatusCode::PROXY_AUTHENTICATION_REQUIRED;
+        res
+    });
+
+    let proxy = format!("http://Aladdin:open%20sesame@{}", server.addr());
+
+    let err = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::https(&proxy).unwrap())
+        .build()
+        .unwrap()
+        .get("https://hyper.rs.local/prox")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_proxy(), "{err:?}");
+    let expected = format!("http://{}/", server.addr());
+    assert_eq!(err.proxy_url().map(|url| url.as_str()), Some(&*expected));
+}
+
+#[cfg(feature = "__tls")]
+#[tokio::test]
+async fn tunnel_refusal_keeps_proxy_response() {
+    let server = server::http(move |_req| async {
+        http::Response::builder()
+            .status(http::StatusCode::FORBIDDEN)
+            .header("x-blocked-by", "policy")
+            .body("<h1>Access to hyper.rs.local is blocked</h1>".into())
+            .unwrap()
+    });
+
+    let err = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::https(format!("http://{}", server.addr())).unwrap())
+        .build()
+        .unwrap()
+        .get("https://hyper.rs.local/prox")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_proxy(), "{err:?}");
+    let refusal = err.proxy_response().expect("proxy response");
+    assert_eq!(refusal.status(), http::StatusCode::FORBIDDEN);
+    assert_eq!(refusal.headers()["x-blocked-by"], "policy");
+    assert_eq!(
+        refusal.body(),
+        "<h1>Access to hyper.rs.local is blocked</h1>".as_bytes()
+    );
+    assert!(format!("{err:?}").contains("is blocked"), "{err:?}");
+}
+
+#[tokio::test]
+async fn unreachable_proxy_is_a_proxy_error() {
+    // Nothing listens on the port once the listener is dropped.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let err = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{addr}")).unwrap())
+        .build()
+        .unwrap()
+        .get("http://hyper.rs.local/prox")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_connect(), "{err:?}");
+    assert!(err.is_proxy(), "{err:?}");
+}
+
 #[cfg(feature = "__tls")]
 #[tokio::test]
 async fn tunnel_includes_proxy_auth() {
@@ -382,6 +506,52 @@ async fn tunnel_includes_user_agent() {
     );
 }
 
+#[cfg(feature = "__tls")]
+#[tokio::test]
+async fn tunnel_includes_connect_headers() {
+    let server = server::http(move |req| {
+        let connect = req.method() == "CONNECT";
+        assert_eq!(req.headers().contains_key("x-client-id"), connect);
+        assert_eq!(
+            req.headers()["x-both"],
+            if connect { "connect" } else { "all" }
+        );
+
+        async {
+            // return 400 to not actually deal with TLS tunneling
+            let mut res = http::Response::default();
+            *res.status_mut() = http::StatusCode::BAD_REQUEST;
+            res
+        }
+    });
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert("x-both", "all".parse().unwrap());
+    let mut connect_headers = http::HeaderMap::new();
+    connect_headers.insert("x-client-id", "ci".parse().unwrap());
+    connect_headers.insert("x-both", "connect".parse().unwrap());
+    let proxy = reqwest::Proxy::all(format!("http://{}", server.addr()))
+        .unwrap()
+        .headers(headers)
+        .connect_headers(connect_headers);
+    let client = reqwest::Client::builder().proxy(proxy).build().unwrap();
+
+    let err = client
+        .get("https://hyper.rs.local/prox")
+        .send()
+        .await
+        .unwrap_err();
+    let err = support::error::inspect(err).pop().unwrap();
+    assert!(err.contains("unsuccessful"), "{err:?}");
+
+    let res = client
+        .get("http://hyper.rs.local/prox")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn tunnel_includes_proxy_auth_with_multiple_proxies() {
     let url = "http://hyper.rs.local/prox";
@@ -443,3 +613,136 @@ async fn tunnel_includes_proxy_auth_with_multiple_proxies() {
     assert_eq!(res.url().as_str(), url);
     assert_eq!(res.status(), reqwest::StatusCode::OK);
 }
+
+#[tokio::test]
+async fn unreachable_proxy_fails_over() {
+    let url = "http://hyper.rs.local/prox";
+    let dead = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), url);
+        async { http::Response::default() }
+    });
+
+    let proxy = reqwest::Proxy::http(format!("http://{dead}"))
+        .unwrap()
+        .fallback(format!("http://{}", server.addr()))
+        .unwrap();
+    let res = reqwest::Client::builder()
+        .proxy(proxy)
+        .build()
+        .unwrap()
+        .get(url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn bad_gateway_proxy_fails_over() {
+    let url = "http://hyper.rs.local/prox";
+    let failing_hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let hits = failing_hits.clone();
+    let failing = server::http(move |_req| {
+        hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        async {
+            let mut res = http::Response::default();
+            *res.status_mut() = http::StatusCode::BAD_GATEWAY;
+            res
+        }
+    });
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), url);
+        async { http::Response::default() }
+    });
+
+    let proxy = reqwest::Proxy::http(format!("http://{}", failing.addr()))
+        .unwrap()
+        .fallback(format!("http://{}", server.addr()))
+        .unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(proxy)
+        .http1_only()
+        .build()
+        .unwrap();
+
+    let res = client.get(url).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // passed over now
+    let res = client.get(url).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(failing_hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    // A request that isn't idempotent gets the gateway error, but the
+    // proxy is passed over all the same.
+    let proxy = reqwest::Proxy::http(format!("http://{}", failing.addr()))
+        .unwrap()
+        .fallback(format!("http://{}", server.addr()))
+        .unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(proxy)
+        .http1_only()
+        .build()
+        .unwrap();
+
+    let res = client.post(url).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_GATEWAY);
+    let res = client.post(url).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(failing_hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn chained_proxies_tunnel_to_the_last() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let url = "http://hyper.rs.local/prox";
+    let last = server::http(move |req| {
+        assert_eq!(req.uri(), url);
+        async { http::Response::default() }
+    });
+    let last_addr = last.addr();
+
+    // A first proxy only opening tunnels, to the last one.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let first_addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut client, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            client.read_exact(&mut byte).await.unwrap();
+            head.push(byte[0]);
+        }
+        let head = String::from_utf8(head).unwrap();
+        assert!(
+            head.starts_with(&format!("CONNECT {last_addr} HTTP/1.1\r\n")),
+            "{head}"
+        );
+        assert!(head.contains("proxy-authorization: Basic "), "{head}");
+        client.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
+        let mut upstream = tokio::net::TcpStream::connect(last_addr).await.unwrap();
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+    });
+
+    let proxy = reqwest::Proxy::chained([
+        reqwest::Proxy::all(format!("http://user:pass@{first_addr}")).unwrap(),
+        reqwest::Proxy::http(format!("http://{last_addr}")).unwrap(),
+    ])
+    .unwrap();
+    let res = reqwest::Client::builder()
+        .proxy(proxy)
+        .build()
+        .unwrap()
+        .get(url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
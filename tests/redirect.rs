@@ -42,6 +42,65 @@ async fn test_redirect_301_and_302_and_303_changes_post_to_get() {
     }
 }
 
+#[tokio::test]
+async fn test_redirect_301_and_302_preserve_method() {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::default().preserve_method(true))
+        .build()
+        .unwrap();
+
+    let redirect = server::http(move |req| async move {
+        match req.uri().path() {
+            "/301" | "/302" => {
+                let code = req.uri().path()[1..].parse::<u16>().unwrap();
+                http::Response::builder()
+                    .status(code)
+                    .header("location", "/dst")
+                    .body(Body::default())
+                    .unwrap()
+            }
+            "/303" => http::Response::builder()
+                .status(303)
+                .header("location", "/dst")
+                .body(Body::default())
+                .unwrap(),
+            _ => {
+                assert_eq!(req.uri(), "/dst");
+                let method = req.method().to_string();
+                let content_type = req
+                    .headers()
+                    .get("content-type")
+                    .map(|v| v.to_str().unwrap().to_owned())
+                    .unwrap_or_default();
+                let body = req.into_body().collect().await.unwrap().to_bytes();
+                let body = String::from_utf8(body.to_vec()).unwrap();
+                http::Response::new(format!("{method} {content_type} {body}").into())
+            }
+        }
+    });
+
+    for code in [301, 302] {
+        let res = client
+            .post(format!("http://{}/{code}", redirect.addr()))
+            .header("content-type", "text/plain")
+            .body("hello")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.url().path(), "/dst");
+        assert_eq!(res.text().await.unwrap(), "POST text/plain hello");
+    }
+
+    // A 303 still changes the method.
+    let res = client
+        .post(format!("http://{}/303", redirect.addr()))
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "GET  ");
+}
+
 #[tokio::test]
 async fn test_redirect_307_and_308_tries_to_get_again() {
     let client = reqwest::Client::new();
@@ -256,6 +315,39 @@ async fn test_redirect_policy_can_stop_redirects_without_an_error() {
     assert_eq!(res.status(), reqwest::StatusCode::FOUND);
 }
 
+#[tokio::test]
+async fn test_redirect_policy_per_request() {
+    let server = server::http(move |req| async move {
+        if req.uri() == "/start" {
+            http::Response::builder()
+                .status(302)
+                .header("location", "/dst")
+                .body(Body::default())
+                .unwrap()
+        } else {
+            assert_eq!(req.uri(), "/dst");
+            http::Response::default()
+        }
+    });
+
+    let url = format!("http://{}/start", server.addr());
+    let client = reqwest::Client::new();
+
+    let res = client
+        .get(&url)
+        .redirect(reqwest::redirect::Policy::none())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.url().as_str(), url);
+    assert_eq!(res.status(), reqwest::StatusCode::FOUND);
+
+    // The client's policy still applies to other requests.
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.url().path(), "/dst");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
 #[tokio::test]
 async fn test_referer_is_not_set_if_disabled() {
     let server = server::http(move |req| async move {
@@ -443,6 +535,59 @@ async fn test_redirect_custom() {
     assert_eq!(res.status(), reqwest::StatusCode::FOUND);
 }
 
+#[tokio::test]
+async fn test_redirect_custom_async() {
+    let server = server::http(move |req| async move {
+        let next = match req.uri().path() {
+            "/a" => "/b",
+            "/b" => "/c",
+            _ => return http::Response::default(),
+        };
+        http::Response::builder()
+            .status(302)
+            .header("location", next)
+            .body(Body::default())
+            .unwrap()
+    });
+
+    let policy = reqwest::redirect::Policy::custom_async(|attempt| async move {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        assert_eq!(attempt.status(), reqwest::StatusCode::FOUND);
+        assert_eq!(attempt.headers()["x-token"], "abc");
+        match attempt.url().path() {
+            "/b" => {
+                assert_eq!(attempt.previous().len(), 1);
+                attempt.follow()
+            }
+            _ => attempt.stop(),
+        }
+    });
+    let client = reqwest::Client::builder().redirect(policy).build().unwrap();
+
+    let res = client
+        .get(format!("http://{}/a", server.addr()))
+        .header("x-token", "abc")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.url().path(), "/b");
+    assert_eq!(res.status(), reqwest::StatusCode::FOUND);
+
+    let policy =
+        reqwest::redirect::Policy::custom_async(
+            |attempt| async move { attempt.error("not allowed") },
+        );
+    let err = reqwest::Client::builder()
+        .redirect(policy)
+        .build()
+        .unwrap()
+        .get(format!("http://{}/a", server.addr()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_redirect());
+}
+
 #[tokio::test]
 async fn test_scheme_only_check_after_policy_return_follow() {
     let server = server::http(move |_| async move {
@@ -541,3 +686,41 @@ async fn test_redirect_301_302_303_empty_payload_headers() {
         );
     }
 }
+
+#[tokio::test]
+async fn scoped_default_headers_dont_follow_redirect_out_of_scope() {
+    let server = server::http(move |req| async move {
+        if req.uri().path() == "/out" {
+            let port = req.headers()["host"].to_str().unwrap().rsplit(':').next();
+            return http::Response::builder()
+                .status(302)
+                .header(
+                    "location",
+                    format!("http://localhost:{}/dst", port.unwrap()),
+                )
+                .body(Body::default())
+                .unwrap();
+        }
+        let key = req
+            .headers()
+            .get("x-api-key")
+            .map(|v| v.as_bytes().to_vec());
+        http::Response::new(Body::from(key.unwrap_or_default()))
+    });
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-api-key", "secret".parse().unwrap());
+    let client = reqwest::Client::builder()
+        .default_headers_for("127.0.0.1", headers)
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/in", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "secret");
+
+    let url = format!("http://{}/out", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.url().host_str(), Some("localhost"));
+    assert_eq!(res.text().await.unwrap(), "");
+}
@@ -5,6 +5,7 @@ use support::server;
 
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[tokio::test]
 async fn retries_apply_in_scope() {
@@ -47,6 +48,141 @@ async fn retries_apply_in_scope() {
     assert_eq!(resp.status(), 200);
 }
 
+#[tokio::test]
+async fn transient_retries_only_idempotent_requests() {
+    let _ = env_logger::try_init();
+    let cnt = Arc::new(AtomicUsize::new(0));
+    let counter = cnt.clone();
+    let server = server::http(move |req| {
+        let cnt = counter.clone();
+        async move {
+            let status = if cnt.fetch_add(1, Ordering::Relaxed) % 2 == 0 {
+                http::StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                http::StatusCode::OK
+            };
+            assert!(req.method() == "GET" || req.method() == "POST");
+            http::Response::builder()
+                .status(status)
+                .body(Default::default())
+                .unwrap()
+        }
+    });
+
+    let scope = server.addr().ip().to_string();
+    let retries = reqwest::retry::for_host(scope)
+        .classify_transient()
+        .backoff(Duration::from_millis(10), Duration::from_millis(50));
+    let client = reqwest::Client::builder().retry(retries).build().unwrap();
+    let url = format!("http://{}", server.addr());
+
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(cnt.load(Ordering::Relaxed), 2);
+
+    let resp = client.post(&url).body("hello").send().await.unwrap();
+    assert_eq!(resp.status(), 503);
+    assert_eq!(cnt.load(Ordering::Relaxed), 3);
+}
+
+#[tokio::test]
+async fn backoff_honors_retry_after_within_max_elapsed() {
+    let _ = env_logger::try_init();
+    let cnt = Arc::new(AtomicUsize::new(0));
+    let counter = cnt.clone();
+    let server = server::http(move |req| {
+        let cnt = counter.clone();
+        async move {
+            let retry_after = if req.uri().path() == "/soon" {
+                "1"
+            } else {
+                "60"
+            };
+            if cnt.fetch_add(1, Ordering::Relaxed) % 2 == 0 {
+                http::Response::builder()
+                    .status(http::StatusCode::TOO_MANY_REQUESTS)
+                    .header("retry-after", retry_after)
+                    .body(Default::default())
+                    .unwrap()
+            } else {
+                http::Response::default()
+            }
+        }
+    });
+
+    let scope = server.addr().ip().to_string();
+    let retries = reqwest::retry::for_host(scope)
+        .classify_transient()
+        .backoff(Duration::from_millis(10), Duration::from_millis(50))
+        .max_elapsed(Duration::from_secs(5));
+    let client = reqwest::Client::builder().retry(retries).build().unwrap();
+
+    let start = Instant::now();
+    let resp = client
+        .get(format!("http://{}/soon", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(start.elapsed() >= Duration::from_secs(1));
+
+    // Waiting a minute would exceed the maximum elapsed time.
+    let start = Instant::now();
+    let resp = client
+        .get(format!("http://{}/later", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 429);
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(cnt.load(Ordering::Relaxed), 3);
+}
+
+#[tokio::test]
+async fn backoff_waits_on_paused_time() {
+    let _ = env_logger::try_init();
+    let cnt = Arc::new(AtomicUsize::new(0));
+    let counter = cnt.clone();
+    let server = server::http(move |_req| {
+        let cnt = counter.clone();
+        async move {
+            if cnt.fetch_add(1, Ordering::Relaxed) == 0 {
+                http::Response::builder()
+                    .status(http::StatusCode::SERVICE_UNAVAILABLE)
+                    .header("retry-after", "60")
+                    .body(Default::default())
+                    .unwrap()
+            } else {
+                http::Response::default()
+            }
+        }
+    });
+
+    let scope = server.addr().ip().to_string();
+    let retries = reqwest::retry::for_host(scope)
+        .classify_transient()
+        .backoff(Duration::from_millis(10), Duration::from_millis(50))
+        .max_elapsed(Duration::from_secs(120));
+    let client = reqwest::Client::builder()
+        .retry(retries)
+        .pool_idle_timeout(None)
+        .build()
+        .unwrap();
+
+    tokio::time::pause();
+    let start = Instant::now();
+    let paused = tokio::time::Instant::now();
+    let resp = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(paused.elapsed() >= Duration::from_secs(60));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(cnt.load(Ordering::Relaxed), 2);
+}
+
 #[cfg(feature = "http2")]
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn default_retries_have_a_limit() {
@@ -142,3 +278,55 @@ async fn highly_concurrent_requests_to_slow_http2_server_with_low_max_concurrent
 
     server.shutdown().await;
 }
+
+#[tokio::test]
+async fn request_id_is_reused_or_regenerated_on_retries() {
+    let _ = env_logger::try_init();
+    let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let server = server::http(move |req| {
+        let log = log.clone();
+        async move {
+            let mut log = log.lock().unwrap();
+            log.push(req.headers()["x-request-id"].to_str().unwrap().to_owned());
+            // Every first attempt is refused.
+            let status = if log.len() % 2 == 1 {
+                http::StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                http::StatusCode::OK
+            };
+            http::Response::builder()
+                .status(status)
+                .body(Default::default())
+                .unwrap()
+        }
+    });
+
+    for regenerate in [false, true] {
+        seen.lock().unwrap().clear();
+        let scope = server.addr().ip().to_string();
+        let retries = reqwest::retry::for_host(scope)
+            .classify_transient()
+            .regenerate_request_id(regenerate);
+        let ids = AtomicUsize::new(0);
+        let client = reqwest::Client::builder()
+            .retry(retries)
+            .request_id(http::HeaderName::from_static("x-request-id"), move || {
+                ids.fetch_add(1, Ordering::Relaxed).into()
+            })
+            .build()
+            .unwrap();
+
+        let res = client
+            .get(format!("http://{}", server.addr()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+
+        let expected = if regenerate { ["0", "1"] } else { ["0", "0"] };
+        assert_eq!(*seen.lock().unwrap(), expected);
+        let id = res.extensions().get::<reqwest::request_id::RequestId>();
+        assert_eq!(id.unwrap().value(), expected[1]);
+    }
+}
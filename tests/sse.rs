@@ -0,0 +1,87 @@
+#![cfg(not(target_arch = "wasm32"))]
+mod support;
+use futures_util::StreamExt;
+use support::server;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+fn event_stream(body: &'static str) -> http::Response<reqwest::Body> {
+    http::Response::builder()
+        .header("content-type", "text/event-stream")
+        .body(body.into())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn response_events() {
+    let server = server::http(move |_req| async {
+        event_stream("event: greeting\ndata: hello\ndata: world\nid: 7\n\ndata: bye\n\n")
+    });
+
+    let res = reqwest::get(format!("http://{}/events", server.addr()))
+        .await
+        .unwrap();
+    let events = res
+        .events()
+        .map(|event| event.unwrap())
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].event(), "greeting");
+    assert_eq!(events[0].data(), "hello\nworld");
+    assert_eq!(events[0].id(), "7");
+    assert_eq!(events[1].event(), "message");
+    assert_eq!(events[1].data(), "bye");
+    assert_eq!(events[1].id(), "7");
+}
+
+#[tokio::test]
+async fn event_source_reconnects() {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    let server = server::http(move |req| {
+        let n = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            assert_eq!(req.headers()["accept"], "text/event-stream");
+            let last_id = req.headers().get("last-event-id").cloned();
+            match n {
+                0 => {
+                    assert!(last_id.is_none());
+                    event_stream("retry: 10\nid: 1\ndata: first\n\n")
+                }
+                1 => {
+                    assert_eq!(last_id.unwrap(), "1");
+                    event_stream("data: second\n\n")
+                }
+                _ => http::Response::builder()
+                    .status(204)
+                    .body(Default::default())
+                    .unwrap(),
+            }
+        }
+    });
+
+    let events = reqwest::Client::new()
+        .get(format!("http://{}/events", server.addr()))
+        .event_source()
+        .map(|event| event.unwrap().data().to_owned())
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(events, ["first", "second"]);
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn event_source_rejects_other_content() {
+    let server = server::http(move |_req| async { http::Response::new("not events".into()) });
+
+    let mut source = reqwest::Client::new()
+        .get(format!("http://{}/events", server.addr()))
+        .event_source();
+
+    assert!(source.next().await.unwrap().is_err());
+    assert!(source.next().await.is_none());
+}
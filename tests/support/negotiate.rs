@@ -0,0 +1,377 @@
+#![cfg(not(target_arch = "wasm32"))]
+//! A mock authenticator that answers like an IIS server doing Windows
+//! authentication, so the Negotiate loop can be tested without Active
+//! Directory.
+//!
+//! The tokens are well-formed (NTLM messages, SPNEGO `NegTokenResp`s), but
+//! nothing is verified cryptographically: any Type 3 message is accepted.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use base64::Engine as _;
+
+use super::server::{self, Server};
+
+/// The NTLMSSP signature every NTLM message starts with.
+pub const NTLMSSP: &[u8; 8] = b"NTLMSSP\0";
+
+/// The server challenge sent in the Type 2 message.
+pub const SERVER_CHALLENGE: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+
+/// DER encoding of the NTLMSSP mechanism OID, 1.3.6.1.4.1.311.2.2.10.
+const NTLM_OID: &[u8] = &[
+    0x06, 0x0a, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x02, 0x0a,
+];
+
+const ACCEPT_COMPLETED: u8 = 0;
+const ACCEPT_INCOMPLETE: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    Negotiate,
+    Ntlm,
+}
+
+impl Scheme {
+    fn name(self) -> &'static str {
+        match self {
+            Scheme::Negotiate => "Negotiate",
+            Scheme::Ntlm => "NTLM",
+        }
+    }
+}
+
+/// A request the authenticator received.
+#[derive(Clone, Debug)]
+pub struct Seen {
+    /// The scheme of the `Authorization` header, if there was one.
+    pub scheme: Option<String>,
+    /// The decoded token of the `Authorization` header.
+    pub token: Option<Vec<u8>>,
+}
+
+#[derive(Clone)]
+pub struct Authenticator {
+    scheme: Scheme,
+    rounds: usize,
+    mutual: bool,
+    offer_ntlm: bool,
+    proxy: bool,
+    behind: Option<Arc<Authenticator>>,
+    seen: Arc<Mutex<Vec<Seen>>>,
+}
+
+impl Authenticator {
+    /// Challenges with `WWW-Authenticate: NTLM`, answering the Type 1
+    /// message with a Type 2 message and accepting the Type 3 message.
+    pub fn ntlm() -> Self {
+        Authenticator {
+            scheme: Scheme::Ntlm,
+            rounds: 1,
+            mutual: false,
+            offer_ntlm: false,
+            proxy: false,
+            behind: None,
+            seen: Arc::default(),
+        }
+    }
+
+    /// Challenges with `WWW-Authenticate: Negotiate`, answering with SPNEGO
+    /// tokens that carry NTLM, like a server without a KDC would.
+    pub fn negotiate() -> Self {
+        Authenticator {
+            scheme: Scheme::Negotiate,
+            ..Authenticator::ntlm()
+        }
+    }
+
+    /// Sets how many 401 responses carrying a token are sent before the
+    /// request is let through. The default is 1, as for NTLM.
+    ///
+    /// Only the first continuation token is an NTLM Type 2 message.
+    pub fn rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    /// Sends a final `WWW-Authenticate: Negotiate` token with the
+    /// successful response, for the client to verify the server.
+    pub fn mutual_auth(mut self, enabled: bool) -> Self {
+        self.mutual = enabled;
+        self
+    }
+
+    /// Also offers `WWW-Authenticate: NTLM` in the initial challenge.
+    pub fn offer_ntlm(mut self, enabled: bool) -> Self {
+        self.offer_ntlm = enabled;
+        self
+    }
+
+    /// Lets requests through only once `proxy` has authenticated them, as
+    /// a proxy would with `407 Proxy Authentication Required`.
+    ///
+    /// The proxy keeps no state between requests, so each one is challenged
+    /// anew unless it has a `Proxy-Authorization` header.
+    pub fn behind_proxy(mut self, mut proxy: Authenticator) -> Self {
+        proxy.proxy = true;
+        self.behind = Some(Arc::new(proxy));
+        self
+    }
+
+    /// The requests received so far.
+    pub fn seen(&self) -> Vec<Seen> {
+        self.seen.lock().unwrap().clone()
+    }
+
+    /// Starts a server that authenticates every request before passing it
+    /// to `func`.
+    pub fn serve<F, Fut>(&self, func: F) -> Server
+    where
+        F: Fn(http::Request<hyper::body::Incoming>) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = http::Response<reqwest::Body>> + Send + 'static,
+    {
+        let auth = self.clone();
+        server::http(move |req| {
+            let auth = auth.clone();
+            let func = func.clone();
+            async move {
+                let proxy_challenge = auth
+                    .behind
+                    .as_ref()
+                    .and_then(|proxy| proxy.challenge(req.headers()));
+                if let Some(challenge) = proxy_challenge {
+                    return challenge;
+                }
+
+                match auth.challenge(req.headers()) {
+                    Some(challenge) => challenge,
+                    None => {
+                        let mut res = func(req).await;
+                        if auth.mutual && auth.scheme == Scheme::Negotiate {
+                            let token = auth.encode(neg_token_resp(ACCEPT_COMPLETED, None));
+                            res.headers_mut()
+                                .append(http::header::WWW_AUTHENTICATE, token.parse().unwrap());
+                        }
+                        res
+                    }
+                }
+            }
+        })
+    }
+
+    /// Returns the 401 (or 407) to send, or `None` once the handshake has
+    /// finished.
+    fn challenge(&self, headers: &http::HeaderMap) -> Option<http::Response<reqwest::Body>> {
+        let seen = parse_authorization(headers.get(self.authorization()));
+        let mut log = self.seen.lock().unwrap();
+        log.push(seen.clone());
+
+        let token = match (&seen.scheme, seen.token) {
+            (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case(self.scheme.name()) => token,
+            (Some(scheme), Some(token))
+                if self.offer_ntlm && scheme.eq_ignore_ascii_case("NTLM") =>
+            {
+                return self.ntlm_step(&token);
+            }
+            _ => {
+                let mut offers = vec![self.scheme.name().to_owned()];
+                if self.offer_ntlm && self.scheme != Scheme::Ntlm {
+                    offers.push("NTLM".to_owned());
+                }
+                return Some(self.unauthorized(&offers));
+            }
+        };
+
+        match self.scheme {
+            Scheme::Ntlm => self.ntlm_step(&token),
+            Scheme::Negotiate => {
+                // Count the tokens sent since the last unauthenticated request.
+                let round = log
+                    .iter()
+                    .rev()
+                    .take_while(|seen| seen.token.is_some())
+                    .count();
+                if round > self.rounds {
+                    return None;
+                }
+                let inner = if round == 1 {
+                    type2()
+                } else {
+                    vec![round as u8; 16]
+                };
+                let token = self.encode(neg_token_resp(ACCEPT_INCOMPLETE, Some(&inner)));
+                Some(self.unauthorized(&[token]))
+            }
+        }
+    }
+
+    fn encode(&self, token: Vec<u8>) -> String {
+        format!(
+            "{} {}",
+            self.scheme.name(),
+            base64::engine::general_purpose::STANDARD.encode(token)
+        )
+    }
+
+    /// The header the client's tokens are read from.
+    fn authorization(&self) -> http::header::HeaderName {
+        if self.proxy {
+            http::header::PROXY_AUTHORIZATION
+        } else {
+            http::header::AUTHORIZATION
+        }
+    }
+
+    fn unauthorized(&self, challenges: &[String]) -> http::Response<reqwest::Body> {
+        let (status, header) = if self.proxy {
+            (
+                http::StatusCode::PROXY_AUTHENTICATION_REQUIRED,
+                http::header::PROXY_AUTHENTICATE,
+            )
+        } else {
+            (
+                http::StatusCode::UNAUTHORIZED,
+                http::header::WWW_AUTHENTICATE,
+            )
+        };
+        let mut res = http::Response::builder().status(status);
+        for challenge in challenges {
+            res = res.header(&header, challenge.as_str());
+        }
+        res.body("unauthorized".into()).unwrap()
+    }
+
+    /// Answers a Type 1 message with a Type 2 message, and lets a Type 3
+    /// message through. Anything else starts over.
+    fn ntlm_step(&self, token: &[u8]) -> Option<http::Response<reqwest::Body>> {
+        match ntlm_message_type(token) {
+            Some(1) => Some(self.unauthorized(&[format!(
+                "NTLM {}",
+                base64::engine::general_purpose::STANDARD.encode(type2())
+            )])),
+            Some(3) => None,
+            _ => Some(self.unauthorized(&["NTLM".to_owned()])),
+        }
+    }
+}
+
+/// The type of the NTLM message in `token`, which may be wrapped in SPNEGO.
+pub fn ntlm_message_type(token: &[u8]) -> Option<u32> {
+    let start = token.windows(NTLMSSP.len()).position(|w| w == NTLMSSP)?;
+    let ty = token.get(start + 8..start + 12)?;
+    Some(u32::from_le_bytes(ty.try_into().unwrap()))
+}
+
+fn parse_authorization(header: Option<&http::HeaderValue>) -> Seen {
+    let value = match header.and_then(|v| v.to_str().ok()) {
+        Some(value) => value.trim(),
+        None => {
+            return Seen {
+                scheme: None,
+                token: None,
+            }
+        }
+    };
+    let (scheme, token) = match value.split_once(' ') {
+        Some((scheme, token)) => (scheme, Some(token.trim())),
+        None => (value, None),
+    };
+    Seen {
+        scheme: Some(scheme.to_owned()),
+        token: token.and_then(|t| base64::engine::general_purpose::STANDARD.decode(t).ok()),
+    }
+}
+
+/// An NTLM Type 2 (CHALLENGE_MESSAGE) for the `MOCK` domain.
+pub fn type2() -> Vec<u8> {
+    let target_name = utf16le("MOCK");
+    let mut target_info = Vec::new();
+    // MsvAvNbDomainName, MsvAvNbComputerName, then MsvAvEOL.
+    for (id, value) in [(2u16, "MOCK"), (1u16, "SERVER")] {
+        let value = utf16le(value);
+        target_info.extend_from_slice(&id.to_le_bytes());
+        target_info.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        target_info.extend_from_slice(&value);
+    }
+    target_info.extend_from_slice(&[0, 0, 0, 0]);
+
+    // UNICODE | REQUEST_TARGET | NTLM | ALWAYS_SIGN | TARGET_TYPE_DOMAIN |
+    // EXTENDED_SESSIONSECURITY | TARGET_INFO | 128 | 56
+    let flags: u32 = 0xa089_8205;
+
+    let header_len = 48u32;
+    let mut msg = Vec::new();
+    msg.extend_from_slice(NTLMSSP);
+    msg.extend_from_slice(&2u32.to_le_bytes());
+    push_field(&mut msg, target_name.len(), header_len);
+    msg.extend_from_slice(&flags.to_le_bytes());
+    msg.extend_from_slice(&SERVER_CHALLENGE);
+    msg.extend_from_slice(&[0; 8]);
+    push_field(
+        &mut msg,
+        target_info.len(),
+        header_len + target_name.len() as u32,
+    );
+    msg.extend_from_slice(&target_name);
+    msg.extend_from_slice(&target_info);
+    msg
+}
+
+/// An NTLM Type 1 (NEGOTIATE_MESSAGE), as a client would send it.
+pub fn type1() -> Vec<u8> {
+    let mut msg = Vec::new();
+    msg.extend_from_slice(NTLMSSP);
+    msg.extend_from_slice(&1u32.to_le_bytes());
+    msg.extend_from_slice(&0xa208_b207u32.to_le_bytes());
+    push_field(&mut msg, 0, 0);
+    push_field(&mut msg, 0, 0);
+    msg
+}
+
+/// An NTLM Type 3 (AUTHENTICATE_MESSAGE) with empty responses.
+pub fn type3() -> Vec<u8> {
+    let mut msg = Vec::new();
+    msg.extend_from_slice(NTLMSSP);
+    msg.extend_from_slice(&3u32.to_le_bytes());
+    for _ in 0..6 {
+        push_field(&mut msg, 0, 64);
+    }
+    msg.extend_from_slice(&0xa288_0205u32.to_le_bytes());
+    msg
+}
+
+/// A SPNEGO `NegTokenResp` with the NTLM mechanism.
+pub fn neg_token_resp(state: u8, response_token: Option<&[u8]>) -> Vec<u8> {
+    let mut seq = der(0xa0, &[0x0a, 0x01, state]);
+    seq.extend(der(0xa1, NTLM_OID));
+    if let Some(token) = response_token {
+        seq.extend(der(0xa2, &der(0x04, token)));
+    }
+    der(0xa1, &der(0x30, &seq))
+}
+
+fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = contents.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else if len <= 0xff {
+        out.extend_from_slice(&[0x81, len as u8]);
+    } else {
+        out.push(0x82);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+fn push_field(msg: &mut Vec<u8>, len: usize, offset: u32) {
+    msg.extend_from_slice(&(len as u16).to_le_bytes());
+    msg.extend_from_slice(&(len as u16).to_le_bytes());
+    msg.extend_from_slice(&offset.to_le_bytes());
+}
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
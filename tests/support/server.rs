@@ -69,6 +69,77 @@ where
 type Builder = hyper_util::server::conn::auto::Builder<hyper_util::rt::TokioExecutor>;
 
 pub fn http_with_config<F1, Fut, E, F2, Bu>(func: F1, apply_config: F2) -> Server
+where
+    F1: Fn(http::Request<hyper::body::Incoming>) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<http::Response<reqwest::Body>, E>> + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    F2: FnOnce(&mut Builder) -> Bu + Send + 'static,
+{
+    serve(func, apply_config, None)
+}
+
+/// Details of the TLS connection a request was received on, available in the
+/// request extensions of an `https` server.
+#[cfg(feature = "rustls")]
+#[derive(Clone, Debug)]
+pub struct TlsConn {
+    pub resumed: bool,
+    pub alpn: Option<Vec<u8>>,
+}
+
+/// The CA that issued the certificate the `https` server presents, which is
+/// valid for `localhost` and `127.0.0.1`.
+#[cfg(feature = "rustls")]
+pub fn cert() -> reqwest::Certificate {
+    reqwest::Certificate::from_der(&std::fs::read("tests/support/tls/ca.der").unwrap()).unwrap()
+}
+
+#[cfg(feature = "rustls")]
+pub fn https<F, Fut>(func: F) -> Server
+where
+    F: Fn(http::Request<hyper::body::Incoming>) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = http::Response<reqwest::Body>> + Send + 'static,
+{
+    https_with_config(func, |_tls| {})
+}
+
+#[cfg(feature = "rustls")]
+pub fn https_with_config<F1, Fut, F2>(func: F1, apply_tls: F2) -> Server
+where
+    F1: Fn(http::Request<hyper::body::Incoming>) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = http::Response<reqwest::Body>> + Send + 'static,
+    F2: FnOnce(&mut rustls::ServerConfig),
+{
+    let cert = std::fs::read("tests/support/tls/server.der")
+        .unwrap()
+        .into();
+    let key = std::fs::read("tests/support/tls/server.key")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let provider = std::sync::Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+    let mut tls = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert], key)
+        .unwrap();
+    tls.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    apply_tls(&mut tls);
+
+    let infall = move |req| {
+        let fut = func(req);
+        async move { Ok::<_, Infallible>(fut.await) }
+    };
+    serve(infall, |_builder| {}, Some(std::sync::Arc::new(tls)))
+}
+
+#[cfg(feature = "rustls")]
+type TlsConfig = Option<std::sync::Arc<rustls::ServerConfig>>;
+#[cfg(not(feature = "rustls"))]
+type TlsConfig = Option<Infallible>;
+
+fn serve<F1, Fut, E, F2, Bu>(func: F1, apply_config: F2, tls: TlsConfig) -> Server
 where
     F1: Fn(http::Request<hyper::body::Incoming>) -> Fut + Clone + Send + 'static,
     Fut: Future<Output = Result<http::Response<reqwest::Body>, E>> + Send + 'static,
@@ -115,12 +186,36 @@ where
                             accepted = listener.accept() => {
                                 let (io, _) = accepted.expect("accepted");
                                 let func = func.clone();
-                                let svc = hyper::service::service_fn(func);
                                 let builder = builder.clone();
                                 let events_tx = events_tx.clone();
                                 let watcher = graceful.watcher();
+                                let tls = tls.clone();
 
                                 tasks.spawn(async move {
+                                    #[cfg(feature = "rustls")]
+                                    if let Some(tls) = tls {
+                                        let io = match tokio_rustls::TlsAcceptor::from(tls).accept(io).await {
+                                            Ok(io) => io,
+                                            Err(_) => return,
+                                        };
+                                        let (_, conn) = io.get_ref();
+                                        let info = TlsConn {
+                                            resumed: conn.handshake_kind() == Some(rustls::HandshakeKind::Resumed),
+                                            alpn: conn.alpn_protocol().map(|p| p.to_vec()),
+                                        };
+                                        let svc = hyper::service::service_fn(move |mut req: http::Request<_>| {
+                                            req.extensions_mut().insert(info.clone());
+                                            func(req)
+                                        });
+                                        let conn = builder.serve_connection_with_upgrades(hyper_util::rt::TokioIo::new(io), svc);
+                                        let _ = watcher.watch(conn).await;
+                                        let _ = events_tx.send(Event::ConnectionClosed);
+                                        return;
+                                    }
+                                    #[cfg(not(feature = "rustls"))]
+                                    let _ = tls;
+
+                                    let svc = hyper::service::service_fn(func);
                                     let conn = builder.serve_connection_with_upgrades(hyper_util::rt::TokioIo::new(io), svc);
                                     let _ = watcher.watch(conn).await;
                                     let _ = events_tx.send(Event::ConnectionClosed);
@@ -0,0 +1,141 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+mod support;
+use http_body_util::BodyExt;
+use support::server;
+
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn client_download_rate() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async { http::Response::new(vec![b'x'; 20_000].into()) });
+
+    let client = reqwest::Client::builder()
+        .max_download_rate(10_000)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/download", server.addr());
+
+    let start = Instant::now();
+    let res = client.get(&url).send().await.expect("response");
+    assert_eq!(res.content_length(), Some(20_000));
+    let body = res.bytes().await.expect("body");
+
+    assert_eq!(body.len(), 20_000);
+    // the first second's worth is allowed as a burst
+    assert!(start.elapsed() >= Duration::from_millis(900));
+}
+
+#[tokio::test]
+async fn request_download_rate_overrides_client() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async { http::Response::new(vec![b'x'; 20_000].into()) });
+
+    let client = reqwest::Client::builder()
+        .max_download_rate(1_000)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/download", server.addr());
+
+    let start = Instant::now();
+    let body = client
+        .get(&url)
+        .max_download_rate(1_000_000)
+        .send()
+        .await
+        .expect("response")
+        .bytes()
+        .await
+        .expect("body");
+
+    assert_eq!(body.len(), 20_000);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn request_upload_rate() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["content-length"], "20000");
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.len(), 20_000);
+        http::Response::default()
+    });
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+
+    let url = format!("http://{}/upload", server.addr());
+
+    let start = Instant::now();
+    let res = client
+        .post(&url)
+        .max_upload_rate(10_000)
+        .body(vec![b'x'; 20_000])
+        .send()
+        .await
+        .expect("response");
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert!(start.elapsed() >= Duration::from_millis(900));
+}
+
+#[tokio::test]
+async fn client_requests_per_second() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let client = reqwest::Client::builder()
+        .max_requests_per_second(10)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/rate", server.addr());
+
+    let start = Instant::now();
+    let requests = (0..15).map(|_| client.get(&url).send());
+    for res in futures_util::future::join_all(requests).await {
+        assert_eq!(res.expect("response").status(), 200);
+    }
+
+    // the first 10 are allowed as a burst, the other 5 take half a second
+    assert!(start.elapsed() >= Duration::from_millis(450));
+}
+
+#[tokio::test]
+async fn client_requests_per_second_per_host() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async { http::Response::default() });
+    let other = server::http(move |_req| async { http::Response::default() });
+
+    let client = reqwest::Client::builder()
+        .max_requests_per_second_per_host(2)
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+    for server in [&server, &other] {
+        let url = format!("http://{}/rate", server.addr());
+        for _ in 0..2 {
+            let res = client.get(&url).send().await.expect("response");
+            assert_eq!(res.status(), 200);
+        }
+    }
+    // each host has its own burst
+    assert!(start.elapsed() < Duration::from_millis(400));
+
+    let url = format!("http://{}/rate", server.addr());
+    client.get(&url).send().await.expect("response");
+    assert!(start.elapsed() >= Duration::from_millis(450));
+}
@@ -0,0 +1,243 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(feature = "rustls")]
+mod support;
+use support::server::{self, TlsConn};
+
+fn client_builder(server: &server::Server) -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .tls_certs_only([server::cert()])
+        .resolve("localhost", server.addr())
+        // every request gets a new connection, and a new handshake
+        .http1_only()
+        .pool_max_idle_per_host(0)
+}
+
+async fn resumed(client: &reqwest::Client, server: &server::Server) -> bool {
+    let url = format!("https://localhost:{}/", server.addr().port());
+    let res = client.get(url).send().await.expect("request");
+    res.text().await.expect("text") == "resumed"
+}
+
+fn tls_conn_server() -> server::Server {
+    server::https(|req| async move {
+        let conn = req.extensions().get::<TlsConn>().expect("tls conn");
+        let body = if conn.resumed { "resumed" } else { "full" };
+        http::Response::new(body.into())
+    })
+}
+
+#[tokio::test]
+async fn session_resumption() {
+    let _ = env_logger::try_init();
+
+    let server = tls_conn_server();
+    let client = client_builder(&server).build().unwrap();
+
+    assert!(!resumed(&client, &server).await);
+    assert!(resumed(&client, &server).await);
+}
+
+#[tokio::test]
+async fn session_resumption_disabled() {
+    let _ = env_logger::try_init();
+
+    let server = tls_conn_server();
+    let client = client_builder(&server)
+        .tls_session_resumption(false)
+        .build()
+        .unwrap();
+
+    assert!(!resumed(&client, &server).await);
+    assert!(!resumed(&client, &server).await);
+}
+
+#[tokio::test]
+async fn session_cache_shared_between_clients() {
+    let _ = env_logger::try_init();
+
+    let server = tls_conn_server();
+    let cache = reqwest::tls::SessionCache::new(8);
+    let a = client_builder(&server)
+        .tls_session_cache(cache.clone())
+        .build()
+        .unwrap();
+    let b = client_builder(&server)
+        .tls_session_cache(cache)
+        .build()
+        .unwrap();
+    let c = client_builder(&server).build().unwrap();
+
+    assert!(!resumed(&a, &server).await);
+    assert!(resumed(&b, &server).await);
+    // not sharing the cache
+    assert!(!resumed(&c, &server).await);
+}
+
+fn alpn_server(protocols: &[&str]) -> server::Server {
+    let protocols = protocols.iter().map(|p| p.as_bytes().to_vec()).collect();
+    server::https_with_config(
+        |req| async move {
+            let conn = req.extensions().get::<TlsConn>().expect("tls conn");
+            let alpn = conn.alpn.clone().unwrap_or_default();
+            http::Response::new(alpn.into())
+        },
+        |tls| tls.alpn_protocols = protocols,
+    )
+}
+
+async fn alpn(client: &reqwest::Client, server: &server::Server) -> String {
+    let url = format!("https://localhost:{}/", server.addr().port());
+    let res = client.get(url).send().await.expect("request");
+    res.text().await.expect("text")
+}
+
+#[tokio::test]
+async fn alpn_protocols_http1_only() {
+    let _ = env_logger::try_init();
+
+    let server = alpn_server(&["h2", "http/1.1"]);
+    let client = reqwest::Client::builder()
+        .tls_certs_only([server::cert()])
+        .resolve("localhost", server.addr())
+        .alpn_protocols(["http/1.1"])
+        .build()
+        .unwrap();
+
+    assert_eq!(alpn(&client, &server).await, "http/1.1");
+}
+
+#[tokio::test]
+async fn alpn_protocols_custom() {
+    let _ = env_logger::try_init();
+
+    let server = alpn_server(&["custom/1"]);
+    let client = reqwest::Client::builder()
+        .tls_certs_only([server::cert()])
+        .resolve("localhost", server.addr())
+        .alpn_protocols(["custom/1", "http/1.1"])
+        .build()
+        .unwrap();
+
+    assert_eq!(alpn(&client, &server).await, "custom/1");
+}
+
+#[test]
+fn alpn_protocols_invalid() {
+    let err = reqwest::Client::builder()
+        .alpn_protocols([""])
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn tls_info() {
+    let _ = env_logger::try_init();
+
+    let server = tls_conn_server();
+    let client = client_builder(&server).tls_info(true).build().unwrap();
+
+    let url = format!("https://localhost:{}/", server.addr().port());
+    let res = client.get(url).send().await.expect("request");
+    let info = res
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .expect("tls info");
+
+    assert_eq!(info.version(), Some(reqwest::tls::Version::TLS_1_3));
+    assert!(info
+        .cipher_suite()
+        .expect("cipher suite")
+        .starts_with("TLS13_"));
+    assert_eq!(info.alpn_protocol(), Some(&b"http/1.1"[..]));
+
+    let leaf = std::fs::read("tests/support/tls/server.der").unwrap();
+    assert_eq!(info.peer_certificate(), Some(&leaf[..]));
+    assert_eq!(
+        info.peer_certificate_chain().collect::<Vec<_>>(),
+        [&leaf[..]]
+    );
+}
+
+#[tokio::test]
+async fn request_accept_invalid_certs() {
+    let _ = env_logger::try_init();
+
+    let server = tls_conn_server();
+    let client = reqwest::Client::builder()
+        .resolve("localhost", server.addr())
+        .http1_only()
+        .build()
+        .unwrap();
+    let url = format!("https://localhost:{}/", server.addr().port());
+
+    client
+        .get(&url)
+        .danger_accept_invalid_certs(true)
+        .send()
+        .await
+        .expect("accepted");
+
+    // the unverified connection isn't reused for other requests
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_connect());
+}
+
+#[tokio::test]
+async fn request_tls_certs_only() {
+    let _ = env_logger::try_init();
+
+    let server = tls_conn_server();
+    let client = reqwest::Client::builder()
+        .resolve("localhost", server.addr())
+        .http1_only()
+        .build()
+        .unwrap();
+    let url = format!("https://localhost:{}/", server.addr().port());
+
+    client
+        .get(&url)
+        .tls_certs_only([server::cert()])
+        .send()
+        .await
+        .expect("trusted");
+
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_connect());
+}
+
+#[tokio::test]
+async fn request_tls_certs_only_many_sets() {
+    let server = tls_conn_server();
+    let client = reqwest::Client::builder()
+        .resolve("localhost", server.addr())
+        .http1_only()
+        .build()
+        .unwrap();
+    let url = format!("https://localhost:{}/", server.addr().port());
+
+    let extra = [
+        "tls/server.der",
+        "revocation/ca.der",
+        "revocation/ca-same-name.der",
+        "revocation/leaf.der",
+        "revocation/leaf-must-staple.der",
+    ]
+    .map(|name| std::fs::read(format!("tests/support/{name}")).unwrap());
+    // More sets of roots than the client keeps connections for, then the
+    // first one again.
+    for set in (0..20).chain([0]) {
+        let mut roots = vec![server::cert()];
+        for (i, der) in extra.iter().enumerate() {
+            if set & (1 << i) != 0 {
+                roots.push(reqwest::Certificate::from_der(der).unwrap());
+            }
+        }
+        client
+            .get(&url)
+            .tls_certs_only(roots)
+            .send()
+            .await
+            .expect("trusted");
+    }
+}
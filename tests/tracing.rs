@@ -0,0 +1,135 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+#![cfg(feature = "tracing")]
+mod support;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use support::server;
+
+type Fields = HashMap<&'static str, String>;
+
+/// Records the name and fields of every span created, its `Id` being its
+/// position in the list plus one.
+#[derive(Clone, Default)]
+struct Spans {
+    spans: Arc<Mutex<Vec<(&'static str, Fields)>>>,
+}
+
+impl Spans {
+    fn named(&self, name: &str) -> Vec<Fields> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(span, _)| *span == name)
+            .map(|(_, fields)| fields.clone())
+            .collect()
+    }
+}
+
+struct Visitor<'a>(&'a mut Fields);
+
+impl Visit for Visitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+impl Subscriber for Spans {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::new();
+        span.record(&mut Visitor(&mut fields));
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((span.metadata().name(), fields));
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        let (_, fields) = &mut spans[span.into_u64() as usize - 1];
+        values.record(&mut Visitor(fields));
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[tokio::test]
+async fn spans_for_request_dns_connect_and_body() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async { http::Response::new("hello".into()) });
+
+    let spans = Spans::default();
+    let _guard = tracing::subscriber::set_default(spans.clone());
+
+    let url = format!("http://localhost:{}/trace", server.addr().port());
+    let res = reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "hello");
+
+    let request = &spans.named("request")[0];
+    assert_eq!(request["http.request.method"], "GET");
+    assert_eq!(request["url.full"], url);
+    assert_eq!(request["server.address"], "localhost");
+    assert_eq!(request["server.port"], server.addr().port().to_string());
+    assert_eq!(request["http.response.status_code"], "200");
+
+    assert_eq!(spans.named("resolve")[0]["server.address"], "localhost");
+    assert_eq!(spans.named("connect")[0]["server.address"], "localhost");
+    assert_eq!(spans.named("body")[0]["http.response.body.size"], "5");
+}
+
+#[cfg(feature = "rustls")]
+#[tokio::test]
+async fn span_for_tls_handshake() {
+    let _ = env_logger::try_init();
+
+    let server = server::https(|_req| async { http::Response::default() });
+
+    let spans = Spans::default();
+    let _guard = tracing::subscriber::set_default(spans.clone());
+
+    let url = format!("https://localhost:{}/", server.addr().port());
+    reqwest::Client::builder()
+        .tls_certs_only([server::cert()])
+        .resolve("localhost", server.addr())
+        // don't keep a connection the server would wait on when dropped
+        .http1_only()
+        .pool_max_idle_per_host(0)
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(spans.named("tls")[0]["server.address"], "localhost");
+}
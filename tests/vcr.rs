@@ -0,0 +1,147 @@
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(not(feature = "rustls-no-provider"))]
+#![cfg(feature = "vcr")]
+mod support;
+
+use std::path::PathBuf;
+
+use http_body_util::BodyExt;
+use reqwest::vcr::Cassette;
+use support::server;
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("reqwest-vcr-{}-{name}.json", std::process::id()))
+}
+
+fn client(cassette: Cassette) -> reqwest::Client {
+    reqwest::Client::builder()
+        .cassette(cassette)
+        .no_proxy()
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn replays_recorded_auth_rounds() {
+    let _ = env_logger::try_init();
+
+    let path = cassette_path("auth");
+    let server = server::http(move |req| async move {
+        match req.headers().get("authorization") {
+            Some(_) => http::Response::builder()
+                .header("set-cookie", "session=abc")
+                .body("welcome".into())
+                .unwrap(),
+            None => http::Response::builder()
+                .status(401)
+                .header("www-authenticate", "Negotiate")
+                .header("www-authenticate", "Basic realm=\"intranet\"")
+                .body(Default::default())
+                .unwrap(),
+        }
+    });
+    let url = format!("http://{}/login", server.addr());
+
+    let rounds = |client: reqwest::Client| {
+        let url = url.clone();
+        async move {
+            let res = client.get(&url).send().await.unwrap();
+            assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+            let challenges = res
+                .headers()
+                .get_all("www-authenticate")
+                .iter()
+                .collect::<Vec<_>>();
+            assert_eq!(challenges, ["Negotiate", "Basic realm=\"intranet\""]);
+
+            let res = client
+                .get(&url)
+                .basic_auth("alice", Some("hunter2"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.status(), reqwest::StatusCode::OK);
+            assert_eq!(res.url().as_str(), url);
+            assert_eq!(res.headers()["set-cookie"], "session=abc");
+            assert_eq!(res.text().await.unwrap(), "welcome");
+        }
+    };
+
+    let cassette = Cassette::new(&path).unwrap();
+    assert!(!cassette.is_replaying());
+    rounds(client(cassette.clone())).await;
+    assert_eq!(cassette.len(), 2);
+
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(saved.contains("Basic [REDACTED]"), "{saved}");
+    drop(server);
+
+    let cassette = Cassette::new(&path).unwrap();
+    assert!(cassette.is_replaying());
+    rounds(client(cassette.clone())).await;
+    assert!(cassette.is_exhausted());
+
+    // Every interaction has been used.
+    let err = client(cassette).get(&url).send().await.unwrap_err();
+    assert!(err.is_request(), "{err:?}");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn matches_bodies_and_redacts_secrets() {
+    let _ = env_logger::try_init();
+
+    let path = cassette_path("body");
+    let server = server::http(move |req| async move {
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        let reply = format!("token for {}", String::from_utf8_lossy(&body));
+        http::Response::new(reply.into())
+    });
+    let url = format!("http://{}/token?key=s3cret", server.addr());
+
+    let recording = Cassette::record(&path)
+        .redact_text("s3cret", "KEY")
+        .match_body(true);
+    let recorder = client(recording);
+    for user in ["alice", "bob"] {
+        let text = recorder
+            .post(&url)
+            .body(format!("{user}:s3cret"))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(text, format!("token for {user}:s3cret"));
+    }
+    drop(server);
+
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(!saved.contains("s3cret"), "{saved}");
+
+    let replaying = Cassette::replay(&path)
+        .unwrap()
+        .redact_text("s3cret", "KEY")
+        .match_body(true);
+    let replayer = client(replaying);
+    let text = replayer
+        .post(&url)
+        .body("bob:s3cret")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(text, "token for bob:KEY");
+
+    let err = replayer
+        .post(&url)
+        .body("carol:s3cret")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_request(), "{err:?}");
+    let _ = std::fs::remove_file(&path);
+}
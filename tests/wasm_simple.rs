@@ -38,6 +38,22 @@ async fn request_with_timeout() {
     assert!(err.is_timeout());
 }
 
+#[wasm_bindgen_test]
+async fn client_with_timeout() {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(1))
+        .build()
+        .expect("client builder");
+    let err = client
+        .get("https://hyper.rs/not-cached")
+        .send()
+        .await
+        .expect_err("Expected error from aborted request");
+
+    assert!(err.is_request());
+    assert!(err.is_timeout());
+}
+
 #[wasm_bindgen_test]
 #[cfg(feature = "json")]
 fn preserve_content_type_if_set_manually() {
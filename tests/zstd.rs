@@ -84,6 +84,60 @@ async fn test_accept_encoding_header_is_not_changed_if_set() {
     assert_eq!(res.status(), reqwest::StatusCode::OK);
 }
 
+#[tokio::test]
+async fn test_no_zstd_is_not_advertised_or_decoded() {
+    let compressed = zstd_compress(b"test response");
+    let server = server::http(move |req| {
+        let compressed = compressed.clone();
+        async move {
+            let accept = req
+                .headers()
+                .get("accept-encoding")
+                .map(|v| v.to_str().unwrap().to_owned())
+                .unwrap_or_default();
+            assert!(!accept.contains("zstd"), "{accept}");
+            http::Response::builder()
+                .header("content-encoding", "zstd")
+                .body(compressed.into())
+                .unwrap()
+        }
+    });
+
+    let client = reqwest::Client::builder().no_zstd().build().unwrap();
+    let res = client
+        .get(&format!("http://{}/no-zstd", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.headers()["content-encoding"], "zstd");
+    assert_eq!(res.bytes().await.unwrap(), zstd_compress(b"test response"));
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn test_zstd_advertised_with_gzip() {
+    let server = server::http(move |req| async move {
+        let accept = req.headers()["accept-encoding"].to_str().unwrap();
+        assert!(accept.contains("gzip"), "{accept}");
+        assert!(accept.contains("zstd"), "{accept}");
+        http::Response::builder()
+            .header("content-encoding", "zstd")
+            .body(zstd_compress(b"test response").into())
+            .unwrap()
+    });
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(&format!("http://{}/gzip-zstd", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.text().await.unwrap(), "test response");
+}
+
 async fn zstd_case(response_size: usize, chunk_size: usize) {
     use futures_util::stream::StreamExt;
 